@@ -0,0 +1,381 @@
+// Reminders proposed from an event's action items.
+//
+// Two steps, so nothing is scheduled behind the user's back:
+// `propose_action_reminders` extracts the event's action items and keeps
+// the ones with a due date ("send the report by Friday"), and
+// `commit_action_reminders` inserts whichever proposals the user confirmed
+// as pending reminders on the event.
+//
+// With a model the LLM returns each item with its due time, given the
+// current date to resolve "Friday" against. Without one, for events opted
+// out of AI, or when the call fails, the heuristic action items are scanned
+// for English date and time phrases: today/tonight/tomorrow, weekdays
+// ("next Friday"), "in 3 days", "end of week", "Oct 20" or "2026-10-20", and
+// times like "3pm", "15:30" or "noon". A date without a time means 9:00.
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::journal::PromptModel;
+use crate::{compression, generate_id, i18n, llm_structured, now_ms, privacy, reminder_scan, summarize, usage_metrics, DbState, LlmRequest, Reminder};
+
+const DEFAULT_HOUR: u32 = 9;
+const MAX_ACTIONS: usize = 10;
+const WEEKDAYS: [(&str, Weekday); 7] = [
+  ("monday", Weekday::Mon),
+  ("tuesday", Weekday::Tue),
+  ("wednesday", Weekday::Wed),
+  ("thursday", Weekday::Thu),
+  ("friday", Weekday::Fri),
+  ("saturday", Weekday::Sat),
+  ("sunday", Weekday::Sun),
+];
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposedReminder {
+  /// The action item, used as the reminder message
+  message: String,
+  remind_at: i64,
+  /// The phrase the time was read from; `None` when the LLM set it
+  #[serde(default)]
+  due_text: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionReminderProposals {
+  event_id: String,
+  proposals: Vec<ProposedReminder>,
+  /// Action items without a due date, not proposed
+  undated: Vec<String>,
+  /// "llm" or "heuristic"
+  source: String,
+}
+
+fn at(date: NaiveDate, time: NaiveTime) -> Option<DateTime<Local>> {
+  Local.from_local_datetime(&date.and_time(time)).earliest()
+}
+
+/// First day after `from` (or `from` itself when `include_today`) that falls
+/// on `weekday`.
+fn next_weekday(from: NaiveDate, weekday: Weekday, include_today: bool) -> NaiveDate {
+  let mut days = (7 + weekday.num_days_from_monday() - from.weekday().num_days_from_monday()) % 7;
+  if days == 0 && !include_today {
+    days = 7;
+  }
+  from + ChronoDuration::days(days as i64)
+}
+
+fn number(word: &str) -> Option<i64> {
+  match word {
+    "a" | "an" | "one" => Some(1),
+    "two" => Some(2),
+    "three" => Some(3),
+    "four" => Some(4),
+    "five" => Some(5),
+    _ => word.parse().ok(),
+  }
+}
+
+fn time_of_day(text: &str) -> Option<(NaiveTime, String)> {
+  let clock = Regex::new(r"\b(\d{1,2})(?::(\d{2}))?\s*(am|pm)\b|\b(\d{1,2}):(\d{2})\b").expect("valid regex");
+  if let Some(caps) = clock.captures(text) {
+    let (hour, minute) = match caps.get(3) {
+      Some(meridiem) => {
+        let hour: u32 = caps[1].parse().ok()?;
+        let minute = caps.get(2).map_or(Some(0), |m| m.as_str().parse().ok())?;
+        let hour = match (hour, meridiem.as_str()) {
+          (12, "am") => 0,
+          (12, "pm") => 12,
+          (h, "pm") => h + 12,
+          (h, _) => h,
+        };
+        (hour, minute)
+      }
+      None => (caps[4].parse().ok()?, caps[5].parse().ok()?),
+    };
+    return NaiveTime::from_hms_opt(hour, minute, 0).map(|t| (t, caps[0].to_string()));
+  }
+  let named = [
+    ("noon", 12),
+    ("tonight", 20),
+    ("end of day", 17),
+    ("eod", 17),
+    ("this morning", 9),
+    ("this afternoon", 14),
+    ("this evening", 18),
+  ];
+  named.iter().find_map(|(phrase, hour)| {
+    Regex::new(&format!(r"\b{}\b", phrase))
+      .ok()
+      .filter(|re| re.is_match(text))
+      .and_then(|_| NaiveTime::from_hms_opt(*hour, 0, 0))
+      .map(|t| (t, phrase.to_string()))
+  })
+}
+
+fn date_of(text: &str, today: NaiveDate) -> Option<(NaiveDate, String)> {
+  let iso = Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})\b").expect("valid regex");
+  if let Some(caps) = iso.captures(text) {
+    let date = NaiveDate::from_ymd_opt(caps[1].parse().ok()?, caps[2].parse().ok()?, caps[3].parse().ok()?)?;
+    return Some((date, caps[0].to_string()));
+  }
+
+  let month = r"(jan(?:uary)?|feb(?:ruary)?|mar(?:ch)?|apr(?:il)?|may|june?|july?|aug(?:ust)?|sep(?:t(?:ember)?)?|oct(?:ober)?|nov(?:ember)?|dec(?:ember)?)";
+  let month_day = Regex::new(&format!(
+    r"\b{0}\.?\s+(\d{{1,2}})(?:st|nd|rd|th)?\b|\b(\d{{1,2}})(?:st|nd|rd|th)?\s+(?:of\s+)?{0}\b",
+    month
+  ))
+  .expect("valid regex");
+  if let Some(caps) = month_day.captures(text) {
+    let (month, day) = match caps.get(1) {
+      Some(month) => (month.as_str(), caps[2].parse().ok()?),
+      None => (caps.get(4)?.as_str(), caps[3].parse().ok()?),
+    };
+    let month = MONTHS.iter().position(|m| month.starts_with(m))? as u32 + 1;
+    // A date that's passed this year means next year's
+    let date = NaiveDate::from_ymd_opt(today.year(), month, day)
+      .filter(|d| *d >= today)
+      .or_else(|| NaiveDate::from_ymd_opt(today.year() + 1, month, day))?;
+    return Some((date, caps[0].to_string()));
+  }
+
+  let relative = [
+    ("day after tomorrow", 2),
+    ("tomorrow", 1),
+    ("today", 0),
+    ("tonight", 0),
+    ("this morning", 0),
+    ("this afternoon", 0),
+    ("this evening", 0),
+    ("end of day", 0),
+    ("eod", 0),
+  ];
+  for (phrase, days) in relative {
+    if Regex::new(&format!(r"\b{}\b", phrase)).is_ok_and(|re| re.is_match(text)) {
+      return Some((today + ChronoDuration::days(days), phrase.to_string()));
+    }
+  }
+  if let Some(m) = Regex::new(r"\bend of (?:the )?week\b").expect("valid regex").find(text) {
+    return Some((next_weekday(today, Weekday::Fri, true), m.as_str().to_string()));
+  }
+  if let Some(m) = Regex::new(r"\bnext week\b").expect("valid regex").find(text) {
+    return Some((next_weekday(today, Weekday::Mon, false), m.as_str().to_string()));
+  }
+  if let Some(m) = Regex::new(r"\bend of (?:the )?month\b").expect("valid regex").find(text) {
+    let first_of_next = NaiveDate::from_ymd_opt(today.year(), today.month(), 1)?.checked_add_months(chrono::Months::new(1))?;
+    return Some((first_of_next - ChronoDuration::days(1), m.as_str().to_string()));
+  }
+
+  let weekday = Regex::new(r"\b(next\s+)?(monday|tuesday|wednesday|thursday|friday|saturday|sunday)\b").expect("valid regex");
+  let caps = weekday.captures(text)?;
+  let (_, day) = WEEKDAYS.iter().find(|(name, _)| *name == &caps[2])?;
+  let date = next_weekday(today, *day, caps.get(1).is_none());
+  Some((date, caps[0].to_string()))
+}
+
+/// When `text` says the action is due, and the phrase that said so.
+fn detect_due(text: &str, now: DateTime<Local>) -> Option<(i64, String)> {
+  let text = text.to_lowercase();
+
+  let relative = Regex::new(r"\bin\s+(\d+|an?|one|two|three|four|five)\s+(minute|hour|day|week)s?\b").expect("valid regex");
+  if let Some(caps) = relative.captures(&text) {
+    let n = number(&caps[1])?;
+    let offset = match &caps[2] {
+      "minute" => ChronoDuration::minutes(n),
+      "hour" => ChronoDuration::hours(n),
+      "day" => ChronoDuration::days(n),
+      _ => ChronoDuration::weeks(n),
+    };
+    return Some(((now + offset).timestamp_millis(), caps[0].to_string()));
+  }
+
+  let time = time_of_day(&text);
+  let date = date_of(&text, now.date_naive());
+  let due = match (&date, &time) {
+    (Some((date, _)), Some((time, _))) => at(*date, *time)?,
+    (Some((date, _)), None) => {
+      let due = at(*date, NaiveTime::from_hms_opt(DEFAULT_HOUR, 0, 0)?)?;
+      // "today" once the morning is over: soon rather than in the past
+      if due < now && *date == now.date_naive() { now + ChronoDuration::hours(1) } else { due }
+    }
+    (None, Some((time, _))) => {
+      let today = at(now.date_naive(), *time)?;
+      if today > now { today } else { at(now.date_naive() + ChronoDuration::days(1), *time)? }
+    }
+    (None, None) => return None,
+  };
+  if due < now {
+    return None;
+  }
+  let phrase = [date.map(|(_, p)| p), time.map(|(_, p)| p)].into_iter().flatten().collect::<Vec<_>>().join(" ");
+  Some((due.timestamp_millis(), phrase))
+}
+
+async fn llm_proposals(text: &str, model: PromptModel, now: DateTime<Local>) -> Result<(Vec<ProposedReminder>, Vec<String>), String> {
+  let request = LlmRequest {
+    provider: model.provider,
+    api_key: model.api_key,
+    model: model.model,
+    prompt: format!(
+      "It is now {}. List the concrete action items in the following text as short imperative \
+       phrases. For each, give the local date and time it's due as \"YYYY-MM-DD HH:MM\" if the \
+       text says when (use 09:00 when only a day is given), or null.\n\n{}",
+      now.format("%A %Y-%m-%d %H:%M"),
+      text
+    ),
+    max_tokens: Some(600),
+  };
+  let schema = json!({
+    "type": "object",
+    "properties": {
+      "items": {
+        "type": "array",
+        "maxItems": MAX_ACTIONS,
+        "items": {
+          "type": "object",
+          "properties": {
+            "action": { "type": "string", "minLength": 1 },
+            "due": { "type": ["string", "null"] },
+          },
+          "required": ["action", "due"],
+        },
+      },
+    },
+    "required": ["items"],
+  });
+  let result = llm_structured::call_structured(&request, &schema).await?;
+
+  let mut proposals = Vec::new();
+  let mut undated = Vec::new();
+  for item in result["items"].as_array().into_iter().flatten() {
+    let action = item["action"].as_str().unwrap_or_default().trim().to_string();
+    let due = item["due"]
+      .as_str()
+      .and_then(|due| NaiveDateTime::parse_from_str(due.trim(), "%Y-%m-%d %H:%M").ok())
+      .and_then(|due| Local.from_local_datetime(&due).earliest())
+      .filter(|due| *due > now);
+    match due {
+      Some(due) => proposals.push(ProposedReminder { message: action, remind_at: due.timestamp_millis(), due_text: None }),
+      None => undated.push(action),
+    }
+  }
+  Ok((proposals, undated))
+}
+
+fn heuristic_proposals(text: &str, now: DateTime<Local>) -> (Vec<ProposedReminder>, Vec<String>) {
+  let mut proposals = Vec::new();
+  let mut undated = Vec::new();
+  for action in summarize::heuristic_actions(text) {
+    match detect_due(&action, now) {
+      Some((remind_at, phrase)) => proposals.push(ProposedReminder { message: action, remind_at, due_text: Some(phrase) }),
+      None => undated.push(action),
+    }
+  }
+  (proposals, undated)
+}
+
+/// Reminders for the dated action items of `event_id`. Nothing is saved;
+/// pass the ones to keep to `commit_action_reminders`.
+#[tauri::command]
+pub async fn propose_action_reminders(
+  state: tauri::State<'_, DbState>,
+  event_id: String,
+  llm: Option<PromptModel>,
+) -> Result<ActionReminderProposals, String> {
+  let _timer = usage_metrics::time_async("propose_action_reminders");
+  let (text, opted_out) = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let (title, note, text_content, locked): (Option<String>, Option<String>, Option<String>, bool) = conn
+      .query_row(
+        "SELECT title, note, text_content, locked, text_compressed, text_codec
+         FROM timeline_events WHERE id = ? AND is_deleted = 0",
+        [&event_id],
+        |row| {
+          let codec: Option<String> = row.get(5)?;
+          let text_content = compression::inflate(row.get(2)?, row.get(4)?, codec.as_deref());
+          Ok((row.get(0)?, row.get(1)?, text_content, row.get::<_, i32>(3)? != 0))
+        },
+      )
+      .map_err(|_| i18n::t("Event not found"))?;
+    if locked {
+      return Err(i18n::t("Unlock the event first"));
+    }
+    let text = [title, note, text_content].into_iter().flatten().collect::<Vec<_>>().join("\n");
+    (text, privacy::is_opted_out(&conn, &event_id)?)
+  };
+
+  let now = Local::now();
+  let llm = llm.filter(|_| !opted_out);
+  if let Some(model) = llm {
+    match llm_proposals(&text, model, now).await {
+      Ok((proposals, undated)) => {
+        return Ok(ActionReminderProposals { event_id, proposals, undated, source: "llm".to_string() });
+      }
+      Err(e) => tracing::warn!(error = %e, "LLM action reminders failed, using heuristics"),
+    }
+  }
+  let (proposals, undated) = heuristic_proposals(&text, now);
+  Ok(ActionReminderProposals { event_id, proposals, undated, source: "heuristic".to_string() })
+}
+
+/// Save confirmed proposals as pending reminders on `event_id`.
+#[tauri::command]
+pub fn commit_action_reminders(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  event_id: String,
+  reminders: Vec<ProposedReminder>,
+) -> Result<Vec<Reminder>, String> {
+  let now = now_ms();
+  if let Some(past) = reminders.iter().find(|r| r.remind_at <= now) {
+    return Err(format!("\"{}\" is due in the past", past.message));
+  }
+  if reminders.iter().any(|r| r.message.trim().is_empty()) {
+    return Err("Reminder message is empty".to_string());
+  }
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+  let exists: i64 = conn
+    .query_row("SELECT COUNT(*) FROM timeline_events WHERE id = ? AND is_deleted = 0", [&event_id], |row| row.get(0))
+    .map_err(|e| e.to_string())?;
+  if exists == 0 {
+    return Err(i18n::t("Event not found"));
+  }
+
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  let mut created = Vec::new();
+  for proposal in reminders {
+    let reminder = Reminder {
+      id: generate_id(),
+      event_id: event_id.clone(),
+      remind_at: proposal.remind_at,
+      message: proposal.message.trim().to_string(),
+      status: "pending".to_string(),
+      triggered_at: None,
+      snooze_until: None,
+      created_at: now,
+      attachment_id: None,
+      follow_up_after_minutes: None,
+      follow_up_message: None,
+    };
+    tx.execute(
+      "INSERT INTO reminders (id, event_id, remind_at, message, status, created_at)
+       VALUES (?1, ?2, ?3, ?4, 'pending', ?5)",
+      (&reminder.id, &reminder.event_id, reminder.remind_at, &reminder.message, reminder.created_at),
+    )
+    .map_err(|e| e.to_string())?;
+    created.push(reminder);
+  }
+  tx.commit().map_err(|e| e.to_string())?;
+  if !created.is_empty() {
+    reminder_scan::wake(&app);
+  }
+  Ok(created)
+}
@@ -0,0 +1,127 @@
+// How attached the pet is to the user.
+//
+// Pats, feeds, chats and play are logged in `pet_interactions`. Affinity is
+// recomputed from that log: each interaction adds its kind's weight, faded
+// with a two-week half-life, and repeating the same kind on one day counts
+// for less each time (the nth counts 1/n), so steady care beats a burst of
+// clicking. The total maps onto 0-100 and a level the pet's behavior can
+// key off.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{generate_id, now_ms, pet_state, timezone, DbState};
+
+const KINDS: &[(&str, f64)] = &[("pat", 1.0), ("play", 1.5), ("feed", 2.0), ("chat", 3.0)];
+const HALF_LIFE_DAYS: f64 = 14.0;
+// Older interactions have faded to almost nothing
+const HISTORY_DAYS: i64 = 90;
+// Score at which affinity reaches about 63
+const SCORE_SCALE: f64 = 40.0;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PetStatus {
+  state: pet_state::PetState,
+  /// 0 - 100
+  affinity: f64,
+  /// "shy" (< 20), "friendly" (< 50), "close" (< 80) or "bonded"
+  level: String,
+  interactions_today: u32,
+  last_interaction_at: Option<i64>,
+  /// All-time counts per kind
+  totals: HashMap<String, u32>,
+}
+
+fn level(affinity: f64) -> &'static str {
+  match affinity {
+    a if a < 20.0 => "shy",
+    a if a < 50.0 => "friendly",
+    a if a < 80.0 => "close",
+    _ => "bonded",
+  }
+}
+
+fn weight(kind: &str) -> Option<f64> {
+  KINDS.iter().find(|(k, _)| *k == kind).map(|(_, w)| *w)
+}
+
+fn affinity(conn: &rusqlite::Connection, now: i64) -> Result<f64, String> {
+  let rows: Vec<(String, i64)> = conn
+    .prepare("SELECT kind, created_at FROM pet_interactions WHERE created_at >= ?1 ORDER BY created_at")
+    .map_err(|e| e.to_string())?
+    .query_map([now - HISTORY_DAYS * DAY_MS], |row| Ok((row.get(0)?, row.get(1)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let mut per_day: HashMap<(String, String), u32> = HashMap::new();
+  let mut score = 0.0;
+  for (kind, at) in rows {
+    let Some(weight) = weight(&kind) else { continue };
+    let day = timezone::date_key(at);
+    let nth = per_day.entry((day, kind)).or_insert(0);
+    *nth += 1;
+    let age_days = (now - at).max(0) as f64 / DAY_MS as f64;
+    score += weight / *nth as f64 * 0.5f64.powf(age_days / HALF_LIFE_DAYS);
+  }
+  Ok(100.0 * (1.0 - (-score / SCORE_SCALE).exp()))
+}
+
+fn status(app: &tauri::AppHandle, conn: &rusqlite::Connection) -> Result<PetStatus, String> {
+  let now = now_ms();
+  let affinity = (affinity(conn, now)? * 10.0).round() / 10.0;
+  let today_start = timezone::day_start(timezone::today()).unwrap_or(now);
+  let (interactions_today, last_interaction_at): (u32, Option<i64>) = conn
+    .query_row(
+      "SELECT SUM(created_at >= ?1), MAX(created_at) FROM pet_interactions",
+      [today_start],
+      |row| Ok((row.get::<_, Option<u32>>(0)?.unwrap_or(0), row.get(1)?)),
+    )
+    .map_err(|e| e.to_string())?;
+  let totals = conn
+    .prepare("SELECT kind, COUNT(*) FROM pet_interactions GROUP BY kind")
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  Ok(PetStatus {
+    state: pet_state::current(app),
+    affinity,
+    level: level(affinity).to_string(),
+    interactions_today,
+    last_interaction_at,
+    totals,
+  })
+}
+
+/// Record an interaction: "pat", "feed", "chat" or "play".
+#[tauri::command]
+pub fn interact_with_pet(app: tauri::AppHandle, state: tauri::State<DbState>, kind: String) -> Result<PetStatus, String> {
+  if weight(&kind).is_none() {
+    return Err(format!("Unknown interaction: {}", kind));
+  }
+  let status = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    conn
+      .execute(
+        "INSERT INTO pet_interactions (id, kind, created_at) VALUES (?1, ?2, ?3)",
+        (generate_id(), &kind, now_ms()),
+      )
+      .map_err(|e| e.to_string())?;
+    status(&app, &conn)?
+  };
+  pet_state::cheer(&app, format!("Enjoyed a {}", kind));
+  Ok(status)
+}
+
+#[tauri::command]
+pub fn get_pet_status(app: tauri::AppHandle, state: tauri::State<DbState>) -> Result<PetStatus, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  status(&app, &conn)
+}
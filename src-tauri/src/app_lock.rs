@@ -0,0 +1,249 @@
+// PIN or password lock for the timeline.
+//
+// While locked, commands that read captured content (listing, search,
+// exports) fail with `LOCKED_ERROR` so the webview can show its unlock
+// screen instead. The secret is stored as an Argon2 hash in `security.app_lock`
+// (empty when no lock is set). The locked flag is process-wide, like the
+// redaction rules, so any command can check it without extra state. The
+// app starts locked, and locks again when the idle detector marks the user
+// away or `lock_app` is called.
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+use crate::{config, i18n, now_ms, read_setting, write_setting, DbState};
+
+pub const APP_LOCK_KEY: &str = "security.app_lock";
+pub const LOCKED_ERROR: &str = "App is locked";
+
+const MIN_PIN_LEN: usize = 4;
+// Failed attempts allowed before unlocking is throttled, then the wait
+// doubles with every further failure up to the cap
+const FREE_ATTEMPTS: u32 = 3;
+const BASE_DELAY_MS: i64 = 30_000;
+const MAX_DELAY_MS: i64 = 15 * 60_000;
+
+struct LockState {
+  enabled: bool,
+  locked: bool,
+  failed_attempts: u32,
+  retry_at: i64,
+}
+
+static LOCK: Mutex<LockState> = Mutex::new(LockState { enabled: false, locked: false, failed_attempts: 0, retry_at: 0 });
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLockStatus {
+  enabled: bool,
+  locked: bool,
+  /// Set while unlocking is throttled after failed attempts
+  retry_after_ms: Option<i64>,
+}
+
+fn status(lock: &LockState) -> AppLockStatus {
+  let wait = lock.retry_at - now_ms();
+  AppLockStatus { enabled: lock.enabled, locked: lock.locked, retry_after_ms: (wait > 0).then_some(wait) }
+}
+
+/// Count a wrong PIN; once the free attempts are used up, unlocking waits.
+fn register_failure(lock: &mut LockState, now: i64) {
+  lock.failed_attempts += 1;
+  if lock.failed_attempts >= FREE_ATTEMPTS {
+    let doublings = (lock.failed_attempts - FREE_ATTEMPTS).min(10);
+    lock.retry_at = now + (BASE_DELAY_MS << doublings).min(MAX_DELAY_MS);
+  }
+}
+
+fn stored_hash(conn: &rusqlite::Connection) -> Option<String> {
+  read_setting(conn, APP_LOCK_KEY).filter(|hash| !hash.is_empty())
+}
+
+/// Read the lock setting of the active profile and lock if one is set.
+/// Called at launch and after switching profiles.
+pub fn init(app: &tauri::AppHandle) {
+  let enabled = {
+    let state = app.state::<DbState>();
+    let Ok(_guard) = state.lock.lock() else { return };
+    match state.open() {
+      Ok(conn) => stored_hash(&conn).is_some(),
+      Err(_) => return,
+    }
+  };
+  if let Ok(mut lock) = LOCK.lock() {
+    *lock = LockState { enabled, locked: enabled, failed_attempts: 0, retry_at: 0 };
+  }
+}
+
+/// `Err(LOCKED_ERROR)` while the app is locked.
+pub fn ensure_unlocked() -> Result<(), String> {
+  let lock = LOCK.lock().map_err(|_| "app lock".to_string())?;
+  if lock.locked {
+    return Err(LOCKED_ERROR.to_string());
+  }
+  Ok(())
+}
+
+fn relock(app: &tauri::AppHandle) {
+  let locked = match LOCK.lock() {
+    Ok(mut lock) if lock.enabled && !lock.locked => {
+      lock.locked = true;
+      true
+    }
+    _ => false,
+  };
+  if locked {
+    tracing::info!("App locked");
+    let _ = app.emit("app-locked", ());
+  }
+}
+
+/// Called by the behavior monitor when the user goes idle.
+pub fn notify_away(app: &tauri::AppHandle) {
+  relock(app);
+}
+
+#[tauri::command]
+pub fn get_app_lock_status() -> Result<AppLockStatus, String> {
+  let lock = LOCK.lock().map_err(|_| "app lock".to_string())?;
+  Ok(status(&lock))
+}
+
+/// Set or change the PIN. Needs the app to be unlocked.
+#[tauri::command]
+pub fn set_app_lock(app: tauri::AppHandle, state: tauri::State<DbState>, pin: String) -> Result<AppLockStatus, String> {
+  ensure_unlocked()?;
+  if pin.chars().count() < MIN_PIN_LEN {
+    return Err(format!("PIN must be at least {} characters", MIN_PIN_LEN));
+  }
+  let salt = SaltString::generate(&mut OsRng);
+  let hash = Argon2::default()
+    .hash_password(pin.as_bytes(), &salt)
+    .map_err(|e| e.to_string())?
+    .to_string();
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, APP_LOCK_KEY, &hash)?;
+  }
+  let status = {
+    let mut lock = LOCK.lock().map_err(|_| "app lock".to_string())?;
+    lock.enabled = true;
+    status(&lock)
+  };
+  config::emit_changed(&app, vec![APP_LOCK_KEY.to_string()]);
+  Ok(status)
+}
+
+/// Turn the lock off. Needs the app to be unlocked.
+#[tauri::command]
+pub fn remove_app_lock(app: tauri::AppHandle, state: tauri::State<DbState>) -> Result<AppLockStatus, String> {
+  ensure_unlocked()?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, APP_LOCK_KEY, "")?;
+  }
+  let status = {
+    let mut lock = LOCK.lock().map_err(|_| "app lock".to_string())?;
+    *lock = LockState { enabled: false, locked: false, failed_attempts: 0, retry_at: 0 };
+    status(&lock)
+  };
+  config::emit_changed(&app, vec![APP_LOCK_KEY.to_string()]);
+  Ok(status)
+}
+
+#[tauri::command]
+pub fn lock_app(app: tauri::AppHandle) -> Result<AppLockStatus, String> {
+  relock(&app);
+  get_app_lock_status()
+}
+
+/// Unlock with the PIN. After a few wrong tries further attempts are
+/// refused until `retryAfterMs` has passed.
+#[tauri::command]
+pub fn unlock_app(app: tauri::AppHandle, state: tauri::State<DbState>, pin: String) -> Result<AppLockStatus, String> {
+  {
+    let lock = LOCK.lock().map_err(|_| "app lock".to_string())?;
+    if !lock.locked {
+      return Ok(status(&lock));
+    }
+    let wait = lock.retry_at - now_ms();
+    if wait > 0 {
+      return Err(format!("Too many attempts, try again in {} s", (wait + 999) / 1000));
+    }
+  }
+
+  let hash = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    stored_hash(&conn)
+  };
+  // A lock without a stored hash means the setting went missing behind our
+  // back; stay locked rather than accept any PIN
+  let Some(hash) = hash else {
+    tracing::warn!("App is locked but no PIN is stored");
+    return Err(i18n::t("Wrong PIN"));
+  };
+  let parsed = PasswordHash::new(&hash).map_err(|e| e.to_string())?;
+  let valid = Argon2::default().verify_password(pin.as_bytes(), &parsed).is_ok();
+
+  let mut lock = LOCK.lock().map_err(|_| "app lock".to_string())?;
+  if !valid {
+    register_failure(&mut lock, now_ms());
+    tracing::warn!(attempts = lock.failed_attempts, "Wrong PIN");
+    return Err(i18n::t("Wrong PIN"));
+  }
+  *lock = LockState { enabled: true, locked: false, failed_attempts: 0, retry_at: 0 };
+  let status = status(&lock);
+  drop(lock);
+  tracing::info!("App unlocked");
+  let _ = app.emit("app-unlocked", ());
+  Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn unlocked() -> LockState {
+    LockState { enabled: true, locked: false, failed_attempts: 0, retry_at: 0 }
+  }
+
+  #[test]
+  fn refuses_reads_only_while_locked() {
+    *LOCK.lock().unwrap() = LockState { locked: true, ..unlocked() };
+    assert_eq!(ensure_unlocked(), Err(LOCKED_ERROR.to_string()));
+    *LOCK.lock().unwrap() = unlocked();
+    assert_eq!(ensure_unlocked(), Ok(()));
+  }
+
+  #[test]
+  fn throttles_after_the_free_attempts() {
+    let mut lock = unlocked();
+    for _ in 1..FREE_ATTEMPTS {
+      register_failure(&mut lock, 1_000);
+    }
+    assert_eq!(lock.retry_at, 0);
+
+    register_failure(&mut lock, 1_000);
+    assert_eq!(lock.retry_at, 1_000 + BASE_DELAY_MS);
+    register_failure(&mut lock, 1_000);
+    assert_eq!(lock.retry_at, 1_000 + 2 * BASE_DELAY_MS);
+    for _ in 0..20 {
+      register_failure(&mut lock, 1_000);
+    }
+    assert_eq!(lock.retry_at, 1_000 + MAX_DELAY_MS);
+  }
+
+  #[test]
+  fn reports_the_wait_only_while_throttled() {
+    assert_eq!(status(&unlocked()).retry_after_ms, None);
+    let wait = status(&LockState { retry_at: now_ms() + 60_000, ..unlocked() }).retry_after_ms.unwrap();
+    assert!(wait > 0 && wait <= 60_000);
+  }
+}
@@ -0,0 +1,133 @@
+// Secondary windows (timeline, settings, reminder popups) so the pet window
+// doesn't have to resize itself to show everything.
+//
+// Every window loads the same frontend bundle; the `view` query parameter
+// tells it which screen to render. The pet is told about windows opening and
+// closing through `window-opened` / `window-closed`.
+
+use tauri::{Emitter, Manager};
+
+use crate::pet_window;
+
+pub const TIMELINE_LABEL: &str = "timeline";
+pub const SETTINGS_LABEL: &str = "settings";
+pub const REMINDER_LABEL_PREFIX: &str = "reminder-";
+/// Setting that makes due reminders open a popup window.
+pub const REMINDER_POPUP_KEY: &str = "reminders.popup_window";
+
+fn notify_main(app: &tauri::AppHandle, event: &str, label: &str) {
+  if let Some(window) = app.get_webview_window("main") {
+    let _ = window.emit(event, serde_json::json!({ "label": label }));
+  }
+}
+
+/// Focus an existing window with this label, if there is one.
+fn focus_existing(app: &tauri::AppHandle, label: &str) -> bool {
+  match app.get_webview_window(label) {
+    Some(window) => {
+      let _ = window.show();
+      let _ = window.unminimize();
+      let _ = window.set_focus();
+      true
+    }
+    None => false,
+  }
+}
+
+fn builder<'a>(
+  app: &'a tauri::AppHandle,
+  label: &'a str,
+  query: &str,
+) -> tauri::WebviewWindowBuilder<'a, tauri::Wry, tauri::AppHandle> {
+  let url = tauri::WebviewUrl::App(format!("index.html?{}", query).into());
+  tauri::WebviewWindowBuilder::new(app, label, url).drag_and_drop(false)
+}
+
+/// Called from the window event handler when any window is destroyed.
+pub fn on_window_destroyed(app: &tauri::AppHandle, label: &str) {
+  if label != "main" {
+    notify_main(app, "window-closed", label);
+  }
+}
+
+pub fn reminder_label(reminder_id: &str) -> String {
+  format!("{}{}", REMINDER_LABEL_PREFIX, reminder_id)
+}
+
+/// Open (or focus) a small always-on-top popup for one reminder. The popup
+/// loads its content through `get_reminder_payload`.
+pub fn show_reminder_popup(app: &tauri::AppHandle, reminder_id: &str) -> Result<(), String> {
+  let label = reminder_label(reminder_id);
+  if focus_existing(app, &label) {
+    return Ok(());
+  }
+
+  let window = builder(app, &label, &format!("view=reminder&reminderId={}", reminder_id))
+    .title("Papa Reminder")
+    .inner_size(360.0, 200.0)
+    .resizable(false)
+    .decorations(false)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .visible(false)
+    .build()
+    .map_err(|e| e.to_string())?;
+
+  let _ = pet_window::snap_to_corner(app, &window, "bottom-right");
+  window.show().map_err(|e| e.to_string())?;
+  notify_main(app, "window-opened", &label);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn open_timeline_window(app: tauri::AppHandle) -> Result<(), String> {
+  if focus_existing(&app, TIMELINE_LABEL) {
+    return Ok(());
+  }
+
+  builder(&app, TIMELINE_LABEL, "view=timeline")
+    .title("Papa Timeline")
+    .inner_size(900.0, 640.0)
+    .min_inner_size(480.0, 360.0)
+    .center()
+    .build()
+    .map_err(|e| e.to_string())?;
+
+  notify_main(&app, "window-opened", TIMELINE_LABEL);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn open_settings_window(app: tauri::AppHandle) -> Result<(), String> {
+  if focus_existing(&app, SETTINGS_LABEL) {
+    return Ok(());
+  }
+
+  builder(&app, SETTINGS_LABEL, "view=settings")
+    .title("Papa Settings")
+    .inner_size(560.0, 520.0)
+    .resizable(false)
+    .center()
+    .build()
+    .map_err(|e| e.to_string())?;
+
+  notify_main(&app, "window-opened", SETTINGS_LABEL);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn open_reminder_popup(app: tauri::AppHandle, reminder_id: String) -> Result<(), String> {
+  show_reminder_popup(&app, &reminder_id)
+}
+
+/// Close a secondary window by label; the pet window can't be closed here.
+#[tauri::command]
+pub fn close_app_window(app: tauri::AppHandle, label: String) -> Result<(), String> {
+  if label == "main" {
+    return Err("The pet window can't be closed".to_string());
+  }
+  if let Some(window) = app.get_webview_window(&label) {
+    window.close().map_err(|e| e.to_string())?;
+  }
+  Ok(())
+}
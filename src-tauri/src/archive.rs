@@ -0,0 +1,96 @@
+// Read-only archive for old timeline events.
+//
+// Archived rows move to `timeline_events_archive`, which has the same
+// columns, so the hot table stays small for multi-year journals. Attachments
+// and reminders reference `event_ids`, which holds live and archived ids
+// alike, so their foreign keys stay satisfied across the move and stay
+// enforced while it happens. Reminders that hadn't fired yet
+// are cancelled on the way, since archived events never come due again.
+// Reads can opt into the archive through `events_table`; edits only ever
+// touch live events.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::{cancel_reminders, i18n, timezone, DbState};
+
+const EVENT_COLUMNS: &str =
+  "id, type, title, note, text_content, created_at, source, is_deleted, metadata, scheduled_for, ai_opt_out, locked, reviewed_at, text_compressed, text_codec, tz_offset_min";
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStats {
+  count: i64,
+  oldest: Option<i64>,
+  newest: Option<i64>,
+}
+
+/// Table expression to select events from, live only or live plus archive.
+pub fn events_table(include_archived: bool) -> String {
+  if include_archived {
+    format!(
+      "(SELECT {cols} FROM timeline_events UNION ALL SELECT {cols} FROM timeline_events_archive)",
+      cols = EVENT_COLUMNS
+    )
+  } else {
+    "timeline_events".to_string()
+  }
+}
+
+/// Move every event created before midnight of `date_key`
+/// ("YYYY-MM-DD") into the archive. Plans that haven't come due stay live.
+/// Returns how many events were archived.
+#[tauri::command]
+pub fn archive_events_before(state: tauri::State<DbState>, date_key: String) -> Result<usize, String> {
+  let date = NaiveDate::parse_from_str(&date_key, "%Y-%m-%d")
+    .map_err(|_| i18n::t("Invalid date format"))?;
+  let cutoff = timezone::day_start(date).ok_or_else(|| "Invalid local time".to_string())?;
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  let with_reminders: Vec<String> = {
+    let mut stmt = tx
+      .prepare(
+        "SELECT DISTINCT e.id FROM timeline_events e JOIN reminders r ON r.event_id = e.id
+         WHERE e.created_at < ?1 AND e.scheduled_for IS NULL AND r.status IN ('pending', 'snoozed')",
+      )
+      .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([cutoff], |row| row.get(0)).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+  };
+  for event_id in &with_reminders {
+    cancel_reminders(&tx, event_id, "event archived")?;
+  }
+  tx.execute(
+    &format!(
+      "INSERT OR REPLACE INTO timeline_events_archive ({cols})
+       SELECT {cols} FROM timeline_events WHERE created_at < ?1 AND scheduled_for IS NULL",
+      cols = EVENT_COLUMNS
+    ),
+    [cutoff],
+  ).map_err(|e| e.to_string())?;
+  let moved = tx
+    .execute(
+      "DELETE FROM timeline_events WHERE created_at < ?1 AND scheduled_for IS NULL",
+      [cutoff],
+    )
+    .map_err(|e| e.to_string())?;
+  tx.commit().map_err(|e| e.to_string())?;
+
+  Ok(moved)
+}
+
+#[tauri::command]
+pub fn get_archive_stats(state: tauri::State<DbState>) -> Result<ArchiveStats, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  conn
+    .query_row(
+      "SELECT COUNT(*), MIN(created_at), MAX(created_at) FROM timeline_events_archive",
+      [],
+      |row| Ok(ArchiveStats { count: row.get(0)?, oldest: row.get(1)?, newest: row.get(2)? }),
+    )
+    .map_err(|e| e.to_string())
+}
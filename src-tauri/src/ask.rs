@@ -0,0 +1,255 @@
+// "Ask the pet about this event".
+//
+// `ask_about_event` builds a prompt from the event (title, note, extracted
+// text) and short summaries of its linked events, sends it to the model the
+// frontend has configured, and streams the answer back as `ask-answer-delta`
+// events while it's generated. The question and the answer are then kept as
+// a `thought` event linked to the one asked about.
+//
+// Events are linked through `metadata.linkedEventId`: the Q&A thoughts made
+// here point at their event, and earlier Q&As come back as context the next
+// time the same event is asked about. Events opted out of AI can't be asked
+// about, and opted-out or locked linked events are left out of the context.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::Emitter;
+
+use crate::journal::PromptModel;
+use crate::{app_lock, compression, connectivity, generate_id, i18n, mentions, now_ms, permissions, privacy, redaction, summarize, tags, usage_metrics, DbState, LlmRequest, TimelineEvent};
+
+pub const DELTA_EVENT: &str = "ask-answer-delta";
+
+const MAX_TOKENS: u32 = 800;
+// Characters of the event's own text put in the prompt
+const MAX_CONTEXT_CHARS: usize = 12_000;
+const MAX_LINKED: usize = 10;
+const LINKED_SUMMARY_SENTENCES: usize = 2;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AnswerDelta {
+  event_id: String,
+  delta: String,
+}
+
+struct LinkedEvent {
+  title: Option<String>,
+  event_type: String,
+  text: String,
+}
+
+fn truncate(text: &str, max_chars: usize) -> &str {
+  match text.char_indices().nth(max_chars) {
+    Some((end, _)) => &text[..end],
+    None => text,
+  }
+}
+
+/// Events linked to `event_id` in either direction, most recent first.
+fn linked_events(conn: &rusqlite::Connection, event_id: &str, links_to: Option<&str>) -> Result<Vec<LinkedEvent>, String> {
+  conn
+    .prepare(
+      "SELECT title, type, note, text_content, text_compressed, text_codec FROM timeline_events
+       WHERE is_deleted = 0 AND ai_opt_out = 0 AND locked = 0
+         AND (json_extract(metadata, '$.linkedEventId') = ?1 OR id = ?2)
+       ORDER BY created_at DESC LIMIT ?3",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map((event_id, links_to, MAX_LINKED as i64), |row| {
+      let note: Option<String> = row.get(2)?;
+      let codec: Option<String> = row.get(5)?;
+      let text_content = compression::inflate(row.get(3)?, row.get(4)?, codec.as_deref());
+      Ok(LinkedEvent {
+        title: row.get(0)?,
+        event_type: row.get(1)?,
+        text: [note, text_content].into_iter().flatten().collect::<Vec<_>>().join("\n"),
+      })
+    })
+    .map_err(|e| e.to_string())
+    .map(|rows| rows.filter_map(|r| r.ok()).collect())
+}
+
+fn build_prompt(event_text: &str, linked: &[LinkedEvent], question: &str) -> String {
+  let mut prompt = String::from(
+    "You're answering a question about an item from the user's personal timeline. \
+     Use the item and its linked items below; say so if they don't contain the answer.\n\n## Item\n",
+  );
+  prompt.push_str(truncate(event_text, MAX_CONTEXT_CHARS));
+  if !linked.is_empty() {
+    prompt.push_str("\n\n## Linked items\n");
+    for item in linked {
+      let summary = summarize::extractive_summary(&item.text, LINKED_SUMMARY_SENTENCES);
+      let title = item.title.as_deref().unwrap_or(&item.event_type);
+      prompt.push_str(&format!("- {}: {}\n", title, summary.replace('\n', " ")));
+    }
+  }
+  prompt.push_str(&format!("\n## Question\n{}", question));
+  prompt
+}
+
+/// Data lines of the server-sent events complete in `buffer`, leaving any
+/// partial line for the next chunk.
+fn drain_sse_data(buffer: &mut Vec<u8>) -> Vec<String> {
+  let mut data = Vec::new();
+  while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+    let line: Vec<u8> = buffer.drain(..=pos).collect();
+    let line = String::from_utf8_lossy(&line);
+    if let Some(payload) = line.trim_end().strip_prefix("data:") {
+      data.push(payload.trim_start().to_string());
+    }
+  }
+  data
+}
+
+/// Like `request_llm`, but asks for a streamed answer and passes each
+/// piece of text to `on_delta` as it arrives. Returns the whole answer.
+async fn stream_llm_api(mut request: LlmRequest, mut on_delta: impl FnMut(&str)) -> Result<String, String> {
+  permissions::ensure_llm()?;
+  request.prompt = redaction::redact(&request.prompt);
+  let max_tokens = request.max_tokens.unwrap_or(MAX_TOKENS);
+  let client = connectivity::client_builder().build().map_err(|e| e.to_string())?;
+  let builder = match request.provider.as_str() {
+    "openai" => client
+      .post("https://api.openai.com/v1/chat/completions")
+      .header("Authorization", format!("Bearer {}", request.api_key))
+      .json(&json!({
+        "model": request.model,
+        "messages": [{ "role": "user", "content": request.prompt }],
+        "max_tokens": max_tokens,
+        "stream": true,
+      })),
+    "anthropic" => client
+      .post("https://api.anthropic.com/v1/messages")
+      .header("x-api-key", &request.api_key)
+      .header("anthropic-version", "2023-06-01")
+      .json(&json!({
+        "model": request.model,
+        "max_tokens": max_tokens,
+        "messages": [{ "role": "user", "content": request.prompt }],
+        "stream": true,
+      })),
+    other => return Err(format!("Unsupported provider: {}", other)),
+  };
+
+  let mut response = builder.send().await.map_err(|e| connectivity::request_error("Request failed", e))?;
+  if !response.status().is_success() {
+    let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+    return Err(format!("API error: {}", error_text));
+  }
+
+  let mut answer = String::new();
+  let mut buffer = Vec::new();
+  while let Some(chunk) = response.chunk().await.map_err(|e| format!("Stream failed: {}", e))? {
+    buffer.extend_from_slice(&chunk);
+    for data in drain_sse_data(&mut buffer) {
+      if data == "[DONE]" {
+        continue;
+      }
+      let Ok(message) = serde_json::from_str::<Value>(&data) else { continue };
+      if message["type"] == "error" {
+        return Err(format!("API error: {}", message["error"]["message"].as_str().unwrap_or("Unknown error")));
+      }
+      let delta = match request.provider.as_str() {
+        "openai" => message["choices"][0]["delta"]["content"].as_str(),
+        _ => message["delta"]["text"].as_str(),
+      };
+      if let Some(delta) = delta.filter(|d| !d.is_empty()) {
+        answer.push_str(delta);
+        on_delta(delta);
+      }
+    }
+  }
+  Ok(answer)
+}
+
+/// Ask `llm` a question about `event_id`. The answer streams in as
+/// `ask-answer-delta` events; the returned `thought` event holds the
+/// question and the full answer.
+#[tauri::command]
+pub async fn ask_about_event(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, DbState>,
+  event_id: String,
+  question: String,
+  llm: PromptModel,
+) -> Result<TimelineEvent, String> {
+  let _timer = usage_metrics::time_async("ask_about_event");
+  app_lock::ensure_unlocked()?;
+  let question = question.trim().to_string();
+  if question.is_empty() {
+    return Err("Question is empty".to_string());
+  }
+
+  let prompt = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let (title, note, text_content, locked, links_to): (Option<String>, Option<String>, Option<String>, bool, Option<String>) = conn
+      .query_row(
+        "SELECT title, note, text_content, locked, json_extract(metadata, '$.linkedEventId'), text_compressed, text_codec
+         FROM timeline_events WHERE id = ? AND is_deleted = 0",
+        [&event_id],
+        |row| {
+          let codec: Option<String> = row.get(6)?;
+          let text_content = compression::inflate(row.get(2)?, row.get(5)?, codec.as_deref());
+          Ok((row.get(0)?, row.get(1)?, text_content, row.get::<_, i32>(3)? != 0, row.get(4)?))
+        },
+      )
+      .map_err(|_| i18n::t("Event not found"))?;
+    if privacy::is_opted_out(&conn, &event_id)? {
+      return Err(i18n::t("This event is excluded from AI"));
+    }
+    if locked {
+      return Err(i18n::t("Unlock the event first"));
+    }
+    let event_text = [title, note, text_content].into_iter().flatten().collect::<Vec<_>>().join("\n");
+    build_prompt(&event_text, &linked_events(&conn, &event_id, links_to.as_deref())?, &question)
+  };
+
+  let request = LlmRequest {
+    provider: llm.provider,
+    api_key: llm.api_key,
+    model: llm.model,
+    prompt,
+    max_tokens: Some(MAX_TOKENS),
+  };
+  let answer = stream_llm_api(request, |delta| {
+    let _ = app.emit(DELTA_EVENT, AnswerDelta { event_id: event_id.clone(), delta: delta.to_string() });
+  })
+  .await?;
+  let answer = answer.trim().to_string();
+  if answer.is_empty() {
+    return Err("The model returned an empty answer".to_string());
+  }
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let thought_id = generate_id();
+  let created_at = now_ms();
+  let metadata = json!({ "linkedEventId": event_id, "question": question });
+  conn
+    .execute(
+      "INSERT INTO timeline_events (id, type, note, text_content, created_at, source, is_deleted, metadata)
+       VALUES (?1, 'thought', ?2, ?3, ?4, 'ask', 0, ?5)",
+      (&thought_id, &question, &answer, created_at, metadata.to_string()),
+    )
+    .map_err(|e| e.to_string())?;
+  tags::auto_tag(&conn, &thought_id);
+  tags::sync_hashtags(&conn, &thought_id)?;
+  mentions::index(&conn, &thought_id)?;
+  tracing::info!(%event_id, %thought_id, "Answered question about event");
+
+  Ok(TimelineEvent {
+    id: thought_id,
+    event_type: "thought".to_string(),
+    title: None,
+    note: Some(question),
+    text_content: Some(answer),
+    created_at,
+    source: Some("ask".to_string()),
+    is_deleted: false,
+    metadata: Some(metadata),
+    ai_opt_out: false,
+    locked: false,
+  })
+}
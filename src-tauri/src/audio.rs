@@ -0,0 +1,281 @@
+// Voice note capture.
+//
+// cpal streams are not `Send`, so each recording lives on its own thread and
+// is controlled through a channel. Samples are written straight to a 16-bit
+// WAV file in the app data dir and attached to an `audio` timeline event
+// when the recording stops.
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SizedSample};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::{
+  cas, generate_id, hash_file, location, now_ms, Attachment, DbState, TimelineEvent,
+  TimelineEventWithAttachments,
+};
+
+type SharedWriter = Arc<Mutex<Option<hound::WavWriter<BufWriter<File>>>>>;
+
+struct RecordingSummary {
+  path: PathBuf,
+  duration_ms: i64,
+}
+
+struct ActiveRecording {
+  stop_tx: mpsc::Sender<()>,
+  handle: JoinHandle<Result<RecordingSummary, String>>,
+  started_at: i64,
+}
+
+#[derive(Default)]
+pub struct AudioState {
+  recording: Mutex<Option<ActiveRecording>>,
+}
+
+fn recordings_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+  use tauri::Manager;
+
+  let dir = app.state::<crate::DbState>().data_dir().join("recordings");
+  fs::create_dir_all(&dir).map_err(|e| format!("Failed to create recordings dir: {}", e))?;
+  Ok(dir)
+}
+
+fn write_samples<T>(data: &[T], writer: &SharedWriter)
+where
+  T: Sample,
+  i16: FromSample<T>,
+{
+  if let Ok(mut guard) = writer.lock() {
+    if let Some(writer) = guard.as_mut() {
+      for &sample in data {
+        let _ = writer.write_sample(sample.to_sample::<i16>());
+      }
+    }
+  }
+}
+
+fn build_stream<T>(
+  device: &cpal::Device,
+  config: &cpal::StreamConfig,
+  writer: SharedWriter,
+) -> Result<cpal::Stream, String>
+where
+  T: SizedSample,
+  i16: FromSample<T>,
+{
+  device
+    .build_input_stream(
+      config,
+      move |data: &[T], _: &cpal::InputCallbackInfo| write_samples(data, &writer),
+      |err| tracing::error!(error = %err, "Audio input error"),
+      None,
+    )
+    .map_err(|e| format!("Failed to open microphone: {}", e))
+}
+
+/// Runs on the recording thread until a stop signal arrives (or the sender
+/// is dropped). `ready_tx` reports whether the stream started.
+fn record_until_stopped(
+  path: PathBuf,
+  ready_tx: mpsc::Sender<Result<(), String>>,
+  stop_rx: mpsc::Receiver<()>,
+) -> Result<RecordingSummary, String> {
+  let setup = || -> Result<(cpal::Stream, SharedWriter, u32), String> {
+    let host = cpal::default_host();
+    let device = host
+      .default_input_device()
+      .ok_or_else(|| "No microphone available".to_string())?;
+    let supported = device
+      .default_input_config()
+      .map_err(|e| format!("Failed to query microphone: {}", e))?;
+
+    let spec = hound::WavSpec {
+      channels: supported.channels(),
+      sample_rate: supported.sample_rate().0,
+      bits_per_sample: 16,
+      sample_format: hound::SampleFormat::Int,
+    };
+    let writer: SharedWriter = Arc::new(Mutex::new(Some(
+      hound::WavWriter::create(&path, spec).map_err(|e| format!("Failed to create WAV file: {}", e))?,
+    )));
+
+    let config: cpal::StreamConfig = supported.config();
+    let stream = match supported.sample_format() {
+      cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, writer.clone())?,
+      cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, writer.clone())?,
+      cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config, writer.clone())?,
+      other => return Err(format!("Unsupported sample format: {}", other)),
+    };
+    stream.play().map_err(|e| format!("Failed to start recording: {}", e))?;
+
+    Ok((stream, writer, spec.sample_rate))
+  };
+
+  let (stream, writer, sample_rate) = match setup() {
+    Ok(parts) => {
+      let _ = ready_tx.send(Ok(()));
+      parts
+    }
+    Err(e) => {
+      let _ = fs::remove_file(&path);
+      let _ = ready_tx.send(Err(e.clone()));
+      return Err(e);
+    }
+  };
+
+  let _ = stop_rx.recv();
+  drop(stream);
+
+  let writer = writer
+    .lock()
+    .map_err(|_| "audio writer lock".to_string())?
+    .take()
+    .ok_or_else(|| "Recording already finalized".to_string())?;
+  // duration() counts frames, i.e. samples per channel
+  let frames = writer.duration() as i64;
+  writer.finalize().map_err(|e| format!("Failed to finalize WAV file: {}", e))?;
+
+  Ok(RecordingSummary {
+    path,
+    duration_ms: frames * 1000 / sample_rate.max(1) as i64,
+  })
+}
+
+#[tauri::command]
+pub fn start_audio_recording(
+  app: tauri::AppHandle,
+  state: tauri::State<AudioState>,
+) -> Result<i64, String> {
+  let mut recording = state.recording.lock().map_err(|_| "audio lock".to_string())?;
+  if recording.is_some() {
+    return Err("Already recording".to_string());
+  }
+
+  let path = recordings_dir(&app)?.join(format!("voice_{}.wav", now_ms()));
+  let (ready_tx, ready_rx) = mpsc::channel();
+  let (stop_tx, stop_rx) = mpsc::channel();
+  let handle = std::thread::spawn(move || record_until_stopped(path, ready_tx, stop_rx));
+
+  ready_rx
+    .recv()
+    .map_err(|_| "Recording thread exited unexpectedly".to_string())??;
+
+  let started_at = now_ms();
+  *recording = Some(ActiveRecording { stop_tx, handle, started_at });
+  Ok(started_at)
+}
+
+/// Stop the active recording and store it as an `audio` timeline event.
+#[tauri::command]
+pub fn stop_audio_recording(
+  db: tauri::State<DbState>,
+  state: tauri::State<AudioState>,
+  note: Option<String>,
+) -> Result<TimelineEventWithAttachments, String> {
+  let active = state
+    .recording
+    .lock()
+    .map_err(|_| "audio lock".to_string())?
+    .take()
+    .ok_or_else(|| "Not recording".to_string())?;
+
+  let _ = active.stop_tx.send(());
+  let summary = active
+    .handle
+    .join()
+    .map_err(|_| "Recording thread panicked".to_string())??;
+
+  let _guard = db.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = db.open().map_err(|e| e.to_string())?;
+
+  let event_id = generate_id();
+  let created_at = active.started_at;
+  let title = Some("Voice note".to_string());
+
+  conn.execute(
+    "INSERT INTO timeline_events (id, type, title, note, created_at, source, is_deleted)
+     VALUES (?1, 'audio', ?2, ?3, ?4, 'voice', 0)",
+    (&event_id, &title, &note, created_at),
+  ).map_err(|e| e.to_string())?;
+  location::stamp(&conn, &event_id)?;
+
+  let attach_id = generate_id();
+  let file_name = summary.path.file_name()
+    .and_then(|n| n.to_str())
+    .map(|s| s.to_string());
+  let mime_type = Some("audio/wav".to_string());
+  let size_bytes = fs::metadata(&summary.path).ok().map(|m| m.len() as i64);
+  let sha256 = hash_file(&summary.path).ok();
+  // The recording moves from the recordings folder into the content store
+  let path_str = match &sha256 {
+    Some(sha) => cas::adopt(&conn, &db.data_dir(), &summary.path, sha)?,
+    None => summary.path.to_string_lossy().to_string(),
+  };
+
+  conn.execute(
+    "INSERT INTO attachments (id, event_id, kind, original_path, stored_path, file_name, mime_type, size_bytes, sha256, created_at, duration_ms)
+     VALUES (?1, ?2, 'audio', ?3, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    (
+      &attach_id,
+      &event_id,
+      &path_str,
+      &file_name,
+      &mime_type,
+      size_bytes,
+      &sha256,
+      created_at,
+      summary.duration_ms,
+    ),
+  ).map_err(|e| e.to_string())?;
+
+  let attachment = Attachment {
+    id: attach_id,
+    event_id: event_id.clone(),
+    kind: "audio".to_string(),
+    original_path: path_str.clone(),
+    stored_path: Some(path_str),
+    file_name,
+    mime_type,
+    size_bytes,
+    sha256,
+    width: None,
+    height: None,
+    created_at,
+    duration_ms: Some(summary.duration_ms),
+    derived_from: None,
+  };
+
+  let event = TimelineEvent {
+    id: event_id,
+    event_type: "audio".to_string(),
+    title,
+    note,
+    text_content: None,
+    created_at,
+    source: Some("voice".to_string()),
+    is_deleted: false,
+    metadata: None,
+    ai_opt_out: false,
+    locked: false,
+  };
+
+  Ok(TimelineEventWithAttachments { event, attachments: vec![attachment], reminders: vec![] })
+}
+
+/// Discard the active recording without creating an event.
+#[tauri::command]
+pub fn cancel_audio_recording(state: tauri::State<AudioState>) -> Result<(), String> {
+  let active = state.recording.lock().map_err(|_| "audio lock".to_string())?.take();
+  if let Some(active) = active {
+    let _ = active.stop_tx.send(());
+    if let Ok(Ok(summary)) = active.handle.join() {
+      let _ = fs::remove_file(&summary.path);
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,480 @@
+// Global input monitoring used to infer the user's mood, plus opt-in
+// tracking of which application has focus.
+
+use chrono::Duration as ChronoDuration;
+use device_query::{DeviceQuery, DeviceState, Keycode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+use crate::{app_lock, gestures, generate_id, now_ms, pet_state, read_setting, supervisor, timezone, wellness, DbState};
+
+/// Setting key that enables recording the focused app and window title.
+pub const TRACK_APPS_KEY: &str = "behavior.track_apps";
+/// Setting key for minutes without input before the user counts as away.
+pub const IDLE_MINUTES_KEY: &str = "behavior.idle_minutes";
+pub const SAMPLE_MS_KEY: &str = "behavior.sample_ms";
+pub const WINDOW_S_KEY: &str = "behavior.window_s";
+pub const TYPING_WEIGHT_KEY: &str = "behavior.typing_weight";
+pub const MOUSE_WEIGHT_KEY: &str = "behavior.mouse_weight";
+pub const CLICK_WEIGHT_KEY: &str = "behavior.click_weight";
+pub const MOUSE_SPEED_CAP_KEY: &str = "behavior.mouse_speed_cap";
+pub const CLICK_RATE_CAP_KEY: &str = "behavior.click_rate_cap";
+
+// How often the focused window is sampled and usage is written to the DB
+const APP_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const APP_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct BehaviorAnalysis {
+  typing_speed: f64,        // 打字速度 (keys per second)
+  key_press_count: u32,     // 按键次数
+  backspace_count: u32,     // 退格键次数
+  mouse_move_speed: f64,     // 鼠标移动速度 (pixels per second)
+  mouse_click_count: u32,   // 鼠标点击次数
+  idle_time: f64,           // 空闲时间 (seconds)
+  activity_level: f64,       // 活动水平 (0.0 - 1.0)
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AppUsage {
+  pub app_name: String,
+  pub seconds: f64,
+  pub last_title: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AwayInterval {
+  id: String,
+  started_at: i64,
+  ended_at: Option<i64>,
+}
+
+/// Tunables for the behavior monitor, loaded from `behavior.*` settings at
+/// startup and on `reload_behavior_config`.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BehaviorConfig {
+  pub(crate) sample_ms: u64,       // input polling interval
+  pub(crate) window_s: f64,        // how often behavior-analysis is emitted
+  pub(crate) typing_weight: f64,
+  pub(crate) mouse_weight: f64,
+  pub(crate) click_weight: f64,
+  pub(crate) mouse_speed_cap: f64, // px/s that counts as full mouse activity
+  pub(crate) click_rate_cap: f64,  // clicks/s that counts as full click activity
+  pub(crate) idle_minutes: f64,
+  pub(crate) track_apps: bool,
+}
+
+impl Default for BehaviorConfig {
+  fn default() -> Self {
+    Self {
+      sample_ms: 100,
+      window_s: 2.0,
+      typing_weight: 0.3,
+      mouse_weight: 0.3,
+      click_weight: 0.4,
+      mouse_speed_cap: 1000.0,
+      click_rate_cap: 5.0,
+      idle_minutes: 5.0,
+      track_apps: false,
+    }
+  }
+}
+
+fn setting_or<T: FromStr>(conn: &rusqlite::Connection, key: &str, default: T) -> T {
+  read_setting(conn, key)
+    .and_then(|v| v.trim().parse().ok())
+    .unwrap_or(default)
+}
+
+fn load_behavior_config(conn: &rusqlite::Connection) -> BehaviorConfig {
+  let d = BehaviorConfig::default();
+  let positive = |v: f64, fallback: f64| if v > 0.0 { v } else { fallback };
+
+  BehaviorConfig {
+    sample_ms: setting_or(conn, SAMPLE_MS_KEY, d.sample_ms).clamp(16, 1000),
+    window_s: setting_or(conn, WINDOW_S_KEY, d.window_s).max(0.5),
+    typing_weight: setting_or(conn, TYPING_WEIGHT_KEY, d.typing_weight).max(0.0),
+    mouse_weight: setting_or(conn, MOUSE_WEIGHT_KEY, d.mouse_weight).max(0.0),
+    click_weight: setting_or(conn, CLICK_WEIGHT_KEY, d.click_weight).max(0.0),
+    mouse_speed_cap: positive(setting_or(conn, MOUSE_SPEED_CAP_KEY, d.mouse_speed_cap), d.mouse_speed_cap),
+    click_rate_cap: positive(setting_or(conn, CLICK_RATE_CAP_KEY, d.click_rate_cap), d.click_rate_cap),
+    idle_minutes: positive(setting_or(conn, IDLE_MINUTES_KEY, d.idle_minutes), d.idle_minutes),
+    track_apps: setting_or(conn, TRACK_APPS_KEY, d.track_apps),
+  }
+}
+
+/// Shared with other subsystems that should hold off while the user is away.
+#[derive(Default)]
+pub struct BehaviorState {
+  away: AtomicBool,
+  config: Mutex<BehaviorConfig>,
+}
+
+/// Whether the idle detector currently considers the user away.
+pub fn is_away(app: &tauri::AppHandle) -> bool {
+  app.state::<BehaviorState>().away.load(Ordering::Relaxed)
+}
+
+#[derive(Default)]
+struct AppUsageEntry {
+  seconds: f64,
+  last_title: String,
+}
+
+fn current_config(app: &tauri::AppHandle) -> BehaviorConfig {
+  app
+    .state::<BehaviorState>()
+    .config
+    .lock()
+    .map(|config| *config)
+    .unwrap_or_default()
+}
+
+fn reload_config(app: &tauri::AppHandle) -> Result<BehaviorConfig, String> {
+  let config = {
+    let state = app.state::<DbState>();
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    load_behavior_config(&conn)
+  };
+
+  let behavior = app.state::<BehaviorState>();
+  *behavior.config.lock().map_err(|_| "behavior lock".to_string())? = config;
+  Ok(config)
+}
+
+fn emit_main<S: Serialize + Clone>(app: &tauri::AppHandle, event: &str, payload: S) {
+  if let Some(window) = app.get_webview_window("main") {
+    let _ = window.emit(event, payload);
+  }
+}
+
+/// Record the start of an away interval and return its id.
+fn begin_away_interval(app: &tauri::AppHandle, started_at: i64) -> Option<String> {
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().ok()?;
+  let conn = state.open().ok()?;
+
+  let id = generate_id();
+  conn
+    .execute(
+      "INSERT INTO away_intervals (id, started_at) VALUES (?1, ?2)",
+      (&id, started_at),
+    )
+    .ok()?;
+  Some(id)
+}
+
+fn end_away_interval(app: &tauri::AppHandle, id: &str, ended_at: i64) {
+  let state = app.state::<DbState>();
+  let Ok(_guard) = state.lock.lock() else { return };
+  let Ok(conn) = state.open() else { return };
+  let _ = conn.execute(
+    "UPDATE away_intervals SET ended_at = ?1 WHERE id = ?2",
+    (ended_at, id),
+  );
+}
+
+fn flush_app_usage(app: &tauri::AppHandle, pending: &mut HashMap<String, AppUsageEntry>) {
+  if pending.is_empty() {
+    return;
+  }
+
+  let state = app.state::<DbState>();
+  let Ok(_guard) = state.lock.lock() else { return };
+  let Ok(conn) = state.open() else { return };
+
+  let date_key = timezone::today_key();
+  let updated_at = now_ms();
+  for (app_name, entry) in pending.drain() {
+    let _ = conn.execute(
+      "INSERT INTO app_usage (date_key, app_name, seconds, last_title, updated_at)
+       VALUES (?1, ?2, ?3, ?4, ?5)
+       ON CONFLICT(date_key, app_name) DO UPDATE SET
+         seconds = seconds + excluded.seconds,
+         last_title = excluded.last_title,
+         updated_at = excluded.updated_at",
+      (&date_key, &app_name, entry.seconds, &entry.last_title, updated_at),
+    );
+  }
+}
+
+pub fn spawn_behavior_monitor(app_handle: tauri::AppHandle) {
+  supervisor::supervise(app_handle, "behavior", |app_handle| async move {
+    let device_state = DeviceState::new();
+    let mut last_keys: Vec<Keycode> = Vec::new();
+    let mut last_mouse_pos: Option<(i32, i32)> = None;
+    let mut last_mouse_click = false;
+
+    let mut key_press_count = 0u32;
+    let mut backspace_count = 0u32;
+    let mut mouse_click_count = 0u32;
+    let mut mouse_move_distance = 0.0f64;
+    let mut last_analysis_time = Instant::now();
+    let mut last_activity_time = Instant::now();
+
+    let _ = reload_config(&app_handle);
+    let mut away_since: Option<i64> = None;
+    let mut away_interval_id: Option<String> = None;
+    let mut app_usage: HashMap<String, AppUsageEntry> = HashMap::new();
+    let mut last_app_sample = Instant::now();
+    let mut last_app_flush = Instant::now();
+
+    loop {
+      let config = current_config(&app_handle);
+      let idle_threshold = Duration::from_secs_f64(config.idle_minutes * 60.0);
+      tokio::time::sleep(Duration::from_millis(config.sample_ms)).await;
+
+      let mouse = device_state.get_mouse();
+      let keys = device_state.get_keys();
+      let current_time = Instant::now();
+
+      // Track keyboard activity
+      if keys.len() > last_keys.len() {
+        key_press_count += 1;
+        // Check for backspace
+        if keys.contains(&Keycode::Backspace) && !last_keys.contains(&Keycode::Backspace) {
+          backspace_count += 1;
+        }
+        last_activity_time = current_time;
+      }
+      let pressed: Vec<Keycode> = keys.iter().filter(|k| !last_keys.contains(k)).cloned().collect();
+      gestures::observe_keys(&app_handle, &pressed, &keys);
+      last_keys = keys.clone();
+
+      // Track mouse activity
+      let current_pos = (mouse.coords.0, mouse.coords.1);
+      if let Some(last_pos) = last_mouse_pos {
+        let dx = (current_pos.0 - last_pos.0) as f64;
+        let dy = (current_pos.1 - last_pos.1) as f64;
+        let distance = (dx * dx + dy * dy).sqrt();
+        mouse_move_distance += distance;
+        if distance > 0.0 {
+          last_activity_time = current_time;
+        }
+      }
+      last_mouse_pos = Some(current_pos);
+
+      if mouse.button_pressed[0] && !last_mouse_click {
+        mouse_click_count += 1;
+        last_activity_time = current_time;
+      }
+      last_mouse_click = mouse.button_pressed[0];
+
+      // Idle detection: mark the user away after the configured threshold
+      // and stop counting activity until input resumes
+      let idle_for = current_time.duration_since(last_activity_time);
+      match away_since {
+        None if idle_for >= idle_threshold => {
+          let started_at = now_ms() - idle_for.as_millis() as i64;
+          away_since = Some(started_at);
+          away_interval_id = begin_away_interval(&app_handle, started_at);
+          app_handle.state::<BehaviorState>().away.store(true, Ordering::Relaxed);
+          flush_app_usage(&app_handle, &mut app_usage);
+          wellness::notify_away(&app_handle);
+          app_lock::notify_away(&app_handle);
+          pet_state::set_away(&app_handle, true);
+          emit_main(&app_handle, "user-idle", serde_json::json!({ "since": started_at }));
+        }
+        Some(started_at) if idle_for < idle_threshold => {
+          let returned_at = now_ms();
+          if let Some(id) = away_interval_id.take() {
+            end_away_interval(&app_handle, &id, returned_at);
+          }
+          away_since = None;
+          app_handle.state::<BehaviorState>().away.store(false, Ordering::Relaxed);
+          pet_state::set_away(&app_handle, false);
+          emit_main(&app_handle, "user-returned", serde_json::json!({
+            "awayMs": returned_at - started_at
+          }));
+
+          // Start a fresh analysis window instead of averaging over the absence
+          key_press_count = 0;
+          backspace_count = 0;
+          mouse_click_count = 0;
+          mouse_move_distance = 0.0;
+          last_analysis_time = current_time;
+          last_app_sample = current_time;
+        }
+        _ => {}
+      }
+      let is_away = away_since.is_some();
+
+      // Attribute the time since the last sample to the focused app
+      let since_sample = current_time.duration_since(last_app_sample);
+      if since_sample >= APP_SAMPLE_INTERVAL {
+        last_app_sample = current_time;
+        if config.track_apps && !is_away {
+          if let Ok(window) = active_win_pos_rs::get_active_window() {
+            let entry = app_usage.entry(window.app_name).or_default();
+            entry.seconds += since_sample.as_secs_f64();
+            entry.last_title = window.title;
+          }
+        }
+      }
+
+      if current_time.duration_since(last_app_flush) >= APP_FLUSH_INTERVAL {
+        last_app_flush = current_time;
+        flush_app_usage(&app_handle, &mut app_usage);
+      }
+
+      // Emit behavior analysis every window (paused while away)
+      let elapsed = current_time.duration_since(last_analysis_time);
+      if !is_away && elapsed.as_secs_f64() >= config.window_s {
+        let time_window = elapsed.as_secs_f64();
+        let idle_time = current_time.duration_since(last_activity_time).as_secs_f64();
+
+        let typing_speed = if time_window > 0.0 {
+          key_press_count as f64 / time_window
+        } else {
+          0.0
+        };
+
+        let mouse_move_speed = if time_window > 0.0 {
+          mouse_move_distance / time_window
+        } else {
+          0.0
+        };
+
+        // Calculate activity level (0.0 - 1.0)
+        let click_rate = mouse_click_count as f64 / time_window;
+        let activity_level = (typing_speed * config.typing_weight
+          + (mouse_move_speed / config.mouse_speed_cap).min(1.0) * config.mouse_weight
+          + click_rate.min(config.click_rate_cap) / config.click_rate_cap * config.click_weight)
+          .min(1.0);
+
+        let analysis = BehaviorAnalysis {
+          typing_speed,
+          key_press_count,
+          backspace_count,
+          mouse_move_speed,
+          mouse_click_count,
+          idle_time,
+          activity_level,
+        };
+
+        emit_main(&app_handle, "behavior-analysis", &analysis);
+        wellness::observe_sample(&app_handle, key_press_count, backspace_count);
+        pet_state::observe_sample(&app_handle, activity_level);
+
+        // Reset counters
+        key_press_count = 0;
+        backspace_count = 0;
+        mouse_click_count = 0;
+        mouse_move_distance = 0.0;
+        last_analysis_time = current_time;
+      }
+    }
+  });
+}
+
+/// First date key (inclusive) covered by a usage period.
+pub fn period_start_key(period: &str) -> Result<Option<String>, String> {
+  let today = timezone::today();
+  let start = match period {
+    "today" => Some(today),
+    "week" => Some(today - ChronoDuration::days(6)),
+    "month" => Some(today - ChronoDuration::days(29)),
+    "all" => None,
+    _ => return Err(format!("Unknown period: {}", period)),
+  };
+  Ok(start.map(|d| d.format("%Y-%m-%d").to_string()))
+}
+
+pub fn query_app_usage(
+  conn: &rusqlite::Connection,
+  start_key: Option<&str>,
+  end_key: Option<&str>,
+) -> Result<Vec<AppUsage>, String> {
+  let usage = conn
+    .prepare(
+      "SELECT app_name, SUM(seconds), MAX(last_title)
+       FROM app_usage
+       WHERE (?1 IS NULL OR date_key >= ?1) AND (?2 IS NULL OR date_key <= ?2)
+       GROUP BY app_name
+       ORDER BY SUM(seconds) DESC"
+    )
+    .map_err(|e| e.to_string())?
+    .query_map((start_key, end_key), |row| {
+      Ok(AppUsage {
+        app_name: row.get(0)?,
+        seconds: row.get(1)?,
+        last_title: row.get(2)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(usage)
+}
+
+/// Format seconds as "1h 05m" / "12m" for exports.
+pub fn format_duration(seconds: f64) -> String {
+  let minutes = (seconds / 60.0).round() as i64;
+  if minutes >= 60 {
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+  } else {
+    format!("{}m", minutes)
+  }
+}
+
+/// Time per application for "today", "week" (last 7 days), "month"
+/// (last 30 days) or "all".
+#[tauri::command]
+pub fn get_app_usage(
+  state: tauri::State<DbState>,
+  period: String,
+) -> Result<Vec<AppUsage>, String> {
+  let start_key = period_start_key(&period)?;
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  query_app_usage(&conn, start_key.as_deref(), None)
+}
+
+/// Away intervals overlapping the given range (unix ms). An interval with no
+/// `endedAt` is still in progress.
+#[tauri::command]
+pub fn list_away_intervals(
+  state: tauri::State<DbState>,
+  start_date: i64,
+  end_date: i64,
+) -> Result<Vec<AwayInterval>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let intervals = conn
+    .prepare(
+      "SELECT id, started_at, ended_at
+       FROM away_intervals
+       WHERE started_at <= ?2 AND (ended_at IS NULL OR ended_at >= ?1)
+       ORDER BY started_at ASC"
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([start_date, end_date], |row| {
+      Ok(AwayInterval {
+        id: row.get(0)?,
+        started_at: row.get(1)?,
+        ended_at: row.get(2)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(intervals)
+}
+
+/// Re-read the `behavior.*` settings and apply them to the running monitor.
+#[tauri::command]
+pub fn reload_behavior_config(app: tauri::AppHandle) -> Result<BehaviorConfig, String> {
+  reload_config(&app)
+}
@@ -0,0 +1,263 @@
+// Import of browser bookmarks as `link` events.
+//
+// Chromium browsers (Chrome, Edge, Brave) keep bookmarks in a JSON file in
+// the profile; Firefox keeps them in `places.sqlite`, which is copied first
+// because the browser holds it locked while running. Each bookmark becomes a
+// `link` event dated when it was bookmarked, with the folders it sits in
+// attached as tags. Bookmarks imported before (same browser and URL) are
+// skipped, so importing again only picks up new ones.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::Manager;
+
+use crate::{generate_id, now_ms, tags, DbState};
+
+// Chromium counts microseconds from 1601-01-01
+const WINDOWS_EPOCH_OFFSET_MS: i64 = 11_644_473_600_000;
+const FIREFOX_ROOTS: &[&str] = &["root________", "menu________", "toolbar_____", "unfiled_____", "mobile______"];
+const FIREFOX_TAGS_ROOT: &str = "tags________";
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BookmarkImport {
+  imported: usize,
+  /// Already imported earlier
+  skipped: usize,
+}
+
+struct Bookmark {
+  title: String,
+  url: String,
+  /// unix ms
+  added_at: Option<i64>,
+  /// Folder names from the outermost in, roots left out
+  folders: Vec<String>,
+}
+
+fn chromium_dir(app: &tauri::AppHandle, browser: &str) -> Result<PathBuf, String> {
+  let path = app.path();
+  let dir = if cfg!(target_os = "windows") {
+    let base = path.local_data_dir().map_err(|e| e.to_string())?;
+    match browser {
+      "chrome" => base.join("Google").join("Chrome").join("User Data"),
+      "edge" => base.join("Microsoft").join("Edge").join("User Data"),
+      _ => base.join("BraveSoftware").join("Brave-Browser").join("User Data"),
+    }
+  } else if cfg!(target_os = "macos") {
+    let base = path.data_dir().map_err(|e| e.to_string())?;
+    match browser {
+      "chrome" => base.join("Google").join("Chrome"),
+      "edge" => base.join("Microsoft Edge"),
+      _ => base.join("BraveSoftware").join("Brave-Browser"),
+    }
+  } else {
+    let base = path.config_dir().map_err(|e| e.to_string())?;
+    match browser {
+      "chrome" => base.join("google-chrome"),
+      "edge" => base.join("microsoft-edge"),
+      _ => base.join("BraveSoftware").join("Brave-Browser"),
+    }
+  };
+  Ok(dir)
+}
+
+fn firefox_profiles_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+  let path = app.path();
+  let dir = if cfg!(target_os = "windows") {
+    path.data_dir().map_err(|e| e.to_string())?.join("Mozilla").join("Firefox").join("Profiles")
+  } else if cfg!(target_os = "macos") {
+    path.data_dir().map_err(|e| e.to_string())?.join("Firefox").join("Profiles")
+  } else {
+    path.home_dir().map_err(|e| e.to_string())?.join(".mozilla").join("firefox")
+  };
+  Ok(dir)
+}
+
+fn chromium_node(node: &Value, folders: &mut Vec<String>, out: &mut Vec<Bookmark>) {
+  match node["type"].as_str() {
+    Some("url") => {
+      let added_at = node["date_added"]
+        .as_str()
+        .and_then(|s| s.parse::<i64>().ok())
+        .filter(|us| *us > 0)
+        .map(|us| us / 1000 - WINDOWS_EPOCH_OFFSET_MS);
+      out.push(Bookmark {
+        title: node["name"].as_str().unwrap_or_default().to_string(),
+        url: node["url"].as_str().unwrap_or_default().to_string(),
+        added_at,
+        folders: folders.clone(),
+      });
+    }
+    Some("folder") => {
+      folders.push(node["name"].as_str().unwrap_or_default().to_string());
+      for child in node["children"].as_array().into_iter().flatten() {
+        chromium_node(child, folders, out);
+      }
+      folders.pop();
+    }
+    _ => {}
+  }
+}
+
+fn chromium_bookmarks(app: &tauri::AppHandle, browser: &str, profile: &str) -> Result<Vec<Bookmark>, String> {
+  let file = chromium_dir(app, browser)?.join(profile).join("Bookmarks");
+  let json: Value = serde_json::from_str(
+    &fs::read_to_string(&file).map_err(|_| format!("No bookmarks found at {}", file.display()))?,
+  )
+  .map_err(|e| format!("Could not read bookmarks: {}", e))?;
+
+  let mut out = Vec::new();
+  for root in json["roots"].as_object().into_iter().flat_map(|roots| roots.values()) {
+    // Roots ("Bookmarks bar", "Other bookmarks") aren't meaningful tags
+    for child in root["children"].as_array().into_iter().flatten() {
+      chromium_node(child, &mut Vec::new(), &mut out);
+    }
+  }
+  Ok(out)
+}
+
+fn firefox_bookmarks(app: &tauri::AppHandle, profile: Option<&str>) -> Result<Vec<Bookmark>, String> {
+  let profiles = firefox_profiles_dir(app)?;
+  let profile_dir = match profile {
+    Some(name) => profiles.join(name),
+    // The default profile's directory ends in `.default-release` (or
+    // `.default` on older installs)
+    None => {
+      let mut dirs: Vec<PathBuf> = fs::read_dir(&profiles)
+        .map_err(|_| format!("No Firefox profiles found at {}", profiles.display()))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.join("places.sqlite").exists())
+        .collect();
+      dirs.sort_by_key(|p| {
+        let name = p.to_string_lossy().to_string();
+        (!name.ends_with(".default-release"), !name.ends_with(".default"))
+      });
+      dirs.into_iter().next().ok_or_else(|| "No Firefox profile with bookmarks found".to_string())?
+    }
+  };
+
+  let places = profile_dir.join("places.sqlite");
+  if !places.exists() {
+    return Err(format!("No bookmarks found at {}", places.display()));
+  }
+  // Firefox keeps the database locked; read a copy, with its WAL if any
+  let copy = std::env::temp_dir().join(format!("papa-places-{}.sqlite", generate_id()));
+  fs::copy(&places, &copy).map_err(|e| e.to_string())?;
+  let wal = profile_dir.join("places.sqlite-wal");
+  let wal_copy = PathBuf::from(format!("{}-wal", copy.display()));
+  if wal.exists() {
+    let _ = fs::copy(&wal, &wal_copy);
+  }
+  let result = read_places(&copy);
+  let _ = fs::remove_file(&copy);
+  let _ = fs::remove_file(&wal_copy);
+  result
+}
+
+fn read_places(path: &PathBuf) -> Result<Vec<Bookmark>, String> {
+  let conn = rusqlite::Connection::open(path).map_err(|e| e.to_string())?;
+
+  // id -> (parent, title, guid)
+  let folders: HashMap<i64, (i64, String, String)> = conn
+    .prepare("SELECT id, parent, COALESCE(title, ''), guid FROM moz_bookmarks WHERE type = 2")
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?, row.get(3)?))))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let rows: Vec<(i64, String, Option<i64>, String)> = conn
+    .prepare(
+      "SELECT b.parent, COALESCE(b.title, ''), b.dateAdded, p.url
+       FROM moz_bookmarks b JOIN moz_places p ON p.id = b.fk
+       WHERE b.type = 1",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let mut out = Vec::new();
+  'rows: for (parent, title, added_us, url) in rows {
+    let mut path = Vec::new();
+    let mut current = parent;
+    while let Some((up, name, guid)) = folders.get(&current) {
+      // Entries under the tags root are Firefox's own tags, not bookmarks
+      if guid == FIREFOX_TAGS_ROOT {
+        continue 'rows;
+      }
+      if FIREFOX_ROOTS.contains(&guid.as_str()) {
+        break;
+      }
+      path.push(name.clone());
+      current = *up;
+    }
+    path.reverse();
+    out.push(Bookmark { title, url, added_at: added_us.map(|us| us / 1000), folders: path });
+  }
+  Ok(out)
+}
+
+/// Create `link` events from a browser's bookmarks. `browser` is "chrome",
+/// "edge", "brave" or "firefox"; `profile` is the profile directory name
+/// ("Default" for Chromium browsers and the default Firefox profile when
+/// left out).
+#[tauri::command]
+pub fn import_browser_bookmarks(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  browser: String,
+  profile: Option<String>,
+) -> Result<BookmarkImport, String> {
+  let profile = profile.filter(|p| !p.trim().is_empty());
+  let bookmarks = match browser.as_str() {
+    "chrome" | "edge" | "brave" => chromium_bookmarks(&app, &browser, profile.as_deref().unwrap_or("Default"))?,
+    "firefox" => firefox_bookmarks(&app, profile.as_deref())?,
+    _ => return Err(format!("Unsupported browser: {}", browser)),
+  };
+  let source = format!("browser:{}", browser);
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+  let mut result = BookmarkImport { imported: 0, skipped: 0 };
+  let now = now_ms();
+  for bookmark in bookmarks {
+    if !(bookmark.url.starts_with("http://") || bookmark.url.starts_with("https://")) {
+      continue;
+    }
+    let exists: bool = tx
+      .query_row(
+        "SELECT EXISTS (SELECT 1 FROM timeline_events
+                        WHERE source = ?1 AND json_extract(metadata, '$.url') = ?2)",
+        (&source, &bookmark.url),
+        |row| row.get(0),
+      )
+      .map_err(|e| e.to_string())?;
+    if exists {
+      result.skipped += 1;
+      continue;
+    }
+
+    let event_id = generate_id();
+    let title = if bookmark.title.trim().is_empty() { bookmark.url.clone() } else { bookmark.title.clone() };
+    let metadata = json!({ "url": bookmark.url, "browser": browser, "folder": bookmark.folders.join("/") });
+    tx.execute(
+      "INSERT INTO timeline_events (id, type, title, text_content, created_at, source, is_deleted, metadata)
+       VALUES (?1, 'link', ?2, ?3, ?4, ?5, 0, ?6)",
+      (&event_id, &title, &bookmark.url, bookmark.added_at.unwrap_or(now), &source, metadata.to_string()),
+    )
+    .map_err(|e| e.to_string())?;
+    tags::apply(&tx, &event_id, &bookmark.folders, "import")?;
+    result.imported += 1;
+  }
+  tx.commit().map_err(|e| e.to_string())?;
+  Ok(result)
+}
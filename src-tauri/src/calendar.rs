@@ -0,0 +1,819 @@
+// Read-only calendar subscriptions.
+//
+// `subscribe_calendar` takes an ICS feed URL (webcal:// works too, which is
+// what most calendar apps hand out for sharing) and the feed is fetched again
+// every 30 minutes. Entries are kept in `calendar_entries` for a window from
+// yesterday to two weeks ahead, replaced wholesale on every fetch; nothing is
+// ever written back to the calendar. Meetings show up next to the timeline
+// through `list_meetings`, and shortly before one starts the pet is told with
+// `meeting-starting` so it can offer to take notes. `capture_meeting_notes`
+// then creates a text event titled after the meeting with the entry in its
+// metadata, and returns that same event when asked again.
+//
+// The parser covers what calendar exports use in practice: daily and weekly
+// RRULEs (INTERVAL, BYDAY, COUNT, UNTIL) with EXDATE and moved occurrences
+// (RECURRENCE-ID). Other recurrences only show their first occurrence. Times
+// with a TZID are read as local time, since there is no timezone database to
+// convert them with; UTC times are converted properly.
+
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::permissions::{self, Permission};
+use crate::{
+  app_lock, connectivity, day_bounds, event_from_row, generate_id, location, mentions, now_ms, query_attachments, query_reminders,
+  quiet_hours, supervisor, tags, timezone, usage_metrics, DbState, TimelineEventWithAttachments,
+};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+// Offer notes this long before a meeting starts, and until this long after
+const OFFER_BEFORE_MS: i64 = 5 * 60 * 1000;
+const OFFER_AFTER_MS: i64 = 10 * 60 * 1000;
+const WINDOW_PAST_DAYS: i64 = 1;
+const WINDOW_FUTURE_DAYS: i64 = 14;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Calendar {
+  id: String,
+  url: String,
+  name: Option<String>,
+  last_fetched_at: Option<i64>,
+  last_error: Option<String>,
+  created_at: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Meeting {
+  calendar_id: String,
+  uid: String,
+  title: String,
+  location: Option<String>,
+  description: Option<String>,
+  starts_at: i64,
+  ends_at: i64,
+  all_day: bool,
+  /// The notes event captured for this meeting, if any
+  notes_event_id: Option<String>,
+}
+
+// ---- ICS parsing ----
+
+#[derive(Clone, Copy, PartialEq)]
+enum TimeKind {
+  Utc,
+  Local,
+  Date,
+}
+
+#[derive(Clone, Copy)]
+struct IcsTime {
+  at: NaiveDateTime,
+  kind: TimeKind,
+}
+
+impl IcsTime {
+  fn parse(params: &str, value: &str) -> Option<Self> {
+    let value = value.trim();
+    if params.to_uppercase().contains("VALUE=DATE") && !params.to_uppercase().contains("VALUE=DATE-TIME")
+      || value.len() == 8
+    {
+      let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+      return Some(Self { at: date.and_hms_opt(0, 0, 0)?, kind: TimeKind::Date });
+    }
+    let (value, kind) = match value.strip_suffix('Z') {
+      Some(utc) => (utc, TimeKind::Utc),
+      None => (value, TimeKind::Local),
+    };
+    let at = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    Some(Self { at, kind })
+  }
+
+  fn to_ms(self) -> Option<i64> {
+    match self.kind {
+      TimeKind::Utc => Some(Utc.from_utc_datetime(&self.at).timestamp_millis()),
+      TimeKind::Local | TimeKind::Date => Local.from_local_datetime(&self.at).earliest().map(|t| t.timestamp_millis()),
+    }
+  }
+
+  fn shifted(self, days: i64) -> Self {
+    Self { at: self.at + ChronoDuration::days(days), kind: self.kind }
+  }
+}
+
+#[derive(Default)]
+struct VEvent {
+  uid: Option<String>,
+  summary: Option<String>,
+  location: Option<String>,
+  description: Option<String>,
+  start: Option<IcsTime>,
+  end: Option<IcsTime>,
+  rrule: Option<String>,
+  exdates: Vec<IcsTime>,
+  recurrence_id: Option<IcsTime>,
+  cancelled: bool,
+}
+
+struct ParsedCalendar {
+  name: Option<String>,
+  events: Vec<VEvent>,
+}
+
+fn unescape(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  let mut chars = text.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      out.push(c);
+      continue;
+    }
+    match chars.next() {
+      Some('n') | Some('N') => out.push('\n'),
+      Some(other) => out.push(other),
+      None => {}
+    }
+  }
+  out
+}
+
+fn parse_ics(body: &str) -> Result<ParsedCalendar, String> {
+  // Continuation lines start with a space or tab
+  let mut lines: Vec<String> = Vec::new();
+  for raw in body.lines() {
+    match (raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t')), lines.last_mut()) {
+      (Some(rest), Some(last)) => last.push_str(rest),
+      _ => lines.push(raw.to_string()),
+    }
+  }
+  if !lines.iter().any(|l| l.trim().eq_ignore_ascii_case("BEGIN:VCALENDAR")) {
+    return Err("Not an ICS calendar".to_string());
+  }
+
+  let mut calendar = ParsedCalendar { name: None, events: Vec::new() };
+  let mut current: Option<VEvent> = None;
+  // Alarms and other components nested in an event carry their own properties
+  let mut nested = 0;
+  for line in &lines {
+    let Some((key, value)) = line.split_once(':') else { continue };
+    let (name, params) = key.split_once(';').unwrap_or((key, ""));
+    let name = name.to_uppercase();
+    match (name.as_str(), current.as_mut()) {
+      ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => current = Some(VEvent::default()),
+      ("BEGIN", Some(_)) => nested += 1,
+      ("END", Some(_)) if nested > 0 => nested -= 1,
+      ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => calendar.events.extend(current.take()),
+      (_, Some(_)) if nested > 0 => {}
+      ("X-WR-CALNAME", None) => calendar.name = Some(unescape(value)),
+      ("UID", Some(event)) => event.uid = Some(value.to_string()),
+      ("SUMMARY", Some(event)) => event.summary = Some(unescape(value)),
+      ("LOCATION", Some(event)) => event.location = Some(unescape(value)).filter(|l| !l.trim().is_empty()),
+      ("DESCRIPTION", Some(event)) => event.description = Some(unescape(value)).filter(|d| !d.trim().is_empty()),
+      ("DTSTART", Some(event)) => event.start = IcsTime::parse(params, value),
+      ("DTEND", Some(event)) => event.end = IcsTime::parse(params, value),
+      ("RRULE", Some(event)) => event.rrule = Some(value.to_uppercase()),
+      ("EXDATE", Some(event)) => event.exdates.extend(value.split(',').filter_map(|v| IcsTime::parse(params, v))),
+      ("RECURRENCE-ID", Some(event)) => event.recurrence_id = IcsTime::parse(params, value),
+      ("STATUS", Some(event)) => event.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+      _ => {}
+    }
+  }
+  Ok(calendar)
+}
+
+fn weekday(code: &str) -> Option<Weekday> {
+  // BYDAY may carry an ordinal ("1MO"), which only means something monthly
+  match code.trim_start_matches(|c: char| c == '+' || c == '-' || c.is_ascii_digit()) {
+    "MO" => Some(Weekday::Mon),
+    "TU" => Some(Weekday::Tue),
+    "WE" => Some(Weekday::Wed),
+    "TH" => Some(Weekday::Thu),
+    "FR" => Some(Weekday::Fri),
+    "SA" => Some(Weekday::Sat),
+    "SU" => Some(Weekday::Sun),
+    _ => None,
+  }
+}
+
+/// Start times of `event` up to `until_ms`, earliest first.
+fn occurrences(event: &VEvent, until_ms: i64) -> Vec<IcsTime> {
+  let Some(start) = event.start else { return Vec::new() };
+  let Some(rule) = &event.rrule else { return vec![start] };
+  let parts: HashMap<&str, &str> = rule.split(';').filter_map(|p| p.split_once('=')).collect();
+  let interval = parts.get("INTERVAL").and_then(|v| v.parse::<i64>().ok()).unwrap_or(1).max(1);
+  let count = parts.get("COUNT").and_then(|v| v.parse::<usize>().ok());
+  let until = parts.get("UNTIL").and_then(|v| IcsTime::parse("", v)).and_then(|t| t.to_ms());
+  let days: Vec<Weekday> = parts.get("BYDAY").map(|v| v.split(',').filter_map(weekday).collect()).unwrap_or_default();
+
+  let weekly = match parts.get("FREQ").copied() {
+    Some("DAILY") => false,
+    Some("WEEKLY") => true,
+    _ => return vec![start],
+  };
+  let days = if days.is_empty() { vec![start.at.weekday()] } else { days };
+  let week_of = |date: NaiveDate| date.num_days_from_ce() as i64 - date.weekday().num_days_from_monday() as i64;
+  let first_week = week_of(start.at.date());
+
+  let mut found = Vec::new();
+  let mut offset = 0;
+  loop {
+    let candidate = start.shifted(offset);
+    offset += 1;
+    let Some(ms) = candidate.to_ms() else { continue };
+    if ms > until_ms || until.is_some_and(|u| ms > u) || count.is_some_and(|c| found.len() >= c) {
+      break;
+    }
+    let date = candidate.at.date();
+    let matches = match weekly {
+      true => days.contains(&date.weekday()) && ((week_of(date) - first_week) / 7) % interval == 0,
+      false => (offset - 1) % interval == 0,
+    };
+    if matches {
+      found.push(candidate);
+    }
+  }
+  // Excluded dates still count towards COUNT, so drop them last
+  let excluded: HashSet<Option<i64>> = event.exdates.iter().map(|t| t.to_ms()).collect();
+  found.retain(|t| !excluded.contains(&t.to_ms()));
+  found
+}
+
+struct Entry {
+  uid: String,
+  title: String,
+  location: Option<String>,
+  description: Option<String>,
+  starts_at: i64,
+  ends_at: i64,
+  all_day: bool,
+}
+
+/// The entries of `calendar` that overlap the window around `now`.
+fn entries_in_window(calendar: &ParsedCalendar, now: i64) -> Vec<Entry> {
+  let day_ms = 24 * 60 * 60 * 1000;
+  let (from, until) = (now - WINDOW_PAST_DAYS * day_ms, now + WINDOW_FUTURE_DAYS * day_ms);
+
+  // Occurrences moved or cancelled individually, by UID and original start
+  let moved: HashSet<(String, Option<i64>)> = calendar
+    .events
+    .iter()
+    .filter_map(|e| Some((e.uid.clone()?, e.recurrence_id?.to_ms())))
+    .collect();
+
+  let mut entries = Vec::new();
+  for event in &calendar.events {
+    let (Some(uid), Some(start)) = (&event.uid, event.start) else { continue };
+    let length = event
+      .end
+      .and_then(|end| Some(end.to_ms()? - start.to_ms()?))
+      .filter(|l| *l >= 0)
+      .unwrap_or(if start.kind == TimeKind::Date { day_ms } else { 0 });
+    for occurrence in occurrences(event, until) {
+      let Some(starts_at) = occurrence.to_ms() else { continue };
+      let ends_at = starts_at + length;
+      let replaced = event.recurrence_id.is_none() && moved.contains(&(uid.clone(), Some(starts_at)));
+      if event.cancelled || replaced || ends_at < from || starts_at > until {
+        continue;
+      }
+      entries.push(Entry {
+        uid: uid.clone(),
+        title: event.summary.clone().unwrap_or_else(|| "(untitled)".to_string()),
+        location: event.location.clone(),
+        description: event.description.clone(),
+        starts_at,
+        ends_at,
+        all_day: start.kind == TimeKind::Date,
+      });
+    }
+  }
+  entries
+}
+
+// ---- Fetching ----
+
+fn normalize_url(url: &str) -> Result<String, String> {
+  let url = url.trim();
+  let url = match url.strip_prefix("webcal://") {
+    Some(rest) => format!("https://{}", rest),
+    None => url.to_string(),
+  };
+  if !url.starts_with("https://") && !url.starts_with("http://") {
+    return Err("Calendar URL must be http(s) or webcal".to_string());
+  }
+  Ok(url)
+}
+
+async fn download(url: &str) -> Result<ParsedCalendar, String> {
+  permissions::ensure(Permission::Network)?;
+  let client = connectivity::client_builder().timeout(HTTP_TIMEOUT).build().map_err(|e| e.to_string())?;
+  let response = client.get(url).send().await.map_err(|e| connectivity::request_error("Calendar request failed", e))?;
+  if !response.status().is_success() {
+    return Err(format!("Calendar server returned {}", response.status()));
+  }
+  let body = response.text().await.map_err(|e| e.to_string())?;
+  parse_ics(&body)
+}
+
+fn store_entries(conn: &mut rusqlite::Connection, calendar_id: &str, entries: &[Entry]) -> Result<(), String> {
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  tx.execute("DELETE FROM calendar_entries WHERE calendar_id = ?", [calendar_id]).map_err(|e| e.to_string())?;
+  for entry in entries {
+    tx.execute(
+      "INSERT OR REPLACE INTO calendar_entries
+         (calendar_id, uid, starts_at, ends_at, all_day, title, location, description)
+       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+      rusqlite::params![
+        calendar_id,
+        entry.uid,
+        entry.starts_at,
+        entry.ends_at,
+        entry.all_day,
+        entry.title,
+        entry.location,
+        entry.description,
+      ],
+    )
+    .map_err(|e| e.to_string())?;
+  }
+  tx.execute(
+    "UPDATE calendars SET last_fetched_at = ?1, last_error = NULL WHERE id = ?2",
+    (now_ms(), calendar_id),
+  )
+  .map_err(|e| e.to_string())?;
+  tx.commit().map_err(|e| e.to_string())
+}
+
+/// Fetch one subscription and replace its entries. Failures are kept on the
+/// calendar so the settings can show them.
+async fn refresh(app: &tauri::AppHandle, calendar_id: &str, url: &str) -> Result<(), String> {
+  let result = download(url).await.map(|calendar| entries_in_window(&calendar, now_ms()));
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+  match result {
+    Ok(entries) => store_entries(&mut conn, calendar_id, &entries),
+    Err(error) => {
+      conn
+        .execute("UPDATE calendars SET last_error = ?1 WHERE id = ?2", (&error, calendar_id))
+        .map_err(|e| e.to_string())?;
+      Err(error)
+    }
+  }
+}
+
+fn subscriptions(app: &tauri::AppHandle) -> Result<Vec<(String, String)>, String> {
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let calendars = conn
+    .prepare("SELECT id, url FROM calendars")
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(calendars)
+}
+
+async fn refresh_all(app: &tauri::AppHandle) -> Result<(), String> {
+  for (id, url) in subscriptions(app)? {
+    if let Err(e) = refresh(app, &id, &url).await {
+      tracing::warn!(calendar = %id, error = %e, "Calendar refresh failed");
+    }
+  }
+  Ok(())
+}
+
+// ---- Meetings ----
+
+const MEETING_COLUMNS: &str = "c.calendar_id, c.uid, c.title, c.location, c.description, c.starts_at, c.ends_at, c.all_day,
+  (SELECT e.id FROM timeline_events e
+   WHERE e.is_deleted = 0 AND json_extract(e.metadata, '$.calendar.uid') = c.uid
+     AND json_extract(e.metadata, '$.calendar.startsAt') = c.starts_at
+   LIMIT 1)";
+
+fn meeting_from_row(row: &rusqlite::Row) -> rusqlite::Result<Meeting> {
+  Ok(Meeting {
+    calendar_id: row.get(0)?,
+    uid: row.get(1)?,
+    title: row.get(2)?,
+    location: row.get(3)?,
+    description: row.get(4)?,
+    starts_at: row.get(5)?,
+    ends_at: row.get(6)?,
+    all_day: row.get(7)?,
+    notes_event_id: row.get(8)?,
+  })
+}
+
+fn meetings_between(conn: &rusqlite::Connection, from: i64, until: i64) -> Result<Vec<Meeting>, String> {
+  let meetings = conn
+    .prepare(&format!(
+      "SELECT {} FROM calendar_entries c
+       WHERE c.starts_at <= ?2 AND c.ends_at >= ?1
+       ORDER BY c.all_day DESC, c.starts_at ASC",
+      MEETING_COLUMNS
+    ))
+    .map_err(|e| e.to_string())?
+    .query_map((from, until), meeting_from_row)
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(meetings)
+}
+
+/// Title, start and end of the timed meetings overlapping `from..until`,
+/// for laying out a day around them. All-day entries don't block time.
+pub fn busy_between(conn: &rusqlite::Connection, from: i64, until: i64) -> Result<Vec<(String, i64, i64)>, String> {
+  Ok(
+    meetings_between(conn, from, until)?
+      .into_iter()
+      .filter(|m| !m.all_day)
+      .map(|m| (m.title, m.starts_at, m.ends_at))
+      .collect(),
+  )
+}
+
+/// Meetings about to start that have no notes yet, each offered once per run.
+fn due_offers(app: &tauri::AppHandle, offered: &mut HashSet<(String, i64)>) -> Result<Vec<Meeting>, String> {
+  let now = now_ms();
+  let meetings = {
+    let state = app.state::<DbState>();
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    meetings_between(&conn, now - OFFER_AFTER_MS, now + OFFER_BEFORE_MS)?
+  };
+  Ok(
+    meetings
+      .into_iter()
+      .filter(|m| !m.all_day && m.notes_event_id.is_none())
+      .filter(|m| m.starts_at >= now - OFFER_AFTER_MS && m.starts_at <= now + OFFER_BEFORE_MS)
+      .filter(|m| offered.insert((m.uid.clone(), m.starts_at)))
+      .collect(),
+  )
+}
+
+pub fn spawn_calendar_watcher(app: tauri::AppHandle) {
+  supervisor::supervise(app, "calendar", |app| async move {
+    let mut offered: HashSet<(String, i64)> = HashSet::new();
+    let mut next_refresh = tokio::time::Instant::now();
+    loop {
+      if tokio::time::Instant::now() >= next_refresh && connectivity::reachable() {
+        if let Err(e) = refresh_all(&app).await {
+          tracing::warn!(error = %e, "Calendar refresh failed");
+        }
+        next_refresh = tokio::time::Instant::now() + REFRESH_INTERVAL;
+      }
+      let offers = due_offers(&app, &mut offered).unwrap_or_default();
+      if !quiet_hours::is_quiet(&app) {
+        if let Some(window) = app.get_webview_window("main") {
+          for meeting in offers {
+            let _ = window.emit("meeting-starting", &meeting);
+          }
+        }
+      }
+      // Back online: refresh now rather than at the next interval
+      if connectivity::sleep(CHECK_INTERVAL).await {
+        next_refresh = tokio::time::Instant::now();
+      }
+    }
+  });
+}
+
+// ---- Commands ----
+
+#[tauri::command]
+pub async fn subscribe_calendar(
+  app: tauri::AppHandle,
+  ics_url: String,
+  name: Option<String>,
+) -> Result<Calendar, String> {
+  let _timer = usage_metrics::time_async("subscribe_calendar");
+  let url = normalize_url(&ics_url)?;
+  let parsed = download(&url).await?;
+  let entries = entries_in_window(&parsed, now_ms());
+
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+  let exists: bool = conn
+    .query_row("SELECT COUNT(*) FROM calendars WHERE url = ?", [&url], |row| row.get::<_, i64>(0))
+    .map_err(|e| e.to_string())?
+    > 0;
+  if exists {
+    return Err("Already subscribed to this calendar".to_string());
+  }
+
+  let calendar = Calendar {
+    id: generate_id(),
+    url,
+    name: name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).or(parsed.name),
+    last_fetched_at: None,
+    last_error: None,
+    created_at: now_ms(),
+  };
+  conn
+    .execute(
+      "INSERT INTO calendars (id, url, name, created_at) VALUES (?1, ?2, ?3, ?4)",
+      (&calendar.id, &calendar.url, &calendar.name, calendar.created_at),
+    )
+    .map_err(|e| e.to_string())?;
+  store_entries(&mut conn, &calendar.id, &entries)?;
+  tracing::info!(calendar = %calendar.id, entries = entries.len(), "Subscribed to calendar");
+  Ok(Calendar { last_fetched_at: Some(now_ms()), ..calendar })
+}
+
+#[tauri::command]
+pub fn unsubscribe_calendar(state: tauri::State<DbState>, calendar_id: String) -> Result<(), String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  conn.execute("DELETE FROM calendar_entries WHERE calendar_id = ?", [&calendar_id]).map_err(|e| e.to_string())?;
+  let removed = conn.execute("DELETE FROM calendars WHERE id = ?", [&calendar_id]).map_err(|e| e.to_string())?;
+  if removed == 0 {
+    return Err("Calendar not found".to_string());
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub fn list_calendars(state: tauri::State<DbState>) -> Result<Vec<Calendar>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let calendars = conn
+    .prepare("SELECT id, url, name, last_fetched_at, last_error, created_at FROM calendars ORDER BY created_at")
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| {
+      Ok(Calendar {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        name: row.get(2)?,
+        last_fetched_at: row.get(3)?,
+        last_error: row.get(4)?,
+        created_at: row.get(5)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(calendars)
+}
+
+/// Fetch every subscription now instead of waiting for the next refresh.
+#[tauri::command]
+pub async fn refresh_calendars(app: tauri::AppHandle) -> Result<(), String> {
+  let _timer = usage_metrics::time_async("refresh_calendars");
+  refresh_all(&app).await
+}
+
+/// Meetings on `date_key` (YYYY-MM-DD, default today), all-day ones first.
+#[tauri::command]
+pub fn list_meetings(state: tauri::State<DbState>, date_key: Option<String>) -> Result<Vec<Meeting>, String> {
+  app_lock::ensure_unlocked()?;
+  let date_key = date_key.unwrap_or_else(timezone::today_key);
+  let (start, end) = day_bounds(&date_key)?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  // An all-day entry ends at the next midnight, which isn't part of the day
+  let meetings = meetings_between(&conn, start, end)?
+    .into_iter()
+    .filter(|m| !(m.all_day && m.ends_at == start))
+    .collect();
+  Ok(meetings)
+}
+
+/// Create a text event for taking notes in a meeting, titled after it and
+/// linked to the entry through `metadata.calendar`. If notes were already
+/// captured for that meeting, returns them instead.
+#[tauri::command]
+pub fn capture_meeting_notes(
+  state: tauri::State<DbState>,
+  calendar_id: String,
+  uid: String,
+  starts_at: i64,
+) -> Result<TimelineEventWithAttachments, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let meeting = conn
+    .query_row(
+      &format!(
+        "SELECT {} FROM calendar_entries c WHERE c.calendar_id = ?1 AND c.uid = ?2 AND c.starts_at = ?3",
+        MEETING_COLUMNS
+      ),
+      (&calendar_id, &uid, starts_at),
+      meeting_from_row,
+    )
+    .map_err(|_| "Meeting not found".to_string())?;
+
+  let event_id = match meeting.notes_event_id {
+    Some(existing) => existing,
+    None => {
+      let event_id = generate_id();
+      let metadata = json!({
+        "calendar": {
+          "calendarId": meeting.calendar_id,
+          "uid": meeting.uid,
+          "startsAt": meeting.starts_at,
+          "endsAt": meeting.ends_at,
+          "title": meeting.title,
+          "location": meeting.location,
+        }
+      });
+      conn
+        .execute(
+          "INSERT INTO timeline_events (id, type, title, note, created_at, source, is_deleted, metadata)
+           VALUES (?1, 'text', ?2, '', ?3, 'calendar', 0, ?4)",
+          (&event_id, &meeting.title, now_ms(), metadata.to_string()),
+        )
+        .map_err(|e| e.to_string())?;
+      tags::auto_tag(&conn, &event_id);
+      tags::sync_hashtags(&conn, &event_id)?;
+      mentions::index(&conn, &event_id)?;
+      location::stamp(&conn, &event_id)?;
+      event_id
+    }
+  };
+
+  let event = conn
+    .query_row(
+      "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
+       FROM timeline_events WHERE id = ?",
+      [&event_id],
+      event_from_row,
+    )
+    .map_err(|e| e.to_string())?;
+  let attachments = query_attachments(&conn, &event_id)?;
+  let reminders = query_reminders(&conn, &event_id)?;
+  Ok(TimelineEventWithAttachments { event, attachments, reminders })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ics(lines: &[&str]) -> String {
+    let mut all = vec!["BEGIN:VCALENDAR", "VERSION:2.0"];
+    all.extend_from_slice(lines);
+    all.push("END:VCALENDAR");
+    all.join("\r\n")
+  }
+
+  fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> i64 {
+    Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap().timestamp_millis()
+  }
+
+  fn starts(calendar: &ParsedCalendar) -> Vec<i64> {
+    occurrences(&calendar.events[0], i64::MAX).iter().filter_map(|t| t.to_ms()).collect()
+  }
+
+  #[test]
+  fn parses_folded_and_escaped_properties() {
+    let calendar = parse_ics(&ics(&[
+      "X-WR-CALNAME:Team\\, shared",
+      "BEGIN:VEVENT",
+      "UID:standup@example.com",
+      "SUMMARY:Daily stand-up\\nroom 4",
+      "LOCATION:",
+      "DTSTART:20240304T090000Z",
+      "DTEND:20240304T091500Z",
+      "DESCRIPTION:Agenda: blockers an",
+      " d updates",
+      "BEGIN:VALARM",
+      "DESCRIPTION:Reminder",
+      "END:VALARM",
+      "END:VEVENT",
+    ]))
+    .unwrap();
+
+    assert_eq!(calendar.name.as_deref(), Some("Team, shared"));
+    assert_eq!(calendar.events.len(), 1);
+    let event = &calendar.events[0];
+    assert_eq!(event.uid.as_deref(), Some("standup@example.com"));
+    assert_eq!(event.summary.as_deref(), Some("Daily stand-up\nroom 4"));
+    assert_eq!(event.location, None);
+    // The alarm's description doesn't replace the event's
+    assert_eq!(event.description.as_deref(), Some("Agenda: blockers and updates"));
+    assert_eq!(event.start.and_then(|t| t.to_ms()), Some(at(2024, 3, 4, 9, 0)));
+    assert_eq!(event.end.and_then(|t| t.to_ms()), Some(at(2024, 3, 4, 9, 15)));
+  }
+
+  #[test]
+  fn rejects_other_documents() {
+    assert!(parse_ics("<html><body>Sign in</body></html>").is_err());
+  }
+
+  #[test]
+  fn reads_dates_as_all_day() {
+    let calendar = parse_ics(&ics(&[
+      "BEGIN:VEVENT",
+      "UID:holiday",
+      "DTSTART;VALUE=DATE:20240305",
+      "END:VEVENT",
+    ]))
+    .unwrap();
+    let start = calendar.events[0].start.unwrap();
+    assert!(start.kind == TimeKind::Date);
+    assert_eq!(start.at, NaiveDate::from_ymd_opt(2024, 3, 5).unwrap().and_hms_opt(0, 0, 0).unwrap());
+  }
+
+  #[test]
+  fn expands_weekly_rules_with_count_and_exdate() {
+    // Mondays and Wednesdays; the excluded Wednesday still counts
+    let calendar = parse_ics(&ics(&[
+      "BEGIN:VEVENT",
+      "UID:sync",
+      "DTSTART:20240304T090000Z",
+      "RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4",
+      "EXDATE:20240306T090000Z",
+      "END:VEVENT",
+    ]))
+    .unwrap();
+    assert_eq!(starts(&calendar), vec![at(2024, 3, 4, 9, 0), at(2024, 3, 11, 9, 0), at(2024, 3, 13, 9, 0)]);
+  }
+
+  #[test]
+  fn expands_rules_with_interval_and_until() {
+    let fortnightly = parse_ics(&ics(&[
+      "BEGIN:VEVENT",
+      "UID:review",
+      "DTSTART:20240301T100000Z",
+      "RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=FR;UNTIL=20240401T000000Z",
+      "END:VEVENT",
+    ]))
+    .unwrap();
+    assert_eq!(
+      starts(&fortnightly),
+      vec![at(2024, 3, 1, 10, 0), at(2024, 3, 15, 10, 0), at(2024, 3, 29, 10, 0)]
+    );
+
+    let every_third_day = parse_ics(&ics(&[
+      "BEGIN:VEVENT",
+      "UID:water",
+      "DTSTART:20240304T080000Z",
+      "RRULE:FREQ=DAILY;INTERVAL=3;COUNT=3",
+      "END:VEVENT",
+    ]))
+    .unwrap();
+    assert_eq!(
+      starts(&every_third_day),
+      vec![at(2024, 3, 4, 8, 0), at(2024, 3, 7, 8, 0), at(2024, 3, 10, 8, 0)]
+    );
+  }
+
+  #[test]
+  fn keeps_only_the_first_occurrence_of_other_rules() {
+    let calendar = parse_ics(&ics(&[
+      "BEGIN:VEVENT",
+      "UID:rent",
+      "DTSTART:20240301T090000Z",
+      "RRULE:FREQ=MONTHLY;BYMONTHDAY=1",
+      "END:VEVENT",
+    ]))
+    .unwrap();
+    assert_eq!(starts(&calendar), vec![at(2024, 3, 1, 9, 0)]);
+  }
+
+  #[test]
+  fn applies_moved_and_cancelled_occurrences() {
+    let calendar = parse_ics(&ics(&[
+      "BEGIN:VEVENT",
+      "UID:standup",
+      "SUMMARY:Stand-up",
+      "DTSTART:20240304T090000Z",
+      "DTEND:20240304T091500Z",
+      "RRULE:FREQ=DAILY;COUNT=3",
+      "END:VEVENT",
+      "BEGIN:VEVENT",
+      "UID:standup",
+      "SUMMARY:Stand-up (moved)",
+      "RECURRENCE-ID:20240305T090000Z",
+      "DTSTART:20240305T140000Z",
+      "DTEND:20240305T141500Z",
+      "END:VEVENT",
+      "BEGIN:VEVENT",
+      "UID:offsite",
+      "STATUS:CANCELLED",
+      "DTSTART:20240306T090000Z",
+      "END:VEVENT",
+    ]))
+    .unwrap();
+
+    let mut entries = entries_in_window(&calendar, at(2024, 3, 4, 12, 0));
+    entries.sort_by_key(|e| e.starts_at);
+    let found: Vec<(&str, i64, i64)> = entries.iter().map(|e| (e.title.as_str(), e.starts_at, e.ends_at)).collect();
+    assert_eq!(
+      found,
+      vec![
+        ("Stand-up", at(2024, 3, 4, 9, 0), at(2024, 3, 4, 9, 15)),
+        ("Stand-up (moved)", at(2024, 3, 5, 14, 0), at(2024, 3, 5, 14, 15)),
+        ("Stand-up", at(2024, 3, 6, 9, 0), at(2024, 3, 6, 9, 15)),
+      ]
+    );
+  }
+}
@@ -0,0 +1,162 @@
+// Aggregates over what was captured: words written, files kept, the hours
+// the user captures most and their most used tags.
+//
+// Everything is computed in SQL over events created in the period, deleted
+// ones left out. Words are whitespace-separated runs in the note and text
+// content; runs of up to eight blanks are collapsed first, which covers
+// anything but pasted layouts.
+
+use serde::Serialize;
+
+use crate::{app_lock, i18n, now_ms, reminder_stats, timezone, DbState};
+
+const TOP_TAGS: usize = 10;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+  pub name: String,
+  pub count: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureStats {
+  pub events: i64,
+  pub words: i64,
+  pub attachments: i64,
+  pub attachment_bytes: i64,
+  /// Events per local hour of the day, index 0 is midnight
+  pub events_by_hour: Vec<i64>,
+  pub busiest_hour: Option<u32>,
+  pub top_tags: Vec<TagCount>,
+}
+
+/// SQL counting the words in `column`, 0 for NULL or blank text.
+fn word_count_sql(column: &str) -> String {
+  let mut text = format!("REPLACE(REPLACE(REPLACE(COALESCE({}, ''), char(10), ' '), char(13), ' '), char(9), ' ')", column);
+  // Each pass halves the longest run of blanks
+  for _ in 0..3 {
+    text = format!("REPLACE({}, '  ', ' ')", text);
+  }
+  format!(
+    "CASE WHEN TRIM({0}) = '' THEN 0 ELSE LENGTH(TRIM({0})) - LENGTH(REPLACE(TRIM({0}), ' ', '')) + 1 END",
+    text
+  )
+}
+
+pub fn query_capture_stats(
+  conn: &rusqlite::Connection,
+  start_ms: Option<i64>,
+  end_ms: Option<i64>,
+) -> Result<CaptureStats, String> {
+  const IN_PERIOD: &str = "e.is_deleted = 0 AND (?1 IS NULL OR e.created_at >= ?1) AND (?2 IS NULL OR e.created_at <= ?2)";
+
+  let (events, words): (i64, i64) = conn
+    .query_row(
+      &format!(
+        "SELECT COUNT(*), COALESCE(SUM(({}) + ({})), 0) FROM timeline_events e WHERE {}",
+        word_count_sql("e.note"),
+        word_count_sql("e.text_content"),
+        IN_PERIOD
+      ),
+      (start_ms, end_ms),
+      |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(|e| e.to_string())?;
+
+  let (attachments, attachment_bytes): (i64, i64) = conn
+    .query_row(
+      &format!(
+        "SELECT COUNT(*), COALESCE(SUM(a.size_bytes), 0)
+         FROM attachments a JOIN timeline_events e ON e.id = a.event_id
+         WHERE {}",
+        IN_PERIOD
+      ),
+      (start_ms, end_ms),
+      |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(|e| e.to_string())?;
+
+  let mut events_by_hour = vec![0; 24];
+  let hours: Vec<(usize, i64)> = conn
+    .prepare(&format!(
+      "SELECT CAST(strftime('%H', e.created_at / 1000, 'unixepoch', {}) AS INTEGER), COUNT(*)
+       FROM timeline_events e WHERE {}
+       GROUP BY 1",
+      timezone::event_modifier_sql("?3"),
+      IN_PERIOD
+    ))
+    .map_err(|e| e.to_string())?
+    .query_map((start_ms, end_ms, timezone::sql_modifier()), |row| Ok((row.get(0)?, row.get(1)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  for (hour, count) in hours {
+    if let Some(slot) = events_by_hour.get_mut(hour) {
+      *slot = count;
+    }
+  }
+  // Earliest hour wins a tie
+  let busiest_hour = (0..24u32)
+    .filter(|h| events_by_hour[*h as usize] > 0)
+    .max_by_key(|h| (events_by_hour[*h as usize], std::cmp::Reverse(*h)));
+
+  let top_tags = conn
+    .prepare(&format!(
+      "SELECT t.name, COUNT(*)
+       FROM event_tags et
+       JOIN tags t ON t.id = et.tag_id
+       JOIN timeline_events e ON e.id = et.event_id
+       WHERE {}
+       GROUP BY t.id
+       ORDER BY COUNT(*) DESC, t.name
+       LIMIT {}",
+      IN_PERIOD, TOP_TAGS
+    ))
+    .map_err(|e| e.to_string())?
+    .query_map((start_ms, end_ms), |row| Ok(TagCount { name: row.get(0)?, count: row.get(1)? }))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  Ok(CaptureStats { events, words, attachments, attachment_bytes, events_by_hour, busiest_hour, top_tags })
+}
+
+/// Markdown bullet list for exports.
+pub fn format_stats(stats: &CaptureStats) -> String {
+  let mut out = format!(
+    "{}\n{}\n",
+    i18n::tf(
+      "- {events} events, {words} words written",
+      &[("events", stats.events.to_string()), ("words", stats.words.to_string())]
+    ),
+    i18n::tf(
+      "- {files} files captured ({size} MB)",
+      &[
+        ("files", stats.attachments.to_string()),
+        ("size", format!("{:.1}", stats.attachment_bytes as f64 / (1024.0 * 1024.0))),
+      ]
+    ),
+  );
+  if let Some(hour) = stats.busiest_hour {
+    let (from, to) = (format!("{:02}:00", hour), format!("{:02}:00", (hour + 1) % 24));
+    out.push_str(&format!("{}\n", i18n::tf("- Busiest hour: {from} - {to}", &[("from", from), ("to", to)])));
+  }
+  if !stats.top_tags.is_empty() {
+    let tags: Vec<String> = stats.top_tags.iter().map(|t| format!("#{} ({})", t.name, t.count)).collect();
+    out.push_str(&format!("{}\n", i18n::tf("- Top tags: {tags}", &[("tags", tags.join(", "))])));
+  }
+  out
+}
+
+/// Capture statistics for a period ("today", "week", "month" or "all").
+#[tauri::command]
+pub fn get_capture_stats(state: tauri::State<DbState>, period: String) -> Result<CaptureStats, String> {
+  app_lock::ensure_unlocked()?;
+  let start_ms = reminder_stats::period_start_ms(&period)?;
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  query_capture_stats(&conn, start_ms, Some(now_ms()))
+}
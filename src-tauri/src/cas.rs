@@ -0,0 +1,213 @@
+// Content-addressed storage for the files the app keeps.
+//
+// Copied attachments live under `attachments/` in the profile's folder,
+// named by their sha256: `attachments/ab/cdef…`, with the original
+// extension kept so the webview still serves them with the right type.
+// Dropping the same file twice stores it once; both attachments point at
+// the same blob.
+//
+// Every blob has a row in `blobs` whose `ref_count` is kept by triggers on
+// `attachments.stored_path`, so no code path that adds, moves or deletes an
+// attachment can forget to count. A blob is deleted when the last
+// attachment using it goes, from `remove_unused_file`.
+//
+// Files the app wrote itself (drops staged by the webview, recordings, moved
+// watch folder files) are moved into the store rather than copied: they're
+// hard-linked to the blob path and the old name is removed.
+// `migrate_attachment_storage` does the same for files stored before this
+// layout, and reports the space freed by duplicates.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+use crate::{hash_file, now_ms, usage_metrics, DbState};
+
+pub const DIR: &str = "attachments";
+
+#[derive(Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageMigration {
+  /// Stored files moved into the content-addressed layout
+  migrated: usize,
+  /// Of those, files whose content was already stored
+  duplicates: usize,
+  bytes_reclaimed: u64,
+  /// Stored files that couldn't be migrated, with the reason
+  failed: Vec<String>,
+}
+
+pub fn root(data_dir: &Path) -> PathBuf {
+  data_dir.join(DIR)
+}
+
+pub fn is_blob(data_dir: &Path, path: &Path) -> bool {
+  path.starts_with(root(data_dir))
+}
+
+/// Where content with `sha256` is stored, given the name it came with.
+fn blob_path(data_dir: &Path, sha256: &str, name: &Path) -> Result<PathBuf, String> {
+  if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+    return Err(format!("Invalid sha256: {}", sha256));
+  }
+  let sha256 = sha256.to_ascii_lowercase();
+  let extension = name
+    .extension()
+    .map(|ext| ext.to_string_lossy().to_lowercase())
+    .filter(|ext| !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()));
+  let file_name = match extension {
+    Some(ext) => format!("{}.{}", &sha256[2..], ext),
+    None => sha256[2..].to_string(),
+  };
+  Ok(root(data_dir).join(&sha256[..2]).join(file_name))
+}
+
+fn register(conn: &rusqlite::Connection, blob: &Path, sha256: &str) -> Result<String, String> {
+  let path = blob.to_string_lossy().to_string();
+  let size = fs::metadata(blob).map(|m| m.len() as i64).map_err(|e| e.to_string())?;
+  conn
+    .execute(
+      "INSERT OR IGNORE INTO blobs (path, sha256, size_bytes, ref_count, created_at) VALUES (?1, ?2, ?3, 0, ?4)",
+      (&path, sha256, size, now_ms()),
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(path)
+}
+
+/// Write through a temp name, so a blob path never holds a partial file.
+fn write_blob(blob: &Path, write: impl FnOnce(&Path) -> std::io::Result<()>) -> Result<(), String> {
+  let dir = blob.parent().ok_or("Invalid blob path")?;
+  fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+  let temp = dir.join(format!(".{}.part", now_ms()));
+  write(&temp).and_then(|_| fs::rename(&temp, blob)).map_err(|e| {
+    let _ = fs::remove_file(&temp);
+    format!("Failed to store file: {}", e)
+  })
+}
+
+/// Store a copy of `source`, reusing the blob when the content is already
+/// there. Returns the blob's path.
+pub fn store_file(conn: &rusqlite::Connection, data_dir: &Path, source: &Path, sha256: &str) -> Result<String, String> {
+  let blob = blob_path(data_dir, sha256, source)?;
+  if !blob.is_file() {
+    write_blob(&blob, |temp| fs::copy(source, temp).map(|_| ()))?;
+  }
+  register(conn, &blob, sha256)
+}
+
+/// Store `bytes` under a name like `name`. Returns the blob's path and the
+/// content's sha256.
+pub fn store_bytes(conn: &rusqlite::Connection, data_dir: &Path, name: &str, bytes: &[u8]) -> Result<(String, String), String> {
+  let sha256 = hex::encode(Sha256::digest(bytes));
+  let blob = blob_path(data_dir, &sha256, Path::new(name))?;
+  if !blob.is_file() {
+    write_blob(&blob, |temp| fs::write(temp, bytes))?;
+  }
+  Ok((register(conn, &blob, &sha256)?, sha256))
+}
+
+/// Move a file the app wrote itself into the store. Returns the blob's path.
+pub fn adopt(conn: &rusqlite::Connection, data_dir: &Path, file: &Path, sha256: &str) -> Result<String, String> {
+  let blob = blob_path(data_dir, sha256, file)?;
+  if !blob.is_file() {
+    // Same folder tree, so a hard link is enough; copy if it isn't supported
+    write_blob(&blob, |temp| fs::hard_link(file, temp).or_else(|_| fs::copy(file, temp).map(|_| ())))?;
+  }
+  let path = register(conn, &blob, sha256)?;
+  if let Err(e) = fs::remove_file(file) {
+    tracing::warn!(path = %file.display(), error = %e, "Stored file left behind");
+  }
+  Ok(path)
+}
+
+/// For a blob, delete it if no attachment uses it any more. Returns `false`
+/// when `path` isn't a blob and the caller should handle it.
+pub fn release(conn: &rusqlite::Connection, path: &str) -> Result<bool, String> {
+  let ref_count: Option<i64> = conn
+    .query_row("SELECT ref_count FROM blobs WHERE path = ?", [path], |row| row.get(0))
+    .ok();
+  let Some(ref_count) = ref_count else { return Ok(false) };
+  let as_original: i64 = conn
+    .query_row("SELECT COUNT(*) FROM attachments WHERE original_path = ?", [path], |row| row.get(0))
+    .map_err(|e| e.to_string())?;
+  if ref_count <= 0 && as_original == 0 {
+    conn.execute("DELETE FROM blobs WHERE path = ?", [path]).map_err(|e| e.to_string())?;
+    let _ = fs::remove_file(path);
+  }
+  Ok(true)
+}
+
+fn migrate(conn: &rusqlite::Connection, data_dir: &Path) -> Result<StorageMigration, String> {
+  // Files in the profile's folder but not in the store yet: drops, recordings
+  let files: Vec<String> = conn
+    .prepare("SELECT DISTINCT stored_path FROM attachments WHERE stored_path IS NOT NULL")
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| row.get(0))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .filter(|path: &String| Path::new(path).starts_with(data_dir) && !is_blob(data_dir, Path::new(path)))
+    .collect();
+
+  let mut report = StorageMigration::default();
+  for file in files {
+    let path = Path::new(&file);
+    let result = (|| -> Result<(), String> {
+      let size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+      let sha256 = hash_file(path)?;
+      let duplicate = blob_path(data_dir, &sha256, path)?.is_file();
+      let blob = adopt(conn, data_dir, path, &sha256)?;
+      conn
+        .execute(
+          "UPDATE attachments
+           SET stored_path = ?1, sha256 = ?2,
+               original_path = CASE WHEN original_path = ?3 THEN ?1 ELSE original_path END
+           WHERE stored_path = ?3",
+          (&blob, &sha256, &file),
+        )
+        .map_err(|e| e.to_string())?;
+      report.migrated += 1;
+      if duplicate {
+        report.duplicates += 1;
+        report.bytes_reclaimed += size;
+      }
+      Ok(())
+    })();
+    if let Err(e) = result {
+      report.failed.push(format!("{}: {}", file, e));
+    }
+  }
+
+  // The triggers keep counts from here on; recount once in case of history
+  conn
+    .execute(
+      "UPDATE blobs SET ref_count = (SELECT COUNT(*) FROM attachments a WHERE a.stored_path = blobs.path)",
+      [],
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(report)
+}
+
+/// Move attachments stored before the content-addressed layout into it,
+/// merging identical files.
+#[tauri::command]
+pub async fn migrate_attachment_storage(app: tauri::AppHandle) -> Result<StorageMigration, String> {
+  let _timer = usage_metrics::time_async("migrate_attachment_storage");
+  tokio::task::spawn_blocking(move || {
+    let state = app.state::<DbState>();
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let report = migrate(&conn, &state.data_dir())?;
+    tracing::info!(
+      migrated = report.migrated,
+      duplicates = report.duplicates,
+      bytes_reclaimed = report.bytes_reclaimed,
+      failed = report.failed.len(),
+      "Attachment storage migrated"
+    );
+    Ok(report)
+  })
+  .await
+  .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,111 @@
+// Writing captured content back to the system clipboard.
+//
+// The clipboard handle is kept for the life of the app: on X11 the contents
+// are served by whoever set them, so dropping the handle right after a copy
+// would empty the clipboard again.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::{app_lock, archive, event_from_row, event_icon, i18n, query_attachments, Attachment, DbState, TimelineEvent};
+
+#[derive(Default)]
+pub struct ClipboardState {
+  clipboard: Mutex<Option<arboard::Clipboard>>,
+}
+
+impl ClipboardState {
+  fn with<T>(&self, f: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>) -> Result<T, String> {
+    let mut slot = self.clipboard.lock().map_err(|_| "clipboard lock".to_string())?;
+    if slot.is_none() {
+      *slot = Some(arboard::Clipboard::new().map_err(|e| format!("Clipboard unavailable: {}", e))?);
+    }
+    let clipboard = slot.as_mut().expect("clipboard was just created");
+    f(clipboard).map_err(|e| format!("Clipboard write failed: {}", e))
+  }
+}
+
+fn attachment_path(attachment: &Attachment) -> PathBuf {
+  PathBuf::from(attachment.stored_path.as_ref().unwrap_or(&attachment.original_path))
+}
+
+/// The event as Markdown, laid out like an entry in the daily export.
+fn event_markdown(event: &TimelineEvent, attachments: &[Attachment]) -> String {
+  let mut out = format!(
+    "## {} {}\n\n",
+    event_icon(&event.event_type),
+    event.title.as_deref().unwrap_or("Untitled")
+  );
+  if let Some(note) = event.note.as_deref().filter(|n| !n.is_empty()) {
+    out.push_str(&format!("{}\n\n", note));
+  }
+  if let Some(text) = event.text_content.as_deref().filter(|t| !t.is_empty()) {
+    out.push_str(&format!("```\n{}\n```\n\n", text));
+  }
+  for attachment in attachments {
+    let name = attachment.file_name.as_deref().unwrap_or("unknown");
+    let path = attachment_path(attachment).to_string_lossy().replace(' ', "%20");
+    if attachment.kind == "image" {
+      out.push_str(&format!("![{}]({})\n", name, path));
+    } else {
+      out.push_str(&format!("- 📎 [{}]({})\n", name, path));
+    }
+  }
+  out.trim_end().to_string()
+}
+
+/// Put part of an event on the clipboard. `what` is "text" (note and text
+/// content), "markdown" (the whole entry) or "file" (the attachments, as
+/// files the user can paste into a folder or another app).
+#[tauri::command]
+pub fn copy_event_to_clipboard(
+  state: tauri::State<DbState>,
+  clipboard: tauri::State<ClipboardState>,
+  event_id: String,
+  what: String,
+) -> Result<(), String> {
+  app_lock::ensure_unlocked()?;
+  let (event, attachments) = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let event: TimelineEvent = conn
+      .query_row(
+        &format!(
+          "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
+           FROM {} WHERE id = ? AND is_deleted = 0",
+          archive::events_table(true)
+        ),
+        [&event_id],
+        event_from_row,
+      )
+      .map_err(|_| i18n::t("Event not found"))?;
+    (event, query_attachments(&conn, &event_id)?)
+  };
+
+  match what.as_str() {
+    "text" => {
+      let text = [event.note.as_deref(), event.text_content.as_deref()]
+        .into_iter()
+        .flatten()
+        .filter(|s| !s.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+      if text.is_empty() {
+        return Err("Event has no text".to_string());
+      }
+      clipboard.with(|c| c.set_text(text))
+    }
+    "markdown" => {
+      let markdown = event_markdown(&event, &attachments);
+      clipboard.with(|c| c.set_text(markdown))
+    }
+    "file" => {
+      let paths: Vec<PathBuf> = attachments.iter().map(attachment_path).filter(|p| p.is_file()).collect();
+      if paths.is_empty() {
+        return Err("Event has no attachment files".to_string());
+      }
+      clipboard.with(|c| c.set().file_list(&paths))
+    }
+    _ => Err(format!("Unknown clipboard content: {}", what)),
+  }
+}
@@ -0,0 +1,203 @@
+// Compression of large text content.
+//
+// A pasted multi-megabyte log or document would otherwise sit in the
+// database as is. Text above the threshold in `storage.compress_text_bytes`
+// (64 KiB unless changed, 0 turns compression off) moves into
+// `text_compressed`, with its codec in `text_codec`, and `text_content`
+// keeps the first `PREVIEW_CHARS` characters for list previews. Queries
+// that match on the text go through `FULL_TEXT_SQL`, which inflates
+// compressed rows with the `inflate_text` SQL function `register` adds to
+// every connection, so keyword and hashtag search see the whole text.
+// `event_from_row` inflates the full text whenever the query selects the two
+// columns, so code reading events gets the whole text back without knowing
+// it was compressed.
+//
+// New texts are written with zstd. Rows compressed before that keep their
+// "deflate" codec and are still read.
+
+use flate2::read::DeflateDecoder;
+use rusqlite::functions::FunctionFlags;
+use serde::Serialize;
+use std::io::Read;
+
+use crate::{config, read_setting, write_setting, DbState};
+
+pub const THRESHOLD_KEY: &str = "storage.compress_text_bytes";
+
+const DEFAULT_THRESHOLD: usize = 64 * 1024;
+const PREVIEW_CHARS: usize = 4_000;
+const CODEC: &str = "zstd";
+const LEGACY_CODEC: &str = "deflate";
+const ZSTD_LEVEL: i32 = 3;
+
+/// SQL for the full text of a `timeline_events` row. Only compressed rows
+/// pay for inflating.
+pub const FULL_TEXT_SQL: &str =
+  "(CASE WHEN text_codec IS NULL THEN text_content ELSE inflate_text(text_content, text_compressed, text_codec) END)";
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TextCompression {
+  /// Texts longer than this many bytes are compressed; 0 means never
+  threshold_bytes: usize,
+  compressed_events: i64,
+  /// Space the compressed texts take
+  compressed_bytes: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressionReport {
+  compressed: usize,
+  bytes_saved: i64,
+}
+
+fn threshold(conn: &rusqlite::Connection) -> usize {
+  read_setting(conn, THRESHOLD_KEY)
+    .and_then(|value| value.parse().ok())
+    .unwrap_or(DEFAULT_THRESHOLD)
+}
+
+/// The full text of an event: `text_content`, or what was compressed out of
+/// it. Falls back to the preview if the payload can't be read.
+pub fn inflate(text_content: Option<String>, compressed: Option<Vec<u8>>, codec: Option<&str>) -> Option<String> {
+  let (Some(compressed), Some(codec)) = (compressed, codec) else { return text_content };
+  let mut text = String::new();
+  let result = match codec {
+    CODEC => zstd::decode_all(compressed.as_slice())
+      .map_err(|e| e.to_string())
+      .and_then(|bytes| String::from_utf8(bytes).map_err(|e| e.to_string()))
+      .map(|decoded| text = decoded),
+    LEGACY_CODEC => DeflateDecoder::new(compressed.as_slice())
+      .read_to_string(&mut text)
+      .map(|_| ())
+      .map_err(|e| e.to_string()),
+    other => Err(format!("unknown codec {}", other)),
+  };
+  match result {
+    Ok(_) => Some(text),
+    Err(e) => {
+      tracing::warn!(error = %e, "Compressed text unreadable, using the preview");
+      text_content
+    }
+  }
+}
+
+/// Add `inflate_text(text_content, text_compressed, text_codec)` to `conn`,
+/// the SQL side of `inflate`.
+pub fn register(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+  conn.create_scalar_function(
+    "inflate_text",
+    3,
+    FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+    |ctx| {
+      let text_content = ctx.get::<Option<String>>(0)?;
+      let compressed = ctx.get::<Option<Vec<u8>>>(1)?;
+      let codec = ctx.get::<Option<String>>(2)?;
+      Ok(inflate(text_content, compressed, codec.as_deref()))
+    },
+  )
+}
+
+fn preview(text: &str) -> String {
+  match text.char_indices().nth(PREVIEW_CHARS) {
+    Some((end, _)) => format!("{}…", &text[..end]),
+    None => text.to_string(),
+  }
+}
+
+/// Compress the text of `event_id` if it's over the threshold. Returns the
+/// bytes saved, `None` when it was left alone.
+pub fn compress_if_large(conn: &rusqlite::Connection, event_id: &str) -> Result<Option<i64>, String> {
+  let threshold = threshold(conn);
+  if threshold == 0 {
+    return Ok(None);
+  }
+  let text: Option<String> = conn
+    .query_row(
+      "SELECT text_content FROM timeline_events WHERE id = ? AND text_codec IS NULL AND locked = 0",
+      [event_id],
+      |row| row.get(0),
+    )
+    .unwrap_or(None);
+  let Some(text) = text.filter(|t| t.len() > threshold) else { return Ok(None) };
+
+  let compressed = zstd::encode_all(text.as_bytes(), ZSTD_LEVEL).map_err(|e| e.to_string())?;
+  let preview = preview(&text);
+  let saved = text.len() as i64 - (compressed.len() + preview.len()) as i64;
+  if saved <= 0 {
+    return Ok(None);
+  }
+  conn
+    .execute(
+      "UPDATE timeline_events SET text_content = ?1, text_compressed = ?2, text_codec = ?3 WHERE id = ?4",
+      (&preview, &compressed, CODEC, event_id),
+    )
+    .map_err(|e| e.to_string())?;
+  tracing::debug!(%event_id, bytes = text.len(), saved, "Compressed event text");
+  Ok(Some(saved))
+}
+
+fn status(conn: &rusqlite::Connection) -> Result<TextCompression, String> {
+  let (compressed_events, compressed_bytes) = conn
+    .query_row(
+      "SELECT COUNT(*), COALESCE(SUM(LENGTH(text_compressed)), 0) FROM timeline_events WHERE text_codec IS NOT NULL",
+      [],
+      |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(TextCompression { threshold_bytes: threshold(conn), compressed_events, compressed_bytes })
+}
+
+#[tauri::command]
+pub fn get_text_compression(state: tauri::State<DbState>) -> Result<TextCompression, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  status(&conn)
+}
+
+/// Set the size above which new texts are compressed; 0 turns it off. Texts
+/// already stored are left as they are, see `compress_large_texts`.
+#[tauri::command]
+pub fn set_text_compression(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  threshold_bytes: usize,
+) -> Result<TextCompression, String> {
+  let status = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, THRESHOLD_KEY, &threshold_bytes.to_string())?;
+    status(&conn)?
+  };
+  config::emit_changed(&app, vec![THRESHOLD_KEY.to_string()]);
+  Ok(status)
+}
+
+/// Compress every stored text over the threshold.
+#[tauri::command]
+pub fn compress_large_texts(state: tauri::State<DbState>) -> Result<CompressionReport, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let threshold = threshold(&conn);
+  if threshold == 0 {
+    return Err("Text compression is turned off".to_string());
+  }
+  let ids: Vec<String> = conn
+    .prepare("SELECT id FROM timeline_events WHERE text_codec IS NULL AND LENGTH(CAST(text_content AS BLOB)) > ?")
+    .map_err(|e| e.to_string())?
+    .query_map([threshold as i64], |row| row.get(0))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let mut report = CompressionReport { compressed: 0, bytes_saved: 0 };
+  for id in ids {
+    if let Some(saved) = compress_if_large(&conn, &id)? {
+      report.compressed += 1;
+      report.bytes_saved += saved;
+    }
+  }
+  tracing::info!(compressed = report.compressed, bytes_saved = report.bytes_saved, "Compressed stored texts");
+  Ok(report)
+}
@@ -0,0 +1,272 @@
+// Typed view over the `settings` table.
+//
+// Each field of `AppConfig` maps to one existing setting key, so modules that
+// read their keys directly keep working. `update_config` takes a partial JSON
+// object, checks types, enums and ranges before anything is written, and
+// emits `settings-changed` with the keys that changed so running loops can
+// pick up new values without a restart.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{Emitter, Listener};
+
+use crate::behavior::{self, BehaviorConfig};
+use crate::ingest::{self, IngestionPolicy};
+use crate::quiet_hours::{self, QuietHours};
+use crate::{
+  app_windows, i18n, logging, permissions, pet_window, photo_meta, read_setting, redaction, reminder_scan, timezone,
+  tts, write_setting, DbState,
+};
+
+pub const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WindowSettings {
+  click_through: bool,
+  always_on_top: bool,
+  all_workspaces: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ReminderSettings {
+  /// Open a popup window for due reminders
+  popup_window: bool,
+  /// Read due reminders aloud
+  speak: bool,
+  quiet_hours: QuietHours,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IngestSettings {
+  backdate_photos: bool,
+  policy: IngestionPolicy,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AppConfig {
+  behavior: BehaviorConfig,
+  window: WindowSettings,
+  reminders: ReminderSettings,
+  ingest: IngestSettings,
+}
+
+impl Default for AppConfig {
+  fn default() -> Self {
+    Self {
+      behavior: BehaviorConfig::default(),
+      window: WindowSettings {
+        click_through: false,
+        always_on_top: true,
+        all_workspaces: false,
+      },
+      reminders: ReminderSettings {
+        popup_window: false,
+        speak: false,
+        quiet_hours: QuietHours::default(),
+      },
+      ingest: IngestSettings {
+        backdate_photos: false,
+        policy: IngestionPolicy::default(),
+      },
+    }
+  }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsChanged {
+  pub keys: Vec<String>,
+}
+
+/// (section, field, setting key) for every config value.
+const FIELDS: &[(&str, &str, &str)] = &[
+  ("behavior", "sampleMs", behavior::SAMPLE_MS_KEY),
+  ("behavior", "windowS", behavior::WINDOW_S_KEY),
+  ("behavior", "typingWeight", behavior::TYPING_WEIGHT_KEY),
+  ("behavior", "mouseWeight", behavior::MOUSE_WEIGHT_KEY),
+  ("behavior", "clickWeight", behavior::CLICK_WEIGHT_KEY),
+  ("behavior", "mouseSpeedCap", behavior::MOUSE_SPEED_CAP_KEY),
+  ("behavior", "clickRateCap", behavior::CLICK_RATE_CAP_KEY),
+  ("behavior", "idleMinutes", behavior::IDLE_MINUTES_KEY),
+  ("behavior", "trackApps", behavior::TRACK_APPS_KEY),
+  ("window", "clickThrough", pet_window::CLICK_THROUGH_KEY),
+  ("window", "alwaysOnTop", pet_window::ALWAYS_ON_TOP_KEY),
+  ("window", "allWorkspaces", pet_window::ALL_WORKSPACES_KEY),
+  ("reminders", "popupWindow", app_windows::REMINDER_POPUP_KEY),
+  ("reminders", "speak", tts::SPEAK_REMINDERS_KEY),
+  ("reminders", "quietHours", quiet_hours::QUIET_HOURS_KEY),
+  ("ingest", "backdatePhotos", photo_meta::BACKDATE_PHOTOS_KEY),
+  ("ingest", "policy", ingest::INGESTION_POLICY_KEY),
+];
+
+fn same_type(a: &Value, b: &Value) -> bool {
+  std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+fn validate(config: AppConfig) -> Result<AppConfig, String> {
+  let b = &config.behavior;
+  if !(16..=1000).contains(&b.sample_ms) {
+    return Err("behavior.sampleMs must be between 16 and 1000".to_string());
+  }
+  if b.window_s < 0.5 {
+    return Err("behavior.windowS must be at least 0.5".to_string());
+  }
+  for (name, weight) in [
+    ("typingWeight", b.typing_weight),
+    ("mouseWeight", b.mouse_weight),
+    ("clickWeight", b.click_weight),
+  ] {
+    if !(0.0..=1.0).contains(&weight) {
+      return Err(format!("behavior.{} must be between 0 and 1", name));
+    }
+  }
+  for (name, value) in [
+    ("mouseSpeedCap", b.mouse_speed_cap),
+    ("clickRateCap", b.click_rate_cap),
+    ("idleMinutes", b.idle_minutes),
+  ] {
+    if value <= 0.0 {
+      return Err(format!("behavior.{} must be positive", name));
+    }
+  }
+
+  let policy = ingest::validate(config.ingest.policy.clone())?;
+  let quiet_hours = quiet_hours::validate(config.reminders.quiet_hours.clone())?;
+  Ok(AppConfig {
+    ingest: IngestSettings { policy, ..config.ingest },
+    reminders: ReminderSettings { quiet_hours, ..config.reminders },
+    ..config
+  })
+}
+
+/// Current config: stored values over defaults. Values that don't parse as
+/// the field's type are ignored.
+pub fn load_config(conn: &rusqlite::Connection) -> AppConfig {
+  let mut value = serde_json::to_value(AppConfig::default()).unwrap_or(Value::Null);
+  for (section, field, key) in FIELDS {
+    let Some(stored) = read_setting(conn, key) else { continue };
+    let Ok(parsed) = serde_json::from_str::<Value>(stored.trim()) else { continue };
+    if let Some(slot) = value.get_mut(*section).and_then(|s| s.get_mut(*field)) {
+      if same_type(slot, &parsed) {
+        *slot = parsed;
+      }
+    }
+  }
+  serde_json::from_value(value)
+    .map_err(|e| tracing::warn!(error = %e, "Invalid stored config"))
+    .unwrap_or_default()
+}
+
+/// Recursively apply a JSON merge patch; objects merge, anything else
+/// replaces.
+fn merge(target: &mut Value, patch: Value) {
+  match (target, patch) {
+    (Value::Object(target), Value::Object(patch)) => {
+      for (k, v) in patch {
+        match target.get_mut(&k) {
+          Some(slot) if slot.is_object() && v.is_object() => merge(slot, v),
+          _ => {
+            target.insert(k, v);
+          }
+        }
+      }
+    }
+    (target, patch) => *target = patch,
+  }
+}
+
+/// Tell the UI and background loops which setting keys changed.
+pub fn emit_changed(app: &tauri::AppHandle, keys: Vec<String>) {
+  if !keys.is_empty() {
+    let _ = app.emit(SETTINGS_CHANGED_EVENT, SettingsChanged { keys });
+  }
+}
+
+/// Apply changed settings to the running subsystems.
+pub fn watch_settings(app: &tauri::AppHandle) {
+  let handle = app.clone();
+  app.listen_any(SETTINGS_CHANGED_EVENT, move |event| {
+    let Ok(changed) = serde_json::from_str::<Value>(event.payload()) else { return };
+    let keys: Vec<&str> = changed["keys"]
+      .as_array()
+      .map(|keys| keys.iter().filter_map(|k| k.as_str()).collect())
+      .unwrap_or_default();
+
+    if keys.iter().any(|k| k.starts_with("behavior.")) {
+      let _ = behavior::reload_behavior_config(handle.clone());
+    }
+    if keys.iter().any(|k| {
+      [pet_window::CLICK_THROUGH_KEY, pet_window::ALWAYS_ON_TOP_KEY, pet_window::ALL_WORKSPACES_KEY].contains(k)
+    }) {
+      pet_window::reload_window_modes(&handle);
+    }
+    if keys.contains(&logging::LOG_LEVEL_KEY) {
+      logging::reload_level(&handle);
+    }
+    if keys.contains(&redaction::REDACTION_KEY) {
+      redaction::reload(&handle);
+    }
+    if keys.contains(&quiet_hours::QUIET_HOURS_KEY) {
+      reminder_scan::wake(&handle);
+    }
+    if keys.contains(&timezone::TIMEZONE_KEY) {
+      timezone::reload(&handle);
+    }
+    if keys.contains(&i18n::LOCALE_KEY) {
+      i18n::reload(&handle);
+    }
+    if keys.contains(&permissions::PERMISSIONS_KEY) {
+      permissions::reload(&handle);
+    }
+  });
+}
+
+#[tauri::command]
+pub fn get_config(state: tauri::State<DbState>) -> Result<AppConfig, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_config(&conn))
+}
+
+/// Merge `patch` (e.g. `{"behavior": {"idleMinutes": 10}}`) into the current
+/// config. Nothing is written unless the whole result validates.
+#[tauri::command]
+pub fn update_config(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  patch: Value,
+) -> Result<AppConfig, String> {
+  if !patch.is_object() {
+    return Err("Config patch must be an object".to_string());
+  }
+
+  let (config, keys) = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+
+    let current = serde_json::to_value(load_config(&conn)).map_err(|e| e.to_string())?;
+    let mut merged = current.clone();
+    merge(&mut merged, patch);
+    let config: AppConfig =
+      serde_json::from_value(merged).map_err(|e| format!("Invalid config: {}", e))?;
+    let config = validate(config)?;
+    let updated = serde_json::to_value(&config).map_err(|e| e.to_string())?;
+
+    let mut changed = Vec::new();
+    for (section, field, key) in FIELDS {
+      let new = &updated[*section][*field];
+      if *new != current[*section][*field] {
+        write_setting(&conn, key, &new.to_string())?;
+        changed.push(key.to_string());
+      }
+    }
+    (config, changed)
+  };
+
+  emit_changed(&app, keys);
+  Ok(config)
+}
@@ -0,0 +1,137 @@
+// Whether the internet is reachable.
+//
+// A supervised probe opens a TCP connection to a couple of public resolvers
+// every 30 seconds (every 10 while offline); when none answers the pet counts
+// as offline. Each change is announced to the webview as
+// `online-status-changed`. Nothing is probed while network access is
+// switched off (see permissions.rs).
+//
+// The probe is only a hint: a firewall may well block the resolvers and
+// still let the actual endpoints through. Background fetchers skip their
+// rounds while it reports offline and are woken to catch up as soon as it
+// reports the connection back. Requests the user asked for still go out, but
+// clients from `client_builder` give up connecting after a few seconds while
+// offline, so they fail fast with `OFFLINE_ERROR` rather than waiting out
+// the system's connect timeout. Work that can wait (Notion exports, journal
+// pushes) runs as jobs: one that fails with `OFFLINE_ERROR` is held in the
+// queue and released once the probe sees the connection again, without
+// having used up an attempt.
+
+use serde::Serialize;
+use std::net::{SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::sync::Notify;
+
+use crate::permissions::{self, Permission};
+use crate::{jobs, now_ms, supervisor};
+
+pub const OFFLINE_ERROR: &str = "Offline";
+pub const STATUS_EVENT: &str = "online-status-changed";
+
+const ONLINE_INTERVAL: Duration = Duration::from_secs(30);
+const OFFLINE_INTERVAL: Duration = Duration::from_secs(10);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+// Cloudflare and Google DNS; a raw address needs no lookup to try
+const PROBE_ADDRS: &[&str] = &["1.1.1.1:443", "8.8.8.8:443"];
+
+static ONLINE: AtomicBool = AtomicBool::new(true);
+static LAST_CHECK: RwLock<Option<i64>> = RwLock::new(None);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OnlineStatus {
+  online: bool,
+  /// Whether the probe runs at all; false while network access is off
+  checking: bool,
+  checked_at: Option<i64>,
+}
+
+fn reconnected() -> &'static Notify {
+  static NOTIFY: OnceLock<Notify> = OnceLock::new();
+  NOTIFY.get_or_init(Notify::new)
+}
+
+pub fn is_online() -> bool {
+  ONLINE.load(Ordering::SeqCst)
+}
+
+/// Whether the internet may and can be used right now, for background work
+/// that should quietly skip a round otherwise.
+pub fn reachable() -> bool {
+  permissions::allowed(Permission::Network) && is_online()
+}
+
+/// HTTP client builder for requests that should fail fast while offline. Set
+/// a request timeout on top as the call needs.
+pub fn client_builder() -> reqwest::ClientBuilder {
+  let builder = reqwest::Client::builder();
+  match is_online() {
+    true => builder,
+    false => builder.connect_timeout(PROBE_TIMEOUT),
+  }
+}
+
+/// Message for a failed request: `OFFLINE_ERROR` when the host couldn't be
+/// reached or didn't answer in time, otherwise `context` and the cause.
+pub fn request_error(context: &str, e: reqwest::Error) -> String {
+  match e.is_connect() || e.is_timeout() {
+    true => OFFLINE_ERROR.to_string(),
+    false => format!("{}: {}", context, e),
+  }
+}
+
+/// Sleep for `duration`, or less if the connection comes back meanwhile.
+/// Returns true in that case.
+pub async fn sleep(duration: Duration) -> bool {
+  tokio::time::timeout(duration, reconnected().notified()).await.is_ok()
+}
+
+fn probe() -> bool {
+  PROBE_ADDRS
+    .iter()
+    .filter_map(|addr| addr.parse::<SocketAddr>().ok())
+    .any(|addr| TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok())
+}
+
+fn status() -> OnlineStatus {
+  OnlineStatus {
+    online: is_online(),
+    checking: permissions::allowed(Permission::Network),
+    checked_at: LAST_CHECK.read().ok().and_then(|at| *at),
+  }
+}
+
+fn record(app: &tauri::AppHandle, online: bool) {
+  if let Ok(mut at) = LAST_CHECK.write() {
+    *at = Some(now_ms());
+  }
+  if ONLINE.swap(online, Ordering::SeqCst) == online {
+    return;
+  }
+  tracing::info!(online, "Connectivity changed");
+  let _ = app.emit(STATUS_EVENT, status());
+  if online {
+    reconnected().notify_waiters();
+    jobs::release_offline(app);
+  }
+}
+
+pub fn spawn_connectivity_probe(app: tauri::AppHandle) {
+  supervisor::supervise(app, "connectivity", |app| async move {
+    loop {
+      if permissions::allowed(Permission::Network) {
+        let online = tokio::task::spawn_blocking(probe).await.unwrap_or(true);
+        record(&app, online);
+      }
+      tokio::time::sleep(if is_online() { ONLINE_INTERVAL } else { OFFLINE_INTERVAL }).await;
+    }
+  });
+}
+
+#[tauri::command]
+pub fn get_online_status() -> Result<OnlineStatus, String> {
+  Ok(status())
+}
@@ -0,0 +1,338 @@
+// A suggested plan for the day.
+//
+// `generate_day_plan` lays the open reminders (overdue, snoozed or due that
+// day) and plans dated that day into time blocks between 9:00 and 18:00.
+// Meetings from subscribed calendars and plans keep their times; one focus
+// block is reserved first, as long as a typical focus session of the last
+// four weeks and at the hour they most often happened, and the reminders
+// then fill the earliest free slots, the most overdue first. Whatever
+// doesn't fit comes back as `unscheduled`.
+//
+// Nothing is scheduled or moved: the plan is a suggestion for the pet to
+// present in the morning. With `write_event` it's also kept as a text event
+// on the timeline, one per day, rewritten when the plan is generated again.
+
+use chrono::{NaiveDate, NaiveTime, Timelike};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{calendar, compression, generate_id, i18n, now_ms, tags, timezone, DbState};
+
+const DAY_START_HOUR: u32 = 9;
+const DAY_END_HOUR: u32 = 18;
+const TASK_MINUTES: i64 = 15;
+const PLAN_MINUTES: i64 = 30;
+const FOCUS_DEFAULT_MINUTES: i64 = 50;
+const FOCUS_MIN_MINUTES: i64 = 25;
+const FOCUS_MAX_MINUTES: i64 = 120;
+const FOCUS_HISTORY_DAYS: i64 = 28;
+// Reminders that fired but were never dismissed stay on the list this long
+const TRIGGERED_MAX_AGE_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+// Block starts are rounded up to this many minutes
+const SLOT_MINUTES: i64 = 5;
+const MINUTE_MS: i64 = 60 * 1000;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanBlock {
+  /// "meeting", "plan", "focus" or "task"
+  kind: String,
+  title: String,
+  starts_at: i64,
+  ends_at: i64,
+  /// The reminder a task block is for
+  reminder_id: Option<String>,
+  /// The event a task or plan block is for
+  event_id: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanTask {
+  reminder_id: String,
+  event_id: String,
+  title: String,
+  due_at: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DayPlan {
+  date_key: String,
+  starts_at: i64,
+  ends_at: i64,
+  blocks: Vec<PlanBlock>,
+  /// Tasks there was no room for
+  unscheduled: Vec<PlanTask>,
+  /// The timeline entry holding the plan, when written
+  event_id: Option<String>,
+}
+
+fn round_up(ms: i64) -> i64 {
+  let slot = SLOT_MINUTES * MINUTE_MS;
+  (ms + slot - 1).div_euclid(slot) * slot
+}
+
+fn hour_on(date: NaiveDate, hour: u32) -> Result<i64, String> {
+  let time = NaiveTime::from_hms_opt(hour, 0, 0).ok_or("Invalid hour")?;
+  timezone::at(date, time).ok_or_else(|| "Invalid local time".to_string())
+}
+
+/// Open reminders due by `until`, the most overdue first.
+fn open_tasks(conn: &rusqlite::Connection, until: i64, now: i64) -> Result<Vec<PlanTask>, String> {
+  let tasks = conn
+    .prepare(
+      "SELECT r.id, r.event_id, r.message, CASE WHEN r.status = 'snoozed' THEN r.snooze_until ELSE r.remind_at END AS due
+       FROM reminders r JOIN timeline_events e ON e.id = r.event_id
+       WHERE e.is_deleted = 0
+         AND (r.status IN ('pending', 'snoozed') OR (r.status = 'triggered' AND r.triggered_at >= ?2))
+         AND due <= ?1
+       ORDER BY due ASC",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map((until, now - TRIGGERED_MAX_AGE_MS), |row| {
+      Ok(PlanTask { reminder_id: row.get(0)?, event_id: row.get(1)?, title: row.get(2)?, due_at: row.get(3)? })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(tasks)
+}
+
+/// Plans dated between `from` and `until`, as fixed blocks.
+fn plan_blocks(conn: &rusqlite::Connection, from: i64, until: i64) -> Result<Vec<PlanBlock>, String> {
+  let blocks = conn
+    .prepare(
+      "SELECT id, COALESCE(NULLIF(title, ''), note, ''), scheduled_for FROM timeline_events
+       WHERE scheduled_for IS NOT NULL AND scheduled_for >= ?1 AND scheduled_for < ?2 AND is_deleted = 0
+       ORDER BY scheduled_for ASC",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map((from, until), |row| {
+      let starts_at: i64 = row.get(2)?;
+      Ok(PlanBlock {
+        kind: "plan".to_string(),
+        title: row.get(1)?,
+        starts_at,
+        ends_at: starts_at + PLAN_MINUTES * MINUTE_MS,
+        reminder_id: None,
+        event_id: Some(row.get(0)?),
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(blocks)
+}
+
+/// Typical focus session length in minutes and the hour of day most focus
+/// time started in, from recent sessions.
+fn focus_habits(conn: &rusqlite::Connection, now: i64) -> Result<(i64, Option<u32>), String> {
+  let sessions: Vec<(i64, i64)> = conn
+    .prepare("SELECT started_at, ends_at FROM focus_sessions WHERE started_at >= ? AND ends_at > started_at")
+    .map_err(|e| e.to_string())?
+    .query_map([now - FOCUS_HISTORY_DAYS * 24 * 60 * MINUTE_MS], |row| Ok((row.get(0)?, row.get(1)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  if sessions.is_empty() {
+    return Ok((FOCUS_DEFAULT_MINUTES, None));
+  }
+  let mut lengths: Vec<i64> = sessions.iter().map(|(start, end)| (end - start) / MINUTE_MS).collect();
+  lengths.sort_unstable();
+  let length = lengths[lengths.len() / 2].clamp(FOCUS_MIN_MINUTES, FOCUS_MAX_MINUTES);
+
+  let mut minutes_by_hour = [0i64; 24];
+  for (start, end) in &sessions {
+    if let Some(t) = timezone::datetime(*start) {
+      minutes_by_hour[t.hour() as usize] += (end - start) / MINUTE_MS;
+    }
+  }
+  let hour = (0..24u32).max_by_key(|h| minutes_by_hour[*h as usize]).filter(|h| minutes_by_hour[*h as usize] > 0);
+  Ok((length, hour))
+}
+
+/// `window` minus the busy blocks, as sorted gaps.
+fn free_gaps(window: (i64, i64), blocks: &[PlanBlock]) -> Vec<(i64, i64)> {
+  let mut busy: Vec<(i64, i64)> = blocks.iter().map(|b| (b.starts_at, b.ends_at)).collect();
+  busy.sort_unstable();
+  let mut gaps = Vec::new();
+  let mut cursor = window.0;
+  for (start, end) in busy {
+    if start > cursor {
+      gaps.push((cursor, start.min(window.1)));
+    }
+    cursor = cursor.max(end);
+    if cursor >= window.1 {
+      break;
+    }
+  }
+  if cursor < window.1 {
+    gaps.push((cursor, window.1));
+  }
+  gaps
+    .into_iter()
+    .map(|(start, end)| (round_up(start), end))
+    .filter(|(start, end)| end > start)
+    .collect()
+}
+
+/// Start of a `length` ms slot in `gaps`, as close to `target` as possible,
+/// or in the longest gap without one.
+fn focus_start(gaps: &[(i64, i64)], length: i64, target: Option<i64>) -> Option<i64> {
+  let fitting = gaps.iter().filter(|(start, end)| end - start >= length);
+  match target {
+    Some(target) => fitting
+      .map(|(start, end)| round_up(target.clamp(*start, end - length)).min(end - length))
+      .min_by_key(|start| (start - target).abs()),
+    None => fitting.max_by_key(|(start, end)| end - start).map(|(start, _)| *start),
+  }
+}
+
+fn build(conn: &rusqlite::Connection, date: NaiveDate, now: i64) -> Result<DayPlan, String> {
+  let day_start = hour_on(date, DAY_START_HOUR)?;
+  let day_end = hour_on(date, DAY_END_HOUR)?;
+  let window = (round_up(day_start.max(now)), day_end);
+
+  let mut blocks: Vec<PlanBlock> = calendar::busy_between(conn, day_start, day_end)?
+    .into_iter()
+    .map(|(title, starts_at, ends_at)| PlanBlock {
+      kind: "meeting".to_string(),
+      title,
+      starts_at: starts_at.max(day_start),
+      ends_at: ends_at.min(day_end),
+      reminder_id: None,
+      event_id: None,
+    })
+    .collect();
+  blocks.extend(plan_blocks(conn, day_start, day_end)?);
+
+  let (focus_minutes, focus_hour) = focus_habits(conn, now)?;
+  let focus_length = focus_minutes * MINUTE_MS;
+  let target = match focus_hour {
+    Some(hour) => Some(hour_on(date, hour)?),
+    None => None,
+  };
+  if let Some(start) = focus_start(&free_gaps(window, &blocks), focus_length, target) {
+    blocks.push(PlanBlock {
+      kind: "focus".to_string(),
+      title: i18n::t("Focus time"),
+      starts_at: start,
+      ends_at: start + focus_length,
+      reminder_id: None,
+      event_id: None,
+    });
+  }
+
+  let mut gaps = free_gaps(window, &blocks);
+  let task_length = TASK_MINUTES * MINUTE_MS;
+  let mut unscheduled = Vec::new();
+  for task in open_tasks(conn, day_end, now)? {
+    match gaps.iter_mut().find(|(start, end)| *end - *start >= task_length) {
+      Some(gap) => {
+        blocks.push(PlanBlock {
+          kind: "task".to_string(),
+          title: task.title,
+          starts_at: gap.0,
+          ends_at: gap.0 + task_length,
+          reminder_id: Some(task.reminder_id),
+          event_id: Some(task.event_id),
+        });
+        gap.0 += task_length;
+      }
+      None => unscheduled.push(task),
+    }
+  }
+  blocks.sort_by_key(|b| b.starts_at);
+
+  Ok(DayPlan {
+    date_key: date.format("%Y-%m-%d").to_string(),
+    starts_at: window.0,
+    ends_at: window.1,
+    blocks,
+    unscheduled,
+    event_id: None,
+  })
+}
+
+fn render(plan: &DayPlan) -> String {
+  let time = |ms: i64| timezone::datetime(ms).map(|t| t.format("%H:%M").to_string()).unwrap_or_default();
+  let mut text: Vec<String> = plan
+    .blocks
+    .iter()
+    .map(|b| format!("{} - {}  {}", time(b.starts_at), time(b.ends_at), b.title))
+    .collect();
+  if !plan.unscheduled.is_empty() {
+    text.push(String::new());
+    text.push(i18n::t("Not scheduled:"));
+    text.extend(plan.unscheduled.iter().map(|task| format!("- {}", task.title)));
+  }
+  text.join("\n")
+}
+
+/// Store the plan as the day's plan entry, replacing an earlier one unless
+/// it was deleted or locked since.
+fn store(conn: &rusqlite::Connection, plan: &DayPlan) -> Result<String, String> {
+  let title = i18n::tf("Plan for {date}", &[("date", plan.date_key.clone())]);
+  let text = render(plan);
+  let metadata = json!({ "dayPlan": { "date": plan.date_key } });
+  let existing: Option<String> = conn
+    .query_row(
+      "SELECT id FROM timeline_events
+       WHERE source = 'day_plan' AND json_extract(metadata, '$.dayPlan.date') = ? AND is_deleted = 0 AND locked = 0
+       ORDER BY created_at DESC LIMIT 1",
+      [&plan.date_key],
+      |row| row.get(0),
+    )
+    .ok();
+  let event_id = match existing {
+    Some(event_id) => {
+      conn
+        .execute(
+          "UPDATE timeline_events SET title = ?1, text_content = ?2, text_compressed = NULL, text_codec = NULL
+           WHERE id = ?3",
+          (&title, &text, &event_id),
+        )
+        .map_err(|e| e.to_string())?;
+      event_id
+    }
+    None => {
+      let event_id = generate_id();
+      conn
+        .execute(
+          "INSERT INTO timeline_events (id, type, title, note, text_content, created_at, source, is_deleted, metadata)
+           VALUES (?1, 'text', ?2, '', ?3, ?4, 'day_plan', 0, ?5)",
+          (&event_id, &title, &text, now_ms(), metadata.to_string()),
+        )
+        .map_err(|e| e.to_string())?;
+      tags::auto_tag(conn, &event_id);
+      event_id
+    }
+  };
+  compression::compress_if_large(conn, &event_id)?;
+  Ok(event_id)
+}
+
+/// Suggest time blocks for `date_key` (today when `None`). `write_event`
+/// also keeps the plan as a timeline entry.
+#[tauri::command]
+pub fn generate_day_plan(
+  state: tauri::State<DbState>,
+  date_key: Option<String>,
+  write_event: Option<bool>,
+) -> Result<DayPlan, String> {
+  let date = match date_key.as_deref() {
+    Some(key) => NaiveDate::parse_from_str(key, "%Y-%m-%d").map_err(|_| i18n::t("Invalid date format"))?,
+    None => timezone::today(),
+  };
+  if date < timezone::today() {
+    return Err("That day is already over".to_string());
+  }
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let mut plan = build(&conn, date, now_ms())?;
+  if write_event.unwrap_or(false) {
+    plan.event_id = Some(store(&conn, &plan)?);
+  }
+  Ok(plan)
+}
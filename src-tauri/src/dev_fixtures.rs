@@ -0,0 +1,156 @@
+// A throwaway journal for development.
+//
+// Started with `--dev-fixtures` (or `PAPA_DEV_FIXTURES=1` in the
+// environment, handier under `tauri dev`), the app opens a fresh database in
+// the temp folder instead of the active profile's and seeds it with a few
+// days of sample events, attachments and reminders. The webview can then be
+// worked on, and the backend poked at, without touching the real journal.
+//
+// The database is a file rather than `:memory:` because every command opens
+// its own connection by path, and the profile folder's files (the content
+// store, recordings, drops) live next to it. Each run gets its own folder,
+// named after the process, so the seed is always the same. Switching profiles
+// is refused while the fixtures are in use, since that would open a real
+// database again.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::{generate_id, ingest, insert_attachment, mentions, now_ms, open_db, tags};
+
+pub const FLAG: &str = "--dev-fixtures";
+pub const ENV_VAR: &str = "PAPA_DEV_FIXTURES";
+const DIR_PREFIX: &str = "papa-dev-fixtures-";
+const DB_FILE: &str = "papa_pet.sqlite";
+
+const HOUR_MS: i64 = 60 * 60 * 1000;
+const DAY_MS: i64 = 24 * HOUR_MS;
+
+/// Whether this run uses the fixture database. Read once; the flag can't
+/// change while the app runs.
+pub fn enabled() -> bool {
+  static ENABLED: OnceLock<bool> = OnceLock::new();
+  *ENABLED.get_or_init(|| {
+    std::env::args().any(|arg| arg == FLAG)
+      || std::env::var(ENV_VAR).is_ok_and(|value| !matches!(value.trim(), "" | "0" | "false"))
+  })
+}
+
+/// `Err` while the fixtures are in use, for commands that would leave them.
+pub fn ensure_disabled() -> Result<(), String> {
+  match enabled() {
+    true => Err("Not available while running on development fixtures".to_string()),
+    false => Ok(()),
+  }
+}
+
+/// Path of an empty fixture database for this run. Anything left from an
+/// earlier run with the same process id is removed first.
+pub fn prepare() -> Result<PathBuf, String> {
+  let dir = std::env::temp_dir().join(format!("{}{}", DIR_PREFIX, std::process::id()));
+  if dir.exists() {
+    fs::remove_dir_all(&dir).map_err(|e| format!("Clearing {} failed: {}", dir.display(), e))?;
+  }
+  fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+  Ok(dir.join(DB_FILE))
+}
+
+fn insert_event(
+  conn: &rusqlite::Connection,
+  event_type: &str,
+  title: Option<&str>,
+  note: &str,
+  text_content: Option<&str>,
+  created_at: i64,
+  source: &str,
+) -> Result<String, String> {
+  let event_id = generate_id();
+  conn
+    .execute(
+      "INSERT INTO timeline_events (id, type, title, note, text_content, created_at, source, is_deleted)
+       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0)",
+      (&event_id, event_type, title, note, text_content, created_at, source),
+    )
+    .map_err(|e| e.to_string())?;
+  tags::auto_tag(conn, &event_id);
+  tags::sync_hashtags(conn, &event_id)?;
+  mentions::index(conn, &event_id)?;
+  Ok(event_id)
+}
+
+fn insert_reminder(
+  conn: &rusqlite::Connection,
+  event_id: &str,
+  remind_at: i64,
+  message: &str,
+  status: &str,
+  attachment_id: Option<&str>,
+) -> Result<(), String> {
+  conn
+    .execute(
+      "INSERT INTO reminders (id, event_id, remind_at, message, status, created_at, attachment_id)
+       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+      (generate_id(), event_id, remind_at, message, status, now_ms(), attachment_id),
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Write a sample file into the profile folder's drops, from where
+/// `insert_attachment` moves it into the content store.
+fn sample_file(data_dir: &Path, name: &str, write: impl FnOnce(&Path) -> Result<(), String>) -> Result<String, String> {
+  let dir = data_dir.join("drops");
+  fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+  let path = dir.join(name);
+  write(&path)?;
+  Ok(path.to_string_lossy().to_string())
+}
+
+/// Fill the (already initialised) database at `db_path` with sample data
+/// spread over the last three days.
+pub fn seed(db_path: &Path) -> Result<(), String> {
+  let data_dir = db_path.parent().map(Path::to_path_buf).unwrap_or_default();
+  let mut conn = open_db(db_path).map_err(|e| e.to_string())?;
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  let policy = ingest::load_policy(&tx);
+  let now = now_ms();
+
+  insert_event(
+    &tx,
+    "text",
+    None,
+    "Sprint planning",
+    Some("Agreed on the export rework for this sprint. @sam takes the settings page. #work"),
+    now - 2 * DAY_MS,
+    "manual",
+  )?;
+  let coffee = "Try the new coffee place on the corner #personal";
+  insert_event(&tx, "thought", None, coffee, None, now - 2 * DAY_MS + 3 * HOUR_MS, "manual")?;
+
+  let photo_note = "Whiteboard after the design review";
+  let photo_event = insert_event(&tx, "image", Some("whiteboard.png"), photo_note, None, now - DAY_MS, "drop")?;
+  let photo = sample_file(&data_dir, "whiteboard.png", |path| {
+    image::RgbImage::from_fn(64, 48, |x, y| image::Rgb([(x * 4) as u8, (y * 5) as u8, 180]))
+      .save(path)
+      .map_err(|e| e.to_string())
+  })?;
+  insert_attachment(&tx, &policy, &data_dir, &photo_event, &photo, now - DAY_MS)?;
+
+  let list_note = "Articles to get through";
+  let list_event = insert_event(&tx, "file", Some("reading-list.txt"), list_note, None, now - 6 * HOUR_MS, "drop")?;
+  let list = sample_file(&data_dir, "reading-list.txt", |path| {
+    fs::write(path, "Local-first software\nThe log is the database\nOn writing well\n").map_err(|e| e.to_string())
+  })?;
+  let list_attachment = insert_attachment(&tx, &policy, &data_dir, &list_event, &list, now - 6 * HOUR_MS)?;
+
+  let todo_note = "Send the invoice to the client";
+  let todo_event = insert_event(&tx, "thought", None, todo_note, None, now - 3 * HOUR_MS, "manual")?;
+  insert_reminder(&tx, &todo_event, now - HOUR_MS, "Send the invoice", "pending", None)?;
+  insert_reminder(&tx, &list_event, now + HOUR_MS, "Read one article", "pending", Some(&list_attachment.id))?;
+  insert_reminder(&tx, &photo_event, now - DAY_MS + 2 * HOUR_MS, "Type up the whiteboard", "dismissed", None)?;
+
+  tx.commit().map_err(|e| e.to_string())?;
+  tracing::info!(path = %db_path.display(), "Seeded development fixtures");
+  Ok(())
+}
@@ -0,0 +1,302 @@
+// Downloads triage: the pet asks what to do with each new download.
+//
+// A watch folder preset for the Downloads folder. New files aren't captured
+// straight away; they're queued in `download_triage` and announced as
+// `triage-needed`, and the user answers with `resolve_triage`:
+// - "journal": capture the file as a drop event
+// - "remind": capture it with a reminder, Friday 9:00 unless a time is given
+// - "ignore": drop it from the queue and remember a name pattern for it in
+//   `downloads.ignored_patterns`, so the next version of the same installer
+//   isn't asked about again
+//
+// Patterns are globs matched case-insensitively against the file name; a
+// pattern made for a file replaces its version numbers with `*`, e.g.
+// `zoom_installer_5.16.2.dmg` becomes `zoom_installer_*.dmg`.
+
+use chrono::{Datelike, Duration as ChronoDuration, Local, TimeZone, Weekday};
+use regex::Regex;
+use serde::Serialize;
+use std::path::Path;
+use tauri::Manager;
+
+use crate::watch_folders::{self, WatchFolder, WatchRules};
+use crate::{config, generate_id, i18n, now_ms, read_setting, reminder_scan, write_setting, DbState, Reminder, TimelineEventWithAttachments};
+
+pub const PRESET: &str = "downloads";
+pub const IGNORED_PATTERNS_KEY: &str = "downloads.ignored_patterns";
+
+const REMIND_HOUR: u32 = 9;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TriageItem {
+  path: String,
+  file_name: String,
+  size_bytes: i64,
+  seen_at: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadTriage {
+  /// The Downloads folder, if it could be found
+  folder: Option<String>,
+  enabled: bool,
+  ignored_patterns: Vec<String>,
+}
+
+fn load_patterns(conn: &rusqlite::Connection) -> Vec<String> {
+  read_setting(conn, IGNORED_PATTERNS_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn save_patterns(conn: &rusqlite::Connection, patterns: &[String]) -> Result<(), String> {
+  let json = serde_json::to_string(patterns).map_err(|e| e.to_string())?;
+  write_setting(conn, IGNORED_PATTERNS_KEY, &json)
+}
+
+fn glob_matches(pattern: &str, name: &str) -> bool {
+  let regex = format!("(?i)^{}$", regex::escape(pattern).replace(r"\*", ".*").replace(r"\?", "."));
+  Regex::new(&regex).is_ok_and(|re| re.is_match(name))
+}
+
+/// Pattern for files like `file_name`: version numbers become `*`.
+fn pattern_for(file_name: &str) -> String {
+  let (stem, extension) = match file_name.rsplit_once('.') {
+    Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+    _ => (file_name, None),
+  };
+  let versions = Regex::new(r"\d+([._-]\d+)*").expect("valid regex");
+  let stem = versions.replace_all(stem, "*");
+  match extension {
+    Some(ext) => format!("{}.{}", stem, ext),
+    None => stem.to_string(),
+  }
+}
+
+/// Next Friday at `REMIND_HOUR`, today if it's Friday morning.
+fn next_friday() -> Result<i64, String> {
+  let now = Local::now();
+  let mut days = (7 + Weekday::Fri.num_days_from_monday() - now.weekday().num_days_from_monday()) % 7;
+  if days == 0 && now.time() >= chrono::NaiveTime::from_hms_opt(REMIND_HOUR, 0, 0).unwrap_or_default() {
+    days = 7;
+  }
+  let date = now.date_naive() + ChronoDuration::days(days as i64);
+  date
+    .and_hms_opt(REMIND_HOUR, 0, 0)
+    .and_then(|dt| Local.from_local_datetime(&dt).earliest())
+    .map(|dt| dt.timestamp_millis())
+    .ok_or_else(|| "Invalid local time".to_string())
+}
+
+/// Queue a settled download for triage. `None` when it matches an ignored
+/// pattern or is already queued or decided.
+pub fn queue(conn: &rusqlite::Connection, path: &Path, size: u64) -> Result<Option<TriageItem>, String> {
+  let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+  if load_patterns(conn).iter().any(|pattern| glob_matches(pattern, &file_name)) {
+    return Ok(None);
+  }
+  let item = TriageItem {
+    path: path.to_string_lossy().to_string(),
+    file_name,
+    size_bytes: size as i64,
+    seen_at: now_ms(),
+  };
+  let inserted = conn
+    .execute(
+      "INSERT OR IGNORE INTO download_triage (path, file_name, size_bytes, seen_at) VALUES (?1, ?2, ?3, ?4)",
+      (&item.path, &item.file_name, item.size_bytes, item.seen_at),
+    )
+    .map_err(|e| e.to_string())?;
+  Ok((inserted > 0).then_some(item))
+}
+
+fn status(app: &tauri::AppHandle, conn: &rusqlite::Connection) -> DownloadTriage {
+  let preset = watch_folders::load_folders(conn).into_iter().find(|f| f.preset.as_deref() == Some(PRESET));
+  DownloadTriage {
+    enabled: preset.is_some(),
+    folder: preset
+      .map(|f| f.path)
+      .or_else(|| app.path().download_dir().ok().map(|dir| dir.to_string_lossy().to_string())),
+    ignored_patterns: load_patterns(conn),
+  }
+}
+
+#[tauri::command]
+pub fn get_download_triage(app: tauri::AppHandle, state: tauri::State<DbState>) -> Result<DownloadTriage, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(status(&app, &conn))
+}
+
+/// Turn downloads triage on or off. Files already in the folder aren't
+/// asked about.
+#[tauri::command]
+pub fn set_download_triage(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  enabled: bool,
+) -> Result<DownloadTriage, String> {
+  let preset = if enabled {
+    let dir = app.path().download_dir().map_err(|_| "No Downloads folder found on this system".to_string())?;
+    let dir = std::fs::canonicalize(&dir).map_err(|e| e.to_string())?;
+    Some(WatchFolder {
+      path: dir.to_string_lossy().to_string(),
+      rules: WatchRules::default(),
+      preset: Some(PRESET.to_string()),
+    })
+  } else {
+    None
+  };
+  watch_folders::update_folders(&app, &state, |folders| {
+    folders.retain(|f| f.preset.as_deref() != Some(PRESET));
+    if let Some(preset) = preset {
+      folders.retain(|f| f.path != preset.path);
+      folders.push(preset);
+    }
+  })?;
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(status(&app, &conn))
+}
+
+/// Downloads waiting for a decision, oldest first. Files deleted in the
+/// meantime are dropped from the queue.
+#[tauri::command]
+pub fn list_pending_triage(state: tauri::State<DbState>) -> Result<Vec<TriageItem>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let items: Vec<TriageItem> = conn
+    .prepare(
+      "SELECT path, file_name, size_bytes, seen_at FROM download_triage
+       WHERE decision IS NULL ORDER BY seen_at",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| {
+      Ok(TriageItem { path: row.get(0)?, file_name: row.get(1)?, size_bytes: row.get(2)?, seen_at: row.get(3)? })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let (present, gone): (Vec<_>, Vec<_>) = items.into_iter().partition(|item| Path::new(&item.path).is_file());
+  for item in gone {
+    conn
+      .execute("DELETE FROM download_triage WHERE path = ?", [&item.path])
+      .map_err(|e| e.to_string())?;
+  }
+  Ok(present)
+}
+
+/// Decide what happens to a queued download: "journal", "remind" or
+/// "ignore". `remind_at` overrides the Friday default; `pattern` overrides
+/// the pattern remembered for "ignore". Returns the event for "journal" and
+/// "remind".
+#[tauri::command]
+pub fn resolve_triage(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  path: String,
+  action: String,
+  remind_at: Option<i64>,
+  pattern: Option<String>,
+) -> Result<Option<TimelineEventWithAttachments>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let file_name: String = conn
+    .query_row("SELECT file_name FROM download_triage WHERE path = ? AND decision IS NULL", [&path], |row| {
+      row.get(0)
+    })
+    .map_err(|_| "Download is not waiting for triage".to_string())?;
+
+  let created = match action.as_str() {
+    "journal" | "remind" => {
+      let folder = watch_folders::load_folders(&conn)
+        .into_iter()
+        .find(|f| f.preset.as_deref() == Some(PRESET))
+        .ok_or("Downloads triage is off")?;
+      let mut created = watch_folders::capture(&app, &conn, &folder, Path::new(&path))?
+        .ok_or_else(|| i18n::t("This file was already captured or is skipped by the ingestion policy"))?;
+      if action == "remind" {
+        let remind_at = match remind_at {
+          Some(at) => at,
+          None => next_friday()?,
+        };
+        let reminder = Reminder {
+          id: generate_id(),
+          event_id: created.event.id.clone(),
+          remind_at,
+          message: i18n::tf("Look at {name}", &[("name", file_name.to_string())]),
+          status: "pending".to_string(),
+          triggered_at: None,
+          snooze_until: None,
+          created_at: now_ms(),
+          // Opening the reminder opens the file
+          attachment_id: created.attachments.first().map(|a| a.id.clone()),
+          follow_up_after_minutes: None,
+          follow_up_message: None,
+        };
+        conn
+          .execute(
+            "INSERT INTO reminders (id, event_id, remind_at, message, status, created_at, attachment_id)
+             VALUES (?1, ?2, ?3, ?4, 'pending', ?5, ?6)",
+            (
+              &reminder.id,
+              &reminder.event_id,
+              reminder.remind_at,
+              &reminder.message,
+              reminder.created_at,
+              &reminder.attachment_id,
+            ),
+          )
+          .map_err(|e| e.to_string())?;
+        created.reminders.push(reminder);
+        reminder_scan::wake(&app);
+      }
+      Some(created)
+    }
+    "ignore" => {
+      let pattern = pattern
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| pattern_for(&file_name));
+      let mut patterns = load_patterns(&conn);
+      if !patterns.contains(&pattern) {
+        patterns.push(pattern);
+        save_patterns(&conn, &patterns)?;
+        config::emit_changed(&app, vec![IGNORED_PATTERNS_KEY.to_string()]);
+      }
+      None
+    }
+    _ => return Err(format!("Unknown triage action: {}", action)),
+  };
+
+  conn
+    .execute(
+      "UPDATE download_triage SET decision = ?1, decided_at = ?2 WHERE path = ?3",
+      (&action, now_ms(), &path),
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(created)
+}
+
+/// Forget an ignored pattern.
+#[tauri::command]
+pub fn remove_ignored_download_pattern(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  pattern: String,
+) -> Result<Vec<String>, String> {
+  let patterns = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let mut patterns = load_patterns(&conn);
+    patterns.retain(|p| p != &pattern);
+    save_patterns(&conn, &patterns)?;
+    patterns
+  };
+  config::emit_changed(&app, vec![IGNORED_PATTERNS_KEY.to_string()]);
+  Ok(patterns)
+}
@@ -0,0 +1,125 @@
+// Getting attachments back out of the pet.
+//
+// `prepare_drag_out` copies an attachment to a scratch folder under the
+// system temp dir and returns that path for the webview to start a native
+// file drag with; dragging a copy means a drop target that moves the file
+// can't take it out of app storage. `save_attachment_as` is the plain
+// alternative: copy to a destination the user picked in a save dialog.
+
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::{app_lock, sandbox, DbState};
+
+const DRAG_DIR: &str = "papa-drag-out";
+// Scratch copies older than this are removed on the next drag
+const DRAG_COPY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DragOut {
+  /// Copy to drag
+  path: String,
+  file_name: String,
+  mime_type: Option<String>,
+}
+
+struct Source {
+  path: PathBuf,
+  file_name: String,
+  mime_type: Option<String>,
+}
+
+fn attachment_source(conn: &rusqlite::Connection, attachment_id: &str) -> Result<Source, String> {
+  let (path, file_name, mime_type): (String, Option<String>, Option<String>) = conn
+    .query_row(
+      "SELECT COALESCE(stored_path, original_path), file_name, mime_type FROM attachments WHERE id = ?",
+      [attachment_id],
+      |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .map_err(|_| "Attachment not found".to_string())?;
+  let path = PathBuf::from(path);
+  if !path.is_file() {
+    return Err(format!("File not found: {}", path.display()));
+  }
+  let file_name = file_name
+    .or_else(|| path.file_name().map(|n| n.to_string_lossy().to_string()))
+    .unwrap_or_else(|| attachment_id.to_string());
+  Ok(Source { path, file_name: sandbox::safe_file_name(&file_name)?, mime_type })
+}
+
+fn remove_stale_copies(root: &Path) {
+  let Ok(entries) = fs::read_dir(root) else { return };
+  for entry in entries.flatten() {
+    let stale = entry
+      .metadata()
+      .and_then(|m| m.modified())
+      .ok()
+      .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+      .is_some_and(|age| age > DRAG_COPY_TTL);
+    if stale {
+      let _ = fs::remove_dir_all(entry.path());
+    }
+  }
+}
+
+#[tauri::command]
+pub fn prepare_drag_out(state: tauri::State<DbState>, attachment_id: String) -> Result<DragOut, String> {
+  app_lock::ensure_unlocked()?;
+  let source = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    attachment_source(&conn, &attachment_id)?
+  };
+
+  let root = std::env::temp_dir().join(DRAG_DIR);
+  remove_stale_copies(&root);
+  // One folder per attachment keeps the original file name intact
+  let dir = root.join(sandbox::safe_file_name(&attachment_id)?);
+  fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+  let target = dir.join(&source.file_name);
+  fs::copy(&source.path, &target).map_err(|e| e.to_string())?;
+
+  Ok(DragOut {
+    path: target.to_string_lossy().to_string(),
+    file_name: source.file_name,
+    mime_type: source.mime_type,
+  })
+}
+
+/// Copy an attachment to `dest`, a file path or an existing folder (the
+/// attachment's own name is used then). Existing files are only replaced
+/// with `overwrite`. Returns the path written.
+#[tauri::command]
+pub fn save_attachment_as(
+  state: tauri::State<DbState>,
+  attachment_id: String,
+  dest: String,
+  overwrite: Option<bool>,
+) -> Result<String, String> {
+  app_lock::ensure_unlocked()?;
+  let source = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    attachment_source(&conn, &attachment_id)?
+  };
+
+  let dest = PathBuf::from(dest.trim());
+  if !dest.is_absolute() {
+    return Err("Destination must be an absolute path".to_string());
+  }
+  let target = if dest.is_dir() { dest.join(&source.file_name) } else { dest };
+  if target.exists() && !overwrite.unwrap_or(false) {
+    return Err(format!("{} already exists", target.display()));
+  }
+  if fs::canonicalize(&target).ok() == fs::canonicalize(&source.path).ok() {
+    return Err("Destination is the attachment itself".to_string());
+  }
+  if let Some(parent) = target.parent() {
+    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+  }
+  fs::copy(&source.path, &target).map_err(|e| e.to_string())?;
+  Ok(target.to_string_lossy().to_string())
+}
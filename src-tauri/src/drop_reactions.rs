@@ -0,0 +1,250 @@
+// How the pet reacts to what's dropped on it.
+//
+// A reaction rule matches files by MIME type ("application/pdf", or "image/*"
+// for a whole family), extension and/or a case-insensitive regex on the file
+// name, and carries a message for the pet to say plus suggested actions for
+// the UI to offer ("summarize", "remind", ...). Every criterion a rule sets
+// has to match. `create_drop_event` evaluates the rules against the dropped
+// files and returns the first match with the event.
+//
+// Rules saved by the user live in `drop_reactions` and are tried by
+// priority, highest first. The built-in rules below only apply when none of
+// them match, so a user rule for PDFs replaces the default one.
+
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{generate_id, get_mime_type, now_ms, receipts, DbState};
+
+// Actions the UI knows how to offer
+const ACTIONS: &[&str] = &["summarize", "remind", "extract_text", "tag", "open"];
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionRule {
+  /// Empty when saving a new rule
+  #[serde(default)]
+  id: String,
+  name: String,
+  /// "type/subtype" or "type/*"
+  mime_pattern: Option<String>,
+  /// Lowercase, without the dot
+  #[serde(default)]
+  extensions: Vec<String>,
+  /// Regex on the file name, case-insensitive
+  filename_pattern: Option<String>,
+  message: String,
+  #[serde(default)]
+  actions: Vec<String>,
+  #[serde(default)]
+  priority: i64,
+  #[serde(default = "enabled_default")]
+  enabled: bool,
+  /// Shipped with the app; can't be edited or deleted
+  #[serde(default)]
+  builtin: bool,
+}
+
+fn enabled_default() -> bool {
+  true
+}
+
+/// What the pet should do about a drop.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DropReaction {
+  rule_id: String,
+  message: String,
+  actions: Vec<String>,
+  /// The file that matched
+  file_name: Option<String>,
+}
+
+fn builtin(id: &str, name: &str, mime: Option<&str>, filename: Option<&str>, message: &str, actions: &[&str]) -> ReactionRule {
+  ReactionRule {
+    id: id.to_string(),
+    name: name.to_string(),
+    mime_pattern: mime.map(|m| m.to_string()),
+    extensions: Vec::new(),
+    filename_pattern: filename.map(|f| f.to_string()),
+    message: message.to_string(),
+    actions: actions.iter().map(|a| a.to_string()).collect(),
+    priority: 0,
+    enabled: true,
+    builtin: true,
+  }
+}
+
+fn builtin_rules() -> Vec<ReactionRule> {
+  vec![
+    builtin(
+      "builtin:invoice",
+      "Invoices",
+      None,
+      Some(receipts::RECEIPT_NAME_PATTERN),
+      "Looks like an invoice. Want me to remind you to pay it?",
+      &["remind"],
+    ),
+    builtin("builtin:pdf", "PDFs", Some("application/pdf"), None, "A PDF! I can summarize it for you.", &["summarize"]),
+    builtin("builtin:image", "Images", Some("image/*"), None, "Ooh, a picture!", &["tag"]),
+  ]
+}
+
+fn validate(mut rule: ReactionRule) -> Result<ReactionRule, String> {
+  rule.name = rule.name.trim().to_string();
+  rule.message = rule.message.trim().to_string();
+  if rule.name.is_empty() {
+    return Err("Rule name must not be empty".to_string());
+  }
+  if rule.message.is_empty() {
+    return Err("Reaction message must not be empty".to_string());
+  }
+  rule.mime_pattern = rule.mime_pattern.map(|m| m.trim().to_lowercase()).filter(|m| !m.is_empty());
+  if let Some(mime) = &rule.mime_pattern {
+    if !mime.contains('/') {
+      return Err(format!("Invalid MIME pattern: {}", mime));
+    }
+  }
+  rule.extensions = rule
+    .extensions
+    .iter()
+    .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+    .filter(|e| !e.is_empty())
+    .collect();
+  rule.filename_pattern = rule.filename_pattern.filter(|p| !p.trim().is_empty());
+  if let Some(pattern) = &rule.filename_pattern {
+    RegexBuilder::new(pattern).case_insensitive(true).build().map_err(|e| format!("Invalid file name pattern: {}", e))?;
+  }
+  if rule.mime_pattern.is_none() && rule.extensions.is_empty() && rule.filename_pattern.is_none() {
+    return Err("A rule needs a MIME type, extension or file name pattern".to_string());
+  }
+  if let Some(action) = rule.actions.iter().find(|a| !ACTIONS.contains(&a.as_str())) {
+    return Err(format!("Unknown action: {}", action));
+  }
+  Ok(rule)
+}
+
+fn matches(rule: &ReactionRule, path: &Path, mime: Option<&str>) -> bool {
+  let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+  let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).unwrap_or_default();
+  let mime_ok = rule.mime_pattern.as_deref().is_none_or(|pattern| match (pattern.strip_suffix("/*"), mime) {
+    (_, None) => false,
+    (Some(family), Some(mime)) => mime.split('/').next() == Some(family),
+    (None, Some(mime)) => pattern == mime,
+  });
+  let extension_ok = rule.extensions.is_empty() || rule.extensions.contains(&extension);
+  let filename_ok = rule.filename_pattern.as_deref().is_none_or(|pattern| {
+    RegexBuilder::new(pattern).case_insensitive(true).build().is_ok_and(|re| re.is_match(file_name))
+  });
+  mime_ok && extension_ok && filename_ok
+}
+
+fn rule_from_row(row: &rusqlite::Row) -> rusqlite::Result<ReactionRule> {
+  let extensions: String = row.get(3)?;
+  let actions: String = row.get(6)?;
+  Ok(ReactionRule {
+    id: row.get(0)?,
+    name: row.get(1)?,
+    mime_pattern: row.get(2)?,
+    extensions: serde_json::from_str(&extensions).unwrap_or_default(),
+    filename_pattern: row.get(4)?,
+    message: row.get(5)?,
+    actions: serde_json::from_str(&actions).unwrap_or_default(),
+    priority: row.get(7)?,
+    enabled: row.get::<_, i32>(8)? != 0,
+    builtin: false,
+  })
+}
+
+fn user_rules(conn: &rusqlite::Connection) -> Result<Vec<ReactionRule>, String> {
+  let rules = conn
+    .prepare(
+      "SELECT id, name, mime_pattern, extensions, filename_pattern, message, actions, priority, enabled
+       FROM drop_reactions ORDER BY priority DESC, created_at",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([], rule_from_row)
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(rules)
+}
+
+/// The reaction to a drop of `paths`: the first rule, in priority order,
+/// that matches any of the files.
+pub fn evaluate(conn: &rusqlite::Connection, paths: &[&String]) -> Option<DropReaction> {
+  let rules = user_rules(conn).unwrap_or_default().into_iter().filter(|r| r.enabled).chain(builtin_rules());
+  let files: Vec<(&Path, Option<String>)> =
+    paths.iter().map(|p| (Path::new(p.as_str()), get_mime_type(Path::new(p.as_str())))).collect();
+  for rule in rules {
+    if let Some((path, _)) = files.iter().find(|(path, mime)| matches(&rule, path, mime.as_deref())) {
+      return Some(DropReaction {
+        rule_id: rule.id,
+        message: rule.message,
+        actions: rule.actions,
+        file_name: path.file_name().and_then(|n| n.to_str()).map(|s| s.to_string()),
+      });
+    }
+  }
+  None
+}
+
+/// User rules in the order they're tried, then the built-in ones.
+#[tauri::command]
+pub fn list_drop_reactions(state: tauri::State<DbState>) -> Result<Vec<ReactionRule>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let mut rules = user_rules(&conn)?;
+  rules.extend(builtin_rules());
+  Ok(rules)
+}
+
+/// Create a rule, or update the one with `rule.id`.
+#[tauri::command]
+pub fn save_drop_reaction(state: tauri::State<DbState>, rule: ReactionRule) -> Result<ReactionRule, String> {
+  if rule.builtin || rule.id.starts_with("builtin:") {
+    return Err("Built-in rules can't be changed; add a rule with a higher priority instead".to_string());
+  }
+  let mut rule = validate(rule)?;
+  let extensions = serde_json::to_string(&rule.extensions).map_err(|e| e.to_string())?;
+  let actions = serde_json::to_string(&rule.actions).map_err(|e| e.to_string())?;
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  if rule.id.is_empty() {
+    rule.id = generate_id();
+  }
+  conn
+    .execute(
+      "INSERT INTO drop_reactions
+         (id, name, mime_pattern, extensions, filename_pattern, message, actions, priority, enabled, created_at)
+       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+       ON CONFLICT(id) DO UPDATE SET
+         name = excluded.name, mime_pattern = excluded.mime_pattern, extensions = excluded.extensions,
+         filename_pattern = excluded.filename_pattern, message = excluded.message, actions = excluded.actions,
+         priority = excluded.priority, enabled = excluded.enabled",
+      rusqlite::params![
+        &rule.id,
+        &rule.name,
+        &rule.mime_pattern,
+        &extensions,
+        &rule.filename_pattern,
+        &rule.message,
+        &actions,
+        rule.priority,
+        rule.enabled,
+        now_ms(),
+      ],
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(rule)
+}
+
+#[tauri::command]
+pub fn delete_drop_reaction(state: tauri::State<DbState>, id: String) -> Result<(), String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  conn.execute("DELETE FROM drop_reactions WHERE id = ?", [&id]).map_err(|e| e.to_string())?;
+  Ok(())
+}
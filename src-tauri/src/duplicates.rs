@@ -0,0 +1,337 @@
+// Near-duplicate text events and merging them.
+//
+// A background job goes over every live event with enough text, turns it
+// into word 3-gram shingles and a MinHash signature, and uses LSH banding to
+// find pairs worth comparing without comparing every pair. Pairs whose
+// estimated Jaccard similarity reaches `MIN_SIMILARITY` go into
+// `duplicate_candidates`, once: a pair the user dismissed isn't suggested
+// again.
+//
+// `merge_events` folds one event of a pair into the other. Attachments,
+// tags and reminders move to the kept event and the merged one is deleted
+// (softly, like any delete). Locked events can't be merged, since their
+// attachments are encrypted for that event.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::{app_lock, event_from_row, i18n, now_ms, query_attachments, query_reminders, supervisor, DbState, TimelineEvent, TimelineEventWithAttachments};
+
+const SCAN_INTERVAL: Duration = Duration::from_secs(30 * 60);
+// Shorter texts share too many shingles by chance
+const MIN_WORDS: usize = 8;
+const SHINGLE_WORDS: usize = 3;
+const BANDS: usize = 16;
+const ROWS_PER_BAND: usize = 4;
+const SIGNATURE_LEN: usize = BANDS * ROWS_PER_BAND;
+const MIN_SIMILARITY: f64 = 0.8;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateCandidate {
+  first: TimelineEvent,
+  second: TimelineEvent,
+  /// Estimated Jaccard similarity of the two texts' shingles
+  similarity: f64,
+  detected_at: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DuplicatesFound {
+  count: usize,
+}
+
+/// FNV-1a, stable across runs unlike `DefaultHasher`.
+fn fnv1a(text: &str) -> u64 {
+  text.bytes().fold(0xcbf29ce484222325, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// splitmix64, to derive independent hash functions from one shingle hash.
+fn mix(mut x: u64) -> u64 {
+  x = x.wrapping_add(0x9e3779b97f4a7c15);
+  x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+  x ^ (x >> 31)
+}
+
+fn shingles(text: &str) -> Option<HashSet<u64>> {
+  let words: Vec<String> = text
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|w| !w.is_empty())
+    .map(|w| w.to_lowercase())
+    .collect();
+  if words.len() < MIN_WORDS {
+    return None;
+  }
+  Some(words.windows(SHINGLE_WORDS).map(|w| fnv1a(&w.join(" "))).collect())
+}
+
+fn signature(shingles: &HashSet<u64>) -> Vec<u64> {
+  (0..SIGNATURE_LEN as u64)
+    .map(|i| shingles.iter().map(|s| mix(s ^ mix(i))).min().unwrap_or(u64::MAX))
+    .collect()
+}
+
+fn estimated_similarity(a: &[u64], b: &[u64]) -> f64 {
+  a.iter().zip(b).filter(|(x, y)| x == y).count() as f64 / SIGNATURE_LEN as f64
+}
+
+/// Pairs of event ids (ordered) with similarity at least `MIN_SIMILARITY`.
+fn find_pairs(texts: Vec<(String, String)>) -> Vec<(String, String, f64)> {
+  let signatures: Vec<(String, Vec<u64>)> = texts
+    .into_iter()
+    .filter_map(|(id, text)| shingles(&text).map(|s| (id, signature(&s))))
+    .collect();
+
+  let mut compared = HashSet::new();
+  let mut pairs = Vec::new();
+  for band in 0..BANDS {
+    let rows = band * ROWS_PER_BAND..(band + 1) * ROWS_PER_BAND;
+    let mut buckets: HashMap<&[u64], Vec<usize>> = HashMap::new();
+    for (i, (_, sig)) in signatures.iter().enumerate() {
+      buckets.entry(&sig[rows.clone()]).or_default().push(i);
+    }
+    for bucket in buckets.values().filter(|b| b.len() > 1) {
+      for (n, &i) in bucket.iter().enumerate() {
+        for &j in &bucket[n + 1..] {
+          if !compared.insert((i, j)) {
+            continue;
+          }
+          let similarity = estimated_similarity(&signatures[i].1, &signatures[j].1);
+          if similarity >= MIN_SIMILARITY {
+            let (a, b) = (&signatures[i].0, &signatures[j].0);
+            let (a, b) = if a < b { (a, b) } else { (b, a) };
+            pairs.push((a.clone(), b.clone(), similarity));
+          }
+        }
+      }
+    }
+  }
+  pairs
+}
+
+fn load_texts(conn: &rusqlite::Connection) -> Result<Vec<(String, String)>, String> {
+  conn
+    .prepare(
+      "SELECT id, COALESCE(title, '') || ' ' || COALESCE(note, '') || ' ' || COALESCE(text_content, '')
+       FROM timeline_events
+       WHERE is_deleted = 0 AND locked = 0 AND scheduled_for IS NULL
+         AND (note IS NOT NULL OR text_content IS NOT NULL)",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+    .map_err(|e| e.to_string())
+    .map(|rows| rows.filter_map(|r| r.ok()).collect())
+}
+
+/// Record new pairs; returns how many weren't known yet.
+fn record_pairs(conn: &rusqlite::Connection, pairs: &[(String, String, f64)]) -> Result<usize, String> {
+  let detected_at = now_ms();
+  let mut added = 0;
+  for (a, b, similarity) in pairs {
+    added += conn
+      .execute(
+        "INSERT OR IGNORE INTO duplicate_candidates (event_a, event_b, similarity, detected_at, status)
+         VALUES (?1, ?2, ?3, ?4, 'pending')",
+        (a, b, similarity, detected_at),
+      )
+      .map_err(|e| e.to_string())?;
+  }
+  Ok(added)
+}
+
+pub fn spawn_duplicate_scanner(app: tauri::AppHandle) {
+  supervisor::supervise(app, "duplicates", |app| async move {
+    loop {
+      tokio::time::sleep(SCAN_INTERVAL).await;
+
+      let texts = {
+        let state = app.state::<DbState>();
+        let Ok(_guard) = state.lock.lock() else { continue };
+        match state.open().map_err(|e| e.to_string()).and_then(|conn| load_texts(&conn)) {
+          Ok(texts) => texts,
+          Err(e) => {
+            tracing::warn!(error = %e, "Duplicate scan failed");
+            continue;
+          }
+        }
+      };
+      let Ok(pairs) = tokio::task::spawn_blocking(move || find_pairs(texts)).await else { continue };
+      if pairs.is_empty() {
+        continue;
+      }
+
+      let added = {
+        let state = app.state::<DbState>();
+        let Ok(_guard) = state.lock.lock() else { continue };
+        state.open()
+          .map_err(|e| e.to_string())
+          .and_then(|conn| record_pairs(&conn, &pairs))
+      };
+      match added {
+        Ok(0) => {}
+        Ok(count) => {
+          tracing::info!(count, "Found duplicate candidates");
+          if let Some(window) = app.get_webview_window("main") {
+            let _ = window.emit("duplicates-found", DuplicatesFound { count });
+          }
+        }
+        Err(e) => tracing::warn!(error = %e, "Recording duplicate candidates failed"),
+      }
+    }
+  });
+}
+
+/// Pending pairs of likely duplicates, most similar first.
+#[tauri::command]
+pub fn list_duplicate_candidates(state: tauri::State<DbState>) -> Result<Vec<DuplicateCandidate>, String> {
+  app_lock::ensure_unlocked()?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let columns = "id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec";
+  let pairs: Vec<(String, String, f64, i64)> = conn
+    .prepare(
+      "SELECT event_a, event_b, similarity, detected_at FROM duplicate_candidates
+       WHERE status = 'pending' ORDER BY similarity DESC, detected_at DESC",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let live = |id: &str| -> Option<TimelineEvent> {
+    conn
+      .query_row(
+        &format!("SELECT {} FROM timeline_events WHERE id = ? AND is_deleted = 0", columns),
+        [id],
+        event_from_row,
+      )
+      .ok()
+  };
+  Ok(
+    pairs
+      .into_iter()
+      .filter_map(|(a, b, similarity, detected_at)| {
+        Some(DuplicateCandidate { first: live(&a)?, second: live(&b)?, similarity, detected_at })
+      })
+      .collect(),
+  )
+}
+
+/// Stop suggesting a pair.
+#[tauri::command]
+pub fn dismiss_duplicate_candidate(state: tauri::State<DbState>, first_id: String, second_id: String) -> Result<(), String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  conn
+    .execute(
+      "UPDATE duplicate_candidates SET status = 'dismissed'
+       WHERE (event_a = ?1 AND event_b = ?2) OR (event_a = ?2 AND event_b = ?1)",
+      (&first_id, &second_id),
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Move the attachments, tags and reminders of `merge_id` to `keep_id` and
+/// delete `merge_id`. Returns the kept event as it is now.
+#[tauri::command]
+pub fn merge_events(
+  state: tauri::State<DbState>,
+  keep_id: String,
+  merge_id: String,
+) -> Result<TimelineEventWithAttachments, String> {
+  if keep_id == merge_id {
+    return Err("Can't merge an event into itself".to_string());
+  }
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+
+  for id in [&keep_id, &merge_id] {
+    let locked: bool = conn
+      .query_row("SELECT locked FROM timeline_events WHERE id = ? AND is_deleted = 0", [id], |row| {
+        row.get::<_, i32>(0).map(|l| l != 0)
+      })
+      .map_err(|_| i18n::t("Event not found"))?;
+    if locked {
+      return Err(i18n::t("Unlock both events first"));
+    }
+  }
+
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  tx.execute("UPDATE attachments SET event_id = ?1 WHERE event_id = ?2", (&keep_id, &merge_id))
+    .map_err(|e| e.to_string())?;
+  tx.execute(
+    "INSERT OR IGNORE INTO event_tags (event_id, tag_id, source, created_at)
+     SELECT ?1, tag_id, source, created_at FROM event_tags WHERE event_id = ?2",
+    (&keep_id, &merge_id),
+  )
+  .map_err(|e| e.to_string())?;
+  tx.execute("DELETE FROM event_tags WHERE event_id = ?", [&merge_id]).map_err(|e| e.to_string())?;
+  tx.execute("UPDATE reminders SET event_id = ?1 WHERE event_id = ?2", (&keep_id, &merge_id))
+    .map_err(|e| e.to_string())?;
+  tx.execute("UPDATE timeline_events SET is_deleted = 1 WHERE id = ?", [&merge_id])
+    .map_err(|e| e.to_string())?;
+  tx.execute(
+    "UPDATE duplicate_candidates SET status = 'merged' WHERE event_a = ?1 OR event_b = ?1",
+    [&merge_id],
+  )
+  .map_err(|e| e.to_string())?;
+  tx.commit().map_err(|e| e.to_string())?;
+  tracing::info!(%keep_id, %merge_id, "Merged events");
+
+  let event = conn
+    .query_row(
+      "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
+       FROM timeline_events WHERE id = ?",
+      [&keep_id],
+      event_from_row,
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(TimelineEventWithAttachments {
+    attachments: query_attachments(&conn, &keep_id)?,
+    reminders: query_reminders(&conn, &keep_id)?,
+    event,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const HEATING: &str = "Met with the landlord about the broken heating in the flat. He promised to send a plumber on \
+    Thursday morning and to refund the repair costs if the boiler has to be replaced before winter starts.";
+  const NOVEL: &str = "Finished reading the novel about the lighthouse keeper. The ending was quiet but satisfying, \
+    and I want to lend it to my sister when she visits next month with the kids.";
+
+  #[test]
+  fn skips_short_texts() {
+    assert!(shingles("Bought milk and bread").is_none());
+    assert!(shingles(HEATING).is_some());
+  }
+
+  #[test]
+  fn signatures_ignore_case_and_punctuation() {
+    let plain = signature(&shingles(&HEATING.to_lowercase().replace(['.', ','], "")).unwrap());
+    let original = signature(&shingles(HEATING).unwrap());
+    assert_eq!(estimated_similarity(&plain, &original), 1.0);
+  }
+
+  #[test]
+  fn pairs_near_duplicates_only() {
+    let edited = HEATING.replace("winter starts", "winter begins");
+    let pairs = find_pairs(vec![
+      ("b".to_string(), HEATING.to_string()),
+      ("c".to_string(), NOVEL.to_string()),
+      ("a".to_string(), edited),
+    ]);
+    assert_eq!(pairs.len(), 1);
+    let (first, second, similarity) = &pairs[0];
+    assert_eq!((first.as_str(), second.as_str()), ("a", "b"));
+    assert!(*similarity >= MIN_SIMILARITY && *similarity < 1.0);
+  }
+}
@@ -0,0 +1,346 @@
+// Morning email with the previous day's export.
+//
+// Once a day, after the configured time in the day-counting timezone (see
+// timezone.rs), the HTML export of yesterday
+// is written as usual and mailed over SMTP. Image assets can go along as
+// inline attachments so the email shows them; otherwise they're replaced with
+// their names. The SMTP password lives in the credential store
+// (`email_digest.smtp_password`, see secrets.rs), the rest of the settings
+// under `email_digest`.
+
+use chrono::{Duration as ChronoDuration, NaiveTime};
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::permissions::{self, Permission};
+use crate::{
+  config, connectivity, export_rules, i18n, maintenance, now_ms, read_setting, secrets, supervisor, usage_metrics, write_daily_export,
+  timezone, write_setting, DbState,
+};
+
+pub const EMAIL_DIGEST_KEY: &str = "email_digest";
+const LAST_SENT_KEY: &str = "email_digest.last_sent";
+const PASSWORD_SECRET: &str = "email_digest.smtp_password";
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// Wait this long before trying again after a failed send
+const RETRY_AFTER: Duration = Duration::from_secs(15 * 60);
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+// Attachments beyond this total are left out
+const MAX_ATTACHMENT_BYTES: u64 = 20 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SmtpSettings {
+  host: String,
+  port: u16,
+  /// "tls", "starttls" or "none"
+  security: String,
+  /// Empty when the server doesn't need a login
+  #[serde(default)]
+  username: String,
+  /// Sender address; defaults to `username`
+  #[serde(default)]
+  from: Option<String>,
+  /// Only accepted on input; stored in the credential store
+  #[serde(default, skip_serializing)]
+  password: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DigestSchedule {
+  enabled: bool,
+  /// Time "HH:MM" after which the digest goes out, in the day-counting timezone
+  send_at: String,
+  #[serde(default)]
+  include_attachments: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailDigestConfig {
+  smtp: SmtpSettings,
+  recipient: String,
+  schedule: DigestSchedule,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestFailed {
+  date_key: String,
+  error: String,
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox, String> {
+  address
+    .trim()
+    .parse()
+    .map_err(|_| format!("Invalid email address: {}", address))
+}
+
+fn sender(smtp: &SmtpSettings) -> Result<Mailbox, String> {
+  parse_mailbox(smtp.from.as_deref().filter(|f| !f.trim().is_empty()).unwrap_or(&smtp.username))
+}
+
+fn validate(config: &EmailDigestConfig) -> Result<(), String> {
+  if config.smtp.host.trim().is_empty() {
+    return Err("SMTP host must not be empty".to_string());
+  }
+  if !matches!(config.smtp.security.as_str(), "tls" | "starttls" | "none") {
+    return Err(format!("Unknown SMTP security: {}", config.smtp.security));
+  }
+  sender(&config.smtp)?;
+  parse_mailbox(&config.recipient)?;
+  NaiveTime::parse_from_str(&config.schedule.send_at, "%H:%M")
+    .map_err(|_| format!("Invalid time: {}", config.schedule.send_at))?;
+  Ok(())
+}
+
+fn load_config(conn: &rusqlite::Connection) -> Option<EmailDigestConfig> {
+  read_setting(conn, EMAIL_DIGEST_KEY).and_then(|json| serde_json::from_str(&json).ok())
+}
+
+fn transport(smtp: &SmtpSettings) -> Result<SmtpTransport, String> {
+  let builder = match smtp.security.as_str() {
+    "tls" => SmtpTransport::relay(&smtp.host).map_err(|e| e.to_string())?,
+    "starttls" => SmtpTransport::starttls_relay(&smtp.host).map_err(|e| e.to_string())?,
+    _ => SmtpTransport::builder_dangerous(&smtp.host),
+  };
+  let mut builder = builder.port(smtp.port).timeout(Some(SMTP_TIMEOUT));
+  if !smtp.username.is_empty() {
+    let password = secrets::get(PASSWORD_SECRET)?.unwrap_or_default();
+    builder = builder.credentials(Credentials::new(smtp.username.clone(), password));
+  }
+  Ok(builder.build())
+}
+
+fn send(config: &EmailDigestConfig, message: Message) -> Result<(), String> {
+  permissions::ensure(Permission::Network)?;
+  transport(&config.smtp)?
+    .send(&message)
+    .map(|_| ())
+    .map_err(|e| format!("Sending email failed: {}", e))
+}
+
+fn content_type(bytes: &[u8]) -> ContentType {
+  let mime = infer::get(bytes).map(|t| t.mime_type()).unwrap_or("application/octet-stream");
+  ContentType::parse(mime).unwrap_or(ContentType::TEXT_PLAIN)
+}
+
+/// The digest for the export at `export_path`. Asset images are referenced
+/// relative to the export file, so they're either swapped for inline parts
+/// or replaced with their names.
+fn digest_message(
+  config: &EmailDigestConfig,
+  date_key: &str,
+  export_path: &Path,
+) -> Result<Message, String> {
+  let html = fs::read_to_string(export_path).map_err(|e| e.to_string())?;
+  let assets_dir = export_path.with_file_name(format!("{}_assets", date_key));
+  let img = Regex::new(&format!(r#"<img src="{}_assets/([^"]+)" alt="([^"]*)"[^>]*>"#, regex::escape(date_key)))
+    .map_err(|e| e.to_string())?;
+
+  // Asset file name -> Content-ID for images shown inline
+  let mut content_ids: HashMap<String, String> = HashMap::new();
+  let mut inline = Vec::new();
+  let mut files = Vec::new();
+  if config.schedule.include_attachments {
+    let mut budget = MAX_ATTACHMENT_BYTES;
+    let mut entries: Vec<_> = fs::read_dir(&assets_dir)
+      .map(|dir| dir.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+      .unwrap_or_default();
+    entries.sort();
+    for path in entries {
+      let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(u64::MAX);
+      if size > budget {
+        continue;
+      }
+      let Ok(bytes) = fs::read(&path) else { continue };
+      budget -= size;
+      let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+      let kind = content_type(&bytes);
+      if html.contains(&format!("src=\"{}_assets/{}\"", date_key, name)) {
+        let content_id = format!("asset{}@papa", content_ids.len());
+        inline.push(Attachment::new_inline(content_id.clone()).body(bytes, kind));
+        content_ids.insert(name, content_id);
+      } else {
+        files.push(Attachment::new(name).body(bytes, kind));
+      }
+    }
+  }
+
+  let html = img
+    .replace_all(&html, |caps: &regex::Captures| match content_ids.get(&caps[1]) {
+      Some(content_id) => format!(
+        r#"<img src="cid:{}" alt="{}" style="max-width: 100%; border-radius: 8px;">"#,
+        content_id, &caps[2]
+      ),
+      None => format!("<p>🖼️ {}</p>", &caps[2]),
+    })
+    .to_string();
+
+  let mut related = MultiPart::related().singlepart(SinglePart::html(html));
+  for part in inline {
+    related = related.singlepart(part);
+  }
+  let mut body = MultiPart::mixed().multipart(related);
+  for part in files {
+    body = body.singlepart(part);
+  }
+
+  Message::builder()
+    .from(sender(&config.smtp)?)
+    .to(parse_mailbox(&config.recipient)?)
+    .subject(i18n::tf("Daily Record - {date}", &[("date", date_key.to_string())]))
+    .multipart(body)
+    .map_err(|e| e.to_string())
+}
+
+/// Export yesterday and mail it. Returns false when there was nothing to send.
+fn send_digest(app: &tauri::AppHandle, config: &EmailDigestConfig, date_key: &str) -> Result<bool, String> {
+  let export_path = {
+    let maintenance = app.state::<maintenance::MaintenanceState>();
+    let _export = maintenance.begin_export();
+    let state = app.state::<DbState>();
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+
+    if export_rules::query_day(&conn, date_key, &export_rules::resolve(&conn, None))?.is_empty() {
+      return Ok(false);
+    }
+    write_daily_export(app, &conn, date_key, "html", None, None, None)?
+  };
+  send(config, digest_message(config, date_key, Path::new(&export_path))?)?;
+  Ok(true)
+}
+
+/// The configuration, the day to send and the day it's sent on, if a digest
+/// is due now. Time and date come from the same clock, otherwise a pinned
+/// timezone would roll the day over partway through the local one.
+fn due(app: &tauri::AppHandle) -> Option<(EmailDigestConfig, String, String)> {
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().ok()?;
+  let conn = state.open().ok()?;
+  let config = load_config(&conn).filter(|c| c.schedule.enabled)?;
+
+  let now = timezone::datetime(now_ms())?;
+  let send_at = NaiveTime::parse_from_str(&config.schedule.send_at, "%H:%M").ok()?;
+  let today = now.format("%Y-%m-%d").to_string();
+  if now.time() < send_at || read_setting(&conn, LAST_SENT_KEY).as_deref() == Some(today.as_str()) {
+    return None;
+  }
+  let yesterday = (now.date_naive() - ChronoDuration::days(1)).format("%Y-%m-%d").to_string();
+  Some((config, yesterday, today))
+}
+
+fn mark_sent(app: &tauri::AppHandle, sent_on: &str) -> Result<(), String> {
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  write_setting(&conn, LAST_SENT_KEY, sent_on)
+}
+
+pub fn spawn_digest_sender(app: tauri::AppHandle) {
+  supervisor::supervise(app, "email_digest", |app| async move {
+    let mut retry_at: Option<tokio::time::Instant> = None;
+    loop {
+      // A send that failed for want of a connection is retried once it's back
+      if connectivity::sleep(CHECK_INTERVAL).await {
+        retry_at = None;
+      }
+      if retry_at.is_some_and(|at| tokio::time::Instant::now() < at) || !connectivity::reachable() {
+        continue;
+      }
+      let Some((config, date_key, sent_on)) = due(&app) else { continue };
+
+      let sender_app = app.clone();
+      let day = date_key.clone();
+      let result = tokio::task::spawn_blocking(move || send_digest(&sender_app, &config, &day))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r);
+      match result {
+        Ok(sent) => {
+          retry_at = None;
+          if sent {
+            tracing::info!(%date_key, "Email digest sent");
+          }
+          if let Err(e) = mark_sent(&app, &sent_on) {
+            tracing::warn!(error = %e, "Could not record email digest");
+          }
+        }
+        Err(error) => {
+          tracing::warn!(%date_key, error = %error, "Email digest failed");
+          retry_at = Some(tokio::time::Instant::now() + RETRY_AFTER);
+          if let Some(window) = app.get_webview_window("main") {
+            let _ = window.emit("email-digest-failed", DigestFailed { date_key, error });
+          }
+        }
+      }
+    }
+  });
+}
+
+/// Save the digest settings. A `smtp.password` goes to the credential store;
+/// leave it out to keep the stored one.
+#[tauri::command]
+pub fn configure_email_digest(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  smtp: SmtpSettings,
+  recipient: String,
+  schedule: DigestSchedule,
+) -> Result<EmailDigestConfig, String> {
+  let mut config = EmailDigestConfig { smtp, recipient: recipient.trim().to_string(), schedule };
+  validate(&config)?;
+  if let Some(password) = config.smtp.password.take().filter(|p| !p.is_empty()) {
+    secrets::set_secret(PASSWORD_SECRET.to_string(), password)?;
+  }
+
+  let json = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, EMAIL_DIGEST_KEY, &json)?;
+  }
+  config::emit_changed(&app, vec![EMAIL_DIGEST_KEY.to_string()]);
+  Ok(config)
+}
+
+#[tauri::command]
+pub fn get_email_digest_config(state: tauri::State<DbState>) -> Result<Option<EmailDigestConfig>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_config(&conn))
+}
+
+/// Send a short message with the saved settings to check they work.
+#[tauri::command]
+pub async fn send_test_email(state: tauri::State<'_, DbState>) -> Result<(), String> {
+  let _timer = usage_metrics::time_async("send_test_email");
+  let config = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    load_config(&conn).ok_or_else(|| "Email digest is not configured".to_string())?
+  };
+  let message = Message::builder()
+    .from(sender(&config.smtp)?)
+    .to(parse_mailbox(&config.recipient)?)
+    .subject(i18n::t("Papa test email"))
+    .header(ContentType::TEXT_PLAIN)
+    .body("Your daily digest will arrive at this address.".to_string())
+    .map_err(|e| e.to_string())?;
+  tokio::task::spawn_blocking(move || send(&config, message))
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,271 @@
+// Passphrase locks for sensitive events.
+//
+// Locking encrypts the event's note, text and attachments with AES-256-GCM
+// under a key derived from the passphrase with Argon2id. The ciphertext goes
+// to `event_locks` and the plain columns are cleared, so lists, search and
+// exports only ever see the title. Attachment rows move into the encrypted
+// payload and their files into `locked/<event id>/` in the profile folder;
+// copies the app made are deleted, files elsewhere on disk are left alone.
+// `unlock_event` decrypts in memory for viewing and changes nothing stored.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::Manager;
+
+use crate::{app_lock, compression, i18n, mentions, now_ms, query_attachments, remove_unused_file, tags, thumbnails, undo, Attachment, DbState};
+
+const LOCKED_DIR: &str = "locked";
+const MIN_PASSPHRASE_LEN: usize = 8;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+#[derive(Serialize, Deserialize)]
+struct LockedContent {
+  note: Option<String>,
+  text_content: Option<String>,
+  attachments: Vec<LockedAttachment>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LockedAttachment {
+  attachment: Attachment,
+  /// Nonce of the encrypted file; `None` when the file was already missing
+  nonce: Option<Vec<u8>>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockedEvent {
+  event_id: String,
+  note: Option<String>,
+  text_content: Option<String>,
+  attachments: Vec<UnlockedAttachment>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockedAttachment {
+  #[serde(flatten)]
+  attachment: Attachment,
+  /// `data:<mime>;base64,...`, `None` when the file was missing at lock time
+  data_url: Option<String>,
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+  let mut bytes = [0u8; N];
+  rand::thread_rng().fill_bytes(&mut bytes);
+  bytes
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<Aes256Gcm, String> {
+  let mut key = [0u8; 32];
+  Argon2::default()
+    .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+    .map_err(|e| format!("Key derivation failed: {}", e))?;
+  Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+fn locked_dir(state: &DbState, event_id: &str) -> PathBuf {
+  state.data_dir().join(LOCKED_DIR).join(event_id)
+}
+
+pub fn is_locked(conn: &rusqlite::Connection, event_id: &str) -> Result<bool, String> {
+  conn
+    .query_row("SELECT locked FROM timeline_events WHERE id = ?", [event_id], |row| row.get::<_, i32>(0))
+    .map(|locked| locked != 0)
+    .map_err(|_| i18n::t("Event not found"))
+}
+
+/// Encrypt the event's note, text and attachments under `passphrase`. The
+/// passphrase isn't stored anywhere; without it the content is gone.
+#[tauri::command]
+pub fn lock_event(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  event_id: String,
+  passphrase: String,
+) -> Result<(), String> {
+  if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+    return Err(format!("Passphrase must be at least {} characters", MIN_PASSPHRASE_LEN));
+  }
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+
+  let (note, text_content, locked): (Option<String>, Option<String>, i32) = conn
+    .query_row(
+      "SELECT note, text_content, locked, text_compressed, text_codec FROM timeline_events WHERE id = ? AND is_deleted = 0",
+      [&event_id],
+      |row| {
+        let codec: Option<String> = row.get(4)?;
+        Ok((row.get(0)?, compression::inflate(row.get(1)?, row.get(3)?, codec.as_deref()), row.get(2)?))
+      },
+    )
+    .map_err(|_| i18n::t("Event not found"))?;
+  if locked != 0 {
+    return Err("Event is already locked".to_string());
+  }
+
+  let salt = random_bytes::<SALT_LEN>();
+  let cipher = cipher(&passphrase, &salt)?;
+  let attachments = query_attachments(&conn, &event_id)?;
+
+  // Files first, so a failure leaves the event as it was
+  let dir = locked_dir(&state, &event_id);
+  let encrypt_files = || -> Result<Vec<LockedAttachment>, String> {
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let mut locked = Vec::new();
+    for attachment in &attachments {
+      let source = attachment.stored_path.as_deref().unwrap_or(&attachment.original_path);
+      let nonce = match fs::read(source) {
+        Ok(bytes) => {
+          let nonce = random_bytes::<NONCE_LEN>();
+          let sealed = cipher
+            .encrypt(Nonce::from_slice(&nonce), bytes.as_slice())
+            .map_err(|_| "Encryption failed".to_string())?;
+          fs::write(dir.join(&attachment.id), sealed).map_err(|e| e.to_string())?;
+          Some(nonce.to_vec())
+        }
+        Err(_) => None,
+      };
+      locked.push(LockedAttachment { attachment: attachment.clone(), nonce });
+    }
+    Ok(locked)
+  };
+  let locked_attachments = encrypt_files().inspect_err(|_| {
+    let _ = fs::remove_dir_all(&dir);
+  })?;
+
+  let content = LockedContent { note, text_content, attachments: locked_attachments };
+  let plaintext = serde_json::to_vec(&content).map_err(|e| e.to_string())?;
+  let nonce = random_bytes::<NONCE_LEN>();
+  let ciphertext = cipher
+    .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+    .map_err(|_| "Encryption failed".to_string())?;
+
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  tx.execute(
+    "INSERT INTO event_locks (event_id, salt, nonce, ciphertext, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+    rusqlite::params![&event_id, &salt[..], &nonce[..], &ciphertext, now_ms()],
+  )
+  .map_err(|e| e.to_string())?;
+  tx.execute(
+    "UPDATE timeline_events
+     SET note = NULL, text_content = NULL, text_compressed = NULL, text_codec = NULL, locked = 1
+     WHERE id = ?",
+    [&event_id],
+  )
+  .map_err(|e| e.to_string())?;
+  for attachment in &attachments {
+    tx.execute("DELETE FROM attachment_metadata WHERE attachment_id = ?", [&attachment.id])
+      .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM attachments WHERE id = ?", [&attachment.id])
+      .map_err(|e| e.to_string())?;
+  }
+  tx.commit().map_err(|e| e.to_string())?;
+  // Mentions and hashtags came from the note, which is now sealed
+  mentions::index(&conn, &event_id)?;
+  tags::sync_hashtags(&conn, &event_id)?;
+  undo::forget_event(&app, &event_id);
+
+  // Plain copies the app made aren't needed any more
+  let app_data = app.path().app_data_dir().map_err(|e| e.to_string())?;
+  for attachment in &attachments {
+    for file in std::iter::once(&attachment.original_path).chain(attachment.stored_path.as_ref()) {
+      remove_unused_file(&conn, &app_data, file)?;
+    }
+    thumbnails::forget(&app, &attachment.id);
+  }
+  tracing::info!(%event_id, attachments = attachments.len(), "Event locked");
+  Ok(())
+}
+
+/// Decrypt a locked event for viewing. Nothing is written back; the event
+/// stays locked.
+#[tauri::command]
+pub fn unlock_event(
+  state: tauri::State<DbState>,
+  event_id: String,
+  passphrase: String,
+) -> Result<UnlockedEvent, String> {
+  app_lock::ensure_unlocked()?;
+  let (salt, nonce, ciphertext): (Vec<u8>, Vec<u8>, Vec<u8>) = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let sealed = conn
+      .query_row(
+        "SELECT salt, nonce, ciphertext FROM event_locks WHERE event_id = ?",
+        [&event_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+      )
+      .map_err(|_| "Event is not locked".to_string())?;
+    // The event stays locked, so nothing taken from its note may linger;
+    // events locked before hashtags were cleared on lock still carry them
+    mentions::index(&conn, &event_id)?;
+    tags::sync_hashtags(&conn, &event_id)?;
+    sealed
+  };
+
+  let cipher = cipher(&passphrase, &salt)?;
+  let plaintext = cipher
+    .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+    .map_err(|_| "Wrong passphrase".to_string())?;
+  let content: LockedContent = serde_json::from_slice(&plaintext).map_err(|e| e.to_string())?;
+
+  let dir = locked_dir(&state, &event_id);
+  let mut attachments = Vec::new();
+  for locked in content.attachments {
+    let data_url = match &locked.nonce {
+      Some(nonce) => {
+        let sealed = fs::read(dir.join(&locked.attachment.id))
+          .map_err(|_| format!("Encrypted file missing for {}", locked.attachment.id))?;
+        let bytes = cipher
+          .decrypt(Nonce::from_slice(nonce), sealed.as_slice())
+          .map_err(|_| "Encrypted file is damaged".to_string())?;
+        let mime = locked.attachment.mime_type.as_deref().unwrap_or("application/octet-stream");
+        Some(format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(bytes)))
+      }
+      None => None,
+    };
+    attachments.push(UnlockedAttachment { attachment: locked.attachment, data_url });
+  }
+
+  Ok(UnlockedEvent { event_id, note: content.note, text_content: content.text_content, attachments })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn opens_only_with_the_same_passphrase_and_salt() {
+    let salt = random_bytes::<SALT_LEN>();
+    let nonce = random_bytes::<NONCE_LEN>();
+    let sealed = cipher("correct horse", &salt)
+      .unwrap()
+      .encrypt(Nonce::from_slice(&nonce), b"private note".as_slice())
+      .unwrap();
+
+    let opened = cipher("correct horse", &salt).unwrap().decrypt(Nonce::from_slice(&nonce), sealed.as_slice());
+    assert_eq!(opened.unwrap(), b"private note");
+    assert!(cipher("correct horsE", &salt).unwrap().decrypt(Nonce::from_slice(&nonce), sealed.as_slice()).is_err());
+    let other_salt = random_bytes::<SALT_LEN>();
+    assert!(cipher("correct horse", &other_salt).unwrap().decrypt(Nonce::from_slice(&nonce), sealed.as_slice()).is_err());
+  }
+
+  #[test]
+  fn tampered_ciphertext_is_refused() {
+    let salt = random_bytes::<SALT_LEN>();
+    let nonce = random_bytes::<NONCE_LEN>();
+    let cipher = cipher("correct horse", &salt).unwrap();
+    let mut sealed = cipher.encrypt(Nonce::from_slice(&nonce), b"private note".as_slice()).unwrap();
+    sealed[0] ^= 1;
+    assert!(cipher.decrypt(Nonce::from_slice(&nonce), sealed.as_slice()).is_err());
+  }
+}
@@ -0,0 +1,176 @@
+// Where exports are written and what they're called.
+//
+// By default exports go to `exports/` in the active profile's folder as
+// `<date>.md`. The `export.files` setting can point them at another folder
+// (a synced Documents folder, say), name them from a template, and keep the
+// previous file when a day is exported again instead of replacing it.
+//
+// Templates know `{date}`, `{format}`, `{profile}` and `{search}`, the last
+// being the saved search a filtered export was narrowed to. A filtered
+// export whose template leaves `{search}` out gets the name appended, so it
+// never replaces the full day's file. A `custom_path` passed to an export
+// call still wins over the stored folder.
+//
+// Copied attachments stay in `<date>_assets/` next to the file whatever it's
+// called, since the journal repository and the export's own links expect
+// them there.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::Manager;
+
+use crate::{config, profiles, read_setting, sandbox, write_setting, DbState};
+
+pub const EXPORT_FILES_KEY: &str = "export.files";
+const DEFAULT_TEMPLATE: &str = "{date}";
+const PLACEHOLDERS: &[&str] = &["date", "format", "profile", "search"];
+// Versioned names stop at "<name> (999)"
+const MAX_VERSIONS: u32 = 999;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OnConflict {
+  /// Replace an existing export of the same name
+  #[default]
+  Overwrite,
+  /// Keep it and write "<name> (2)" and so on
+  Version,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportFileSettings {
+  /// Absolute folder for exports; `None` uses the active profile's folder
+  directory: Option<String>,
+  /// e.g. "{date}_{format}_{profile}", without extension
+  filename_template: String,
+  on_conflict: OnConflict,
+}
+
+impl Default for ExportFileSettings {
+  fn default() -> Self {
+    Self { directory: None, filename_template: DEFAULT_TEMPLATE.to_string(), on_conflict: OnConflict::Overwrite }
+  }
+}
+
+fn placeholder_re() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| Regex::new(r"\{([^{}]*)\}").expect("valid regex"))
+}
+
+fn validate(settings: ExportFileSettings) -> Result<ExportFileSettings, String> {
+  let directory = settings.directory.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+  if let Some(dir) = &directory {
+    if !Path::new(dir).is_absolute() {
+      return Err(format!("Export folder must be an absolute path: {}", dir));
+    }
+  }
+  let template = settings.filename_template.trim();
+  let template = if template.is_empty() { DEFAULT_TEMPLATE } else { template };
+  for captures in placeholder_re().captures_iter(template) {
+    if !PLACEHOLDERS.contains(&&captures[1]) {
+      return Err(format!("Unknown placeholder {{{}}} (use {{{}}})", &captures[1], PLACEHOLDERS.join("}, {")));
+    }
+  }
+  // Rejects separators and names that clean up to nothing
+  sandbox::safe_file_name(&placeholder_re().replace_all(template, "x"))?;
+  Ok(ExportFileSettings { directory, filename_template: template.to_string(), on_conflict: settings.on_conflict })
+}
+
+fn load(conn: &rusqlite::Connection) -> ExportFileSettings {
+  read_setting(conn, EXPORT_FILES_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .and_then(|settings| validate(settings).ok())
+    .unwrap_or_default()
+}
+
+/// Folder exports go to: `custom_path` if given, else the stored folder,
+/// else `exports/` in the active profile's folder, so profiles don't
+/// overwrite each other's exports. Not created here.
+pub fn exports_dir(
+  app: &tauri::AppHandle,
+  conn: &rusqlite::Connection,
+  custom_path: Option<&str>,
+) -> Result<PathBuf, String> {
+  if let Some(custom) = custom_path.filter(|p| !p.is_empty()) {
+    return Ok(PathBuf::from(custom));
+  }
+  match load(conn).directory {
+    Some(dir) => Ok(PathBuf::from(dir)),
+    None => Ok(app.state::<DbState>().data_dir().join("exports")),
+  }
+}
+
+fn render(template: &str, date_key: &str, format: &str, profile: &str, search: Option<&str>) -> String {
+  let name = placeholder_re().replace_all(template, |captures: &regex::Captures| match &captures[1] {
+    "date" => date_key.to_string(),
+    "format" => format.to_string(),
+    "profile" => profile.to_string(),
+    _ => search.unwrap_or_default().to_string(),
+  });
+  let mut name = name.to_string();
+  if let Some(search) = search.filter(|_| !template.contains("{search}")) {
+    name = format!("{}_{}", name, search);
+  }
+  // An empty {search} leaves separators behind
+  while name.contains("__") {
+    name = name.replace("__", "_");
+  }
+  name.trim_matches(|c: char| c == '_' || c == '-' || c.is_whitespace()).to_string()
+}
+
+/// Path to write an export of `date_key` to in `dir`, following the stored
+/// template and conflict setting. `search` names the saved search a filtered
+/// export was narrowed to.
+pub fn output_path(
+  app: &tauri::AppHandle,
+  conn: &rusqlite::Connection,
+  dir: &Path,
+  date_key: &str,
+  format: &str,
+  search: Option<&str>,
+) -> Result<PathBuf, String> {
+  let settings = load(conn);
+  let profile = profiles::active_name(&app.state::<DbState>(), app);
+  let ext = if format == "html" { "html" } else { "md" };
+  let stem = render(&settings.filename_template, date_key, format, &profile, search);
+  let stem = if stem.is_empty() { date_key.to_string() } else { stem };
+  let path = dir.join(sandbox::safe_file_name(&format!("{}.{}", stem, ext))?);
+  if settings.on_conflict == OnConflict::Overwrite || !path.exists() {
+    return Ok(path);
+  }
+  (2..=MAX_VERSIONS)
+    .map(|n| dir.join(format!("{} ({}).{}", stem, n, ext)))
+    .find(|candidate| !candidate.exists())
+    .ok_or_else(|| format!("Too many versions of {}.{}", stem, ext))
+}
+
+#[tauri::command]
+pub fn get_export_file_settings(state: tauri::State<DbState>) -> Result<ExportFileSettings, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load(&conn))
+}
+
+#[tauri::command]
+pub fn set_export_file_settings(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  settings: ExportFileSettings,
+) -> Result<ExportFileSettings, String> {
+  let settings = validate(settings)?;
+  if let Some(dir) = &settings.directory {
+    fs::create_dir_all(dir).map_err(|e| format!("Export folder can't be created: {}", e))?;
+  }
+  let json = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, EXPORT_FILES_KEY, &json)?;
+  }
+  config::emit_changed(&app, vec![EXPORT_FILES_KEY.to_string()]);
+  Ok(settings)
+}
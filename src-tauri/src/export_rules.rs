@@ -0,0 +1,166 @@
+// Include/exclude rules for exports.
+//
+// Defaults are stored under `export.rules`; each export call can override
+// any of the lists. The rules become extra `WHERE` conditions on the export
+// query, so excluded events never reach the output. A tag counts the same way
+// as in search: attached to the event or written as a `#hashtag` in its text.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{archive, compression, config, event_from_row, read_setting, search, tags, timezone, write_setting, DbState, TimelineEvent};
+
+pub const EXPORT_RULES_KEY: &str = "export.rules";
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportRules {
+  /// e.g. ["private"]; a leading `#` is ignored
+  exclude_tags: Vec<String>,
+  /// Event types, e.g. ["audio"]
+  exclude_types: Vec<String>,
+  /// Event sources, e.g. ["clipboard"]
+  exclude_sources: Vec<String>,
+}
+
+/// Per-call changes to the stored rules. A list that is present replaces the
+/// stored one, so `[]` turns that kind of exclusion off for the call.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportRulesOverride {
+  exclude_tags: Option<Vec<String>>,
+  exclude_types: Option<Vec<String>>,
+  exclude_sources: Option<Vec<String>>,
+}
+
+fn normalize(rules: ExportRules) -> ExportRules {
+  let mut tags: Vec<String> = rules.exclude_tags.iter().filter_map(|t| tags::normalize(t)).collect();
+  tags.sort();
+  tags.dedup();
+  let clean = |values: Vec<String>| -> Vec<String> {
+    values
+      .into_iter()
+      .map(|v| v.trim().to_string())
+      .filter(|v| !v.is_empty())
+      .collect()
+  };
+  ExportRules {
+    exclude_tags: tags,
+    exclude_types: clean(rules.exclude_types),
+    exclude_sources: clean(rules.exclude_sources),
+  }
+}
+
+fn load(conn: &rusqlite::Connection) -> ExportRules {
+  read_setting(conn, EXPORT_RULES_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .map(normalize)
+    .unwrap_or_default()
+}
+
+/// The stored rules with `overrides` applied.
+pub fn resolve(conn: &rusqlite::Connection, overrides: Option<ExportRulesOverride>) -> ExportRules {
+  let stored = load(conn);
+  let Some(overrides) = overrides else { return stored };
+  normalize(ExportRules {
+    exclude_tags: overrides.exclude_tags.unwrap_or(stored.exclude_tags),
+    exclude_types: overrides.exclude_types.unwrap_or(stored.exclude_types),
+    exclude_sources: overrides.exclude_sources.unwrap_or(stored.exclude_sources),
+  })
+}
+
+/// Append the conditions for `rules` to a query over events aliased `e`.
+fn push_conditions(sql: &mut String, params: &mut Vec<Box<dyn rusqlite::ToSql>>, rules: &ExportRules) {
+  if !rules.exclude_types.is_empty() {
+    sql.push_str(&format!(" AND e.type NOT IN ({})", vec!["?"; rules.exclude_types.len()].join(", ")));
+    for event_type in &rules.exclude_types {
+      params.push(Box::new(event_type.clone()));
+    }
+  }
+  if !rules.exclude_sources.is_empty() {
+    sql.push_str(&format!(
+      " AND COALESCE(e.source, '') NOT IN ({})",
+      vec!["?"; rules.exclude_sources.len()].join(", ")
+    ));
+    for source in &rules.exclude_sources {
+      params.push(Box::new(source.clone()));
+    }
+  }
+  for tag in &rules.exclude_tags {
+    let hashtag: Vec<char> = format!("#{}", tag).chars().collect();
+    sql.push_str(&format!(
+      " AND NOT EXISTS (SELECT 1 FROM event_tags et JOIN tags t ON t.id = et.tag_id
+                        WHERE et.event_id = e.id AND t.name = ?)
+        AND LOWER(COALESCE(e.title, '')) NOT LIKE ? ESCAPE '\\'
+        AND LOWER(COALESCE(e.note, '')) NOT LIKE ? ESCAPE '\\'
+        AND LOWER(COALESCE({}, '')) NOT LIKE ? ESCAPE '\\'",
+      compression::FULL_TEXT_SQL
+    ));
+    params.push(Box::new(tag.clone()));
+    let pattern = search::like_pattern(&hashtag);
+    for _ in 0..3 {
+      params.push(Box::new(pattern.clone()));
+    }
+  }
+}
+
+/// Events on the "YYYY-MM-DD" day `date_key` that `rules` let through,
+/// oldest first. Each event's day is counted in the offset recorded when it
+/// was captured, see timezone.rs. Old days may already be archived, so both
+/// tables are read.
+pub fn query_day(
+  conn: &rusqlite::Connection,
+  date_key: &str,
+  rules: &ExportRules,
+) -> Result<Vec<TimelineEvent>, String> {
+  let (start, end) = timezone::day_bounds(date_key)?;
+  let mut sql = format!(
+    "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
+     FROM {} e
+     WHERE created_at >= ? AND created_at <= ? AND date(created_at / 1000, 'unixepoch', {}) = ?
+       AND is_deleted = 0 AND scheduled_for IS NULL",
+    archive::events_table(true),
+    timezone::event_modifier_sql("?")
+  );
+  let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![
+    Box::new(start - timezone::DAY_MARGIN_MS),
+    Box::new(end + timezone::DAY_MARGIN_MS),
+    Box::new(timezone::sql_modifier()),
+    Box::new(date_key.to_string()),
+  ];
+  push_conditions(&mut sql, &mut params, rules);
+  sql.push_str(" ORDER BY created_at ASC");
+
+  let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+  let events = conn
+    .prepare(&sql)
+    .map_err(|e| e.to_string())?
+    .query_map(params_refs.as_slice(), event_from_row)
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(events)
+}
+
+#[tauri::command]
+pub fn get_export_rules(state: tauri::State<DbState>) -> Result<ExportRules, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load(&conn))
+}
+
+#[tauri::command]
+pub fn set_export_rules(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  rules: ExportRules,
+) -> Result<ExportRules, String> {
+  let rules = normalize(rules);
+  let json = serde_json::to_string(&rules).map_err(|e| e.to_string())?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, EXPORT_RULES_KEY, &json)?;
+  }
+  config::emit_changed(&app, vec![EXPORT_RULES_KEY.to_string()]);
+  Ok(rules)
+}
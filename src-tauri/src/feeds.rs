@@ -0,0 +1,406 @@
+// RSS and Atom subscriptions.
+//
+// `subscribe_feed` follows a feed (a blog, or a newsletter that publishes
+// one) and every 30 minutes new items become `link` events with
+// `source = 'feed'`. A feed can have keywords; then only items whose title or
+// summary contains one of them come in. Each imported item is tagged with the
+// feed's name and the keywords it matched, and auto-tagging runs on it like on
+// any new event.
+//
+// Every item seen is remembered in `feed_items`, imported or not, so changing
+// the keywords only affects items published afterwards. The first fetch of a
+// new subscription brings in at most the latest few matching items instead of
+// the feed's whole history.
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::json;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::permissions::{self, Permission};
+use crate::{connectivity, generate_id, now_ms, supervisor, tags, usage_metrics, DbState};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+const FIRST_FETCH_LIMIT: usize = 10;
+const MAX_SUMMARY_CHARS: usize = 280;
+const MAX_KEYWORDS: usize = 50;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Feed {
+  id: String,
+  url: String,
+  title: Option<String>,
+  /// Items must mention one of these; empty takes every item
+  keywords: Vec<String>,
+  last_fetched_at: Option<i64>,
+  last_error: Option<String>,
+  created_at: i64,
+  /// Link events created from this feed so far
+  imported: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedRefresh {
+  feeds: usize,
+  imported: usize,
+  failed: usize,
+}
+
+struct FeedItem {
+  guid: String,
+  title: String,
+  link: String,
+  summary: Option<String>,
+  author: Option<String>,
+  published_at: Option<i64>,
+}
+
+struct ParsedFeed {
+  title: Option<String>,
+  items: Vec<FeedItem>,
+}
+
+fn tag_regex() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| Regex::new(r"(?s)<[^>]*>").unwrap())
+}
+
+/// Plain text of an HTML summary, cut to a note-sized snippet.
+fn plain_text(html: &str) -> String {
+  let text = tag_regex().replace_all(html, " ");
+  let text = text
+    .replace("&nbsp;", " ")
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&quot;", "\"")
+    .replace("&#39;", "'")
+    .replace("&amp;", "&");
+  let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+  match text.char_indices().nth(MAX_SUMMARY_CHARS) {
+    Some((cut, _)) => format!("{}…", text[..cut].trim_end()),
+    None => text,
+  }
+}
+
+fn date_ms(text: &str) -> Option<i64> {
+  chrono::DateTime::parse_from_rfc2822(text)
+    .or_else(|_| chrono::DateTime::parse_from_rfc3339(text))
+    .ok()
+    .map(|t| t.timestamp_millis())
+}
+
+fn parse_feed(bytes: &[u8]) -> Result<ParsedFeed, String> {
+  if let Ok(channel) = rss::Channel::read_from(bytes) {
+    let items = channel
+      .items()
+      .iter()
+      .filter_map(|item| {
+        let link = item.link()?.trim().to_string();
+        let guid = item.guid().map(|g| g.value().to_string()).unwrap_or_else(|| link.clone());
+        Some(FeedItem {
+          guid,
+          title: item.title().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).unwrap_or_else(|| link.clone()),
+          link,
+          summary: item.description().map(plain_text).filter(|s| !s.is_empty()),
+          author: item.author().or_else(|| item.dublin_core_ext().and_then(|dc| dc.creators().first()).map(|c| c.as_str()))
+            .map(str::to_string),
+          published_at: item.pub_date().and_then(date_ms),
+        })
+      })
+      .collect();
+    return Ok(ParsedFeed { title: Some(channel.title().trim().to_string()).filter(|t| !t.is_empty()), items });
+  }
+
+  let feed = atom_syndication::Feed::read_from(bytes).map_err(|_| "Not an RSS or Atom feed".to_string())?;
+  let items = feed
+    .entries()
+    .iter()
+    .filter_map(|entry| {
+      let link = entry
+        .links()
+        .iter()
+        .find(|l| l.rel() == "alternate")
+        .or_else(|| entry.links().first())?
+        .href()
+        .trim()
+        .to_string();
+      let summary = entry.summary().map(|s| s.as_str()).or_else(|| entry.content().and_then(|c| c.value()));
+      Some(FeedItem {
+        guid: entry.id().to_string(),
+        title: Some(entry.title().as_str().trim().to_string()).filter(|t| !t.is_empty()).unwrap_or_else(|| link.clone()),
+        link,
+        summary: summary.map(plain_text).filter(|s| !s.is_empty()),
+        author: entry.authors().first().map(|a| a.name().to_string()),
+        published_at: Some(entry.published().unwrap_or(entry.updated()).timestamp_millis()),
+      })
+    })
+    .collect();
+  Ok(ParsedFeed { title: Some(feed.title().as_str().trim().to_string()).filter(|t| !t.is_empty()), items })
+}
+
+async fn download(url: &str) -> Result<ParsedFeed, String> {
+  permissions::ensure(Permission::Network)?;
+  let client = connectivity::client_builder().timeout(HTTP_TIMEOUT).user_agent("papa").build().map_err(|e| e.to_string())?;
+  let response = client.get(url).send().await.map_err(|e| connectivity::request_error("Feed request failed", e))?;
+  if !response.status().is_success() {
+    return Err(format!("Feed server returned {}", response.status()));
+  }
+  let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+  parse_feed(&bytes)
+}
+
+fn clean_keywords(keywords: Vec<String>) -> Result<Vec<String>, String> {
+  let mut cleaned: Vec<String> = Vec::new();
+  for keyword in keywords {
+    let keyword = keyword.trim().to_lowercase();
+    if !keyword.is_empty() && !cleaned.contains(&keyword) {
+      cleaned.push(keyword);
+    }
+  }
+  if cleaned.len() > MAX_KEYWORDS {
+    return Err(format!("At most {} keywords per feed", MAX_KEYWORDS));
+  }
+  Ok(cleaned)
+}
+
+/// The keywords `item` mentions; `None` if it doesn't pass the filter.
+fn matches(item: &FeedItem, keywords: &[String]) -> Option<Vec<String>> {
+  if keywords.is_empty() {
+    return Some(Vec::new());
+  }
+  let text = format!("{} {}", item.title, item.summary.as_deref().unwrap_or_default()).to_lowercase();
+  let found: Vec<String> = keywords.iter().filter(|k| text.contains(k.as_str())).cloned().collect();
+  (!found.is_empty()).then_some(found)
+}
+
+/// Record the items of `feed_id` not seen before and turn the matching ones
+/// into link events. Returns how many were imported.
+fn import_items(
+  conn: &mut rusqlite::Connection,
+  feed_id: &str,
+  parsed: &ParsedFeed,
+  first_fetch: bool,
+) -> Result<usize, String> {
+  let (title, keywords): (Option<String>, String) = conn
+    .query_row("SELECT title, keywords FROM feeds WHERE id = ?", [feed_id], |row| Ok((row.get(0)?, row.get(1)?)))
+    .map_err(|_| "Feed not found".to_string())?;
+  let keywords: Vec<String> = serde_json::from_str(&keywords).unwrap_or_default();
+  let feed_title = title.or_else(|| parsed.title.clone());
+
+  let mut items: Vec<&FeedItem> = parsed.items.iter().collect();
+  items.sort_by_key(|item| std::cmp::Reverse(item.published_at));
+  let now = now_ms();
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  let mut imported = 0;
+  for item in items {
+    let seen = tx
+      .execute(
+        "INSERT OR IGNORE INTO feed_items (feed_id, guid, seen_at) VALUES (?1, ?2, ?3)",
+        (feed_id, &item.guid, now),
+      )
+      .map_err(|e| e.to_string())?
+      == 0;
+    let Some(matched) = matches(item, &keywords) else { continue };
+    if seen || (first_fetch && imported >= FIRST_FETCH_LIMIT) {
+      continue;
+    }
+
+    let event_id = generate_id();
+    let metadata = json!({
+      "url": item.link,
+      "feed": { "id": feed_id, "title": feed_title },
+      "author": item.author,
+      "matchedKeywords": matched,
+    });
+    tx.execute(
+      "INSERT INTO timeline_events (id, type, title, note, text_content, created_at, source, is_deleted, metadata)
+       VALUES (?1, 'link', ?2, ?3, ?4, ?5, 'feed', 0, ?6)",
+      (
+        &event_id,
+        &item.title,
+        &item.summary,
+        &item.link,
+        // Feeds sometimes date items in the future
+        item.published_at.unwrap_or(now).min(now),
+        metadata.to_string(),
+      ),
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute("UPDATE feed_items SET event_id = ?1 WHERE feed_id = ?2 AND guid = ?3", (&event_id, feed_id, &item.guid))
+      .map_err(|e| e.to_string())?;
+    let mut names: Vec<String> = feed_title.iter().cloned().collect();
+    names.extend(matched);
+    tags::apply(&tx, &event_id, &names, "auto")?;
+    tags::auto_tag(&tx, &event_id);
+    imported += 1;
+  }
+  tx.execute(
+    "UPDATE feeds SET title = COALESCE(title, ?1), last_fetched_at = ?2, last_error = NULL WHERE id = ?3",
+    (&parsed.title, now, feed_id),
+  )
+  .map_err(|e| e.to_string())?;
+  tx.commit().map_err(|e| e.to_string())?;
+  Ok(imported)
+}
+
+async fn refresh(app: &tauri::AppHandle, feed_id: &str, url: &str) -> Result<usize, String> {
+  let result = download(url).await;
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+  match result {
+    Ok(parsed) => import_items(&mut conn, feed_id, &parsed, false),
+    Err(error) => {
+      conn
+        .execute("UPDATE feeds SET last_error = ?1 WHERE id = ?2", (&error, feed_id))
+        .map_err(|e| e.to_string())?;
+      Err(error)
+    }
+  }
+}
+
+async fn refresh_all(app: &tauri::AppHandle) -> Result<FeedRefresh, String> {
+  let feeds: Vec<(String, String)> = {
+    let state = app.state::<DbState>();
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let feeds = conn
+      .prepare("SELECT id, url FROM feeds")
+      .map_err(|e| e.to_string())?
+      .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+      .map_err(|e| e.to_string())?
+      .filter_map(|r| r.ok())
+      .collect();
+    feeds
+  };
+  let mut result = FeedRefresh { feeds: feeds.len(), imported: 0, failed: 0 };
+  for (id, url) in feeds {
+    match refresh(app, &id, &url).await {
+      Ok(imported) => result.imported += imported,
+      Err(e) => {
+        result.failed += 1;
+        tracing::warn!(feed = %id, error = %e, "Feed refresh failed");
+      }
+    }
+  }
+  if result.imported > 0 {
+    tracing::info!(imported = result.imported, "Imported feed items");
+  }
+  Ok(result)
+}
+
+pub fn spawn_feed_poller(app: tauri::AppHandle) {
+  supervisor::supervise(app, "feeds", |app| async move {
+    loop {
+      connectivity::sleep(REFRESH_INTERVAL).await;
+      if !connectivity::reachable() {
+        continue;
+      }
+      if let Err(e) = refresh_all(&app).await {
+        tracing::warn!(error = %e, "Feed refresh failed");
+      }
+    }
+  });
+}
+
+fn query_feeds(conn: &rusqlite::Connection, feed_id: Option<&str>) -> Result<Vec<Feed>, String> {
+  let feeds = conn
+    .prepare(
+      "SELECT f.id, f.url, f.title, f.keywords, f.last_fetched_at, f.last_error, f.created_at,
+              (SELECT COUNT(*) FROM feed_items i WHERE i.feed_id = f.id AND i.event_id IS NOT NULL)
+       FROM feeds f WHERE ?1 IS NULL OR f.id = ?1 ORDER BY f.created_at",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([feed_id], |row| {
+      let keywords: String = row.get(3)?;
+      Ok(Feed {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        title: row.get(2)?,
+        keywords: serde_json::from_str(&keywords).unwrap_or_default(),
+        last_fetched_at: row.get(4)?,
+        last_error: row.get(5)?,
+        created_at: row.get(6)?,
+        imported: row.get(7)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(feeds)
+}
+
+#[tauri::command]
+pub async fn subscribe_feed(app: tauri::AppHandle, url: String, keywords: Option<Vec<String>>) -> Result<Feed, String> {
+  let _timer = usage_metrics::time_async("subscribe_feed");
+  let url = url.trim().replacen("feed://", "https://", 1);
+  if !url.starts_with("https://") && !url.starts_with("http://") {
+    return Err("Feed URL must be http(s)".to_string());
+  }
+  let keywords = clean_keywords(keywords.unwrap_or_default())?;
+  let parsed = download(&url).await?;
+
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+  let feed_id = generate_id();
+  let inserted = conn
+    .execute(
+      "INSERT OR IGNORE INTO feeds (id, url, keywords, created_at) VALUES (?1, ?2, ?3, ?4)",
+      (&feed_id, &url, serde_json::to_string(&keywords).map_err(|e| e.to_string())?, now_ms()),
+    )
+    .map_err(|e| e.to_string())?;
+  if inserted == 0 {
+    return Err("Already subscribed to this feed".to_string());
+  }
+  let imported = import_items(&mut conn, &feed_id, &parsed, true)?;
+  tracing::info!(feed = %feed_id, imported, "Subscribed to feed");
+  query_feeds(&conn, Some(&feed_id))?.pop().ok_or_else(|| "Feed not found".to_string())
+}
+
+/// Replace a feed's keyword filter. Applies to items published from now on.
+#[tauri::command]
+pub fn set_feed_keywords(state: tauri::State<DbState>, feed_id: String, keywords: Vec<String>) -> Result<Feed, String> {
+  let keywords = clean_keywords(keywords)?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  conn
+    .execute(
+      "UPDATE feeds SET keywords = ?1 WHERE id = ?2",
+      (serde_json::to_string(&keywords).map_err(|e| e.to_string())?, &feed_id),
+    )
+    .map_err(|e| e.to_string())?;
+  query_feeds(&conn, Some(&feed_id))?.pop().ok_or_else(|| "Feed not found".to_string())
+}
+
+/// Stop following a feed. Link events already created from it stay.
+#[tauri::command]
+pub fn unsubscribe_feed(state: tauri::State<DbState>, feed_id: String) -> Result<(), String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  conn.execute("DELETE FROM feed_items WHERE feed_id = ?", [&feed_id]).map_err(|e| e.to_string())?;
+  let removed = conn.execute("DELETE FROM feeds WHERE id = ?", [&feed_id]).map_err(|e| e.to_string())?;
+  if removed == 0 {
+    return Err("Feed not found".to_string());
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub fn list_feeds(state: tauri::State<DbState>) -> Result<Vec<Feed>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  query_feeds(&conn, None)
+}
+
+/// Fetch every feed now instead of waiting for the next refresh.
+#[tauri::command]
+pub async fn refresh_feeds(app: tauri::AppHandle) -> Result<FeedRefresh, String> {
+  let _timer = usage_metrics::time_async("refresh_feeds");
+  refresh_all(&app).await
+}
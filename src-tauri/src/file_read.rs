@@ -0,0 +1,124 @@
+// Reading dropped files back for the frontend (LLM context, previews).
+//
+// Text is decoded with encoding detection so legacy files (GBK, Shift_JIS,
+// Windows-1252...) come back readable instead of failing as invalid UTF-8.
+// Binary content is served as base64 in ranges. Both go through the sandbox.
+
+use base64::Engine;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use crate::{app_lock, ingest, sandbox, usage_metrics, DbState};
+
+// Upper bound for a single `read_file_bytes` range
+const MAX_CHUNK_BYTES: u64 = 4 * 1024 * 1024;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextFileContent {
+  content: String,
+  /// WHATWG name of the encoding used, e.g. "UTF-8" or "GBK"
+  encoding: String,
+  /// True when some bytes couldn't be decoded and were replaced
+  lossy: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileBytes {
+  /// Base64 of the requested range
+  data: String,
+  offset: u64,
+  length: u64,
+  total_size: u64,
+  eof: bool,
+}
+
+/// Sandbox check plus the configured text size limit, under the DB lock.
+fn open_checked(
+  app: &tauri::AppHandle,
+  state: &DbState,
+  file_path: &str,
+) -> Result<(PathBuf, u64), String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let path = sandbox::check_readable(app, &conn, file_path)?;
+  Ok((path, ingest::load_policy(&conn).max_text_bytes))
+}
+
+/// Decode bytes as text: BOM first, then UTF-8, then chardetng's guess.
+fn decode_text(bytes: &[u8]) -> TextFileContent {
+  let encoding = match encoding_rs::Encoding::for_bom(bytes) {
+    Some((encoding, _)) => encoding,
+    None if std::str::from_utf8(bytes).is_ok() => encoding_rs::UTF_8,
+    None => {
+      let mut detector = chardetng::EncodingDetector::new();
+      detector.feed(bytes, true);
+      detector.guess(None, true)
+    }
+  };
+
+  let (content, used, lossy) = encoding.decode(bytes);
+  TextFileContent {
+    content: content.into_owned(),
+    encoding: used.name().to_string(),
+    lossy,
+  }
+}
+
+#[tauri::command]
+pub async fn read_file_content(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, DbState>,
+  file_path: String,
+) -> Result<TextFileContent, String> {
+  let _timer = usage_metrics::time_async("read_file_content");
+  app_lock::ensure_unlocked()?;
+  let (path, max_text_bytes) = open_checked(&app, &state, &file_path)?;
+
+  let metadata = fs::metadata(&path).map_err(|e| format!("Failed to read file metadata: {}", e))?;
+  if metadata.len() > max_text_bytes {
+    return Err(format!(
+      "File too large ({} bytes, max {}); use read_file_bytes instead",
+      metadata.len(),
+      max_text_bytes
+    ));
+  }
+
+  let bytes = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+  Ok(decode_text(&bytes))
+}
+
+/// Read `length` bytes (default: up to 4MB) starting at `offset`, base64
+/// encoded. Call repeatedly with the next offset to stream large files.
+#[tauri::command]
+pub async fn read_file_bytes(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, DbState>,
+  file_path: String,
+  offset: Option<u64>,
+  length: Option<u64>,
+) -> Result<FileBytes, String> {
+  let _timer = usage_metrics::time_async("read_file_bytes");
+  app_lock::ensure_unlocked()?;
+  let (path, _) = open_checked(&app, &state, &file_path)?;
+
+  let mut file = File::open(&path).map_err(|e| format!("Failed to open file: {}", e))?;
+  let total_size = file.metadata().map_err(|e| e.to_string())?.len();
+  let offset = offset.unwrap_or(0).min(total_size);
+  let length = length.unwrap_or(MAX_CHUNK_BYTES).min(MAX_CHUNK_BYTES).min(total_size - offset);
+
+  file.seek(SeekFrom::Start(offset)).map_err(|e| e.to_string())?;
+  let mut buffer = vec![0u8; length as usize];
+  file.read_exact(&mut buffer).map_err(|e| format!("Failed to read file: {}", e))?;
+
+  Ok(FileBytes {
+    data: base64::engine::general_purpose::STANDARD.encode(&buffer),
+    offset,
+    length,
+    total_size,
+    eof: offset + length >= total_size,
+  })
+}
@@ -0,0 +1,220 @@
+// Key-combo gestures such as triple-tapping Ctrl to summon the pet.
+//
+// The behavior monitor already polls the keyboard, so it hands every sample
+// to the detector here instead of installing a separate global hook.
+
+use device_query::Keycode;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+use crate::{pet_window, read_setting, write_setting, DbState};
+
+pub const GESTURES_KEY: &str = "gestures";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GestureKey {
+  Ctrl,
+  Shift,
+  Alt,
+  Meta,
+}
+
+impl GestureKey {
+  fn matches(self, key: &Keycode) -> bool {
+    matches!(
+      (self, key),
+      (GestureKey::Ctrl, Keycode::LControl | Keycode::RControl)
+        | (GestureKey::Shift, Keycode::LShift | Keycode::RShift)
+        | (GestureKey::Alt, Keycode::LAlt | Keycode::RAlt)
+        | (GestureKey::Meta, Keycode::LMeta | Keycode::RMeta)
+    )
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum GestureAction {
+  /// Show and focus the pet window, then emit `summon-pet`
+  Summon,
+  /// Hide the pet window if visible, show it otherwise
+  ToggleVisibility,
+  /// Turn click-through off if on, on otherwise
+  ToggleClickThrough,
+  /// Emit an arbitrary event to the frontend
+  Emit { event: String },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Gesture {
+  key: GestureKey,
+  taps: u32,
+  #[serde(default = "default_within_ms")]
+  within_ms: u64,
+  action: GestureAction,
+}
+
+fn default_within_ms() -> u64 {
+  800
+}
+
+fn default_gestures() -> Vec<Gesture> {
+  vec![
+    Gesture {
+      key: GestureKey::Ctrl,
+      taps: 3,
+      within_ms: default_within_ms(),
+      action: GestureAction::Summon,
+    },
+    Gesture {
+      key: GestureKey::Alt,
+      taps: 3,
+      within_ms: default_within_ms(),
+      action: GestureAction::ToggleClickThrough,
+    },
+  ]
+}
+
+#[derive(Default)]
+struct TapTracker {
+  key: Option<GestureKey>,
+  taps: Vec<Instant>,
+}
+
+#[derive(Default)]
+pub struct GestureState {
+  gestures: Mutex<Vec<Gesture>>,
+  tracker: Mutex<TapTracker>,
+}
+
+fn parse_gestures(json: &str) -> Result<Vec<Gesture>, String> {
+  let gestures: Vec<Gesture> =
+    serde_json::from_str(json).map_err(|e| format!("Invalid gestures: {}", e))?;
+  if gestures.iter().any(|g| g.taps < 2) {
+    return Err("Gestures need at least 2 taps".to_string());
+  }
+  Ok(gestures)
+}
+
+/// Load gestures from settings (defaults when unset).
+pub fn load_gestures(app: &tauri::AppHandle) {
+  let stored = {
+    let state = app.state::<DbState>();
+    let Ok(_guard) = state.lock.lock() else { return };
+    state.open()
+      .ok()
+      .and_then(|conn| read_setting(&conn, GESTURES_KEY))
+  };
+
+  let gestures = stored
+    .and_then(|json| parse_gestures(&json).map_err(|e| tracing::warn!("{}", e)).ok())
+    .unwrap_or_else(default_gestures);
+
+  if let Ok(mut current) = app.state::<GestureState>().gestures.lock() {
+    *current = gestures;
+  }
+}
+
+fn run_action(app: &tauri::AppHandle, action: &GestureAction) {
+  let Some(window) = app.get_webview_window("main") else { return };
+  match action {
+    GestureAction::Summon => {
+      let _ = window.show();
+      let _ = window.set_focus();
+      let _ = window.emit("summon-pet", ());
+    }
+    GestureAction::ToggleVisibility => {
+      if window.is_visible().unwrap_or(false) {
+        let _ = window.hide();
+      } else {
+        let _ = window.show();
+        let _ = window.set_focus();
+      }
+    }
+    GestureAction::ToggleClickThrough => {
+      let _ = pet_window::toggle_click_through(app);
+    }
+    GestureAction::Emit { event } => {
+      let _ = window.emit(event, ());
+    }
+  }
+}
+
+/// Feed one keyboard sample. `pressed` is the set of keys that went down
+/// since the previous sample, `held` is everything currently held.
+pub fn observe_keys(app: &tauri::AppHandle, pressed: &[Keycode], held: &[Keycode]) {
+  if pressed.is_empty() {
+    return;
+  }
+
+  let state = app.state::<GestureState>();
+  let Ok(gestures) = state.gestures.lock() else { return };
+  let Ok(mut tracker) = state.tracker.lock() else { return };
+  let now = Instant::now();
+
+  // A tap only counts when the modifier is pressed on its own
+  let tapped = if held.len() == 1 {
+    [GestureKey::Ctrl, GestureKey::Shift, GestureKey::Alt, GestureKey::Meta]
+      .into_iter()
+      .find(|k| k.matches(&held[0]))
+  } else {
+    None
+  };
+
+  let Some(key) = tapped else {
+    tracker.key = None;
+    tracker.taps.clear();
+    return;
+  };
+
+  if tracker.key != Some(key) {
+    tracker.key = Some(key);
+    tracker.taps.clear();
+  }
+  tracker.taps.push(now);
+
+  let fired = gestures.iter().find(|g| {
+    g.key == key
+      && tracker.taps.len() >= g.taps as usize
+      && tracker.taps[tracker.taps.len() - g.taps as usize..]
+        .first()
+        .map(|first| now.duration_since(*first) <= Duration::from_millis(g.within_ms))
+        .unwrap_or(false)
+  });
+
+  if let Some(gesture) = fired {
+    let action = gesture.action.clone();
+    tracker.taps.clear();
+    drop(tracker);
+    drop(gestures);
+    run_action(app, &action);
+  }
+}
+
+#[tauri::command]
+pub fn get_gestures(state: tauri::State<GestureState>) -> Result<Vec<Gesture>, String> {
+  let gestures = state.gestures.lock().map_err(|_| "gesture lock".to_string())?;
+  Ok(gestures.clone())
+}
+
+#[tauri::command]
+pub fn set_gestures(
+  db: tauri::State<DbState>,
+  state: tauri::State<GestureState>,
+  gestures: Vec<Gesture>,
+) -> Result<(), String> {
+  let json = serde_json::to_string(&gestures).map_err(|e| e.to_string())?;
+  let gestures = parse_gestures(&json)?;
+
+  {
+    let _guard = db.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = db.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, GESTURES_KEY, &json)?;
+  }
+
+  *state.gestures.lock().map_err(|_| "gesture lock".to_string())? = gestures;
+  Ok(())
+}
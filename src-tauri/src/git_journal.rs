@@ -0,0 +1,260 @@
+// Markdown exports kept in a git repository.
+//
+// When enabled, every full Markdown export (`<date>.md` plus its
+// `<date>_assets` folder) is copied into a repository owned by the app and
+// committed, and optionally pushed to a remote. That gives a versioned copy
+// of the journal that any git host or editor can read.
+//
+// The repository is driven through git2, so no git install is needed. The
+// repository work runs on a thread of its own, one export at a time, so an
+// export doesn't hold the database lock while git copies and commits. Pushes
+// go through the job queue (`git_push`), which retries them and holds them
+// while offline; they authenticate with the SSH agent or the user's git
+// credential helper.
+
+use git2::{Cred, CredentialType, IndexAddOption, PushOptions, RemoteCallbacks, Repository, RepositoryInitOptions, Signature};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::permissions::{self, Permission};
+use crate::{config, connectivity, jobs, read_setting, write_setting, DbState};
+
+pub const GIT_JOURNAL_KEY: &str = "export.git";
+const DEFAULT_BRANCH: &str = "main";
+// libgit2 asks again after a rejected credential; give up rather than loop
+const MAX_AUTH_ATTEMPTS: u32 = 3;
+
+// Held while a repository is being written, so commits don't race on its index
+static REPO_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GitJournalConfig {
+  enabled: bool,
+  /// Defaults to `journal` in the active profile's folder
+  repo_path: Option<String>,
+  /// Remote URL for `origin`; nothing is pushed without one
+  remote_url: Option<String>,
+  push: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GitJournalStatus {
+  config: GitJournalConfig,
+  /// The repository in use
+  repo_path: String,
+  /// Subject of the latest commit
+  last_commit: Option<String>,
+}
+
+fn git_error(e: git2::Error) -> String {
+  format!("git: {}", e.message())
+}
+
+fn load_config(conn: &rusqlite::Connection) -> GitJournalConfig {
+  read_setting(conn, GIT_JOURNAL_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn repo_dir(app: &tauri::AppHandle, config: &GitJournalConfig) -> Result<PathBuf, String> {
+  match config.repo_path.as_deref().filter(|p| !p.trim().is_empty()) {
+    Some(path) => Ok(PathBuf::from(path)),
+    // Per profile, so two profiles never share a repo or its `origin`
+    None => Ok(app.state::<DbState>().data_dir().join("journal")),
+  }
+}
+
+/// Open the repository, creating it if needed, and point `origin` at the
+/// configured remote.
+fn prepare_repo(path: &Path, config: &GitJournalConfig) -> Result<Repository, String> {
+  fs::create_dir_all(path).map_err(|e| e.to_string())?;
+  let repo = match Repository::open(path) {
+    Ok(repo) => repo,
+    Err(_) => Repository::init_opts(path, RepositoryInitOptions::new().initial_head(DEFAULT_BRANCH))
+      .map_err(git_error)?,
+  };
+
+  let current = repo.find_remote("origin").ok().and_then(|r| r.url().map(str::to_string));
+  match (config.remote_url.as_deref().map(str::trim).filter(|u| !u.is_empty()), current) {
+    (Some(url), None) => {
+      repo.remote("origin", url).map_err(git_error)?;
+    }
+    (Some(url), Some(current)) if current != url => repo.remote_set_url("origin", url).map_err(git_error)?,
+    (None, Some(_)) => repo.remote_delete("origin").map_err(git_error)?,
+    _ => {}
+  }
+  Ok(repo)
+}
+
+/// The user's git identity if they have one, the app's otherwise.
+fn signature(repo: &Repository) -> Result<Signature<'static>, String> {
+  repo
+    .signature()
+    .or_else(|_| Signature::now("Papa", "papa@localhost"))
+    .map_err(git_error)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+  // Mirror the folder so removed attachments disappear from the repo too
+  if to.exists() {
+    fs::remove_dir_all(to).map_err(|e| e.to_string())?;
+  }
+  fs::create_dir_all(to).map_err(|e| e.to_string())?;
+  for entry in fs::read_dir(from).map_err(|e| e.to_string())?.flatten() {
+    if entry.path().is_file() {
+      fs::copy(entry.path(), to.join(entry.file_name())).map_err(|e| e.to_string())?;
+    }
+  }
+  Ok(())
+}
+
+/// Copy a finished Markdown export into the repository and commit it, then
+/// push if asked to, all in the background. Does nothing when the option is
+/// off; failures are logged.
+pub fn record_export(
+  app: &tauri::AppHandle,
+  conn: &rusqlite::Connection,
+  date_key: &str,
+  export_path: &Path,
+) -> Result<(), String> {
+  let config = load_config(conn);
+  if !config.enabled {
+    return Ok(());
+  }
+  let repo = repo_dir(app, &config)?;
+  let date_key = date_key.to_string();
+  let export_path = export_path.to_path_buf();
+  let app = app.clone();
+  std::thread::spawn(move || {
+    let committed = {
+      let _repo = REPO_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+      commit_export(&repo, &config, &date_key, &export_path)
+    };
+    match committed {
+      Ok(true) if config.push && config.remote_url.is_some() => {
+        if let Err(e) = jobs::queue_git_push(&app, &repo) {
+          tracing::warn!(error = %e, "Queueing the journal push failed");
+        }
+      }
+      Ok(_) => {}
+      Err(e) => tracing::warn!(%date_key, error = %e, "Committing export to the journal repository failed"),
+    }
+  });
+  Ok(())
+}
+
+/// Push the repository to `origin`, for the `git_push` job. Fails with
+/// `OFFLINE_ERROR` while offline, so the job waits for the connection.
+pub fn push(repo: &Path) -> Result<(), String> {
+  permissions::ensure(Permission::Network)?;
+  if !connectivity::is_online() {
+    return Err(connectivity::OFFLINE_ERROR.to_string());
+  }
+  let _repo = REPO_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+  let repo = Repository::open(repo).map_err(git_error)?;
+  let head = repo.head().map_err(git_error)?;
+  let head = head.name().ok_or_else(|| "git: HEAD is not a branch".to_string())?;
+  let git_config = repo.config().map_err(git_error)?;
+  let mut remote = repo.find_remote("origin").map_err(git_error)?;
+
+  let attempts = Cell::new(0);
+  let mut callbacks = RemoteCallbacks::new();
+  callbacks.credentials(|url, username, allowed| {
+    attempts.set(attempts.get() + 1);
+    if attempts.get() > MAX_AUTH_ATTEMPTS {
+      return Err(git2::Error::from_str("authentication failed"));
+    }
+    if allowed.contains(CredentialType::SSH_KEY) {
+      Cred::ssh_key_from_agent(username.unwrap_or("git"))
+    } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+      Cred::credential_helper(&git_config, url, username)
+    } else {
+      Cred::default()
+    }
+  });
+  // A rejected ref doesn't fail `push` itself
+  callbacks.push_update_reference(|_, status| match status {
+    Some(message) => Err(git2::Error::from_str(message)),
+    None => Ok(()),
+  });
+
+  let refspec = format!("{}:refs/heads/{}", head, DEFAULT_BRANCH);
+  remote
+    .push(&[refspec.as_str()], Some(PushOptions::new().remote_callbacks(callbacks)))
+    .map_err(git_error)
+}
+
+/// Returns whether there was anything to commit.
+fn commit_export(path: &Path, config: &GitJournalConfig, date_key: &str, export_path: &Path) -> Result<bool, String> {
+  let repo = prepare_repo(path, config)?;
+
+  let file_name = format!("{}.md", date_key);
+  fs::copy(export_path, path.join(&file_name)).map_err(|e| e.to_string())?;
+  let assets = format!("{}_assets", date_key);
+  let assets_dir = export_path.with_file_name(&assets);
+  if assets_dir.is_dir() {
+    copy_dir(&assets_dir, &path.join(&assets))?;
+  }
+
+  // Stage everything, removals included
+  let mut index = repo.index().map_err(git_error)?;
+  index.add_all(["*"], IndexAddOption::DEFAULT, None).map_err(git_error)?;
+  index.update_all(["*"], None).map_err(git_error)?;
+  index.write().map_err(git_error)?;
+  let tree = repo.find_tree(index.write_tree().map_err(git_error)?).map_err(git_error)?;
+
+  let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+  // Re-exporting an unchanged day leaves nothing to commit
+  if parent.as_ref().is_some_and(|parent| parent.tree_id() == tree.id()) {
+    return Ok(false);
+  }
+  let signature = signature(&repo)?;
+  let parents: Vec<&git2::Commit> = parent.iter().collect();
+  repo
+    .commit(Some("HEAD"), &signature, &signature, &format!("Daily record {}", date_key), &tree, &parents)
+    .map_err(git_error)?;
+  Ok(true)
+}
+
+#[tauri::command]
+pub fn get_git_journal(app: tauri::AppHandle, state: tauri::State<DbState>) -> Result<GitJournalStatus, String> {
+  let config = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    load_config(&conn)
+  };
+  let repo = repo_dir(&app, &config)?;
+  let last_commit = Repository::open(&repo)
+    .ok()
+    .and_then(|r| r.head().ok()?.peel_to_commit().ok()?.summary().map(str::to_string))
+    .filter(|s| !s.is_empty());
+  Ok(GitJournalStatus { config, repo_path: repo.to_string_lossy().to_string(), last_commit })
+}
+
+/// Save the settings; switching the option on sets up the repository right
+/// away so problems show up here rather than on the next export.
+#[tauri::command]
+pub fn set_git_journal(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  git_journal: GitJournalConfig,
+) -> Result<GitJournalStatus, String> {
+  if git_journal.enabled {
+    let _repo = REPO_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    prepare_repo(&repo_dir(&app, &git_journal)?, &git_journal)?;
+  }
+  let json = serde_json::to_string(&git_journal).map_err(|e| e.to_string())?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, GIT_JOURNAL_KEY, &json)?;
+  }
+  config::emit_changed(&app, vec![GIT_JOURNAL_KEY.to_string()]);
+  get_git_journal(app, state)
+}
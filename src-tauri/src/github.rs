@@ -0,0 +1,424 @@
+// GitHub activity in the timeline.
+//
+// With `github.settings` enabled and a personal access token stored as
+// `github.token` (see secrets.rs), the user's public and private activity is
+// pulled from the events API every 30 minutes: commits pushed, pull requests
+// opened, merged or closed, and reviews submitted. Each item is kept in
+// `github_activity` once, and every day with activity gets one timeline
+// entry (`source = 'github'`) summarizing it, which is rewritten as more of
+// that day's activity comes in. The entry stays put once it's deleted or
+// locked.
+//
+// The events API only reaches back 90 days and 300 events, so older activity
+// can't be backfilled.
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::permissions::{self, Permission};
+use crate::{compression, config, connectivity, generate_id, read_setting, secrets, supervisor, tags, timezone, usage_metrics, write_setting, DbState};
+
+pub const SETTINGS_KEY: &str = "github.settings";
+pub const TOKEN_SECRET: &str = "github.token";
+
+const API_URL: &str = "https://api.github.com";
+const SYNC_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+// The events API serves at most 3 pages of 100
+const MAX_PAGES: u32 = 3;
+const MAX_LISTED_PER_KIND: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GithubSettings {
+  enabled: bool,
+  /// Login whose activity is pulled; defaults to the token's owner
+  username: Option<String>,
+}
+
+#[derive(Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GithubSync {
+  /// Activity not seen before
+  new_activity: usize,
+  /// Days whose timeline entry was created or rewritten
+  days_updated: usize,
+}
+
+struct Activity {
+  /// Only set on activity fresh from the API
+  id: String,
+  kind: String,
+  repo: String,
+  number: Option<i64>,
+  title: Option<String>,
+  count: i64,
+  created_at: i64,
+}
+
+fn load_settings(conn: &rusqlite::Connection) -> GithubSettings {
+  read_setting(conn, SETTINGS_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn client() -> Result<reqwest::Client, String> {
+  connectivity::client_builder()
+    .timeout(HTTP_TIMEOUT)
+    .user_agent("papa")
+    .build()
+    .map_err(|e| e.to_string())
+}
+
+async fn get(client: &reqwest::Client, token: &str, url: &str) -> Result<Value, String> {
+  let response = client
+    .get(url)
+    .bearer_auth(token)
+    .header("Accept", "application/vnd.github+json")
+    .header("X-GitHub-Api-Version", "2022-11-28")
+    .send()
+    .await
+    .map_err(|e| connectivity::request_error("GitHub request failed", e))?;
+  let status = response.status();
+  let body: Value = response.json().await.map_err(|e| format!("Invalid GitHub response: {}", e))?;
+  if !status.is_success() {
+    let message = body["message"].as_str().unwrap_or("unknown error");
+    return Err(format!("GitHub returned {}: {}", status, message));
+  }
+  Ok(body)
+}
+
+fn first_line(text: &str) -> String {
+  text.lines().next().unwrap_or_default().trim().to_string()
+}
+
+/// The commits, pull requests and reviews in one item of the events API.
+fn parse_event(event: &Value) -> Vec<Activity> {
+  let (Some(id), Some(created_at)) = (event["id"].as_str(), event["created_at"].as_str()) else { return Vec::new() };
+  let Ok(created_at) = DateTime::parse_from_rfc3339(created_at) else { return Vec::new() };
+  let created_at = created_at.timestamp_millis();
+  let repo = event["repo"]["name"].as_str().unwrap_or_default().to_string();
+  let payload = &event["payload"];
+  let pull = &payload["pull_request"];
+  let activity = |id: String, kind: &str, number, title: Option<&str>, count| Activity {
+    id,
+    kind: kind.to_string(),
+    repo: repo.clone(),
+    number,
+    title: title.map(first_line),
+    count,
+    created_at,
+  };
+
+  match event["type"].as_str() {
+    Some("PushEvent") => {
+      let commits = payload["commits"].as_array().cloned().unwrap_or_default();
+      if commits.is_empty() {
+        // Pushes don't always list their commits; count them instead
+        let size = payload["size"].as_i64().or(payload["distinct_size"].as_i64()).unwrap_or(1);
+        return vec![activity(format!("push:{}", id), "commit", None, None, size)];
+      }
+      // A commit pushed to several branches is still one commit
+      commits
+        .iter()
+        .filter(|c| c["distinct"].as_bool().unwrap_or(true))
+        .filter_map(|c| Some(activity(format!("commit:{}", c["sha"].as_str()?), "commit", None, c["message"].as_str(), 1)))
+        .collect()
+    }
+    Some("PullRequestEvent") => {
+      let kind = match (payload["action"].as_str(), pull["merged"].as_bool()) {
+        (Some("opened"), _) => "pr_opened",
+        (Some("closed"), Some(true)) => "pr_merged",
+        (Some("closed"), _) => "pr_closed",
+        _ => return Vec::new(),
+      };
+      vec![activity(format!("{}:{}", kind, id), kind, pull["number"].as_i64(), pull["title"].as_str(), 1)]
+    }
+    Some("PullRequestReviewEvent") => {
+      vec![activity(format!("review:{}", id), "review", pull["number"].as_i64(), pull["title"].as_str(), 1)]
+    }
+    _ => Vec::new(),
+  }
+}
+
+async fn fetch_activity(token: &str, username: Option<String>) -> Result<Vec<Activity>, String> {
+  permissions::ensure(Permission::Network)?;
+  let client = client()?;
+  let login = match username.filter(|u| !u.trim().is_empty()) {
+    Some(login) => login.trim().to_string(),
+    None => get(&client, token, &format!("{}/user", API_URL)).await?["login"]
+      .as_str()
+      .ok_or("Could not tell whose token this is")?
+      .to_string(),
+  };
+  let mut activity = Vec::new();
+  for page in 1..=MAX_PAGES {
+    let url = format!("{}/users/{}/events?per_page=100&page={}", API_URL, login, page);
+    let events = get(&client, token, &url).await?;
+    let events = events.as_array().cloned().unwrap_or_default();
+    activity.extend(events.iter().flat_map(parse_event));
+    if events.len() < 100 {
+      break;
+    }
+  }
+  Ok(activity)
+}
+
+/// Store activity not seen before; returns the days it falls on.
+fn store_activity(conn: &rusqlite::Connection, activity: &[Activity]) -> Result<(usize, Vec<String>), String> {
+  let mut added = 0;
+  let mut days: Vec<String> = Vec::new();
+  for item in activity {
+    let day = timezone::date_key(item.created_at);
+    let inserted = conn
+      .execute(
+        "INSERT OR IGNORE INTO github_activity (id, date_key, kind, repo, number, title, count, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![item.id, day, item.kind, item.repo, item.number, item.title, item.count, item.created_at],
+      )
+      .map_err(|e| e.to_string())?;
+    if inserted > 0 {
+      added += 1;
+      if !days.contains(&day) {
+        days.push(day);
+      }
+    }
+  }
+  Ok((added, days))
+}
+
+fn plural(count: i64, one: &str, many: &str) -> String {
+  format!("{} {}", count, if count == 1 { one } else { many })
+}
+
+/// The note, Markdown text and metadata of a day's entry, and the time of
+/// its latest activity.
+fn summarize_day(conn: &rusqlite::Connection, day: &str) -> Result<Option<(String, String, Value, i64)>, String> {
+  let rows: Vec<Activity> = conn
+    .prepare(
+      "SELECT kind, repo, number, title, count, created_at FROM github_activity
+       WHERE date_key = ? ORDER BY created_at",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([day], |row| {
+      Ok(Activity {
+        id: String::new(),
+        kind: row.get(0)?,
+        repo: row.get(1)?,
+        number: row.get(2)?,
+        title: row.get(3)?,
+        count: row.get(4)?,
+        created_at: row.get(5)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  let Some(latest) = rows.iter().map(|r| r.created_at).max() else { return Ok(None) };
+
+  let mut totals: BTreeMap<&str, i64> = BTreeMap::new();
+  let mut commits_by_repo: BTreeMap<&str, i64> = BTreeMap::new();
+  for row in &rows {
+    *totals.entry(row.kind.as_str()).or_default() += row.count;
+    if row.kind == "commit" {
+      *commits_by_repo.entry(row.repo.as_str()).or_default() += row.count;
+    }
+  }
+  let total = |kind: &str| totals.get(kind).copied().unwrap_or(0);
+
+  let mut parts = Vec::new();
+  if total("commit") > 0 {
+    parts.push(plural(total("commit"), "commit", "commits"));
+  }
+  if total("pr_opened") > 0 {
+    parts.push(plural(total("pr_opened"), "PR opened", "PRs opened"));
+  }
+  if total("pr_merged") > 0 {
+    parts.push(format!("{} merged", total("pr_merged")));
+  }
+  if total("pr_closed") > 0 {
+    parts.push(format!("{} closed", total("pr_closed")));
+  }
+  if total("review") > 0 {
+    parts.push(plural(total("review"), "review", "reviews"));
+  }
+  let note = format!("GitHub: {}", parts.join(", "));
+
+  let mut text = String::new();
+  if !commits_by_repo.is_empty() {
+    text.push_str("## Commits\n\n");
+    for (repo, count) in &commits_by_repo {
+      text.push_str(&format!("**{}** ({})\n", repo, count));
+      let messages = rows.iter().filter(|r| r.kind == "commit" && r.repo == *repo).filter_map(|r| r.title.as_deref());
+      for message in messages.take(MAX_LISTED_PER_KIND) {
+        text.push_str(&format!("- {}\n", message));
+      }
+      text.push('\n');
+    }
+  }
+  for (kind, heading) in [
+    ("pr_opened", "Pull requests opened"),
+    ("pr_merged", "Pull requests merged"),
+    ("pr_closed", "Pull requests closed"),
+    ("review", "Reviews"),
+  ] {
+    let items: Vec<String> = rows
+      .iter()
+      .filter(|r| r.kind == kind)
+      .take(MAX_LISTED_PER_KIND)
+      .map(|r| match r.number {
+        Some(number) => format!("- {}#{} {}", r.repo, number, r.title.as_deref().unwrap_or_default()),
+        None => format!("- {} {}", r.repo, r.title.as_deref().unwrap_or_default()),
+      })
+      .collect();
+    if !items.is_empty() {
+      text.push_str(&format!("## {}\n\n{}\n\n", heading, items.join("\n")));
+    }
+  }
+
+  let metadata = json!({
+    "github": {
+      "date": day,
+      "commits": total("commit"),
+      "pullRequestsOpened": total("pr_opened"),
+      "pullRequestsMerged": total("pr_merged"),
+      "pullRequestsClosed": total("pr_closed"),
+      "reviews": total("review"),
+      "repos": commits_by_repo.keys().collect::<Vec<_>>(),
+    }
+  });
+  Ok(Some((note, text.trim_end().to_string(), metadata, latest)))
+}
+
+/// Create or rewrite the timeline entry for `day`. Returns whether it was
+/// written.
+fn write_day(conn: &rusqlite::Connection, day: &str) -> Result<bool, String> {
+  let Some((note, text, metadata, latest)) = summarize_day(conn, day)? else { return Ok(false) };
+  // A purged entry leaves its row here with no event, and stays gone too
+  let existing: Option<(String, Option<bool>, Option<bool>)> = conn
+    .query_row(
+      "SELECT d.event_id, e.is_deleted, e.locked FROM github_days d LEFT JOIN timeline_events e ON e.id = d.event_id
+       WHERE d.date_key = ?",
+      [day],
+      |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .ok();
+
+  let event_id = match existing {
+    Some((_, Some(false), Some(false))) | None => existing.map(|(id, _, _)| id),
+    Some(_) => return Ok(false),
+  };
+  let event_id = match event_id {
+    Some(event_id) => {
+      conn
+        .execute(
+          "UPDATE timeline_events
+           SET note = ?1, text_content = ?2, text_compressed = NULL, text_codec = NULL, created_at = ?3,
+               metadata = json_patch(COALESCE(metadata, '{}'), ?4)
+           WHERE id = ?5",
+          (&note, &text, latest, metadata.to_string(), &event_id),
+        )
+        .map_err(|e| e.to_string())?;
+      event_id
+    }
+    None => {
+      let event_id = generate_id();
+      conn
+        .execute(
+          "INSERT INTO timeline_events (id, type, note, text_content, created_at, source, is_deleted, metadata)
+           VALUES (?1, 'text', ?2, ?3, ?4, 'github', 0, ?5)",
+          (&event_id, &note, &text, latest, metadata.to_string()),
+        )
+        .map_err(|e| e.to_string())?;
+      conn
+        .execute(
+          "INSERT OR REPLACE INTO github_days (date_key, event_id) VALUES (?1, ?2)",
+          (day, &event_id),
+        )
+        .map_err(|e| e.to_string())?;
+      tags::auto_tag(conn, &event_id);
+      event_id
+    }
+  };
+  compression::compress_if_large(conn, &event_id)?;
+  Ok(true)
+}
+
+async fn sync(app: &tauri::AppHandle) -> Result<GithubSync, String> {
+  let settings = {
+    let state = app.state::<DbState>();
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    load_settings(&conn)
+  };
+  if !settings.enabled {
+    return Ok(GithubSync::default());
+  }
+  let token = secrets::get(TOKEN_SECRET)?.ok_or_else(|| "GitHub token is not set".to_string())?;
+  let activity = fetch_activity(&token, settings.username).await?;
+
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let (new_activity, days) = store_activity(&conn, &activity)?;
+  let mut days_updated = 0;
+  for day in &days {
+    if write_day(&conn, day)? {
+      days_updated += 1;
+    }
+  }
+  if new_activity > 0 {
+    tracing::info!(new_activity, days_updated, "Synced GitHub activity");
+  }
+  Ok(GithubSync { new_activity, days_updated })
+}
+
+pub fn spawn_github_sync(app: tauri::AppHandle) {
+  supervisor::supervise(app, "github", |app| async move {
+    loop {
+      if connectivity::reachable() {
+        if let Err(e) = sync(&app).await {
+          tracing::warn!(error = %e, "GitHub sync failed");
+        }
+      }
+      connectivity::sleep(SYNC_INTERVAL).await;
+    }
+  });
+}
+
+#[tauri::command]
+pub fn get_github_settings(state: tauri::State<DbState>) -> Result<GithubSettings, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_settings(&conn))
+}
+
+#[tauri::command]
+pub fn set_github_settings(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  settings: GithubSettings,
+) -> Result<GithubSettings, String> {
+  if settings.enabled && secrets::get(TOKEN_SECRET)?.is_none() {
+    return Err("Store a GitHub token first".to_string());
+  }
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    write_setting(&conn, SETTINGS_KEY, &json)?;
+  }
+  config::emit_changed(&app, vec![SETTINGS_KEY.to_string()]);
+  Ok(settings)
+}
+
+/// Pull GitHub activity now instead of waiting for the next sync.
+#[tauri::command]
+pub async fn sync_github(app: tauri::AppHandle) -> Result<GithubSync, String> {
+  let _timer = usage_metrics::time_async("sync_github");
+  sync(&app).await
+}
@@ -0,0 +1,206 @@
+// Daily goals, e.g. three captures or two hours of focus a day.
+//
+// Targets live in the `goals.daily` setting, keyed by kind:
+// - "captures": events captured that day
+// - "journal": journal entries written that day
+// - "focusMinutes": minutes of focus sessions that day
+// A background check every minute keeps `goal_progress` up to date for today, emits
+// `goal-progress` when a value moves and `goal-met` once a goal is reached,
+// which also makes the pet cheer. The daily export lists the day's goals.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::{config, day_bounds, i18n, now_ms, pet_state, read_setting, supervisor, timezone, write_setting, DbState};
+
+pub const DAILY_GOALS_KEY: &str = "goals.daily";
+
+const KINDS: &[&str] = &["captures", "journal", "focusMinutes"];
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalProgress {
+  kind: String,
+  target: u32,
+  value: u32,
+  met_at: Option<i64>,
+}
+
+fn load_goals(conn: &rusqlite::Connection) -> BTreeMap<String, u32> {
+  read_setting(conn, DAILY_GOALS_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn value(conn: &rusqlite::Connection, kind: &str, start: i64, end: i64) -> Result<u32, String> {
+  let sql = match kind {
+    "captures" => {
+      "SELECT COUNT(*) FROM timeline_events
+       WHERE created_at BETWEEN ?1 AND ?2 AND is_deleted = 0 AND scheduled_for IS NULL AND type != 'journal'"
+    }
+    "journal" => {
+      "SELECT COUNT(*) FROM timeline_events
+       WHERE created_at BETWEEN ?1 AND ?2 AND is_deleted = 0 AND type = 'journal'"
+    }
+    // Sessions are clipped to the day and to now
+    _ => {
+      "SELECT COALESCE(SUM(MIN(ends_at, ?2, ?3) - MAX(started_at, ?1)), 0) / 60000 FROM focus_sessions
+       WHERE started_at <= ?2 AND ends_at >= ?1 AND started_at <= ?3"
+    }
+  };
+  let count: i64 = if kind == "focusMinutes" {
+    conn.query_row(sql, [start, end, now_ms()], |row| row.get(0))
+  } else {
+    conn.query_row(sql, [start, end], |row| row.get(0))
+  }
+  .map_err(|e| e.to_string())?;
+  Ok(count.max(0) as u32)
+}
+
+/// Progress on every goal for `date_key`, as stored in `goal_progress`.
+fn stored_progress(conn: &rusqlite::Connection, date_key: &str) -> Result<Vec<GoalProgress>, String> {
+  let rows = conn
+    .prepare("SELECT kind, target, value, met_at FROM goal_progress WHERE date_key = ? ORDER BY kind")
+    .map_err(|e| e.to_string())?
+    .query_map([date_key], |row| {
+      Ok(GoalProgress { kind: row.get(0)?, target: row.get(1)?, value: row.get(2)?, met_at: row.get(3)? })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(rows)
+}
+
+/// Today's progress computed afresh, with `met_at` from the stored row (or
+/// now, for goals just reached).
+fn live_progress(conn: &rusqlite::Connection, previous: &[GoalProgress]) -> Result<Vec<GoalProgress>, String> {
+  let (start, end) = day_bounds(&timezone::today_key())?;
+  load_goals(conn)
+    .into_iter()
+    .map(|(kind, target)| {
+      let value = value(conn, &kind, start, end)?;
+      let met_at = previous
+        .iter()
+        .find(|p| p.kind == kind)
+        .and_then(|p| p.met_at)
+        .or_else(|| (value >= target).then(now_ms));
+      Ok(GoalProgress { kind, target, value, met_at })
+    })
+    .collect()
+}
+
+/// Recompute today's progress and store it. Returns the goals whose value
+/// changed and those newly met.
+fn refresh(conn: &rusqlite::Connection) -> Result<(Vec<GoalProgress>, Vec<GoalProgress>), String> {
+  let date_key = timezone::today_key();
+  let previous = stored_progress(conn, &date_key)?;
+
+  let mut changed = Vec::new();
+  let mut met = Vec::new();
+  for progress in live_progress(conn, &previous)? {
+    let before = previous.iter().find(|p| p.kind == progress.kind);
+    conn
+      .execute(
+        "INSERT INTO goal_progress (date_key, kind, target, value, met_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(date_key, kind) DO UPDATE SET target = ?3, value = ?4, met_at = ?5, updated_at = ?6",
+        rusqlite::params![&date_key, &progress.kind, progress.target, progress.value, progress.met_at, now_ms()],
+      )
+      .map_err(|e| e.to_string())?;
+
+    if before.map(|p| (p.value, p.target)) != Some((progress.value, progress.target)) {
+      changed.push(progress.clone());
+    }
+    if progress.met_at.is_some() && before.and_then(|p| p.met_at).is_none() {
+      met.push(progress);
+    }
+  }
+  Ok((changed, met))
+}
+
+pub fn spawn_goal_checker(app: tauri::AppHandle) {
+  supervisor::supervise(app, "goals", |app| async move {
+    loop {
+      tokio::time::sleep(CHECK_INTERVAL).await;
+
+      let (changed, met) = {
+        let state = app.state::<DbState>();
+        let Ok(_guard) = state.lock.lock() else { continue };
+        state.open()
+          .map_err(|e| e.to_string())
+          .and_then(|conn| refresh(&conn))
+          .unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Goal check failed");
+            Default::default()
+          })
+      };
+
+      for progress in &changed {
+        let _ = app.emit("goal-progress", progress);
+      }
+      for progress in &met {
+        tracing::info!(kind = %progress.kind, target = progress.target, "Daily goal met");
+        pet_state::cheer(&app, format!("Daily {} goal met", progress.kind));
+        let _ = app.emit("goal-met", progress);
+      }
+    }
+  });
+}
+
+/// Goals section for the daily export of `date_key`, `None` without goals.
+pub fn format_goals(conn: &rusqlite::Connection, date_key: &str) -> Result<Option<String>, String> {
+  let progress = stored_progress(conn, date_key)?;
+  if progress.is_empty() {
+    return Ok(None);
+  }
+  let mut out = String::new();
+  for goal in progress {
+    let mark = if goal.met_at.is_some() { "✅" } else { "⬜" };
+    out.push_str(&format!("- {} {}: {} / {}\n", mark, i18n::t(&goal.kind), goal.value, goal.target));
+  }
+  Ok(Some(out))
+}
+
+/// Set the daily target for `kind` ("captures", "journal" or
+/// "focusMinutes"); 0 removes the goal. Returns today's progress.
+#[tauri::command]
+pub fn set_daily_goal(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  kind: String,
+  target: u32,
+) -> Result<Vec<GoalProgress>, String> {
+  if !KINDS.contains(&kind.as_str()) {
+    return Err(format!("Unknown goal: {}", kind));
+  }
+  let progress = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let mut goals = load_goals(&conn);
+    if target == 0 {
+      goals.remove(&kind);
+      conn
+        .execute("DELETE FROM goal_progress WHERE date_key = ?1 AND kind = ?2", (timezone::today_key(), &kind))
+        .map_err(|e| e.to_string())?;
+    } else {
+      goals.insert(kind, target);
+    }
+    let json = serde_json::to_string(&goals).map_err(|e| e.to_string())?;
+    write_setting(&conn, DAILY_GOALS_KEY, &json)?;
+    live_progress(&conn, &stored_progress(&conn, &timezone::today_key())?)?
+  };
+  config::emit_changed(&app, vec![DAILY_GOALS_KEY.to_string()]);
+  Ok(progress)
+}
+
+/// Today's progress on every goal. Goals reached since the last check are
+/// announced by the checker, not here.
+#[tauri::command]
+pub fn get_daily_goals(state: tauri::State<DbState>) -> Result<Vec<GoalProgress>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  live_progress(&conn, &stored_progress(&conn, &timezone::today_key())?)
+}
@@ -0,0 +1,247 @@
+// Translations of text the backend generates.
+//
+// Exports, email subjects, the tray menu, default nudges and the most common
+// error messages go through `t`, which looks the English text up in the
+// catalog of the active locale and falls back to the English when there's no
+// entry. Placeholders are named (`{count}`) and filled in by `tf`, so a
+// translation can put them in any order. Text that isn't in a catalog simply
+// stays English.
+//
+// The `locale` setting is "system" (the default) or one of `LOCALES`. The
+// system language comes from the user's Windows or macOS settings, or from
+// LANG/LC_* elsewhere.
+//
+// Error strings the webview matches on, like `app_lock::LOCKED_ERROR`, are
+// never translated.
+
+use serde::Serialize;
+use std::sync::RwLock;
+use tauri::Manager;
+
+use crate::{config, read_setting, write_setting, DbState};
+
+pub const LOCALE_KEY: &str = "locale";
+const SYSTEM: &str = "system";
+/// Supported locales besides "system"
+pub const LOCALES: &[&str] = &["en", "zh-CN"];
+
+static LOCALE: RwLock<&'static str> = RwLock::new("en");
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleInfo {
+  /// The setting: "system" or a supported locale
+  locale: String,
+  /// The locale in use
+  active: String,
+  available: Vec<String>,
+}
+
+const ZH_CN: &[(&str, &str)] = &[
+  // Daily export
+  ("Daily Record - {date}", "每日记录 - {date}"),
+  ("Daily Record - {date} ({name})", "每日记录 - {date}（{name}）"),
+  ("{count} records", "{count} 条记录"),
+  ("Where your time went", "时间去向"),
+  ("Captures", "记录统计"),
+  ("Goals", "目标"),
+  ("Reminders", "提醒"),
+  ("Untitled", "无标题"),
+  ("{name} (file not found)", "{name}（文件不存在）"),
+  ("- {events} events, {words} words written", "- {events} 条记录，写了 {words} 个字"),
+  ("- {files} files captured ({size} MB)", "- 收录了 {files} 个文件（{size} MB）"),
+  ("- Busiest hour: {from} - {to}", "- 最忙的时段：{from} - {to}"),
+  ("- Top tags: {tags}", "- 常用标签：{tags}"),
+  (
+    "- {total} reminders: {on_time} dismissed on time, {after_snooze} after snoozing, {open} still open",
+    "- {total} 个提醒：{on_time} 个按时完成，{after_snooze} 个推迟后完成，{open} 个未完成",
+  ),
+  ("- Snoozed {count} times, {average} snoozes on average", "- 推迟了 {count} 次，平均每个提醒 {average} 次"),
+  ("- Average response time: {duration}", "- 平均响应时间：{duration}"),
+  ("captures", "记录"),
+  ("journal", "日记"),
+  ("focusMinutes", "专注分钟"),
+  // Day plan
+  ("Plan for {date}", "{date} 的计划"),
+  ("Focus time", "专注时间"),
+  ("Not scheduled:", "未安排："),
+  // Weather line
+  ("{weekday}, {temperature}, {summary}", "{weekday}，{temperature}，{summary}"),
+  ("Monday", "星期一"),
+  ("Tuesday", "星期二"),
+  ("Wednesday", "星期三"),
+  ("Thursday", "星期四"),
+  ("Friday", "星期五"),
+  ("Saturday", "星期六"),
+  ("Sunday", "星期日"),
+  ("clear", "晴"),
+  ("mostly clear", "大致晴朗"),
+  ("partly cloudy", "多云"),
+  ("overcast", "阴"),
+  ("fog", "雾"),
+  ("drizzle", "毛毛雨"),
+  ("rain", "雨"),
+  ("snow", "雪"),
+  ("showers", "阵雨"),
+  ("snow showers", "阵雪"),
+  ("thunderstorm", "雷雨"),
+  ("mixed weather", "天气多变"),
+  // Tray, email and nudges
+  ("Show Papa", "显示 Papa"),
+  ("Toggle Click-through", "切换点击穿透"),
+  ("Quit", "退出"),
+  ("Papa test email", "Papa 测试邮件"),
+  ("You've been at it for a while. Time to stretch and rest your eyes?", "已经忙了好一阵了，起来伸展一下、让眼睛休息休息吧？"),
+  ("Lots of rewrites lately. Take a breath, you've got this.", "最近改了很多遍。深呼吸一下，你可以的。"),
+  ("Look at {name}", "查看 {name}"),
+  // Errors
+  ("Event not found", "找不到该记录"),
+  ("Event is locked", "该记录已加密锁定"),
+  ("Unlock the event first", "请先解锁该记录"),
+  ("Reminder is no longer active", "该提醒已不再有效"),
+  ("Unlock both events first", "请先解锁这两条记录"),
+  ("This event is excluded from AI", "该记录已排除在 AI 之外"),
+  ("Wrong PIN", "PIN 码错误"),
+  ("Invalid date format", "日期格式无效"),
+  ("This file type is skipped by the ingestion policy", "收录策略会跳过这种文件类型"),
+  ("This file was already captured or is skipped by the ingestion policy", "该文件已收录过，或被收录策略跳过"),
+];
+
+fn catalog(locale: &str) -> &'static [(&'static str, &'static str)] {
+  match locale {
+    "zh-CN" => ZH_CN,
+    _ => &[],
+  }
+}
+
+/// Map a language tag like "zh_CN.UTF-8", "zh-Hans-CN" or "en-GB" to a
+/// supported locale.
+fn supported(tag: &str) -> Option<&'static str> {
+  let tag = tag.split('.').next().unwrap_or_default().replace('_', "-").to_lowercase();
+  if let Some(exact) = LOCALES.iter().copied().find(|l| l.to_lowercase() == tag) {
+    return Some(exact);
+  }
+  match tag.split('-').next().unwrap_or_default() {
+    "zh" if !tag.contains("hant") && !tag.ends_with("-tw") && !tag.ends_with("-hk") => Some("zh-CN"),
+    "en" => Some("en"),
+    _ => None,
+  }
+}
+
+#[cfg(target_os = "windows")]
+fn system_tag() -> Option<String> {
+  use windows_sys::Win32::Globalization::GetUserDefaultLocaleName;
+  let mut buffer = [0u16; 85];
+  // SAFETY: the buffer is valid for its whole length, which is passed along
+  let len = unsafe { GetUserDefaultLocaleName(buffer.as_mut_ptr(), buffer.len() as i32) };
+  (len > 1).then(|| String::from_utf16_lossy(&buffer[..len as usize - 1]))
+}
+
+#[cfg(target_os = "macos")]
+fn system_tag() -> Option<String> {
+  let output = std::process::Command::new("defaults").args(["read", "-g", "AppleLocale"]).output().ok()?;
+  Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    .filter(|tag| output.status.success() && !tag.is_empty())
+    .or_else(env_tag)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn system_tag() -> Option<String> {
+  env_tag()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn env_tag() -> Option<String> {
+  ["LC_ALL", "LC_MESSAGES", "LANG"]
+    .iter()
+    .filter_map(|key| std::env::var(key).ok())
+    .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+}
+
+fn resolve(setting: &str) -> &'static str {
+  match setting {
+    SYSTEM => system_tag().as_deref().and_then(supported).unwrap_or("en"),
+    other => supported(other).unwrap_or("en"),
+  }
+}
+
+fn active() -> &'static str {
+  LOCALE.read().map(|locale| *locale).unwrap_or("en")
+}
+
+fn load_setting(conn: &rusqlite::Connection) -> String {
+  read_setting(conn, LOCALE_KEY).unwrap_or_else(|| SYSTEM.to_string())
+}
+
+/// Read the setting again; call after it changes.
+pub fn reload(app: &tauri::AppHandle) {
+  let state = app.state::<DbState>();
+  let setting = {
+    let Ok(_guard) = state.lock.lock() else { return };
+    let Ok(conn) = state.open() else { return };
+    load_setting(&conn)
+  };
+  if let Ok(mut slot) = LOCALE.write() {
+    *slot = resolve(&setting);
+  }
+}
+
+/// `text` in the active locale, or `text` itself without a translation.
+pub fn t(text: &str) -> String {
+  catalog(active())
+    .iter()
+    .find(|(source, _)| *source == text)
+    .map(|(_, translated)| translated.to_string())
+    .unwrap_or_else(|| text.to_string())
+}
+
+/// `t` with `{name}` placeholders filled in.
+pub fn tf(text: &str, args: &[(&str, String)]) -> String {
+  let mut out = t(text);
+  for (name, value) in args {
+    out = out.replace(&format!("{{{}}}", name), value);
+  }
+  out
+}
+
+/// BCP 47 tag of the active locale, for `<html lang>`.
+pub fn lang() -> &'static str {
+  active()
+}
+
+fn info(setting: String) -> LocaleInfo {
+  LocaleInfo {
+    locale: setting,
+    active: active().to_string(),
+    available: LOCALES.iter().map(|l| l.to_string()).collect(),
+  }
+}
+
+#[tauri::command]
+pub fn get_locale(state: tauri::State<DbState>) -> Result<LocaleInfo, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(info(load_setting(&conn)))
+}
+
+#[tauri::command]
+pub fn set_locale(app: tauri::AppHandle, state: tauri::State<DbState>, locale: String) -> Result<LocaleInfo, String> {
+  let setting = match locale.trim() {
+    SYSTEM => SYSTEM,
+    other => LOCALES
+      .iter()
+      .copied()
+      .find(|l| l.eq_ignore_ascii_case(other))
+      .ok_or_else(|| format!("Unsupported locale: {}", other))?,
+  };
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, LOCALE_KEY, setting)?;
+  }
+  if let Ok(mut slot) = LOCALE.write() {
+    *slot = resolve(setting);
+  }
+  config::emit_changed(&app, vec![LOCALE_KEY.to_string()]);
+  Ok(info(setting.to_string()))
+}
@@ -0,0 +1,145 @@
+// Ingestion policy: what gets accepted when files are dropped or uploaded,
+// and whether they're copied into app storage or referenced in place.
+//
+// Stored as one JSON setting (`ingest.policy`). Defaults match the old
+// hardcoded behavior: no size cap, files referenced where they are, nothing
+// skipped and 1MB for text reads.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::{read_setting, write_setting, DbState};
+
+pub const INGESTION_POLICY_KEY: &str = "ingest.policy";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageMode {
+  /// Keep pointing at the original file
+  Reference,
+  /// Copy into the content store, unless the file is above `copy_max_bytes`
+  Copy,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IngestionPolicy {
+  /// Files above this are rejected; `None` means no limit
+  pub max_ingest_bytes: Option<u64>,
+  pub storage_mode: StorageMode,
+  /// In copy mode, larger files are referenced instead of copied
+  pub copy_max_bytes: u64,
+  /// Lowercase extensions without the dot, e.g. "exe", "tmp"
+  pub skip_extensions: Vec<String>,
+  /// Largest file `read_file_content` will decode
+  pub max_text_bytes: u64,
+}
+
+impl Default for IngestionPolicy {
+  fn default() -> Self {
+    Self {
+      max_ingest_bytes: None,
+      storage_mode: StorageMode::Reference,
+      copy_max_bytes: 50 * 1024 * 1024,
+      skip_extensions: Vec::new(),
+      max_text_bytes: 1_000_000,
+    }
+  }
+}
+
+pub fn validate(mut policy: IngestionPolicy) -> Result<IngestionPolicy, String> {
+  if policy.max_ingest_bytes == Some(0) {
+    return Err("Max ingest size must be positive".to_string());
+  }
+  if policy.max_text_bytes == 0 {
+    return Err("Max text size must be positive".to_string());
+  }
+  policy.skip_extensions = policy
+    .skip_extensions
+    .iter()
+    .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+    .filter(|ext| !ext.is_empty())
+    .collect();
+  policy.skip_extensions.sort();
+  policy.skip_extensions.dedup();
+  Ok(policy)
+}
+
+pub fn load_policy(conn: &rusqlite::Connection) -> IngestionPolicy {
+  read_setting(conn, INGESTION_POLICY_KEY)
+    .and_then(|json| {
+      serde_json::from_str(&json)
+        .map_err(|e| e.to_string())
+        .and_then(validate)
+        .map_err(|e| tracing::warn!(error = %e, "Invalid ingestion policy"))
+        .ok()
+    })
+    .unwrap_or_default()
+}
+
+/// Current policy, opening the DB under its lock.
+pub fn policy_for(state: &DbState) -> Result<IngestionPolicy, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_policy(&conn))
+}
+
+/// Whether a file should be ingested: `Ok(false)` for skipped extensions, an
+/// error when it's missing or over the size limit.
+pub fn admit(policy: &IngestionPolicy, path: &Path) -> Result<bool, String> {
+  let size = fs::metadata(path)
+    .map_err(|_| format!("File not found: {}", path.display()))?
+    .len();
+
+  if is_skipped(policy, path) {
+    tracing::info!(path = %path.display(), "Skipped by ingestion policy");
+    return Ok(false);
+  }
+
+  check_size(policy, size).inspect_err(|e| {
+    tracing::warn!(path = %path.display(), error = %e, "Rejected by ingestion policy");
+  })?;
+  Ok(true)
+}
+
+pub fn is_skipped(policy: &IngestionPolicy, path: &Path) -> bool {
+  path
+    .extension()
+    .and_then(|e| e.to_str())
+    .map(|ext| policy.skip_extensions.contains(&ext.to_lowercase()))
+    .unwrap_or(false)
+}
+
+pub fn check_size(policy: &IngestionPolicy, size: u64) -> Result<(), String> {
+  match policy.max_ingest_bytes {
+    Some(max) if size > max => Err(format!(
+      "File is too large ({} bytes, limit is {} bytes)",
+      size, max
+    )),
+    _ => Ok(()),
+  }
+}
+
+pub fn should_copy(policy: &IngestionPolicy, size: u64) -> bool {
+  policy.storage_mode == StorageMode::Copy && size <= policy.copy_max_bytes
+}
+
+#[tauri::command]
+pub fn get_ingestion_policy(state: tauri::State<DbState>) -> Result<IngestionPolicy, String> {
+  policy_for(&state)
+}
+
+#[tauri::command]
+pub fn set_ingestion_policy(
+  state: tauri::State<DbState>,
+  policy: IngestionPolicy,
+) -> Result<IngestionPolicy, String> {
+  let policy = validate(policy)?;
+  let json = serde_json::to_string(&policy).map_err(|e| e.to_string())?;
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  write_setting(&conn, INGESTION_POLICY_KEY, &json)?;
+  Ok(policy)
+}
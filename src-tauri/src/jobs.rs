@@ -0,0 +1,645 @@
+// Background job queue.
+//
+// Work that doesn't need to finish before a command returns is queued as a
+// row in `jobs` and picked up by a single supervised worker, one job at a
+// time. Each job has a kind, a JSON payload and a retry budget: a failed
+// attempt is put back in the queue with a growing delay until
+// `max_attempts` is used up. Every state change is announced through
+// `job-updated`, so the UI can show progress without polling.
+//
+// Kinds handled here:
+//   - `hash_attachment`: fill in the sha256 of an attachment whose file
+//     couldn't be read when it was added
+//   - `thumbnail`: generate the preview of a new image or video attachment
+//     ahead of the first time it's shown
+//   - `daily_export`: write a daily export, queued by `queue_daily_export`
+//   - `notion_export`: export days to Notion, queued by `queue_notion_export`
+//   - `git_push`: push the journal repository after an export committed to
+//     it, see git_journal.rs
+// There's no OCR or embeddings pipeline in the app yet; new kinds go in
+// `run_job`.
+//
+// A job that fails with `connectivity::OFFLINE_ERROR` doesn't use up an
+// attempt; it waits in the queue until the connection is back.
+//
+// Cancelling a queued job takes it out of the queue. A running job only
+// sees the request between steps, so whatever it finishes with is discarded
+// and the job is still reported as cancelled.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tokio::sync::Notify;
+
+use crate::{
+  app_lock, connectivity, export_rules, generate_id, git_journal, hash_file, maintenance, notion, now_ms,
+  run_daily_export, supervisor, thumbnails, DbState,
+};
+
+pub const UPDATED_EVENT: &str = "job-updated";
+
+// Upper bound on an idle sleep, so jobs queued without a `wake` (from code
+// that only has a connection) still start promptly
+const MAX_IDLE: Duration = Duration::from_secs(5);
+const RETRY_BASE_MS: i64 = 30_000;
+const MAX_RETRY_DELAY_MS: i64 = 60 * 60 * 1000;
+// Finished jobs are kept this long for `list_jobs`
+const KEEP_FINISHED_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+const DEFAULT_LIST_LIMIT: u32 = 100;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+  id: String,
+  kind: String,
+  payload: Value,
+  /// "queued", "running", "succeeded", "failed" or "cancelled"
+  status: String,
+  attempts: u32,
+  max_attempts: u32,
+  /// When a queued job may start; later than `created_at` while retrying
+  run_after: i64,
+  result: Option<Value>,
+  error: Option<String>,
+  created_at: i64,
+  updated_at: i64,
+}
+
+#[derive(Default)]
+pub struct JobState {
+  notify: Notify,
+  /// Cancel flags of the jobs currently running
+  running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl JobState {
+  pub fn wake(&self) {
+    self.notify.notify_one();
+  }
+}
+
+const JOB_COLUMNS: &str =
+  "id, kind, payload, status, attempts, max_attempts, run_after, result, error, created_at, updated_at";
+
+fn job_from_row(row: &rusqlite::Row) -> rusqlite::Result<Job> {
+  let payload: String = row.get(2)?;
+  let result: Option<String> = row.get(7)?;
+  Ok(Job {
+    id: row.get(0)?,
+    kind: row.get(1)?,
+    payload: serde_json::from_str(&payload).unwrap_or(Value::Null),
+    status: row.get(3)?,
+    attempts: row.get(4)?,
+    max_attempts: row.get(5)?,
+    run_after: row.get(6)?,
+    result: result.and_then(|r| serde_json::from_str(&r).ok()),
+    error: row.get(8)?,
+    created_at: row.get(9)?,
+    updated_at: row.get(10)?,
+  })
+}
+
+fn load_job(conn: &rusqlite::Connection, id: &str) -> Result<Job, String> {
+  conn
+    .query_row(&format!("SELECT {} FROM jobs WHERE id = ?", JOB_COLUMNS), [id], job_from_row)
+    .map_err(|_| "Job not found".to_string())
+}
+
+fn emit_updated(app: &tauri::AppHandle, job: &Job) {
+  let _ = app.emit(UPDATED_EVENT, job);
+}
+
+/// Queue a job of `kind` and return its id. The worker picks it up within a
+/// few seconds; `JobState::wake` starts it right away.
+pub fn enqueue(conn: &rusqlite::Connection, kind: &str, payload: Value, max_attempts: u32) -> Result<String, String> {
+  let id = generate_id();
+  let now = now_ms();
+  conn
+    .execute(
+      "INSERT INTO jobs (id, kind, payload, status, attempts, max_attempts, run_after, created_at, updated_at)
+       VALUES (?1, ?2, ?3, 'queued', 0, ?4, ?5, ?5, ?5)",
+      (&id, kind, payload.to_string(), max_attempts.max(1), now),
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(id)
+}
+
+fn retry_delay_ms(attempts: u32) -> i64 {
+  (RETRY_BASE_MS << attempts.saturating_sub(1).min(10)).min(MAX_RETRY_DELAY_MS)
+}
+
+/// Claim the next job that's due, marking it running.
+fn claim_next(conn: &rusqlite::Connection) -> Result<Option<Job>, String> {
+  let id: Option<String> = conn
+    .query_row(
+      "SELECT id FROM jobs WHERE status = 'queued' AND run_after <= ?1 ORDER BY run_after, created_at LIMIT 1",
+      [now_ms()],
+      |row| row.get(0),
+    )
+    .ok();
+  let Some(id) = id else { return Ok(None) };
+  conn
+    .execute(
+      "UPDATE jobs SET status = 'running', attempts = attempts + 1, error = NULL, updated_at = ?1 WHERE id = ?2",
+      (now_ms(), &id),
+    )
+    .map_err(|e| e.to_string())?;
+  load_job(conn, &id).map(Some)
+}
+
+/// Record how a run ended: done, cancelled, queued again or failed for good.
+fn finish(conn: &rusqlite::Connection, job: &Job, outcome: Result<Value, String>, cancelled: bool) -> Result<Job, String> {
+  let now = now_ms();
+  match outcome {
+    _ if cancelled => conn.execute(
+      "UPDATE jobs SET status = 'cancelled', updated_at = ?1 WHERE id = ?2",
+      (now, &job.id),
+    ),
+    Ok(result) => conn.execute(
+      "UPDATE jobs SET status = 'succeeded', result = ?1, updated_at = ?2 WHERE id = ?3",
+      (result.to_string(), now, &job.id),
+    ),
+    // Held until `release_offline`; the hour is a fallback should that be missed
+    Err(e) if e == connectivity::OFFLINE_ERROR => conn.execute(
+      "UPDATE jobs SET status = 'queued', attempts = attempts - 1, error = ?1, run_after = ?2, updated_at = ?3
+       WHERE id = ?4",
+      (&e, now + MAX_RETRY_DELAY_MS, now, &job.id),
+    ),
+    Err(e) if job.attempts < job.max_attempts => conn.execute(
+      "UPDATE jobs SET status = 'queued', error = ?1, run_after = ?2, updated_at = ?3 WHERE id = ?4",
+      (&e, now + retry_delay_ms(job.attempts), now, &job.id),
+    ),
+    Err(e) => conn.execute(
+      "UPDATE jobs SET status = 'failed', error = ?1, updated_at = ?2 WHERE id = ?3",
+      (&e, now, &job.id),
+    ),
+  }
+  .map_err(|e| e.to_string())?;
+  load_job(conn, &job.id)
+}
+
+fn payload_str<'a>(payload: &'a Value, key: &str) -> Result<&'a str, String> {
+  payload[key].as_str().ok_or_else(|| format!("Job payload is missing {}", key))
+}
+
+fn hash_attachment(app: &tauri::AppHandle, payload: &Value) -> Result<Value, String> {
+  let attachment_id = payload_str(payload, "attachmentId")?;
+  let state = app.state::<DbState>();
+  let source: String = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    conn
+      .query_row(
+        "SELECT COALESCE(stored_path, original_path) FROM attachments WHERE id = ?",
+        [attachment_id],
+        |row| row.get(0),
+      )
+      .map_err(|_| "Attachment not found".to_string())?
+  };
+  let sha256 = hash_file(Path::new(&source))?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  conn
+    .execute(
+      "UPDATE attachments SET sha256 = ?1 WHERE id = ?2 AND sha256 IS NULL",
+      (&sha256, attachment_id),
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(json!({ "sha256": sha256 }))
+}
+
+fn thumbnail(app: &tauri::AppHandle, payload: &Value) -> Result<Value, String> {
+  let attachment_id = payload_str(payload, "attachmentId")?;
+  Ok(json!({ "thumbnailPath": thumbnails::prepare(app, attachment_id)? }))
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DailyExportJob {
+  date_key: String,
+  format: String,
+  custom_path: Option<String>,
+  saved_search_id: Option<String>,
+  rules: Option<export_rules::ExportRulesOverride>,
+}
+
+fn daily_export(app: &tauri::AppHandle, payload: &Value, cancel: &AtomicBool) -> Result<Value, String> {
+  let request: DailyExportJob = serde_json::from_value(payload.clone()).map_err(|e| e.to_string())?;
+  let maintenance = app.state::<maintenance::MaintenanceState>();
+  let _export = maintenance.begin_export();
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  if cancel.load(Ordering::SeqCst) {
+    return Err("Cancelled".to_string());
+  }
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let output_path = run_daily_export(
+    app,
+    &conn,
+    &request.date_key,
+    &request.format,
+    request.custom_path,
+    request.saved_search_id,
+    request.rules,
+  )?;
+  Ok(json!({ "outputPath": output_path }))
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotionExportJob {
+  database_id: String,
+  date_range: notion::DateRange,
+}
+
+fn notion_export(app: &tauri::AppHandle, payload: &Value) -> Result<Value, String> {
+  let request: NotionExportJob = serde_json::from_value(payload.clone()).map_err(|e| e.to_string())?;
+  let state = app.state::<DbState>();
+  let result = tauri::async_runtime::block_on(notion::export(&state, &request.database_id, &request.date_range))?;
+  serde_json::to_value(result).map_err(|e| e.to_string())
+}
+
+fn git_push(payload: &Value) -> Result<Value, String> {
+  let repo = payload_str(payload, "repoPath")?;
+  git_journal::push(Path::new(repo))?;
+  Ok(Value::Null)
+}
+
+fn run_job(app: &tauri::AppHandle, job: &Job, cancel: &AtomicBool) -> Result<Value, String> {
+  match job.kind.as_str() {
+    "hash_attachment" => hash_attachment(app, &job.payload),
+    "thumbnail" => thumbnail(app, &job.payload),
+    "daily_export" => daily_export(app, &job.payload, cancel),
+    "notion_export" => notion_export(app, &job.payload),
+    "git_push" => git_push(&job.payload),
+    other => Err(format!("Unknown job kind: {}", other)),
+  }
+}
+
+/// Run one due job if there is one. Returns false when the queue was idle.
+async fn run_next(app: &tauri::AppHandle) -> bool {
+  let claimed = {
+    let state = app.state::<DbState>();
+    let Ok(_guard) = state.lock.lock() else { return false };
+    state.open().map_err(|e| e.to_string()).and_then(|conn| claim_next(&conn))
+  };
+  let job = match claimed {
+    Ok(Some(job)) => job,
+    Ok(None) => return false,
+    Err(e) => {
+      tracing::warn!(error = %e, "Claiming a job failed");
+      return false;
+    }
+  };
+  emit_updated(app, &job);
+
+  let cancel = Arc::new(AtomicBool::new(false));
+  if let Ok(mut running) = app.state::<JobState>().running.lock() {
+    running.insert(job.id.clone(), cancel.clone());
+  }
+  let outcome = {
+    let (app, job, cancel) = (app.clone(), job.clone(), cancel.clone());
+    tokio::task::spawn_blocking(move || run_job(&app, &job, &cancel))
+      .await
+      .unwrap_or_else(|e| Err(format!("Job panicked: {}", e)))
+  };
+  if let Ok(mut running) = app.state::<JobState>().running.lock() {
+    running.remove(&job.id);
+  }
+  let cancelled = cancel.load(Ordering::SeqCst);
+  if let Err(e) = &outcome {
+    tracing::warn!(job_id = %job.id, kind = %job.kind, attempt = job.attempts, error = %e, "Job failed");
+  }
+
+  let finished = {
+    let state = app.state::<DbState>();
+    let Ok(_guard) = state.lock.lock() else { return true };
+    state.open()
+      .map_err(|e| e.to_string())
+      .and_then(|conn| finish(&conn, &job, outcome, cancelled))
+  };
+  match finished {
+    Ok(job) => emit_updated(app, &job),
+    Err(e) => tracing::warn!(job_id = %job.id, error = %e, "Recording job result failed"),
+  }
+  true
+}
+
+/// Make jobs held back while offline due now. Called when the connection
+/// comes back.
+pub fn release_offline(app: &tauri::AppHandle) {
+  let state = app.state::<DbState>();
+  let released = {
+    let Ok(_guard) = state.lock.lock() else { return };
+    state.open().map_err(|e| e.to_string()).and_then(|conn| {
+      conn
+        .execute(
+          "UPDATE jobs SET run_after = ?1, updated_at = ?1 WHERE status = 'queued' AND error = ?2",
+          (now_ms(), connectivity::OFFLINE_ERROR),
+        )
+        .map_err(|e| e.to_string())
+    })
+  };
+  match released {
+    Ok(0) => {}
+    Ok(count) => {
+      tracing::info!(count, "Releasing jobs held while offline");
+      app.state::<JobState>().wake();
+    }
+    Err(e) => tracing::warn!(error = %e, "Releasing jobs held while offline failed"),
+  }
+}
+
+/// Whether the worker is running a job from this database. Claiming and
+/// finishing both happen under the db lock, so holding it makes the answer
+/// stick.
+pub fn has_running(conn: &rusqlite::Connection) -> Result<bool, String> {
+  conn
+    .query_row("SELECT COUNT(*) > 0 FROM jobs WHERE status = 'running'", [], |row| row.get(0))
+    .map_err(|e| e.to_string())
+}
+
+/// Jobs left running by a previous run were interrupted; queue them again
+/// and give back the attempt the interruption cost. Also forgets finished
+/// jobs past `KEEP_FINISHED_MS`.
+fn recover(conn: &rusqlite::Connection) -> Result<(), String> {
+  let now = now_ms();
+  conn
+    .execute(
+      "UPDATE jobs SET status = 'queued', attempts = MAX(attempts - 1, 0), updated_at = ?1 WHERE status = 'running'",
+      [now],
+    )
+    .map_err(|e| e.to_string())?;
+  conn
+    .execute(
+      "DELETE FROM jobs WHERE status IN ('succeeded', 'failed', 'cancelled') AND updated_at < ?1",
+      [now - KEEP_FINISHED_MS],
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+pub fn spawn_job_worker(app: tauri::AppHandle) {
+  supervisor::supervise(app, "jobs", |app| async move {
+    {
+      let state = app.state::<DbState>();
+      let recovered = match state.lock.lock() {
+        Ok(_guard) => state.open().map_err(|e| e.to_string()).and_then(|conn| recover(&conn)),
+        Err(_) => Err("db lock".to_string()),
+      };
+      if let Err(e) = recovered {
+        tracing::warn!(error = %e, "Recovering interrupted jobs failed");
+      }
+    }
+    loop {
+      if !run_next(&app).await {
+        let state = app.state::<JobState>();
+        let _ = tokio::time::timeout(MAX_IDLE, state.notify.notified()).await;
+      }
+    }
+  });
+}
+
+/// Jobs, newest first, optionally only those in `status`.
+#[tauri::command]
+pub fn list_jobs(state: tauri::State<DbState>, status: Option<String>, limit: Option<u32>) -> Result<Vec<Job>, String> {
+  app_lock::ensure_unlocked()?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let jobs = conn
+    .prepare(&format!(
+      "SELECT {} FROM jobs WHERE ?1 IS NULL OR status = ?1 ORDER BY created_at DESC LIMIT ?2",
+      JOB_COLUMNS
+    ))
+    .map_err(|e| e.to_string())?
+    .query_map((&status, limit.unwrap_or(DEFAULT_LIST_LIMIT)), job_from_row)
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(jobs)
+}
+
+/// Cancel a queued or running job. Finished jobs are left as they are.
+#[tauri::command]
+pub fn cancel_job(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  jobs: tauri::State<JobState>,
+  id: String,
+) -> Result<Job, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let job = load_job(&conn, &id)?;
+  match job.status.as_str() {
+    "queued" => {
+      conn
+        .execute(
+          "UPDATE jobs SET status = 'cancelled', updated_at = ?1 WHERE id = ?2",
+          (now_ms(), &id),
+        )
+        .map_err(|e| e.to_string())?;
+      let job = load_job(&conn, &id)?;
+      emit_updated(&app, &job);
+      Ok(job)
+    }
+    // The worker records the cancellation once the run returns
+    "running" => {
+      if let Some(flag) = jobs.running.lock().map_err(|_| "jobs lock".to_string())?.get(&id) {
+        flag.store(true, Ordering::SeqCst);
+      }
+      Ok(job)
+    }
+    other => Err(format!("Job already {}", other)),
+  }
+}
+
+/// Queue a failed or cancelled job again with a fresh retry budget.
+#[tauri::command]
+pub fn retry_job(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  jobs: tauri::State<JobState>,
+  id: String,
+) -> Result<Job, String> {
+  let job = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let now = now_ms();
+    let updated = conn
+      .execute(
+        "UPDATE jobs SET status = 'queued', attempts = 0, error = NULL, run_after = ?1, updated_at = ?1
+         WHERE id = ?2 AND status IN ('failed', 'cancelled')",
+        (now, &id),
+      )
+      .map_err(|e| e.to_string())?;
+    let job = load_job(&conn, &id)?;
+    if updated == 0 {
+      return Err(format!("Job is {}", job.status));
+    }
+    job
+  };
+  emit_updated(&app, &job);
+  jobs.wake();
+  Ok(job)
+}
+
+/// Queue a daily export instead of writing it while the caller waits. The
+/// output path arrives as the job's result.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn queue_daily_export(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  jobs: tauri::State<JobState>,
+  date_key: String,
+  format: String,
+  custom_path: Option<String>,
+  saved_search_id: Option<String>,
+  rules: Option<export_rules::ExportRulesOverride>,
+) -> Result<Job, String> {
+  app_lock::ensure_unlocked()?;
+  let payload = serde_json::to_value(DailyExportJob { date_key, format, custom_path, saved_search_id, rules })
+    .map_err(|e| e.to_string())?;
+  let job = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let id = enqueue(&conn, "daily_export", payload, 3)?;
+    load_job(&conn, &id)?
+  };
+  emit_updated(&app, &job);
+  jobs.wake();
+  Ok(job)
+}
+
+/// Queue an export to Notion. Unlike `export_to_notion` it survives being
+/// offline: the job waits for the connection and then runs.
+#[tauri::command]
+pub fn queue_notion_export(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  jobs: tauri::State<JobState>,
+  database_id: String,
+  date_range: notion::DateRange,
+) -> Result<Job, String> {
+  app_lock::ensure_unlocked()?;
+  let payload = serde_json::to_value(NotionExportJob { database_id, date_range }).map_err(|e| e.to_string())?;
+  let job = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let id = enqueue(&conn, "notion_export", payload, 3)?;
+    load_job(&conn, &id)?
+  };
+  emit_updated(&app, &job);
+  jobs.wake();
+  Ok(job)
+}
+
+/// Queue a push of the journal repository at `repo`, unless one is already
+/// waiting; it pushes whatever has been committed by the time it runs.
+pub fn queue_git_push(app: &tauri::AppHandle, repo: &Path) -> Result<(), String> {
+  let payload = json!({ "repoPath": repo.to_string_lossy() });
+  let state = app.state::<DbState>();
+  let job = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let waiting: bool = conn
+      .query_row(
+        "SELECT COUNT(*) > 0 FROM jobs WHERE kind = 'git_push' AND status = 'queued' AND payload = ?",
+        [payload.to_string()],
+        |row| row.get(0),
+      )
+      .map_err(|e| e.to_string())?;
+    if waiting {
+      return Ok(());
+    }
+    let id = enqueue(&conn, "git_push", payload, 5)?;
+    load_job(&conn, &id)?
+  };
+  emit_updated(app, &job);
+  app.state::<JobState>().wake();
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn queue() -> rusqlite::Connection {
+    let path = std::env::temp_dir().join(format!("papa-jobs-{}.sqlite", generate_id()));
+    crate::init_db(&path).unwrap();
+    crate::open_db(&path).unwrap()
+  }
+
+  fn make_due(conn: &rusqlite::Connection) {
+    conn.execute("UPDATE jobs SET run_after = 0", []).unwrap();
+  }
+
+  #[test]
+  fn retry_delay_doubles_up_to_the_cap() {
+    assert_eq!(retry_delay_ms(1), RETRY_BASE_MS);
+    assert_eq!(retry_delay_ms(2), 2 * RETRY_BASE_MS);
+    assert_eq!(retry_delay_ms(30), MAX_RETRY_DELAY_MS);
+  }
+
+  #[test]
+  fn claims_the_oldest_due_job() {
+    let conn = queue();
+    let first = enqueue(&conn, "thumbnail", json!({ "attachmentId": "a" }), 1).unwrap();
+    enqueue(&conn, "thumbnail", json!({ "attachmentId": "b" }), 1).unwrap();
+    assert!(!has_running(&conn).unwrap());
+
+    let job = claim_next(&conn).unwrap().unwrap();
+    assert_eq!((job.id.as_str(), job.status.as_str(), job.attempts), (first.as_str(), "running", 1));
+    assert!(has_running(&conn).unwrap());
+  }
+
+  #[test]
+  fn retries_until_the_attempts_are_used_up() {
+    let conn = queue();
+    enqueue(&conn, "thumbnail", json!({}), 2).unwrap();
+
+    let job = finish(&conn, &claim_next(&conn).unwrap().unwrap(), Err("boom".to_string()), false).unwrap();
+    assert_eq!(job.status, "queued");
+    assert!(job.run_after > job.updated_at);
+    assert!(claim_next(&conn).unwrap().is_none());
+
+    make_due(&conn);
+    let job = finish(&conn, &claim_next(&conn).unwrap().unwrap(), Err("boom".to_string()), false).unwrap();
+    assert_eq!((job.status.as_str(), job.attempts, job.error.as_deref()), ("failed", 2, Some("boom")));
+  }
+
+  #[test]
+  fn waiting_for_the_connection_costs_no_attempt() {
+    let conn = queue();
+    enqueue(&conn, "notion_export", json!({}), 1).unwrap();
+    let job = claim_next(&conn).unwrap().unwrap();
+    let job = finish(&conn, &job, Err(connectivity::OFFLINE_ERROR.to_string()), false).unwrap();
+    assert_eq!((job.status.as_str(), job.attempts), ("queued", 0));
+  }
+
+  #[test]
+  fn cancelling_discards_the_result() {
+    let conn = queue();
+    enqueue(&conn, "thumbnail", json!({}), 1).unwrap();
+    let job = finish(&conn, &claim_next(&conn).unwrap().unwrap(), Ok(json!("done")), true).unwrap();
+    assert_eq!(job.status, "cancelled");
+    assert!(job.result.is_none());
+  }
+
+  #[test]
+  fn recovery_requeues_interrupted_jobs_without_using_an_attempt() {
+    let conn = queue();
+    let id = enqueue(&conn, "thumbnail", json!({}), 1).unwrap();
+    claim_next(&conn).unwrap().unwrap();
+
+    recover(&conn).unwrap();
+    let job = load_job(&conn, &id).unwrap();
+    assert_eq!((job.status.as_str(), job.attempts), ("queued", 0));
+    assert!(!has_running(&conn).unwrap());
+  }
+}
@@ -0,0 +1,198 @@
+// End-of-day reflection: a daily question and `journal` events for the
+// answers.
+//
+// The built-in question is picked by date so it stays the same all day. When
+// the frontend passes LLM credentials the question is personalized from
+// today's events instead, falling back to the built-in one if the call fails.
+
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  generate_id, location, mentions, now_ms, request_llm, tags, timezone, usage_metrics, DbState, LlmRequest, TimelineEvent,
+  TimelineEventWithAttachments,
+};
+
+const PROMPTS: &[&str] = &[
+  "What made you smile today?",
+  "What took more energy than you expected?",
+  "What's one thing you learned today?",
+  "Who did you enjoy spending time with today?",
+  "What would you do differently if you could redo today?",
+  "What are you grateful for right now?",
+  "What's still on your mind from today?",
+  "Which moment today do you want to remember?",
+  "What did you finish today, however small?",
+  "What are you looking forward to tomorrow?",
+  "When did you feel most focused today?",
+  "What drained you today, and what recharged you?",
+  "What's one thing you'd like to let go of tonight?",
+  "How did you take care of yourself today?",
+];
+
+// How many of today's events go into the personalization prompt
+const MAX_CONTEXT_EVENTS: usize = 20;
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptModel {
+  pub(crate) provider: String,
+  pub(crate) api_key: String,
+  pub(crate) model: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyPrompt {
+  question: String,
+  /// "builtin" or "llm"
+  source: String,
+  date: String,
+  /// Whether a journal entry was already written today
+  answered: bool,
+}
+
+fn builtin_prompt(date: chrono::NaiveDate) -> &'static str {
+  PROMPTS[date.num_days_from_ce() as usize % PROMPTS.len()]
+}
+
+fn today_start_ms() -> Result<i64, String> {
+  timezone::day_start(timezone::today()).ok_or_else(|| "Invalid local time".to_string())
+}
+
+/// One line per event for the LLM: time, type and whatever text it has.
+fn describe_today(conn: &rusqlite::Connection, since: i64) -> Result<Vec<String>, String> {
+  let lines = conn
+    .prepare(
+      "SELECT created_at, type, COALESCE(title, ''), COALESCE(note, '')
+       FROM timeline_events
+       WHERE created_at >= ?1 AND is_deleted = 0 AND scheduled_for IS NULL AND type != 'journal'
+         AND ai_opt_out = 0
+       ORDER BY created_at ASC
+       LIMIT ?2",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map((since, MAX_CONTEXT_EVENTS as i64), |row| {
+      let created_at: i64 = row.get(0)?;
+      let event_type: String = row.get(1)?;
+      let title: String = row.get(2)?;
+      let note: String = row.get(3)?;
+      let time = timezone::datetime(created_at).map(|dt| dt.format("%H:%M").to_string()).unwrap_or_default();
+      let text: String = [title, note]
+        .into_iter()
+        .filter(|s| !s.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(" - ");
+      Ok(format!("{} [{}] {}", time, event_type, text.chars().take(200).collect::<String>()))
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(lines)
+}
+
+#[tauri::command]
+pub async fn get_daily_prompt(
+  state: tauri::State<'_, DbState>,
+  llm: Option<PromptModel>,
+) -> Result<DailyPrompt, String> {
+  let _timer = usage_metrics::time_async("get_daily_prompt");
+  let today = timezone::today();
+  let since = today_start_ms()?;
+
+  let (answered, events) = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let answered: i64 = conn
+      .query_row(
+        "SELECT COUNT(*) FROM timeline_events
+         WHERE type = 'journal' AND created_at >= ? AND is_deleted = 0",
+        [since],
+        |row| row.get(0),
+      )
+      .map_err(|e| e.to_string())?;
+    let events = match llm {
+      Some(_) => describe_today(&conn, since)?,
+      None => Vec::new(),
+    };
+    (answered > 0, events)
+  };
+
+  let mut prompt = DailyPrompt {
+    question: builtin_prompt(today).to_string(),
+    source: "builtin".to_string(),
+    date: today.format("%Y-%m-%d").to_string(),
+    answered,
+  };
+
+  // Nothing to personalize from; the built-in question is as good
+  let Some(model) = llm.filter(|_| !events.is_empty()) else { return Ok(prompt) };
+
+  let request = LlmRequest {
+    provider: model.provider,
+    api_key: model.api_key,
+    model: model.model,
+    prompt: format!(
+      "Here is what I captured today:\n{}\n\n\
+       Ask me one short, warm, reflective question about my day for an \
+       end-of-day journal. Reply with the question only.",
+      events.join("\n")
+    ),
+    max_tokens: Some(80),
+  };
+  match request_llm(request).await {
+    Ok(question) if !question.trim().is_empty() => {
+      prompt.question = question.trim().trim_matches('"').to_string();
+      prompt.source = "llm".to_string();
+    }
+    Ok(_) => {}
+    Err(e) => tracing::warn!(error = %e, "Daily prompt personalization failed"),
+  }
+  Ok(prompt)
+}
+
+/// Store the answer to a daily prompt as a `journal` event titled with the
+/// question.
+#[tauri::command]
+pub fn save_journal_entry(
+  state: tauri::State<DbState>,
+  question: String,
+  answer: String,
+) -> Result<TimelineEventWithAttachments, String> {
+  if answer.trim().is_empty() {
+    return Err("Journal entry is empty".to_string());
+  }
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let event_id = generate_id();
+  let created_at = now_ms();
+  let title = Some(question.trim().to_string()).filter(|q| !q.is_empty());
+
+  conn.execute(
+    "INSERT INTO timeline_events (id, type, title, note, created_at, source, is_deleted)
+     VALUES (?1, 'journal', ?2, ?3, ?4, 'journal', 0)",
+    (&event_id, &title, &answer, created_at),
+  ).map_err(|e| e.to_string())?;
+  tags::auto_tag(&conn, &event_id);
+  tags::sync_hashtags(&conn, &event_id)?;
+  mentions::index(&conn, &event_id)?;
+  location::stamp(&conn, &event_id)?;
+
+  let event = TimelineEvent {
+    id: event_id,
+    event_type: "journal".to_string(),
+    title,
+    note: Some(answer),
+    text_content: None,
+    created_at,
+    source: Some("journal".to_string()),
+    is_deleted: false,
+    metadata: None,
+    ai_opt_out: false,
+    locked: false,
+  };
+
+  Ok(TimelineEventWithAttachments { event, attachments: vec![], reminders: vec![] })
+}
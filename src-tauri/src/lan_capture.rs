@@ -0,0 +1,546 @@
+// Capture from a phone on the same network.
+//
+// Off by default. When switched on, a small HTTP listener accepts notes and
+// photos and turns them into timeline events with `source = 'mobile'`, and
+// the service is announced over mDNS as `_papa._tcp` so a companion app can
+// find it. Every upload needs a device token from `pair_lan_device`; only a
+// hash of it is kept, and revoking a device deletes that hash.
+//
+// The listener speaks plain HTTP, so tokens and uploads cross the network
+// unencrypted. Requests are only served to addresses on one of this
+// machine's own subnets; anything routed in from further away is refused.
+//
+//   GET  /ping           no auth, identifies the service
+//   POST /capture/text   {"text": "...", "note": "..."}
+//   POST /capture/photo  raw image bytes, optional X-File-Name header
+
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
+
+use crate::permissions::{self, Permission};
+use crate::{
+  cas, compression, config, generate_id, ingest, jobs, location, mentions, now_ms, rate_limit, read_setting, tags,
+  write_setting,
+  Attachment, DbState, TimelineEvent, TimelineEventWithAttachments,
+};
+
+pub const LAN_CAPTURE_KEY: &str = "lan.capture";
+const SERVICE_TYPE: &str = "_papa._tcp.local.";
+const DEFAULT_PORT: u16 = 8765;
+// Photos from phones run to a few MB; anything far beyond is refused
+const MAX_BODY_BYTES: u64 = 25 * 1024 * 1024;
+const MAX_TEXT_BYTES: u64 = 256 * 1024;
+// Enough that one stalled upload doesn't hold up every other phone
+const WORKERS: usize = 4;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LanCaptureConfig {
+  enabled: bool,
+  port: u16,
+}
+
+impl Default for LanCaptureConfig {
+  fn default() -> Self {
+    Self { enabled: false, port: DEFAULT_PORT }
+  }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LanCaptureStatus {
+  config: LanCaptureConfig,
+  running: bool,
+  /// Local addresses a phone can reach the listener on
+  addresses: Vec<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LanDevice {
+  id: String,
+  name: String,
+  created_at: i64,
+  last_seen_at: Option<i64>,
+}
+
+/// Everything the phone needs; the token is shown only this once. It is sent
+/// over plain HTTP, so pair only on a network you trust.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LanPairing {
+  device: LanDevice,
+  token: String,
+  port: u16,
+  addresses: Vec<String>,
+  service_type: String,
+}
+
+#[derive(Deserialize)]
+struct TextCapture {
+  text: String,
+  #[serde(default)]
+  note: Option<String>,
+}
+
+struct Listener {
+  server: Arc<tiny_http::Server>,
+  mdns: Option<ServiceDaemon>,
+}
+
+#[derive(Default)]
+pub struct LanState {
+  listener: Mutex<Option<Listener>>,
+}
+
+fn load_config(conn: &rusqlite::Connection) -> LanCaptureConfig {
+  read_setting(conn, LAN_CAPTURE_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn local_addresses() -> Vec<String> {
+  if_addrs::get_if_addrs()
+    .map(|ifaces| {
+      ifaces
+        .into_iter()
+        .filter(|iface| !iface.is_loopback() && iface.ip().is_ipv4())
+        .map(|iface| iface.ip().to_string())
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Whether `remote` shares a subnet with one of this machine's IPv4
+/// interfaces. The listener binds IPv4 only.
+fn on_local_subnet(remote: IpAddr) -> bool {
+  let IpAddr::V4(remote) = remote else { return false };
+  let same_subnet = |ip: Ipv4Addr, netmask: Ipv4Addr| {
+    let mask = u32::from(netmask);
+    mask != 0 && u32::from(ip) & mask == u32::from(remote) & mask
+  };
+  if_addrs::get_if_addrs()
+    .map(|ifaces| {
+      ifaces.iter().any(|iface| match &iface.addr {
+        if_addrs::IfAddr::V4(v4) => same_subnet(v4.ip, v4.netmask),
+        _ => false,
+      })
+    })
+    .unwrap_or(false)
+}
+
+fn token_hash(token: &str) -> String {
+  hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn respond(request: tiny_http::Request, status: u16, body: serde_json::Value) {
+  let header = tiny_http::Header::from_bytes("Content-Type", "application/json").expect("static header");
+  let response = tiny_http::Response::from_string(body.to_string())
+    .with_status_code(status)
+    .with_header(header);
+  let _ = request.respond(response);
+}
+
+fn header<'a>(request: &'a tiny_http::Request, name: &'static str) -> Option<&'a str> {
+  request
+    .headers()
+    .iter()
+    .find(|h| h.field.equiv(name))
+    .map(|h| h.value.as_str())
+}
+
+/// The paired device's name, recording that it was seen.
+fn authorize(conn: &rusqlite::Connection, request: &tiny_http::Request) -> Option<String> {
+  let token = header(request, "Authorization")?.strip_prefix("Bearer ")?.trim();
+  let hash = token_hash(token);
+  let name: String = conn
+    .query_row("SELECT name FROM lan_devices WHERE token_hash = ?", [&hash], |row| row.get(0))
+    .ok()?;
+  let _ = conn.execute("UPDATE lan_devices SET last_seen_at = ?1 WHERE token_hash = ?2", (now_ms(), &hash));
+  Some(name)
+}
+
+fn read_body(request: &mut tiny_http::Request, limit: u64) -> Result<Vec<u8>, String> {
+  let mut body = Vec::new();
+  request
+    .as_reader()
+    .take(limit + 1)
+    .read_to_end(&mut body)
+    .map_err(|e| e.to_string())?;
+  if body.len() as u64 > limit {
+    return Err("Upload is too large".to_string());
+  }
+  Ok(body)
+}
+
+fn capture_text(conn: &rusqlite::Connection, device: &str, body: &[u8]) -> Result<TimelineEventWithAttachments, String> {
+  let capture: TextCapture = serde_json::from_slice(body).map_err(|e| format!("Invalid request: {}", e))?;
+  if capture.text.trim().is_empty() {
+    return Err("Text is empty".to_string());
+  }
+  let admission = rate_limit::check("mobile", (device, &capture.text, &capture.note))?;
+  let event_id = generate_id();
+  let created_at = now_ms();
+  let metadata = json!({ "device": device });
+  conn.execute(
+    "INSERT INTO timeline_events (id, type, note, text_content, created_at, source, is_deleted, metadata)
+     VALUES (?1, 'text', ?2, ?3, ?4, 'mobile', 0, ?5)",
+    (&event_id, &capture.note, &capture.text, created_at, metadata.to_string()),
+  ).map_err(|e| e.to_string())?;
+  compression::compress_if_large(conn, &event_id)?;
+  tags::auto_tag(conn, &event_id);
+  tags::sync_hashtags(conn, &event_id)?;
+  mentions::index(conn, &event_id)?;
+
+  let event = TimelineEvent {
+    id: event_id,
+    event_type: "text".to_string(),
+    title: None,
+    note: capture.note,
+    text_content: Some(capture.text),
+    created_at,
+    source: Some("mobile".to_string()),
+    is_deleted: false,
+    metadata: Some(metadata),
+    ai_opt_out: false,
+    locked: false,
+  };
+  rate_limit::record(admission);
+  Ok(TimelineEventWithAttachments { event, attachments: vec![], reminders: vec![] })
+}
+
+fn capture_photo(
+  app: &tauri::AppHandle,
+  conn: &rusqlite::Connection,
+  device: &str,
+  file_name: Option<&str>,
+  bytes: Vec<u8>,
+) -> Result<TimelineEventWithAttachments, String> {
+  ingest::check_size(&ingest::load_policy(conn), bytes.len() as u64)?;
+  let admission = rate_limit::check("mobile", (device, &bytes))?;
+  let kind = infer::get(&bytes)
+    .filter(|t| t.matcher_type() == infer::MatcherType::Image)
+    .ok_or_else(|| "Upload is not an image".to_string())?;
+  let file_name = file_name
+    .filter(|n| !n.trim().is_empty())
+    .map(|n| n.to_string())
+    .unwrap_or_else(|| format!("photo.{}", kind.extension()));
+
+  let (path_str, sha256) = cas::store_bytes(conn, &app.state::<DbState>().data_dir(), &file_name, &bytes)?;
+  let (width, height) = image::image_dimensions(&path_str)
+    .map(|(w, h)| (Some(w as i32), Some(h as i32)))
+    .unwrap_or((None, None));
+
+  let event_id = generate_id();
+  let created_at = now_ms();
+  let title = Some(format!("Photo from {}", device));
+  let metadata = json!({ "device": device });
+  conn.execute(
+    "INSERT INTO timeline_events (id, type, title, created_at, source, is_deleted, metadata)
+     VALUES (?1, 'image', ?2, ?3, 'mobile', 0, ?4)",
+    (&event_id, &title, created_at, metadata.to_string()),
+  ).map_err(|e| e.to_string())?;
+
+  let attachment = Attachment {
+    id: generate_id(),
+    event_id: event_id.clone(),
+    kind: "image".to_string(),
+    original_path: path_str.clone(),
+    stored_path: Some(path_str),
+    file_name: Some(file_name),
+    mime_type: Some(kind.mime_type().to_string()),
+    size_bytes: Some(bytes.len() as i64),
+    sha256: Some(sha256),
+    width,
+    height,
+    created_at,
+    duration_ms: None,
+    derived_from: None,
+  };
+  conn.execute(
+    "INSERT INTO attachments (id, event_id, kind, original_path, stored_path, file_name, mime_type, size_bytes, sha256, width, height, created_at)
+     VALUES (?1, ?2, 'image', ?3, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    rusqlite::params![
+      &attachment.id,
+      &event_id,
+      &attachment.original_path,
+      &attachment.file_name,
+      &attachment.mime_type,
+      attachment.size_bytes,
+      &attachment.sha256,
+      width,
+      height,
+      created_at,
+    ],
+  ).map_err(|e| e.to_string())?;
+  jobs::enqueue(conn, "thumbnail", json!({ "attachmentId": &attachment.id }), 1)?;
+  location::stamp(conn, &event_id)?;
+
+  let event = TimelineEvent {
+    id: event_id,
+    event_type: "image".to_string(),
+    title,
+    note: None,
+    text_content: None,
+    created_at,
+    source: Some("mobile".to_string()),
+    is_deleted: false,
+    metadata: Some(metadata),
+    ai_opt_out: false,
+    locked: false,
+  };
+  rate_limit::record(admission);
+  Ok(TimelineEventWithAttachments { event, attachments: vec![attachment], reminders: vec![] })
+}
+
+fn handle(app: &tauri::AppHandle, mut request: tiny_http::Request) {
+  if !request.remote_addr().is_some_and(|addr| on_local_subnet(addr.ip())) {
+    tracing::warn!(remote = ?request.remote_addr(), "LAN capture from outside the local subnet");
+    return respond(request, 403, json!({ "error": "Forbidden" }));
+  }
+  let method = request.method().clone();
+  let path = request.url().split('?').next().unwrap_or_default().to_string();
+
+  if method == tiny_http::Method::Get && path == "/ping" {
+    return respond(request, 200, json!({ "service": "papa" }));
+  }
+  if method != tiny_http::Method::Post || !matches!(path.as_str(), "/capture/text" | "/capture/photo") {
+    return respond(request, 404, json!({ "error": "Not found" }));
+  }
+
+  // Check the token before reading a byte of the body, so an unpaired
+  // device can't tie up the listener with a large upload
+  let state = app.state::<DbState>();
+  let authorized = {
+    let Ok(_guard) = state.lock.lock() else {
+      return respond(request, 503, json!({ "error": "Busy" }));
+    };
+    state.open().map(|conn| authorize(&conn, &request))
+  };
+  let device = match authorized {
+    Ok(Some(device)) => device,
+    Ok(None) => {
+      tracing::warn!(remote = ?request.remote_addr(), "LAN capture with unknown token");
+      return respond(request, 401, json!({ "error": "Unauthorized" }));
+    }
+    Err(e) => return respond(request, 500, json!({ "error": e.to_string() })),
+  };
+
+  let limit = if path == "/capture/text" { MAX_TEXT_BYTES } else { MAX_BODY_BYTES };
+  if request.body_length().is_some_and(|length| length as u64 > limit) {
+    return respond(request, 413, json!({ "error": "Upload is too large" }));
+  }
+  let body = match read_body(&mut request, limit) {
+    Ok(body) => body,
+    Err(e) => return respond(request, 413, json!({ "error": e })),
+  };
+
+  let result = {
+    let Ok(_guard) = state.lock.lock() else {
+      return respond(request, 503, json!({ "error": "Busy" }));
+    };
+    let conn = match state.open() {
+      Ok(conn) => conn,
+      Err(e) => return respond(request, 500, json!({ "error": e.to_string() })),
+    };
+    if path == "/capture/text" {
+      capture_text(&conn, &device, &body)
+    } else {
+      capture_photo(app, &conn, &device, header(&request, "X-File-Name"), body)
+    }
+  };
+
+  match result {
+    Ok(created) => {
+      respond(request, 201, json!({ "eventId": created.event.id }));
+      if let Some(window) = app.get_webview_window("main") {
+        let _ = window.emit("mobile-capture", &created);
+      }
+    }
+    Err(e) if rate_limit::is_rate_limited(&e) => respond(request, 429, json!({ "error": e })),
+    Err(e) => respond(request, 400, json!({ "error": e })),
+  }
+}
+
+fn announce(port: u16) -> Result<ServiceDaemon, String> {
+  let daemon = ServiceDaemon::new().map_err(|e| e.to_string())?;
+  let host = std::env::var("COMPUTERNAME")
+    .or_else(|_| std::env::var("HOSTNAME"))
+    .unwrap_or_else(|_| "papa".to_string());
+  let instance: String = host.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect();
+  let instance = if instance.is_empty() { "papa".to_string() } else { instance };
+  let info = ServiceInfo::new(
+    SERVICE_TYPE,
+    &format!("Papa on {}", instance),
+    &format!("{}.local.", instance),
+    "",
+    port,
+    &[("path", "/capture")][..],
+  )
+  .map_err(|e| e.to_string())?
+  .enable_addr_auto();
+  daemon.register(info).map_err(|e| e.to_string())?;
+  Ok(daemon)
+}
+
+fn stop(state: &LanState) {
+  let Ok(mut slot) = state.listener.lock() else { return };
+  if let Some(listener) = slot.take() {
+    // Each call releases one waiting worker
+    for _ in 0..WORKERS {
+      listener.server.unblock();
+    }
+    if let Some(mdns) = listener.mdns {
+      let _ = mdns.shutdown();
+    }
+  }
+}
+
+fn start(app: &tauri::AppHandle, port: u16) -> Result<(), String> {
+  let state = app.state::<LanState>();
+  stop(&state);
+
+  let server = Arc::new(
+    tiny_http::Server::http(("0.0.0.0", port)).map_err(|e| format!("Could not listen on port {}: {}", port, e))?,
+  );
+  // Discovery is a convenience; the listener works without it
+  let mdns = announce(port)
+    .map_err(|e| tracing::warn!(error = %e, "mDNS announcement failed"))
+    .ok();
+
+  for _ in 0..WORKERS {
+    let worker = server.clone();
+    let app_handle = app.clone();
+    std::thread::spawn(move || {
+      for request in worker.incoming_requests() {
+        handle(&app_handle, request);
+      }
+    });
+  }
+  tracing::info!(port, "LAN capture listening");
+
+  *state.listener.lock().map_err(|_| "lan lock".to_string())? = Some(Listener { server, mdns });
+  Ok(())
+}
+
+/// Start or stop the listener to match the stored setting, at launch and
+/// after switching profiles.
+pub fn restore(app: &tauri::AppHandle) {
+  let config = {
+    let state = app.state::<DbState>();
+    let Ok(_guard) = state.lock.lock() else { return };
+    match state.open() {
+      Ok(conn) => load_config(&conn),
+      Err(_) => return,
+    }
+  };
+  if config.enabled && permissions::allowed(Permission::Lan) {
+    if let Err(e) = start(app, config.port) {
+      tracing::warn!(error = %e, "LAN capture not started");
+    }
+  } else {
+    stop(&app.state::<LanState>());
+  }
+}
+
+#[tauri::command]
+pub fn get_lan_capture_status(
+  state: tauri::State<DbState>,
+  lan: tauri::State<LanState>,
+) -> Result<LanCaptureStatus, String> {
+  let config = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    load_config(&conn)
+  };
+  let running = lan.listener.lock().map_err(|_| "lan lock".to_string())?.is_some();
+  Ok(LanCaptureStatus { config, running, addresses: local_addresses() })
+}
+
+#[tauri::command]
+pub fn set_lan_capture(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  lan: tauri::State<LanState>,
+  lan_capture: LanCaptureConfig,
+) -> Result<LanCaptureStatus, String> {
+  if lan_capture.port < 1024 {
+    return Err("Port must be 1024 or higher".to_string());
+  }
+  if lan_capture.enabled {
+    permissions::ensure(Permission::Lan)?;
+    start(&app, lan_capture.port)?;
+  } else {
+    stop(&lan);
+  }
+  let json = serde_json::to_string(&lan_capture).map_err(|e| e.to_string())?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, LAN_CAPTURE_KEY, &json)?;
+  }
+  config::emit_changed(&app, vec![LAN_CAPTURE_KEY.to_string()]);
+  get_lan_capture_status(state, lan)
+}
+
+/// Register a phone and return the token it has to send with uploads.
+#[tauri::command]
+pub fn pair_lan_device(state: tauri::State<DbState>, name: String) -> Result<LanPairing, String> {
+  let name = name.trim().to_string();
+  if name.is_empty() {
+    return Err("Device name must not be empty".to_string());
+  }
+  let token = hex::encode(rand::random::<[u8; 32]>());
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let device = LanDevice { id: generate_id(), name, created_at: now_ms(), last_seen_at: None };
+  conn.execute(
+    "INSERT INTO lan_devices (id, name, token_hash, created_at) VALUES (?1, ?2, ?3, ?4)",
+    (&device.id, &device.name, token_hash(&token), device.created_at),
+  ).map_err(|e| e.to_string())?;
+
+  Ok(LanPairing {
+    device,
+    token,
+    port: load_config(&conn).port,
+    addresses: local_addresses(),
+    service_type: SERVICE_TYPE.to_string(),
+  })
+}
+
+#[tauri::command]
+pub fn list_lan_devices(state: tauri::State<DbState>) -> Result<Vec<LanDevice>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let devices = conn
+    .prepare("SELECT id, name, created_at, last_seen_at FROM lan_devices ORDER BY created_at")
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| {
+      Ok(LanDevice { id: row.get(0)?, name: row.get(1)?, created_at: row.get(2)?, last_seen_at: row.get(3)? })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(devices)
+}
+
+/// Forget a device; its token stops working immediately.
+#[tauri::command]
+pub fn revoke_lan_device(state: tauri::State<DbState>, device_id: String) -> Result<(), String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let removed = conn
+    .execute("DELETE FROM lan_devices WHERE id = ?", [&device_id])
+    .map_err(|e| e.to_string())?;
+  if removed == 0 {
+    return Err("Device not found".to_string());
+  }
+  Ok(())
+}
@@ -0,0 +1,143 @@
+// Model lists for the settings model picker.
+//
+// OpenAI and Ollama are asked which models are available; Anthropic's list is
+// the documented set below. Context lengths are filled in where the provider
+// reports them.
+
+use serde::Serialize;
+
+use crate::{connectivity, usage_metrics};
+use crate::permissions::{self, Permission};
+
+const OPENAI_MODELS_URL: &str = "https://api.openai.com/v1/models";
+const OLLAMA_DEFAULT_URL: &str = "http://localhost:11434";
+
+// (id, display name, context window in tokens)
+const ANTHROPIC_MODELS: &[(&str, &str, u32)] = &[
+  ("claude-opus-4-0", "Claude Opus 4", 200_000),
+  ("claude-sonnet-4-0", "Claude Sonnet 4", 200_000),
+  ("claude-3-7-sonnet-latest", "Claude Sonnet 3.7", 200_000),
+  ("claude-3-5-sonnet-latest", "Claude Sonnet 3.5", 200_000),
+  ("claude-3-5-haiku-latest", "Claude Haiku 3.5", 200_000),
+];
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmModel {
+  id: String,
+  display_name: Option<String>,
+  context_length: Option<u64>,
+}
+
+async fn get_json(request: reqwest::RequestBuilder) -> Result<serde_json::Value, String> {
+  let response = request
+    .send()
+    .await
+    .map_err(|e| connectivity::request_error("Request failed", e))?;
+  if !response.status().is_success() {
+    let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+    return Err(format!("API error: {}", error_text));
+  }
+  response
+    .json()
+    .await
+    .map_err(|e| format!("Failed to parse response: {}", e))
+}
+
+async fn openai_models(api_key: &str) -> Result<Vec<LlmModel>, String> {
+  permissions::ensure(Permission::Network)?;
+  let client = connectivity::client_builder().build().map_err(|e| e.to_string())?;
+  let json = get_json(
+    client
+      .get(OPENAI_MODELS_URL)
+      .header("Authorization", format!("Bearer {}", api_key)),
+  )
+  .await?;
+
+  let mut models: Vec<LlmModel> = json["data"]
+    .as_array()
+    .map(|data| {
+      data
+        .iter()
+        .filter_map(|m| m["id"].as_str())
+        .map(|id| LlmModel { id: id.to_string(), display_name: None, context_length: None })
+        .collect()
+    })
+    .unwrap_or_default();
+  models.sort_by(|a, b| a.id.cmp(&b.id));
+  Ok(models)
+}
+
+fn anthropic_models() -> Vec<LlmModel> {
+  ANTHROPIC_MODELS
+    .iter()
+    .map(|(id, name, context)| LlmModel {
+      id: id.to_string(),
+      display_name: Some(name.to_string()),
+      context_length: Some(*context as u64),
+    })
+    .collect()
+}
+
+/// Context length from `/api/show`, reported as `<architecture>.context_length`.
+async fn ollama_context_length(client: &reqwest::Client, base_url: &str, model: &str) -> Option<u64> {
+  let json = get_json(
+    client
+      .post(format!("{}/api/show", base_url))
+      .json(&serde_json::json!({ "model": model })),
+  )
+  .await
+  .ok()?;
+  json["model_info"]
+    .as_object()?
+    .iter()
+    .find(|(key, _)| key.ends_with(".context_length"))
+    .and_then(|(_, value)| value.as_u64())
+}
+
+async fn ollama_models(base_url: &str) -> Result<Vec<LlmModel>, String> {
+  let client = reqwest::Client::new();
+  let json = get_json(client.get(format!("{}/api/tags", base_url))).await?;
+
+  let names: Vec<String> = json["models"]
+    .as_array()
+    .map(|models| {
+      models
+        .iter()
+        .filter_map(|m| m["name"].as_str().map(|s| s.to_string()))
+        .collect()
+    })
+    .unwrap_or_default();
+
+  let mut models = Vec::new();
+  for name in names {
+    let context_length = ollama_context_length(&client, base_url, &name).await;
+    models.push(LlmModel { id: name, display_name: None, context_length });
+  }
+  Ok(models)
+}
+
+/// Models `provider` offers: "openai" (needs `api_key`), "anthropic", or
+/// "ollama" (at `base_url`, default http://localhost:11434).
+#[tauri::command]
+pub async fn list_llm_models(
+  provider: String,
+  api_key: Option<String>,
+  base_url: Option<String>,
+) -> Result<Vec<LlmModel>, String> {
+  let _timer = usage_metrics::time_async("list_llm_models");
+  match provider.as_str() {
+    "openai" => {
+      let api_key = api_key
+        .filter(|k| !k.trim().is_empty())
+        .ok_or_else(|| "An API key is required to list OpenAI models".to_string())?;
+      openai_models(&api_key).await
+    }
+    "anthropic" => Ok(anthropic_models()),
+    "ollama" => {
+      let base_url = base_url.unwrap_or_else(|| OLLAMA_DEFAULT_URL.to_string());
+      ollama_models(base_url.trim_end_matches('/')).await
+    }
+    _ => Err(format!("Unsupported provider: {}", provider)),
+  }
+}
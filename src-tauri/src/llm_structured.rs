@@ -0,0 +1,243 @@
+// LLM calls that must return JSON matching a schema.
+//
+// OpenAI runs in JSON mode with the schema in the prompt; Anthropic is forced
+// to call a single tool whose input schema is the requested one. Either way
+// the result is checked here against the schema, and a failing answer gets
+// one retry with the validation error fed back to the model.
+//
+// The validator covers the JSON Schema keywords extraction prompts need:
+// `type`, `enum`, `properties`, `required`, `additionalProperties`, `items`,
+// `minItems`/`maxItems`, `minLength`/`maxLength` and `minimum`/`maximum`.
+
+use serde_json::{json, Value};
+
+use crate::{connectivity, permissions, redaction, usage_metrics, LlmRequest};
+
+const TOOL_NAME: &str = "record_result";
+// Attempts per call, including the first
+const MAX_ATTEMPTS: usize = 2;
+
+fn type_matches(value: &Value, ty: &str) -> bool {
+  match ty {
+    "object" => value.is_object(),
+    "array" => value.is_array(),
+    "string" => value.is_string(),
+    "number" => value.is_number(),
+    "integer" => value.is_i64() || value.is_u64(),
+    "boolean" => value.is_boolean(),
+    "null" => value.is_null(),
+    _ => false,
+  }
+}
+
+/// Check `value` against `schema`, reporting the first problem with a
+/// `$.path` to where it is.
+pub fn validate(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+  let fail = |msg: String| Err(format!("{}: {}", path, msg));
+
+  match &schema["type"] {
+    Value::String(ty) if !type_matches(value, ty) => return fail(format!("expected {}", ty)),
+    Value::Array(types) if !types.iter().filter_map(|t| t.as_str()).any(|t| type_matches(value, t)) => {
+      return fail(format!("expected one of {}", Value::Array(types.clone())));
+    }
+    _ => {}
+  }
+
+  if let Some(allowed) = schema["enum"].as_array() {
+    if !allowed.contains(value) {
+      return fail(format!("must be one of {}", schema["enum"]));
+    }
+  }
+
+  match value {
+    Value::Object(map) => {
+      if let Some(required) = schema["required"].as_array() {
+        for key in required.iter().filter_map(|k| k.as_str()) {
+          if !map.contains_key(key) {
+            return fail(format!("missing required property '{}'", key));
+          }
+        }
+      }
+      let properties = schema["properties"].as_object();
+      for (key, item) in map {
+        match properties.and_then(|p| p.get(key)) {
+          Some(item_schema) => validate(item, item_schema, &format!("{}.{}", path, key))?,
+          None if schema["additionalProperties"] == Value::Bool(false) => {
+            return fail(format!("unexpected property '{}'", key));
+          }
+          None => {}
+        }
+      }
+    }
+    Value::Array(items) => {
+      if let Some(min) = schema["minItems"].as_u64() {
+        if (items.len() as u64) < min {
+          return fail(format!("needs at least {} items", min));
+        }
+      }
+      if let Some(max) = schema["maxItems"].as_u64() {
+        if items.len() as u64 > max {
+          return fail(format!("allows at most {} items", max));
+        }
+      }
+      if schema["items"].is_object() {
+        for (i, item) in items.iter().enumerate() {
+          validate(item, &schema["items"], &format!("{}[{}]", path, i))?;
+        }
+      }
+    }
+    Value::String(s) => {
+      let len = s.chars().count() as u64;
+      if schema["minLength"].as_u64().is_some_and(|min| len < min) {
+        return fail(format!("shorter than {} characters", schema["minLength"]));
+      }
+      if schema["maxLength"].as_u64().is_some_and(|max| len > max) {
+        return fail(format!("longer than {} characters", schema["maxLength"]));
+      }
+    }
+    Value::Number(n) => {
+      let n = n.as_f64().unwrap_or_default();
+      if schema["minimum"].as_f64().is_some_and(|min| n < min) {
+        return fail(format!("less than {}", schema["minimum"]));
+      }
+      if schema["maximum"].as_f64().is_some_and(|max| n > max) {
+        return fail(format!("greater than {}", schema["maximum"]));
+      }
+    }
+    _ => {}
+  }
+  Ok(())
+}
+
+// Tool inputs are always objects, so other schemas are wrapped in one
+fn wraps(schema: &Value) -> bool {
+  schema["type"] != "object"
+}
+
+fn tool_schema(schema: &Value) -> Value {
+  if wraps(schema) {
+    json!({ "type": "object", "properties": { "result": schema }, "required": ["result"] })
+  } else {
+    schema.clone()
+  }
+}
+
+// Models sometimes wrap JSON in a ```json fence even in JSON mode
+fn strip_fence(text: &str) -> &str {
+  let text = text.trim();
+  text
+    .strip_prefix("```json")
+    .or_else(|| text.strip_prefix("```"))
+    .and_then(|rest| rest.strip_suffix("```"))
+    .map(str::trim)
+    .unwrap_or(text)
+}
+
+async fn send(request: &LlmRequest, body: Value) -> Result<Value, String> {
+  permissions::ensure_llm()?;
+  let client = connectivity::client_builder().build().map_err(|e| e.to_string())?;
+  let builder = match request.provider.as_str() {
+    "openai" => client
+      .post("https://api.openai.com/v1/chat/completions")
+      .header("Authorization", format!("Bearer {}", request.api_key)),
+    "anthropic" => client
+      .post("https://api.anthropic.com/v1/messages")
+      .header("x-api-key", &request.api_key)
+      .header("anthropic-version", "2023-06-01"),
+    other => return Err(format!("Unsupported provider: {}", other)),
+  };
+
+  let response = builder
+    .header("Content-Type", "application/json")
+    .json(&body)
+    .send()
+    .await
+    .map_err(|e| connectivity::request_error("Request failed", e))?;
+  if !response.status().is_success() {
+    let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+    return Err(format!("API error: {}", error_text));
+  }
+  response.json().await.map_err(|e| format!("Failed to parse response: {}", e))
+}
+
+/// One request; `Ok(Err(..))` is an answer that didn't parse, which is worth
+/// a retry, while `Err(..)` is a failed request, which isn't.
+async fn attempt(request: &LlmRequest, schema: &Value, prompt: &str) -> Result<Result<Value, String>, String> {
+  let max_tokens = request.max_tokens.unwrap_or(1024);
+
+  if request.provider == "anthropic" {
+    let body = json!({
+      "model": request.model,
+      "max_tokens": max_tokens,
+      "tools": [{
+        "name": TOOL_NAME,
+        "description": "Record the structured result.",
+        "input_schema": tool_schema(schema),
+      }],
+      "tool_choice": { "type": "tool", "name": TOOL_NAME },
+      "messages": [{ "role": "user", "content": prompt }],
+    });
+    let json = send(request, body).await?;
+    let input = json["content"]
+      .as_array()
+      .and_then(|blocks| blocks.iter().find(|b| b["type"] == "tool_use"))
+      .map(|block| block["input"].clone());
+    return Ok(match input {
+      Some(input) if wraps(schema) => Ok(input["result"].clone()),
+      Some(input) => Ok(input),
+      None => Err("No tool call in response".to_string()),
+    });
+  }
+
+  let body = json!({
+    "model": request.model,
+    "messages": [{
+      "role": "user",
+      "content": format!(
+        "{}\n\nRespond with JSON only, matching this JSON Schema:\n{}",
+        prompt, schema
+      ),
+    }],
+    "max_tokens": max_tokens,
+    "temperature": 0.2,
+    "response_format": { "type": if wraps(schema) { "text" } else { "json_object" } },
+  });
+  let json = send(request, body).await?;
+  let content = json["choices"][0]["message"]["content"]
+    .as_str()
+    .ok_or_else(|| "No content in response".to_string())?;
+  Ok(serde_json::from_str(strip_fence(content)).map_err(|e| format!("Invalid JSON: {}", e)))
+}
+
+/// Ask for JSON matching `schema`, retrying once if the answer is invalid.
+pub async fn call_structured(request: &LlmRequest, schema: &Value) -> Result<Value, String> {
+  let original = redaction::redact(&request.prompt);
+  let mut prompt = original.clone();
+  let mut last_error = String::new();
+
+  for _ in 0..MAX_ATTEMPTS {
+    let problem = match attempt(request, schema, &prompt).await? {
+      Ok(value) => match validate(&value, schema, "$") {
+        Ok(()) => return Ok(value),
+        Err(e) => e,
+      },
+      Err(e) => e,
+    };
+    tracing::warn!(provider = %request.provider, error = %problem, "Structured LLM answer rejected");
+    prompt = format!(
+      "{}\n\nYour previous answer was rejected ({}). Answer again, following the schema exactly.",
+      original, problem
+    );
+    last_error = problem;
+  }
+  Err(format!("Model did not return valid JSON: {}", last_error))
+}
+
+#[tauri::command]
+pub async fn call_llm_structured(request: LlmRequest, json_schema: Value) -> Result<Value, String> {
+  let _timer = usage_metrics::time_async("call_llm_structured");
+  if !json_schema.is_object() {
+    return Err("Schema must be a JSON object".to_string());
+  }
+  call_structured(&request, &json_schema).await
+}
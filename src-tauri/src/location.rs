@@ -0,0 +1,325 @@
+// Where events were captured, for travel journaling. Off unless turned on.
+//
+// With `location.settings` enabled, a background loop asks the OS for a fix
+// every ten minutes and keeps the latest one in memory; events created by
+// hand (drops, notes, journal entries, clipboard images, recordings) get it
+// as `metadata.location`. Taking a fix can be slow, so it never happens while
+// an event is being written, and a fix older than half an hour isn't used.
+//
+// There's no location API in Tauri, so the OS is asked through a platform
+// tool: `CoreLocationCLI` on macOS, GeoClue's `where-am-i` on Linux, and
+// .NET's GeoCoordinateWatcher through PowerShell on Windows. When none of
+// them answers and `ipFallback` is set, the public IP is looked up instead,
+// which is only good to city level. OS fixes get a place name only with
+// `reverseGeocode`, since that sends the coordinates to OpenStreetMap.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::RwLock;
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::media::tool_command;
+use crate::permissions::{self, Permission};
+use crate::{
+  app_lock, config, connectivity, event_from_row, now_ms, read_setting, supervisor, usage_metrics, write_setting, DbState, TimelineEvent,
+};
+
+pub const SETTINGS_KEY: &str = "location.settings";
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const MAX_FIX_AGE_MS: i64 = 30 * 60 * 1000;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+const EARTH_RADIUS_KM: f64 = 6371.0;
+const DEFAULT_NEAR_LIMIT: u32 = 100;
+
+static CURRENT: RwLock<Option<Fix>> = RwLock::new(None);
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LocationSettings {
+  enabled: bool,
+  /// Look up the public IP when the OS has no fix
+  ip_fallback: bool,
+  /// Name OS fixes through OpenStreetMap's Nominatim
+  reverse_geocode: bool,
+}
+
+impl Default for LocationSettings {
+  fn default() -> Self {
+    Self { enabled: false, ip_fallback: true, reverse_geocode: false }
+  }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Fix {
+  lat: f64,
+  lon: f64,
+  place: Option<String>,
+  accuracy_m: Option<f64>,
+  /// "os" or "ip"
+  source: String,
+  captured_at: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EventNear {
+  event: TimelineEvent,
+  distance_km: f64,
+}
+
+fn load_settings(conn: &rusqlite::Connection) -> LocationSettings {
+  read_setting(conn, SETTINGS_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn current_settings(app: &tauri::AppHandle) -> LocationSettings {
+  let state = app.state::<DbState>();
+  let Ok(_guard) = state.lock.lock() else { return LocationSettings::default() };
+  state.open().map(|conn| load_settings(&conn)).unwrap_or_default()
+}
+
+fn valid(lat: f64, lon: f64) -> bool {
+  (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) && (lat != 0.0 || lon != 0.0)
+}
+
+/// Latitude, longitude and accuracy from the tool's output: GeoClue prints
+/// "Latitude: 52.52°" style lines, the others "lat lon [accuracy]".
+fn parse_fix(output: &str) -> Vec<f64> {
+  let number = |text: &str| text.trim().trim_end_matches(['°', 'm']).split_whitespace().next()?.parse::<f64>().ok();
+  if output.contains("Latitude:") {
+    return ["Latitude:", "Longitude:", "Accuracy:"]
+      .iter()
+      .map_while(|label| output.lines().find_map(|line| number(line.trim().strip_prefix(label)?)))
+      .collect();
+  }
+  output.split_whitespace().map_while(|token| token.parse().ok()).collect()
+}
+
+fn os_fix() -> Option<(f64, f64, Option<f64>)> {
+  #[cfg(target_os = "macos")]
+  let output = tool_command("CoreLocationCLI").args(["-once", "-format", "%latitude %longitude %h_accuracy"]).output();
+  #[cfg(target_os = "linux")]
+  let output = tool_command("/usr/libexec/geoclue-2.0/demos/where-am-i").args(["-t", "15"]).output();
+  #[cfg(target_os = "windows")]
+  let output = tool_command("powershell")
+    .args([
+      "-NoProfile",
+      "-Command",
+      "Add-Type -AssemblyName System.Device; \
+       $w = New-Object System.Device.Location.GeoCoordinateWatcher; $w.Start(); \
+       $i = 0; while ($w.Status -ne 'Ready' -and $i -lt 30) { Start-Sleep -Milliseconds 500; $i++ }; \
+       $c = $w.Position.Location; \"$($c.Latitude) $($c.Longitude) $($c.HorizontalAccuracy)\"",
+    ])
+    .output();
+  #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+  let output: std::io::Result<std::process::Output> = Err(std::io::ErrorKind::Unsupported.into());
+
+  let output = output.ok().filter(|o| o.status.success())?;
+  let values = parse_fix(&String::from_utf8_lossy(&output.stdout));
+  let (lat, lon) = (*values.first()?, *values.get(1)?);
+  valid(lat, lon).then(|| (lat, lon, values.get(2).copied().filter(|a| *a > 0.0)))
+}
+
+async fn ip_fix(client: &reqwest::Client) -> Result<Fix, String> {
+  let body: Value = client
+    .get("https://ipapi.co/json/")
+    .send()
+    .await
+    .map_err(|e| connectivity::request_error("Location request failed", e))?
+    .json()
+    .await
+    .map_err(|e| e.to_string())?;
+  let (Some(lat), Some(lon)) = (body["latitude"].as_f64(), body["longitude"].as_f64()) else {
+    return Err(format!("No location for this IP: {}", body["reason"].as_str().unwrap_or("unknown")));
+  };
+  let place = [&body["city"], &body["country_name"]]
+    .iter()
+    .filter_map(|v| v.as_str())
+    .collect::<Vec<_>>()
+    .join(", ");
+  Ok(Fix {
+    lat,
+    lon,
+    place: Some(place).filter(|p| !p.is_empty()),
+    accuracy_m: None,
+    source: "ip".to_string(),
+    captured_at: now_ms(),
+  })
+}
+
+async fn reverse_geocode(client: &reqwest::Client, lat: f64, lon: f64) -> Option<String> {
+  let body: Value = client
+    .get("https://nominatim.openstreetmap.org/reverse")
+    .query(&[("format", "jsonv2"), ("zoom", "10"), ("lat", &lat.to_string()), ("lon", &lon.to_string())])
+    .header("User-Agent", "Papa desktop pet")
+    .send()
+    .await
+    .ok()?
+    .json()
+    .await
+    .ok()?;
+  let address = &body["address"];
+  let place = ["city", "town", "village", "county"].iter().find_map(|key| address[*key].as_str());
+  let parts: Vec<&str> = place.into_iter().chain(address["country"].as_str()).collect();
+  (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+/// Take a fresh fix with the given settings.
+async fn locate(settings: &LocationSettings) -> Result<Fix, String> {
+  let client = connectivity::client_builder().timeout(HTTP_TIMEOUT).build().map_err(|e| e.to_string())?;
+  let os = tokio::task::spawn_blocking(os_fix).await.ok().flatten();
+  match os {
+    Some((lat, lon, accuracy_m)) => {
+      let place = match settings.reverse_geocode && connectivity::reachable() {
+        true => reverse_geocode(&client, lat, lon).await,
+        false => None,
+      };
+      Ok(Fix { lat, lon, place, accuracy_m, source: "os".to_string(), captured_at: now_ms() })
+    }
+    None if settings.ip_fallback => {
+      permissions::ensure(Permission::Network)?;
+      ip_fix(&client).await
+    }
+    None => Err("Location services didn't return a position".to_string()),
+  }
+}
+
+async fn refresh(app: &tauri::AppHandle) -> Result<Option<Fix>, String> {
+  let settings = current_settings(app);
+  if !settings.enabled {
+    if let Ok(mut current) = CURRENT.write() {
+      *current = None;
+    }
+    return Ok(None);
+  }
+  let fix = locate(&settings).await?;
+  if let Ok(mut current) = CURRENT.write() {
+    *current = Some(fix.clone());
+  }
+  Ok(Some(fix))
+}
+
+pub fn spawn_location_watcher(app: tauri::AppHandle) {
+  supervisor::supervise(app, "location", |app| async move {
+    loop {
+      if let Err(e) = refresh(&app).await {
+        tracing::debug!(error = %e, "Location refresh failed");
+      }
+      connectivity::sleep(REFRESH_INTERVAL).await;
+    }
+  });
+}
+
+/// Coordinates of the current fix, if location is on and one was taken.
+pub fn current_position() -> Option<(f64, f64)> {
+  CURRENT.read().ok().and_then(|current| current.as_ref().map(|fix| (fix.lat, fix.lon)))
+}
+
+/// Put the current fix on a new event, if location is on and the fix is
+/// recent enough.
+pub fn stamp(conn: &rusqlite::Connection, event_id: &str) -> Result<(), String> {
+  let fix = CURRENT.read().ok().and_then(|current| current.clone());
+  let Some(fix) = fix.filter(|f| now_ms() - f.captured_at <= MAX_FIX_AGE_MS) else { return Ok(()) };
+  if !load_settings(conn).enabled {
+    return Ok(());
+  }
+  conn
+    .execute(
+      "UPDATE timeline_events SET metadata = json_patch(COALESCE(metadata, '{}'), ?1) WHERE id = ?2",
+      (json!({ "location": fix }).to_string(), event_id),
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+fn distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+  let (dlat, dlon) = ((lat2 - lat1).to_radians(), (lon2 - lon1).to_radians());
+  let a = (dlat / 2.0).sin().powi(2) + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlon / 2.0).sin().powi(2);
+  2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+#[tauri::command]
+pub fn get_location_settings(state: tauri::State<DbState>) -> Result<LocationSettings, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_settings(&conn))
+}
+
+#[tauri::command]
+pub async fn set_location_settings(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, DbState>,
+  settings: LocationSettings,
+) -> Result<LocationSettings, String> {
+  let _timer = usage_metrics::time_async("set_location_settings");
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    write_setting(&conn, SETTINGS_KEY, &json)?;
+  }
+  config::emit_changed(&app, vec![SETTINGS_KEY.to_string()]);
+  // Take a fix straight away rather than at the next round
+  if let Err(e) = refresh(&app).await {
+    tracing::debug!(error = %e, "Location refresh failed");
+  }
+  Ok(settings)
+}
+
+/// Take a fix now and return it. `None` while location is off.
+#[tauri::command]
+pub async fn refresh_location(app: tauri::AppHandle) -> Result<Option<Fix>, String> {
+  let _timer = usage_metrics::time_async("refresh_location");
+  refresh(&app).await
+}
+
+/// Events stamped within `radius_km` of a point, nearest first.
+#[tauri::command]
+pub fn list_events_near(
+  state: tauri::State<DbState>,
+  lat: f64,
+  lon: f64,
+  radius_km: f64,
+  limit: Option<u32>,
+) -> Result<Vec<EventNear>, String> {
+  app_lock::ensure_unlocked()?;
+  if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+    return Err("Invalid coordinates".to_string());
+  }
+  if radius_km <= 0.0 {
+    return Err("Radius must be positive".to_string());
+  }
+
+  // Latitude bounds narrow it down in SQL; longitude wraps, so it's left to
+  // the exact distance check
+  let lat_span = radius_km / 111.0;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let events: Vec<TimelineEvent> = conn
+    .prepare(
+      "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
+       FROM timeline_events
+       WHERE is_deleted = 0 AND json_extract(metadata, '$.location.lat') BETWEEN ?1 AND ?2",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map((lat - lat_span, lat + lat_span), event_from_row)
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let mut near: Vec<EventNear> = events
+    .into_iter()
+    .filter_map(|event| {
+      let location = &event.metadata.as_ref()?["location"];
+      let distance_km = distance_km(lat, lon, location["lat"].as_f64()?, location["lon"].as_f64()?);
+      (distance_km <= radius_km).then_some(EventNear { event, distance_km })
+    })
+    .collect();
+  near.sort_by(|a, b| a.distance_km.total_cmp(&b.distance_km));
+  near.truncate(limit.unwrap_or(DEFAULT_NEAR_LIMIT) as usize);
+  Ok(near)
+}
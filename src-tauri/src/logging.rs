@@ -0,0 +1,173 @@
+// Diagnostic log: `tracing` events written to daily files under
+// `<app data>/logs`.
+//
+// Background work like the reminder scanner and file ingestion has nobody to
+// report errors to, so it logs instead. `get_recent_logs` returns the tail of
+// the log so users can attach it to a bug report. The level comes from the
+// `logs.level` setting and can change without a restart.
+
+use std::fs;
+use std::path::PathBuf;
+use tauri::Manager;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, Registry};
+
+use crate::{config, read_setting, write_setting, DbState};
+
+pub const LOG_LEVEL_KEY: &str = "logs.level";
+
+const LOG_DIR: &str = "logs";
+const LOG_FILE_PREFIX: &str = "papa";
+const LOG_FILE_SUFFIX: &str = "log";
+// Daily files kept before the oldest is deleted
+const MAX_LOG_FILES: usize = 7;
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::INFO;
+const DEFAULT_RECENT_LINES: usize = 200;
+const MAX_RECENT_LINES: usize = 5000;
+
+pub struct LogState {
+  dir: PathBuf,
+  level: reload::Handle<LevelFilter, Registry>,
+  // Flushes buffered lines when the app exits
+  _guard: WorkerGuard,
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+  level
+    .trim()
+    .parse::<LevelFilter>()
+    .map_err(|_| format!("Unknown log level: {}", level))
+}
+
+fn stored_level(app: &tauri::AppHandle) -> LevelFilter {
+  let state = app.state::<DbState>();
+  let Ok(_guard) = state.lock.lock() else { return DEFAULT_LEVEL };
+  state.open()
+    .ok()
+    .and_then(|conn| read_setting(&conn, LOG_LEVEL_KEY))
+    .and_then(|level| parse_level(&level).ok())
+    .unwrap_or(DEFAULT_LEVEL)
+}
+
+/// Install the global subscriber. Must run after `DbState` is managed.
+pub fn init(app: &tauri::AppHandle) -> Result<(), String> {
+  let dir = app
+    .path()
+    .resolve(LOG_DIR, tauri::path::BaseDirectory::AppData)
+    .map_err(|e| e.to_string())?;
+  fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+  let appender = RollingFileAppender::builder()
+    .rotation(Rotation::DAILY)
+    .filename_prefix(LOG_FILE_PREFIX)
+    .filename_suffix(LOG_FILE_SUFFIX)
+    .max_log_files(MAX_LOG_FILES)
+    .build(&dir)
+    .map_err(|e| e.to_string())?;
+  let (writer, guard) = tracing_appender::non_blocking(appender);
+
+  let (filter, level) = reload::Layer::new(stored_level(app));
+  tracing_subscriber::registry()
+    .with(filter)
+    .with(tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(false))
+    .try_init()
+    .map_err(|e| e.to_string())?;
+
+  app.manage(LogState { dir, level, _guard: guard });
+  tracing::info!(version = env!("CARGO_PKG_VERSION"), "Papa started");
+  Ok(())
+}
+
+/// Re-read `logs.level`; called when settings change.
+pub fn reload_level(app: &tauri::AppHandle) {
+  let level = stored_level(app);
+  if let Some(state) = app.try_state::<LogState>() {
+    if state.level.modify(|filter| *filter = level).is_ok() {
+      tracing::info!(%level, "Log level changed");
+    }
+  }
+}
+
+// Files are named `papa.YYYY-MM-DD.log`, so name order is date order
+fn log_files(dir: &PathBuf) -> Vec<PathBuf> {
+  let mut files: Vec<PathBuf> = fs::read_dir(dir)
+    .map(|entries| {
+      entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+          p.file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.starts_with(LOG_FILE_PREFIX) && n.ends_with(LOG_FILE_SUFFIX))
+            .unwrap_or(false)
+        })
+        .collect()
+    })
+    .unwrap_or_default();
+  files.sort();
+  files
+}
+
+// Level of a formatted line: "<timestamp> <LEVEL> <target>: <message>"
+fn line_level(line: &str) -> Option<LevelFilter> {
+  line.split_whitespace().nth(1).and_then(|level| parse_level(level).ok())
+}
+
+/// The last `limit` log lines, oldest first, optionally only those at
+/// `min_level` or more severe.
+#[tauri::command]
+pub fn get_recent_logs(
+  log_state: tauri::State<LogState>,
+  limit: Option<usize>,
+  min_level: Option<String>,
+) -> Result<Vec<String>, String> {
+  let limit = limit.unwrap_or(DEFAULT_RECENT_LINES).min(MAX_RECENT_LINES);
+  let min_level = min_level.as_deref().map(parse_level).transpose()?;
+
+  let mut lines: Vec<String> = Vec::new();
+  for file in log_files(&log_state.dir).iter().rev() {
+    let Ok(content) = fs::read_to_string(file) else { continue };
+    let kept = content
+      .lines()
+      .rev()
+      // Lines without a level are continuations of multi-line messages
+      .filter(|line| match (min_level, line_level(line)) {
+        (Some(min), Some(level)) => level <= min,
+        _ => true,
+      })
+      .take(limit - lines.len())
+      .map(str::to_string);
+    lines.extend(kept);
+    if lines.len() >= limit {
+      break;
+    }
+  }
+  lines.reverse();
+  Ok(lines)
+}
+
+#[tauri::command]
+pub fn get_log_level(app: tauri::AppHandle) -> String {
+  stored_level(&app).to_string().to_lowercase()
+}
+
+/// Set the minimum level written to the log: "off", "error", "warn", "info",
+/// "debug" or "trace".
+#[tauri::command]
+pub fn set_log_level(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  level: String,
+) -> Result<String, String> {
+  let level = parse_level(&level)?.to_string().to_lowercase();
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, LOG_LEVEL_KEY, &level)?;
+  }
+  config::emit_changed(&app, vec![LOG_LEVEL_KEY.to_string()]);
+  Ok(level)
+}
@@ -1,18 +1,107 @@
 // Hide console window on Windows in release builds
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use chrono::{DateTime, Local, Utc};
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tauri::{Emitter, Manager};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 use tauri::menu::{MenuBuilder, MenuItemBuilder};
-use device_query::{DeviceQuery, DeviceState, Keycode};
+
+mod action_reminders;
+mod affinity;
+mod app_lock;
+mod app_windows;
+mod archive;
+mod ask;
+mod audio;
+mod behavior;
+mod bookmarks;
+mod calendar;
+mod capture_stats;
+mod cas;
+mod clipboard;
+mod compression;
+mod config;
+mod connectivity;
+mod day_plan;
+mod dev_fixtures;
+mod downloads;
+mod drag_out;
+mod drop_reactions;
+mod duplicates;
+mod email_digest;
+mod event_lock;
+mod export_files;
+mod export_rules;
+mod feeds;
+mod file_read;
+mod gestures;
+mod git_journal;
+mod github;
+mod goals;
+mod i18n;
+mod ingest;
+mod jobs;
+mod journal;
+mod lan_capture;
+mod llm_models;
+mod llm_structured;
+mod location;
+mod logging;
+mod maintenance;
+mod media;
+mod mentions;
+mod mouse_stream;
+mod notion;
+mod permissions;
+mod pet_state;
+mod pet_window;
+mod photo_meta;
+mod privacy;
+mod profiles;
+mod qr;
+mod receipts;
+mod recovery;
+mod quiet_hours;
+mod rate_limit;
+mod redaction;
+mod relink;
+mod reminder_log;
+mod reminder_scan;
+mod reminder_stats;
+mod sandbox;
+mod saved_searches;
+mod schedule;
+mod screenshots;
+mod secrets;
+mod search;
+mod similar;
+mod snooze;
+mod sound;
+mod streaks;
+mod summarize;
+mod tags;
+mod supervisor;
+mod synthesis;
+mod thumbnails;
+mod timezone;
+mod tts;
+mod undo;
+mod upload;
+mod usage_metrics;
+mod watch_folders;
+mod weather;
+mod weekly_review;
+mod wellness;
+mod year_review;
 
 fn generate_id() -> String {
   let now = SystemTime::now()
@@ -31,17 +120,38 @@ fn now_ms() -> i64 {
 }
 
 fn get_mime_type(path: &Path) -> Option<String> {
-  let ext = path.extension()?.to_str()?.to_lowercase();
-  Some(match ext.as_str() {
+  let ext = path.extension()
+    .and_then(|e| e.to_str())
+    .map(|e| e.to_lowercase())
+    .unwrap_or_default();
+  let mime = match ext.as_str() {
     "jpg" | "jpeg" => "image/jpeg",
     "png" => "image/png",
     "gif" => "image/gif",
     "webp" => "image/webp",
     "svg" => "image/svg+xml",
     "bmp" => "image/bmp",
+    "heic" => "image/heic",
+    "heif" => "image/heif",
+    "avif" => "image/avif",
+    "tif" | "tiff" => "image/tiff",
+    "ico" => "image/x-icon",
+    "mp4" | "m4v" => "video/mp4",
+    "mov" => "video/quicktime",
+    "webm" => "video/webm",
+    "mkv" => "video/x-matroska",
+    "avi" => "video/x-msvideo",
+    "mp3" => "audio/mpeg",
+    "wav" => "audio/wav",
+    "ogg" | "oga" => "audio/ogg",
+    "opus" => "audio/opus",
+    "m4a" => "audio/mp4",
+    "aac" => "audio/aac",
+    "flac" => "audio/flac",
     "pdf" => "application/pdf",
     "txt" => "text/plain",
     "md" => "text/markdown",
+    "csv" => "text/csv",
     "json" => "application/json",
     "html" | "htm" => "text/html",
     "css" => "text/css",
@@ -49,25 +159,63 @@ fn get_mime_type(path: &Path) -> Option<String> {
     "ts" => "application/typescript",
     "xml" => "application/xml",
     "zip" => "application/zip",
+    "7z" => "application/x-7z-compressed",
+    "gz" => "application/gzip",
+    "tar" => "application/x-tar",
+    "rtf" => "application/rtf",
     "doc" => "application/msword",
     "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
     "xls" => "application/vnd.ms-excel",
     "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
     "ppt" => "application/vnd.ms-powerpoint",
     "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
-    _ => "application/octet-stream",
-  }.to_string())
+    // Unknown or missing extension: look at the first bytes instead
+    _ => {
+      return Some(
+        infer::get_from_path(path)
+          .ok()
+          .flatten()
+          .map(|t| t.mime_type())
+          .unwrap_or("application/octet-stream")
+          .to_string(),
+      );
+    }
+  };
+  Some(mime.to_string())
 }
 
-fn is_image_type(mime: &Option<String>) -> bool {
-  mime.as_ref().map(|m| m.starts_with("image/")).unwrap_or(false)
+/// Open the database at `path` with foreign keys enforced. SQLite keeps that
+/// setting per connection, so every connection is opened through here rather
+/// than relying on how the library was built.
+fn open_db(path: impl AsRef<Path>) -> rusqlite::Result<rusqlite::Connection> {
+  let conn = rusqlite::Connection::open(path)?;
+  conn.execute_batch("PRAGMA foreign_keys = ON")?;
+  compression::register(&conn)?;
+  Ok(conn)
 }
 
 struct DbState {
-  path: PathBuf,
+  /// Database of the active profile, swapped by `profiles::switch_profile`
+  db_path: RwLock<PathBuf>,
   lock: Mutex<()>,
 }
 
+impl DbState {
+  fn path(&self) -> PathBuf {
+    self.db_path.read().unwrap_or_else(|e| e.into_inner()).clone()
+  }
+
+  /// A connection to the active profile's database, see `open_db`.
+  fn open(&self) -> rusqlite::Result<rusqlite::Connection> {
+    open_db(self.path())
+  }
+
+  /// Folder of the active profile, holding its database and files.
+  fn data_dir(&self) -> PathBuf {
+    self.path().parent().map(Path::to_path_buf).unwrap_or_default()
+  }
+}
+
 #[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 struct DropRecord {
@@ -83,18 +231,6 @@ struct DropProcessedPayload {
   record: DropRecord,
 }
 
-#[derive(Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
-struct BehaviorAnalysis {
-  typing_speed: f64,        // 打字速度 (keys per second)
-  key_press_count: u32,     // 按键次数
-  backspace_count: u32,     // 退格键次数
-  mouse_move_speed: f64,     // 鼠标移动速度 (pixels per second)
-  mouse_click_count: u32,   // 鼠标点击次数
-  idle_time: f64,           // 空闲时间 (seconds)
-  activity_level: f64,       // 活动水平 (0.0 - 1.0)
-}
-
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct LlmRequest {
@@ -112,13 +248,16 @@ struct LlmRequest {
 struct TimelineEvent {
   id: String,
   #[serde(rename = "type")]
-  event_type: String,  // 'file' | 'image' | 'text' | 'thought'
+  event_type: String,  // 'file' | 'image' | 'video' | 'text' | 'thought' | 'audio' | 'journal'
   title: Option<String>,
   note: Option<String>,
   text_content: Option<String>,
   created_at: i64,
-  source: Option<String>,  // 'drop' | 'manual' | 'clipboard'
+  source: Option<String>,  // 'drop' | 'manual' | 'clipboard' | 'voice' | 'journal' | 'plan' | 'mobile'
   is_deleted: bool,
+  metadata: Option<serde_json::Value>,  // free-form JSON object, see set_event_metadata
+  ai_opt_out: bool,  // never sent to an LLM, see privacy.rs
+  locked: bool,  // note, text and attachments encrypted, see event_lock.rs
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -126,7 +265,7 @@ struct TimelineEvent {
 struct Attachment {
   id: String,
   event_id: String,
-  kind: String,  // 'file' | 'image'
+  kind: String,  // 'file' | 'image' | 'video' | 'audio'
   original_path: String,
   stored_path: Option<String>,
   file_name: Option<String>,
@@ -136,6 +275,8 @@ struct Attachment {
   width: Option<i32>,
   height: Option<i32>,
   created_at: i64,
+  duration_ms: Option<i64>,
+  derived_from: Option<String>,  // what generated it, e.g. 'qr'; None for captured files
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -145,10 +286,19 @@ struct Reminder {
   event_id: String,
   remind_at: i64,
   message: String,
-  status: String,  // 'pending' | 'triggered' | 'dismissed' | 'snoozed'
+  status: String,  // 'pending' | 'triggered' | 'dismissed' | 'snoozed' | 'cancelled'
   triggered_at: Option<i64>,
   snooze_until: Option<i64>,
   created_at: i64,
+  /// The file on the event this reminder is about, e.g. a PDF to read
+  #[serde(default)]
+  attachment_id: Option<String>,
+  /// Dismissing the reminder schedules another this many minutes later
+  #[serde(default)]
+  follow_up_after_minutes: Option<i64>,
+  /// Message of that next reminder; the same message when `None`
+  #[serde(default)]
+  follow_up_message: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -159,6 +309,9 @@ struct DailyExport {
   output_format: String,
   output_path: String,
   created_at: i64,
+  /// The file is no longer at `output_path`; `open_export` can regenerate it
+  #[serde(default)]
+  missing: bool,
 }
 
 #[derive(Serialize, Clone)]
@@ -169,6 +322,15 @@ struct TimelineEventWithAttachments {
   reminders: Vec<Reminder>,
 }
 
+/// A new drop event, with how the pet should react to the files.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DropEventCreated {
+  #[serde(flatten)]
+  created: TimelineEventWithAttachments,
+  reaction: Option<drop_reactions::DropReaction>,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct CreateDropEventRequest {
@@ -176,6 +338,9 @@ struct CreateDropEventRequest {
   note: Option<String>,
   remind_at: Option<i64>,
   remind_message: Option<String>,
+  /// Use the photos' capture time as the event time (defaults to the
+  /// `ingest.backdate_photos` setting)
+  backdate: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -201,14 +366,46 @@ struct ListEventsRequest {
   end_date: Option<i64>,    // unix ms
   page: Option<u32>,
   page_size: Option<u32>,
+  /// Metadata key (dots for nesting) -> value it must equal; `null` matches
+  /// events without the key
+  metadata_filter: Option<HashMap<String, serde_json::Value>>,
+  /// Also return events moved to the archive
+  include_archived: Option<bool>,
 }
 
 #[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct ReminderDuePayload {
+pub(crate) struct ReminderDuePayload {
   reminder: Reminder,
   event: TimelineEvent,
   attachments: Vec<Attachment>,
+  /// The attachment the reminder points at, to open straight from the popup
+  attachment: Option<Attachment>,
+  /// Thumbnails of image attachments
+  previews: Vec<thumbnails::AttachmentPreview>,
+  /// Start of the event's text content
+  text_preview: Option<String>,
+}
+
+// Characters of text content included in a reminder payload
+const TEXT_PREVIEW_CHARS: usize = 200;
+
+impl ReminderDuePayload {
+  fn new(app: &tauri::AppHandle, reminder: Reminder, event: TimelineEvent, attachments: Vec<Attachment>) -> Self {
+    let previews = thumbnails::image_previews(app, &attachments);
+    let text_preview = event.text_content.as_deref().map(|text| {
+      let mut preview: String = text.chars().take(TEXT_PREVIEW_CHARS).collect();
+      if preview.len() < text.len() {
+        preview.push('…');
+      }
+      preview
+    });
+    let attachment = reminder
+      .attachment_id
+      .as_ref()
+      .and_then(|id| attachments.iter().find(|a| &a.id == id).cloned());
+    Self { reminder, event, attachments, attachment, previews, text_preview }
+  }
 }
 
 fn init_db(db_path: &Path) -> Result<(), String> {
@@ -216,7 +413,7 @@ fn init_db(db_path: &Path) -> Result<(), String> {
     fs::create_dir_all(parent).map_err(|e| e.to_string())?;
   }
 
-  let conn = rusqlite::Connection::open(db_path).map_err(|e| e.to_string())?;
+  let conn = open_db(db_path).map_err(|e| e.to_string())?;
   conn.execute_batch(
     "
     -- Legacy table (keep for migration compatibility)
@@ -245,6 +442,40 @@ fn init_db(db_path: &Path) -> Result<(), String> {
     );
     CREATE INDEX IF NOT EXISTS idx_timeline_created_at ON timeline_events(created_at);
 
+    -- Old events moved out by archive_events_before; same columns as timeline_events
+    CREATE TABLE IF NOT EXISTS timeline_events_archive (
+      id TEXT PRIMARY KEY,
+      type TEXT NOT NULL,
+      title TEXT,
+      note TEXT,
+      text_content TEXT,
+      created_at INTEGER NOT NULL,
+      source TEXT,
+      is_deleted INTEGER DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS idx_archive_created_at ON timeline_events_archive(created_at);
+
+    -- Ids of live and archived events. Attachments and reminders reference
+    -- this rather than timeline_events, so their keys survive archiving; the
+    -- triggers drop an id only once neither table has the event
+    CREATE TABLE IF NOT EXISTS event_ids (
+      id TEXT PRIMARY KEY
+    );
+    CREATE TRIGGER IF NOT EXISTS event_ids_live_insert AFTER INSERT ON timeline_events BEGIN
+      INSERT OR IGNORE INTO event_ids (id) VALUES (NEW.id);
+    END;
+    CREATE TRIGGER IF NOT EXISTS event_ids_archive_insert AFTER INSERT ON timeline_events_archive BEGIN
+      INSERT OR IGNORE INTO event_ids (id) VALUES (NEW.id);
+    END;
+    CREATE TRIGGER IF NOT EXISTS event_ids_live_delete AFTER DELETE ON timeline_events
+    WHEN NOT EXISTS (SELECT 1 FROM timeline_events_archive WHERE id = OLD.id) BEGIN
+      DELETE FROM event_ids WHERE id = OLD.id;
+    END;
+    CREATE TRIGGER IF NOT EXISTS event_ids_archive_delete AFTER DELETE ON timeline_events_archive
+    WHEN NOT EXISTS (SELECT 1 FROM timeline_events WHERE id = OLD.id) BEGIN
+      DELETE FROM event_ids WHERE id = OLD.id;
+    END;
+
     CREATE TABLE IF NOT EXISTS attachments (
       id TEXT PRIMARY KEY,
       event_id TEXT NOT NULL,
@@ -258,10 +489,71 @@ fn init_db(db_path: &Path) -> Result<(), String> {
       width INTEGER,
       height INTEGER,
       created_at INTEGER NOT NULL,
-      FOREIGN KEY(event_id) REFERENCES timeline_events(id)
+      FOREIGN KEY(event_id) REFERENCES event_ids(id)
     );
     CREATE INDEX IF NOT EXISTS idx_attach_event ON attachments(event_id);
 
+    CREATE TABLE IF NOT EXISTS attachment_metadata (
+      attachment_id TEXT PRIMARY KEY,
+      taken_at INTEGER,
+      camera_make TEXT,
+      camera_model TEXT,
+      lens_model TEXT,
+      gps_latitude REAL,
+      gps_longitude REAL,
+      orientation INTEGER,
+      FOREIGN KEY(attachment_id) REFERENCES attachments(id)
+    );
+
+    -- New downloads waiting for (or given) a decision, see downloads.rs
+    CREATE TABLE IF NOT EXISTS download_triage (
+      path TEXT PRIMARY KEY,
+      file_name TEXT NOT NULL,
+      size_bytes INTEGER NOT NULL,
+      seen_at INTEGER NOT NULL,
+      decision TEXT,  -- 'journal' | 'remind' | 'ignore'
+      decided_at INTEGER
+    );
+
+    -- Pairs of near-duplicate text events, see duplicates.rs; event_a < event_b
+    CREATE TABLE IF NOT EXISTS duplicate_candidates (
+      event_a TEXT NOT NULL,
+      event_b TEXT NOT NULL,
+      similarity REAL NOT NULL,
+      detected_at INTEGER NOT NULL,
+      status TEXT NOT NULL,  -- 'pending' | 'dismissed' | 'merged'
+      PRIMARY KEY (event_a, event_b)
+    );
+
+    -- Stored files by content, see cas.rs; ref_count is kept by the triggers below
+    CREATE TABLE IF NOT EXISTS blobs (
+      path TEXT PRIMARY KEY,
+      sha256 TEXT NOT NULL,
+      size_bytes INTEGER NOT NULL,
+      ref_count INTEGER NOT NULL DEFAULT 0,
+      created_at INTEGER NOT NULL
+    );
+    CREATE TRIGGER IF NOT EXISTS blobs_ref_insert AFTER INSERT ON attachments BEGIN
+      UPDATE blobs SET ref_count = ref_count + 1 WHERE path = NEW.stored_path;
+    END;
+    CREATE TRIGGER IF NOT EXISTS blobs_ref_delete AFTER DELETE ON attachments BEGIN
+      UPDATE blobs SET ref_count = ref_count - 1 WHERE path = OLD.stored_path;
+    END;
+    CREATE TRIGGER IF NOT EXISTS blobs_ref_update AFTER UPDATE OF stored_path ON attachments BEGIN
+      UPDATE blobs SET ref_count = ref_count - 1 WHERE path = OLD.stored_path;
+      UPDATE blobs SET ref_count = ref_count + 1 WHERE path = NEW.stored_path;
+    END;
+
+    -- Attachments whose original moved, see relink.rs
+    CREATE TABLE IF NOT EXISTS attachment_relocations (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      attachment_id TEXT NOT NULL,
+      old_path TEXT NOT NULL,
+      new_path TEXT NOT NULL,
+      method TEXT NOT NULL,  -- 'auto' | 'manual'
+      created_at INTEGER NOT NULL
+    );
+
     CREATE TABLE IF NOT EXISTS reminders (
       id TEXT PRIMARY KEY,
       event_id TEXT NOT NULL,
@@ -271,7 +563,7 @@ fn init_db(db_path: &Path) -> Result<(), String> {
       triggered_at INTEGER,
       snooze_until INTEGER,
       created_at INTEGER NOT NULL,
-      FOREIGN KEY(event_id) REFERENCES timeline_events(id)
+      FOREIGN KEY(event_id) REFERENCES event_ids(id)
     );
     CREATE INDEX IF NOT EXISTS idx_remind_due ON reminders(status, remind_at);
 
@@ -288,12 +580,479 @@ fn init_db(db_path: &Path) -> Result<(), String> {
       key TEXT PRIMARY KEY,
       value TEXT NOT NULL
     );
+
+    -- Seconds spent per focused app per day (opt-in, see behavior.track_apps)
+    CREATE TABLE IF NOT EXISTS app_usage (
+      date_key TEXT NOT NULL,
+      app_name TEXT NOT NULL,
+      seconds REAL NOT NULL DEFAULT 0,
+      last_title TEXT,
+      updated_at INTEGER NOT NULL,
+      PRIMARY KEY (date_key, app_name)
+    );
+
+    -- Periods with no input longer than behavior.idle_minutes
+    CREATE TABLE IF NOT EXISTS away_intervals (
+      id TEXT PRIMARY KEY,
+      started_at INTEGER NOT NULL,
+      ended_at INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS idx_away_started ON away_intervals(started_at);
+
+    -- Named filter combinations, see search::SearchFilter
+    CREATE TABLE IF NOT EXISTS saved_searches (
+      id TEXT PRIMARY KEY,
+      name TEXT NOT NULL,
+      filter TEXT NOT NULL,
+      created_at INTEGER NOT NULL
+    );
+
+    -- Normalized tag names, see tags::normalize
+    CREATE TABLE IF NOT EXISTS tags (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      name TEXT NOT NULL UNIQUE,
+      created_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS event_tags (
+      event_id TEXT NOT NULL,
+      tag_id INTEGER NOT NULL,
+      source TEXT NOT NULL,  -- 'manual' | 'auto' | 'hashtag' | 'import'
+      created_at INTEGER NOT NULL,
+      PRIMARY KEY (event_id, tag_id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_event_tags_tag ON event_tags(tag_id);
+
+    -- One Notion page per exported day, so re-exports update it
+    CREATE TABLE IF NOT EXISTS notion_pages (
+      database_id TEXT NOT NULL,
+      date_key TEXT NOT NULL,
+      page_id TEXT NOT NULL,
+      synced_at INTEGER NOT NULL,
+      PRIMARY KEY (database_id, date_key)
+    );
+
+    -- Phones allowed to upload, see lan_capture.rs; only token hashes are kept
+    CREATE TABLE IF NOT EXISTS lan_devices (
+      id TEXT PRIMARY KEY,
+      name TEXT NOT NULL,
+      token_hash TEXT NOT NULL UNIQUE,
+      created_at INTEGER NOT NULL,
+      last_seen_at INTEGER
+    );
+
+    -- Focus sessions as started, ends_at cut short when ended early
+    CREATE TABLE IF NOT EXISTS focus_sessions (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      started_at INTEGER NOT NULL,
+      ends_at INTEGER NOT NULL
+    );
+
+    -- Daily goal progress, see goals.rs
+    CREATE TABLE IF NOT EXISTS goal_progress (
+      date_key TEXT NOT NULL,
+      kind TEXT NOT NULL,
+      target INTEGER NOT NULL,
+      value INTEGER NOT NULL,
+      met_at INTEGER,
+      updated_at INTEGER NOT NULL,
+      PRIMARY KEY (date_key, kind)
+    );
+
+    -- Pats, feeds, chats and play, see affinity.rs
+    CREATE TABLE IF NOT EXISTS pet_interactions (
+      id TEXT PRIMARY KEY,
+      kind TEXT NOT NULL,
+      created_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_pet_interactions_created ON pet_interactions(created_at);
+
+    -- User rules for reacting to dropped files, see drop_reactions.rs
+    CREATE TABLE IF NOT EXISTS drop_reactions (
+      id TEXT PRIMARY KEY,
+      name TEXT NOT NULL,
+      mime_pattern TEXT,
+      extensions TEXT NOT NULL,  -- JSON array
+      filename_pattern TEXT,
+      message TEXT NOT NULL,
+      actions TEXT NOT NULL,  -- JSON array
+      priority INTEGER NOT NULL DEFAULT 0,
+      enabled INTEGER NOT NULL DEFAULT 1,
+      created_at INTEGER NOT NULL
+    );
+
+    -- @mentions in notes, see mentions.rs
+    CREATE TABLE IF NOT EXISTS mentions (
+      event_id TEXT NOT NULL,
+      name TEXT NOT NULL,  -- lowercase
+      display_name TEXT NOT NULL,
+      created_at INTEGER NOT NULL,  -- the event's
+      PRIMARY KEY (event_id, name)
+    );
+    CREATE INDEX IF NOT EXISTS idx_mentions_name ON mentions(name);
+
+    -- Read-only calendar subscriptions, see calendar.rs
+    CREATE TABLE IF NOT EXISTS calendars (
+      id TEXT PRIMARY KEY,
+      url TEXT NOT NULL UNIQUE,
+      name TEXT,
+      last_fetched_at INTEGER,
+      last_error TEXT,
+      created_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS calendar_entries (
+      calendar_id TEXT NOT NULL,
+      uid TEXT NOT NULL,
+      starts_at INTEGER NOT NULL,  -- one row per occurrence
+      ends_at INTEGER NOT NULL,
+      all_day INTEGER NOT NULL,
+      title TEXT NOT NULL,
+      location TEXT,
+      description TEXT,
+      PRIMARY KEY (calendar_id, uid, starts_at)
+    );
+    CREATE INDEX IF NOT EXISTS idx_calendar_entries_start ON calendar_entries(starts_at);
+
+    -- RSS and Atom subscriptions, see feeds.rs
+    CREATE TABLE IF NOT EXISTS feeds (
+      id TEXT PRIMARY KEY,
+      url TEXT NOT NULL UNIQUE,
+      title TEXT,
+      keywords TEXT NOT NULL,  -- JSON array, lowercase
+      last_fetched_at INTEGER,
+      last_error TEXT,
+      created_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS feed_items (
+      feed_id TEXT NOT NULL,
+      guid TEXT NOT NULL,
+      event_id TEXT,  -- set when the item was imported
+      seen_at INTEGER NOT NULL,
+      PRIMARY KEY (feed_id, guid)
+    );
+
+    -- Pulled GitHub activity and the daily entries made from it, see github.rs
+    CREATE TABLE IF NOT EXISTS github_activity (
+      id TEXT PRIMARY KEY,  -- kind-prefixed commit sha or GitHub event id
+      date_key TEXT NOT NULL,
+      kind TEXT NOT NULL,  -- commit | pr_opened | pr_merged | pr_closed | review
+      repo TEXT NOT NULL,
+      number INTEGER,
+      title TEXT,
+      count INTEGER NOT NULL,
+      created_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_github_activity_day ON github_activity(date_key);
+    CREATE TABLE IF NOT EXISTS github_days (
+      date_key TEXT PRIMARY KEY,
+      event_id TEXT NOT NULL
+    );
+
+    -- One row per day, see weather.rs
+    CREATE TABLE IF NOT EXISTS daily_weather (
+      date_key TEXT PRIMARY KEY,  -- YYYY-MM-DD
+      lat REAL NOT NULL,
+      lon REAL NOT NULL,
+      temp_max REAL NOT NULL,
+      temp_min REAL NOT NULL,
+      unit TEXT NOT NULL,  -- celsius | fahrenheit
+      precipitation_mm REAL NOT NULL,
+      weather_code INTEGER NOT NULL,  -- WMO
+      summary TEXT NOT NULL,
+      fetched_at INTEGER NOT NULL
+    );
+
+    -- Background work, see jobs.rs
+    CREATE TABLE IF NOT EXISTS jobs (
+      id TEXT PRIMARY KEY,
+      kind TEXT NOT NULL,
+      payload TEXT NOT NULL,
+      status TEXT NOT NULL,  -- queued | running | succeeded | failed | cancelled
+      attempts INTEGER NOT NULL DEFAULT 0,
+      max_attempts INTEGER NOT NULL,
+      run_after INTEGER NOT NULL,
+      result TEXT,
+      error TEXT,
+      created_at INTEGER NOT NULL,
+      updated_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_jobs_queue ON jobs(status, run_after);
+
+    -- Encrypted content of locked events, see event_lock.rs
+    CREATE TABLE IF NOT EXISTS event_locks (
+      event_id TEXT PRIMARY KEY,
+      salt BLOB NOT NULL,
+      nonce BLOB NOT NULL,
+      ciphertext BLOB NOT NULL,
+      created_at INTEGER NOT NULL
+    );
+
+    -- Command calls per day, see usage_metrics.rs; never sent anywhere
+    CREATE TABLE IF NOT EXISTS usage_metrics (
+      date_key TEXT NOT NULL,
+      command TEXT NOT NULL,
+      calls INTEGER NOT NULL,
+      total_ms REAL NOT NULL,
+      max_ms REAL NOT NULL,
+      PRIMARY KEY (date_key, command)
+    );
+
+    -- Reminder status changes, see reminder_log.rs
+    CREATE TABLE IF NOT EXISTS reminder_log (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      reminder_id TEXT NOT NULL,
+      event_id TEXT,
+      from_status TEXT,
+      to_status TEXT NOT NULL,
+      reason TEXT NOT NULL,
+      created_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_reminder_log_reminder ON reminder_log(reminder_id, created_at);
     ",
   )
   .map_err(|e| e.to_string())?;
+
+  add_column_if_missing(&conn, "attachments", "duration_ms", "INTEGER")?;
+  add_column_if_missing(&conn, "attachments", "derived_from", "TEXT")?;
+  add_column_if_missing(&conn, "reminders", "snooze_count", "INTEGER NOT NULL DEFAULT 0")?;
+  add_column_if_missing(&conn, "reminders", "dismissed_at", "INTEGER")?;
+  add_column_if_missing(&conn, "reminders", "attachment_id", "TEXT")?;
+  add_column_if_missing(&conn, "reminders", "follow_up_after_minutes", "INTEGER")?;
+  add_column_if_missing(&conn, "reminders", "follow_up_message", "TEXT")?;
+  for table in ["timeline_events", "timeline_events_archive"] {
+    add_column_if_missing(&conn, table, "metadata", "TEXT")?;
+    add_column_if_missing(&conn, table, "scheduled_for", "INTEGER")?;
+    add_column_if_missing(&conn, table, "ai_opt_out", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, table, "locked", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, table, "reviewed_at", "INTEGER")?;
+    // Large texts, see compression.rs
+    add_column_if_missing(&conn, table, "text_compressed", "BLOB")?;
+    add_column_if_missing(&conn, table, "text_codec", "TEXT")?;
+    // Machine's UTC offset when captured, see timezone.rs
+    add_column_if_missing(&conn, table, "tz_offset_min", "INTEGER")?;
+  }
+  // Only fresh events; restored or imported ones keep an unknown offset. The
+  // offset is the pinned `timezone` setting ("+01:00", as set_timezone writes
+  // it) or the machine's; recreated since it used to record the latter only
+  conn.execute_batch(
+    "DROP TRIGGER IF EXISTS timeline_events_tz_offset;
+     CREATE TRIGGER timeline_events_tz_offset AFTER INSERT ON timeline_events
+     WHEN NEW.tz_offset_min IS NULL AND NEW.created_at >= (strftime('%s', 'now') - 86400) * 1000
+     BEGIN
+       UPDATE timeline_events
+       SET tz_offset_min = COALESCE(
+         (SELECT (CASE substr(value, 1, 1) WHEN '-' THEN -1 ELSE 1 END)
+                 * (CAST(substr(value, 2, 2) AS INTEGER) * 60 + CAST(substr(value, 5, 2) AS INTEGER))
+          FROM settings WHERE key = 'timezone' AND value GLOB '[+-][0-9][0-9]:[0-9][0-9]'),
+         CAST(round((julianday('now', 'localtime') - julianday('now')) * 1440) AS INTEGER))
+       WHERE id = NEW.id;
+     END;",
+  ).map_err(|e| e.to_string())?;
+  rekey_event_children(&conn)?;
+  // Deleting an event didn't use to cancel its reminders
+  conn.execute(
+    "UPDATE reminders SET status = 'cancelled'
+     WHERE status IN ('pending', 'snoozed')
+       AND event_id IN (SELECT id FROM timeline_events WHERE is_deleted = 1)",
+    [],
+  ).map_err(|e| e.to_string())?;
+
+  // `open_db` switched them on; a library built without foreign key support
+  // ignores that
+  let enforced: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0)).unwrap_or(false);
+  if !enforced {
+    tracing::warn!("SQLite doesn't support foreign keys; referential integrity is not checked");
+  }
+  Ok(())
+}
+
+/// Older databases have attachments and reminders referencing
+/// timeline_events, which archiving can't satisfy. SQLite can't alter a
+/// foreign key, so those tables are rebuilt from their stored definition with
+/// the key pointing at event_ids. This follows SQLite's documented procedure:
+/// checks are off only for the rebuild, and it's rolled back unless
+/// `foreign_key_check` comes back clean.
+fn rekey_event_children(conn: &rusqlite::Connection) -> Result<(), String> {
+  let mut stale = Vec::new();
+  for table in ["attachments", "reminders"] {
+    let references_events: bool = conn
+      .query_row(
+        &format!("SELECT COUNT(*) > 0 FROM pragma_foreign_key_list('{}') WHERE \"table\" = 'timeline_events'", table),
+        [],
+        |row| row.get(0),
+      )
+      .map_err(|e| e.to_string())?;
+    if references_events {
+      stale.push(table);
+    }
+  }
+  if stale.is_empty() {
+    return Ok(());
+  }
+
+  conn.execute_batch("PRAGMA foreign_keys = OFF").map_err(|e| e.to_string())?;
+  let result = rebuild_event_children(conn, &stale);
+  conn.execute_batch("PRAGMA foreign_keys = ON").map_err(|e| e.to_string())?;
+
+  match result {
+    Ok(()) => {
+      tracing::info!(tables = ?stale, "Pointed event foreign keys at event_ids");
+      Ok(())
+    }
+    Err(e) => {
+      tracing::warn!(error = %e, "Kept the old event foreign keys; archiving will fail until they're repaired");
+      Ok(())
+    }
+  }
+}
+
+fn rebuild_event_children(conn: &rusqlite::Connection, tables: &[&str]) -> Result<(), String> {
+  let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+  tx.execute(
+    "INSERT OR IGNORE INTO event_ids (id)
+     SELECT id FROM timeline_events UNION SELECT id FROM timeline_events_archive",
+    [],
+  ).map_err(|e| e.to_string())?;
+  for table in tables {
+    let create: String = tx
+      .query_row("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?", [table], |row| row.get(0))
+      .map_err(|e| e.to_string())?;
+    // Indexes and triggers go with the old table
+    let dependents: Vec<String> = tx
+      .prepare("SELECT sql FROM sqlite_master WHERE tbl_name = ? AND type IN ('index', 'trigger') AND sql IS NOT NULL")
+      .map_err(|e| e.to_string())?
+      .query_map([table], |row| row.get(0))
+      .map_err(|e| e.to_string())?
+      .collect::<Result<_, _>>()
+      .map_err(|e| e.to_string())?;
+    let rebuilt = format!("{}_rebuild", table);
+    let create = create
+      .replacen(&format!("CREATE TABLE {}", table), &format!("CREATE TABLE {}", rebuilt), 1)
+      .replace("REFERENCES timeline_events(id)", "REFERENCES event_ids(id)");
+    tx.execute_batch(&format!(
+      "{create};
+       INSERT INTO {rebuilt} SELECT * FROM {table};
+       DROP TABLE {table};
+       ALTER TABLE {rebuilt} RENAME TO {table};",
+    )).map_err(|e| e.to_string())?;
+    for sql in dependents {
+      tx.execute_batch(&sql).map_err(|e| e.to_string())?;
+    }
+    let violations: i64 = tx
+      .query_row("SELECT COUNT(*) FROM pragma_foreign_key_check(?)", [table], |row| row.get(0))
+      .map_err(|e| e.to_string())?;
+    if violations > 0 {
+      return Err(format!("{} rows in {} reference missing events", violations, table));
+    }
+  }
+  tx.commit().map_err(|e| e.to_string())
+}
+
+/// `CREATE TABLE IF NOT EXISTS` won't touch existing databases, so new columns
+/// are added here for users upgrading from an older schema.
+fn add_column_if_missing(
+  conn: &rusqlite::Connection,
+  table: &str,
+  column: &str,
+  decl: &str,
+) -> Result<(), String> {
+  let exists: bool = conn
+    .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))
+    .map_err(|e| e.to_string())?
+    .exists([column])
+    .map_err(|e| e.to_string())?;
+
+  if !exists {
+    conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))
+      .map_err(|e| e.to_string())?;
+  }
   Ok(())
 }
 
+/// Maps `SELECT id, type, title, note, text_content, created_at, source,
+/// is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec`.
+/// Without the last two a compressed text comes back as its preview.
+fn event_from_row(row: &rusqlite::Row) -> rusqlite::Result<TimelineEvent> {
+  let metadata: Option<String> = row.get(8)?;
+  let compressed: Option<Vec<u8>> = row.get(11).unwrap_or(None);
+  let codec: Option<String> = row.get(12).unwrap_or(None);
+  Ok(TimelineEvent {
+    id: row.get(0)?,
+    event_type: row.get(1)?,
+    title: row.get(2)?,
+    note: row.get(3)?,
+    text_content: compression::inflate(row.get(4)?, compressed, codec.as_deref()),
+    created_at: row.get(5)?,
+    source: row.get(6)?,
+    is_deleted: row.get::<_, i32>(7)? != 0,
+    metadata: metadata.and_then(|json| serde_json::from_str(&json).ok()),
+    ai_opt_out: row.get::<_, i32>(9)? != 0,
+    locked: row.get::<_, i32>(10)? != 0,
+  })
+}
+
+const ATTACHMENT_COLUMNS: &str =
+  "id, event_id, kind, original_path, stored_path, file_name, mime_type, size_bytes, sha256, width, height, created_at, duration_ms, derived_from";
+
+fn attachment_from_row(row: &rusqlite::Row) -> rusqlite::Result<Attachment> {
+  Ok(Attachment {
+    id: row.get(0)?,
+    event_id: row.get(1)?,
+    kind: row.get(2)?,
+    original_path: row.get(3)?,
+    stored_path: row.get(4)?,
+    file_name: row.get(5)?,
+    mime_type: row.get(6)?,
+    size_bytes: row.get(7)?,
+    sha256: row.get(8)?,
+    width: row.get(9)?,
+    height: row.get(10)?,
+    created_at: row.get(11)?,
+    duration_ms: row.get(12)?,
+    derived_from: row.get(13)?,
+  })
+}
+
+fn query_reminders(conn: &rusqlite::Connection, event_id: &str) -> Result<Vec<Reminder>, String> {
+  let reminders = conn
+    .prepare(
+      "SELECT id, event_id, remind_at, message, status, triggered_at, snooze_until, created_at, attachment_id,
+              follow_up_after_minutes, follow_up_message
+       FROM reminders WHERE event_id = ?",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([event_id], |row| {
+      Ok(Reminder {
+        id: row.get(0)?,
+        event_id: row.get(1)?,
+        remind_at: row.get(2)?,
+        message: row.get(3)?,
+        status: row.get(4)?,
+        triggered_at: row.get(5)?,
+        snooze_until: row.get(6)?,
+        created_at: row.get(7)?,
+        attachment_id: row.get(8)?,
+        follow_up_after_minutes: row.get(9)?,
+        follow_up_message: row.get(10)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(reminders)
+}
+
+fn query_attachments(conn: &rusqlite::Connection, event_id: &str) -> Result<Vec<Attachment>, String> {
+  let attachments = conn
+    .prepare(&format!("SELECT {} FROM attachments WHERE event_id = ?", ATTACHMENT_COLUMNS))
+    .map_err(|e| e.to_string())?
+    .query_map([event_id], attachment_from_row)
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(attachments)
+}
+
 fn hash_file(path: &Path) -> Result<String, String> {
   let mut file = File::open(path).map_err(|e| e.to_string())?;
   let mut hasher = Sha256::new();
@@ -318,7 +1077,7 @@ fn insert_drop_record(db_path: &Path, path: &Path) -> Result<DropRecord, String>
     .map_err(|e| e.to_string())?
     .as_secs() as i64;
 
-  let conn = rusqlite::Connection::open(db_path).map_err(|e| e.to_string())?;
+  let conn = open_db(db_path).map_err(|e| e.to_string())?;
   conn
     .execute(
       "INSERT INTO drop_records (path, hash, created_at) VALUES (?1, ?2, ?3)",
@@ -344,7 +1103,7 @@ fn process_drop_paths(
   }
   let first_path = paths[0].clone();
   let guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let result = insert_drop_record(&state.path, &first_path);
+  let result = insert_drop_record(&state.path(), &first_path);
   drop(guard);
   result
 }
@@ -357,7 +1116,7 @@ fn save_mock_result(
   content: String,
 ) -> Result<(), String> {
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
 
   let column = match kind.as_str() {
     "summarize" => "summary",
@@ -434,10 +1193,19 @@ fn set_window_size(
 
 #[tauri::command]
 async fn call_llm_api(request: LlmRequest) -> Result<String, String> {
+  let _timer = usage_metrics::time_async("call_llm_api");
+  request_llm(request).await
+}
+
+/// Send a prompt to the chosen provider and return its answer; the body of
+/// `call_llm_api`, for use from other commands.
+async fn request_llm(mut request: LlmRequest) -> Result<String, String> {
+  permissions::ensure_llm()?;
+  request.prompt = redaction::redact(&request.prompt);
   let max_tokens = request.max_tokens.unwrap_or(150);
   
   if request.provider == "openai" {
-    let client = reqwest::Client::new();
+    let client = connectivity::client_builder().build().map_err(|e| e.to_string())?;
     let url = "https://api.openai.com/v1/chat/completions";
     
     let body = serde_json::json!({
@@ -459,7 +1227,7 @@ async fn call_llm_api(request: LlmRequest) -> Result<String, String> {
       .json(&body)
       .send()
       .await
-      .map_err(|e| format!("Request failed: {}", e))?;
+      .map_err(|e| connectivity::request_error("Request failed", e))?;
     
     if response.status().is_success() {
       let json: serde_json::Value = response.json().await
@@ -475,7 +1243,7 @@ async fn call_llm_api(request: LlmRequest) -> Result<String, String> {
       Err(format!("API error: {}", error_text))
     }
   } else if request.provider == "anthropic" {
-    let client = reqwest::Client::new();
+    let client = connectivity::client_builder().build().map_err(|e| e.to_string())?;
     let url = "https://api.anthropic.com/v1/messages";
     
     let body = serde_json::json!({
@@ -497,7 +1265,7 @@ async fn call_llm_api(request: LlmRequest) -> Result<String, String> {
       .json(&body)
       .send()
       .await
-      .map_err(|e| format!("Request failed: {}", e))?;
+      .map_err(|e| connectivity::request_error("Request failed", e))?;
     
     if response.status().is_success() {
       let json: serde_json::Value = response.json().await
@@ -517,51 +1285,37 @@ async fn call_llm_api(request: LlmRequest) -> Result<String, String> {
   }
 }
 
-#[tauri::command]
-async fn read_file_content(file_path: String) -> Result<String, String> {
-  let path = PathBuf::from(&file_path);
-
-  // Check if file exists
-  if !path.exists() {
-    return Err(format!("File not found: {}", file_path));
-  }
-
-  // Check file size (limit to 1MB to avoid memory issues)
-  let metadata = fs::metadata(&path).map_err(|e| format!("Failed to read file metadata: {}", e))?;
-  if metadata.len() > 1_000_000 {
-    return Err("File too large (max 1MB)".to_string());
-  }
-
-  // Read file content
-  let content = fs::read_to_string(&path)
-    .map_err(|e| format!("Failed to read file: {}", e))?;
+// ============ Timeline Event Commands ============
 
-  Ok(content)
+/// Directory where files dropped from the webview are staged until they're
+/// attached and moved into the content store.
+fn drops_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+  let drops_dir = app.state::<DbState>().data_dir().join("drops");
+  fs::create_dir_all(&drops_dir)
+    .map_err(|e| format!("Failed to create drops dir: {}", e))?;
+  Ok(drops_dir)
 }
 
-// ============ Timeline Event Commands ============
+/// Sanitize the file name and prefix it with a timestamp to avoid
+/// collisions in the drops dir.
+fn unique_drop_name(file_name: &str) -> Result<String, String> {
+  Ok(format!("{}_{}", now_ms(), sandbox::safe_file_name(file_name)?))
+}
 
 #[tauri::command]
 fn save_dropped_file(
   app: tauri::AppHandle,
+  state: tauri::State<DbState>,
   request: SaveDroppedFileRequest,
 ) -> Result<String, String> {
-  // Get app data directory
-  let app_data = app.path().app_data_dir()
-    .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-  // Create drops directory if it doesn't exist
-  let drops_dir = app_data.join("drops");
-  fs::create_dir_all(&drops_dir)
-    .map_err(|e| format!("Failed to create drops dir: {}", e))?;
+  let policy = ingest::policy_for(&state)?;
+  if ingest::is_skipped(&policy, Path::new(&request.file_name)) {
+    return Err(i18n::t("This file type is skipped by the ingestion policy"));
+  }
+  ingest::check_size(&policy, request.content.len() as u64)?;
 
-  // Generate unique filename to avoid collisions
-  let timestamp = std::time::SystemTime::now()
-    .duration_since(std::time::UNIX_EPOCH)
-    .unwrap()
-    .as_millis();
-  let unique_name = format!("{}_{}", timestamp, request.file_name);
-  let file_path = drops_dir.join(&unique_name);
+  let drops_dir = drops_dir(&app)?;
+  let file_path = drops_dir.join(unique_drop_name(&request.file_name)?);
 
   // Write file content
   fs::write(&file_path, &request.content)
@@ -573,31 +1327,138 @@ fn save_dropped_file(
     .ok_or_else(|| "Invalid path".to_string())
 }
 
+/// Hash, sniff and record one file as an attachment of `event_id`. Depending
+/// on the ingestion policy the file is copied into the content store under
+/// `data_dir` or stays where it is with only its original path stored. Files
+/// the app wrote itself, like staged drops, are always moved into the store.
+fn insert_attachment(
+  conn: &rusqlite::Connection,
+  policy: &ingest::IngestionPolicy,
+  data_dir: &Path,
+  event_id: &str,
+  path_str: &str,
+  created_at: i64,
+) -> Result<Attachment, String> {
+  let path = PathBuf::from(path_str);
+  let attach_id = generate_id();
+  let file_name = path.file_name()
+    .and_then(|n| n.to_str())
+    .map(|s| s.to_string());
+  let mime_type = get_mime_type(&path);
+  let kind = media::attachment_kind(&mime_type);
+  let size_bytes = fs::metadata(&path).ok().map(|m| m.len() as i64);
+  let sha256 = hash_file(&path).ok();
+  let duration_ms = media::probe_duration_ms(&path, &mime_type);
+
+  let app_owned = path.starts_with(data_dir) && !cas::is_blob(data_dir, &path);
+  let stored_path = match (&sha256, size_bytes) {
+    (Some(sha), _) if app_owned => Some(cas::adopt(conn, data_dir, &path, sha)?),
+    (Some(sha), Some(size)) if ingest::should_copy(policy, size as u64) => {
+      Some(cas::store_file(conn, data_dir, &path, sha).inspect_err(|e| {
+        tracing::error!(path = %path.display(), error = %e, "Copying dropped file failed");
+      })?)
+    }
+    _ => None,
+  };
+  // An app-owned file only exists in the store from here on
+  let original_path = match &stored_path {
+    Some(stored) if app_owned => stored.clone(),
+    _ => path_str.to_string(),
+  };
+
+  conn.execute(
+    "INSERT INTO attachments (id, event_id, kind, original_path, stored_path, file_name, mime_type, size_bytes, sha256, created_at, duration_ms)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+    (
+      &attach_id,
+      event_id,
+      kind,
+      &original_path,
+      &stored_path,
+      &file_name,
+      &mime_type,
+      size_bytes,
+      &sha256,
+      created_at,
+      duration_ms,
+    ),
+  ).map_err(|e| e.to_string())?;
+
+  // EXIF is best effort; a broken header shouldn't fail the drop
+  let _ = photo_meta::record_metadata(conn, &attach_id, Path::new(&original_path));
+  // The file may still be locked or half written; try the hash again later
+  if sha256.is_none() {
+    jobs::enqueue(conn, "hash_attachment", serde_json::json!({ "attachmentId": attach_id }), 3)?;
+  }
+  if kind == "image" || kind == "video" {
+    jobs::enqueue(conn, "thumbnail", serde_json::json!({ "attachmentId": attach_id }), 1)?;
+  }
+
+  Ok(Attachment {
+    id: attach_id,
+    event_id: event_id.to_string(),
+    kind: kind.to_string(),
+    original_path,
+    stored_path,
+    file_name,
+    mime_type,
+    size_bytes,
+    sha256,
+    width: None,
+    height: None,
+    created_at,
+    duration_ms,
+    derived_from: None,
+  })
+}
+
 #[tauri::command]
 fn create_drop_event(
+  app: tauri::AppHandle,
   state: tauri::State<DbState>,
+  undo: tauri::State<undo::UndoState>,
   request: CreateDropEventRequest,
-) -> Result<TimelineEventWithAttachments, String> {
+) -> Result<DropEventCreated, String> {
   if request.paths.is_empty() {
     return Err("No files provided".to_string());
   }
 
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+  for path_str in &request.paths {
+    sandbox::check_readable(&app, &conn, path_str)?;
+  }
+  let admission = rate_limit::check("drop", (&request.paths, &request.note))?;
+
+  // Apply the ingestion policy before anything is written
+  let policy = ingest::load_policy(&conn);
+  let mut paths = Vec::new();
+  for path_str in &request.paths {
+    if ingest::admit(&policy, Path::new(path_str))? {
+      paths.push(path_str);
+    }
+  }
+  if paths.is_empty() {
+    return Err("All files were skipped by the ingestion policy".to_string());
+  }
+  let data_dir = state.data_dir();
+  let reaction = drop_reactions::evaluate(&conn, &paths);
 
   let event_id = generate_id();
   let created_at = now_ms();
 
   // Determine event type based on first file
-  let first_path = PathBuf::from(&request.paths[0]);
+  let first_path = PathBuf::from(paths[0]);
   let mime = get_mime_type(&first_path);
-  let event_type = if is_image_type(&mime) { "image" } else { "file" };
+  let event_type = media::attachment_kind(&mime);
   let title = first_path.file_name()
     .and_then(|n| n.to_str())
     .map(|s| s.to_string());
 
-  // Insert event
-  conn.execute(
+  // One transaction, so a failure doesn't leave an event missing some of
+  // its attachments
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  tx.execute(
     "INSERT INTO timeline_events (id, type, title, note, created_at, source, is_deleted)
      VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0)",
     (
@@ -612,49 +1473,25 @@ fn create_drop_event(
 
   // Insert attachments
   let mut attachments = Vec::new();
-  for path_str in &request.paths {
-    let path = PathBuf::from(path_str);
-    let attach_id = generate_id();
-    let file_name = path.file_name()
-      .and_then(|n| n.to_str())
-      .map(|s| s.to_string());
-    let mime_type = get_mime_type(&path);
-    let kind = if is_image_type(&mime_type) { "image" } else { "file" };
-    let size_bytes = fs::metadata(&path).ok().map(|m| m.len() as i64);
-    let sha256 = hash_file(&path).ok();
+  for path_str in paths {
+    attachments.push(insert_attachment(&tx, &policy, &data_dir, &event_id, path_str, created_at)?);
+  }
 
-    conn.execute(
-      "INSERT INTO attachments (id, event_id, kind, original_path, file_name, mime_type, size_bytes, sha256, created_at)
-       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-      (
-        &attach_id,
-        &event_id,
-        kind,
-        path_str,
-        &file_name,
-        &mime_type,
-        size_bytes,
-        &sha256,
-        created_at,
-      ),
+  let mut event_created_at = created_at;
+  if let Some(taken_at) = photo_meta::backdate_target(&tx, &event_id, request.backdate) {
+    tx.execute(
+      "UPDATE timeline_events SET created_at = ? WHERE id = ?",
+      (taken_at, &event_id),
     ).map_err(|e| e.to_string())?;
-
-    attachments.push(Attachment {
-      id: attach_id,
-      event_id: event_id.clone(),
-      kind: kind.to_string(),
-      original_path: path_str.clone(),
-      stored_path: None,
-      file_name,
-      mime_type,
-      size_bytes,
-      sha256,
-      width: None,
-      height: None,
-      created_at,
-    });
+    event_created_at = taken_at;
   }
 
+  tags::auto_tag(&tx, &event_id);
+  receipts::detect(&tx, &event_id);
+  tags::sync_hashtags(&tx, &event_id)?;
+  mentions::index(&tx, &event_id)?;
+  location::stamp(&tx, &event_id)?;
+
   // Insert reminder if requested
   let mut reminders = Vec::new();
   if let Some(remind_at) = request.remind_at {
@@ -663,7 +1500,7 @@ fn create_drop_event(
       .or(request.note.clone())
       .unwrap_or_else(|| title.clone().unwrap_or_else(|| "Reminder".to_string()));
 
-    conn.execute(
+    tx.execute(
       "INSERT INTO reminders (id, event_id, remind_at, message, status, created_at)
        VALUES (?1, ?2, ?3, ?4, 'pending', ?5)",
       (&reminder_id, &event_id, remind_at, &message, created_at),
@@ -678,8 +1515,16 @@ fn create_drop_event(
       triggered_at: None,
       snooze_until: None,
       created_at,
+      attachment_id: None,
+      follow_up_after_minutes: None,
+      follow_up_message: None,
     });
   }
+  tx.commit().map_err(|e| e.to_string())?;
+  if !reminders.is_empty() {
+    reminder_scan::wake(&app);
+  }
+  undo.record("Drop files", undo::Operation::SetDeleted { event_ids: vec![event_id.clone()], deleted: false });
 
   let event = TimelineEvent {
     id: event_id,
@@ -687,21 +1532,126 @@ fn create_drop_event(
     title,
     note: request.note,
     text_content: None,
-    created_at,
+    created_at: event_created_at,
     source: Some("drop".to_string()),
     is_deleted: false,
+    metadata: None,
+    ai_opt_out: false,
+    locked: false,
   };
 
-  Ok(TimelineEventWithAttachments { event, attachments, reminders })
+  rate_limit::record(admission);
+  Ok(DropEventCreated { created: TimelineEventWithAttachments { event, attachments, reminders }, reaction })
+}
+
+/// Width and height from a PNG's IHDR chunk.
+fn png_dimensions(bytes: &[u8]) -> Option<(i32, i32)> {
+  const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+  if bytes.len() < 24 || !bytes.starts_with(SIGNATURE) || &bytes[12..16] != b"IHDR" {
+    return None;
+  }
+  let width = u32::from_be_bytes(bytes[16..20].try_into().ok()?);
+  let height = u32::from_be_bytes(bytes[20..24].try_into().ok()?);
+  Some((width as i32, height as i32))
+}
+
+/// Create an image event from PNG bytes (e.g. a screenshot pasted from the
+/// clipboard). The image is written to the content store.
+#[tauri::command]
+fn create_image_event_from_bytes(
+  state: tauri::State<DbState>,
+  png_bytes: Vec<u8>,
+  note: Option<String>,
+) -> Result<TimelineEventWithAttachments, String> {
+  let (width, height) = png_dimensions(&png_bytes)
+    .ok_or_else(|| "Clipboard data is not a PNG image".to_string())?;
+
+  let file_name = "clipboard.png".to_string();
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let admission = rate_limit::check("clipboard", (&png_bytes, &note))?;
+  // Same size limit as dropped and uploaded files, checked before writing
+  ingest::check_size(&ingest::load_policy(&conn), png_bytes.len() as u64)?;
+  let (path_str, sha256) = cas::store_bytes(&conn, &state.data_dir(), &file_name, &png_bytes)?;
+
+  let event_id = generate_id();
+  let attach_id = generate_id();
+  let created_at = now_ms();
+  let title = Some("Clipboard image".to_string());
+  let mime_type = Some("image/png".to_string());
+  let size_bytes = Some(png_bytes.len() as i64);
+  let sha256 = Some(sha256);
+
+  let ai_opt_out = privacy::clipboard_opt_out(&conn);
+  conn.execute(
+    "INSERT INTO timeline_events (id, type, title, note, created_at, source, is_deleted, ai_opt_out)
+     VALUES (?1, 'image', ?2, ?3, ?4, 'clipboard', 0, ?5)",
+    (&event_id, &title, &note, created_at, ai_opt_out),
+  ).map_err(|e| e.to_string())?;
+
+  conn.execute(
+    "INSERT INTO attachments (id, event_id, kind, original_path, stored_path, file_name, mime_type, size_bytes, sha256, width, height, created_at)
+     VALUES (?1, ?2, 'image', ?3, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    (
+      &attach_id,
+      &event_id,
+      &path_str,
+      &file_name,
+      &mime_type,
+      size_bytes,
+      &sha256,
+      width,
+      height,
+      created_at,
+    ),
+  ).map_err(|e| e.to_string())?;
+  location::stamp(&conn, &event_id)?;
+
+  let event = TimelineEvent {
+    id: event_id.clone(),
+    event_type: "image".to_string(),
+    title,
+    note,
+    text_content: None,
+    created_at,
+    source: Some("clipboard".to_string()),
+    is_deleted: false,
+    metadata: None,
+    ai_opt_out,
+    locked: false,
+  };
+
+  let attachment = Attachment {
+    id: attach_id,
+    event_id,
+    kind: "image".to_string(),
+    original_path: path_str.clone(),
+    stored_path: Some(path_str),
+    file_name: Some(file_name),
+    mime_type,
+    size_bytes,
+    sha256,
+    width: Some(width),
+    height: Some(height),
+    created_at,
+    duration_ms: None,
+    derived_from: None,
+  };
+
+  rate_limit::record(admission);
+  Ok(TimelineEventWithAttachments { event, attachments: vec![attachment], reminders: Vec::new() })
 }
 
 #[tauri::command]
 fn create_text_event(
   state: tauri::State<DbState>,
+  scan: tauri::State<reminder_scan::ReminderScanState>,
   request: CreateTextEventRequest,
 ) -> Result<TimelineEventWithAttachments, String> {
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let admission = rate_limit::check("manual", (&request.note, &request.text_content))?;
 
   let event_id = generate_id();
   let created_at = now_ms();
@@ -718,6 +1668,11 @@ fn create_text_event(
       created_at,
     ),
   ).map_err(|e| e.to_string())?;
+  compression::compress_if_large(&conn, &event_id)?;
+  tags::auto_tag(&conn, &event_id);
+  tags::sync_hashtags(&conn, &event_id)?;
+  mentions::index(&conn, &event_id)?;
+  location::stamp(&conn, &event_id)?;
 
   // Insert reminder if requested
   let mut reminders = Vec::new();
@@ -741,7 +1696,11 @@ fn create_text_event(
       triggered_at: None,
       snooze_until: None,
       created_at,
+      attachment_id: None,
+      follow_up_after_minutes: None,
+      follow_up_message: None,
     });
+    scan.wake();
   }
 
   let event = TimelineEvent {
@@ -753,8 +1712,12 @@ fn create_text_event(
     created_at,
     source: Some("manual".to_string()),
     is_deleted: false,
+    metadata: None,
+    ai_opt_out: false,
+    locked: false,
   };
 
+  rate_limit::record(admission);
   Ok(TimelineEventWithAttachments { event, attachments: vec![], reminders })
 }
 
@@ -763,16 +1726,18 @@ fn list_events(
   state: tauri::State<DbState>,
   request: ListEventsRequest,
 ) -> Result<Vec<TimelineEventWithAttachments>, String> {
+  app_lock::ensure_unlocked()?;
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
 
   let page = request.page.unwrap_or(0);
   let page_size = request.page_size.unwrap_or(50);
   let offset = page * page_size;
 
-  let mut sql = String::from(
-    "SELECT id, type, title, note, text_content, created_at, source, is_deleted
-     FROM timeline_events WHERE is_deleted = 0"
+  let mut sql = format!(
+    "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
+     FROM {} WHERE is_deleted = 0 AND scheduled_for IS NULL",
+    archive::events_table(request.include_archived.unwrap_or(false))
   );
   let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![];
 
@@ -784,6 +1749,9 @@ fn list_events(
     sql.push_str(" AND created_at <= ?");
     params.push(Box::new(end));
   }
+  for (key, value) in request.metadata_filter.iter().flatten() {
+    push_metadata_condition(&mut sql, &mut params, key, value)?;
+  }
 
   sql.push_str(" ORDER BY created_at DESC LIMIT ? OFFSET ?");
   params.push(Box::new(page_size));
@@ -793,18 +1761,7 @@ fn list_events(
 
   let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
   let events: Vec<TimelineEvent> = stmt
-    .query_map(params_refs.as_slice(), |row| {
-      Ok(TimelineEvent {
-        id: row.get(0)?,
-        event_type: row.get(1)?,
-        title: row.get(2)?,
-        note: row.get(3)?,
-        text_content: row.get(4)?,
-        created_at: row.get(5)?,
-        source: row.get(6)?,
-        is_deleted: row.get::<_, i32>(7)? != 0,
-      })
-    })
+    .query_map(params_refs.as_slice(), event_from_row)
     .map_err(|e| e.to_string())?
     .filter_map(|r| r.ok())
     .collect();
@@ -812,47 +1769,9 @@ fn list_events(
   // Fetch attachments and reminders for each event
   let mut results = Vec::new();
   for event in events {
-    let attachments: Vec<Attachment> = conn
-      .prepare("SELECT id, event_id, kind, original_path, stored_path, file_name, mime_type, size_bytes, sha256, width, height, created_at FROM attachments WHERE event_id = ?")
-      .map_err(|e| e.to_string())?
-      .query_map([&event.id], |row| {
-        Ok(Attachment {
-          id: row.get(0)?,
-          event_id: row.get(1)?,
-          kind: row.get(2)?,
-          original_path: row.get(3)?,
-          stored_path: row.get(4)?,
-          file_name: row.get(5)?,
-          mime_type: row.get(6)?,
-          size_bytes: row.get(7)?,
-          sha256: row.get(8)?,
-          width: row.get(9)?,
-          height: row.get(10)?,
-          created_at: row.get(11)?,
-        })
-      })
-      .map_err(|e| e.to_string())?
-      .filter_map(|r| r.ok())
-      .collect();
+    let attachments: Vec<Attachment> = query_attachments(&conn, &event.id)?;
 
-    let reminders: Vec<Reminder> = conn
-      .prepare("SELECT id, event_id, remind_at, message, status, triggered_at, snooze_until, created_at FROM reminders WHERE event_id = ?")
-      .map_err(|e| e.to_string())?
-      .query_map([&event.id], |row| {
-        Ok(Reminder {
-          id: row.get(0)?,
-          event_id: row.get(1)?,
-          remind_at: row.get(2)?,
-          message: row.get(3)?,
-          status: row.get(4)?,
-          triggered_at: row.get(5)?,
-          snooze_until: row.get(6)?,
-          created_at: row.get(7)?,
-        })
-      })
-      .map_err(|e| e.to_string())?
-      .filter_map(|r| r.ok())
-      .collect();
+    let reminders: Vec<Reminder> = query_reminders(&conn, &event.id)?;
 
     results.push(TimelineEventWithAttachments { event, attachments, reminders });
   }
@@ -865,70 +1784,26 @@ fn get_event_detail(
   state: tauri::State<DbState>,
   event_id: String,
 ) -> Result<TimelineEventWithAttachments, String> {
+  app_lock::ensure_unlocked()?;
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
 
+  // Archived events can still be opened, just not edited
   let event: TimelineEvent = conn
     .query_row(
-      "SELECT id, type, title, note, text_content, created_at, source, is_deleted
-       FROM timeline_events WHERE id = ?",
+      &format!(
+        "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
+         FROM {} WHERE id = ?",
+        archive::events_table(true)
+      ),
       [&event_id],
-      |row| {
-        Ok(TimelineEvent {
-          id: row.get(0)?,
-          event_type: row.get(1)?,
-          title: row.get(2)?,
-          note: row.get(3)?,
-          text_content: row.get(4)?,
-          created_at: row.get(5)?,
-          source: row.get(6)?,
-          is_deleted: row.get::<_, i32>(7)? != 0,
-        })
-      },
+      event_from_row,
     )
-    .map_err(|_| "Event not found".to_string())?;
+    .map_err(|_| i18n::t("Event not found"))?;
 
-  let attachments: Vec<Attachment> = conn
-    .prepare("SELECT id, event_id, kind, original_path, stored_path, file_name, mime_type, size_bytes, sha256, width, height, created_at FROM attachments WHERE event_id = ?")
-    .map_err(|e| e.to_string())?
-    .query_map([&event_id], |row| {
-      Ok(Attachment {
-        id: row.get(0)?,
-        event_id: row.get(1)?,
-        kind: row.get(2)?,
-        original_path: row.get(3)?,
-        stored_path: row.get(4)?,
-        file_name: row.get(5)?,
-        mime_type: row.get(6)?,
-        size_bytes: row.get(7)?,
-        sha256: row.get(8)?,
-        width: row.get(9)?,
-        height: row.get(10)?,
-        created_at: row.get(11)?,
-      })
-    })
-    .map_err(|e| e.to_string())?
-    .filter_map(|r| r.ok())
-    .collect();
+  let attachments: Vec<Attachment> = query_attachments(&conn, &event_id)?;
 
-  let reminders: Vec<Reminder> = conn
-    .prepare("SELECT id, event_id, remind_at, message, status, triggered_at, snooze_until, created_at FROM reminders WHERE event_id = ?")
-    .map_err(|e| e.to_string())?
-    .query_map([&event_id], |row| {
-      Ok(Reminder {
-        id: row.get(0)?,
-        event_id: row.get(1)?,
-        remind_at: row.get(2)?,
-        message: row.get(3)?,
-        status: row.get(4)?,
-        triggered_at: row.get(5)?,
-        snooze_until: row.get(6)?,
-        created_at: row.get(7)?,
-      })
-    })
-    .map_err(|e| e.to_string())?
-    .filter_map(|r| r.ok())
-    .collect();
+  let reminders: Vec<Reminder> = query_reminders(&conn, &event_id)?;
 
   Ok(TimelineEventWithAttachments { event, attachments, reminders })
 }
@@ -936,15 +1811,20 @@ fn get_event_detail(
 #[tauri::command]
 fn delete_event(
   state: tauri::State<DbState>,
+  undo: tauri::State<undo::UndoState>,
   event_id: String,
 ) -> Result<(), String> {
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
 
-  conn.execute(
-    "UPDATE timeline_events SET is_deleted = 1 WHERE id = ?",
+  let changed = conn.execute(
+    "UPDATE timeline_events SET is_deleted = 1 WHERE id = ? AND is_deleted = 0",
     [&event_id],
   ).map_err(|e| e.to_string())?;
+  if changed > 0 {
+    cancel_reminders(&conn, &event_id, "event deleted")?;
+    undo.record("Delete event", undo::Operation::SetDeleted { event_ids: vec![event_id], deleted: true });
+  }
 
   Ok(())
 }
@@ -952,40 +1832,291 @@ fn delete_event(
 #[tauri::command]
 fn update_event_note(
   state: tauri::State<DbState>,
+  undo: tauri::State<undo::UndoState>,
   event_id: String,
   note: String,
 ) -> Result<(), String> {
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  // The note of a locked event lives encrypted in event_locks
+  if event_lock::is_locked(&conn, &event_id)? {
+    return Err(i18n::t("Event is locked"));
+  }
+  let before: Option<String> = conn
+    .query_row("SELECT note FROM timeline_events WHERE id = ?", [&event_id], |row| row.get(0))
+    .map_err(|e| e.to_string())?;
 
   conn.execute(
     "UPDATE timeline_events SET note = ? WHERE id = ?",
     (&note, &event_id),
   ).map_err(|e| e.to_string())?;
+  tags::sync_hashtags(&conn, &event_id)?;
+  mentions::index(&conn, &event_id)?;
+  if before.as_deref() != Some(note.as_str()) {
+    undo.record("Edit note", undo::Operation::UpdateNote { event_id, before, after: Some(note) });
+  }
+
+  Ok(())
+}
+
+/// Merge `json_patch` into the event's metadata (RFC 7396: `null` removes a
+/// key) and return the result.
+#[tauri::command]
+fn set_event_metadata(
+  state: tauri::State<DbState>,
+  event_id: String,
+  json_patch: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+  if !json_patch.is_object() {
+    return Err("Metadata patch must be a JSON object".to_string());
+  }
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let updated = conn.execute(
+    "UPDATE timeline_events SET metadata = json_patch(COALESCE(metadata, '{}'), ?1)
+     WHERE id = ?2 AND is_deleted = 0",
+    (json_patch.to_string(), &event_id),
+  ).map_err(|e| e.to_string())?;
+  if updated == 0 {
+    return Err(i18n::t("Event not found"));
+  }
 
+  let metadata: String = conn
+    .query_row("SELECT metadata FROM timeline_events WHERE id = ?", [&event_id], |row| row.get(0))
+    .map_err(|e| e.to_string())?;
+  serde_json::from_str(&metadata).map_err(|e| e.to_string())
+}
+
+/// JSON path for a metadata filter key like `mood` or `project.name`.
+fn metadata_path(key: &str) -> Result<String, String> {
+  let valid = !key.is_empty()
+    && key.split('.').all(|part| {
+      !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    });
+  if !valid {
+    return Err(format!("Invalid metadata key: {}", key));
+  }
+  let quoted: Vec<String> = key.split('.').map(|part| format!("\"{}\"", part)).collect();
+  Ok(format!("$.{}", quoted.join(".")))
+}
+
+/// Append `AND <metadata key> = <value>` to a query under construction.
+fn push_metadata_condition(
+  sql: &mut String,
+  params: &mut Vec<Box<dyn rusqlite::ToSql>>,
+  key: &str,
+  value: &serde_json::Value,
+) -> Result<(), String> {
+  let path = metadata_path(key)?;
+  // json_extract returns SQL values: 1/0 for booleans, text for strings
+  match value {
+    serde_json::Value::Null => {
+      sql.push_str(" AND json_extract(metadata, ?) IS NULL");
+      params.push(Box::new(path));
+    }
+    serde_json::Value::Bool(b) => {
+      sql.push_str(" AND json_extract(metadata, ?) = ?");
+      params.push(Box::new(path));
+      params.push(Box::new(*b as i64));
+    }
+    serde_json::Value::Number(n) => {
+      sql.push_str(" AND json_extract(metadata, ?) = ?");
+      params.push(Box::new(path));
+      params.push(Box::new(n.as_f64().unwrap_or_default()));
+    }
+    serde_json::Value::String(text) => {
+      sql.push_str(" AND json_extract(metadata, ?) = ?");
+      params.push(Box::new(path));
+      params.push(Box::new(text.clone()));
+    }
+    _ => {
+      // Arrays and objects come back as JSON text
+      sql.push_str(" AND json_extract(metadata, ?) = json(?)");
+      params.push(Box::new(path));
+      params.push(Box::new(value.to_string()));
+    }
+  }
+  Ok(())
+}
+
+/// Attach another file to an existing event.
+#[tauri::command]
+fn add_attachment_to_event(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  event_id: String,
+  path: String,
+) -> Result<Attachment, String> {
+  if !Path::new(&path).is_file() {
+    return Err(format!("File not found: {}", path));
+  }
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  sandbox::check_readable(&app, &conn, &path)?;
+
+  let policy = ingest::load_policy(&conn);
+  if !ingest::admit(&policy, Path::new(&path))? {
+    return Err(i18n::t("This file type is skipped by the ingestion policy"));
+  }
+
+  let exists: bool = conn
+    .query_row(
+      "SELECT COUNT(*) FROM timeline_events WHERE id = ? AND is_deleted = 0",
+      [&event_id],
+      |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+    .map_err(|e| e.to_string())?;
+  if !exists {
+    return Err(i18n::t("Event not found"));
+  }
+  if event_lock::is_locked(&conn, &event_id)? {
+    return Err(i18n::t("Event is locked"));
+  }
+
+  insert_attachment(&conn, &policy, &state.data_dir(), &event_id, &path, now_ms())
+}
+
+/// Remove an attachment. Files the app keeps in its own data folder (the
+/// content store, older drops and recordings) are deleted once nothing else
+/// references them;
+/// files elsewhere on disk are never touched.
+#[tauri::command]
+fn remove_attachment(
+  app_handle: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  attachment_id: String,
+) -> Result<(), String> {
+  let app_data = app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let (original_path, stored_path): (String, Option<String>) = conn
+    .query_row(
+      "SELECT original_path, stored_path FROM attachments WHERE id = ?",
+      [&attachment_id],
+      |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(|_| "Attachment not found".to_string())?;
+
+  conn.execute("DELETE FROM attachment_metadata WHERE attachment_id = ?", [&attachment_id])
+    .map_err(|e| e.to_string())?;
+  conn.execute("DELETE FROM attachments WHERE id = ?", [&attachment_id])
+    .map_err(|e| e.to_string())?;
+  // Reminders about the file fall back to the event
+  conn.execute("UPDATE reminders SET attachment_id = NULL WHERE attachment_id = ?", [&attachment_id])
+    .map_err(|e| e.to_string())?;
+
+  for file in std::iter::once(original_path).chain(stored_path) {
+    remove_unused_file(&conn, &app_data, &file)?;
+  }
+
+  Ok(())
+}
+
+/// Delete `file` if it lies in the app's data folder and no attachment
+/// refers to it any more.
+fn remove_unused_file(conn: &rusqlite::Connection, app_data: &Path, file: &str) -> Result<(), String> {
+  let path = PathBuf::from(file);
+  if !path.starts_with(app_data) || cas::release(conn, file)? {
+    return Ok(());
+  }
+  let still_used: i64 = conn
+    .query_row(
+      "SELECT COUNT(*) FROM attachments WHERE original_path = ?1 OR stored_path = ?1",
+      [file],
+      |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())?;
+  if still_used == 0 {
+    let _ = fs::remove_file(&path);
+  }
   Ok(())
 }
 
 // ============ Reminder Commands ============
 
+/// Cancel the reminders of a deleted or archived event that haven't fired
+/// yet, so the scanner never brings them up. `reason` goes to the reminder log.
+fn cancel_reminders(conn: &rusqlite::Connection, event_id: &str, reason: &str) -> Result<(), String> {
+  let ids = reminder_ids(conn, event_id, "status IN ('pending', 'snoozed')")?;
+  for id in ids {
+    reminder_log::record(conn, &id, None, Some("cancelled"), reason)?;
+    conn.execute("UPDATE reminders SET status = 'cancelled' WHERE id = ?", [&id]).map_err(|e| e.to_string())?;
+  }
+  Ok(())
+}
+
+/// Ids of the reminders of `event_id` matching `condition`.
+fn reminder_ids(conn: &rusqlite::Connection, event_id: &str, condition: &str) -> Result<Vec<String>, String> {
+  let mut stmt = conn
+    .prepare(&format!("SELECT id FROM reminders WHERE event_id = ? AND {}", condition))
+    .map_err(|e| e.to_string())?;
+  let rows = stmt.query_map([event_id], |row| row.get(0)).map_err(|e| e.to_string())?;
+  rows.collect::<Result<Vec<String>, _>>().map_err(|e| e.to_string())
+}
+
+/// Undo `cancel_reminders` for an event brought back from the trash.
+/// Reminders that were snoozed go back to waiting for their snooze.
+fn restore_reminders(conn: &rusqlite::Connection, event_id: &str) -> Result<(), String> {
+  let ids = reminder_ids(conn, event_id, "status = 'cancelled'")?;
+  for id in ids {
+    conn.execute(
+      "UPDATE reminders SET status = CASE WHEN snooze_until IS NULL THEN 'pending' ELSE 'snoozed' END WHERE id = ?",
+      [&id],
+    ).map_err(|e| e.to_string())?;
+    reminder_log::record(conn, &id, Some("cancelled"), None, "event restored")?;
+  }
+  Ok(())
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn create_reminder(
   state: tauri::State<DbState>,
+  scan: tauri::State<reminder_scan::ReminderScanState>,
   event_id: String,
   remind_at: i64,
   message: String,
+  attachment_id: Option<String>,
+  follow_up_after_minutes: Option<i64>,
+  follow_up_message: Option<String>,
 ) -> Result<Reminder, String> {
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  if let Some(attachment_id) = &attachment_id {
+    let on_event: bool = conn
+      .query_row(
+        "SELECT COUNT(*) > 0 FROM attachments WHERE id = ? AND event_id = ?",
+        [attachment_id, &event_id],
+        |row| row.get(0),
+      )
+      .map_err(|e| e.to_string())?;
+    if !on_event {
+      return Err("Attachment not found on this event".to_string());
+    }
+  }
+
+  if follow_up_after_minutes.is_some_and(|minutes| minutes <= 0) {
+    return Err("Follow-up delay must be at least a minute".to_string());
+  }
+  let follow_up_message = follow_up_message.map(|m| m.trim().to_string()).filter(|m| !m.is_empty());
 
   let reminder_id = generate_id();
   let created_at = now_ms();
 
   conn.execute(
-    "INSERT INTO reminders (id, event_id, remind_at, message, status, created_at)
-     VALUES (?1, ?2, ?3, ?4, 'pending', ?5)",
-    (&reminder_id, &event_id, remind_at, &message, created_at),
+    "INSERT INTO reminders (id, event_id, remind_at, message, status, created_at, attachment_id,
+                            follow_up_after_minutes, follow_up_message)
+     VALUES (?1, ?2, ?3, ?4, 'pending', ?5, ?6, ?7, ?8)",
+    (&reminder_id, &event_id, remind_at, &message, created_at, &attachment_id, follow_up_after_minutes, &follow_up_message),
   ).map_err(|e| e.to_string())?;
+  scan.wake();
 
   Ok(Reminder {
     id: reminder_id,
@@ -996,57 +2127,125 @@ fn create_reminder(
     triggered_at: None,
     snooze_until: None,
     created_at,
+    attachment_id,
+    follow_up_after_minutes,
+    follow_up_message,
   })
 }
 
+/// Snooze by `snooze_minutes` or a named preset (see `snooze.rs`).
+/// Returns the computed `snooze_until`.
 #[tauri::command]
 fn snooze_reminder(
   state: tauri::State<DbState>,
+  scan: tauri::State<reminder_scan::ReminderScanState>,
   reminder_id: String,
-  snooze_minutes: i64,
-) -> Result<(), String> {
+  snooze_minutes: Option<i64>,
+  preset: Option<String>,
+) -> Result<i64, String> {
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
 
-  let snooze_until = now_ms() + snooze_minutes * 60 * 1000;
+  let snooze_until = snooze::snooze_target(&conn, snooze_minutes, preset.as_deref())?;
 
-  conn.execute(
-    "UPDATE reminders SET status = 'snoozed', snooze_until = ? WHERE id = ?",
+  // Like dismissing: a dismissed or cancelled reminder stays that way
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  reminder_log::record(&tx, &reminder_id, None, Some("snoozed"), "snoozed")?;
+  let snoozed = tx.execute(
+    "UPDATE reminders SET status = 'snoozed', snooze_until = ?1, snooze_count = snooze_count + 1
+     WHERE id = ?2 AND status IN ('pending', 'snoozed', 'triggered')",
     (snooze_until, &reminder_id),
   ).map_err(|e| e.to_string())?;
+  if snoozed == 0 {
+    return Err(i18n::t("Reminder is no longer active"));
+  }
+  tx.commit().map_err(|e| e.to_string())?;
+  scan.wake();
 
-  Ok(())
+  Ok(snooze_until)
 }
 
+/// Dismiss a reminder. One with a follow-up set schedules the next step and
+/// returns it.
 #[tauri::command]
 fn dismiss_reminder(
   state: tauri::State<DbState>,
+  scan: tauri::State<reminder_scan::ReminderScanState>,
   reminder_id: String,
-) -> Result<(), String> {
+) -> Result<Option<Reminder>, String> {
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
 
   let triggered_at = now_ms();
-
-  conn.execute(
-    "UPDATE reminders SET status = 'dismissed', triggered_at = ? WHERE id = ?",
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+  // Only live reminders can be dismissed: dismissing twice mustn't schedule
+  // the follow-up twice, and a cancelled one belongs to a deleted or
+  // archived event
+  reminder_log::record(&tx, &reminder_id, None, Some("dismissed"), "dismissed")?;
+  let dismissed = tx.execute(
+    "UPDATE reminders SET status = 'dismissed', triggered_at = ?1, dismissed_at = ?1
+     WHERE id = ?2 AND status IN ('pending', 'snoozed', 'triggered')",
     (triggered_at, &reminder_id),
   ).map_err(|e| e.to_string())?;
+  if dismissed == 0 {
+    return Err(i18n::t("Reminder is no longer active"));
+  }
+  let next = tx
+    .query_row(
+      "SELECT event_id, follow_up_after_minutes, COALESCE(follow_up_message, message), attachment_id
+       FROM reminders WHERE id = ? AND follow_up_after_minutes IS NOT NULL",
+      [&reminder_id],
+      |row| {
+        Ok(Reminder {
+          id: generate_id(),
+          event_id: row.get(0)?,
+          remind_at: triggered_at + row.get::<_, i64>(1)? * 60 * 1000,
+          message: row.get(2)?,
+          status: "pending".to_string(),
+          triggered_at: None,
+          snooze_until: None,
+          created_at: triggered_at,
+          attachment_id: row.get(3)?,
+          follow_up_after_minutes: None,
+          follow_up_message: None,
+        })
+      },
+    )
+    .optional()
+    .map_err(|e| e.to_string())?;
+  if let Some(reminder) = &next {
+    tx.execute(
+      "INSERT INTO reminders (id, event_id, remind_at, message, status, created_at, attachment_id)
+       VALUES (?1, ?2, ?3, ?4, 'pending', ?5, ?6)",
+      (&reminder.id, &reminder.event_id, reminder.remind_at, &reminder.message, reminder.created_at, &reminder.attachment_id),
+    ).map_err(|e| e.to_string())?;
+  }
+  tx.commit().map_err(|e| e.to_string())?;
 
-  Ok(())
+  if let Some(next) = &next {
+    tracing::info!(reminder_id = %reminder_id, follow_up = %next.id, "Follow-up reminder scheduled");
+    scan.wake();
+  }
+  Ok(next)
 }
 
 #[tauri::command]
 fn list_pending_reminders(
   state: tauri::State<DbState>,
 ) -> Result<Vec<Reminder>, String> {
+  app_lock::ensure_unlocked()?;
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
 
   let reminders: Vec<Reminder> = conn
     .prepare(
-      "SELECT id, event_id, remind_at, message, status, triggered_at, snooze_until, created_at
-       FROM reminders WHERE status = 'pending' OR status = 'snoozed' ORDER BY remind_at ASC"
+      "SELECT id, event_id, remind_at, message, status, triggered_at, snooze_until, created_at, attachment_id,
+              follow_up_after_minutes, follow_up_message
+       FROM reminders
+       WHERE (status = 'pending' OR status = 'snoozed')
+         AND NOT EXISTS (SELECT 1 FROM timeline_events e WHERE e.id = reminders.event_id AND e.is_deleted = 1)
+       ORDER BY remind_at ASC"
     )
     .map_err(|e| e.to_string())?
     .query_map([], |row| {
@@ -1059,6 +2258,9 @@ fn list_pending_reminders(
         triggered_at: row.get(5)?,
         snooze_until: row.get(6)?,
         created_at: row.get(7)?,
+        attachment_id: row.get(8)?,
+        follow_up_after_minutes: row.get(9)?,
+        follow_up_message: row.get(10)?,
       })
     })
     .map_err(|e| e.to_string())?
@@ -1068,6 +2270,55 @@ fn list_pending_reminders(
   Ok(reminders)
 }
 
+/// Reminder plus its event and attachments, as shown by a reminder popup.
+#[tauri::command]
+fn get_reminder_payload(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  reminder_id: String,
+) -> Result<ReminderDuePayload, String> {
+  app_lock::ensure_unlocked()?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let reminder: Reminder = conn
+    .query_row(
+      "SELECT id, event_id, remind_at, message, status, triggered_at, snooze_until, created_at, attachment_id,
+              follow_up_after_minutes, follow_up_message
+       FROM reminders WHERE id = ?",
+      [&reminder_id],
+      |row| {
+        Ok(Reminder {
+          id: row.get(0)?,
+          event_id: row.get(1)?,
+          remind_at: row.get(2)?,
+          message: row.get(3)?,
+          status: row.get(4)?,
+          triggered_at: row.get(5)?,
+          snooze_until: row.get(6)?,
+          created_at: row.get(7)?,
+          attachment_id: row.get(8)?,
+          follow_up_after_minutes: row.get(9)?,
+          follow_up_message: row.get(10)?,
+        })
+      },
+    )
+    .map_err(|_| "Reminder not found".to_string())?;
+
+  let event: TimelineEvent = conn
+    .query_row(
+      "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
+       FROM timeline_events WHERE id = ?",
+      [&reminder.event_id],
+      event_from_row,
+    )
+    .map_err(|_| i18n::t("Event not found"))?;
+
+  let attachments = query_attachments(&conn, &reminder.event_id)?;
+
+  Ok(ReminderDuePayload::new(&app, reminder, event, attachments))
+}
+
 // ============ Settings Commands ============
 
 // ============ RAG Search Command ============
@@ -1084,38 +2335,29 @@ fn search_for_rag(
   query: String,
   limit: Option<i32>,
 ) -> Result<RagContext, String> {
+  app_lock::ensure_unlocked()?;
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
 
   let search_limit = limit.unwrap_or(10);
   let search_pattern = format!("%{}%", query.to_lowercase());
 
-  // Search events by title, note, or text_content
+  // Search events by title, note, or the full text
   let events: Vec<TimelineEvent> = conn
-    .prepare(
-      "SELECT id, type, title, note, text_content, created_at, source, is_deleted
+    .prepare(&format!(
+      "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
        FROM timeline_events
-       WHERE is_deleted = 0 AND (
+       WHERE is_deleted = 0 AND scheduled_for IS NULL AND ai_opt_out = 0 AND (
          LOWER(title) LIKE ?1 OR
          LOWER(note) LIKE ?1 OR
-         LOWER(text_content) LIKE ?1
+         LOWER({}) LIKE ?1
        )
        ORDER BY created_at DESC
-       LIMIT ?2"
-    )
+       LIMIT ?2",
+      compression::FULL_TEXT_SQL
+    ))
     .map_err(|e| e.to_string())?
-    .query_map([&search_pattern, &search_limit.to_string()], |row| {
-      Ok(TimelineEvent {
-        id: row.get(0)?,
-        event_type: row.get(1)?,
-        title: row.get(2)?,
-        note: row.get(3)?,
-        text_content: row.get(4)?,
-        created_at: row.get(5)?,
-        source: row.get(6)?,
-        is_deleted: row.get::<_, i32>(7)? != 0,
-      })
-    })
+    .query_map([&search_pattern, &search_limit.to_string()], event_from_row)
     .map_err(|e| e.to_string())?
     .filter_map(|r| r.ok())
     .collect();
@@ -1125,31 +2367,7 @@ fn search_for_rag(
   let mut attachments: Vec<Attachment> = Vec::new();
 
   for event_id in &event_ids {
-    let event_attachments: Vec<Attachment> = conn
-      .prepare("SELECT id, event_id, kind, original_path, stored_path, file_name, mime_type, size_bytes, sha256, width, height, created_at FROM attachments WHERE event_id = ?")
-      .ok()
-      .map(|mut stmt| {
-        stmt.query_map([event_id], |row| {
-          Ok(Attachment {
-            id: row.get(0)?,
-            event_id: row.get(1)?,
-            kind: row.get(2)?,
-            original_path: row.get(3)?,
-            stored_path: row.get(4)?,
-            file_name: row.get(5)?,
-            mime_type: row.get(6)?,
-            size_bytes: row.get(7)?,
-            sha256: row.get(8)?,
-            width: row.get(9)?,
-            height: row.get(10)?,
-            created_at: row.get(11)?,
-          })
-        })
-        .ok()
-        .map(|iter| iter.filter_map(|r| r.ok()).collect())
-        .unwrap_or_default()
-      })
-      .unwrap_or_default();
+    let event_attachments: Vec<Attachment> = query_attachments(&conn, event_id).unwrap_or_default();
     attachments.extend(event_attachments);
   }
 
@@ -1157,25 +2375,14 @@ fn search_for_rag(
   if events.is_empty() {
     let recent_events: Vec<TimelineEvent> = conn
       .prepare(
-        "SELECT id, type, title, note, text_content, created_at, source, is_deleted
+        "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
          FROM timeline_events
-         WHERE is_deleted = 0
+         WHERE is_deleted = 0 AND scheduled_for IS NULL AND ai_opt_out = 0
          ORDER BY created_at DESC
          LIMIT ?1"
       )
       .map_err(|e| e.to_string())?
-      .query_map([&search_limit.to_string()], |row| {
-        Ok(TimelineEvent {
-          id: row.get(0)?,
-          event_type: row.get(1)?,
-          title: row.get(2)?,
-          note: row.get(3)?,
-          text_content: row.get(4)?,
-          created_at: row.get(5)?,
-          source: row.get(6)?,
-          is_deleted: row.get::<_, i32>(7)? != 0,
-        })
-      })
+      .query_map([&search_limit.to_string()], event_from_row)
       .map_err(|e| e.to_string())?
       .filter_map(|r| r.ok())
       .collect();
@@ -1189,13 +2396,45 @@ fn search_for_rag(
   Ok(RagContext { events, attachments })
 }
 
+fn read_setting(conn: &rusqlite::Connection, key: &str) -> Option<String> {
+  conn
+    .query_row("SELECT value FROM settings WHERE key = ?", [key], |row| row.get(0))
+    .ok()
+}
+
+fn write_setting(conn: &rusqlite::Connection, key: &str, value: &str) -> Result<(), String> {
+  conn.execute(
+    "INSERT INTO settings (key, value) VALUES (?1, ?2)
+     ON CONFLICT(key) DO UPDATE SET value = ?2",
+    (key, value),
+  ).map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Settings only their own commands may touch: the PIN hash and anything
+/// else under `security.`, and the folders approved for reads. The generic
+/// commands below refuse them, so the webview can neither read the hash nor
+/// clear the lock or widen the sandbox behind the user's back.
+fn is_protected_setting(key: &str) -> bool {
+  key.starts_with("security.") || key == sandbox::ALLOWED_ROOTS_KEY
+}
+
+fn ensure_accessible_setting(key: &str) -> Result<(), String> {
+  if is_protected_setting(key) {
+    return Err(format!("Setting {} can't be accessed here", key));
+  }
+  Ok(())
+}
+
 #[tauri::command]
 fn get_setting(
   state: tauri::State<DbState>,
   key: String,
 ) -> Result<Option<String>, String> {
+  app_lock::ensure_unlocked()?;
+  ensure_accessible_setting(&key)?;
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
 
   let result = conn.query_row(
     "SELECT value FROM settings WHERE key = ?",
@@ -1212,19 +2451,19 @@ fn get_setting(
 
 #[tauri::command]
 fn set_setting(
+  app: tauri::AppHandle,
   state: tauri::State<DbState>,
   key: String,
   value: String,
 ) -> Result<(), String> {
-  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
-
-  conn.execute(
-    "INSERT INTO settings (key, value) VALUES (?1, ?2)
-     ON CONFLICT(key) DO UPDATE SET value = ?2",
-    (&key, &value),
-  ).map_err(|e| e.to_string())?;
-
+  app_lock::ensure_unlocked()?;
+  ensure_accessible_setting(&key)?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, &key, &value)?;
+  }
+  config::emit_changed(&app, vec![key]);
   Ok(())
 }
 
@@ -1232,8 +2471,9 @@ fn set_setting(
 fn list_settings(
   state: tauri::State<DbState>,
 ) -> Result<Vec<(String, String)>, String> {
+  app_lock::ensure_unlocked()?;
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
 
   let settings: Vec<(String, String)> = conn
     .prepare("SELECT key, value FROM settings")
@@ -1241,6 +2481,7 @@ fn list_settings(
     .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
     .map_err(|e| e.to_string())?
     .filter_map(|r| r.ok())
+    .filter(|(key, _): &(String, String)| !is_protected_setting(key))
     .collect();
 
   Ok(settings)
@@ -1248,98 +2489,168 @@ fn list_settings(
 
 // ============ Export Commands (Phase 5) ============
 
+fn event_icon(event_type: &str) -> &'static str {
+  match event_type {
+    "image" => "🖼️",
+    "text" => "📝",
+    "thought" => "💭",
+    "audio" => "🎙️",
+    "video" => "🎬",
+    "journal" => "📓",
+    "link" => "🔗",
+    _ => "📄",
+  }
+}
+
+/// First and last millisecond of a "YYYY-MM-DD" day, in the timezone days
+/// are counted in (see timezone.rs).
+fn day_bounds(date_key: &str) -> Result<(i64, i64), String> {
+  timezone::day_bounds(date_key)
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn generate_daily_export(
   app_handle: tauri::AppHandle,
   state: tauri::State<DbState>,
   date_key: String,
   format: String,
   custom_path: Option<String>,
+  saved_search_id: Option<String>,
+  rules: Option<export_rules::ExportRulesOverride>,
+  maintenance: tauri::State<maintenance::MaintenanceState>,
 ) -> Result<String, String> {
+  app_lock::ensure_unlocked()?;
+  let _export = maintenance.begin_export();
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  run_daily_export(&app_handle, &conn, &date_key, &format, custom_path, saved_search_id, rules)
+}
 
-  // Parse date_key to get start and end timestamps (in local timezone)
-  let naive_date = NaiveDate::parse_from_str(&date_key, "%Y-%m-%d")
-    .map_err(|_| "Invalid date format".to_string())?;
+/// Write the export for `date_key` and hand full-day exports to the journal
+/// repository, which commits them in the background. Shared by
+/// `generate_daily_export` and the export job; callers hold the db lock and
+/// an export guard.
+fn run_daily_export(
+  app_handle: &tauri::AppHandle,
+  conn: &rusqlite::Connection,
+  date_key: &str,
+  format: &str,
+  custom_path: Option<String>,
+  saved_search_id: Option<String>,
+  rules: Option<export_rules::ExportRulesOverride>,
+) -> Result<String, String> {
+  let full_day = saved_search_id.is_none();
+  let output_path = write_daily_export(app_handle, conn, date_key, format, custom_path, saved_search_id, rules)?;
+  if full_day && format != "html" {
+    // The export itself succeeded; a failed commit shouldn't undo that
+    if let Err(e) = git_journal::record_export(app_handle, conn, date_key, Path::new(&output_path)) {
+      tracing::warn!(%date_key, error = %e, "Committing export to the journal repository failed");
+    }
+  }
+  Ok(output_path)
+}
 
-  let start_of_day = Local
-    .from_local_datetime(&naive_date.and_hms_opt(0, 0, 0).unwrap())
-    .single()
-    .ok_or_else(|| "Invalid local time".to_string())?
-    .timestamp_millis();
+/// Write the export for `date_key` and return its path. Callers hold the db
+/// lock and an export guard.
+fn write_daily_export(
+  app_handle: &tauri::AppHandle,
+  conn: &rusqlite::Connection,
+  date_key: &str,
+  format: &str,
+  custom_path: Option<String>,
+  saved_search_id: Option<String>,
+  rules: Option<export_rules::ExportRulesOverride>,
+) -> Result<String, String> {
+  let (start_of_day, end_of_day) = day_bounds(date_key)?;
 
-  let end_of_day = Local
-    .from_local_datetime(&naive_date.and_hms_opt(23, 59, 59).unwrap())
-    .single()
-    .ok_or_else(|| "Invalid local time".to_string())?
-    .timestamp_millis() + 999;
+  // Fetch events for the day, minus whatever the export rules leave out
+  let mut events = export_rules::query_day(conn, date_key, &export_rules::resolve(conn, rules))?;
 
-  // Fetch events for the day
-  let events: Vec<TimelineEvent> = conn
-    .prepare(
-      "SELECT id, type, title, note, text_content, created_at, source, is_deleted
-       FROM timeline_events
-       WHERE created_at >= ?1 AND created_at <= ?2 AND is_deleted = 0
-       ORDER BY created_at ASC"
-    )
-    .map_err(|e| e.to_string())?
-    .query_map([start_of_day, end_of_day], |row| {
-      Ok(TimelineEvent {
-        id: row.get(0)?,
-        event_type: row.get(1)?,
-        title: row.get(2)?,
-        note: row.get(3)?,
-        text_content: row.get(4)?,
-        created_at: row.get(5)?,
-        source: row.get(6)?,
-        is_deleted: row.get::<_, i32>(7)? != 0,
-      })
-    })
-    .map_err(|e| e.to_string())?
-    .filter_map(|r| r.ok())
-    .collect();
-
-  // Create exports directory and assets folder early (needed for copying files)
-  let exports_dir = if let Some(ref custom) = custom_path {
-    if !custom.is_empty() {
-      PathBuf::from(custom)
-    } else {
-      app_handle
-        .path()
-        .resolve("exports", tauri::path::BaseDirectory::AppData)
-        .map_err(|e| e.to_string())?
+  // Optionally narrow the day down to a saved search
+  let saved_search = match &saved_search_id {
+    Some(id) => {
+      let filter = saved_searches::load_filter(conn, id)?;
+      let name: String = conn
+        .query_row("SELECT name FROM saved_searches WHERE id = ?", [id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+      Some((filter, name))
     }
-  } else {
-    app_handle
-      .path()
-      .resolve("exports", tauri::path::BaseDirectory::AppData)
-      .map_err(|e| e.to_string())?
+    None => None,
   };
+  if let Some((filter, _)) = &saved_search {
+    let matching: std::collections::HashSet<String> = search::run_filter(conn, filter, None)?
+      .into_iter()
+      .map(|hit| hit.event.id)
+      .collect();
+    events.retain(|event| matching.contains(&event.id));
+  }
+
+  // Create exports directory and assets folder early (needed for copying files)
+  let exports_dir = export_files::exports_dir(app_handle, conn, custom_path.as_deref())?;
   fs::create_dir_all(&exports_dir).map_err(|e| e.to_string())?;
 
   let assets_dir = exports_dir.join(format!("{}_assets", date_key));
   fs::create_dir_all(&assets_dir).map_err(|e| e.to_string())?;
 
   // Generate Markdown content
-  let mut content = format!("# Daily Record - {}\n\n", date_key);
-  content.push_str(&format!("{} records\n\n---\n\n", events.len()));
+  let mut content = match &saved_search {
+    Some((_, name)) => format!(
+      "# {}\n\n",
+      i18n::tf("Daily Record - {date} ({name})", &[("date", date_key.to_string()), ("name", name.clone())])
+    ),
+    None => format!("# {}\n\n", i18n::tf("Daily Record - {date}", &[("date", date_key.to_string())])),
+  };
+  if let Some(weather) = weather::format_day(conn, date_key) {
+    content.push_str(&format!("{}\n\n", weather));
+  }
+  content.push_str(&format!("{}\n\n---\n\n", i18n::tf("{count} records", &[("count", events.len().to_string())])));
+
+  // Where your time went (only present when app tracking is enabled)
+  let app_usage = behavior::query_app_usage(conn, Some(date_key), Some(date_key)).unwrap_or_default();
+  if !app_usage.is_empty() {
+    content.push_str(&format!("## {}\n\n", i18n::t("Where your time went")));
+    for usage in app_usage.iter().take(10) {
+      content.push_str(&format!("- {}: {}\n", usage.app_name, behavior::format_duration(usage.seconds)));
+    }
+    content.push_str("\n---\n\n");
+  }
+
+  let capture_stats = capture_stats::query_capture_stats(conn, Some(start_of_day), Some(end_of_day))?;
+  if capture_stats.events > 0 {
+    content.push_str(&format!("## {}\n\n", i18n::t("Captures")));
+    content.push_str(&capture_stats::format_stats(&capture_stats));
+    content.push_str("\n---\n\n");
+  }
+
+  if let Some(goals) = goals::format_goals(conn, date_key)? {
+    content.push_str(&format!("## {}\n\n", i18n::t("Goals")));
+    content.push_str(&goals);
+    content.push_str("\n---\n\n");
+  }
+
+  // How the day's reminders were handled
+  let reminder_stats = reminder_stats::query_reminder_stats(conn, Some(start_of_day), Some(end_of_day))?;
+  if reminder_stats.total > 0 {
+    content.push_str(&format!("## {}\n\n", i18n::t("Reminders")));
+    content.push_str(&reminder_stats::format_stats(&reminder_stats));
+    content.push_str("\n---\n\n");
+  }
 
   for event in &events {
-    // Format time (in local timezone)
-    let time = DateTime::<Utc>::from_timestamp_millis(event.created_at)
-      .map(|dt| dt.with_timezone(&Local).format("%H:%M").to_string())
+    // Format time, plus the local time where it was captured if that differs
+    let mut time = timezone::datetime(event.created_at)
+      .map(|dt| dt.format("%H:%M").to_string())
       .unwrap_or_else(|| "??:??".to_string());
+    if let Some(offset) = timezone::capture_offset(conn, &event.id, event.created_at) {
+      if let Some(local) = DateTime::<Utc>::from_timestamp_millis(event.created_at) {
+        time.push_str(&format!(" ({} UTC{})", local.with_timezone(&offset).format("%H:%M"), offset));
+      }
+    }
 
-    // Event type icon
-    let icon = match event.event_type.as_str() {
-      "image" => "🖼️",
-      "text" => "📝",
-      "thought" => "💭",
-      _ => "📄",
-    };
-
-    content.push_str(&format!("## {} {} {}\n\n", time, icon, event.title.as_deref().unwrap_or("Untitled")));
+    let icon = event_icon(&event.event_type);
+    let title = event.title.clone().unwrap_or_else(|| i18n::t("Untitled"));
+    content.push_str(&format!("## {} {} {}\n\n", time, icon, title));
 
     if let Some(note) = &event.note {
       if !note.is_empty() {
@@ -1354,31 +2665,7 @@ fn generate_daily_export(
     }
 
     // Get attachments
-    let attachments: Vec<Attachment> = conn
-      .prepare("SELECT id, event_id, kind, original_path, stored_path, file_name, mime_type, size_bytes, sha256, width, height, created_at FROM attachments WHERE event_id = ?")
-      .ok()
-      .map(|mut stmt| {
-        stmt.query_map([&event.id], |row| {
-          Ok(Attachment {
-            id: row.get(0)?,
-            event_id: row.get(1)?,
-            kind: row.get(2)?,
-            original_path: row.get(3)?,
-            stored_path: row.get(4)?,
-            file_name: row.get(5)?,
-            mime_type: row.get(6)?,
-            size_bytes: row.get(7)?,
-            sha256: row.get(8)?,
-            width: row.get(9)?,
-            height: row.get(10)?,
-            created_at: row.get(11)?,
-          })
-        })
-        .ok()
-        .map(|iter| iter.filter_map(|r| r.ok()).collect())
-        .unwrap_or_default()
-      })
-      .unwrap_or_default();
+    let attachments: Vec<Attachment> = query_attachments(conn, &event.id).unwrap_or_default();
 
     if !attachments.is_empty() {
       for att in &attachments {
@@ -1386,7 +2673,7 @@ fn generate_daily_export(
         // Try to get the file path (stored_path or original_path)
         let source_path = att.stored_path.as_ref()
           .or(Some(&att.original_path))
-          .map(|p| PathBuf::from(p));
+          .map(PathBuf::from);
 
         if let Some(src) = source_path {
           if src.exists() {
@@ -1422,7 +2709,8 @@ fn generate_daily_export(
           } else {
             // File not found, just show name
             let icon = if att.kind == "image" { "🖼️" } else { "📎" };
-            content.push_str(&format!("- {} {} (file not found)\n", icon, file_name));
+            let missing = i18n::tf("{name} (file not found)", &[("name", file_name.to_string())]);
+            content.push_str(&format!("- {} {}\n", icon, missing));
           }
         }
       }
@@ -1433,9 +2721,8 @@ fn generate_daily_export(
   }
 
   // Save to file
-  let file_ext = if format == "html" { "html" } else { "md" };
-  let file_name = format!("{}.{}", date_key, file_ext);
-  let output_path = exports_dir.join(&file_name);
+  let search_name = saved_search.as_ref().map(|(_, name)| name.as_str());
+  let output_path = export_files::output_path(app_handle, conn, &exports_dir, date_key, format, search_name)?;
 
   // If HTML, wrap content
   let final_content = if format == "html" {
@@ -1446,16 +2733,16 @@ fn generate_daily_export(
         // Don't convert lines that already contain HTML tags
         if line.contains("<img") || line.contains("<a ") || line.contains("<p>") {
           line.to_string()
-        } else if line.starts_with("# ") {
-          format!("<h1>{}</h1>", &line[2..])
-        } else if line.starts_with("## ") {
-          format!("<h2>{}</h2>", &line[3..])
+        } else if let Some(heading) = line.strip_prefix("# ") {
+          format!("<h1>{}</h1>", heading)
+        } else if let Some(heading) = line.strip_prefix("## ") {
+          format!("<h2>{}</h2>", heading)
         } else if line.starts_with("---") {
           "<hr>".to_string()
         } else if line.starts_with("```") {
           if line == "```" { "</pre>".to_string() } else { "<pre>".to_string() }
-        } else if line.starts_with("- ") {
-          format!("<li>{}</li>", &line[2..])
+        } else if let Some(item) = line.strip_prefix("- ") {
+          format!("<li>{}</li>", item)
         } else if line.starts_with("**") && line.ends_with("**") {
           format!("<strong>{}</strong>", &line[2..line.len()-2])
         } else if line.is_empty() {
@@ -1469,10 +2756,10 @@ fn generate_daily_export(
 
     format!(
       r#"<!DOCTYPE html>
-<html>
+<html lang="{}">
 <head>
   <meta charset="UTF-8">
-  <title>Daily Record - {}</title>
+  <title>{}</title>
   <style>
     body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; line-height: 1.6; }}
     h1 {{ color: #333; border-bottom: 2px solid #ffb347; padding-bottom: 10px; }}
@@ -1487,7 +2774,8 @@ fn generate_daily_export(
 {}
 </body>
 </html>"#,
-      date_key,
+      i18n::lang(),
+      i18n::tf("Daily Record - {date}", &[("date", date_key.to_string())]),
       html_body
     )
   } else {
@@ -1496,10 +2784,16 @@ fn generate_daily_export(
 
   fs::write(&output_path, &final_content).map_err(|e| e.to_string())?;
 
+  let output_path_str = output_path.to_string_lossy().to_string();
+
+  // Filtered exports sit next to the full one; only the latter is recorded
+  if saved_search.is_some() {
+    return Ok(output_path_str);
+  }
+
   // Save export record
   let export_id = generate_id();
   let created_at = now_ms();
-  let output_path_str = output_path.to_string_lossy().to_string();
 
   conn.execute(
     "INSERT INTO daily_exports (id, date_key, output_format, output_path, created_at)
@@ -1515,8 +2809,9 @@ fn generate_daily_export(
 fn list_exports(
   state: tauri::State<DbState>,
 ) -> Result<Vec<DailyExport>, String> {
+  app_lock::ensure_unlocked()?;
   let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
-  let conn = rusqlite::Connection::open(&state.path).map_err(|e| e.to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
 
   let exports: Vec<DailyExport> = conn
     .prepare("SELECT id, date_key, output_format, output_path, created_at FROM daily_exports ORDER BY date_key DESC")
@@ -1528,60 +2823,129 @@ fn list_exports(
         output_format: row.get(2)?,
         output_path: row.get(3)?,
         created_at: row.get(4)?,
+        missing: false,
       })
     })
     .map_err(|e| e.to_string())?
     .filter_map(|r| r.ok())
+    .map(|export: DailyExport| DailyExport { missing: !Path::new(&export.output_path).is_file(), ..export })
     .collect();
 
   Ok(exports)
 }
 
+fn load_export(conn: &rusqlite::Connection, export_id: &str) -> Result<DailyExport, String> {
+  conn
+    .query_row(
+      "SELECT id, date_key, output_format, output_path, created_at FROM daily_exports WHERE id = ?",
+      [export_id],
+      |row| {
+        Ok(DailyExport {
+          id: row.get(0)?,
+          date_key: row.get(1)?,
+          output_format: row.get(2)?,
+          output_path: row.get(3)?,
+          created_at: row.get(4)?,
+          missing: false,
+        })
+      },
+    )
+    .map_err(|_| "Export not found".to_string())
+}
+
+/// Open a recorded export with the default app. A file that has gone missing
+/// is an error unless `regenerate` is set, in which case the day is exported
+/// again into the same folder and the new file opened.
 #[tauri::command]
-fn open_export_folder(
+fn open_export(
   app_handle: tauri::AppHandle,
-  custom_path: Option<String>,
+  state: tauri::State<DbState>,
+  maintenance: tauri::State<maintenance::MaintenanceState>,
+  export_id: String,
+  regenerate: Option<bool>,
 ) -> Result<String, String> {
-  let exports_dir = if let Some(ref custom) = custom_path {
-    if !custom.is_empty() {
-      PathBuf::from(custom)
+  app_lock::ensure_unlocked()?;
+  let path = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let export = load_export(&conn, &export_id)?;
+    let path = PathBuf::from(&export.output_path);
+    if path.is_file() {
+      path
+    } else if regenerate.unwrap_or(false) {
+      let _export = maintenance.begin_export();
+      let folder = path.parent().map(|dir| dir.to_string_lossy().to_string());
+      tracing::info!(date_key = %export.date_key, "Regenerating missing export");
+      PathBuf::from(run_daily_export(&app_handle, &conn, &export.date_key, &export.output_format, folder, None, None)?)
     } else {
-      app_handle
-        .path()
-        .resolve("exports", tauri::path::BaseDirectory::AppData)
-        .map_err(|e| e.to_string())?
+      return Err(format!("Export file is missing: {}", export.output_path));
     }
-  } else {
-    app_handle
-      .path()
-      .resolve("exports", tauri::path::BaseDirectory::AppData)
-      .map_err(|e| e.to_string())?
   };
+  open_in_default_app(&path)?;
+  Ok(path.to_string_lossy().to_string())
+}
 
-  fs::create_dir_all(&exports_dir).map_err(|e| e.to_string())?;
+/// Forget a recorded export and delete its file. Copied attachments are kept,
+/// since other exports of the day link to them too.
+#[tauri::command]
+fn delete_export(state: tauri::State<DbState>, export_id: String) -> Result<(), String> {
+  app_lock::ensure_unlocked()?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let export = load_export(&conn, &export_id)?;
+  match fs::remove_file(&export.output_path) {
+    Ok(()) => {}
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+    Err(e) => return Err(e.to_string()),
+  }
+  conn
+    .execute("DELETE FROM daily_exports WHERE id = ?", [&export_id])
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
 
-  // Open folder in file explorer
+/// Open a file or folder the way double-clicking it would.
+fn open_in_default_app(path: &Path) -> Result<(), String> {
   #[cfg(target_os = "windows")]
   {
     std::process::Command::new("explorer")
-      .arg(&exports_dir)
+      .arg(path)
       .spawn()
       .map_err(|e| e.to_string())?;
   }
   #[cfg(target_os = "macos")]
   {
     std::process::Command::new("open")
-      .arg(&exports_dir)
+      .arg(path)
       .spawn()
       .map_err(|e| e.to_string())?;
   }
   #[cfg(target_os = "linux")]
   {
     std::process::Command::new("xdg-open")
-      .arg(&exports_dir)
+      .arg(path)
       .spawn()
       .map_err(|e| e.to_string())?;
   }
+  Ok(())
+}
+
+#[tauri::command]
+fn open_export_folder(
+  app_handle: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  custom_path: Option<String>,
+) -> Result<String, String> {
+  let exports_dir = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    export_files::exports_dir(&app_handle, &conn, custom_path.as_deref())?
+  };
+
+  fs::create_dir_all(&exports_dir).map_err(|e| e.to_string())?;
+
+  // Open folder in file explorer
+  open_in_default_app(&exports_dir)?;
 
   Ok(exports_dir.to_string_lossy().to_string())
 }
@@ -1590,28 +2954,74 @@ fn main() {
   tauri::Builder::default()
     .plugin(tauri_plugin_dialog::init())
     .setup(|app| {
-      let db_path = app
-        .path()
-        .resolve("papa_pet.sqlite", tauri::path::BaseDirectory::AppData)
-        .map_err(|e| e.to_string())?;
-      init_db(&db_path)?;
+      let mut recovery_report = None;
+      let db_path = if dev_fixtures::enabled() {
+        // Nothing to recover or back up in a database made for this run
+        let db_path = dev_fixtures::prepare()?;
+        init_db(&db_path)?;
+        dev_fixtures::seed(&db_path)?;
+        db_path
+      } else {
+        let db_path = profiles::active_db_path(app.handle())?;
+        // A damaged database is repaired (or replaced) rather than failing setup
+        recovery_report = recovery::check_and_recover(&db_path);
+        match init_db(&db_path) {
+          Ok(()) => recovery::spawn_daily_backup(db_path.clone()),
+          Err(e) => {
+            tracing::error!(error = %e, "Database could not be opened");
+            recovery_report = Some(recovery::RecoveryReport::failed(&e));
+          }
+        }
+        db_path
+      };
 
       let state = DbState {
-        path: db_path,
+        db_path: RwLock::new(db_path),
         lock: Mutex::new(()),
       };
       app.manage(state);
+      app.manage(supervisor::SupervisorState::default());
+      app.manage(reminder_scan::ReminderScanState::default());
+      logging::init(app.handle())?;
+      redaction::reload(app.handle());
+      timezone::reload(app.handle());
+      i18n::reload(app.handle());
+      permissions::reload(app.handle());
+      app_lock::init(app.handle());
+      app.manage(upload::UploadState::default());
+      app.manage(sandbox::DropState::default());
+      app.manage(audio::AudioState::default());
+      app.manage(behavior::BehaviorState::default());
+      app.manage(maintenance::MaintenanceState::default());
+      app.manage(jobs::JobState::default());
+      app.manage(mouse_stream::MouseStreamState::default());
+      app.manage(wellness::WellnessState::default());
+      app.manage(gestures::GestureState::default());
+      app.manage(pet_window::PlacementState::default());
+      app.manage(clipboard::ClipboardState::default());
+      app.manage(lan_capture::LanState::default());
+      app.manage(watch_folders::WatchState::default());
+      app.manage(undo::UndoState::default());
+      app.manage(pet_state::PetStateState::default());
+      if let Some(report) = &recovery_report {
+        recovery::announce(app.handle(), report);
+      }
+      app.manage(recovery::RecoveryState::new(recovery_report));
 
       // Setup system tray
-      let show_item = MenuItemBuilder::new("Show Papa").id("show").build(app)?;
-      let quit_item = MenuItemBuilder::new("Quit").id("quit").build(app)?;
+      let show_item = MenuItemBuilder::new(i18n::t("Show Papa")).id("show").build(app)?;
+      let click_through_item = MenuItemBuilder::new(i18n::t("Toggle Click-through"))
+        .id("click_through")
+        .build(app)?;
+      let quit_item = MenuItemBuilder::new(i18n::t("Quit")).id("quit").build(app)?;
       let menu = MenuBuilder::new(app)
         .item(&show_item)
+        .item(&click_through_item)
         .separator()
         .item(&quit_item)
         .build()?;
 
-      let _tray = TrayIconBuilder::new()
+      let _tray = TrayIconBuilder::with_id(pet_state::TRAY_ID)
         .icon(app.default_window_icon().unwrap().clone())
         .menu(&menu)
         .tooltip("Papa Pet")
@@ -1623,6 +3033,9 @@ fn main() {
                 let _ = window.set_focus();
               }
             }
+            "click_through" => {
+              let _ = pet_window::toggle_click_through(app);
+            }
             "quit" => {
               app.exit(0);
             }
@@ -1640,167 +3053,86 @@ fn main() {
         .build(app)?;
 
       // Start global mouse tracking
-      let app_handle_mouse = app.handle().clone();
-      tauri::async_runtime::spawn(async move {
-        let device_state = DeviceState::new();
-        let mut last_x: Option<i32> = None;
-        let mut last_y: Option<i32> = None;
-        let mut last_button_pressed = false;
-        
-        loop {
-          // Poll mouse position every ~16ms (60fps)
-          tokio::time::sleep(Duration::from_millis(16)).await;
-          
-          let mouse = device_state.get_mouse();
-          let x = mouse.coords.0;
-          let y = mouse.coords.1;
-          let button_pressed = mouse.button_pressed[0]; // Left button
-          
-          // Emit mouse position if changed
-          if last_x != Some(x) || last_y != Some(y) {
-            last_x = Some(x);
-            last_y = Some(y);
-            
-            if let Some(window) = app_handle_mouse.get_webview_window("main") {
-              let _ = window.emit("global-mouse-move", serde_json::json!({
-                "x": x,
-                "y": y,
-                "buttonPressed": button_pressed
-              }));
-            }
-          }
-          
-          // Emit button state change
-          if last_button_pressed != button_pressed {
-            last_button_pressed = button_pressed;
-            if let Some(window) = app_handle_mouse.get_webview_window("main") {
-              let _ = window.emit("global-mouse-button", serde_json::json!({
-                "pressed": button_pressed
-              }));
-            }
-          }
-        }
-      });
+      mouse_stream::spawn_mouse_tracker(app.handle().clone());
 
       // Start behavior analysis monitoring
-      let app_handle_behavior = app.handle().clone();
-      tauri::async_runtime::spawn(async move {
-        let device_state = DeviceState::new();
-        let mut last_keys: Vec<Keycode> = Vec::new();
-        let mut last_mouse_pos: Option<(i32, i32)> = None;
-        let mut last_mouse_click = false;
-        
-        let mut key_press_count = 0u32;
-        let mut backspace_count = 0u32;
-        let mut mouse_click_count = 0u32;
-        let mut mouse_move_distance = 0.0f64;
-        let mut last_analysis_time = Instant::now();
-        let mut last_activity_time = Instant::now();
-        
-        loop {
-          tokio::time::sleep(Duration::from_millis(100)).await; // Check every 100ms
-          
-          let mouse = device_state.get_mouse();
-          let keys = device_state.get_keys();
-          let current_time = Instant::now();
-          
-          // Track keyboard activity
-          if keys.len() > last_keys.len() {
-            key_press_count += 1;
-            // Check for backspace
-            if keys.contains(&Keycode::Backspace) && !last_keys.contains(&Keycode::Backspace) {
-              backspace_count += 1;
-            }
-            last_activity_time = current_time;
-          }
-          last_keys = keys.clone();
-          
-          // Track mouse activity
-          let current_pos = (mouse.coords.0, mouse.coords.1);
-          if let Some(last_pos) = last_mouse_pos {
-            let dx = (current_pos.0 - last_pos.0) as f64;
-            let dy = (current_pos.1 - last_pos.1) as f64;
-            let distance = (dx * dx + dy * dy).sqrt();
-            mouse_move_distance += distance;
-            if distance > 0.0 {
-              last_activity_time = current_time;
-            }
-          }
-          last_mouse_pos = Some(current_pos);
-          
-          if mouse.button_pressed[0] && !last_mouse_click {
-            mouse_click_count += 1;
-            last_activity_time = current_time;
-          }
-          last_mouse_click = mouse.button_pressed[0];
-          
-          // Emit behavior analysis every 2 seconds
-          let elapsed = current_time.duration_since(last_analysis_time);
-          if elapsed.as_secs() >= 2 {
-            let time_window = elapsed.as_secs_f64();
-            let idle_time = current_time.duration_since(last_activity_time).as_secs_f64();
-            
-            let typing_speed = if time_window > 0.0 {
-              key_press_count as f64 / time_window
-            } else {
-              0.0
-            };
-            
-            let mouse_move_speed = if time_window > 0.0 {
-              mouse_move_distance / time_window
-            } else {
-              0.0
-            };
-            
-            // Calculate activity level (0.0 - 1.0)
-            let activity_level = (typing_speed * 0.3 + (mouse_move_speed / 1000.0).min(1.0) * 0.3 + 
-                                 (mouse_click_count as f64 / time_window).min(5.0) / 5.0 * 0.4).min(1.0);
-            
-            let analysis = BehaviorAnalysis {
-              typing_speed,
-              key_press_count,
-              backspace_count,
-              mouse_move_speed,
-              mouse_click_count,
-              idle_time,
-              activity_level,
-            };
-            
-            if let Some(window) = app_handle_behavior.get_webview_window("main") {
-              let _ = window.emit("behavior-analysis", &analysis);
-            }
-            
-            // Reset counters
-            key_press_count = 0;
-            backspace_count = 0;
-            mouse_click_count = 0;
-            mouse_move_distance = 0.0;
-            last_analysis_time = current_time;
+      wellness::load_rules(app.handle());
+      gestures::load_gestures(app.handle());
+      behavior::spawn_behavior_monitor(app.handle().clone());
+
+      // Restore the pet's position for the current displays and keep it saved
+      pet_window::spawn_placement_watcher(app.handle().clone());
+      pet_window::restore_window_modes(app.handle());
+      config::watch_settings(app.handle());
+      streaks::spawn_streak_watcher(app.handle().clone());
+      goals::spawn_goal_checker(app.handle().clone());
+      duplicates::spawn_duplicate_scanner(app.handle().clone());
+      connectivity::spawn_connectivity_probe(app.handle().clone());
+      usage_metrics::spawn_metrics_flusher(app.handle().clone());
+      jobs::spawn_job_worker(app.handle().clone());
+      location::spawn_location_watcher(app.handle().clone());
+      maintenance::spawn_maintenance_scheduler(app.handle().clone());
+      email_digest::spawn_digest_sender(app.handle().clone());
+      lan_capture::restore(app.handle());
+      watch_folders::restore(app.handle());
+      watch_folders::spawn_settler(app.handle().clone());
+      weather::spawn_weather_fetcher(app.handle().clone());
+      calendar::spawn_calendar_watcher(app.handle().clone());
+      github::spawn_github_sync(app.handle().clone());
+      feeds::spawn_feed_poller(app.handle().clone());
+
+      // Start reminder scanner (wakes when the next reminder is due)
+      supervisor::supervise(app.handle().clone(), "reminders", |app_handle_reminder| async move {
+        if let Ok(conn) = app_handle_reminder.state::<DbState>().open() {
+          if let Err(e) = reminder_log::prune(&conn) {
+            tracing::warn!(error = %e, "Pruning the reminder log failed");
           }
         }
-      });
-
-      // Start reminder scanner (every 30 seconds)
-      let app_handle_reminder = app.handle().clone();
-      let db_path_reminder = app
-        .path()
-        .resolve("papa_pet.sqlite", tauri::path::BaseDirectory::AppData)
-        .map_err(|e| e.to_string())?;
-      tauri::async_runtime::spawn(async move {
+        let mut last_scan = 0;
+        // Start of the current quiet time, while reminders are being held
+        let mut quiet_since: Option<i64> = None;
         loop {
-          tokio::time::sleep(Duration::from_secs(30)).await;
+          // Re-read each round; switching profiles swaps the database
+          let db_path_reminder = app_handle_reminder.state::<DbState>().path();
+          reminder_scan::wait_for_next(&app_handle_reminder, &db_path_reminder, last_scan).await;
 
           let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as i64;
+          last_scan = now;
 
           // Check for due reminders
-          if let Ok(conn) = rusqlite::Connection::open(&db_path_reminder) {
+          if let Ok(conn) = open_db(&db_path_reminder) {
+            // Plans whose time has come join the timeline before their
+            // reminders fire
+            let promoted = schedule::promote_due_events(&conn, now).unwrap_or_else(|e| {
+              tracing::error!(error = %e, "Promoting due plans failed");
+              Vec::new()
+            });
+            if let Some(window) = app_handle_reminder.get_webview_window("main") {
+              for plan in &promoted {
+                let _ = window.emit("scheduled-event-due", plan);
+              }
+            }
+
+            // Hold reminders back during quiet hours; they fire together once
+            // the quiet time is over
+            if quiet_hours::quiet_until(&conn, Local::now()).is_some() {
+              if quiet_since.is_none() {
+                tracing::info!("Quiet time started, holding reminders");
+                quiet_since = Some(now);
+              }
+              continue;
+            }
+            let held_since = quiet_since.take();
+            let mut held = Vec::new();
+
             // Find pending reminders that are due
             let due_reminders: Vec<Reminder> = conn
               .prepare(
-                "SELECT id, event_id, remind_at, message, status, triggered_at, snooze_until, created_at
+                "SELECT id, event_id, remind_at, message, status, triggered_at, snooze_until, created_at, attachment_id,
+                        follow_up_after_minutes, follow_up_message
                  FROM reminders
                  WHERE (status = 'pending' AND remind_at <= ?1)
                     OR (status = 'snoozed' AND snooze_until <= ?1)
@@ -1818,6 +3150,9 @@ fn main() {
                     triggered_at: row.get(5)?,
                     snooze_until: row.get(6)?,
                     created_at: row.get(7)?,
+                    attachment_id: row.get(8)?,
+                    follow_up_after_minutes: row.get(9)?,
+                    follow_up_message: row.get(10)?,
                   })
                 })
                 .ok()
@@ -1825,112 +3160,374 @@ fn main() {
                 .unwrap_or_default()
               })
               .unwrap_or_default();
+            if !due_reminders.is_empty() {
+              tracing::debug!(count = due_reminders.len(), "Reminders due");
+            }
 
+            let mut fired = 0;
             for reminder in due_reminders {
               // Get event details
               let event: Option<TimelineEvent> = conn
                 .query_row(
-                  "SELECT id, type, title, note, text_content, created_at, source, is_deleted
+                  "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
                    FROM timeline_events WHERE id = ?",
                   [&reminder.event_id],
-                  |row| {
-                    Ok(TimelineEvent {
-                      id: row.get(0)?,
-                      event_type: row.get(1)?,
-                      title: row.get(2)?,
-                      note: row.get(3)?,
-                      text_content: row.get(4)?,
-                      created_at: row.get(5)?,
-                      source: row.get(6)?,
-                      is_deleted: row.get::<_, i32>(7)? != 0,
-                    })
-                  },
+                  event_from_row,
                 )
                 .ok();
 
               if let Some(event) = event {
-                // Get attachments
-                let attachments: Vec<Attachment> = conn
-                  .prepare("SELECT id, event_id, kind, original_path, stored_path, file_name, mime_type, size_bytes, sha256, width, height, created_at FROM attachments WHERE event_id = ?")
-                  .ok()
-                  .map(|mut stmt| {
-                    stmt.query_map([&reminder.event_id], |row| {
-                      Ok(Attachment {
-                        id: row.get(0)?,
-                        event_id: row.get(1)?,
-                        kind: row.get(2)?,
-                        original_path: row.get(3)?,
-                        stored_path: row.get(4)?,
-                        file_name: row.get(5)?,
-                        mime_type: row.get(6)?,
-                        size_bytes: row.get(7)?,
-                        sha256: row.get(8)?,
-                        width: row.get(9)?,
-                        height: row.get(10)?,
-                        created_at: row.get(11)?,
-                      })
-                    })
-                    .ok()
-                    .map(|iter| iter.filter_map(|r| r.ok()).collect())
-                    .unwrap_or_default()
-                  })
-                  .unwrap_or_default();
+                // Deleting an event cancels its reminders, but the event may
+                // have gone since this scan read them, or been deleted by
+                // something that doesn't. Cancelled like that, they come back
+                // if the event is restored
+                if event.is_deleted {
+                  let _ = cancel_reminders(&conn, &reminder.event_id, "event deleted");
+                  tracing::info!(
+                    reminder_id = %reminder.id,
+                    event_id = %reminder.event_id,
+                    "Cancelled reminder of a deleted event"
+                  );
+                  continue;
+                }
 
-                // Mark as triggered
-                let _ = conn.execute(
-                  "UPDATE reminders SET status = 'triggered', triggered_at = ? WHERE id = ?",
-                  (now, &reminder.id),
+                // Mark as triggered, unless it was snoozed, dismissed or
+                // edited since it was read; the next scan judges it afresh
+                let marked = conn.execute(
+                  "UPDATE reminders SET status = 'triggered', triggered_at = ?1
+                   WHERE id = ?2 AND status = ?3 AND remind_at = ?4 AND snooze_until IS ?5 AND message = ?6",
+                  (now, &reminder.id, &reminder.status, reminder.remind_at, reminder.snooze_until, &reminder.message),
                 );
+                if !matches!(marked, Ok(1)) {
+                  let _ = reminder_log::record(&conn, &reminder.id, Some(&reminder.status), None, "changed while due");
+                  tracing::debug!(reminder_id = %reminder.id, "Reminder changed while due");
+                  reminder_scan::wake(&app_handle_reminder);
+                  continue;
+                }
+                let reason = if reminder.status == "snoozed" { "snooze over" } else { "due" };
+                let _ = reminder_log::record(&conn, &reminder.id, Some(&reminder.status), Some("triggered"), reason);
+
+                // Get attachments
+                let attachments: Vec<Attachment> = query_attachments(&conn, &reminder.event_id).unwrap_or_default();
 
                 // Emit reminder-due event
-                let payload = ReminderDuePayload {
-                  reminder: reminder.clone(),
-                  event,
-                  attachments,
-                };
-
-                if let Some(window) = app_handle_reminder.get_webview_window("main") {
-                  let _ = window.emit("reminder-due", &payload);
+                let payload = ReminderDuePayload::new(&app_handle_reminder, reminder.clone(), event, attachments);
+                fired += 1;
+                if held_since.is_some() {
+                  held.push(payload);
+                  continue;
+                }
+
+                match app_handle_reminder.get_webview_window("main") {
+                  Some(window) => {
+                    if let Err(e) = window.emit("reminder-due", &payload) {
+                      tracing::error!(reminder_id = %reminder.id, error = %e, "Emitting reminder failed");
+                    }
+                  }
+                  None => tracing::warn!(reminder_id = %reminder.id, "Reminder fired without a main window"),
                 }
+                tracing::info!(reminder_id = %reminder.id, event_id = %reminder.event_id, "Reminder fired");
+
+                if read_setting(&conn, tts::SPEAK_REMINDERS_KEY).as_deref() == Some("true") {
+                  let _ = tts::speak_text(&reminder.message);
+                }
+
+                if read_setting(&conn, app_windows::REMINDER_POPUP_KEY).as_deref() == Some("true") {
+                  let _ = app_windows::show_reminder_popup(&app_handle_reminder, &reminder.id);
+                }
+              } else {
+                // Archived, or gone some other way; cancelled so this is said once
+                tracing::warn!(
+                  reminder_id = %reminder.id,
+                  event_id = %reminder.event_id,
+                  "Reminder is due but its event is missing"
+                );
+                let _ = cancel_reminders(&conn, &reminder.event_id, "event missing");
               }
             }
+
+            // One sound per batch, however many reminders fired
+            if fired > 0 {
+              sound::play_for_reminders(&conn);
+              let reason = match fired {
+                1 => "A reminder is due".to_string(),
+                n => format!("{} reminders are due", n),
+              };
+              pet_state::concern(&app_handle_reminder, reason);
+            }
+
+            if let Some(since) = held_since.filter(|_| !held.is_empty()) {
+              tracing::info!(count = held.len(), "Firing reminders held during quiet time");
+              if let Some(window) = app_handle_reminder.get_webview_window("main") {
+                let _ = window.emit("reminders-while-away", &quiet_hours::RemindersWhileAway::new(since, held));
+              }
+            }
+          } else {
+            tracing::error!(path = %db_path_reminder.display(), "Reminder scanner could not open the database");
           }
         }
       });
 
       Ok(())
     })
-    .invoke_handler(tauri::generate_handler![
+    .on_window_event(|window, event| match event {
+      tauri::WindowEvent::Moved(position) if window.label() == "main" => {
+        pet_window::track_window_move(window.app_handle(), position.x, position.y);
+      }
+      tauri::WindowEvent::Destroyed => {
+        app_windows::on_window_destroyed(window.app_handle(), window.label());
+      }
+      tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) => {
+        sandbox::record_drop(window.app_handle(), paths);
+      }
+      _ => {}
+    })
+    // Every call is counted and timed locally, see usage_metrics.rs
+    .invoke_handler(usage_metrics::measure(tauri::generate_handler![
       save_mock_result,
       hide_for,
       set_window_size,
       process_drop_paths_command,
       call_llm_api,
-      read_file_content,
+      file_read::read_file_content,
+      file_read::read_file_bytes,
+      config::get_config,
+      config::update_config,
+      ingest::get_ingestion_policy,
+      journal::get_daily_prompt,
+      journal::save_journal_entry,
+      llm_models::list_llm_models,
+      llm_structured::call_llm_structured,
+      logging::get_recent_logs,
+      logging::get_log_level,
+      logging::set_log_level,
+      supervisor::get_background_task_status,
+      reminder_scan::trigger_reminder_scan,
+      reminder_log::get_reminder_log,
+      redaction::get_redaction_config,
+      redaction::set_redaction_config,
+      redaction::preview_redaction,
+      privacy::set_event_ai_opt_out,
+      privacy::get_clipboard_ai_opt_out,
+      privacy::set_clipboard_ai_opt_out,
+      quiet_hours::get_quiet_hours,
+      quiet_hours::set_quiet_hours,
+      quiet_hours::get_quiet_status,
+      quiet_hours::start_focus_session,
+      quiet_hours::end_focus_session,
+      streaks::get_streaks,
+      streaks::set_streak_goals,
+      schedule::create_scheduled_event,
+      schedule::list_upcoming_events,
+      search::search_events,
+      search::get_search_snippet,
+      saved_searches::create_saved_search,
+      saved_searches::list_saved_searches,
+      saved_searches::delete_saved_search,
+      saved_searches::run_saved_search,
+      summarize::summarize_text,
+      synthesis::summarize_events,
+      similar::find_similar_events,
+      duplicates::list_duplicate_candidates,
+      duplicates::dismiss_duplicate_candidate,
+      duplicates::merge_events,
+      cas::migrate_attachment_storage,
+      compression::get_text_compression,
+      compression::set_text_compression,
+      compression::compress_large_texts,
+      jobs::list_jobs,
+      jobs::cancel_job,
+      jobs::retry_job,
+      jobs::queue_daily_export,
+      jobs::queue_notion_export,
+      mouse_stream::set_mouse_stream_rate,
+      drop_reactions::list_drop_reactions,
+      drop_reactions::save_drop_reaction,
+      drop_reactions::delete_drop_reaction,
+      receipts::extract_receipt,
+      receipts::get_expense_summary,
+      mentions::list_events_mentioning,
+      mentions::list_known_mentions,
+      summarize::extract_action_items,
+      tags::suggest_tags,
+      tags::add_event_tags,
+      tags::remove_event_tag,
+      tags::get_event_tags,
+      tags::list_tags,
+      tags::set_auto_tagging,
+      tags::backfill_hashtags,
+      location::get_location_settings,
+      location::set_location_settings,
+      location::refresh_location,
+      location::list_events_near,
+      weather::get_weather_settings,
+      weather::set_weather_settings,
+      weather::fetch_weather,
+      calendar::subscribe_calendar,
+      calendar::unsubscribe_calendar,
+      calendar::list_calendars,
+      calendar::refresh_calendars,
+      calendar::list_meetings,
+      calendar::capture_meeting_notes,
+      day_plan::generate_day_plan,
+      github::get_github_settings,
+      github::set_github_settings,
+      github::sync_github,
+      feeds::subscribe_feed,
+      feeds::set_feed_keywords,
+      feeds::unsubscribe_feed,
+      feeds::list_feeds,
+      feeds::refresh_feeds,
+      timezone::get_timezone,
+      timezone::set_timezone,
+      i18n::get_locale,
+      i18n::set_locale,
+      permissions::get_permissions,
+      permissions::set_permissions,
+      connectivity::get_online_status,
+      usage_metrics::get_usage_metrics,
+      archive::archive_events_before,
+      archive::get_archive_stats,
+      maintenance::run_maintenance,
+      ingest::set_ingestion_policy,
       // Timeline event commands
       save_dropped_file,
+      upload::begin_upload,
+      upload::append_chunk,
+      upload::finish_upload,
+      upload::abort_upload,
+      upload::copy_dropped_file,
       create_drop_event,
       create_text_event,
+      create_image_event_from_bytes,
       list_events,
       get_event_detail,
       delete_event,
       update_event_note,
+      set_event_metadata,
+      add_attachment_to_event,
+      remove_attachment,
+      photo_meta::get_attachment_metadata,
+      thumbnails::get_attachment_thumbnail,
+      sandbox::list_allowed_roots,
+      sandbox::add_allowed_root,
+      sandbox::remove_allowed_root,
+      // Voice note commands
+      audio::start_audio_recording,
+      audio::stop_audio_recording,
+      audio::cancel_audio_recording,
       // Reminder commands
       create_reminder,
       snooze_reminder,
       dismiss_reminder,
       list_pending_reminders,
+      get_reminder_payload,
+      reminder_stats::list_reminder_history,
+      reminder_stats::get_reminder_stats,
+      capture_stats::get_capture_stats,
+      year_review::generate_year_review,
+      relink::verify_attachments,
+      relink::relink_attachment,
+      relink::list_attachment_relocations,
+      relink::list_relink_folders,
+      relink::add_relink_folder,
+      relink::remove_relink_folder,
+      watch_folders::list_watch_folders,
+      watch_folders::add_watch_folder,
+      watch_folders::remove_watch_folder,
+      screenshots::get_screenshot_import,
+      screenshots::set_screenshot_import,
+      downloads::get_download_triage,
+      downloads::set_download_triage,
+      downloads::list_pending_triage,
+      downloads::resolve_triage,
+      downloads::remove_ignored_download_pattern,
+      weekly_review::start_weekly_review,
+      weekly_review::mark_reviewed,
+      action_reminders::propose_action_reminders,
+      action_reminders::commit_action_reminders,
+      ask::ask_about_event,
+      snooze::list_snooze_options,
+      snooze::get_snooze_presets,
+      snooze::set_snooze_presets,
+      tts::speak,
       // Settings commands
       get_setting,
       set_setting,
       list_settings,
+      // Behavior commands
+      behavior::get_app_usage,
+      behavior::list_away_intervals,
+      behavior::reload_behavior_config,
+      wellness::get_wellness_rules,
+      wellness::set_wellness_rules,
+      gestures::get_gestures,
+      gestures::set_gestures,
+      pet_window::get_monitor_layout,
+      pet_window::move_to_monitor,
+      pet_window::set_click_through,
+      pet_window::set_always_on_top,
+      pet_window::set_visible_on_all_workspaces,
+      pet_window::get_window_modes,
+      app_windows::open_timeline_window,
+      app_windows::open_settings_window,
+      app_windows::open_reminder_popup,
+      app_windows::close_app_window,
       // Export commands
       generate_daily_export,
       list_exports,
       open_export_folder,
+      open_export,
+      delete_export,
+      export_files::get_export_file_settings,
+      export_files::set_export_file_settings,
+      export_rules::get_export_rules,
+      export_rules::set_export_rules,
+      notion::export_to_notion,
+      secrets::set_secret,
+      secrets::delete_secret,
+      secrets::has_secret,
+      email_digest::configure_email_digest,
+      email_digest::get_email_digest_config,
+      email_digest::send_test_email,
+      git_journal::get_git_journal,
+      git_journal::set_git_journal,
+      bookmarks::import_browser_bookmarks,
+      drag_out::prepare_drag_out,
+      drag_out::save_attachment_as,
+      clipboard::copy_event_to_clipboard,
+      qr::generate_qr_for_event,
+      lan_capture::get_lan_capture_status,
+      lan_capture::set_lan_capture,
+      lan_capture::pair_lan_device,
+      lan_capture::list_lan_devices,
+      lan_capture::revoke_lan_device,
+      profiles::list_profiles,
+      profiles::create_profile,
+      profiles::switch_profile,
+      event_lock::lock_event,
+      event_lock::unlock_event,
+      app_lock::get_app_lock_status,
+      app_lock::set_app_lock,
+      app_lock::remove_app_lock,
+      app_lock::lock_app,
+      app_lock::unlock_app,
+      undo::undo_last,
+      undo::redo_last,
+      undo::get_undo_history,
+      recovery::get_startup_recovery,
+      sound::play_notification_sound,
+      sound::list_notification_sounds,
+      sound::get_reminder_sound,
+      sound::set_reminder_sound,
+      pet_state::get_pet_state,
+      affinity::interact_with_pet,
+      affinity::get_pet_status,
+      goals::set_daily_goal,
+      goals::get_daily_goals,
       // RAG commands
       search_for_rag
-    ])
+    ]))
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
 }
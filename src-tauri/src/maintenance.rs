@@ -0,0 +1,163 @@
+// Database upkeep: integrity check, ANALYZE and vacuuming.
+//
+// Runs on demand through `run_maintenance`, and at most once a day in the
+// background while the user is away, so the pause never lands on someone
+// who's using the pet. Nothing runs while an export is writing. Each run
+// reports through `maintenance-finished`.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::{behavior, now_ms, read_setting, supervisor, write_setting, DbState};
+
+const LAST_RUN_KEY: &str = "maintenance.last_run";
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const MIN_RUN_GAP_MS: i64 = 24 * 60 * 60 * 1000;
+// integrity_check stops after this many problems
+const MAX_INTEGRITY_ERRORS: u32 = 20;
+
+#[derive(Default)]
+pub struct MaintenanceState {
+  exports_running: AtomicUsize,
+}
+
+/// Held for the duration of an export; maintenance waits until it's dropped.
+pub struct ExportGuard<'a>(&'a AtomicUsize);
+
+impl Drop for ExportGuard<'_> {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+impl MaintenanceState {
+  pub fn begin_export(&self) -> ExportGuard<'_> {
+    self.exports_running.fetch_add(1, Ordering::SeqCst);
+    ExportGuard(&self.exports_running)
+  }
+
+  fn exporting(&self) -> bool {
+    self.exports_running.load(Ordering::SeqCst) > 0
+  }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceReport {
+  /// "manual" or "idle"
+  trigger: String,
+  integrity_ok: bool,
+  /// Problems reported by integrity_check, empty when it passed
+  integrity_errors: Vec<String>,
+  /// "incremental", or "full" the first time auto_vacuum is switched on
+  vacuum: String,
+  /// Pages given back to the file system
+  freed_pages: i64,
+  page_count_before: i64,
+  page_count_after: i64,
+  started_at: i64,
+  duration_ms: i64,
+}
+
+fn pragma_i64(conn: &rusqlite::Connection, pragma: &str) -> Result<i64, String> {
+  conn
+    .query_row(&format!("PRAGMA {}", pragma), [], |row| row.get(0))
+    .map_err(|e| e.to_string())
+}
+
+fn run(conn: &rusqlite::Connection, trigger: &str) -> Result<MaintenanceReport, String> {
+  let started_at = now_ms();
+  let page_count_before = pragma_i64(conn, "page_count")?;
+
+  let integrity: Vec<String> = conn
+    .prepare(&format!("PRAGMA integrity_check({})", MAX_INTEGRITY_ERRORS))
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| row.get(0))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  let integrity_ok = integrity.len() == 1 && integrity[0] == "ok";
+  let integrity_errors = if integrity_ok { Vec::new() } else { integrity };
+
+  conn.execute_batch("ANALYZE").map_err(|e| e.to_string())?;
+
+  // Incremental vacuum only works once auto_vacuum is set, which takes
+  // one full VACUUM on an existing database
+  let vacuum = if pragma_i64(conn, "auto_vacuum")? == 2 {
+    conn.execute_batch("PRAGMA incremental_vacuum").map_err(|e| e.to_string())?;
+    "incremental"
+  } else {
+    conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM")
+      .map_err(|e| e.to_string())?;
+    "full"
+  };
+
+  let page_count_after = pragma_i64(conn, "page_count")?;
+  let freed_pages = page_count_before - page_count_after;
+
+  let report = MaintenanceReport {
+    trigger: trigger.to_string(),
+    integrity_ok,
+    integrity_errors,
+    vacuum: vacuum.to_string(),
+    freed_pages,
+    page_count_before,
+    page_count_after,
+    started_at,
+    duration_ms: now_ms() - started_at,
+  };
+  write_setting(conn, LAST_RUN_KEY, &started_at.to_string())?;
+  Ok(report)
+}
+
+fn run_locked(app: &tauri::AppHandle, trigger: &str) -> Result<MaintenanceReport, String> {
+  if app.state::<MaintenanceState>().exporting() {
+    return Err("An export is in progress, try again when it's done".to_string());
+  }
+
+  let state = app.state::<DbState>();
+  let report = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    run(&conn, trigger)?
+  };
+
+  if let Some(window) = app.get_webview_window("main") {
+    let _ = window.emit("maintenance-finished", &report);
+  }
+  Ok(report)
+}
+
+fn due(app: &tauri::AppHandle) -> bool {
+  let state = app.state::<DbState>();
+  let Ok(_guard) = state.lock.lock() else { return false };
+  let last_run = state.open()
+    .ok()
+    .and_then(|conn| read_setting(&conn, LAST_RUN_KEY))
+    .and_then(|v| v.parse::<i64>().ok())
+    .unwrap_or(0);
+  now_ms() - last_run >= MIN_RUN_GAP_MS
+}
+
+pub fn spawn_maintenance_scheduler(app: tauri::AppHandle) {
+  supervisor::supervise(app, "maintenance", |app| async move {
+    loop {
+      tokio::time::sleep(CHECK_INTERVAL).await;
+
+      if !behavior::is_away(&app) || app.state::<MaintenanceState>().exporting() || !due(&app) {
+        continue;
+      }
+      if let Err(e) = run_locked(&app, "idle") {
+        tracing::error!(error = %e, "Scheduled maintenance failed");
+      }
+    }
+  });
+}
+
+#[tauri::command]
+pub fn run_maintenance(app: tauri::AppHandle) -> Result<MaintenanceReport, String> {
+  run_locked(&app, "manual")
+}
@@ -0,0 +1,81 @@
+// Audio/video probing: duration during ingestion and poster frames for
+// video thumbnails.
+//
+// WAV is read directly; everything else goes through ffprobe/ffmpeg when they
+// are on PATH. Missing tools just mean no duration or poster.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Command for an external helper tool that never flashes a console window.
+pub fn tool_command(program: &str) -> Command {
+  #[allow(unused_mut)]
+  let mut cmd = Command::new(program);
+  #[cfg(target_os = "windows")]
+  {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    cmd.creation_flags(CREATE_NO_WINDOW);
+  }
+  cmd.stdin(Stdio::null()).stderr(Stdio::null());
+  cmd
+}
+
+/// Attachment kind for a mime type: 'image' | 'video' | 'audio' | 'file'.
+pub fn attachment_kind(mime: &Option<String>) -> &'static str {
+  match mime.as_deref().and_then(|m| m.split('/').next()) {
+    Some("image") => "image",
+    Some("video") => "video",
+    Some("audio") => "audio",
+    _ => "file",
+  }
+}
+
+fn wav_duration_ms(path: &Path) -> Option<i64> {
+  let reader = hound::WavReader::open(path).ok()?;
+  let spec = reader.spec();
+  Some(reader.duration() as i64 * 1000 / spec.sample_rate.max(1) as i64)
+}
+
+fn ffprobe_duration_ms(path: &Path) -> Option<i64> {
+  let output = tool_command("ffprobe")
+    .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1"])
+    .arg(path)
+    .output()
+    .ok()?;
+  if !output.status.success() {
+    return None;
+  }
+  let seconds: f64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+  Some((seconds * 1000.0).round() as i64)
+}
+
+/// Duration of an audio or video file in milliseconds.
+pub fn probe_duration_ms(path: &Path, mime: &Option<String>) -> Option<i64> {
+  match mime.as_deref() {
+    Some("audio/wav") => wav_duration_ms(path).or_else(|| ffprobe_duration_ms(path)),
+    Some(m) if m.starts_with("audio/") || m.starts_with("video/") => ffprobe_duration_ms(path),
+    _ => None,
+  }
+}
+
+/// Grab one frame (one second in, or the first frame for very short clips)
+/// scaled to fit `size`, written as PNG to `dest`.
+pub fn extract_poster_frame(source: &Path, dest: &Path, size: u32) -> Result<(), String> {
+  let scale = format!("scale={}:{}:force_original_aspect_ratio=decrease", size, size);
+  for seek in ["1", "0"] {
+    let ok = tool_command("ffmpeg")
+      .args(["-y", "-loglevel", "error", "-ss", seek, "-i"])
+      .arg(source)
+      .args(["-vf", &scale, "-frames:v", "1"])
+      .arg(dest)
+      .stdout(Stdio::null())
+      .status()
+      .map(|s| s.success())
+      .unwrap_or(false);
+    if ok && dest.is_file() {
+      return Ok(());
+    }
+  }
+  Err("ffmpeg could not extract a poster frame".to_string())
+}
@@ -0,0 +1,199 @@
+// @mentions in notes.
+//
+// Writing "@alex" in a note is enough to find the event again by person: the
+// note is parsed whenever it's created or edited and each mention is kept in
+// `mentions`, keyed by its lowercased name, with the spelling last used for
+// display. Events written before mentions were indexed are picked up the
+// first time either command runs.
+//
+// An @ directly after a letter or digit is part of an email address, not a
+// mention, and trailing dots and dashes are sentence punctuation.
+
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::{
+  app_lock, event_from_row, query_attachments, query_reminders, read_setting, write_setting, DbState,
+  TimelineEvent, TimelineEventWithAttachments,
+};
+
+// Set once every existing note has been indexed
+const INDEXED_KEY: &str = "mentions.indexed";
+const DEFAULT_LIMIT: u32 = 50;
+const DEFAULT_SUGGESTIONS: u32 = 10;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownMention {
+  name: String,
+  display_name: String,
+  event_count: i64,
+  last_mentioned_at: i64,
+}
+
+fn mention_regex() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| Regex::new(r"(?:^|[^\p{L}\p{N}_@])@([\p{L}\p{N}_][\p{L}\p{N}_.\-]*)").unwrap())
+}
+
+/// Mentions in `text` as (lowercased name, spelling), first spelling wins.
+pub fn parse(text: &str) -> Vec<(String, String)> {
+  let mut found: BTreeMap<String, String> = BTreeMap::new();
+  for caps in mention_regex().captures_iter(text) {
+    let display = caps[1].trim_end_matches(['.', '-']);
+    if !display.is_empty() {
+      found.entry(display.to_lowercase()).or_insert_with(|| display.to_string());
+    }
+  }
+  found.into_iter().collect()
+}
+
+/// Replace the mentions recorded for `event_id` with those in its current
+/// note. Call after writing a note; a locked or missing event ends up with
+/// none.
+pub fn index(conn: &rusqlite::Connection, event_id: &str) -> Result<(), String> {
+  let row: Option<(Option<String>, i64)> = conn
+    .query_row(
+      "SELECT note, created_at FROM timeline_events WHERE id = ? AND locked = 0",
+      [event_id],
+      |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .ok();
+  conn.execute("DELETE FROM mentions WHERE event_id = ?", [event_id]).map_err(|e| e.to_string())?;
+  let Some((Some(note), created_at)) = row else { return Ok(()) };
+  for (name, display_name) in parse(&note) {
+    conn
+      .execute(
+        "INSERT INTO mentions (event_id, name, display_name, created_at) VALUES (?1, ?2, ?3, ?4)",
+        (event_id, &name, &display_name, created_at),
+      )
+      .map_err(|e| e.to_string())?;
+  }
+  Ok(())
+}
+
+fn ensure_indexed(conn: &rusqlite::Connection) -> Result<(), String> {
+  if read_setting(conn, INDEXED_KEY).is_some() {
+    return Ok(());
+  }
+  let ids: Vec<String> = conn
+    .prepare("SELECT id FROM timeline_events WHERE note LIKE '%@%' AND locked = 0")
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| row.get(0))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  for id in &ids {
+    index(conn, id)?;
+  }
+  tracing::info!(events = ids.len(), "Indexed mentions in existing notes");
+  write_setting(conn, INDEXED_KEY, "1")
+}
+
+/// Live events whose note mentions `name` (with or without the @), newest
+/// first.
+#[tauri::command]
+pub fn list_events_mentioning(
+  state: tauri::State<DbState>,
+  name: String,
+  limit: Option<u32>,
+) -> Result<Vec<TimelineEventWithAttachments>, String> {
+  app_lock::ensure_unlocked()?;
+  let name = name.trim().trim_start_matches('@').to_lowercase();
+  if name.is_empty() {
+    return Err("Name must not be empty".to_string());
+  }
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  ensure_indexed(&conn)?;
+  let events: Vec<TimelineEvent> = conn
+    .prepare(
+      "SELECT e.id, e.type, e.title, e.note, e.text_content, e.created_at, e.source, e.is_deleted, e.metadata,
+              e.ai_opt_out, e.locked, e.text_compressed, e.text_codec
+       FROM mentions m JOIN timeline_events e ON e.id = m.event_id
+       WHERE m.name = ?1 AND e.is_deleted = 0 AND e.scheduled_for IS NULL
+       ORDER BY e.created_at DESC
+       LIMIT ?2",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map((&name, limit.unwrap_or(DEFAULT_LIMIT)), event_from_row)
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let mut results = Vec::new();
+  for event in events {
+    let attachments = query_attachments(&conn, &event.id)?;
+    let reminders = query_reminders(&conn, &event.id)?;
+    results.push(TimelineEventWithAttachments { event, attachments, reminders });
+  }
+  Ok(results)
+}
+
+/// People mentioned so far, for autocomplete after "@". With `prefix`, only
+/// names starting with it. Most mentioned first.
+#[tauri::command]
+pub fn list_known_mentions(
+  state: tauri::State<DbState>,
+  prefix: Option<String>,
+  limit: Option<u32>,
+) -> Result<Vec<KnownMention>, String> {
+  let prefix = prefix.unwrap_or_default().trim().trim_start_matches('@').to_lowercase();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  ensure_indexed(&conn)?;
+  let mentions = conn
+    .prepare(
+      "SELECT m.name,
+              (SELECT display_name FROM mentions WHERE name = m.name ORDER BY created_at DESC LIMIT 1),
+              COUNT(*), MAX(m.created_at)
+       FROM mentions m JOIN timeline_events e ON e.id = m.event_id
+       WHERE e.is_deleted = 0 AND substr(m.name, 1, length(?1)) = ?1
+       GROUP BY m.name
+       ORDER BY COUNT(*) DESC, MAX(m.created_at) DESC
+       LIMIT ?2",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map((&prefix, limit.unwrap_or(DEFAULT_SUGGESTIONS)), |row| {
+      Ok(KnownMention {
+        name: row.get(0)?,
+        display_name: row.get(1)?,
+        event_count: row.get(2)?,
+        last_mentioned_at: row.get(3)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(mentions)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn pair(name: &str, display: &str) -> (String, String) {
+    (name.to_string(), display.to_string())
+  }
+
+  #[test]
+  fn finds_mentions_without_sentence_punctuation() {
+    assert_eq!(
+      parse("Lunch with @Alex and @sam.k. (@jo-)"),
+      vec![pair("alex", "Alex"), pair("jo", "jo"), pair("sam.k", "sam.k")]
+    );
+  }
+
+  #[test]
+  fn keeps_the_first_spelling() {
+    assert_eq!(parse("@alex, then @ALEX again"), vec![pair("alex", "alex")]);
+  }
+
+  #[test]
+  fn skips_email_addresses() {
+    assert!(parse("Mail bob@example.com or @@nobody, @-").is_empty());
+  }
+}
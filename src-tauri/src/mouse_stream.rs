@@ -0,0 +1,142 @@
+// Global mouse tracking for the pet's eyes and drag handling.
+//
+// The cursor is polled at 60 Hz, but sending every change across IPC kept
+// the webview busy re-rendering for each pixel. Moves are coalesced instead:
+// at most one `global-mouse-move` per interval, carrying the latest position
+// and the velocity since the previous one, so the frontend can extrapolate
+// between updates. The rate comes from `mouse.stream_hz` and is set with
+// `set_mouse_stream_rate`. Button changes aren't throttled; a pending move
+// is flushed just before one so a press always lands on the right spot.
+
+use device_query::{DeviceQuery, DeviceState};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+use crate::{config, read_setting, supervisor, write_setting, DbState};
+
+pub const RATE_KEY: &str = "mouse.stream_hz";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(16);
+const DEFAULT_HZ: u32 = 30;
+// Polling at 60 Hz makes anything faster pointless
+const MAX_HZ: u32 = 60;
+
+pub struct MouseStreamState {
+  hz: AtomicU32,
+}
+
+impl Default for MouseStreamState {
+  fn default() -> Self {
+    Self { hz: AtomicU32::new(DEFAULT_HZ) }
+  }
+}
+
+impl MouseStreamState {
+  fn interval(&self) -> Duration {
+    Duration::from_secs(1) / self.hz.load(Ordering::Relaxed).max(1)
+  }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct MouseMove {
+  x: i32,
+  y: i32,
+  button_pressed: bool,
+  /// Pixels per second since the previous move event
+  vx: f64,
+  vy: f64,
+}
+
+fn load_rate(app: &tauri::AppHandle) {
+  let stored = {
+    let state = app.state::<DbState>();
+    let Ok(_guard) = state.lock.lock() else { return };
+    state.open().ok().and_then(|conn| read_setting(&conn, RATE_KEY))
+  };
+  if let Some(hz) = stored.and_then(|value| value.parse::<u32>().ok()) {
+    app.state::<MouseStreamState>().hz.store(hz.clamp(1, MAX_HZ), Ordering::Relaxed);
+  }
+}
+
+pub fn spawn_mouse_tracker(app: tauri::AppHandle) {
+  load_rate(&app);
+  supervisor::supervise(app, "mouse", |app| async move {
+    let device_state = DeviceState::new();
+    // Last position sent, and when
+    let mut sent: Option<((i32, i32), Instant)> = None;
+    let mut pending: Option<(i32, i32)> = None;
+    let mut last_position: Option<(i32, i32)> = None;
+    let mut last_button_pressed = false;
+
+    let emit_move = |position: (i32, i32), button_pressed: bool, sent: &mut Option<((i32, i32), Instant)>| {
+      let now = Instant::now();
+      let (vx, vy) = match sent {
+        Some((previous, at)) => {
+          let secs = now.duration_since(*at).as_secs_f64().max(0.001);
+          ((position.0 - previous.0) as f64 / secs, (position.1 - previous.1) as f64 / secs)
+        }
+        None => (0.0, 0.0),
+      };
+      *sent = Some((position, now));
+      if let Some(window) = app.get_webview_window("main") {
+        let _ = window.emit("global-mouse-move", MouseMove { x: position.0, y: position.1, button_pressed, vx, vy });
+      }
+    };
+
+    loop {
+      tokio::time::sleep(POLL_INTERVAL).await;
+
+      let mouse = device_state.get_mouse();
+      let position = mouse.coords;
+      let button_pressed = mouse.button_pressed[0]; // Left button
+
+      if last_position != Some(position) {
+        last_position = Some(position);
+        pending = Some(position);
+      }
+
+      if last_button_pressed != button_pressed {
+        last_button_pressed = button_pressed;
+        if let Some(position) = pending.take() {
+          emit_move(position, button_pressed, &mut sent);
+        }
+        if let Some(window) = app.get_webview_window("main") {
+          let _ = window.emit("global-mouse-button", serde_json::json!({ "pressed": button_pressed }));
+        }
+        continue;
+      }
+
+      let interval = app.state::<MouseStreamState>().interval();
+      let due = sent.is_none_or(|(_, at)| at.elapsed() >= interval);
+      if let (true, Some(position)) = (due, pending) {
+        pending = None;
+        emit_move(position, button_pressed, &mut sent);
+      }
+    }
+  });
+}
+
+/// How many `global-mouse-move` events to send per second at most, 1-60.
+#[tauri::command]
+pub fn set_mouse_stream_rate(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  stream: tauri::State<MouseStreamState>,
+  hz: u32,
+) -> Result<u32, String> {
+  if hz == 0 {
+    return Err("Rate must be at least 1 Hz".to_string());
+  }
+  let hz = hz.min(MAX_HZ);
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, RATE_KEY, &hz.to_string())?;
+  }
+  stream.hz.store(hz, Ordering::Relaxed);
+  config::emit_changed(&app, vec![RATE_KEY.to_string()]);
+  Ok(hz)
+}
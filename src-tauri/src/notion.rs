@@ -0,0 +1,413 @@
+// Export days to a Notion database.
+//
+// Each day with records becomes one page in the database: a heading per
+// event, its note and text, and its image attachments uploaded through
+// Notion's file upload API. The page id is remembered in `notion_pages`, so
+// exporting the same day again replaces that page's content instead of adding
+// a second page. Export rules apply as they do to file exports.
+//
+// The integration token is read from the credential store (`notion.token`,
+// see secrets.rs) and never passes through the UI.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::permissions::{self, Permission};
+use crate::{
+  app_lock, connectivity, event_icon, export_rules, generate_id, i18n, now_ms, query_attachments, secrets, timezone,
+  usage_metrics, Attachment, DbState, TimelineEvent,
+};
+
+pub const TOKEN_SECRET: &str = "notion.token";
+
+const API_URL: &str = "https://api.notion.com/v1";
+const API_VERSION: &str = "2022-06-28";
+// Notion caps children per request and characters per rich text object
+const MAX_BLOCKS_PER_REQUEST: usize = 100;
+const MAX_TEXT_CHARS: usize = 2000;
+// Single-part uploads are limited to 20MB
+const MAX_UPLOAD_BYTES: u64 = 20 * 1024 * 1024;
+const MAX_DAYS: i64 = 366;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DateRange {
+  /// "YYYY-MM-DD", inclusive
+  start: String,
+  /// "YYYY-MM-DD", inclusive
+  end: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NotionPage {
+  date_key: String,
+  page_id: String,
+  url: Option<String>,
+  /// false when an existing page was updated
+  created: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NotionExportResult {
+  pages: Vec<NotionPage>,
+  /// Days in the range with nothing to export
+  skipped_days: Vec<String>,
+}
+
+struct Day {
+  date_key: String,
+  events: Vec<(TimelineEvent, Vec<Attachment>)>,
+  page_id: Option<String>,
+}
+
+struct Client {
+  http: reqwest::Client,
+  token: String,
+}
+
+impl Client {
+  fn builder(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+    self
+      .http
+      .request(method, format!("{}{}", API_URL, path))
+      .header("Authorization", format!("Bearer {}", self.token))
+      .header("Notion-Version", API_VERSION)
+  }
+
+  /// Status and body, whatever the status.
+  async fn send_raw(&self, request: reqwest::RequestBuilder) -> Result<(u16, Value), String> {
+    let response = request.send().await.map_err(|e| connectivity::request_error("Request failed", e))?;
+    let status = response.status().as_u16();
+    let body = response.json().await.unwrap_or(Value::Null);
+    Ok((status, body))
+  }
+
+  async fn send(&self, request: reqwest::RequestBuilder) -> Result<Value, String> {
+    let (status, body) = self.send_raw(request).await?;
+    if !(200..300).contains(&status) {
+      let message = body["message"].as_str().unwrap_or("Unknown error");
+      return Err(format!("Notion API error ({}): {}", status, message));
+    }
+    Ok(body)
+  }
+
+  async fn call(&self, method: reqwest::Method, path: &str, body: Option<Value>) -> Result<Value, String> {
+    let mut request = self.builder(method, path);
+    if let Some(body) = body {
+      request = request.json(&body);
+    }
+    self.send(request).await
+  }
+
+  /// The page, or `None` if it was deleted or moved to the trash.
+  async fn live_page(&self, page_id: &str) -> Result<Option<Value>, String> {
+    let (status, body) = self
+      .send_raw(self.builder(reqwest::Method::GET, &format!("/pages/{}", page_id)))
+      .await?;
+    match status {
+      404 => Ok(None),
+      200..=299 if body["archived"] == true || body["in_trash"] == true => Ok(None),
+      200..=299 => Ok(Some(body)),
+      _ => Err(format!(
+        "Notion API error ({}): {}",
+        status,
+        body["message"].as_str().unwrap_or("Unknown error")
+      )),
+    }
+  }
+
+  async fn clear_children(&self, block_id: &str) -> Result<(), String> {
+    let mut cursor: Option<String> = None;
+    let mut children = Vec::new();
+    loop {
+      let mut path = format!("/blocks/{}/children?page_size=100", block_id);
+      if let Some(cursor) = &cursor {
+        path.push_str(&format!("&start_cursor={}", cursor));
+      }
+      let page = self.call(reqwest::Method::GET, &path, None).await?;
+      if let Some(results) = page["results"].as_array() {
+        children.extend(results.iter().filter_map(|b| b["id"].as_str().map(|s| s.to_string())));
+      }
+      match page["next_cursor"].as_str() {
+        Some(next) if page["has_more"] == true => cursor = Some(next.to_string()),
+        _ => break,
+      }
+    }
+    for child in children {
+      self.call(reqwest::Method::DELETE, &format!("/blocks/{}", child), None).await?;
+    }
+    Ok(())
+  }
+
+  async fn append_children(&self, block_id: &str, blocks: &[Value]) -> Result<(), String> {
+    for chunk in blocks.chunks(MAX_BLOCKS_PER_REQUEST) {
+      self
+        .call(
+          reqwest::Method::PATCH,
+          &format!("/blocks/{}/children", block_id),
+          Some(json!({ "children": chunk })),
+        )
+        .await?;
+    }
+    Ok(())
+  }
+
+  /// Upload a file and return the id to reference it by in a block.
+  async fn upload(&self, path: &PathBuf, file_name: &str, mime_type: &str) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let upload = self
+      .call(
+        reqwest::Method::POST,
+        "/file_uploads",
+        Some(json!({ "filename": file_name, "content_type": mime_type })),
+      )
+      .await?;
+    let upload_id = upload["id"].as_str().ok_or_else(|| "No upload id in response".to_string())?;
+
+    // reqwest is built without multipart support, so the form is put
+    // together by hand
+    let boundary = format!("papa-{}", generate_id());
+    let mut body = format!(
+      "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+      boundary,
+      file_name.replace('"', "'"),
+      mime_type
+    )
+    .into_bytes();
+    body.extend_from_slice(&bytes);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    self
+      .send(
+        self
+          .builder(reqwest::Method::POST, &format!("/file_uploads/{}/send", upload_id))
+          .header("Content-Type", format!("multipart/form-data; boundary={}", boundary))
+          .body(body),
+      )
+      .await?;
+    Ok(upload_id.to_string())
+  }
+}
+
+/// Rich text objects for `text`, split at Notion's length limit.
+fn rich_text(text: &str) -> Vec<Value> {
+  let chars: Vec<char> = text.chars().collect();
+  chars
+    .chunks(MAX_TEXT_CHARS)
+    .map(|chunk| json!({ "type": "text", "text": { "content": chunk.iter().collect::<String>() } }))
+    .collect()
+}
+
+fn text_block(kind: &str, text: &str) -> Value {
+  json!({ "type": kind, kind: { "rich_text": rich_text(text) } })
+}
+
+async fn attachment_block(client: &Client, attachment: &Attachment) -> Value {
+  let file_name = attachment.file_name.as_deref().unwrap_or("unknown");
+  let path = PathBuf::from(attachment.stored_path.as_ref().unwrap_or(&attachment.original_path));
+  let size = fs::metadata(&path).map(|m| m.len()).ok();
+
+  if attachment.kind == "image" && size.is_some_and(|s| s <= MAX_UPLOAD_BYTES) {
+    let mime_type = attachment.mime_type.as_deref().unwrap_or("image/png");
+    match client.upload(&path, file_name, mime_type).await {
+      Ok(upload_id) => {
+        return json!({
+          "type": "image",
+          "image": { "type": "file_upload", "file_upload": { "id": upload_id } },
+        });
+      }
+      Err(e) => tracing::warn!(attachment_id = %attachment.id, error = %e, "Notion image upload failed"),
+    }
+  }
+  let label = match size {
+    Some(_) => format!("📎 {}", file_name),
+    None => format!("📎 {}", i18n::tf("{name} (file not found)", &[("name", file_name.to_string())])),
+  };
+  text_block("paragraph", &label)
+}
+
+async fn day_blocks(client: &Client, day: &Day) -> Vec<Value> {
+  let mut blocks = Vec::new();
+  for (event, attachments) in &day.events {
+    let time = timezone::datetime(event.created_at)
+      .map(|dt| dt.format("%H:%M").to_string())
+      .unwrap_or_else(|| "??:??".to_string());
+    let title = event.title.clone().unwrap_or_else(|| i18n::t("Untitled"));
+    blocks.push(text_block("heading_3", &format!("{} {} {}", time, event_icon(&event.event_type), title)));
+    if let Some(note) = event.note.as_deref().filter(|n| !n.is_empty()) {
+      blocks.push(text_block("paragraph", note));
+    }
+    if let Some(text) = event.text_content.as_deref().filter(|t| !t.is_empty()) {
+      blocks.push(json!({
+        "type": "code",
+        "code": { "rich_text": rich_text(text), "language": "plain text" },
+      }));
+    }
+    for attachment in attachments {
+      blocks.push(attachment_block(client, attachment).await);
+    }
+    blocks.push(json!({ "type": "divider", "divider": {} }));
+  }
+  blocks
+}
+
+fn page_properties(date_key: &str, title_property: &str, date_property: Option<&str>) -> Value {
+  let mut properties = json!({
+    title_property: { "title": rich_text(&i18n::tf("Daily Record - {date}", &[("date", date_key.to_string())])) },
+  });
+  if let Some(date_property) = date_property {
+    properties[date_property] = json!({ "date": { "start": date_key } });
+  }
+  properties
+}
+
+fn load_days(
+  conn: &rusqlite::Connection,
+  database_id: &str,
+  date_range: &DateRange,
+) -> Result<Vec<Day>, String> {
+  let parse = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| i18n::t("Invalid date format"));
+  let (start, end) = (parse(&date_range.start)?, parse(&date_range.end)?);
+  if end < start {
+    return Err("Date range ends before it starts".to_string());
+  }
+  if (end - start).num_days() >= MAX_DAYS {
+    return Err(format!("Date range is limited to {} days", MAX_DAYS));
+  }
+
+  let rules = export_rules::resolve(conn, None);
+  let mut days = Vec::new();
+  for date in start.iter_days().take_while(|d| *d <= end) {
+    let date_key = date.format("%Y-%m-%d").to_string();
+    let events = export_rules::query_day(conn, &date_key, &rules)?
+      .into_iter()
+      .map(|event| {
+        let attachments = query_attachments(conn, &event.id).unwrap_or_default();
+        (event, attachments)
+      })
+      .collect();
+    let page_id = conn
+      .query_row(
+        "SELECT page_id FROM notion_pages WHERE database_id = ?1 AND date_key = ?2",
+        (database_id, &date_key),
+        |row| row.get(0),
+      )
+      .ok();
+    days.push(Day { date_key, events, page_id });
+  }
+  Ok(days)
+}
+
+/// Export every day in `date_range` that has records to the Notion database
+/// `database_id`, one page per day. Days exported before are updated in place.
+#[tauri::command]
+pub async fn export_to_notion(
+  state: tauri::State<'_, DbState>,
+  database_id: String,
+  date_range: DateRange,
+) -> Result<NotionExportResult, String> {
+  let _timer = usage_metrics::time_async("export_to_notion");
+  export(&state, &database_id, &date_range).await
+}
+
+/// The work of `export_to_notion`, also run by the `notion_export` job.
+pub async fn export(state: &DbState, database_id: &str, date_range: &DateRange) -> Result<NotionExportResult, String> {
+  app_lock::ensure_unlocked()?;
+  permissions::ensure(Permission::Network)?;
+  let database_id = database_id.trim().to_string();
+  if database_id.is_empty() {
+    return Err("Notion database id must not be empty".to_string());
+  }
+  let token = secrets::get(TOKEN_SECRET)?.ok_or_else(|| "Notion token is not set".to_string())?;
+  let http = connectivity::client_builder().build().map_err(|e| e.to_string())?;
+  let client = Client { http, token };
+
+  let days = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    load_days(&conn, &database_id, date_range)?
+  };
+
+  // Databases name their title property freely; use a date property too if
+  // there is one
+  let database = client
+    .call(reqwest::Method::GET, &format!("/databases/{}", database_id), None)
+    .await?;
+  let properties = database["properties"].as_object().cloned().unwrap_or_default();
+  let property_of_type = |ty: &str| properties.iter().find(|(_, p)| p["type"] == ty).map(|(name, _)| name.clone());
+  let title_property = property_of_type("title").ok_or_else(|| "Notion database has no title property".to_string())?;
+  let date_property = property_of_type("date");
+
+  let mut result = NotionExportResult { pages: Vec::new(), skipped_days: Vec::new() };
+  for day in days {
+    // Empty days only matter if they were exported before and need clearing
+    if day.events.is_empty() && day.page_id.is_none() {
+      result.skipped_days.push(day.date_key);
+      continue;
+    }
+    let blocks = day_blocks(&client, &day).await;
+    let properties = page_properties(&day.date_key, &title_property, date_property.as_deref());
+
+    let existing = match &day.page_id {
+      Some(page_id) => client.live_page(page_id).await?,
+      None => None,
+    };
+    let (page, created) = match existing {
+      Some(page) => {
+        let page_id = page["id"].as_str().unwrap_or_default().to_string();
+        let page = client
+          .call(
+            reqwest::Method::PATCH,
+            &format!("/pages/{}", page_id),
+            Some(json!({ "properties": properties })),
+          )
+          .await?;
+        client.clear_children(&page_id).await?;
+        client.append_children(&page_id, &blocks).await?;
+        (page, false)
+      }
+      None => {
+        let (first, rest) = blocks.split_at(blocks.len().min(MAX_BLOCKS_PER_REQUEST));
+        let page = client
+          .call(
+            reqwest::Method::POST,
+            "/pages",
+            Some(json!({
+              "parent": { "database_id": database_id },
+              "properties": properties,
+              "children": first,
+            })),
+          )
+          .await?;
+        let page_id = page["id"].as_str().unwrap_or_default();
+        client.append_children(page_id, rest).await?;
+        (page, true)
+      }
+    };
+
+    let page_id = page["id"].as_str().ok_or_else(|| "No page id in response".to_string())?.to_string();
+    {
+      let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+      let conn = state.open().map_err(|e| e.to_string())?;
+      conn
+        .execute(
+          "INSERT INTO notion_pages (database_id, date_key, page_id, synced_at) VALUES (?1, ?2, ?3, ?4)
+           ON CONFLICT(database_id, date_key) DO UPDATE SET page_id = ?3, synced_at = ?4",
+          (&database_id, &day.date_key, &page_id, now_ms()),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    result.pages.push(NotionPage {
+      date_key: day.date_key,
+      page_id,
+      url: page["url"].as_str().map(|s| s.to_string()),
+      created,
+    });
+  }
+  Ok(result)
+}
@@ -0,0 +1,140 @@
+// Switches for what the pet may reach beyond this machine.
+//
+// The `permissions` setting holds three toggles, all on by default:
+//
+//   allowNetwork  requests to the internet: weather, GitHub, feeds, calendars,
+//                 IP location, Notion, the email digest, pushing the journal
+//                 repository, and LLM providers
+//   allowLlm      sending anything to a language model
+//   allowLan      the LAN capture listener phones upload to
+//
+// Turning all three off makes a strictly offline pet. Commands that need a
+// switch that's off fail with "Permission denied: <name>" (the name being
+// "network", "llm" or "lan"); the webview matches on `PERMISSION_DENIED`, so
+// the text is never translated. Background fetchers skip their round while
+// the switch is off instead, and features with a heuristic fallback
+// (summaries, action items) use it as they would after a failed LLM call.
+//
+// The toggles are read on every check, so they're kept in a process-wide
+// slot like the redaction rules and refreshed with `reload`.
+
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tauri::Manager;
+
+use crate::{config, lan_capture, read_setting, write_setting, DbState};
+
+pub const PERMISSIONS_KEY: &str = "permissions";
+pub const PERMISSION_DENIED: &str = "Permission denied";
+
+static CURRENT: RwLock<Permissions> = RwLock::new(Permissions::ALL);
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Permissions {
+  allow_network: bool,
+  allow_llm: bool,
+  allow_lan: bool,
+}
+
+impl Permissions {
+  const ALL: Self = Self { allow_network: true, allow_llm: true, allow_lan: true };
+}
+
+impl Default for Permissions {
+  fn default() -> Self {
+    Self::ALL
+  }
+}
+
+#[derive(Clone, Copy)]
+pub enum Permission {
+  Network,
+  Llm,
+  Lan,
+}
+
+impl Permission {
+  fn name(self) -> &'static str {
+    match self {
+      Permission::Network => "network",
+      Permission::Llm => "llm",
+      Permission::Lan => "lan",
+    }
+  }
+}
+
+fn load(conn: &rusqlite::Connection) -> Permissions {
+  read_setting(conn, PERMISSIONS_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn current() -> Permissions {
+  CURRENT.read().map(|p| *p).unwrap_or_default()
+}
+
+/// Read the setting again; call after it changes.
+pub fn reload(app: &tauri::AppHandle) {
+  let state = app.state::<DbState>();
+  let permissions = {
+    let Ok(_guard) = state.lock.lock() else { return };
+    let Ok(conn) = state.open() else { return };
+    load(&conn)
+  };
+  if let Ok(mut slot) = CURRENT.write() {
+    *slot = permissions;
+  }
+}
+
+pub fn allowed(permission: Permission) -> bool {
+  let permissions = current();
+  match permission {
+    Permission::Network => permissions.allow_network,
+    Permission::Llm => permissions.allow_llm,
+    Permission::Lan => permissions.allow_lan,
+  }
+}
+
+/// `Err("Permission denied: <name>")` unless `permission` is on.
+pub fn ensure(permission: Permission) -> Result<(), String> {
+  if allowed(permission) {
+    return Ok(());
+  }
+  tracing::debug!(permission = permission.name(), "Permission denied");
+  Err(format!("{}: {}", PERMISSION_DENIED, permission.name()))
+}
+
+/// Every LLM provider is reached over the internet, so a model call needs
+/// both switches.
+pub fn ensure_llm() -> Result<(), String> {
+  ensure(Permission::Llm)?;
+  ensure(Permission::Network)
+}
+
+#[tauri::command]
+pub fn get_permissions() -> Result<Permissions, String> {
+  Ok(current())
+}
+
+#[tauri::command]
+pub fn set_permissions(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  permissions: Permissions,
+) -> Result<Permissions, String> {
+  let json = serde_json::to_string(&permissions).map_err(|e| e.to_string())?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, PERMISSIONS_KEY, &json)?;
+  }
+  if let Ok(mut slot) = CURRENT.write() {
+    *slot = permissions;
+  }
+  // Stops the listener when LAN access was just switched off, and brings it
+  // back when switched on again
+  lan_capture::restore(&app);
+  config::emit_changed(&app, vec![PERMISSIONS_KEY.to_string()]);
+  Ok(permissions)
+}
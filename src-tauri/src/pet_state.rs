@@ -0,0 +1,162 @@
+// The pet's mood, decided in one place for every window.
+//
+// Subsystems report what they see: the behavior monitor its activity
+// samples and away/return, the reminder scanner fired reminders, wellness
+// its nudges and the streak watcher milestones. From these the pet is, in
+// order of precedence: sleeping while the user is away, cheering for a
+// short while after a milestone, concerned for a while after reminders or
+// nudges, busy while activity stays high, and idle otherwise. Every change
+// is emitted to all windows as `pet-state-changed` with the reason, and the
+// tray tooltip follows it.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+use crate::now_ms;
+
+pub const TRAY_ID: &str = "main";
+
+const CHEER_DURATION: Duration = Duration::from_secs(30);
+const CONCERN_DURATION: Duration = Duration::from_secs(5 * 60);
+// Smoothing of the activity level over analysis windows, and the smoothed
+// level that counts as busy
+const ACTIVITY_SMOOTHING: f64 = 0.2;
+const BUSY_LEVEL: f64 = 0.5;
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Mood {
+  Idle,
+  Busy,
+  Concerned,
+  Cheering,
+  Sleeping,
+}
+
+impl Mood {
+  fn label(self) -> &'static str {
+    match self {
+      Mood::Idle => "idle",
+      Mood::Busy => "busy",
+      Mood::Concerned => "concerned",
+      Mood::Cheering => "cheering",
+      Mood::Sleeping => "sleeping",
+    }
+  }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PetState {
+  state: Mood,
+  reason: String,
+  since: i64,
+}
+
+struct Engine {
+  away: bool,
+  activity: f64,
+  cheer: Option<(Instant, String)>,
+  concern: Option<(Instant, String)>,
+  current: PetState,
+}
+
+impl Default for Engine {
+  fn default() -> Self {
+    Engine {
+      away: false,
+      activity: 0.0,
+      cheer: None,
+      concern: None,
+      current: PetState { state: Mood::Idle, reason: "Starting up".to_string(), since: now_ms() },
+    }
+  }
+}
+
+impl Engine {
+  fn evaluate(&mut self) -> (Mood, String) {
+    let now = Instant::now();
+    let live = |slot: &mut Option<(Instant, String)>| match slot {
+      Some((until, reason)) if *until > now => Some(reason.clone()),
+      _ => {
+        *slot = None;
+        None
+      }
+    };
+    if self.away {
+      return (Mood::Sleeping, "User is away".to_string());
+    }
+    if let Some(reason) = live(&mut self.cheer) {
+      return (Mood::Cheering, reason);
+    }
+    if let Some(reason) = live(&mut self.concern) {
+      return (Mood::Concerned, reason);
+    }
+    if self.activity >= BUSY_LEVEL {
+      return (Mood::Busy, "Lots of activity".to_string());
+    }
+    (Mood::Idle, "Nothing going on".to_string())
+  }
+}
+
+#[derive(Default)]
+pub struct PetStateState {
+  engine: Mutex<Engine>,
+}
+
+/// Apply `change`, re-evaluate and announce the new state if it changed.
+fn update(app: &tauri::AppHandle, change: impl FnOnce(&mut Engine)) {
+  let pet = app.state::<PetStateState>();
+  let changed = {
+    let Ok(mut engine) = pet.engine.lock() else { return };
+    change(&mut engine);
+    let (state, reason) = engine.evaluate();
+    if state == engine.current.state && reason == engine.current.reason {
+      return;
+    }
+    engine.current = PetState { state, reason, since: now_ms() };
+    engine.current.clone()
+  };
+
+  if let Some(tray) = app.tray_by_id(TRAY_ID) {
+    let _ = tray.set_tooltip(Some(format!("Papa Pet ({}: {})", changed.state.label(), changed.reason)));
+  }
+  let _ = app.emit("pet-state-changed", &changed);
+}
+
+/// An analysis window from the behavior monitor.
+pub fn observe_sample(app: &tauri::AppHandle, activity_level: f64) {
+  update(app, |engine| {
+    engine.activity += (activity_level - engine.activity) * ACTIVITY_SMOOTHING;
+  });
+}
+
+pub fn set_away(app: &tauri::AppHandle, away: bool) {
+  update(app, |engine| {
+    engine.away = away;
+    engine.activity = 0.0;
+  });
+}
+
+pub fn cheer(app: &tauri::AppHandle, reason: String) {
+  update(app, |engine| engine.cheer = Some((Instant::now() + CHEER_DURATION, reason)));
+}
+
+pub fn concern(app: &tauri::AppHandle, reason: String) {
+  update(app, |engine| engine.concern = Some((Instant::now() + CONCERN_DURATION, reason)));
+}
+
+pub fn current(app: &tauri::AppHandle) -> PetState {
+  let pet = app.state::<PetStateState>();
+  let current = pet.engine.lock().map(|engine| engine.current.clone());
+  current.unwrap_or_else(|_| Engine::default().current)
+}
+
+/// Current state, for windows opened after the last change.
+#[tauri::command]
+pub fn get_pet_state(pet: tauri::State<PetStateState>) -> Result<PetState, String> {
+  let engine = pet.engine.lock().map_err(|_| "pet state lock".to_string())?;
+  Ok(engine.current.clone())
+}
@@ -0,0 +1,441 @@
+// Placement and window modes (click-through, always-on-top, pinning to all
+// workspaces) of the pet window.
+//
+// The last position is remembered per monitor configuration (a fingerprint
+// of every display's name, position and size), so unplugging a laptop from
+// its dock and plugging it back in puts the pet back where it was on each
+// setup instead of leaving it off-screen.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::{read_setting, supervisor, write_setting, DbState};
+
+const POSITION_KEY_PREFIX: &str = "window.position.";
+pub const CLICK_THROUGH_KEY: &str = "window.click_through";
+pub const ALWAYS_ON_TOP_KEY: &str = "window.always_on_top";
+pub const ALL_WORKSPACES_KEY: &str = "window.all_workspaces";
+// Gap kept between the pet and the screen edge when snapping to a corner
+const CORNER_MARGIN: f64 = 16.0;
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorInfo {
+  index: usize,
+  name: Option<String>,
+  x: i32,
+  y: i32,
+  width: u32,
+  height: u32,
+  work_x: i32,
+  work_y: i32,
+  work_width: u32,
+  work_height: u32,
+  scale_factor: f64,
+  is_primary: bool,
+  is_current: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorLayout {
+  fingerprint: String,
+  monitors: Vec<MonitorInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct PetPosition {
+  x: i32,
+  y: i32,
+}
+
+#[derive(Default)]
+pub struct PlacementState {
+  pending: Mutex<Option<(String, PetPosition)>>,
+  fingerprint: Mutex<Option<String>>,
+  click_through: AtomicBool,
+  always_on_top: AtomicBool,
+  all_workspaces: AtomicBool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowModes {
+  click_through: bool,
+  always_on_top: bool,
+  all_workspaces: bool,
+}
+
+fn main_window(app: &tauri::AppHandle) -> Result<tauri::WebviewWindow, String> {
+  app
+    .get_webview_window("main")
+    .ok_or_else(|| "missing window".to_string())
+}
+
+fn same_monitor(a: &tauri::Monitor, b: &tauri::Monitor) -> bool {
+  a.name() == b.name() && a.position() == b.position() && a.size() == b.size()
+}
+
+fn layout_fingerprint(monitors: &[tauri::Monitor]) -> String {
+  let mut parts: Vec<String> = monitors
+    .iter()
+    .map(|m| {
+      format!(
+        "{}@{},{}:{}x{}",
+        m.name().map(|n| n.as_str()).unwrap_or(""),
+        m.position().x,
+        m.position().y,
+        m.size().width,
+        m.size().height
+      )
+    })
+    .collect();
+  // Enumeration order isn't stable across OS sessions
+  parts.sort();
+
+  let digest = Sha256::digest(parts.join("|").as_bytes());
+  hex::encode(&digest[..8])
+}
+
+fn current_fingerprint(window: &tauri::WebviewWindow) -> Result<String, String> {
+  let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+  Ok(layout_fingerprint(&monitors))
+}
+
+fn monitor_layout(window: &tauri::WebviewWindow) -> Result<MonitorLayout, String> {
+  let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+  let primary = window.primary_monitor().map_err(|e| e.to_string())?;
+  let current = window.current_monitor().map_err(|e| e.to_string())?;
+
+  let infos = monitors
+    .iter()
+    .enumerate()
+    .map(|(index, m)| {
+      let work = m.work_area();
+      MonitorInfo {
+        index,
+        name: m.name().cloned(),
+        x: m.position().x,
+        y: m.position().y,
+        width: m.size().width,
+        height: m.size().height,
+        work_x: work.position.x,
+        work_y: work.position.y,
+        work_width: work.size.width,
+        work_height: work.size.height,
+        scale_factor: m.scale_factor(),
+        is_primary: primary.as_ref().map(|p| same_monitor(p, m)).unwrap_or(false),
+        is_current: current.as_ref().map(|c| same_monitor(c, m)).unwrap_or(false),
+      }
+    })
+    .collect();
+
+  Ok(MonitorLayout {
+    fingerprint: layout_fingerprint(&monitors),
+    monitors: infos,
+  })
+}
+
+fn is_on_screen(window: &tauri::WebviewWindow, pos: PetPosition) -> bool {
+  window
+    .available_monitors()
+    .map(|monitors| {
+      monitors.iter().any(|m| {
+        let area = m.work_area();
+        pos.x >= area.position.x
+          && pos.y >= area.position.y
+          && pos.x < area.position.x + area.size.width as i32
+          && pos.y < area.position.y + area.size.height as i32
+      })
+    })
+    .unwrap_or(false)
+}
+
+fn set_position(window: &tauri::WebviewWindow, pos: PetPosition) -> Result<(), String> {
+  window
+    .set_position(tauri::Position::Physical(tauri::PhysicalPosition { x: pos.x, y: pos.y }))
+    .map_err(|e| e.to_string())
+}
+
+fn load_saved_position(app: &tauri::AppHandle, fingerprint: &str) -> Option<PetPosition> {
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().ok()?;
+  let conn = state.open().ok()?;
+  read_setting(&conn, &format!("{}{}", POSITION_KEY_PREFIX, fingerprint))
+    .and_then(|json| serde_json::from_str(&json).ok())
+}
+
+fn save_position(app: &tauri::AppHandle, fingerprint: &str, pos: PetPosition) -> Result<(), String> {
+  let json = serde_json::to_string(&pos).map_err(|e| e.to_string())?;
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  write_setting(&conn, &format!("{}{}", POSITION_KEY_PREFIX, fingerprint), &json)
+}
+
+fn load_flag(app: &tauri::AppHandle, key: &str) -> Option<bool> {
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().ok()?;
+  state.open()
+    .ok()
+    .and_then(|conn| read_setting(&conn, key))
+    .map(|v| v == "true")
+}
+
+fn save_flag(app: &tauri::AppHandle, key: &str, enabled: bool) -> Result<(), String> {
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  write_setting(&conn, key, if enabled { "true" } else { "false" })
+}
+
+/// Snap the window into a corner of the monitor's work area.
+fn corner_position(
+  window: &tauri::WebviewWindow,
+  monitor: &tauri::Monitor,
+  corner: &str,
+) -> Result<PetPosition, String> {
+  let area = monitor.work_area();
+  let size = window.outer_size().map_err(|e| e.to_string())?;
+  let margin = (CORNER_MARGIN * monitor.scale_factor()).round() as i32;
+
+  let left = area.position.x + margin;
+  let top = area.position.y + margin;
+  let right = area.position.x + area.size.width as i32 - size.width as i32 - margin;
+  let bottom = area.position.y + area.size.height as i32 - size.height as i32 - margin;
+
+  let (x, y) = match corner {
+    "top-left" => (left, top),
+    "top-right" => (right, top),
+    "bottom-left" => (left, bottom),
+    "bottom-right" => (right, bottom),
+    "center" => (
+      area.position.x + (area.size.width as i32 - size.width as i32) / 2,
+      area.position.y + (area.size.height as i32 - size.height as i32) / 2,
+    ),
+    _ => return Err(format!("Unknown corner: {}", corner)),
+  };
+  Ok(PetPosition { x, y })
+}
+
+/// Snap any window into a corner of the monitor the pet is on (falling back
+/// to the primary monitor).
+pub fn snap_to_corner(app: &tauri::AppHandle, window: &tauri::WebviewWindow, corner: &str) -> Result<(), String> {
+  let pet = main_window(app)?;
+  let monitor = match pet.current_monitor().map_err(|e| e.to_string())? {
+    Some(monitor) => monitor,
+    None => pet
+      .primary_monitor()
+      .map_err(|e| e.to_string())?
+      .ok_or_else(|| "No monitor available".to_string())?,
+  };
+  let pos = corner_position(window, &monitor, corner)?;
+  set_position(window, pos)
+}
+
+/// Put the window back where it was last seen on this monitor setup, or
+/// into the primary monitor's corner if that spot is no longer visible.
+fn restore_for_layout(app: &tauri::AppHandle, window: &tauri::WebviewWindow, fingerprint: &str) {
+  if let Some(pos) = load_saved_position(app, fingerprint) {
+    if is_on_screen(window, pos) {
+      let _ = set_position(window, pos);
+      return;
+    }
+  }
+
+  let visible = window
+    .outer_position()
+    .map(|p| is_on_screen(window, PetPosition { x: p.x, y: p.y }))
+    .unwrap_or(true);
+  if !visible {
+    if let Ok(Some(primary)) = window.primary_monitor() {
+      if let Ok(pos) = corner_position(window, &primary, "bottom-right") {
+        let _ = set_position(window, pos);
+      }
+    }
+  }
+}
+
+/// Called from the window event handler whenever the pet window moves.
+pub fn track_window_move(app: &tauri::AppHandle, x: i32, y: i32) {
+  let state = app.state::<PlacementState>();
+  let Ok(Some(fingerprint)) = state.fingerprint.lock().map(|f| f.clone()) else { return };
+  if let Ok(mut pending) = state.pending.lock() {
+    *pending = Some((fingerprint, PetPosition { x, y }));
+  };
+}
+
+/// Restore the saved position at startup, then persist moves and react to
+/// display changes in the background.
+pub fn spawn_placement_watcher(app: tauri::AppHandle) {
+  if let Ok(window) = main_window(&app) {
+    if let Ok(fingerprint) = current_fingerprint(&window) {
+      restore_for_layout(&app, &window, &fingerprint);
+      if let Ok(mut current) = app.state::<PlacementState>().fingerprint.lock() {
+        *current = Some(fingerprint);
+      }
+    }
+  }
+
+  supervisor::supervise(app, "placement", |app| async move {
+    loop {
+      tokio::time::sleep(WATCH_INTERVAL).await;
+
+      let state = app.state::<PlacementState>();
+      let pending = state.pending.lock().ok().and_then(|mut p| p.take());
+      if let Some((fingerprint, pos)) = pending {
+        let _ = save_position(&app, &fingerprint, pos);
+      }
+
+      let Ok(window) = main_window(&app) else { continue };
+      let Ok(fingerprint) = current_fingerprint(&window) else { continue };
+      let changed = state
+        .fingerprint
+        .lock()
+        .map(|mut current| {
+          let changed = current.as_deref() != Some(fingerprint.as_str());
+          *current = Some(fingerprint.clone());
+          changed
+        })
+        .unwrap_or(false);
+
+      if changed {
+        restore_for_layout(&app, &window, &fingerprint);
+        if let Ok(layout) = monitor_layout(&window) {
+          let _ = window.emit("monitor-layout-changed", &layout);
+        }
+      }
+    }
+  });
+}
+
+/// Let clicks fall through the pet to whatever is underneath. Persisted so
+/// the pet comes back in the same mode after a restart.
+pub fn apply_click_through(app: &tauri::AppHandle, enabled: bool) -> Result<(), String> {
+  let window = main_window(app)?;
+  window
+    .set_ignore_cursor_events(enabled)
+    .map_err(|e| e.to_string())?;
+  app
+    .state::<PlacementState>()
+    .click_through
+    .store(enabled, Ordering::Relaxed);
+  save_flag(app, CLICK_THROUGH_KEY, enabled)?;
+  let _ = window.emit("click-through-changed", serde_json::json!({ "enabled": enabled }));
+  Ok(())
+}
+
+/// Flip click-through; used by the tray menu and gestures, which are the
+/// only way back once the window ignores the mouse.
+pub fn toggle_click_through(app: &tauri::AppHandle) -> Result<bool, String> {
+  let enabled = !app
+    .state::<PlacementState>()
+    .click_through
+    .load(Ordering::Relaxed);
+  apply_click_through(app, enabled)?;
+  Ok(enabled)
+}
+
+fn apply_always_on_top(app: &tauri::AppHandle, enabled: bool) -> Result<(), String> {
+  main_window(app)?
+    .set_always_on_top(enabled)
+    .map_err(|e| e.to_string())?;
+  app
+    .state::<PlacementState>()
+    .always_on_top
+    .store(enabled, Ordering::Relaxed);
+  Ok(())
+}
+
+/// Show the pet on every virtual desktop. Supported on macOS and Linux;
+/// Windows has no public API for it, so the call is a no-op there.
+fn apply_all_workspaces(app: &tauri::AppHandle, enabled: bool) -> Result<(), String> {
+  main_window(app)?
+    .set_visible_on_all_workspaces(enabled)
+    .map_err(|e| e.to_string())?;
+  app
+    .state::<PlacementState>()
+    .all_workspaces
+    .store(enabled, Ordering::Relaxed);
+  Ok(())
+}
+
+/// Re-apply persisted window modes at startup. Always-on-top defaults to
+/// on, matching the window config.
+pub fn restore_window_modes(app: &tauri::AppHandle) {
+  let _ = apply_always_on_top(app, load_flag(app, ALWAYS_ON_TOP_KEY).unwrap_or(true));
+  if load_flag(app, ALL_WORKSPACES_KEY) == Some(true) {
+    let _ = apply_all_workspaces(app, true);
+  }
+  if load_flag(app, CLICK_THROUGH_KEY) == Some(true) {
+    let _ = apply_click_through(app, true);
+  }
+}
+
+/// Apply the stored window modes after they were changed through settings.
+pub fn reload_window_modes(app: &tauri::AppHandle) {
+  let _ = apply_always_on_top(app, load_flag(app, ALWAYS_ON_TOP_KEY).unwrap_or(true));
+  let _ = apply_all_workspaces(app, load_flag(app, ALL_WORKSPACES_KEY).unwrap_or(false));
+  let click_through = load_flag(app, CLICK_THROUGH_KEY).unwrap_or(false);
+  if click_through != app.state::<PlacementState>().click_through.load(Ordering::Relaxed) {
+    let _ = apply_click_through(app, click_through);
+  }
+}
+
+#[tauri::command]
+pub fn set_click_through(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+  apply_click_through(&app, enabled)
+}
+
+#[tauri::command]
+pub fn set_always_on_top(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+  apply_always_on_top(&app, enabled)?;
+  save_flag(&app, ALWAYS_ON_TOP_KEY, enabled)
+}
+
+#[tauri::command]
+pub fn set_visible_on_all_workspaces(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+  apply_all_workspaces(&app, enabled)?;
+  save_flag(&app, ALL_WORKSPACES_KEY, enabled)
+}
+
+#[tauri::command]
+pub fn get_window_modes(state: tauri::State<PlacementState>) -> WindowModes {
+  WindowModes {
+    click_through: state.click_through.load(Ordering::Relaxed),
+    always_on_top: state.always_on_top.load(Ordering::Relaxed),
+    all_workspaces: state.all_workspaces.load(Ordering::Relaxed),
+  }
+}
+
+#[tauri::command]
+pub fn get_monitor_layout(app: tauri::AppHandle) -> Result<MonitorLayout, String> {
+  monitor_layout(&main_window(&app)?)
+}
+
+/// Move the pet to a corner ("top-left", "top-right", "bottom-left",
+/// "bottom-right" or "center") of the monitor at `index`.
+#[tauri::command]
+pub fn move_to_monitor(
+  app: tauri::AppHandle,
+  index: usize,
+  corner: String,
+) -> Result<PetPosition, String> {
+  let window = main_window(&app)?;
+  let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+  let monitor = monitors
+    .get(index)
+    .ok_or_else(|| format!("No monitor at index {}", index))?;
+
+  let pos = corner_position(&window, monitor, &corner)?;
+  set_position(&window, pos)?;
+
+  // Persist right away rather than waiting for the watcher
+  save_position(&app, &layout_fingerprint(&monitors), pos)?;
+  Ok(pos)
+}
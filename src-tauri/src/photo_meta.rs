@@ -0,0 +1,181 @@
+// EXIF extraction for photos (JPEG, TIFF, HEIC/HEIF, PNG, WebP).
+//
+// Capture time, camera and GPS position go into `attachment_metadata`.
+// When `ingest.backdate_photos` is on, drop events built from photos take the
+// earliest capture time as their `created_at` so imports land on the right
+// day.
+
+use chrono::{FixedOffset, Local, NaiveDate, TimeZone};
+use exif::{In, Reader, Tag, Value};
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::{app_lock, read_setting, DbState};
+
+pub const BACKDATE_PHOTOS_KEY: &str = "ingest.backdate_photos";
+
+const EXIF_EXTENSIONS: &[&str] = &["jpg", "jpeg", "tif", "tiff", "heic", "heif", "png", "webp"];
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoMetadata {
+  taken_at: Option<i64>,
+  camera_make: Option<String>,
+  camera_model: Option<String>,
+  lens_model: Option<String>,
+  gps_latitude: Option<f64>,
+  gps_longitude: Option<f64>,
+  orientation: Option<u32>,
+}
+
+fn ascii_field(exif: &exif::Exif, tag: Tag) -> Option<String> {
+  let field = exif.get_field(tag, In::PRIMARY)?;
+  match &field.value {
+    Value::Ascii(parts) => parts
+      .first()
+      .map(|bytes| String::from_utf8_lossy(bytes).trim().to_string())
+      .filter(|s| !s.is_empty()),
+    _ => None,
+  }
+}
+
+/// Capture time in unix ms. EXIF times are wall-clock; use the recorded
+/// offset when present and the local zone otherwise.
+fn taken_at(exif: &exif::Exif) -> Option<i64> {
+  let field = exif
+    .get_field(Tag::DateTimeOriginal, In::PRIMARY)
+    .or_else(|| exif.get_field(Tag::DateTime, In::PRIMARY))?;
+  let Value::Ascii(parts) = &field.value else { return None };
+  let mut dt = exif::DateTime::from_ascii(parts.first()?).ok()?;
+  if let Some(Value::Ascii(offset)) = exif
+    .get_field(Tag::OffsetTimeOriginal, In::PRIMARY)
+    .map(|f| &f.value)
+  {
+    if let Some(offset) = offset.first() {
+      let _ = dt.parse_offset(offset);
+    }
+  }
+
+  let naive = NaiveDate::from_ymd_opt(dt.year as i32, dt.month as u32, dt.day as u32)?
+    .and_hms_opt(dt.hour as u32, dt.minute as u32, dt.second as u32)?;
+  match dt.offset {
+    Some(minutes) => FixedOffset::east_opt(minutes as i32 * 60)?
+      .from_local_datetime(&naive)
+      .single()
+      .map(|t| t.timestamp_millis()),
+    None => Local
+      .from_local_datetime(&naive)
+      .earliest()
+      .map(|t| t.timestamp_millis()),
+  }
+}
+
+fn gps_coordinate(exif: &exif::Exif, tag: Tag, ref_tag: Tag, negative_ref: &str) -> Option<f64> {
+  let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else { return None };
+  if parts.len() < 3 {
+    return None;
+  }
+  let degrees = parts[0].to_f64() + parts[1].to_f64() / 60.0 + parts[2].to_f64() / 3600.0;
+  let negative = ascii_field(exif, ref_tag)
+    .map(|r| r.eq_ignore_ascii_case(negative_ref))
+    .unwrap_or(false);
+  Some(if negative { -degrees } else { degrees })
+}
+
+/// Read EXIF from a photo; `None` for other files or photos without EXIF.
+pub fn read_photo_metadata(path: &Path) -> Option<PhotoMetadata> {
+  let ext = path.extension()?.to_str()?.to_lowercase();
+  if !EXIF_EXTENSIONS.contains(&ext.as_str()) {
+    return None;
+  }
+
+  let file = File::open(path).ok()?;
+  let exif = Reader::new()
+    .read_from_container(&mut BufReader::new(file))
+    .ok()?;
+
+  Some(PhotoMetadata {
+    taken_at: taken_at(&exif),
+    camera_make: ascii_field(&exif, Tag::Make),
+    camera_model: ascii_field(&exif, Tag::Model),
+    lens_model: ascii_field(&exif, Tag::LensModel),
+    gps_latitude: gps_coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S"),
+    gps_longitude: gps_coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W"),
+    orientation: exif
+      .get_field(Tag::Orientation, In::PRIMARY)
+      .and_then(|f| f.value.get_uint(0)),
+  })
+}
+
+/// Extract and store metadata for a freshly inserted attachment.
+pub fn record_metadata(conn: &rusqlite::Connection, attachment_id: &str, path: &Path) -> Result<(), String> {
+  let Some(meta) = read_photo_metadata(path) else { return Ok(()) };
+  conn.execute(
+    "INSERT OR REPLACE INTO attachment_metadata
+       (attachment_id, taken_at, camera_make, camera_model, lens_model, gps_latitude, gps_longitude, orientation)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+    (
+      attachment_id,
+      meta.taken_at,
+      &meta.camera_make,
+      &meta.camera_model,
+      &meta.lens_model,
+      meta.gps_latitude,
+      meta.gps_longitude,
+      meta.orientation,
+    ),
+  ).map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Earliest capture time among an event's photos, if backdating is enabled
+/// (`requested` overrides the setting).
+pub fn backdate_target(conn: &rusqlite::Connection, event_id: &str, requested: Option<bool>) -> Option<i64> {
+  let enabled = requested.unwrap_or_else(|| read_setting(conn, BACKDATE_PHOTOS_KEY).as_deref() == Some("true"));
+  if !enabled {
+    return None;
+  }
+  conn
+    .query_row(
+      "SELECT MIN(m.taken_at)
+       FROM attachment_metadata m
+       JOIN attachments a ON a.id = m.attachment_id
+       WHERE a.event_id = ?",
+      [event_id],
+      |row| row.get(0),
+    )
+    .ok()
+    .flatten()
+}
+
+#[tauri::command]
+pub fn get_attachment_metadata(
+  state: tauri::State<DbState>,
+  attachment_id: String,
+) -> Result<Option<PhotoMetadata>, String> {
+  app_lock::ensure_unlocked()?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let meta = conn
+    .query_row(
+      "SELECT taken_at, camera_make, camera_model, lens_model, gps_latitude, gps_longitude, orientation
+       FROM attachment_metadata WHERE attachment_id = ?",
+      [&attachment_id],
+      |row| {
+        Ok(PhotoMetadata {
+          taken_at: row.get(0)?,
+          camera_make: row.get(1)?,
+          camera_model: row.get(2)?,
+          lens_model: row.get(3)?,
+          gps_latitude: row.get(4)?,
+          gps_longitude: row.get(5)?,
+          orientation: row.get(6)?,
+        })
+      },
+    )
+    .ok();
+  Ok(meta)
+}
@@ -0,0 +1,61 @@
+// Per-event "never send to AI" flag.
+//
+// Events with `ai_opt_out` set are left out of every LLM pipeline: journal
+// prompts, RAG context, tag suggestions and anything built on top of them.
+// The check lives here, in the backend, so a UI that forgets to filter can't
+// leak an event. Clipboard captures can default to opted out through the
+// `privacy.clipboard_ai_opt_out` setting.
+
+use crate::{i18n, read_setting, write_setting, DbState};
+
+pub const CLIPBOARD_OPT_OUT_KEY: &str = "privacy.clipboard_ai_opt_out";
+
+/// Whether new clipboard captures start out opted out.
+pub fn clipboard_opt_out(conn: &rusqlite::Connection) -> bool {
+  read_setting(conn, CLIPBOARD_OPT_OUT_KEY).as_deref() == Some("true")
+}
+
+pub fn is_opted_out(conn: &rusqlite::Connection, event_id: &str) -> Result<bool, String> {
+  conn
+    .query_row(
+      "SELECT ai_opt_out FROM timeline_events WHERE id = ?",
+      [event_id],
+      |row| row.get::<_, i32>(0),
+    )
+    .map(|flag| flag != 0)
+    .map_err(|_| i18n::t("Event not found"))
+}
+
+#[tauri::command]
+pub fn set_event_ai_opt_out(
+  state: tauri::State<DbState>,
+  event_id: String,
+  opt_out: bool,
+) -> Result<(), String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let updated = conn
+    .execute(
+      "UPDATE timeline_events SET ai_opt_out = ?1 WHERE id = ?2",
+      (opt_out, &event_id),
+    )
+    .map_err(|e| e.to_string())?;
+  if updated == 0 {
+    return Err(i18n::t("Event not found"));
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_clipboard_ai_opt_out(state: tauri::State<DbState>) -> Result<bool, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(clipboard_opt_out(&conn))
+}
+
+#[tauri::command]
+pub fn set_clipboard_ai_opt_out(state: tauri::State<DbState>, enabled: bool) -> Result<(), String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  write_setting(&conn, CLIPBOARD_OPT_OUT_KEY, if enabled { "true" } else { "false" })
+}
@@ -0,0 +1,190 @@
+// Separate profiles (say "work" and "personal"), each with its own database
+// and attachment files.
+//
+// The default profile keeps the original layout in the app data folder, so
+// existing installs carry on as that profile. Other profiles live in
+// `profiles/<name>/` with the same layout. Which one is active is kept in a
+// small file next to them rather than in a database, since it decides which
+// database to open.
+
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{Emitter, Manager};
+
+use crate::{app_lock, config, dev_fixtures, init_db, jobs, lan_capture, open_db, reminder_scan, undo, watch_folders, DbState};
+
+pub const DEFAULT_PROFILE: &str = "default";
+const DB_FILE: &str = "papa_pet.sqlite";
+const PROFILES_DIR: &str = "profiles";
+const ACTIVE_FILE: &str = "active_profile";
+const MAX_NAME_LEN: usize = 40;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+  name: String,
+  active: bool,
+  /// Folder holding the profile's database and files
+  path: String,
+}
+
+/// Every key stored in the settings table of the database at `db_path`.
+fn setting_keys(db_path: &Path) -> Result<BTreeSet<String>, String> {
+  let conn = open_db(db_path).map_err(|e| e.to_string())?;
+  let keys = conn
+    .prepare("SELECT key FROM settings")
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| row.get(0))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(keys)
+}
+
+fn app_data(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+  app.path().app_data_dir().map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+fn profile_dir(app: &tauri::AppHandle, name: &str) -> Result<PathBuf, String> {
+  let base = app_data(app)?;
+  Ok(if name == DEFAULT_PROFILE { base } else { base.join(PROFILES_DIR).join(name) })
+}
+
+/// Folder holding every profile other than the default one. It sits inside
+/// the default profile's folder, so reads from there are checked against it.
+pub fn profiles_root(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+  Ok(app_data(app)?.join(PROFILES_DIR))
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+  let name = name.trim();
+  if name.is_empty() || name.len() > MAX_NAME_LEN {
+    return Err(format!("Profile name must be 1 to {} characters", MAX_NAME_LEN));
+  }
+  if !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == ' ') {
+    return Err("Profile name may only use letters, digits, spaces, '-' and '_'".to_string());
+  }
+  Ok(name.to_string())
+}
+
+fn stored_active(app: &tauri::AppHandle) -> String {
+  app_data(app)
+    .ok()
+    .and_then(|dir| fs::read_to_string(dir.join(ACTIVE_FILE)).ok())
+    .map(|name| name.trim().to_string())
+    .filter(|name| validate_name(name).is_ok())
+    .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// Name of the profile whose database is open.
+pub fn active_name(state: &DbState, app: &tauri::AppHandle) -> String {
+  let path = state.data_dir();
+  profile_names(app)
+    .into_iter()
+    .find(|name| profile_dir(app, name).is_ok_and(|dir| dir == path))
+    .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+fn profile_names(app: &tauri::AppHandle) -> Vec<String> {
+  let mut names: Vec<String> = app_data(app)
+    .ok()
+    .and_then(|dir| fs::read_dir(dir.join(PROFILES_DIR)).ok())
+    .into_iter()
+    .flatten()
+    .flatten()
+    .filter(|entry| entry.path().join(DB_FILE).is_file())
+    .map(|entry| entry.file_name().to_string_lossy().to_string())
+    .filter(|name| name != DEFAULT_PROFILE && validate_name(name).is_ok())
+    .collect();
+  names.sort_by_key(|name| name.to_lowercase());
+  names.insert(0, DEFAULT_PROFILE.to_string());
+  names
+}
+
+/// Database of the profile that was active when the app last ran. Falls
+/// back to the default profile if that one has gone missing.
+pub fn active_db_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+  let name = stored_active(app);
+  let dir = profile_dir(app, &name)?;
+  if name != DEFAULT_PROFILE && !dir.join(DB_FILE).is_file() {
+    tracing::warn!(profile = %name, "Active profile not found, using the default one");
+    return Ok(profile_dir(app, DEFAULT_PROFILE)?.join(DB_FILE));
+  }
+  Ok(dir.join(DB_FILE))
+}
+
+#[tauri::command]
+pub fn list_profiles(app: tauri::AppHandle, state: tauri::State<DbState>) -> Result<Vec<Profile>, String> {
+  let active = active_name(&state, &app);
+  profile_names(&app)
+    .into_iter()
+    .map(|name| {
+      Ok(Profile {
+        active: name == active,
+        path: profile_dir(&app, &name)?.to_string_lossy().to_string(),
+        name,
+      })
+    })
+    .collect()
+}
+
+/// Create an empty profile. It doesn't become active until switched to.
+#[tauri::command]
+pub fn create_profile(app: tauri::AppHandle, name: String) -> Result<Profile, String> {
+  let name = validate_name(&name)?;
+  if profile_names(&app).iter().any(|existing| existing.eq_ignore_ascii_case(&name)) {
+    return Err(format!("Profile {} already exists", name));
+  }
+  let dir = profile_dir(&app, &name)?;
+  init_db(&dir.join(DB_FILE))?;
+  tracing::info!(profile = %name, "Profile created");
+  Ok(Profile { name, active: false, path: dir.to_string_lossy().to_string() })
+}
+
+/// Make `name` the active profile. Commands already running finish against
+/// the old database; everything after uses the new one. Refused while a
+/// background job runs, since its result belongs to the old database. Settings are reloaded
+/// through `config::watch_settings`, by announcing every key either profile
+/// stores as changed, and the services running off the database restart.
+#[tauri::command]
+pub fn switch_profile(app: tauri::AppHandle, state: tauri::State<DbState>, name: String) -> Result<Profile, String> {
+  dev_fixtures::ensure_disabled()?;
+  let name = validate_name(&name)?;
+  let dir = profile_dir(&app, &name)?;
+  let db_path = dir.join(DB_FILE);
+  if name != DEFAULT_PROFILE && !db_path.is_file() {
+    return Err(format!("Profile {} not found", name));
+  }
+
+  // A setting stored in either profile may differ between them; one stored in
+  // neither is at its default in both
+  let changed = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    if jobs::has_running(&state.open().map_err(|e| e.to_string())?)? {
+      return Err("A background job is running; switch profiles once it has finished".to_string());
+    }
+    let mut keys = setting_keys(&state.path())?;
+    // Brings an older profile's schema up to date
+    init_db(&db_path)?;
+    keys.extend(setting_keys(&db_path)?);
+    *state.db_path.write().map_err(|_| "db lock".to_string())? = db_path;
+    keys
+  };
+  fs::write(app_data(&app)?.join(ACTIVE_FILE), &name).map_err(|e| e.to_string())?;
+  tracing::info!(profile = %name, "Switched profile");
+
+  undo::clear(&app);
+  // Listeners run as the event is emitted, so cached settings (permissions
+  // among them) are reloaded before the services below restart
+  config::emit_changed(&app, changed.into_iter().collect());
+  app_lock::init(&app);
+  lan_capture::restore(&app);
+  watch_folders::restore(&app);
+  reminder_scan::wake(&app);
+
+  let profile = Profile { name, active: true, path: dir.to_string_lossy().to_string() };
+  let _ = app.emit("profile-changed", &profile);
+  Ok(profile)
+}
@@ -0,0 +1,119 @@
+// QR codes for moving a captured note or link to a phone.
+//
+// The code is rendered on-device and stored as an image attachment of the
+// event, marked `derived_from = 'qr'` so it can be told apart from captured
+// files. Generating again replaces the previous code.
+
+use qrcode::{Color, EcLevel, QrCode};
+use tauri::Manager;
+
+use crate::{app_lock, cas, generate_id, i18n, now_ms, query_attachments, remove_unused_file, Attachment, DbState};
+
+const DERIVED_KIND: &str = "qr";
+// Pixels per module, and modules of blank border required around the code
+const MODULE_PX: u32 = 8;
+const QUIET_ZONE: u32 = 4;
+
+/// What the code should carry: the link for `link` events, otherwise the
+/// text, falling back to the note and then the title.
+fn payload(conn: &rusqlite::Connection, event_id: &str) -> Result<String, String> {
+  let (event_type, title, note, text, url): (String, Option<String>, Option<String>, Option<String>, Option<String>) = conn
+    .query_row(
+      "SELECT type, title, note, text_content, json_extract(metadata, '$.url')
+       FROM timeline_events WHERE id = ? AND is_deleted = 0",
+      [event_id],
+      |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+    )
+    .map_err(|_| i18n::t("Event not found"))?;
+
+  let link = if event_type == "link" { url } else { None };
+  [link, text, note, title]
+    .into_iter()
+    .flatten()
+    .map(|s| s.trim().to_string())
+    .find(|s| !s.is_empty())
+    .ok_or_else(|| "Event has no text to encode".to_string())
+}
+
+fn render_png(data: &str) -> Result<(Vec<u8>, u32), String> {
+  let code = QrCode::with_error_correction_level(data.as_bytes(), EcLevel::M)
+    .map_err(|_| "Text is too long for a QR code".to_string())?;
+  let modules = code.width() as u32;
+  let colors = code.to_colors();
+  let size = (modules + 2 * QUIET_ZONE) * MODULE_PX;
+
+  let img = image::GrayImage::from_fn(size, size, |x, y| {
+    let (mx, my) = (x / MODULE_PX, y / MODULE_PX);
+    let inside = (QUIET_ZONE..QUIET_ZONE + modules).contains(&mx) && (QUIET_ZONE..QUIET_ZONE + modules).contains(&my);
+    let dark = inside && colors[((my - QUIET_ZONE) * modules + (mx - QUIET_ZONE)) as usize] == Color::Dark;
+    image::Luma([if dark { 0 } else { 255 }])
+  });
+  let mut png = Vec::new();
+  img
+    .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+    .map_err(|e| e.to_string())?;
+  Ok((png, size))
+}
+
+#[tauri::command]
+pub fn generate_qr_for_event(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  event_id: String,
+) -> Result<Attachment, String> {
+  app_lock::ensure_unlocked()?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let (png, size) = render_png(&payload(&conn, &event_id)?)?;
+  let file_name = "qr.png".to_string();
+  let (path_str, sha256) = cas::store_bytes(&conn, &state.data_dir(), &file_name, &png)?;
+
+  // Replace the previous code, file included
+  let app_data = app.path().app_data_dir().map_err(|e| e.to_string())?;
+  for old in query_attachments(&conn, &event_id)?
+    .into_iter()
+    .filter(|a| a.derived_from.as_deref() == Some(DERIVED_KIND))
+  {
+    conn.execute("DELETE FROM attachments WHERE id = ?", [&old.id]).map_err(|e| e.to_string())?;
+    if let Some(stored) = &old.stored_path {
+      remove_unused_file(&conn, &app_data, stored)?;
+    }
+  }
+
+  let attachment = Attachment {
+    id: generate_id(),
+    event_id,
+    kind: "image".to_string(),
+    original_path: path_str.clone(),
+    stored_path: Some(path_str),
+    file_name: Some(file_name),
+    mime_type: Some("image/png".to_string()),
+    size_bytes: Some(png.len() as i64),
+    sha256: Some(sha256),
+    width: Some(size as i32),
+    height: Some(size as i32),
+    created_at: now_ms(),
+    duration_ms: None,
+    derived_from: Some(DERIVED_KIND.to_string()),
+  };
+  conn.execute(
+    "INSERT INTO attachments (id, event_id, kind, original_path, stored_path, file_name, mime_type, size_bytes, sha256, width, height, created_at, derived_from)
+     VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+    rusqlite::params![
+      &attachment.id,
+      &attachment.event_id,
+      &attachment.kind,
+      &attachment.original_path,
+      &attachment.file_name,
+      &attachment.mime_type,
+      attachment.size_bytes,
+      &attachment.sha256,
+      attachment.width,
+      attachment.height,
+      attachment.created_at,
+      &attachment.derived_from,
+    ],
+  ).map_err(|e| e.to_string())?;
+  Ok(attachment)
+}
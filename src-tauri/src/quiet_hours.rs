@@ -0,0 +1,226 @@
+// Quiet hours: times when the pet shouldn't interrupt.
+//
+// Quiet time is either a recurring window from the `reminders.quiet_hours`
+// setting (e.g. 22:00-08:00) or a focus session started by the user. While
+// it lasts the reminder scanner holds due reminders back and wellness nudges
+// are dropped. Held reminders fire together in one `reminders-while-away`
+// event once it's over.
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::{config, now_ms, read_setting, reminder_scan, write_setting, DbState, ReminderDuePayload};
+
+pub const QUIET_HOURS_KEY: &str = "reminders.quiet_hours";
+const FOCUS_UNTIL_KEY: &str = "reminders.focus_until";
+
+// Longest focus session that can be started in one go
+const MAX_FOCUS_MINUTES: i64 = 12 * 60;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct QuietWindow {
+  /// "HH:MM"; a window whose end is before its start runs past midnight
+  start: String,
+  end: String,
+  /// ISO weekdays the window starts on, 1 = Monday .. 7 = Sunday. Empty
+  /// means every day.
+  #[serde(default)]
+  days: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields, default)]
+pub struct QuietHours {
+  enabled: bool,
+  windows: Vec<QuietWindow>,
+}
+
+impl Default for QuietHours {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      windows: vec![QuietWindow { start: "22:00".to_string(), end: "08:00".to_string(), days: vec![] }],
+    }
+  }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietStatus {
+  quiet: bool,
+  /// When the current quiet time ends
+  until: Option<i64>,
+  focus_until: Option<i64>,
+}
+
+/// Reminders held back during quiet time, emitted as `reminders-while-away`.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RemindersWhileAway {
+  quiet_since: i64,
+  reminders: Vec<ReminderDuePayload>,
+}
+
+impl RemindersWhileAway {
+  pub fn new(quiet_since: i64, reminders: Vec<ReminderDuePayload>) -> Self {
+    Self { quiet_since, reminders }
+  }
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+  NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| format!("Invalid time '{}', expected HH:MM", time))
+}
+
+pub fn validate(hours: QuietHours) -> Result<QuietHours, String> {
+  for window in &hours.windows {
+    if parse_time(&window.start)? == parse_time(&window.end)? {
+      return Err("Quiet hours window must not start and end at the same time".to_string());
+    }
+    if window.days.iter().any(|d| !(1..=7).contains(d)) {
+      return Err("Quiet days must be ISO weekdays 1-7".to_string());
+    }
+  }
+  Ok(hours)
+}
+
+fn load_quiet_hours(conn: &rusqlite::Connection) -> QuietHours {
+  read_setting(conn, QUIET_HOURS_KEY)
+    .and_then(|json| {
+      serde_json::from_str(&json)
+        .map_err(|e| e.to_string())
+        .and_then(validate)
+        .map_err(|e| tracing::warn!(error = %e, "Invalid quiet hours"))
+        .ok()
+    })
+    .unwrap_or_default()
+}
+
+fn focus_until(conn: &rusqlite::Connection, now: i64) -> Option<i64> {
+  read_setting(conn, FOCUS_UNTIL_KEY)
+    .and_then(|v| v.parse::<i64>().ok())
+    .filter(|until| *until > now)
+}
+
+fn local_ms(date: chrono::NaiveDate, time: NaiveTime) -> Option<i64> {
+  Local
+    .from_local_datetime(&date.and_time(time))
+    .earliest()
+    .map(|dt| dt.timestamp_millis())
+}
+
+/// End of `window` if `now` falls inside it. Starts from yesterday are
+/// checked too, for windows that run past midnight.
+fn window_end(window: &QuietWindow, now: DateTime<Local>) -> Option<i64> {
+  let start = parse_time(&window.start).ok()?;
+  let end = parse_time(&window.end).ok()?;
+  let now_ms = now.timestamp_millis();
+
+  [1, 0].into_iter().find_map(|days_back| {
+    let day = now.date_naive() - ChronoDuration::days(days_back);
+    if !window.days.is_empty() && !window.days.contains(&day.weekday().number_from_monday()) {
+      return None;
+    }
+    let end_day = if end > start { day } else { day + ChronoDuration::days(1) };
+    let (from, to) = (local_ms(day, start)?, local_ms(end_day, end)?);
+    (from <= now_ms && now_ms < to).then_some(to)
+  })
+}
+
+/// When the current quiet time ends, or `None` if it isn't quiet now.
+pub fn quiet_until(conn: &rusqlite::Connection, now: DateTime<Local>) -> Option<i64> {
+  let hours = load_quiet_hours(conn);
+  let scheduled = hours
+    .enabled
+    .then(|| hours.windows.iter().filter_map(|w| window_end(w, now)).max())
+    .flatten();
+  scheduled.max(focus_until(conn, now.timestamp_millis()))
+}
+
+/// Whether it's quiet right now, opening the DB under its lock.
+pub fn is_quiet(app: &tauri::AppHandle) -> bool {
+  let state = app.state::<DbState>();
+  let Ok(_guard) = state.lock.lock() else { return false };
+  state.open()
+    .ok()
+    .and_then(|conn| quiet_until(&conn, Local::now()))
+    .is_some()
+}
+
+fn status(conn: &rusqlite::Connection) -> QuietStatus {
+  let until = quiet_until(conn, Local::now());
+  QuietStatus {
+    quiet: until.is_some(),
+    until,
+    focus_until: focus_until(conn, now_ms()),
+  }
+}
+
+#[tauri::command]
+pub fn get_quiet_hours(state: tauri::State<DbState>) -> Result<QuietHours, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_quiet_hours(&conn))
+}
+
+#[tauri::command]
+pub fn set_quiet_hours(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  quiet_hours: QuietHours,
+) -> Result<QuietHours, String> {
+  let quiet_hours = validate(quiet_hours)?;
+  let json = serde_json::to_string(&quiet_hours).map_err(|e| e.to_string())?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, QUIET_HOURS_KEY, &json)?;
+  }
+  config::emit_changed(&app, vec![QUIET_HOURS_KEY.to_string()]);
+  Ok(quiet_hours)
+}
+
+#[tauri::command]
+pub fn get_quiet_status(state: tauri::State<DbState>) -> Result<QuietStatus, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(status(&conn))
+}
+
+/// Hold notifications for the next `minutes`.
+#[tauri::command]
+pub fn start_focus_session(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  minutes: i64,
+) -> Result<QuietStatus, String> {
+  if !(1..=MAX_FOCUS_MINUTES).contains(&minutes) {
+    return Err(format!("Focus sessions must be 1-{} minutes", MAX_FOCUS_MINUTES));
+  }
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let (now, until) = (now_ms(), now_ms() + minutes * 60 * 1000);
+  write_setting(&conn, FOCUS_UNTIL_KEY, &until.to_string())?;
+  // Logged for the focus time goal; a new session replaces a running one
+  conn.execute("UPDATE focus_sessions SET ends_at = ?1 WHERE ends_at > ?1", [now]).map_err(|e| e.to_string())?;
+  conn
+    .execute("INSERT INTO focus_sessions (started_at, ends_at) VALUES (?1, ?2)", [now, until])
+    .map_err(|e| e.to_string())?;
+  reminder_scan::wake(&app);
+  Ok(status(&conn))
+}
+
+/// End a focus session early; held reminders fire on the next scan.
+#[tauri::command]
+pub fn end_focus_session(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+) -> Result<QuietStatus, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  write_setting(&conn, FOCUS_UNTIL_KEY, "0")?;
+  conn.execute("UPDATE focus_sessions SET ends_at = ?1 WHERE ends_at > ?1", [now_ms()]).map_err(|e| e.to_string())?;
+  reminder_scan::wake(&app);
+  Ok(status(&conn))
+}
@@ -0,0 +1,144 @@
+// Guards against floods of new events.
+//
+// A webview stuck in a loop, a phone retrying an upload, or a folder watcher
+// fed a few thousand files at once could otherwise create events as fast as
+// the database takes them. Every path that creates events from outside
+// input asks `check` first, with the event's source and the content it would
+// store, and gets an error instead when either check fails:
+//
+//   - each source may create at most so many events within a sliding window
+//     (`LIMITS`, with `DEFAULT_LIMIT` for sources not listed)
+//   - the same content from the same source is refused again for
+//     `DUPLICATE_WINDOW`, which catches a double-fired handler without
+//     getting in the way of writing the same note twice on purpose
+//
+// Errors start with `RATE_LIMITED` ("Rate limited: manual", "Rate limited:
+// duplicate"), which the webview matches on, so the text is never
+// translated. Background callers treat it as "try again later" rather than
+// a failure. Only events that were stored count towards the limits: callers
+// pass what `check` returned to `record` once the insert went through, and
+// hold the database lock across both so two identical requests can't slip
+// past the check together. The counters live in memory and start over with
+// the app.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+pub const RATE_LIMITED: &str = "Rate limited";
+
+// (source, events, window)
+const LIMITS: &[(&str, usize, Duration)] = &[
+  // A folder may legitimately receive a batch of files at once
+  ("watch", 120, Duration::from_secs(60)),
+  ("screenshot", 60, Duration::from_secs(60)),
+  ("mobile", 30, Duration::from_secs(60)),
+];
+const DEFAULT_LIMIT: (usize, Duration) = (20, Duration::from_secs(10));
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// An event `check` let through, for `record` once it's stored.
+pub struct Admission {
+  source: String,
+  key: u64,
+}
+
+#[derive(Default)]
+struct Limiter {
+  /// When each source's recent events were stored, oldest first
+  recent: HashMap<String, VecDeque<Instant>>,
+  /// Content hash to when it was last stored
+  seen: HashMap<u64, Instant>,
+}
+
+fn limiter() -> &'static Mutex<Limiter> {
+  static LIMITER: OnceLock<Mutex<Limiter>> = OnceLock::new();
+  LIMITER.get_or_init(Mutex::default)
+}
+
+fn limit_for(source: &str) -> (usize, Duration) {
+  LIMITS
+    .iter()
+    .find(|(name, _, _)| *name == source)
+    .map(|(_, events, window)| (*events, *window))
+    .unwrap_or(DEFAULT_LIMIT)
+}
+
+/// Whether `error` came from `check`.
+pub fn is_rate_limited(error: &str) -> bool {
+  error.starts_with(RATE_LIMITED)
+}
+
+/// Whether an event from `source` with `content` (whatever identifies what
+/// it stores: text, file paths, image bytes) may be created; `Err` if it would
+/// be one too many or a repeat of one just stored. Nothing is counted yet.
+pub fn check(source: &str, content: impl Hash) -> Result<Admission, String> {
+  let mut hasher = DefaultHasher::new();
+  (source, content).hash(&mut hasher);
+  let key = hasher.finish();
+
+  let now = Instant::now();
+  let (events, window) = limit_for(source);
+  let mut limiter = limiter().lock().map_err(|_| "rate limit lock".to_string())?;
+  limiter.seen.retain(|_, at| now.duration_since(*at) < DUPLICATE_WINDOW);
+  if limiter.seen.contains_key(&key) {
+    tracing::debug!(source, "Duplicate event suppressed");
+    return Err(format!("{}: duplicate", RATE_LIMITED));
+  }
+  let recent = limiter.recent.entry(source.to_string()).or_default();
+  while recent.front().is_some_and(|at| now.duration_since(*at) >= window) {
+    recent.pop_front();
+  }
+  if recent.len() >= events {
+    tracing::warn!(source, events, window_secs = window.as_secs(), "Event rate limit reached");
+    return Err(format!("{}: {}", RATE_LIMITED, source));
+  }
+  Ok(Admission { source: source.to_string(), key })
+}
+
+/// Count the event `admission` was for, now that it's stored.
+pub fn record(admission: Admission) {
+  let now = Instant::now();
+  let Ok(mut limiter) = limiter().lock() else { return };
+  limiter.recent.entry(admission.source).or_default().push_back(now);
+  limiter.seen.insert(admission.key, now);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // The limiter is shared by every test, so each one uses sources of its own
+
+  #[test]
+  fn refuses_a_repeat_only_once_it_was_stored() {
+    let first = check("test-repeat", "Buy milk").unwrap();
+    // Not stored yet, so asking again is fine
+    assert!(check("test-repeat", "Buy milk").is_ok());
+    record(first);
+
+    let error = check("test-repeat", "Buy milk").err().unwrap();
+    assert_eq!(error, format!("{}: duplicate", RATE_LIMITED));
+    assert!(is_rate_limited(&error));
+    assert!(check("test-repeat", "Buy bread").is_ok());
+    assert!(check("test-repeat-other", "Buy milk").is_ok());
+  }
+
+  #[test]
+  fn limits_each_source_on_its_own() {
+    let (events, _) = limit_for("test-flood");
+    for n in 0..events {
+      record(check("test-flood", n).unwrap());
+    }
+    assert_eq!(check("test-flood", events).err(), Some(format!("{}: test-flood", RATE_LIMITED)));
+    assert!(check("test-flood-other", events).is_ok());
+  }
+
+  #[test]
+  fn listed_sources_get_their_own_limits() {
+    assert_eq!(limit_for("watch"), (120, Duration::from_secs(60)));
+    assert_eq!(limit_for("manual"), DEFAULT_LIMIT);
+  }
+}
@@ -0,0 +1,463 @@
+// Receipts and invoices among dropped files.
+//
+// A drop whose file name looks like a receipt gets an extraction pass right
+// away: vendor, date, amount and currency are pulled out with regexes and
+// stored as `metadata.receipt` on the event, which is what
+// `get_expense_summary` adds up per month. The app has no OCR or PDF text
+// extraction, so the regexes see the file names, the note and the content of
+// text-like attachments (plain text, HTML and .eml receipts); for a scanned
+// receipt that usually leaves just the date and a vendor guessed from the
+// file name. `extract_receipt` runs the pass again on demand, optionally
+// through an LLM reading the same text, and also works for events the file
+// name check missed.
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::sync::OnceLock;
+
+use crate::journal::PromptModel;
+use crate::{app_lock, compression, i18n, llm_structured, privacy, timezone, usage_metrics, DbState, LlmRequest};
+
+/// File names that look like a receipt or invoice, case-insensitive. Not
+/// `\b`, so "amazon_invoice_2024" matches too.
+pub const RECEIPT_NAME_PATTERN: &str = r"(?:^|[^a-zA-Z])(invoice|receipt|bill|rechnung|factura|quittung)s?(?:[^a-zA-Z]|$)";
+
+// Text read from a single attachment
+const MAX_ATTACHMENT_TEXT_BYTES: u64 = 256 * 1024;
+const MAX_PROMPT_CHARS: usize = 8_000;
+const TEXT_MIME_TYPES: &[&str] = &["text/plain", "text/html", "message/rfc822", "text/csv", "text/markdown"];
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+  vendor: Option<String>,
+  /// YYYY-MM-DD
+  date: Option<String>,
+  amount: Option<f64>,
+  /// ISO 4217 code
+  currency: Option<String>,
+  /// "regex" or "llm"
+  #[serde(default)]
+  extracted_by: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpenseEntry {
+  event_id: String,
+  title: Option<String>,
+  receipt: Receipt,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyTotal {
+  currency: String,
+  total: f64,
+  count: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpenseSummary {
+  /// YYYY-MM
+  month: String,
+  /// One total per currency; amounts in different currencies aren't added up
+  totals: Vec<CurrencyTotal>,
+  /// Receipts of the month, by date
+  entries: Vec<ExpenseEntry>,
+  /// Receipts without an amount, which the totals leave out
+  missing_amount: usize,
+}
+
+fn name_regex() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| Regex::new(&format!("(?i){}", RECEIPT_NAME_PATTERN)).unwrap())
+}
+
+fn total_regex() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| {
+    Regex::new(
+      r"(?i)\b(?:grand total|total due|amount due|amount paid|total|summe|gesamt|betrag)\b[^\d\n]{0,20}?(?P<cur>[$€£¥]|USD|EUR|GBP|JPY|CHF|CAD|AUD)?\s*(?P<num>\d{1,3}(?:[,.' ]\d{3})*(?:[.,]\d{2})|\d+(?:[.,]\d{2})?)",
+    )
+    .unwrap()
+  })
+}
+
+fn amount_regex() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| {
+    Regex::new(
+      r"(?P<cur>[$€£¥]|\b(?:USD|EUR|GBP|JPY|CHF|CAD|AUD)\b)\s*(?P<num>\d{1,3}(?:[,.' ]\d{3})*(?:[.,]\d{2})|\d+(?:[.,]\d{2})?)",
+    )
+    .unwrap()
+  })
+}
+
+fn iso_date_regex() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})\b").unwrap())
+}
+
+fn dotted_date_regex() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| Regex::new(r"\b(\d{1,2})[./](\d{1,2})[./](\d{4})\b").unwrap())
+}
+
+pub fn looks_like_receipt(file_name: &str) -> bool {
+  name_regex().is_match(file_name)
+}
+
+fn currency_code(symbol: &str) -> String {
+  match symbol {
+    "$" => "USD",
+    "€" => "EUR",
+    "£" => "GBP",
+    "¥" => "JPY",
+    code => code,
+  }
+  .to_uppercase()
+}
+
+/// "1,234.56", "1.234,56" and "1 234,56" all read as 1234.56.
+fn parse_amount(raw: &str) -> Option<f64> {
+  let digits: String = raw.chars().filter(|c| !matches!(c, ' ' | '\'')).collect();
+  let normalized = match (digits.rfind(','), digits.rfind('.')) {
+    // With both separators, whichever comes last is the decimal one
+    (Some(comma), Some(dot)) if comma > dot => digits.replace('.', "").replace(',', "."),
+    (Some(_), Some(_)) => digits.replace(',', ""),
+    (Some(comma), None) if digits.len() - comma == 3 => digits.replace(',', "."),
+    (Some(_), None) => digits.replace(',', ""),
+    (None, Some(dot)) if digits.len() - dot != 3 => digits.replace('.', ""),
+    _ => digits,
+  };
+  normalized.parse().ok().filter(|amount: &f64| *amount > 0.0)
+}
+
+fn find_amount(text: &str) -> Option<(f64, Option<String>)> {
+  let read = |caps: regex::Captures| {
+    let amount = parse_amount(caps.name("num")?.as_str())?;
+    Some((amount, caps.name("cur").map(|c| currency_code(c.as_str()))))
+  };
+  // The last "total" line wins over subtotals above it
+  if let Some(found) = total_regex().captures_iter(text).filter_map(read).last() {
+    return Some(found);
+  }
+  // Otherwise the largest amount with a currency next to it
+  amount_regex().captures_iter(text).filter_map(read).max_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+fn find_date(text: &str) -> Option<String> {
+  if let Some(caps) = iso_date_regex().captures(text) {
+    let (y, m, d) = (caps[1].parse().ok()?, caps[2].parse().ok()?, caps[3].parse().ok()?);
+    return NaiveDate::from_ymd_opt(y, m, d).map(|date| date.format("%Y-%m-%d").to_string());
+  }
+  // Day first unless that can't be right
+  let caps = dotted_date_regex().captures(text)?;
+  let (a, b, y): (u32, u32, i32) = (caps[1].parse().ok()?, caps[2].parse().ok()?, caps[3].parse().ok()?);
+  NaiveDate::from_ymd_opt(y, b, a)
+    .or_else(|| NaiveDate::from_ymd_opt(y, a, b))
+    .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// First line of the text that reads like a name, else the file name
+/// without the receipt words, dates and numbers.
+fn find_vendor(text: &str, file_names: &[String]) -> Option<String> {
+  let from_text = text
+    .lines()
+    .map(str::trim)
+    .find(|line| {
+      line.len() >= 2
+        && line.len() <= 60
+        && line.chars().any(|c| c.is_alphabetic())
+        && !name_regex().is_match(line)
+        && !line.contains(':')
+    })
+    .map(|line| line.to_string());
+  from_text.or_else(|| {
+    file_names.iter().find_map(|name| {
+      let stem = name.rsplit_once('.').map_or(name.as_str(), |(stem, _)| stem);
+      let words: Vec<&str> = stem
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !name_regex().is_match(w) && !w.chars().all(|c| c.is_ascii_digit()))
+        .collect();
+      (!words.is_empty()).then(|| words.join(" "))
+    })
+  })
+}
+
+/// What the extraction pass reads for `event_id`: file names, then the note
+/// and text, then text-like attachments.
+struct ReceiptSource {
+  file_names: Vec<String>,
+  text: String,
+  created_at: i64,
+  locked: bool,
+}
+
+fn load_source(conn: &rusqlite::Connection, event_id: &str) -> Result<ReceiptSource, String> {
+  let (note, text_content, created_at, locked): (Option<String>, Option<String>, i64, bool) = conn
+    .query_row(
+      "SELECT note, text_content, created_at, locked, text_compressed, text_codec
+       FROM timeline_events WHERE id = ? AND is_deleted = 0",
+      [event_id],
+      |row| {
+        let codec: Option<String> = row.get(5)?;
+        let text_content = compression::inflate(row.get(1)?, row.get(4)?, codec.as_deref());
+        Ok((row.get(0)?, text_content, row.get(2)?, row.get::<_, i32>(3)? != 0))
+      },
+    )
+    .map_err(|_| i18n::t("Event not found"))?;
+
+  let attachments: Vec<(Option<String>, Option<String>, String)> = conn
+    .prepare("SELECT file_name, mime_type, COALESCE(stored_path, original_path) FROM attachments WHERE event_id = ?")
+    .map_err(|e| e.to_string())?
+    .query_map([event_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let mut text: Vec<String> = [note, text_content].into_iter().flatten().collect();
+  for (_, mime, path) in &attachments {
+    if !mime.as_deref().is_some_and(|m| TEXT_MIME_TYPES.contains(&m)) {
+      continue;
+    }
+    let mut bytes = Vec::new();
+    if let Ok(file) = File::open(path) {
+      let _ = file.take(MAX_ATTACHMENT_TEXT_BYTES).read_to_end(&mut bytes);
+      text.push(String::from_utf8_lossy(&bytes).into_owned());
+    }
+  }
+  Ok(ReceiptSource {
+    file_names: attachments.into_iter().filter_map(|(name, _, _)| name).collect(),
+    text: text.join("\n"),
+    created_at,
+    locked,
+  })
+}
+
+fn extract_with_regex(source: &ReceiptSource) -> Receipt {
+  let haystack = format!("{}\n{}", source.file_names.join("\n"), source.text);
+  let amount = find_amount(&haystack);
+  Receipt {
+    vendor: find_vendor(&source.text, &source.file_names),
+    date: find_date(&haystack).or_else(|| Some(timezone::date_key(source.created_at)).filter(|d| !d.is_empty())),
+    amount: amount.as_ref().map(|(amount, _)| *amount),
+    currency: amount.and_then(|(_, currency)| currency),
+    extracted_by: "regex".to_string(),
+  }
+}
+
+fn receipt_schema() -> Value {
+  json!({
+    "type": "object",
+    "properties": {
+      "vendor": { "type": ["string", "null"], "maxLength": 80 },
+      "date": { "type": ["string", "null"], "maxLength": 10 },
+      "amount": { "type": ["number", "null"], "minimum": 0 },
+      "currency": { "type": ["string", "null"], "maxLength": 3 },
+    },
+    "required": ["vendor", "date", "amount", "currency"],
+    "additionalProperties": false,
+  })
+}
+
+async fn extract_with_llm(source: &ReceiptSource, llm: PromptModel, fallback: &Receipt) -> Result<Receipt, String> {
+  let text: String = source.text.chars().take(MAX_PROMPT_CHARS).collect();
+  let prompt = format!(
+    "Extract the vendor, date (YYYY-MM-DD), total amount paid and ISO 4217 currency code from this \
+     receipt or invoice. Use null for anything it doesn't say.\n\nFile names: {}\n\n{}",
+    source.file_names.join(", "),
+    text
+  );
+  let request = LlmRequest { provider: llm.provider, api_key: llm.api_key, model: llm.model, prompt, max_tokens: Some(300) };
+  let value = llm_structured::call_structured(&request, &receipt_schema()).await?;
+  let mut receipt: Receipt = serde_json::from_value(value).map_err(|e| e.to_string())?;
+  receipt.date = receipt
+    .date
+    .filter(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok())
+    .or_else(|| fallback.date.clone());
+  receipt.currency = receipt.currency.map(|c| c.to_uppercase());
+  receipt.extracted_by = "llm".to_string();
+  Ok(receipt)
+}
+
+fn store(conn: &rusqlite::Connection, event_id: &str, receipt: &Receipt) -> Result<(), String> {
+  conn
+    .execute(
+      "UPDATE timeline_events SET metadata = json_patch(COALESCE(metadata, '{}'), ?1) WHERE id = ?2",
+      (json!({ "receipt": receipt }).to_string(), event_id),
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Run the regex pass on a new drop if one of its files is named like a
+/// receipt. Failures are logged; they never fail the drop.
+pub fn detect(conn: &rusqlite::Connection, event_id: &str) {
+  let result = load_source(conn, event_id).and_then(|source| {
+    if !source.file_names.iter().any(|name| looks_like_receipt(name)) {
+      return Ok(());
+    }
+    let receipt = extract_with_regex(&source);
+    tracing::debug!(%event_id, found_amount = receipt.amount.is_some(), "Detected a receipt");
+    store(conn, event_id, &receipt)
+  });
+  if let Err(e) = result {
+    tracing::warn!(%event_id, error = %e, "Receipt extraction failed");
+  }
+}
+
+/// Extract receipt details for `event_id` and store them, replacing earlier
+/// ones. With `llm` the model reads the text, falling back to the regexes
+/// when the call fails or the event is opted out of AI.
+#[tauri::command]
+pub async fn extract_receipt(
+  state: tauri::State<'_, DbState>,
+  event_id: String,
+  llm: Option<PromptModel>,
+) -> Result<Receipt, String> {
+  let _timer = usage_metrics::time_async("extract_receipt");
+  let (source, opted_out) = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    (load_source(&conn, &event_id)?, privacy::is_opted_out(&conn, &event_id)?)
+  };
+  if source.locked {
+    return Err(i18n::t("Unlock the event first"));
+  }
+  let mut receipt = extract_with_regex(&source);
+  if let Some(llm) = llm.filter(|_| !opted_out) {
+    match extract_with_llm(&source, llm, &receipt).await {
+      Ok(extracted) => receipt = extracted,
+      Err(e) => tracing::warn!(%event_id, error = %e, "LLM receipt extraction failed, using regexes"),
+    }
+  }
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  store(&conn, &event_id, &receipt)?;
+  Ok(receipt)
+}
+
+/// Receipts dated in `month` (YYYY-MM) and what they add up to.
+#[tauri::command]
+pub fn get_expense_summary(state: tauri::State<DbState>, month: String) -> Result<ExpenseSummary, String> {
+  app_lock::ensure_unlocked()?;
+  NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").map_err(|_| "Month must be YYYY-MM".to_string())?;
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let entries: Vec<ExpenseEntry> = conn
+    .prepare(
+      "SELECT id, title, json_extract(metadata, '$.receipt') FROM timeline_events
+       WHERE is_deleted = 0 AND json_extract(metadata, '$.receipt.date') LIKE ?1 || '-%'
+       ORDER BY json_extract(metadata, '$.receipt.date'), created_at",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([&month], |row| {
+      let receipt: String = row.get(2)?;
+      Ok(ExpenseEntry {
+        event_id: row.get(0)?,
+        title: row.get(1)?,
+        receipt: serde_json::from_str(&receipt).unwrap_or_default(),
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  // Cents, so the totals don't drift
+  let mut totals: BTreeMap<String, (i64, usize)> = BTreeMap::new();
+  let mut missing_amount = 0;
+  for entry in &entries {
+    match entry.receipt.amount {
+      Some(amount) => {
+        let currency = entry.receipt.currency.clone().unwrap_or_else(|| "?".to_string());
+        let total = totals.entry(currency).or_default();
+        total.0 += (amount * 100.0).round() as i64;
+        total.1 += 1;
+      }
+      None => missing_amount += 1,
+    }
+  }
+
+  Ok(ExpenseSummary {
+    month,
+    totals: totals
+      .into_iter()
+      .map(|(currency, (cents, count))| CurrencyTotal { currency, total: cents as f64 / 100.0, count })
+      .collect(),
+    entries,
+    missing_amount,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reads_both_decimal_styles() {
+    assert_eq!(parse_amount("1,234.56"), Some(1234.56));
+    assert_eq!(parse_amount("1.234,56"), Some(1234.56));
+    assert_eq!(parse_amount("1 234,56"), Some(1234.56));
+    assert_eq!(parse_amount("1'234.56"), Some(1234.56));
+    assert_eq!(parse_amount("12,50"), Some(12.5));
+    assert_eq!(parse_amount("12.50"), Some(12.5));
+    // A lone separator followed by three digits groups thousands
+    assert_eq!(parse_amount("1,234"), Some(1234.0));
+    assert_eq!(parse_amount("1.234"), Some(1234.0));
+    assert_eq!(parse_amount("0.00"), None);
+  }
+
+  #[test]
+  fn prefers_the_last_total_line() {
+    let text = "Subtotal $40.00\nTax $3.20\nTotal $43.20\nThank you!";
+    assert_eq!(find_amount(text), Some((43.2, Some("USD".to_string()))));
+    let text = "Summe 10,00\nGesamt: 1.234,56 EUR";
+    assert_eq!(find_amount(text), Some((1234.56, None)));
+    let text = "Amount due: GBP 99.99";
+    assert_eq!(find_amount(text), Some((99.99, Some("GBP".to_string()))));
+  }
+
+  #[test]
+  fn falls_back_to_the_largest_amount_with_a_currency() {
+    let text = "Coffee € 2,00\nCake EUR 12,50\nTable 7";
+    assert_eq!(find_amount(text), Some((12.5, Some("EUR".to_string()))));
+    assert_eq!(find_amount("Order 12345, table 7"), None);
+  }
+
+  #[test]
+  fn finds_dates() {
+    assert_eq!(find_date("Date: 2024-03-05 10:31").as_deref(), Some("2024-03-05"));
+    assert_eq!(find_date("Datum 05.03.2024").as_deref(), Some("2024-03-05"));
+    // Day first unless the day can't be a month
+    assert_eq!(find_date("04/03/2024").as_deref(), Some("2024-03-04"));
+    assert_eq!(find_date("12/31/2024").as_deref(), Some("2024-12-31"));
+    assert_eq!(find_date("2024-02-30"), None);
+  }
+
+  #[test]
+  fn recognizes_receipt_file_names() {
+    assert!(looks_like_receipt("amazon_invoice_2024.pdf"));
+    assert!(looks_like_receipt("Receipts.png"));
+    assert!(looks_like_receipt("Rechnung-0815.pdf"));
+    assert!(!looks_like_receipt("billboard.jpg"));
+    assert!(!looks_like_receipt("holiday.jpg"));
+  }
+
+  #[test]
+  fn guesses_the_vendor() {
+    let text = "Receipt\nBlue Bottle Coffee\nTotal: $4.50";
+    assert_eq!(find_vendor(text, &[]).as_deref(), Some("Blue Bottle Coffee"));
+    let files = vec!["acme_invoice_2024-03.pdf".to_string()];
+    assert_eq!(find_vendor("", &files).as_deref(), Some("acme"));
+  }
+}
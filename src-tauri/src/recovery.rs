@@ -0,0 +1,301 @@
+// Checks the database at launch and recovers from damage instead of failing
+// setup.
+//
+// A database another process keeps locked is waited on and otherwise left
+// alone. A corrupt one is moved aside (`<name>.corrupt-<ms>`, WAL included)
+// and rebuilt: with the `sqlite3` tool's `.recover` when it's installed,
+// otherwise by copying every table that can still be read. If that yields
+// nothing usable, the newest daily backup is restored, and failing that the
+// app starts with an empty database. What happened is kept for
+// `get_startup_recovery` and emitted as `startup-recovery`, since the
+// webview usually isn't listening yet when setup runs.
+//
+// Backups are `VACUUM INTO` snapshots in `backups/` of the profile folder,
+// one per day, the last `BACKUPS_KEPT` kept.
+
+use chrono::Local;
+use rusqlite::{Connection, ErrorCode};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::{media, now_ms};
+
+const BACKUP_DIR: &str = "backups";
+const BACKUPS_KEPT: usize = 7;
+// How long to wait for another process to release the database
+const LOCK_WAIT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryReport {
+  /// "recovered", "restoredBackup", "reset", "locked" or "failed"
+  outcome: String,
+  /// What was wrong with the database
+  problem: String,
+  /// Where the damaged file was moved
+  damaged_copy: Option<String>,
+  /// Backup restored, for "restoredBackup"
+  backup: Option<String>,
+  /// Timeline events recovered from the damaged file
+  recovered_events: Option<usize>,
+  /// Tables that couldn't be read back, when recovering without `sqlite3`
+  lost_tables: Vec<String>,
+  at: i64,
+}
+
+impl RecoveryReport {
+  fn new(outcome: &str, problem: &str) -> Self {
+    RecoveryReport {
+      outcome: outcome.to_string(),
+      problem: problem.to_string(),
+      damaged_copy: None,
+      backup: None,
+      recovered_events: None,
+      lost_tables: Vec::new(),
+      at: now_ms(),
+    }
+  }
+
+  /// `init_db` failed even after the checks.
+  pub fn failed(problem: &str) -> Self {
+    Self::new("failed", problem)
+  }
+}
+
+pub struct RecoveryState {
+  report: Mutex<Option<RecoveryReport>>,
+}
+
+impl RecoveryState {
+  pub fn new(report: Option<RecoveryReport>) -> Self {
+    RecoveryState { report: Mutex::new(report) }
+  }
+}
+
+enum Health {
+  Ok,
+  Locked(String),
+  Corrupt(String),
+  /// Can't be opened for some other reason (permissions, disk); not ours
+  /// to touch
+  Unreadable(String),
+}
+
+fn classify(e: rusqlite::Error) -> Health {
+  match e.sqlite_error_code() {
+    Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => Health::Locked(e.to_string()),
+    Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) => Health::Corrupt(e.to_string()),
+    _ => Health::Unreadable(e.to_string()),
+  }
+}
+
+fn check(db_path: &Path) -> Health {
+  let conn = match Connection::open(db_path) {
+    Ok(conn) => conn,
+    Err(e) => return classify(e),
+  };
+  let _ = conn.busy_timeout(LOCK_WAIT);
+  match conn.query_row("PRAGMA quick_check", [], |row| row.get::<_, String>(0)) {
+    Ok(result) if result == "ok" => Health::Ok,
+    Ok(result) => Health::Corrupt(result),
+    Err(e) => classify(e),
+  }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+  PathBuf::from(format!("{}{}", path.display(), suffix))
+}
+
+/// Move the database and its WAL/SHM files aside, keeping them paired.
+fn move_aside(db_path: &Path) -> Result<PathBuf, String> {
+  let target = with_suffix(db_path, &format!(".corrupt-{}", now_ms()));
+  fs::rename(db_path, &target).map_err(|e| format!("Could not move the damaged database: {}", e))?;
+  for suffix in ["-wal", "-shm"] {
+    let side = with_suffix(db_path, suffix);
+    if side.exists() {
+      let _ = fs::rename(&side, with_suffix(&target, suffix));
+    }
+  }
+  Ok(target)
+}
+
+fn recover_with_cli(damaged: &Path, fresh: &Path) -> Result<usize, String> {
+  let output = media::tool_command("sqlite3")
+    .stderr(Stdio::piped())
+    .arg(damaged)
+    .arg(".recover")
+    .output()
+    .map_err(|e| e.to_string())?;
+  if !output.status.success() {
+    return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+  }
+  let conn = Connection::open(fresh).map_err(|e| e.to_string())?;
+  // Rows come back in no particular order, and some may have lost their parent
+  conn.execute_batch("PRAGMA foreign_keys = OFF").map_err(|e| e.to_string())?;
+  conn.execute_batch(&String::from_utf8_lossy(&output.stdout)).map_err(|e| e.to_string())?;
+  Ok(event_count(&conn))
+}
+
+/// Copy what can still be read, table by table. Returns the events
+/// recovered and the tables that were lost.
+fn salvage(damaged: &Path, fresh: &Path) -> Result<(usize, Vec<String>), String> {
+  let conn = Connection::open(fresh).map_err(|e| e.to_string())?;
+  // A table whose parent rows were lost is still worth having
+  conn.execute_batch("PRAGMA foreign_keys = OFF").map_err(|e| e.to_string())?;
+  conn
+    .execute("ATTACH DATABASE ?1 AS damaged", [damaged.to_string_lossy().as_ref()])
+    .map_err(|e| e.to_string())?;
+  let tables: Vec<(String, String)> = conn
+    .prepare(
+      "SELECT name, sql FROM damaged.sqlite_master
+       WHERE type = 'table' AND sql IS NOT NULL AND name NOT LIKE 'sqlite_%'",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let mut lost = Vec::new();
+  for (name, sql) in tables {
+    let copied = conn.execute_batch(&sql).and_then(|_| {
+      conn.execute(
+        &format!("INSERT OR IGNORE INTO main.\"{0}\" SELECT * FROM damaged.\"{0}\"", name.replace('"', "\"\"")),
+        [],
+      )
+    });
+    if let Err(e) = copied {
+      tracing::warn!(table = %name, error = %e, "Table could not be recovered");
+      lost.push(name);
+    }
+  }
+  let _ = conn.execute("DETACH DATABASE damaged", []);
+  Ok((event_count(&conn), lost))
+}
+
+fn event_count(conn: &Connection) -> usize {
+  conn
+    .query_row("SELECT COUNT(*) FROM timeline_events", [], |row| row.get::<_, i64>(0))
+    .map(|n| n as usize)
+    .unwrap_or(0)
+}
+
+fn backup_dir(db_path: &Path) -> PathBuf {
+  db_path.parent().unwrap_or(Path::new(".")).join(BACKUP_DIR)
+}
+
+/// Backups sorted oldest first; the date in the name sorts by time.
+fn backups(db_path: &Path) -> Vec<PathBuf> {
+  let mut files: Vec<PathBuf> = fs::read_dir(backup_dir(db_path))
+    .into_iter()
+    .flatten()
+    .flatten()
+    .map(|entry| entry.path())
+    .filter(|path| path.extension().is_some_and(|ext| ext == "sqlite"))
+    .collect();
+  files.sort();
+  files
+}
+
+fn recover(db_path: &Path, problem: &str) -> RecoveryReport {
+  let damaged = match move_aside(db_path) {
+    Ok(path) => path,
+    Err(e) => {
+      tracing::error!(error = %e, "Database recovery failed");
+      return RecoveryReport::failed(&format!("{}; {}", problem, e));
+    }
+  };
+  let mut report = RecoveryReport::new("recovered", problem);
+  report.damaged_copy = Some(damaged.to_string_lossy().to_string());
+
+  let rebuilt = match recover_with_cli(&damaged, db_path) {
+    Ok(events) => Some(events),
+    Err(e) => {
+      tracing::info!(error = %e, "sqlite3 .recover unavailable, copying readable tables");
+      let _ = fs::remove_file(db_path);
+      salvage(&damaged, db_path)
+        .map(|(events, lost)| {
+          report.lost_tables = lost;
+          events
+        })
+        .ok()
+    }
+  };
+  // Anything at all is better than an older backup; with nothing, try one
+  if let Some(events) = rebuilt.filter(|n| *n > 0 && matches!(check(db_path), Health::Ok)) {
+    report.recovered_events = Some(events);
+    return report;
+  }
+
+  let _ = fs::remove_file(db_path);
+  for backup in backups(db_path).into_iter().rev() {
+    if matches!(check(&backup), Health::Ok) && fs::copy(&backup, db_path).is_ok() {
+      report.outcome = "restoredBackup".to_string();
+      report.backup = Some(backup.to_string_lossy().to_string());
+      return report;
+    }
+  }
+  let _ = fs::remove_file(db_path);
+  report.outcome = "reset".to_string();
+  report
+}
+
+/// Check the database before `init_db` opens it and repair it if needed.
+/// `None` when it was healthy (or doesn't exist yet).
+pub fn check_and_recover(db_path: &Path) -> Option<RecoveryReport> {
+  if !db_path.exists() {
+    return None;
+  }
+  let report = match check(db_path) {
+    Health::Ok => return None,
+    Health::Locked(problem) => RecoveryReport::new("locked", &problem),
+    Health::Unreadable(problem) => RecoveryReport::failed(&problem),
+    Health::Corrupt(problem) => recover(db_path, &problem),
+  };
+  tracing::warn!(outcome = %report.outcome, problem = %report.problem, "Database needed recovery at startup");
+  Some(report)
+}
+
+/// Take today's backup if there isn't one yet and prune old ones. Runs on
+/// its own thread; a large database takes a moment to copy.
+pub fn spawn_daily_backup(db_path: PathBuf) {
+  std::thread::spawn(move || {
+    let dir = backup_dir(&db_path);
+    let target = dir.join(format!("papa_pet-{}.sqlite", Local::now().format("%Y-%m-%d")));
+    if target.exists() {
+      return;
+    }
+    let result = fs::create_dir_all(&dir).map_err(|e| e.to_string()).and_then(|_| {
+      let conn = Connection::open(&db_path).map_err(|e| e.to_string())?;
+      conn
+        .execute("VACUUM INTO ?1", [target.to_string_lossy().as_ref()])
+        .map_err(|e| e.to_string())
+    });
+    match result {
+      Ok(_) => {
+        let all = backups(&db_path);
+        for old in all.iter().take(all.len().saturating_sub(BACKUPS_KEPT)) {
+          let _ = fs::remove_file(old);
+        }
+        tracing::info!(path = %target.display(), "Database backed up");
+      }
+      Err(e) => tracing::warn!(error = %e, "Database backup failed"),
+    }
+  });
+}
+
+/// Emit the report for a webview that's already listening.
+pub fn announce(app: &tauri::AppHandle, report: &RecoveryReport) {
+  let _ = app.emit("startup-recovery", report);
+}
+
+/// What the startup check had to do, `None` when the database was fine.
+#[tauri::command]
+pub fn get_startup_recovery(recovery: tauri::State<RecoveryState>) -> Result<Option<RecoveryReport>, String> {
+  Ok(recovery.report.lock().map_err(|_| "recovery lock".to_string())?.clone())
+}
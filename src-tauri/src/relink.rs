@@ -0,0 +1,308 @@
+// Finding attachments whose original file has moved.
+//
+// `verify_attachments` checks every attachment's `original_path`. For each
+// one that's gone it searches the folders listed in
+// `attachments.search_folders` for a file of the same size and, when the
+// attachment has one, the same sha256; without a hash the file name has to
+// match as well. A single match is re-linked automatically, several are left
+// for the user to pick with `relink_attachment`. Every move is logged in
+// `attachment_relocations`.
+//
+// The search walks the folders without holding the db lock and only hashes
+// files whose size matches.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+use crate::{hash_file, now_ms, read_setting, usage_metrics, write_setting, DbState};
+
+pub const SEARCH_FOLDERS_KEY: &str = "attachments.search_folders";
+
+const MAX_DEPTH: usize = 8;
+// Stop walking after this many files, so a search folder set to a whole
+// disk can't run for hours
+const MAX_FILES: usize = 200_000;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Relocation {
+  attachment_id: String,
+  old_path: String,
+  new_path: String,
+  /// "auto" or "manual"
+  method: String,
+  created_at: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingAttachment {
+  attachment_id: String,
+  event_id: String,
+  original_path: String,
+  file_name: Option<String>,
+  /// Whether app storage still has a copy
+  has_stored_copy: bool,
+  /// Files in the search folders that could be it, when more than one
+  candidates: Vec<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+  checked: usize,
+  missing: Vec<MissingAttachment>,
+  relinked: Vec<Relocation>,
+}
+
+struct Row {
+  id: String,
+  event_id: String,
+  original_path: String,
+  stored_path: Option<String>,
+  file_name: Option<String>,
+  size_bytes: Option<i64>,
+  sha256: Option<String>,
+}
+
+fn load_folders(conn: &rusqlite::Connection) -> Vec<String> {
+  read_setting(conn, SEARCH_FOLDERS_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn save_folders(conn: &rusqlite::Connection, folders: &[String]) -> Result<(), String> {
+  let json = serde_json::to_string(folders).map_err(|e| e.to_string())?;
+  write_setting(conn, SEARCH_FOLDERS_KEY, &json)
+}
+
+/// Files under `folders` grouped by size.
+fn index_by_size(folders: &[String]) -> HashMap<u64, Vec<PathBuf>> {
+  let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+  let mut seen = 0;
+  let mut stack: Vec<(PathBuf, usize)> = folders.iter().map(|f| (PathBuf::from(f), 0)).collect();
+  while let Some((dir, depth)) = stack.pop() {
+    let Ok(entries) = fs::read_dir(&dir) else { continue };
+    for entry in entries.flatten() {
+      let Ok(file_type) = entry.file_type() else { continue };
+      if file_type.is_dir() && depth < MAX_DEPTH {
+        stack.push((entry.path(), depth + 1));
+      } else if file_type.is_file() {
+        if let Ok(meta) = entry.metadata() {
+          by_size.entry(meta.len()).or_default().push(entry.path());
+        }
+        seen += 1;
+        if seen >= MAX_FILES {
+          tracing::warn!(files = seen, "Attachment search stopped early");
+          return by_size;
+        }
+      }
+    }
+  }
+  by_size
+}
+
+/// Files in the index that are the attachment's content.
+fn find_matches(row: &Row, by_size: &HashMap<u64, Vec<PathBuf>>) -> Vec<PathBuf> {
+  let Some(size) = row.size_bytes else { return Vec::new() };
+  let Some(same_size) = by_size.get(&(size as u64)) else { return Vec::new() };
+  same_size
+    .iter()
+    .filter(|path| match &row.sha256 {
+      Some(sha256) => hash_file(path).is_ok_and(|hash| &hash == sha256),
+      None => {
+        let name = Path::new(&row.original_path).file_name();
+        name.is_some() && path.file_name() == name
+      }
+    })
+    .cloned()
+    .collect()
+}
+
+fn record(
+  conn: &rusqlite::Connection,
+  attachment_id: &str,
+  old_path: &str,
+  new_path: &str,
+  method: &str,
+) -> Result<Relocation, String> {
+  let created_at = now_ms();
+  conn
+    .execute("UPDATE attachments SET original_path = ?1 WHERE id = ?2", (new_path, attachment_id))
+    .map_err(|e| e.to_string())?;
+  conn
+    .execute(
+      "INSERT INTO attachment_relocations (attachment_id, old_path, new_path, method, created_at)
+       VALUES (?1, ?2, ?3, ?4, ?5)",
+      (attachment_id, old_path, new_path, method, created_at),
+    )
+    .map_err(|e| e.to_string())?;
+  tracing::info!(%attachment_id, %old_path, %new_path, %method, "Attachment re-linked");
+  Ok(Relocation {
+    attachment_id: attachment_id.to_string(),
+    old_path: old_path.to_string(),
+    new_path: new_path.to_string(),
+    method: method.to_string(),
+    created_at,
+  })
+}
+
+fn verify(app: &tauri::AppHandle) -> Result<VerifyReport, String> {
+  let state = app.state::<DbState>();
+  let (rows, folders) = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let rows: Vec<Row> = conn
+      .prepare("SELECT id, event_id, original_path, stored_path, file_name, size_bytes, sha256 FROM attachments")
+      .map_err(|e| e.to_string())?
+      .query_map([], |row| {
+        Ok(Row {
+          id: row.get(0)?,
+          event_id: row.get(1)?,
+          original_path: row.get(2)?,
+          stored_path: row.get(3)?,
+          file_name: row.get(4)?,
+          size_bytes: row.get(5)?,
+          sha256: row.get(6)?,
+        })
+      })
+      .map_err(|e| e.to_string())?
+      .filter_map(|r| r.ok())
+      .collect();
+    (rows, load_folders(&conn))
+  };
+
+  let checked = rows.len();
+  let missing: Vec<Row> = rows.into_iter().filter(|row| !Path::new(&row.original_path).is_file()).collect();
+  let by_size = if missing.is_empty() || folders.is_empty() { HashMap::new() } else { index_by_size(&folders) };
+  let found: Vec<(Row, Vec<PathBuf>)> = missing
+    .into_iter()
+    .map(|row| {
+      let matches = find_matches(&row, &by_size);
+      (row, matches)
+    })
+    .collect();
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let mut report = VerifyReport { checked, missing: Vec::new(), relinked: Vec::new() };
+  for (row, matches) in found {
+    if let [only] = matches.as_slice() {
+      report.relinked.push(record(&conn, &row.id, &row.original_path, &only.to_string_lossy(), "auto")?);
+      continue;
+    }
+    report.missing.push(MissingAttachment {
+      has_stored_copy: row.stored_path.as_deref().is_some_and(|p| Path::new(p).is_file()),
+      attachment_id: row.id,
+      event_id: row.event_id,
+      original_path: row.original_path,
+      file_name: row.file_name,
+      candidates: matches.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+    });
+  }
+  Ok(report)
+}
+
+/// Check every attachment's original file, re-linking the ones found in the
+/// search folders. Returns what's still missing.
+#[tauri::command]
+pub async fn verify_attachments(app: tauri::AppHandle) -> Result<VerifyReport, String> {
+  let _timer = usage_metrics::time_async("verify_attachments");
+  tokio::task::spawn_blocking(move || verify(&app)).await.map_err(|e| e.to_string())?
+}
+
+/// Point an attachment at `new_path`. When the attachment's size or hash is
+/// known the file has to match it.
+#[tauri::command]
+pub fn relink_attachment(
+  state: tauri::State<DbState>,
+  attachment_id: String,
+  new_path: String,
+) -> Result<Relocation, String> {
+  let canonical = fs::canonicalize(&new_path).map_err(|_| format!("File not found: {}", new_path))?;
+  if !canonical.is_file() {
+    return Err(format!("Not a file: {}", new_path));
+  }
+  let size = fs::metadata(&canonical).map_err(|e| e.to_string())?.len();
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let (old_path, size_bytes, sha256): (String, Option<i64>, Option<String>) = conn
+    .query_row(
+      "SELECT original_path, size_bytes, sha256 FROM attachments WHERE id = ?",
+      [&attachment_id],
+      |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .map_err(|_| "Attachment not found".to_string())?;
+  let different = size_bytes.is_some_and(|expected| expected as u64 != size)
+    || sha256.is_some_and(|expected| hash_file(&canonical).is_ok_and(|hash| hash != expected));
+  if different {
+    return Err(format!("{} is a different file", new_path));
+  }
+  record(&conn, &attachment_id, &old_path, &canonical.to_string_lossy(), "manual")
+}
+
+/// Relocations, newest first.
+#[tauri::command]
+pub fn list_attachment_relocations(state: tauri::State<DbState>) -> Result<Vec<Relocation>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let relocations = conn
+    .prepare(
+      "SELECT attachment_id, old_path, new_path, method, created_at
+       FROM attachment_relocations ORDER BY created_at DESC, id DESC",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| {
+      Ok(Relocation {
+        attachment_id: row.get(0)?,
+        old_path: row.get(1)?,
+        new_path: row.get(2)?,
+        method: row.get(3)?,
+        created_at: row.get(4)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(relocations)
+}
+
+#[tauri::command]
+pub fn list_relink_folders(state: tauri::State<DbState>) -> Result<Vec<String>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_folders(&conn))
+}
+
+/// Add a folder to search for moved files. It's stored in canonical form.
+#[tauri::command]
+pub fn add_relink_folder(state: tauri::State<DbState>, path: String) -> Result<Vec<String>, String> {
+  let canonical = fs::canonicalize(&path).map_err(|_| format!("Folder not found: {}", path))?;
+  if !canonical.is_dir() {
+    return Err(format!("Not a folder: {}", path));
+  }
+  let canonical = canonical.to_string_lossy().to_string();
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let mut folders = load_folders(&conn);
+  if !folders.contains(&canonical) {
+    folders.push(canonical);
+    save_folders(&conn, &folders)?;
+  }
+  Ok(folders)
+}
+
+#[tauri::command]
+pub fn remove_relink_folder(state: tauri::State<DbState>, path: String) -> Result<Vec<String>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let mut folders = load_folders(&conn);
+  folders.retain(|folder| folder != &path);
+  save_folders(&conn, &folders)?;
+  Ok(folders)
+}
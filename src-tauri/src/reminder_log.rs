@@ -0,0 +1,94 @@
+// History of reminder status changes, for working out why a reminder did or
+// didn't fire.
+//
+// Every status change goes into `reminder_log` with the status before and
+// after and a short reason: "due" and "snooze over" when the scanner fires a
+// reminder, "snoozed" and "dismissed" from the user, "event deleted" and
+// "event restored" from deleting an event and undoing it (the scanner cancels
+// a deleted event's reminders the same way if it still finds any), "event
+// archived" and "event missing" when the event has left the timeline. The
+// scanner also leaves a "changed while due" entry when a reminder it was
+// about to fire was snoozed, dismissed or edited in the meantime; it looks at
+// it again on the next scan instead. Entries are kept for `KEEP_DAYS`.
+
+use serde::Serialize;
+
+use crate::{now_ms, DbState};
+
+const KEEP_DAYS: i64 = 90;
+const DEFAULT_LIMIT: u32 = 200;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderLogEntry {
+  id: i64,
+  reminder_id: String,
+  event_id: Option<String>,
+  from_status: Option<String>,
+  to_status: String,
+  reason: String,
+  created_at: i64,
+}
+
+/// Log a status change of `reminder_id`. `from` and `to` default to its
+/// current status, so call this before the update when `from` is left out
+/// and after it when `to` is. Changing a reminder to the status it already
+/// has isn't logged.
+pub fn record(
+  conn: &rusqlite::Connection,
+  reminder_id: &str,
+  from: Option<&str>,
+  to: Option<&str>,
+  reason: &str,
+) -> Result<(), String> {
+  conn
+    .execute(
+      "INSERT INTO reminder_log (reminder_id, event_id, from_status, to_status, reason, created_at)
+       SELECT id, event_id, COALESCE(?2, status), COALESCE(?3, status), ?4, ?5 FROM reminders
+       WHERE id = ?1 AND NOT (?2 IS NULL AND status IS ?3)",
+      (reminder_id, from, to, reason, now_ms()),
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Drop entries older than `KEEP_DAYS`.
+pub fn prune(conn: &rusqlite::Connection) -> Result<(), String> {
+  conn
+    .execute("DELETE FROM reminder_log WHERE created_at < ?", [now_ms() - KEEP_DAYS * 24 * 60 * 60 * 1000])
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Newest entries first, for one reminder or all of them.
+#[tauri::command]
+pub fn get_reminder_log(
+  state: tauri::State<DbState>,
+  reminder_id: Option<String>,
+  limit: Option<u32>,
+) -> Result<Vec<ReminderLogEntry>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let mut stmt = conn
+    .prepare(
+      "SELECT id, reminder_id, event_id, from_status, to_status, reason, created_at FROM reminder_log
+       WHERE ?1 IS NULL OR reminder_id = ?1
+       ORDER BY created_at DESC, id DESC
+       LIMIT ?2",
+    )
+    .map_err(|e| e.to_string())?;
+  let rows = stmt
+    .query_map((&reminder_id, limit.unwrap_or(DEFAULT_LIMIT)), |row| {
+      Ok(ReminderLogEntry {
+        id: row.get(0)?,
+        reminder_id: row.get(1)?,
+        event_id: row.get(2)?,
+        from_status: row.get(3)?,
+        to_status: row.get(4)?,
+        reason: row.get(5)?,
+        created_at: row.get(6)?,
+      })
+    })
+    .map_err(|e| e.to_string())?;
+  rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
@@ -0,0 +1,78 @@
+// Wake-ups for the reminder scanner.
+//
+// The scanner sleeps until the next pending reminder, snooze or plan is due
+// instead of polling on a fixed interval. Anything that adds or moves a
+// reminder calls `wake` so the scanner recomputes that time right away.
+// `notify_one` keeps a permit when nobody is waiting, so a change made while
+// a scan is running isn't lost.
+
+use chrono::Local;
+use std::path::Path;
+use std::time::Duration;
+use tauri::Manager;
+use tokio::sync::Notify;
+
+use crate::{now_ms, open_db, quiet_hours};
+
+// Upper bound on a single sleep, so clock changes and rows written outside
+// these commands are still picked up
+const MAX_SLEEP: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+pub struct ReminderScanState {
+  notify: Notify,
+}
+
+impl ReminderScanState {
+  pub fn wake(&self) {
+    self.notify.notify_one();
+  }
+}
+
+pub fn wake(app: &tauri::AppHandle) {
+  app.state::<ReminderScanState>().wake();
+}
+
+/// Earliest time after `after` that anything the scanner handles becomes
+/// due. Items due earlier were already seen by the last scan.
+fn next_due(conn: &rusqlite::Connection, after: i64) -> Option<i64> {
+  conn
+    .query_row(
+      "SELECT MIN(due) FROM (
+         SELECT MIN(CASE status WHEN 'pending' THEN remind_at ELSE snooze_until END) AS due FROM reminders
+         WHERE ((status = 'pending' AND remind_at > ?1) OR (status = 'snoozed' AND snooze_until > ?1))
+           AND NOT EXISTS (SELECT 1 FROM timeline_events e WHERE e.id = reminders.event_id AND e.is_deleted = 1)
+         UNION ALL
+         SELECT MIN(scheduled_for) FROM timeline_events
+         WHERE scheduled_for > ?1 AND is_deleted = 0
+       )",
+      [after],
+      |row| row.get(0),
+    )
+    .ok()
+    .flatten()
+}
+
+/// Sleep until the next reminder after `last_scan` is due, `MAX_SLEEP`
+/// passes or `wake` is called, whichever comes first.
+pub async fn wait_for_next(app: &tauri::AppHandle, db_path: &Path, last_scan: i64) {
+  let sleep = open_db(db_path)
+    .ok()
+    .and_then(|conn| {
+      // Held reminders fire when quiet time ends
+      let quiet_end = quiet_hours::quiet_until(&conn, Local::now());
+      next_due(&conn, last_scan).into_iter().chain(quiet_end).min()
+    })
+    .map(|due| Duration::from_millis((due - now_ms()).max(0) as u64))
+    .map_or(MAX_SLEEP, |until_due| until_due.min(MAX_SLEEP));
+
+  let state = app.state::<ReminderScanState>();
+  let _ = tokio::time::timeout(sleep, state.notify.notified()).await;
+}
+
+/// Run a reminder scan now instead of waiting for the next due time.
+#[tauri::command]
+pub fn trigger_reminder_scan(scan: tauri::State<ReminderScanState>) {
+  tracing::debug!("Reminder scan requested");
+  scan.wake();
+}
@@ -0,0 +1,170 @@
+// Reminder history and response statistics.
+//
+// Reminders are bucketed by `remind_at`. A reminder counts as "dismissed on
+// time" when it was dismissed without ever being snoozed; `snooze_count`
+// records how long each snooze chain got.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::{app_lock, behavior, i18n, now_ms, timezone, DbState};
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderStats {
+  pub total: i64,
+  /// Fired and still waiting for a response
+  pub triggered: i64,
+  pub dismissed_on_time: i64,
+  pub dismissed_after_snooze: i64,
+  /// Snoozed at least once, whatever happened afterwards
+  pub snoozed: i64,
+  pub pending: i64,
+  /// Average number of snoozes among reminders that were snoozed
+  pub avg_snooze_chain: Option<f64>,
+  /// Average time from `remind_at` to dismissal
+  pub avg_response_ms: Option<f64>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderHistoryEntry {
+  id: String,
+  event_id: String,
+  event_title: Option<String>,
+  message: String,
+  status: String,
+  remind_at: i64,
+  triggered_at: Option<i64>,
+  dismissed_at: Option<i64>,
+  snooze_count: i64,
+  created_at: i64,
+}
+
+/// Start of a period ("today", "week", "month", "all") in unix ms.
+pub fn period_start_ms(period: &str) -> Result<Option<i64>, String> {
+  let Some(key) = behavior::period_start_key(period)? else { return Ok(None) };
+  let date = NaiveDate::parse_from_str(&key, "%Y-%m-%d").map_err(|e| e.to_string())?;
+  timezone::day_start(date).map(Some).ok_or_else(|| "Invalid local time".to_string())
+}
+
+pub fn query_reminder_stats(
+  conn: &rusqlite::Connection,
+  start_ms: Option<i64>,
+  end_ms: Option<i64>,
+) -> Result<ReminderStats, String> {
+  conn
+    .query_row(
+      "SELECT
+         COUNT(*),
+         COALESCE(SUM(status = 'triggered'), 0),
+         COALESCE(SUM(status = 'dismissed' AND snooze_count = 0), 0),
+         COALESCE(SUM(status = 'dismissed' AND snooze_count > 0), 0),
+         COALESCE(SUM(snooze_count > 0), 0),
+         COALESCE(SUM(status IN ('pending', 'snoozed')), 0),
+         AVG(CASE WHEN snooze_count > 0 THEN snooze_count END),
+         AVG(CASE WHEN dismissed_at IS NOT NULL THEN MAX(dismissed_at - remind_at, 0) END)
+       FROM reminders
+       WHERE (?1 IS NULL OR remind_at >= ?1) AND (?2 IS NULL OR remind_at <= ?2)",
+      (start_ms, end_ms),
+      |row| {
+        Ok(ReminderStats {
+          total: row.get(0)?,
+          triggered: row.get(1)?,
+          dismissed_on_time: row.get(2)?,
+          dismissed_after_snooze: row.get(3)?,
+          snoozed: row.get(4)?,
+          pending: row.get(5)?,
+          avg_snooze_chain: row.get(6)?,
+          avg_response_ms: row.get(7)?,
+        })
+      },
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Markdown bullet list used by the daily export.
+pub fn format_stats(stats: &ReminderStats) -> String {
+  let mut out = format!(
+    "{}\n",
+    i18n::tf(
+      "- {total} reminders: {on_time} dismissed on time, {after_snooze} after snoozing, {open} still open",
+      &[
+        ("total", stats.total.to_string()),
+        ("on_time", stats.dismissed_on_time.to_string()),
+        ("after_snooze", stats.dismissed_after_snooze.to_string()),
+        ("open", (stats.triggered + stats.pending).to_string()),
+      ]
+    )
+  );
+  if let Some(chain) = stats.avg_snooze_chain {
+    out.push_str(&format!(
+      "{}\n",
+      i18n::tf(
+        "- Snoozed {count} times, {average} snoozes on average",
+        &[("count", stats.snoozed.to_string()), ("average", format!("{:.1}", chain))]
+      )
+    ));
+  }
+  if let Some(ms) = stats.avg_response_ms {
+    let duration = behavior::format_duration(ms / 1000.0);
+    out.push_str(&format!("{}\n", i18n::tf("- Average response time: {duration}", &[("duration", duration)])));
+  }
+  out
+}
+
+/// Reminders due in a period, newest first, with how they were handled.
+#[tauri::command]
+pub fn list_reminder_history(
+  state: tauri::State<DbState>,
+  period: String,
+) -> Result<Vec<ReminderHistoryEntry>, String> {
+  app_lock::ensure_unlocked()?;
+  let start_ms = period_start_ms(&period)?;
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let history = conn
+    .prepare(
+      "SELECT r.id, r.event_id, e.title, r.message, r.status, r.remind_at, r.triggered_at,
+              r.dismissed_at, r.snooze_count, r.created_at
+       FROM reminders r
+       LEFT JOIN timeline_events e ON e.id = r.event_id
+       WHERE (?1 IS NULL OR r.remind_at >= ?1) AND r.remind_at <= ?2
+       ORDER BY r.remind_at DESC"
+    )
+    .map_err(|e| e.to_string())?
+    .query_map((start_ms, now_ms()), |row| {
+      Ok(ReminderHistoryEntry {
+        id: row.get(0)?,
+        event_id: row.get(1)?,
+        event_title: row.get(2)?,
+        message: row.get(3)?,
+        status: row.get(4)?,
+        remind_at: row.get(5)?,
+        triggered_at: row.get(6)?,
+        dismissed_at: row.get(7)?,
+        snooze_count: row.get(8)?,
+        created_at: row.get(9)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  Ok(history)
+}
+
+#[tauri::command]
+pub fn get_reminder_stats(
+  state: tauri::State<DbState>,
+  period: String,
+) -> Result<ReminderStats, String> {
+  app_lock::ensure_unlocked()?;
+  let start_ms = period_start_ms(&period)?;
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  query_reminder_stats(&conn, start_ms, Some(now_ms()))
+}
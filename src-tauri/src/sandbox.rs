@@ -0,0 +1,206 @@
+// Path checks for commands that take file names or paths from the webview.
+//
+// File names written into app storage are reduced to a single safe path
+// component. Reads are limited to the active profile's data dir, roots the
+// user approved (kept in the `sandbox.allowed_roots` setting) and files the
+// user dropped onto a window this session. Drops are taken from the native
+// drag-drop event, not from the webview, so a path can't be smuggled in by
+// claiming it was dropped. Everything is compared after canonicalization so
+// `..` and symlinks can't escape a root. New roots are only ever picked in a
+// native folder dialog; the webview can't name one.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+use tauri_plugin_dialog::DialogExt;
+
+use crate::{profiles, read_setting, usage_metrics, write_setting, DbState};
+
+pub const ALLOWED_ROOTS_KEY: &str = "sandbox.allowed_roots";
+
+const MAX_FILE_NAME_LEN: usize = 200;
+
+/// Reduce a user-supplied file name to one plain path component, rejecting
+/// anything that would escape the target directory.
+pub fn safe_file_name(name: &str) -> Result<String, String> {
+  let name = name.trim();
+  let mut components = Path::new(name).components();
+  let valid = matches!(
+    (components.next(), components.next()),
+    (Some(Component::Normal(_)), None)
+  );
+  // Backslashes are separators on Windows but not elsewhere; reject them everywhere
+  if !valid || name.contains(['/', '\\', '\0']) {
+    return Err(format!("Invalid file name: {}", name));
+  }
+
+  let cleaned: String = name
+    .chars()
+    .filter(|c| !c.is_control() && !matches!(c, ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+    .collect();
+  if cleaned.is_empty() {
+    return Err("Invalid file name".to_string());
+  }
+
+  // Keep the extension when truncating long names
+  if cleaned.chars().count() <= MAX_FILE_NAME_LEN {
+    return Ok(cleaned);
+  }
+  let ext = Path::new(&cleaned)
+    .extension()
+    .and_then(|e| e.to_str())
+    .map(|e| format!(".{}", e))
+    .unwrap_or_default();
+  let stem: String = cleaned.chars().take(MAX_FILE_NAME_LEN - ext.chars().count()).collect();
+  Ok(format!("{}{}", stem, ext))
+}
+
+fn load_roots(conn: &rusqlite::Connection) -> Vec<String> {
+  read_setting(conn, ALLOWED_ROOTS_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn save_roots(conn: &rusqlite::Connection, roots: &[String]) -> Result<(), String> {
+  let json = serde_json::to_string(roots).map_err(|e| e.to_string())?;
+  write_setting(conn, ALLOWED_ROOTS_KEY, &json)
+}
+
+/// Files dropped onto a window since launch, in canonical form.
+#[derive(Default)]
+pub struct DropState {
+  paths: Mutex<HashSet<PathBuf>>,
+}
+
+/// Remember natively dropped files so they may be read and copied in, and
+/// previewed through the asset protocol before they are.
+pub fn record_drop(app: &tauri::AppHandle, paths: &[PathBuf]) {
+  let state = app.state::<DropState>();
+  let mut dropped = state.paths.lock().unwrap_or_else(|e| e.into_inner());
+  for canonical in paths.iter().filter_map(|path| fs::canonicalize(path).ok()) {
+    if let Err(e) = app.asset_protocol_scope().allow_file(&canonical) {
+      tracing::warn!(error = %e, "Dropped file not added to the asset scope");
+    }
+    dropped.insert(canonical);
+  }
+}
+
+fn was_dropped(app: &tauri::AppHandle, canonical: &Path) -> bool {
+  let state = app.state::<DropState>();
+  let dropped = state.paths.lock().unwrap_or_else(|e| e.into_inner());
+  dropped.contains(canonical)
+}
+
+fn canonical_roots(app: &tauri::AppHandle, conn: &rusqlite::Connection) -> Vec<PathBuf> {
+  let data_dir = app.state::<DbState>().data_dir();
+  std::iter::once(data_dir)
+    .chain(load_roots(conn).into_iter().map(PathBuf::from))
+    .filter_map(|root| fs::canonicalize(root).ok())
+    .collect()
+}
+
+/// Whether `canonical` lies in a profile other than the active one. The
+/// default profile's folder contains the others, so being under the data
+/// dir isn't enough.
+fn in_other_profile(app: &tauri::AppHandle, canonical: &Path) -> bool {
+  let Some(profiles_root) = profiles::profiles_root(app).ok().and_then(|p| fs::canonicalize(p).ok()) else {
+    return false;
+  };
+  let data_dir = fs::canonicalize(app.state::<DbState>().data_dir()).unwrap_or_default();
+  canonical.starts_with(&profiles_root) && !canonical.starts_with(&data_dir)
+}
+
+/// Canonical path of `path` if the app may read it, an error otherwise.
+pub fn check_readable(
+  app: &tauri::AppHandle,
+  conn: &rusqlite::Connection,
+  path: &str,
+) -> Result<PathBuf, String> {
+  let canonical = fs::canonicalize(path).map_err(|_| format!("File not found: {}", path))?;
+
+  if was_dropped(app, &canonical) {
+    return Ok(canonical);
+  }
+  if !in_other_profile(app, &canonical)
+    && canonical_roots(app, conn).iter().any(|root| canonical.starts_with(root))
+  {
+    return Ok(canonical);
+  }
+
+  Err(format!("Access denied: {} is outside the allowed folders", path))
+}
+
+#[tauri::command]
+pub fn list_allowed_roots(state: tauri::State<DbState>) -> Result<Vec<String>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_roots(&conn))
+}
+
+fn pick_root(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
+  let picked = app.dialog().file().set_title("Allow reading from folder").blocking_pick_folder();
+
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let mut roots = load_roots(&conn);
+  // Cancelling the dialog leaves the list as it was
+  let Some(picked) = picked else { return Ok(roots) };
+
+  let path = picked.into_path().map_err(|e| e.to_string())?;
+  let canonical = fs::canonicalize(&path).map_err(|_| format!("Folder not found: {}", path.display()))?;
+  if !canonical.is_dir() {
+    return Err(format!("Not a folder: {}", path.display()));
+  }
+  let canonical = canonical.to_string_lossy().to_string();
+  if !roots.contains(&canonical) {
+    roots.push(canonical);
+    save_roots(&conn, &roots)?;
+  }
+  Ok(roots)
+}
+
+/// Ask the user for a folder to approve for reads, in a native dialog. It's
+/// stored in canonical form.
+#[tauri::command]
+pub async fn add_allowed_root(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+  let _timer = usage_metrics::time_async("add_allowed_root");
+  tokio::task::spawn_blocking(move || pick_root(&app)).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn remove_allowed_root(state: tauri::State<DbState>, path: String) -> Result<Vec<String>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let mut roots = load_roots(&conn);
+  roots.retain(|root| root != &path);
+  save_roots(&conn, &roots)?;
+  Ok(roots)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn keeps_plain_file_names() {
+    assert_eq!(safe_file_name("  notes 2024.md ").as_deref(), Ok("notes 2024.md"));
+    assert_eq!(safe_file_name("what?:*.txt").as_deref(), Ok("what.txt"));
+  }
+
+  #[test]
+  fn rejects_names_that_leave_the_folder() {
+    for name in ["", ".", "..", "../secret", "a/b.txt", "a\\b.txt", "/etc/passwd", "nul\0.txt", "???"] {
+      assert!(safe_file_name(name).is_err(), "{:?} was accepted", name);
+    }
+  }
+
+  #[test]
+  fn shortens_long_names_but_keeps_the_extension() {
+    let name = safe_file_name(&format!("{}.jpeg", "x".repeat(300))).unwrap();
+    assert_eq!(name.chars().count(), MAX_FILE_NAME_LEN);
+    assert!(name.ends_with("x.jpeg"));
+  }
+}
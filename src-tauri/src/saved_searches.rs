@@ -0,0 +1,93 @@
+// Saved searches: named `SearchFilter`s the UI can run with one click and
+// exports can be limited to.
+
+use serde::Serialize;
+
+use crate::search::{self, SearchFilter, SearchHit};
+use crate::{app_lock, generate_id, now_ms, DbState};
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+  id: String,
+  name: String,
+  filter: SearchFilter,
+  created_at: i64,
+}
+
+pub fn load_filter(conn: &rusqlite::Connection, id: &str) -> Result<SearchFilter, String> {
+  let json: String = conn
+    .query_row("SELECT filter FROM saved_searches WHERE id = ?", [id], |row| row.get(0))
+    .map_err(|_| "Saved search not found".to_string())?;
+  serde_json::from_str(&json).map_err(|e| format!("Invalid saved search: {}", e))
+}
+
+#[tauri::command]
+pub fn create_saved_search(
+  state: tauri::State<DbState>,
+  name: String,
+  filter: SearchFilter,
+) -> Result<SavedSearch, String> {
+  let name = name.trim().to_string();
+  if name.is_empty() {
+    return Err("Saved search name must not be empty".to_string());
+  }
+  let json = serde_json::to_string(&filter).map_err(|e| e.to_string())?;
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let id = generate_id();
+  let created_at = now_ms();
+  conn.execute(
+    "INSERT INTO saved_searches (id, name, filter, created_at) VALUES (?1, ?2, ?3, ?4)",
+    (&id, &name, &json, created_at),
+  ).map_err(|e| e.to_string())?;
+
+  Ok(SavedSearch { id, name, filter, created_at })
+}
+
+#[tauri::command]
+pub fn list_saved_searches(state: tauri::State<DbState>) -> Result<Vec<SavedSearch>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let searches = conn
+    .prepare("SELECT id, name, filter, created_at FROM saved_searches ORDER BY name COLLATE NOCASE")
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| {
+      let json: String = row.get(2)?;
+      Ok(SavedSearch {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        filter: serde_json::from_str(&json).unwrap_or_default(),
+        created_at: row.get(3)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(searches)
+}
+
+#[tauri::command]
+pub fn delete_saved_search(state: tauri::State<DbState>, id: String) -> Result<(), String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  conn.execute("DELETE FROM saved_searches WHERE id = ?", [&id])
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+#[tauri::command]
+pub fn run_saved_search(
+  state: tauri::State<DbState>,
+  id: String,
+  limit: Option<u32>,
+) -> Result<Vec<SearchHit>, String> {
+  app_lock::ensure_unlocked()?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let filter = load_filter(&conn, &id)?;
+  search::run_filter(&conn, &filter, Some(limit.unwrap_or(50)))
+}
@@ -0,0 +1,178 @@
+// Plans: events dated in the future.
+//
+// A plan is a normal timeline event with `scheduled_for` set and `created_at`
+// equal to the planned time. Timeline, search and export queries skip rows
+// with `scheduled_for`, and the reminder scanner clears it once the time
+// arrives, which turns the plan into an ordinary entry.
+
+use serde::{Deserialize, Serialize};
+
+use crate::reminder_scan::ReminderScanState;
+use crate::{
+  compression, event_from_row, generate_id, mentions, now_ms, query_attachments, query_reminders, rate_limit, tags,
+  DbState, Reminder, TimelineEvent, TimelineEventWithAttachments,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateScheduledEventRequest {
+  note: String,
+  title: Option<String>,
+  text_content: Option<String>,
+  scheduled_for: i64,  // unix ms, must be in the future
+  /// Also fire a reminder when the plan comes due
+  remind: Option<bool>,
+  remind_message: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledEventDue {
+  event_id: String,
+  title: Option<String>,
+  note: Option<String>,
+  scheduled_for: i64,
+}
+
+/// Turn plans whose time has come into normal entries. Called from the
+/// reminder scanner; returns the promoted plans so they can be announced.
+pub fn promote_due_events(conn: &rusqlite::Connection, now: i64) -> Result<Vec<ScheduledEventDue>, String> {
+  let due: Vec<ScheduledEventDue> = conn
+    .prepare(
+      "SELECT id, title, note, scheduled_for FROM timeline_events
+       WHERE scheduled_for IS NOT NULL AND scheduled_for <= ?1 AND is_deleted = 0",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([now], |row| {
+      Ok(ScheduledEventDue {
+        event_id: row.get(0)?,
+        title: row.get(1)?,
+        note: row.get(2)?,
+        scheduled_for: row.get(3)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  for plan in &due {
+    conn.execute(
+      "UPDATE timeline_events SET scheduled_for = NULL, created_at = ?1 WHERE id = ?2",
+      (plan.scheduled_for, &plan.event_id),
+    ).map_err(|e| e.to_string())?;
+  }
+  Ok(due)
+}
+
+#[tauri::command]
+pub fn create_scheduled_event(
+  state: tauri::State<DbState>,
+  scan: tauri::State<ReminderScanState>,
+  request: CreateScheduledEventRequest,
+) -> Result<TimelineEventWithAttachments, String> {
+  let created_at = now_ms();
+  if request.scheduled_for <= created_at {
+    return Err("Scheduled time must be in the future".to_string());
+  }
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let admission = rate_limit::check("plan", (&request.note, &request.text_content, request.scheduled_for))?;
+
+  let event_id = generate_id();
+  let event_type = if request.text_content.is_some() { "text" } else { "thought" };
+
+  conn.execute(
+    "INSERT INTO timeline_events (id, type, title, note, text_content, created_at, source, is_deleted, scheduled_for)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'plan', 0, ?6)",
+    (
+      &event_id,
+      event_type,
+      &request.title,
+      &request.note,
+      &request.text_content,
+      request.scheduled_for,
+    ),
+  ).map_err(|e| e.to_string())?;
+  compression::compress_if_large(&conn, &event_id)?;
+  tags::sync_hashtags(&conn, &event_id)?;
+  mentions::index(&conn, &event_id)?;
+
+  let mut reminders = Vec::new();
+  if request.remind.unwrap_or(false) {
+    let reminder_id = generate_id();
+    let message = request.remind_message
+      .unwrap_or_else(|| request.title.clone().unwrap_or_else(|| request.note.clone()));
+
+    conn.execute(
+      "INSERT INTO reminders (id, event_id, remind_at, message, status, created_at)
+       VALUES (?1, ?2, ?3, ?4, 'pending', ?5)",
+      (&reminder_id, &event_id, request.scheduled_for, &message, created_at),
+    ).map_err(|e| e.to_string())?;
+
+    reminders.push(Reminder {
+      id: reminder_id,
+      event_id: event_id.clone(),
+      remind_at: request.scheduled_for,
+      message,
+      status: "pending".to_string(),
+      triggered_at: None,
+      snooze_until: None,
+      created_at,
+      attachment_id: None,
+      follow_up_after_minutes: None,
+      follow_up_message: None,
+    });
+  }
+
+  scan.wake();
+
+  let event = TimelineEvent {
+    id: event_id,
+    event_type: event_type.to_string(),
+    title: request.title,
+    note: Some(request.note),
+    text_content: request.text_content,
+    created_at: request.scheduled_for,
+    source: Some("plan".to_string()),
+    is_deleted: false,
+    metadata: None,
+    ai_opt_out: false,
+    locked: false,
+  };
+
+  rate_limit::record(admission);
+  Ok(TimelineEventWithAttachments { event, attachments: vec![], reminders })
+}
+
+/// Plans that haven't come due yet, soonest first.
+#[tauri::command]
+pub fn list_upcoming_events(
+  state: tauri::State<DbState>,
+  limit: Option<u32>,
+) -> Result<Vec<TimelineEventWithAttachments>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let events: Vec<TimelineEvent> = conn
+    .prepare(
+      "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
+       FROM timeline_events
+       WHERE scheduled_for IS NOT NULL AND is_deleted = 0
+       ORDER BY scheduled_for ASC
+       LIMIT ?1",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([limit.unwrap_or(50)], event_from_row)
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let mut results = Vec::new();
+  for event in events {
+    let attachments = query_attachments(&conn, &event.id)?;
+    let reminders = query_reminders(&conn, &event.id)?;
+    results.push(TimelineEventWithAttachments { event, attachments, reminders });
+  }
+  Ok(results)
+}
@@ -0,0 +1,121 @@
+// Importing screenshots as they're taken.
+//
+// A watch folder preset for the system's screenshot folder: macOS's
+// `screencapture` location (the Desktop unless changed), `Pictures/Screenshots`
+// on Windows (also under OneDrive) and on Linux desktops that save there.
+// Screenshots become image events with `source = 'screenshot'`. On the
+// macOS Desktop only files named like screenshots are taken, since the
+// Desktop holds everything else too. With `move_files` the screenshot is
+// moved into app storage, which keeps the Desktop clear.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+use crate::watch_folders::{self, WatchFolder, WatchRules};
+use crate::DbState;
+
+pub const PRESET: &str = "screenshots";
+
+const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "heic"];
+// Default macOS names, "Screen Shot" before Ventura
+const MACOS_PREFIXES: &[&str] = &["Screenshot", "Screen Shot"];
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenshotImport {
+  /// Where the system saves screenshots, if it could be found
+  folder: Option<String>,
+  enabled: bool,
+  move_files: bool,
+}
+
+/// The screenshot folder, and whether it's shared with other files and needs
+/// the name filter.
+fn detect(app: &tauri::AppHandle) -> Option<(PathBuf, bool)> {
+  if cfg!(target_os = "macos") {
+    let custom = std::process::Command::new("defaults")
+      .args(["read", "com.apple.screencapture", "location"])
+      .output()
+      .ok()
+      .filter(|output| output.status.success())
+      .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+      .filter(|location| !location.is_empty())
+      .map(|location| match location.strip_prefix("~/") {
+        Some(rest) => app.path().home_dir().map(|home| home.join(rest)).unwrap_or_else(|_| PathBuf::from(&location)),
+        None => PathBuf::from(location),
+      });
+    let desktop = app.path().desktop_dir().ok();
+    return match custom.filter(|dir| dir.is_dir()) {
+      Some(dir) => {
+        let shared = desktop.is_some_and(|desktop| desktop == dir);
+        Some((dir, shared))
+      }
+      None => desktop.map(|dir| (dir, true)),
+    };
+  }
+
+  let mut candidates: Vec<PathBuf> = app.path().picture_dir().into_iter().map(|dir| dir.join("Screenshots")).collect();
+  if cfg!(target_os = "windows") {
+    if let Ok(one_drive) = std::env::var("OneDrive") {
+      candidates.push(Path::new(&one_drive).join("Pictures").join("Screenshots"));
+    }
+  }
+  candidates.into_iter().find(|dir| dir.is_dir()).map(|dir| (dir, false))
+}
+
+fn status(app: &tauri::AppHandle, folders: &[WatchFolder]) -> ScreenshotImport {
+  let preset = folders.iter().find(|f| f.preset.as_deref() == Some(PRESET));
+  ScreenshotImport {
+    folder: preset
+      .map(|f| f.path.clone())
+      .or_else(|| detect(app).map(|(dir, _)| dir.to_string_lossy().to_string())),
+    enabled: preset.is_some(),
+    move_files: preset.is_some_and(|f| f.rules.move_files),
+  }
+}
+
+#[tauri::command]
+pub fn get_screenshot_import(app: tauri::AppHandle, state: tauri::State<DbState>) -> Result<ScreenshotImport, String> {
+  let folders = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    watch_folders::load_folders(&conn)
+  };
+  Ok(status(&app, &folders))
+}
+
+/// Turn screenshot import on or off. `move_files` moves each screenshot into
+/// app storage instead of referencing it where it was saved.
+#[tauri::command]
+pub fn set_screenshot_import(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  enabled: bool,
+  move_files: bool,
+) -> Result<ScreenshotImport, String> {
+  let preset = if enabled {
+    let (dir, shared) = detect(&app).ok_or("No screenshot folder found on this system")?;
+    let dir = std::fs::canonicalize(&dir).map_err(|e| e.to_string())?;
+    let rules = WatchRules {
+      extensions: EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+      name_prefixes: if shared { MACOS_PREFIXES.iter().map(|p| p.to_string()).collect() } else { Vec::new() },
+      tags: vec!["screenshot".to_string()],
+      move_files,
+      ..WatchRules::default()
+    };
+    Some(WatchFolder { path: dir.to_string_lossy().to_string(), rules: watch_folders::validate(rules)?, preset: Some(PRESET.to_string()) })
+  } else {
+    None
+  };
+
+  let folders = watch_folders::update_folders(&app, &state, |folders| {
+    folders.retain(|f| f.preset.as_deref() != Some(PRESET));
+    if let Some(preset) = preset {
+      // The preset takes over a plain watch on the same folder
+      folders.retain(|f| f.path != preset.path);
+      folders.push(preset);
+    }
+  })?;
+  Ok(status(&app, &folders))
+}
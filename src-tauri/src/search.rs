@@ -0,0 +1,289 @@
+// Keyword search with match positions and snippets.
+//
+// Matching is done here rather than in the UI so every search backend
+// highlights the same way. Offsets are in UTF-16 code units because that's
+// how JavaScript indexes strings; `start..end` can be passed to `slice`
+// directly.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{app_lock, archive, compression, event_from_row, tags, push_metadata_condition, query_attachments, DbState, TimelineEvent};
+
+// Characters of context kept on each side of the first hit
+const SNIPPET_CONTEXT: usize = 60;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchRange {
+  start: usize,
+  end: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Snippet {
+  /// "title", "note", "textContent" or "attachment:<id>"
+  field: String,
+  text: String,
+  /// Hits within `text`
+  highlights: Vec<MatchRange>,
+  /// Hits within the full field value
+  matches: Vec<MatchRange>,
+}
+
+/// Everything a search can narrow by. Empty fields don't restrict.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchFilter {
+  pub query: String,
+  /// Event types, e.g. ["image", "thought"]
+  pub types: Vec<String>,
+  /// Tags, all of which the event must have, either attached or as a
+  /// `#hashtag` in its text
+  pub tags: Vec<String>,
+  pub start_date: Option<i64>,  // unix ms
+  pub end_date: Option<i64>,    // unix ms
+  /// Same semantics as `list_events`' metadata filter
+  pub metadata: HashMap<String, serde_json::Value>,
+  pub include_archived: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+  pub(crate) event: TimelineEvent,
+  snippets: Vec<Snippet>,
+}
+
+/// Whitespace-separated, lowercased search terms.
+fn query_terms(query: &str) -> Vec<Vec<char>> {
+  query
+    .split_whitespace()
+    .map(|term| term.chars().map(fold_char).collect())
+    .collect()
+}
+
+// One char in, one char out, so positions in the folded text line up with the
+// original. Multi-char lowercase forms keep only their first char.
+fn fold_char(c: char) -> char {
+  c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Non-overlapping hits of any term, as char index ranges, sorted.
+fn char_matches(chars: &[char], terms: &[Vec<char>]) -> Vec<(usize, usize)> {
+  let folded: Vec<char> = chars.iter().map(|c| fold_char(*c)).collect();
+  let mut hits = Vec::new();
+  let mut i = 0;
+  while i < folded.len() {
+    // Prefer the longest term starting here
+    let len = terms
+      .iter()
+      .filter(|t| !t.is_empty() && folded[i..].starts_with(t))
+      .map(|t| t.len())
+      .max();
+    match len {
+      Some(len) => {
+        hits.push((i, i + len));
+        i += len;
+      }
+      None => i += 1,
+    }
+  }
+  hits
+}
+
+fn utf16_len(chars: &[char]) -> usize {
+  chars.iter().map(|c| c.len_utf16()).sum()
+}
+
+fn to_utf16(chars: &[char], base: usize, ranges: &[(usize, usize)]) -> Vec<MatchRange> {
+  ranges
+    .iter()
+    .map(|(s, e)| MatchRange {
+      start: utf16_len(&chars[base..*s]),
+      end: utf16_len(&chars[base..*e]),
+    })
+    .collect()
+}
+
+/// Snippet around the first hit in `text`, or `None` when nothing matches.
+pub fn snippet(field: &str, text: &str, terms: &[Vec<char>]) -> Option<Snippet> {
+  let chars: Vec<char> = text.chars().collect();
+  let hits = char_matches(&chars, terms);
+  let (first_start, first_end) = *hits.first()?;
+
+  let mut start = first_start.saturating_sub(SNIPPET_CONTEXT);
+  let mut end = (first_end + SNIPPET_CONTEXT).min(chars.len());
+  // Don't cut words in half when there's a space nearby
+  if start > 0 {
+    if let Some(space) = chars[start..first_start].iter().position(|c| c.is_whitespace()) {
+      start += space + 1;
+    }
+  }
+  if end < chars.len() {
+    if let Some(space) = chars[first_end..end].iter().rposition(|c| c.is_whitespace()) {
+      end = first_end + space;
+    }
+  }
+
+  let prefix = if start > 0 { "…" } else { "" };
+  let suffix = if end < chars.len() { "…" } else { "" };
+  let body: String = chars[start..end].iter().collect::<String>().replace('\n', " ");
+  let offset = prefix.encode_utf16().count();
+
+  let visible: Vec<(usize, usize)> = hits.iter().copied().filter(|(s, e)| *s >= start && *e <= end).collect();
+  let highlights = to_utf16(&chars, start, &visible)
+    .into_iter()
+    .map(|r| MatchRange { start: r.start + offset, end: r.end + offset })
+    .collect();
+
+  Some(Snippet {
+    field: field.to_string(),
+    text: format!("{}{}{}", prefix, body, suffix),
+    highlights,
+    matches: to_utf16(&chars, 0, &hits),
+  })
+}
+
+pub(crate) fn like_pattern(term: &[char]) -> String {
+  let mut pattern = String::from("%");
+  for c in term {
+    if matches!(c, '%' | '_' | '\\') {
+      pattern.push('\\');
+    }
+    pattern.push(*c);
+  }
+  pattern.push('%');
+  pattern
+}
+
+/// Events matching `filter`, newest first, with a snippet per field that
+/// contains a query term. Every query term must appear in a text field or an
+/// attachment name.
+pub fn run_filter(
+  conn: &rusqlite::Connection,
+  filter: &SearchFilter,
+  limit: Option<u32>,
+) -> Result<Vec<SearchHit>, String> {
+  let terms = query_terms(&filter.query);
+
+  let mut sql = format!(
+    "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
+     FROM {} e
+     WHERE is_deleted = 0 AND scheduled_for IS NULL",
+    archive::events_table(filter.include_archived)
+  );
+  let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![];
+  for term in &terms {
+    sql.push_str(&format!(
+      " AND (LOWER(title) LIKE ? ESCAPE '\\' OR LOWER(note) LIKE ? ESCAPE '\\'
+             OR LOWER({}) LIKE ? ESCAPE '\\'
+             OR EXISTS (SELECT 1 FROM attachments a
+                        WHERE a.event_id = e.id AND LOWER(a.file_name) LIKE ? ESCAPE '\\'))",
+      compression::FULL_TEXT_SQL
+    ));
+    let pattern = like_pattern(term);
+    for _ in 0..4 {
+      params.push(Box::new(pattern.clone()));
+    }
+  }
+  for tag in filter.tags.iter().filter_map(|t| tags::normalize(t)) {
+    let hashtag: Vec<char> = format!("#{}", tag).chars().collect();
+    sql.push_str(&format!(
+      " AND (EXISTS (SELECT 1 FROM event_tags et JOIN tags t ON t.id = et.tag_id
+                     WHERE et.event_id = e.id AND t.name = ?)
+             OR LOWER(title) LIKE ? ESCAPE '\\' OR LOWER(note) LIKE ? ESCAPE '\\'
+             OR LOWER({}) LIKE ? ESCAPE '\\')",
+      compression::FULL_TEXT_SQL
+    ));
+    params.push(Box::new(tag.clone()));
+    let pattern = like_pattern(&hashtag);
+    for _ in 0..3 {
+      params.push(Box::new(pattern.clone()));
+    }
+  }
+  if !filter.types.is_empty() {
+    sql.push_str(&format!(" AND type IN ({})", vec!["?"; filter.types.len()].join(", ")));
+    for event_type in &filter.types {
+      params.push(Box::new(event_type.clone()));
+    }
+  }
+  if let Some(start) = filter.start_date {
+    sql.push_str(" AND created_at >= ?");
+    params.push(Box::new(start));
+  }
+  if let Some(end) = filter.end_date {
+    sql.push_str(" AND created_at <= ?");
+    params.push(Box::new(end));
+  }
+  for (key, value) in &filter.metadata {
+    push_metadata_condition(&mut sql, &mut params, key, value)?;
+  }
+  sql.push_str(" ORDER BY created_at DESC");
+  if let Some(limit) = limit {
+    sql.push_str(" LIMIT ?");
+    params.push(Box::new(limit));
+  }
+
+  let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+  let events: Vec<TimelineEvent> = conn
+    .prepare(&sql)
+    .map_err(|e| e.to_string())?
+    .query_map(params_refs.as_slice(), event_from_row)
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let mut hits = Vec::new();
+  for event in events {
+    let mut snippets: Vec<Snippet> = [
+      ("title", &event.title),
+      ("note", &event.note),
+      ("textContent", &event.text_content),
+    ]
+    .into_iter()
+    .filter_map(|(field, text)| snippet(field, text.as_deref()?, &terms))
+    .collect();
+
+    for attachment in query_attachments(conn, &event.id)? {
+      let Some(name) = &attachment.file_name else { continue };
+      if let Some(s) = snippet(&format!("attachment:{}", attachment.id), name, &terms) {
+        snippets.push(s);
+      }
+    }
+
+    hits.push(SearchHit { event, snippets });
+  }
+  Ok(hits)
+}
+
+#[tauri::command]
+pub fn search_events(
+  state: tauri::State<DbState>,
+  query: String,
+  limit: Option<u32>,
+  include_archived: Option<bool>,
+) -> Result<Vec<SearchHit>, String> {
+  app_lock::ensure_unlocked()?;
+  if query.trim().is_empty() {
+    return Ok(Vec::new());
+  }
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let filter = SearchFilter {
+    query,
+    include_archived: include_archived.unwrap_or(false),
+    ..SearchFilter::default()
+  };
+  run_filter(&conn, &filter, Some(limit.unwrap_or(50)))
+}
+
+/// Snippet and match offsets for arbitrary text, e.g. file content the UI
+/// loaded with `read_file_content`.
+#[tauri::command]
+pub fn get_search_snippet(text: String, query: String) -> Result<Option<Snippet>, String> {
+  app_lock::ensure_unlocked()?;
+  Ok(snippet("text", &text, &query_terms(&query)))
+}
@@ -0,0 +1,46 @@
+// Tokens for third-party services, kept in the OS credential store
+// (Windows Credential Manager, macOS Keychain, Secret Service on Linux)
+// rather than in the settings table. The UI can store and clear a secret and
+// ask whether one is set, but never read it back.
+
+const SERVICE: &str = "papa";
+
+fn entry(name: &str) -> Result<keyring::Entry, String> {
+  if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')) {
+    return Err(format!("Invalid secret name: {}", name));
+  }
+  keyring::Entry::new(SERVICE, name).map_err(|e| e.to_string())
+}
+
+/// The stored secret, if any.
+pub fn get(name: &str) -> Result<Option<String>, String> {
+  match entry(name)?.get_password() {
+    Ok(value) => Ok(Some(value)),
+    Err(keyring::Error::NoEntry) => Ok(None),
+    Err(e) => Err(format!("Could not read secret '{}': {}", name, e)),
+  }
+}
+
+#[tauri::command]
+pub fn set_secret(name: String, value: String) -> Result<(), String> {
+  let value = value.trim();
+  if value.is_empty() {
+    return Err("Secret must not be empty".to_string());
+  }
+  entry(&name)?
+    .set_password(value)
+    .map_err(|e| format!("Could not store secret '{}': {}", name, e))
+}
+
+#[tauri::command]
+pub fn delete_secret(name: String) -> Result<(), String> {
+  match entry(&name)?.delete_password() {
+    Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+    Err(e) => Err(format!("Could not delete secret '{}': {}", name, e)),
+  }
+}
+
+#[tauri::command]
+pub fn has_secret(name: String) -> Result<bool, String> {
+  Ok(get(&name)?.is_some())
+}
@@ -0,0 +1,195 @@
+// "You wrote something like this on March 3rd."
+//
+// `find_similar_events` is called while a note is being written and ranks
+// earlier events by BM25 against its text. There's no embeddings index in
+// the app, so similarity is lexical: the same tokenizer as keyword tag
+// suggestions, scored over title, note and text of every live event. The
+// scoring happens here rather than in the webview so it stays fast with
+// thousands of events. Texts that were compressed are only scored as far as
+// their stored preview, so the scan never has to inflate them.
+//
+// `similarity` is the BM25 score divided by the best score any document
+// could get for the query, which gives the frontend a 0..1 number to
+// threshold on.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{app_lock, event_from_row, tags, DbState, TimelineEvent};
+
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+const DEFAULT_TOP_K: usize = 5;
+const MAX_TOP_K: usize = 20;
+// Matches weaker than this read as coincidence
+const MIN_SIMILARITY: f64 = 0.2;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarEvent {
+  event: TimelineEvent,
+  score: f64,
+  /// `score` relative to the best possible score for the query, 0..1
+  similarity: f64,
+  /// Query words found in the event
+  matched_terms: Vec<String>,
+}
+
+/// The `top_k` events most similar to `text`, best first. `exclude_event_id`
+/// leaves out the note being edited once it's saved.
+#[tauri::command]
+pub fn find_similar_events(
+  state: tauri::State<DbState>,
+  text: String,
+  top_k: Option<usize>,
+  exclude_event_id: Option<String>,
+) -> Result<Vec<SimilarEvent>, String> {
+  app_lock::ensure_unlocked()?;
+  let query: Vec<String> = tags::words(&text).into_iter().collect::<HashSet<_>>().into_iter().collect();
+  if query.is_empty() {
+    return Ok(Vec::new());
+  }
+  let top_k = top_k.unwrap_or(DEFAULT_TOP_K).clamp(1, MAX_TOP_K);
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let events: Vec<TimelineEvent> = conn
+    .prepare(
+      "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked
+       FROM timeline_events
+       WHERE is_deleted = 0 AND scheduled_for IS NULL AND locked = 0 AND id IS NOT ?1",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([&exclude_event_id], event_from_row)
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let texts: Vec<String> = events
+    .iter()
+    .map(|event| {
+      [&event.title, &event.note, &event.text_content].into_iter().flatten().cloned().collect::<Vec<_>>().join("\n")
+    })
+    .collect();
+
+  Ok(
+    rank(&query, &texts, top_k)
+      .into_iter()
+      .map(|ranked| SimilarEvent {
+        event: events[ranked.index].clone(),
+        score: ranked.score,
+        similarity: ranked.similarity,
+        matched_terms: ranked.matched_terms,
+      })
+      .collect(),
+  )
+}
+
+struct Ranked {
+  /// Position in the texts passed to `rank`
+  index: usize,
+  score: f64,
+  similarity: f64,
+  matched_terms: Vec<String>,
+}
+
+/// The `top_k` of `texts` that score best against the `query` words, best
+/// first, leaving out matches weaker than `MIN_SIMILARITY`.
+fn rank(query: &[String], texts: &[String], top_k: usize) -> Vec<Ranked> {
+  // Term frequencies of the query words per document, and document lengths
+  let docs: Vec<(HashMap<&str, f64>, f64)> = texts
+    .iter()
+    .map(|text| {
+      let words = tags::words(text);
+      let mut tf: HashMap<&str, f64> = HashMap::new();
+      for word in &words {
+        if let Some(term) = query.iter().find(|q| *q == word) {
+          *tf.entry(term.as_str()).or_default() += 1.0;
+        }
+      }
+      (tf, words.len() as f64)
+    })
+    .collect();
+  if docs.is_empty() {
+    return Vec::new();
+  }
+
+  let n = docs.len() as f64;
+  let avg_len = (docs.iter().map(|(_, len)| len).sum::<f64>() / n).max(1.0);
+  let idf: HashMap<&str, f64> = query
+    .iter()
+    .map(|term| {
+      let df = docs.iter().filter(|(tf, _)| tf.contains_key(term.as_str())).count() as f64;
+      (term.as_str(), ((n - df + 0.5) / (df + 0.5) + 1.0).ln())
+    })
+    .collect();
+  let best_possible: f64 = idf.values().map(|idf| idf * (K1 + 1.0)).sum();
+  // A single shared word is only a match for one-word queries
+  let min_matched = query.len().min(2);
+
+  let mut scored: Vec<(usize, f64, Vec<String>)> = docs
+    .iter()
+    .enumerate()
+    .filter(|(_, (tf, _))| tf.len() >= min_matched)
+    .map(|(i, (tf, len))| {
+      let score = tf
+        .iter()
+        .map(|(term, freq)| idf[term] * freq * (K1 + 1.0) / (freq + K1 * (1.0 - B + B * len / avg_len)))
+        .sum::<f64>();
+      let mut matched: Vec<String> = tf.keys().map(|t| t.to_string()).collect();
+      matched.sort();
+      (i, score, matched)
+    })
+    .filter(|(_, score, _)| best_possible > 0.0 && score / best_possible >= MIN_SIMILARITY)
+    .collect();
+  scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+  scored.truncate(top_k);
+  scored
+    .into_iter()
+    .map(|(index, score, matched_terms)| Ranked { index, score, similarity: score / best_possible, matched_terms })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn query(words: &[&str]) -> Vec<String> {
+    words.iter().map(|w| w.to_string()).collect()
+  }
+
+  fn texts(texts: &[&str]) -> Vec<String> {
+    texts.iter().map(|t| t.to_string()).collect()
+  }
+
+  #[test]
+  fn ranks_by_bm25() {
+    let texts = texts(&[
+      "Budget meeting with finance about the budget",
+      "Team lunch downtown",
+      "Quarterly budget review meeting notes",
+      "Meeting room booked",
+    ]);
+    let ranked = rank(&query(&["budget", "meeting"]), &texts, 5);
+    // The last text shares only one word with a two-word query
+    let order: Vec<usize> = ranked.iter().map(|r| r.index).collect();
+    assert_eq!(order, vec![0, 2]);
+    assert_eq!(ranked[0].matched_terms, vec!["budget", "meeting"]);
+    assert!(ranked[0].score > ranked[1].score);
+    assert!(ranked.iter().all(|r| r.similarity > 0.0 && r.similarity <= 1.0));
+  }
+
+  #[test]
+  fn keeps_the_top_k() {
+    let texts = texts(&["Watered the garden", "Read a book", "Garden party invite", "Garden centre receipt"]);
+    let ranked = rank(&query(&["garden"]), &texts, 2);
+    assert_eq!(ranked.len(), 2);
+    assert!(ranked.iter().all(|r| r.index != 1));
+  }
+
+  #[test]
+  fn finds_nothing_without_shared_words() {
+    assert!(rank(&query(&["garden"]), &texts(&["Read a book"]), 5).is_empty());
+    assert!(rank(&query(&["garden"]), &[], 5).is_empty());
+  }
+}
@@ -0,0 +1,233 @@
+// Named snooze presets ("in 1h", "this evening", "tomorrow morning",
+// "next work block") resolved against local time.
+//
+// Presets and work hours are JSON settings so users can move "evening" to
+// 20:00 or work a Sunday-Thursday week without a rebuild.
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::{now_ms, read_setting, write_setting, DbState};
+
+pub const SNOOZE_PRESETS_KEY: &str = "reminders.snooze_presets";
+pub const WORK_HOURS_KEY: &str = "reminders.work_hours";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum SnoozeRule {
+  /// A fixed number of minutes from now
+  Offset { minutes: i64 },
+  /// A wall-clock time ("18:00"), `days_ahead` days from today. With
+  /// `days_ahead` 0 a time that has already passed rolls over to tomorrow.
+  #[serde(rename_all = "camelCase")]
+  TimeOfDay { time: String, #[serde(default)] days_ahead: i64 },
+  /// Start of the next work block according to the work hours setting
+  NextWorkBlock,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SnoozePreset {
+  name: String,
+  #[serde(flatten)]
+  rule: SnoozeRule,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkHours {
+  start: String,
+  /// ISO weekdays, 1 = Monday .. 7 = Sunday
+  days: Vec<u32>,
+}
+
+impl Default for WorkHours {
+  fn default() -> Self {
+    Self {
+      start: "09:00".to_string(),
+      days: vec![1, 2, 3, 4, 5],
+    }
+  }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SnoozeOption {
+  name: String,
+  snooze_until: i64,
+}
+
+fn default_presets() -> Vec<SnoozePreset> {
+  let time_of_day = |time: &str, days_ahead| SnoozeRule::TimeOfDay { time: time.to_string(), days_ahead };
+  vec![
+    SnoozePreset { name: "in 1h".to_string(), rule: SnoozeRule::Offset { minutes: 60 } },
+    SnoozePreset { name: "this evening".to_string(), rule: time_of_day("18:00", 0) },
+    SnoozePreset { name: "tomorrow morning".to_string(), rule: time_of_day("09:00", 1) },
+    SnoozePreset { name: "next work block".to_string(), rule: SnoozeRule::NextWorkBlock },
+  ]
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+  NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| format!("Invalid time '{}', expected HH:MM", time))
+}
+
+fn parse_presets(json: &str) -> Result<Vec<SnoozePreset>, String> {
+  let presets: Vec<SnoozePreset> =
+    serde_json::from_str(json).map_err(|e| format!("Invalid snooze presets: {}", e))?;
+  for preset in &presets {
+    if preset.name.trim().is_empty() {
+      return Err("Snooze preset name must not be empty".to_string());
+    }
+    match &preset.rule {
+      SnoozeRule::Offset { minutes } if *minutes <= 0 => {
+        return Err(format!("Snooze preset '{}' needs a positive offset", preset.name));
+      }
+      SnoozeRule::TimeOfDay { time, days_ahead } => {
+        parse_time(time)?;
+        if *days_ahead < 0 {
+          return Err(format!("Snooze preset '{}' can't point into the past", preset.name));
+        }
+      }
+      _ => {}
+    }
+  }
+  Ok(presets)
+}
+
+fn parse_work_hours(json: &str) -> Result<WorkHours, String> {
+  let hours: WorkHours =
+    serde_json::from_str(json).map_err(|e| format!("Invalid work hours: {}", e))?;
+  parse_time(&hours.start)?;
+  if hours.days.is_empty() || hours.days.iter().any(|d| !(1..=7).contains(d)) {
+    return Err("Work days must be ISO weekdays 1-7".to_string());
+  }
+  Ok(hours)
+}
+
+fn load_presets(conn: &rusqlite::Connection) -> Vec<SnoozePreset> {
+  read_setting(conn, SNOOZE_PRESETS_KEY)
+    .and_then(|json| parse_presets(&json).map_err(|e| tracing::warn!("{}", e)).ok())
+    .unwrap_or_else(default_presets)
+}
+
+fn load_work_hours(conn: &rusqlite::Connection) -> WorkHours {
+  read_setting(conn, WORK_HOURS_KEY)
+    .and_then(|json| parse_work_hours(&json).map_err(|e| tracing::warn!("{}", e)).ok())
+    .unwrap_or_default()
+}
+
+/// Local wall-clock time `days_ahead` days after `now`, as unix ms. DST gaps
+/// resolve to the earliest valid instant.
+fn at_local_time(now: DateTime<Local>, time: NaiveTime, days_ahead: i64) -> Option<i64> {
+  let date = now.date_naive() + ChronoDuration::days(days_ahead);
+  Local
+    .from_local_datetime(&date.and_time(time))
+    .earliest()
+    .map(|dt| dt.timestamp_millis())
+}
+
+fn resolve_rule(rule: &SnoozeRule, work_hours: &WorkHours, now: DateTime<Local>) -> Result<i64, String> {
+  let now_ms = now.timestamp_millis();
+  match rule {
+    SnoozeRule::Offset { minutes } => Ok(now_ms + minutes * 60 * 1000),
+    SnoozeRule::TimeOfDay { time, days_ahead } => {
+      let time = parse_time(time)?;
+      let target = at_local_time(now, time, *days_ahead).ok_or("Invalid local time")?;
+      if target > now_ms {
+        Ok(target)
+      } else {
+        at_local_time(now, time, days_ahead + 1).ok_or_else(|| "Invalid local time".to_string())
+      }
+    }
+    SnoozeRule::NextWorkBlock => {
+      let start = parse_time(&work_hours.start)?;
+      // Look a week ahead for the next work-day start after now
+      (0..=7)
+        .filter(|offset| {
+          let day = (now.date_naive() + ChronoDuration::days(*offset)).weekday();
+          work_hours.days.contains(&day.number_from_monday())
+        })
+        .filter_map(|offset| at_local_time(now, start, offset))
+        .find(|target| *target > now_ms)
+        .ok_or_else(|| "No upcoming work block".to_string())
+    }
+  }
+}
+
+/// Resolve a preset name (case-insensitive) to a unix ms timestamp.
+fn resolve_preset(conn: &rusqlite::Connection, name: &str) -> Result<i64, String> {
+  let presets = load_presets(conn);
+  let preset = presets
+    .iter()
+    .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+    .ok_or_else(|| format!("Unknown snooze preset: {}", name))?;
+  resolve_rule(&preset.rule, &load_work_hours(conn), Local::now())
+}
+
+/// Every preset with the time it would snooze to right now, for the UI.
+#[tauri::command]
+pub fn list_snooze_options(state: tauri::State<DbState>) -> Result<Vec<SnoozeOption>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let work_hours = load_work_hours(&conn);
+  let now = Local::now();
+  Ok(
+    load_presets(&conn)
+      .into_iter()
+      .filter_map(|p| {
+        resolve_rule(&p.rule, &work_hours, now)
+          .ok()
+          .map(|snooze_until| SnoozeOption { name: p.name, snooze_until })
+      })
+      .collect(),
+  )
+}
+
+#[tauri::command]
+pub fn get_snooze_presets(state: tauri::State<DbState>) -> Result<Vec<SnoozePreset>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_presets(&conn))
+}
+
+#[tauri::command]
+pub fn set_snooze_presets(
+  state: tauri::State<DbState>,
+  presets: Vec<SnoozePreset>,
+  work_hours: Option<WorkHours>,
+) -> Result<(), String> {
+  let json = serde_json::to_string(&presets).map_err(|e| e.to_string())?;
+  parse_presets(&json)?;
+  let work_hours_json = match &work_hours {
+    Some(hours) => {
+      let json = serde_json::to_string(hours).map_err(|e| e.to_string())?;
+      parse_work_hours(&json)?;
+      Some(json)
+    }
+    None => None,
+  };
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  write_setting(&conn, SNOOZE_PRESETS_KEY, &json)?;
+  if let Some(json) = work_hours_json {
+    write_setting(&conn, WORK_HOURS_KEY, &json)?;
+  }
+  Ok(())
+}
+
+/// Snooze target from either minutes or a preset name; exactly one must be
+/// given.
+pub fn snooze_target(
+  conn: &rusqlite::Connection,
+  snooze_minutes: Option<i64>,
+  preset: Option<&str>,
+) -> Result<i64, String> {
+  match (snooze_minutes, preset) {
+    (Some(minutes), None) if minutes > 0 => Ok(now_ms() + minutes * 60 * 1000),
+    (Some(_), None) => Err("Snooze minutes must be positive".to_string()),
+    (None, Some(name)) => resolve_preset(conn, name),
+    _ => Err("Pass either snoozeMinutes or preset".to_string()),
+  }
+}
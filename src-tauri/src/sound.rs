@@ -0,0 +1,183 @@
+// Notification sounds played by the backend.
+//
+// The reminder scanner plays the sound from `reminders.sound` when
+// reminders fire, so they're audible with the window hidden or the webview
+// muted. The built-in sounds are synthesized, which keeps audio files out of
+// the bundle; "custom" plays a file the user picked (WAV, Ogg Vorbis or
+// MP3). Each playback runs on its own thread with its own output stream,
+// since rodio's stream handle can't be moved between threads.
+
+use rodio::buffer::SamplesBuffer;
+use rodio::{Decoder, OutputStream, Sink, Source};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::{config, read_setting, write_setting, DbState};
+
+pub const REMINDER_SOUND_KEY: &str = "reminders.sound";
+
+const BUILT_IN: &[&str] = &["chime", "bell", "ping"];
+const CUSTOM: &str = "custom";
+const SAMPLE_RATE: u32 = 44_100;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields, default)]
+pub struct SoundSettings {
+  enabled: bool,
+  /// One of the built-in sounds or "custom"
+  name: String,
+  /// Audio file played for "custom"
+  custom_path: Option<String>,
+  /// 0.0 - 1.0
+  volume: f32,
+}
+
+impl Default for SoundSettings {
+  fn default() -> Self {
+    Self { enabled: false, name: "chime".to_string(), custom_path: None, volume: 0.7 }
+  }
+}
+
+fn validate(settings: SoundSettings) -> Result<SoundSettings, String> {
+  if !(0.0..=1.0).contains(&settings.volume) {
+    return Err("Volume must be between 0 and 1".to_string());
+  }
+  if settings.name == CUSTOM {
+    let path = settings.custom_path.as_deref().ok_or("Pick an audio file for the custom sound")?;
+    decode(Path::new(path))?;
+  } else if !BUILT_IN.contains(&settings.name.as_str()) {
+    return Err(format!("Unknown sound: {}", settings.name));
+  }
+  Ok(settings)
+}
+
+fn load_settings(conn: &rusqlite::Connection) -> SoundSettings {
+  read_setting(conn, REMINDER_SOUND_KEY)
+    .and_then(|json| {
+      serde_json::from_str(&json)
+        .map_err(|e| tracing::warn!(error = %e, "Invalid reminder sound setting"))
+        .ok()
+    })
+    .unwrap_or_default()
+}
+
+fn decode(path: &Path) -> Result<Decoder<BufReader<File>>, String> {
+  let file = File::open(path).map_err(|_| format!("Sound file not found: {}", path.display()))?;
+  Decoder::new(BufReader::new(file)).map_err(|e| format!("Unsupported audio file: {}", e))
+}
+
+/// Sum of decaying sine partials: (frequency, amplitude, start s, decay s).
+fn synth(partials: &[(f32, f32, f32, f32)], seconds: f32) -> SamplesBuffer<f32> {
+  let samples = (0..(seconds * SAMPLE_RATE as f32) as usize)
+    .map(|i| {
+      let t = i as f32 / SAMPLE_RATE as f32;
+      partials
+        .iter()
+        .filter(|(_, _, start, _)| t >= *start)
+        .map(|(freq, amp, start, decay)| {
+          let local = t - start;
+          // Short attack to avoid a click
+          let attack = (local / 0.005).min(1.0);
+          amp * attack * (-local / decay).exp() * (TAU * freq * local).sin()
+        })
+        .sum()
+    })
+    .collect::<Vec<f32>>();
+  SamplesBuffer::new(1, SAMPLE_RATE, samples)
+}
+
+fn built_in(name: &str) -> SamplesBuffer<f32> {
+  match name {
+    "bell" => synth(&[(660.0, 0.5, 0.0, 0.6), (1320.0, 0.2, 0.0, 0.3), (1980.0, 0.1, 0.0, 0.15)], 1.6),
+    "ping" => synth(&[(1760.0, 0.5, 0.0, 0.08)], 0.4),
+    _ => synth(&[(880.0, 0.45, 0.0, 0.2), (1318.5, 0.45, 0.18, 0.35)], 1.2),
+  }
+}
+
+/// Play `name` ("custom" uses `custom_path`) on a background thread.
+fn play(name: &str, custom_path: Option<&str>, volume: f32) -> Result<(), String> {
+  let custom = if name == CUSTOM {
+    let path = custom_path.ok_or("No custom sound file set")?;
+    Some(decode(Path::new(path))?)
+  } else if BUILT_IN.contains(&name) {
+    None
+  } else {
+    return Err(format!("Unknown sound: {}", name));
+  };
+  let name = name.to_string();
+
+  std::thread::spawn(move || {
+    let (_stream, handle) = match OutputStream::try_default() {
+      Ok(output) => output,
+      Err(e) => return tracing::warn!(error = %e, "No audio output for notification sound"),
+    };
+    let sink = match Sink::try_new(&handle) {
+      Ok(sink) => sink,
+      Err(e) => return tracing::warn!(error = %e, "Notification sound failed"),
+    };
+    sink.set_volume(volume);
+    match custom {
+      Some(decoder) => sink.append(decoder.convert_samples::<f32>()),
+      None => sink.append(built_in(&name)),
+    }
+    sink.sleep_until_end();
+  });
+  Ok(())
+}
+
+/// Play the configured reminder sound, if enabled. Called by the reminder
+/// scanner once per batch of fired reminders.
+pub fn play_for_reminders(conn: &rusqlite::Connection) {
+  let settings = load_settings(conn);
+  if !settings.enabled {
+    return;
+  }
+  if let Err(e) = play(&settings.name, settings.custom_path.as_deref(), settings.volume) {
+    tracing::warn!(error = %e, "Reminder sound not played");
+  }
+}
+
+/// Play a sound now, e.g. to preview it. `name` defaults to the configured
+/// reminder sound.
+#[tauri::command]
+pub fn play_notification_sound(state: tauri::State<DbState>, name: Option<String>) -> Result<(), String> {
+  let settings = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    load_settings(&conn)
+  };
+  play(name.as_deref().unwrap_or(&settings.name), settings.custom_path.as_deref(), settings.volume)
+}
+
+/// Names accepted by `play_notification_sound`, built-in ones first.
+#[tauri::command]
+pub fn list_notification_sounds() -> Vec<String> {
+  BUILT_IN.iter().chain([&CUSTOM]).map(|s| s.to_string()).collect()
+}
+
+#[tauri::command]
+pub fn get_reminder_sound(state: tauri::State<DbState>) -> Result<SoundSettings, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_settings(&conn))
+}
+
+#[tauri::command]
+pub fn set_reminder_sound(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  sound: SoundSettings,
+) -> Result<SoundSettings, String> {
+  let sound = validate(sound)?;
+  let json = serde_json::to_string(&sound).map_err(|e| e.to_string())?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, REMINDER_SOUND_KEY, &json)?;
+  }
+  config::emit_changed(&app, vec![REMINDER_SOUND_KEY.to_string()]);
+  Ok(sound)
+}
@@ -0,0 +1,184 @@
+// Capture and journaling streaks: consecutive local days with at least one
+// event (or journal entry).
+//
+// A streak stays alive through today until midnight even if nothing has been
+// captured yet. A background check emits `streak-milestone` once per goal
+// when a streak reaches one of the configured day counts.
+
+use chrono::{Duration as ChronoDuration, NaiveDate};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::{archive, pet_state, read_setting, supervisor, timezone, write_setting, DbState};
+
+pub const STREAK_GOALS_KEY: &str = "streaks.goals";
+// Last milestone celebrated per streak kind, so each fires only once
+const CELEBRATED_KEY: &str = "streaks.celebrated";
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_GOALS: &[u32] = &[3, 7, 14, 30, 50, 100, 365];
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Streak {
+  current: u32,
+  longest: u32,
+  /// Whether today already counts towards the streak
+  active_today: bool,
+  next_goal: Option<u32>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Streaks {
+  capture: Streak,
+  journal: Streak,
+  goals: Vec<u32>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct StreakMilestone {
+  kind: String,
+  days: u32,
+}
+
+fn load_goals(conn: &rusqlite::Connection) -> Vec<u32> {
+  read_setting(conn, STREAK_GOALS_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_else(|| DEFAULT_GOALS.to_vec())
+}
+
+/// Distinct local days with matching events, newest first.
+fn active_days(conn: &rusqlite::Connection, journal_only: bool) -> Result<Vec<NaiveDate>, String> {
+  let days = conn
+    .prepare(&format!(
+      "SELECT DISTINCT date(created_at / 1000, 'unixepoch', {}) AS day
+       FROM {}
+       WHERE is_deleted = 0 AND scheduled_for IS NULL AND (?1 = 0 OR type = 'journal')
+       ORDER BY day DESC",
+      timezone::event_modifier_sql("?2"),
+      archive::events_table(true)
+    ))
+    .map_err(|e| e.to_string())?
+    .query_map((journal_only as i64, timezone::sql_modifier()), |row| row.get::<_, String>(0))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .filter_map(|day| NaiveDate::parse_from_str(&day, "%Y-%m-%d").ok())
+    .collect();
+  Ok(days)
+}
+
+fn compute_streak(days: &[NaiveDate], today: NaiveDate, goals: &[u32]) -> Streak {
+  let active_today = days.first() == Some(&today);
+  let yesterday = today - ChronoDuration::days(1);
+
+  let mut longest = 0;
+  let mut current = 0;
+  let mut run = 0;
+  let mut prev: Option<NaiveDate> = None;
+  for (i, day) in days.iter().enumerate() {
+    run = match prev {
+      Some(p) if p - *day == ChronoDuration::days(1) => run + 1,
+      _ => 1,
+    };
+    // The first run counts as current if it reaches today or yesterday
+    if i as u32 + 1 == run && (days[0] == today || days[0] == yesterday) {
+      current = run;
+    }
+    longest = longest.max(run);
+    prev = Some(*day);
+  }
+
+  Streak {
+    current,
+    longest,
+    active_today,
+    next_goal: goals.iter().copied().filter(|g| *g > current).min(),
+  }
+}
+
+fn load_streaks(conn: &rusqlite::Connection) -> Result<Streaks, String> {
+  let goals = load_goals(conn);
+  let today = timezone::today();
+  Ok(Streaks {
+    capture: compute_streak(&active_days(conn, false)?, today, &goals),
+    journal: compute_streak(&active_days(conn, true)?, today, &goals),
+    goals,
+  })
+}
+
+/// Milestones reached today that haven't been celebrated yet; marks them as
+/// celebrated.
+fn new_milestones(conn: &rusqlite::Connection) -> Result<Vec<StreakMilestone>, String> {
+  let streaks = load_streaks(conn)?;
+  let mut celebrated: HashMap<String, u32> = read_setting(conn, CELEBRATED_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default();
+
+  let mut milestones = Vec::new();
+  for (kind, streak) in [("capture", &streaks.capture), ("journal", &streaks.journal)] {
+    // A broken streak resets so the same goals can be celebrated again
+    if streak.current < celebrated.get(kind).copied().unwrap_or(0) {
+      celebrated.remove(kind);
+    }
+    let reached = streaks.goals.contains(&streak.current) && streak.active_today;
+    if reached && celebrated.get(kind) != Some(&streak.current) {
+      celebrated.insert(kind.to_string(), streak.current);
+      milestones.push(StreakMilestone { kind: kind.to_string(), days: streak.current });
+    }
+  }
+
+  let json = serde_json::to_string(&celebrated).map_err(|e| e.to_string())?;
+  write_setting(conn, CELEBRATED_KEY, &json)?;
+  Ok(milestones)
+}
+
+pub fn spawn_streak_watcher(app: tauri::AppHandle) {
+  supervisor::supervise(app, "streaks", |app| async move {
+    loop {
+      tokio::time::sleep(CHECK_INTERVAL).await;
+
+      let milestones = {
+        let state = app.state::<DbState>();
+        let Ok(_guard) = state.lock.lock() else { continue };
+        state.open()
+          .map_err(|e| e.to_string())
+          .and_then(|conn| new_milestones(&conn))
+          .unwrap_or_default()
+      };
+
+      for milestone in &milestones {
+        pet_state::cheer(&app, format!("{}-day {} streak", milestone.days, milestone.kind));
+      }
+      if let Some(window) = app.get_webview_window("main") {
+        for milestone in milestones {
+          let _ = window.emit("streak-milestone", &milestone);
+        }
+      }
+    }
+  });
+}
+
+#[tauri::command]
+pub fn get_streaks(state: tauri::State<DbState>) -> Result<Streaks, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  load_streaks(&conn)
+}
+
+/// Replace the day counts that trigger `streak-milestone`.
+#[tauri::command]
+pub fn set_streak_goals(state: tauri::State<DbState>, goals: Vec<u32>) -> Result<Vec<u32>, String> {
+  let mut goals: Vec<u32> = goals.into_iter().filter(|g| *g > 0).collect();
+  goals.sort_unstable();
+  goals.dedup();
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let json = serde_json::to_string(&goals).map_err(|e| e.to_string())?;
+  write_setting(&conn, STREAK_GOALS_KEY, &json)?;
+  Ok(goals)
+}
@@ -0,0 +1,210 @@
+// Summaries and action items, with or without an LLM.
+//
+// When the caller passes a model the LLM writes the summary; without one, or
+// when the call fails, an extractive summary is built on-device with
+// TextRank: sentences are ranked by how much vocabulary they share with the
+// rest of the text and the best ones are returned in their original order.
+// Action items fall back to picking out to-do style lines.
+
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashSet;
+
+use crate::journal::PromptModel;
+use crate::{llm_structured, request_llm, usage_metrics, LlmRequest};
+
+const DEFAULT_SENTENCES: usize = 3;
+const DAMPING: f64 = 0.85;
+const ITERATIONS: usize = 30;
+const MAX_ACTIONS: usize = 10;
+
+// Phrases that mark a to-do anywhere in a sentence
+const ACTION_PHRASES: &[&str] = &[
+  "todo", "to-do", "need to", "needs to", "have to", "must ", "should ", "remember to",
+  "don't forget", "follow up", "follow-up",
+];
+// Verbs that mark a to-do when a sentence starts with them
+const ACTION_VERBS: &[&str] = &[
+  "call ", "email ", "send ", "buy ", "book ", "schedule ", "fix ", "check ", "ask ", "pay ",
+  "reply ", "review ", "finish ", "submit ",
+];
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+  pub(crate) text: String,
+  /// "llm" or "extractive"
+  pub(crate) source: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionItems {
+  items: Vec<String>,
+  /// "llm" or "heuristic"
+  source: String,
+}
+
+fn sentences(text: &str) -> Vec<String> {
+  let mut out = Vec::new();
+  for line in text.lines() {
+    let mut current = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+      current.push(c);
+      if matches!(c, '.' | '!' | '?' | '。') && chars.peek().is_none_or(|n| n.is_whitespace()) {
+        out.push(current.trim().to_string());
+        current.clear();
+      }
+    }
+    out.push(current.trim().to_string());
+  }
+  out.into_iter().filter(|s| s.chars().any(|c| c.is_alphanumeric())).collect()
+}
+
+fn sentence_words(sentence: &str) -> HashSet<String> {
+  sentence
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|w| w.chars().count() > 2)
+    .map(|w| w.to_lowercase())
+    .collect()
+}
+
+// TextRank edge weight: shared words normalized by sentence lengths
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+  if a.len() < 2 || b.len() < 2 {
+    return 0.0;
+  }
+  let shared = a.intersection(b).count() as f64;
+  shared / ((a.len() as f64).ln() + (b.len() as f64).ln())
+}
+
+/// Up to `max_sentences` of the most central sentences of `text`, in order.
+pub fn extractive_summary(text: &str, max_sentences: usize) -> String {
+  let sentences = sentences(text);
+  if sentences.len() <= max_sentences {
+    return sentences.join(" ");
+  }
+
+  let words: Vec<HashSet<String>> = sentences.iter().map(|s| sentence_words(s)).collect();
+  let n = sentences.len();
+  let weights: Vec<Vec<f64>> = (0..n)
+    .map(|i| (0..n).map(|j| if i == j { 0.0 } else { similarity(&words[i], &words[j]) }).collect())
+    .collect();
+  let out_weight: Vec<f64> = weights.iter().map(|row| row.iter().sum()).collect();
+
+  let mut scores = vec![1.0; n];
+  for _ in 0..ITERATIONS {
+    scores = (0..n)
+      .map(|i| {
+        let incoming: f64 = (0..n)
+          .filter(|j| out_weight[*j] > 0.0)
+          .map(|j| weights[j][i] / out_weight[j] * scores[j])
+          .sum();
+        (1.0 - DAMPING) + DAMPING * incoming
+      })
+      .collect();
+  }
+
+  let mut ranked: Vec<usize> = (0..n).collect();
+  // Ties go to the earlier sentence, which tends to set the topic
+  ranked.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]).then(a.cmp(b)));
+  let mut chosen: Vec<usize> = ranked.into_iter().take(max_sentences).collect();
+  chosen.sort_unstable();
+  chosen.into_iter().map(|i| sentences[i].as_str()).collect::<Vec<_>>().join(" ")
+}
+
+/// Lines and sentences that read like to-dos.
+pub fn heuristic_actions(text: &str) -> Vec<String> {
+  let mut seen = HashSet::new();
+  sentences(text)
+    .into_iter()
+    .filter_map(|s| {
+      let trimmed = s
+        .trim_start_matches(|c: char| matches!(c, '-' | '*' | '•') || c.is_whitespace())
+        .trim_start_matches("[ ]")
+        .trim();
+      let lower = trimmed.to_lowercase();
+      let is_checkbox = s.trim_start().starts_with("- [ ]") || s.trim_start().starts_with("[ ]");
+      let cued = ACTION_PHRASES.iter().any(|p| lower.contains(p)) || ACTION_VERBS.iter().any(|v| lower.starts_with(v));
+      (is_checkbox || cued).then(|| trimmed.to_string())
+    })
+    .filter(|s| !s.is_empty() && seen.insert(s.to_lowercase()))
+    .take(MAX_ACTIONS)
+    .collect()
+}
+
+fn llm_request(model: PromptModel, prompt: String, max_tokens: u32) -> LlmRequest {
+  LlmRequest {
+    provider: model.provider,
+    api_key: model.api_key,
+    model: model.model,
+    prompt,
+    max_tokens: Some(max_tokens),
+  }
+}
+
+/// Summary of `text`, from the LLM when `llm` is given and it answers,
+/// extractive otherwise.
+pub async fn summarize(text: &str, llm: Option<PromptModel>, max_sentences: usize) -> Summary {
+  if let Some(model) = llm {
+    let prompt = format!(
+      "Summarize the following in at most {} sentences. Reply with the summary only.\n\n{}",
+      max_sentences, text
+    );
+    match request_llm(llm_request(model, prompt, 300)).await {
+      Ok(summary) if !summary.trim().is_empty() => {
+        return Summary { text: summary.trim().to_string(), source: "llm".to_string() };
+      }
+      Ok(_) => {}
+      Err(e) => tracing::warn!(error = %e, "LLM summary failed, using extractive summary"),
+    }
+  }
+  Summary { text: extractive_summary(text, max_sentences), source: "extractive".to_string() }
+}
+
+/// Action items in `text`, from the LLM when `llm` is given and it answers,
+/// by heuristics otherwise.
+pub async fn extract_actions(text: &str, llm: Option<PromptModel>) -> ActionItems {
+  if let Some(model) = llm {
+    let prompt = format!(
+      "List the concrete action items (things someone needs to do) in the following text. \
+       Use short imperative phrases. Return an empty list if there are none.\n\n{}",
+      text
+    );
+    let schema = json!({
+      "type": "object",
+      "properties": {
+        "items": { "type": "array", "items": { "type": "string", "minLength": 1 }, "maxItems": MAX_ACTIONS },
+      },
+      "required": ["items"],
+    });
+    match llm_structured::call_structured(&llm_request(model, prompt, 500), &schema).await {
+      Ok(result) => {
+        let items = result["items"]
+          .as_array()
+          .map(|items| items.iter().filter_map(|i| i.as_str().map(|s| s.trim().to_string())).collect())
+          .unwrap_or_default();
+        return ActionItems { items, source: "llm".to_string() };
+      }
+      Err(e) => tracing::warn!(error = %e, "LLM action extraction failed, using heuristics"),
+    }
+  }
+  ActionItems { items: heuristic_actions(text), source: "heuristic".to_string() }
+}
+
+#[tauri::command]
+pub async fn summarize_text(
+  text: String,
+  llm: Option<PromptModel>,
+  max_sentences: Option<usize>,
+) -> Result<Summary, String> {
+  let _timer = usage_metrics::time_async("summarize_text");
+  Ok(summarize(&text, llm, max_sentences.unwrap_or(DEFAULT_SENTENCES).max(1)).await)
+}
+
+#[tauri::command]
+pub async fn extract_action_items(text: String, llm: Option<PromptModel>) -> Result<ActionItems, String> {
+  let _timer = usage_metrics::time_async("extract_action_items");
+  Ok(extract_actions(&text, llm).await)
+}
@@ -0,0 +1,147 @@
+// Restarts background loops that panic.
+//
+// Each long-running loop is spawned through `supervise`, which awaits the
+// task and starts a fresh one with exponential backoff if it panics. Status is
+// kept per task so the UI can tell when monitoring is degraded, and every
+// crash is logged and announced through `background-task-failed`.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+use crate::now_ms;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+// A task that stayed up this long before crashing starts over at the
+// initial backoff
+const HEALTHY_RUN: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskState {
+  Running,
+  /// Crashed and waiting for the backoff to pass
+  Restarting,
+  /// Returned on its own; won't be restarted
+  Stopped,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskStatus {
+  name: String,
+  state: TaskState,
+  restarts: u32,
+  started_at: i64,
+  last_error: Option<String>,
+  last_failure_at: Option<i64>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackgroundTaskStatus {
+  /// True when any task isn't running
+  degraded: bool,
+  tasks: Vec<TaskStatus>,
+}
+
+#[derive(Default)]
+pub struct SupervisorState {
+  tasks: Mutex<BTreeMap<&'static str, TaskStatus>>,
+}
+
+impl SupervisorState {
+  fn update(&self, name: &'static str, f: impl FnOnce(&mut TaskStatus)) -> Option<TaskStatus> {
+    let mut tasks = self.tasks.lock().ok()?;
+    let status = tasks.entry(name).or_insert_with(|| TaskStatus {
+      name: name.to_string(),
+      state: TaskState::Running,
+      restarts: 0,
+      started_at: now_ms(),
+      last_error: None,
+      last_failure_at: None,
+    });
+    f(status);
+    Some(status.clone())
+  }
+}
+
+fn panic_message(err: tauri::Error) -> String {
+  match err {
+    tauri::Error::JoinError(e) if e.is_panic() => {
+      let payload = e.into_panic();
+      payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "panic".to_string())
+    }
+    other => other.to_string(),
+  }
+}
+
+/// Run `task` in the background, restarting it whenever it panics. `task` is
+/// called again for each restart, so loop state starts fresh.
+pub fn supervise<F, Fut>(app: tauri::AppHandle, name: &'static str, task: F)
+where
+  F: Fn(tauri::AppHandle) -> Fut + Send + 'static,
+  Fut: Future<Output = ()> + Send + 'static,
+{
+  tauri::async_runtime::spawn(async move {
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+      let started = Instant::now();
+      app.state::<SupervisorState>().update(name, |s| {
+        s.state = TaskState::Running;
+        s.started_at = now_ms();
+      });
+
+      let err = match tauri::async_runtime::spawn(task(app.clone())).await {
+        Ok(()) => {
+          tracing::info!(task = name, "Background task stopped");
+          app.state::<SupervisorState>().update(name, |s| s.state = TaskState::Stopped);
+          return;
+        }
+        Err(e) => panic_message(e),
+      };
+
+      if started.elapsed() >= HEALTHY_RUN {
+        backoff = INITIAL_BACKOFF;
+      }
+      tracing::error!(task = name, error = %err, retry_in_secs = backoff.as_secs(), "Background task crashed");
+      let status = app.state::<SupervisorState>().update(name, |s| {
+        s.state = TaskState::Restarting;
+        s.restarts += 1;
+        s.last_error = Some(err);
+        s.last_failure_at = Some(now_ms());
+      });
+      if let (Some(status), Some(window)) = (status, app.get_webview_window("main")) {
+        let _ = window.emit("background-task-failed", &status);
+      }
+
+      tokio::time::sleep(backoff).await;
+      backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+  });
+}
+
+#[tauri::command]
+pub fn get_background_task_status(
+  state: tauri::State<SupervisorState>,
+) -> Result<BackgroundTaskStatus, String> {
+  let tasks: Vec<TaskStatus> = state
+    .tasks
+    .lock()
+    .map_err(|_| "supervisor lock".to_string())?
+    .values()
+    .cloned()
+    .collect();
+  Ok(BackgroundTaskStatus {
+    degraded: tasks.iter().any(|t| t.state != TaskState::Running),
+    tasks,
+  })
+}
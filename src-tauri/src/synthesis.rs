@@ -0,0 +1,213 @@
+// Summaries across many events: "summarize everything tagged #projectX".
+//
+// `summarize_events` takes a search filter (tags, dates, types, ...), renders
+// every matching event as a dated line and packs the lines into chunks that
+// fit the model's context window. Each chunk is summarized on its own (map),
+// then the partial summaries are packed and summarized again until they fit
+// in a single chunk for the final pass (reduce), so hundreds of events work
+// with a small context. The result is saved as a `text` event with the
+// filter it was made from in its metadata.
+//
+// Without a model every step is extractive. Events opted out of AI are left
+// out when a model is used; locked events never have text to include.
+
+use serde::Serialize;
+use serde_json::json;
+use tauri::Emitter;
+
+use crate::journal::PromptModel;
+use crate::search::{self, SearchFilter};
+use crate::{app_lock, generate_id, now_ms, summarize, timezone, usage_metrics, DbState, TimelineEvent};
+
+pub const PROGRESS_EVENT: &str = "summarize-events-progress";
+
+const DEFAULT_CONTEXT_TOKENS: usize = 8_000;
+// Rough token estimate for English text
+const CHARS_PER_TOKEN: usize = 4;
+const MIN_CHUNK_CHARS: usize = 2_000;
+const MAP_SENTENCES: usize = 5;
+const FINAL_SENTENCES: usize = 8;
+// Reduce passes before the remaining summaries are cut to fit
+const MAX_REDUCE_ROUNDS: usize = 5;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Progress {
+  done: usize,
+  total: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSynthesis {
+  event: TimelineEvent,
+  /// Events the summary was built from
+  source_count: usize,
+  /// Matching events left out: opted out of AI, locked or empty
+  skipped: usize,
+  /// Chunks summarized in the map step
+  chunks: usize,
+  /// "llm" or "extractive"
+  source: String,
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+  match text.char_indices().nth(max_chars) {
+    Some((end, _)) => format!("{}…", &text[..end]),
+    None => text.to_string(),
+  }
+}
+
+/// One line per event: date, type, title and text.
+fn render(event: &TimelineEvent) -> Option<String> {
+  let text = [&event.note, &event.text_content]
+    .into_iter()
+    .flatten()
+    .map(|t| t.trim())
+    .filter(|t| !t.is_empty())
+    .collect::<Vec<_>>()
+    .join(" ");
+  if text.is_empty() && event.title.is_none() {
+    return None;
+  }
+  let date = timezone::datetime(event.created_at)
+    .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+    .unwrap_or_default();
+  let title = event.title.as_deref().map(|t| format!("{}: ", t)).unwrap_or_default();
+  Some(format!("[{}] ({}) {}{}", date, event.event_type, title, text.replace('\n', " ")))
+}
+
+/// Greedily pack `pieces` into chunks of at most `max_chars`; a piece longer
+/// than that is cut.
+fn pack(pieces: Vec<String>, max_chars: usize) -> Vec<String> {
+  let mut chunks = Vec::new();
+  let mut current = String::new();
+  for piece in pieces {
+    let piece = truncate(&piece, max_chars);
+    if !current.is_empty() && current.chars().count() + piece.chars().count() + 1 > max_chars {
+      chunks.push(std::mem::take(&mut current));
+    }
+    if !current.is_empty() {
+      current.push('\n');
+    }
+    current.push_str(&piece);
+  }
+  if !current.is_empty() {
+    chunks.push(current);
+  }
+  chunks
+}
+
+/// Summarize every event matching `filter` into one new event.
+/// `context_tokens` is the model's context window (see `list_llm_models`);
+/// a conservative default is used when it's unknown. Map progress is
+/// reported as `summarize-events-progress`.
+#[tauri::command]
+pub async fn summarize_events(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, DbState>,
+  filter: SearchFilter,
+  llm: Option<PromptModel>,
+  context_tokens: Option<usize>,
+) -> Result<EventSynthesis, String> {
+  let _timer = usage_metrics::time_async("summarize_events");
+  app_lock::ensure_unlocked()?;
+  let events = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    search::run_filter(&conn, &filter, None)?
+  };
+  if events.is_empty() {
+    return Err("No events match this filter".to_string());
+  }
+
+  // Oldest first, so the summary reads in order
+  let matched = events.len();
+  let lines: Vec<String> = events
+    .into_iter()
+    .rev()
+    .map(|hit| hit.event)
+    .filter(|event| !event.locked && (llm.is_none() || !event.ai_opt_out))
+    .filter_map(|event| render(&event))
+    .collect();
+  let source_count = lines.len();
+  if lines.is_empty() {
+    return Err("None of the matching events have text that can be summarized".to_string());
+  }
+
+  let context_chars = context_tokens.unwrap_or(DEFAULT_CONTEXT_TOKENS) * CHARS_PER_TOKEN;
+  // Leave half the window for the instructions and the answer
+  let chunk_chars = (context_chars / 2).max(MIN_CHUNK_CHARS);
+
+  let chunks = pack(lines, chunk_chars);
+  let chunk_count = chunks.len();
+  let mut partials = Vec::new();
+  let mut source = "extractive".to_string();
+  for (i, chunk) in chunks.iter().enumerate() {
+    let summary = if chunk_count == 1 {
+      summarize::summarize(chunk, llm.clone(), FINAL_SENTENCES).await
+    } else {
+      summarize::summarize(chunk, llm.clone(), MAP_SENTENCES).await
+    };
+    if summary.source == "llm" {
+      source = summary.source;
+    }
+    partials.push(summary.text);
+    let _ = app.emit(PROGRESS_EVENT, Progress { done: i + 1, total: chunk_count });
+  }
+
+  let mut rounds = 0;
+  while partials.len() > 1 {
+    let joined_chars: usize = partials.iter().map(|p| p.chars().count() + 1).sum();
+    if joined_chars <= chunk_chars || rounds == MAX_REDUCE_ROUNDS {
+      let joined = truncate(&partials.join("\n"), chunk_chars);
+      partials = vec![summarize::summarize(&joined, llm.clone(), FINAL_SENTENCES).await.text];
+      break;
+    }
+    let mut next = Vec::new();
+    for chunk in pack(partials, chunk_chars) {
+      next.push(summarize::summarize(&chunk, llm.clone(), MAP_SENTENCES).await.text);
+    }
+    partials = next;
+    rounds += 1;
+  }
+  let text = partials.pop().unwrap_or_default();
+  if text.trim().is_empty() {
+    return Err("The summary came out empty".to_string());
+  }
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let event_id = generate_id();
+  let created_at = now_ms();
+  let title = format!("Summary of {} events", source_count);
+  let metadata = json!({ "filter": filter, "sourceCount": source_count, "summarySource": source });
+  conn
+    .execute(
+      "INSERT INTO timeline_events (id, type, title, text_content, created_at, source, is_deleted, metadata)
+       VALUES (?1, 'text', ?2, ?3, ?4, 'synthesis', 0, ?5)",
+      (&event_id, &title, &text, created_at, metadata.to_string()),
+    )
+    .map_err(|e| e.to_string())?;
+  tracing::info!(%event_id, source_count, chunks = chunk_count, %source, "Summarized events");
+
+  Ok(EventSynthesis {
+    event: TimelineEvent {
+      id: event_id,
+      event_type: "text".to_string(),
+      title: Some(title),
+      note: None,
+      text_content: Some(text),
+      created_at,
+      source: Some("synthesis".to_string()),
+      is_deleted: false,
+      metadata: Some(metadata),
+      ai_opt_out: false,
+      locked: false,
+    },
+    source_count,
+    skipped: matched - source_count,
+    chunks: chunk_count,
+    source,
+  })
+}
@@ -0,0 +1,503 @@
+// Tags: normalized labels attached to events.
+//
+// Tag names are lowercase, without `#`, with spaces turned into dashes.
+// `suggest_tags` proposes tags for an event, asking the LLM when the caller
+// passes a model and otherwise ranking the event's words by TF-IDF against
+// recent events. With `tags.auto` on, new events get the keyword suggestions
+// applied as they're created.
+//
+// `#hashtags` written in a note or text become tags with source "hashtag"
+// whenever the event is created or its note edited; the text itself is left
+// as written. Removing a hashtag from the note removes the tag again, unless
+// it was also added by hand. Texts that were compressed are only scanned as
+// far as their stored preview.
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use crate::journal::PromptModel;
+use crate::{app_lock, compression, i18n, llm_structured, now_ms, privacy, read_setting, undo, usage_metrics, write_setting, DbState, LlmRequest};
+
+pub const AUTO_TAG_KEY: &str = "tags.auto";
+
+const MAX_TAG_LEN: usize = 32;
+const MAX_SUGGESTIONS: usize = 5;
+// Tags applied automatically at creation time
+const AUTO_TAG_COUNT: usize = 3;
+// Events the document frequencies are computed over
+const IDF_SAMPLE: u32 = 500;
+// Keyword scores below this aren't worth suggesting
+const MIN_KEYWORD_SCORE: f64 = 1.5;
+
+const STOPWORDS: &[&str] = &[
+  "about", "after", "again", "also", "and", "any", "are", "because", "been", "before", "but", "can",
+  "could", "did", "does", "doing", "done", "down", "each", "for", "from", "get", "got", "had", "has",
+  "have", "her", "here", "him", "his", "how", "into", "its", "just", "like", "more", "most", "much",
+  "need", "not", "now", "off", "only", "other", "our", "out", "over", "she", "should", "some", "than",
+  "that", "the", "their", "them", "then", "there", "these", "they", "this", "those", "through", "too",
+  "under", "very", "was", "were", "what", "when", "where", "which", "while", "who", "why", "will",
+  "with", "would", "you", "your",
+];
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+  name: String,
+  count: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HashtagBackfill {
+  events: usize,
+  /// Tags newly attached across those events
+  tags_added: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TagSuggestion {
+  name: String,
+  score: f64,
+  /// "llm" or "keywords"
+  source: String,
+  /// Already used on some other event
+  existing: bool,
+}
+
+/// Canonical form of a tag name, or `None` if nothing is left of it.
+pub fn normalize(name: &str) -> Option<String> {
+  let name: String = name
+    .trim()
+    .trim_start_matches('#')
+    .to_lowercase()
+    .split_whitespace()
+    .collect::<Vec<_>>()
+    .join("-")
+    .chars()
+    .filter(|c| c.is_alphanumeric() || matches!(c, '-' | '_'))
+    .take(MAX_TAG_LEN)
+    .collect();
+  (!name.is_empty()).then_some(name)
+}
+
+/// Attach tags to an event, creating them as needed. `source` records how
+/// they got there ("manual", "auto", "hashtag" or "import").
+pub fn apply(conn: &rusqlite::Connection, event_id: &str, names: &[String], source: &str) -> Result<Vec<String>, String> {
+  let now = now_ms();
+  let mut applied = Vec::new();
+  for name in names.iter().filter_map(|n| normalize(n)) {
+    conn.execute(
+      "INSERT OR IGNORE INTO tags (name, created_at) VALUES (?1, ?2)",
+      (&name, now),
+    ).map_err(|e| e.to_string())?;
+    conn.execute(
+      "INSERT OR IGNORE INTO event_tags (event_id, tag_id, source, created_at)
+       SELECT ?1, id, ?2, ?3 FROM tags WHERE name = ?4",
+      (event_id, source, now, &name),
+    ).map_err(|e| e.to_string())?;
+    applied.push(name);
+  }
+  Ok(applied)
+}
+
+pub fn event_tags(conn: &rusqlite::Connection, event_id: &str) -> Result<Vec<String>, String> {
+  let tags = conn
+    .prepare(
+      "SELECT t.name FROM event_tags et JOIN tags t ON t.id = et.tag_id
+       WHERE et.event_id = ? ORDER BY t.name",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([event_id], |row| row.get(0))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(tags)
+}
+
+fn hashtag_regex() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  // Not after a word character, `&` or `/`, which skips URL fragments and
+  // HTML entities; a leading digit means an issue number, not a tag
+  RE.get_or_init(|| Regex::new(r"(?:^|[^\p{L}\p{N}_&/#])#([\p{L}_][\p{L}\p{N}_\-]*)").unwrap())
+}
+
+/// Normalized tag names of the hashtags in `text`, without duplicates.
+pub fn hashtags(text: &str) -> Vec<String> {
+  let mut names: Vec<String> = Vec::new();
+  for caps in hashtag_regex().captures_iter(text) {
+    if let Some(name) = normalize(caps[1].trim_end_matches('-')) {
+      if !names.contains(&name) {
+        names.push(name);
+      }
+    }
+  }
+  names
+}
+
+/// Make the event's "hashtag" tags match the hashtags in its note and text.
+/// A locked event keeps none, since they'd give away what the note says.
+/// Returns how many tags were newly attached.
+pub fn sync_hashtags(conn: &rusqlite::Connection, event_id: &str) -> Result<usize, String> {
+  let text: Option<(bool, Option<String>, Option<String>)> = conn
+    .query_row(
+      &format!("SELECT locked, note, {} FROM timeline_events WHERE id = ?", compression::FULL_TEXT_SQL),
+      [event_id],
+      |row| Ok((row.get::<_, i32>(0)? != 0, row.get(1)?, row.get(2)?)),
+    )
+    .ok();
+  let Some((locked, note, text)) = text else { return Ok(0) };
+  let found = if locked {
+    Vec::new()
+  } else {
+    hashtags(&[note, text].into_iter().flatten().collect::<Vec<_>>().join("\n"))
+  };
+
+  let from_hashtags: Vec<String> = conn
+    .prepare(
+      "SELECT t.name FROM event_tags et JOIN tags t ON t.id = et.tag_id
+       WHERE et.event_id = ? AND et.source = 'hashtag'",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([event_id], |row| row.get(0))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  for name in from_hashtags.iter().filter(|name| !found.contains(name)) {
+    conn.execute(
+      "DELETE FROM event_tags WHERE event_id = ?1 AND source = 'hashtag'
+       AND tag_id = (SELECT id FROM tags WHERE name = ?2)",
+      (event_id, name),
+    ).map_err(|e| e.to_string())?;
+  }
+
+  let before = event_tags(conn, event_id)?;
+  let added = found.iter().filter(|name| !before.contains(name)).count();
+  apply(conn, event_id, &found, "hashtag")?;
+  Ok(added)
+}
+
+fn existing_tags(conn: &rusqlite::Connection) -> Result<HashSet<String>, String> {
+  let tags = conn
+    .prepare("SELECT name FROM tags")
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| row.get(0))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(tags)
+}
+
+fn event_text(conn: &rusqlite::Connection, event_id: &str) -> Result<String, String> {
+  let (title, note, text): (Option<String>, Option<String>, Option<String>) = conn
+    .query_row(
+      "SELECT title, note, text_content FROM timeline_events WHERE id = ?",
+      [event_id],
+      |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .map_err(|_| i18n::t("Event not found"))?;
+  Ok([title, note, text].into_iter().flatten().collect::<Vec<_>>().join("\n"))
+}
+
+pub fn words(text: &str) -> Vec<String> {
+  text
+    .split(|c: char| !c.is_alphanumeric() && c != '-')
+    .map(|w| w.trim_matches('-').to_lowercase())
+    .filter(|w| w.chars().count() >= 3 && !w.chars().all(|c| c.is_numeric()) && !STOPWORDS.contains(&w.as_str()))
+    .collect()
+}
+
+/// Words of `text` ranked by TF-IDF over recent events. Words that are
+/// already tag names get a boost so the tag vocabulary stays small.
+fn keyword_suggestions(conn: &rusqlite::Connection, text: &str, existing: &HashSet<String>) -> Result<Vec<TagSuggestion>, String> {
+  let mut tf: HashMap<String, f64> = HashMap::new();
+  for word in words(text) {
+    *tf.entry(word).or_default() += 1.0;
+  }
+  if tf.is_empty() {
+    return Ok(Vec::new());
+  }
+
+  let docs: Vec<HashSet<String>> = conn
+    .prepare(
+      "SELECT COALESCE(title, '') || ' ' || COALESCE(note, '') || ' ' || COALESCE(text_content, '')
+       FROM timeline_events WHERE is_deleted = 0 ORDER BY created_at DESC LIMIT ?",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([IDF_SAMPLE], |row| row.get::<_, String>(0))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .map(|doc| words(&doc).into_iter().collect())
+    .collect();
+  let n = docs.len() as f64;
+
+  let mut suggestions: Vec<TagSuggestion> = tf
+    .into_iter()
+    .map(|(word, count)| {
+      let df = docs.iter().filter(|d| d.contains(&word)).count() as f64;
+      let idf = ((n + 1.0) / (df + 1.0)).ln() + 1.0;
+      let existing = existing.contains(&word);
+      let boost = if existing { 1.5 } else { 1.0 };
+      TagSuggestion { name: word, score: count * idf * boost, source: "keywords".to_string(), existing }
+    })
+    .filter(|s| s.score >= MIN_KEYWORD_SCORE)
+    .collect();
+  suggestions.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+  suggestions.truncate(MAX_SUGGESTIONS);
+  Ok(suggestions)
+}
+
+async fn llm_suggestions(model: PromptModel, text: &str, existing: &HashSet<String>) -> Result<Vec<TagSuggestion>, String> {
+  let mut vocabulary: Vec<&String> = existing.iter().collect();
+  vocabulary.sort();
+  let request = LlmRequest {
+    provider: model.provider,
+    api_key: model.api_key,
+    model: model.model,
+    prompt: format!(
+      "Suggest up to {} short topic tags for this note. Reuse these existing \
+       tags where they fit: {}\n\nNote:\n{}",
+      MAX_SUGGESTIONS,
+      vocabulary.iter().map(|t| t.as_str()).collect::<Vec<_>>().join(", "),
+      text
+    ),
+    max_tokens: Some(200),
+  };
+  let schema = json!({
+    "type": "object",
+    "properties": {
+      "tags": {
+        "type": "array",
+        "items": { "type": "string", "minLength": 1, "maxLength": MAX_TAG_LEN },
+        "maxItems": MAX_SUGGESTIONS,
+      },
+    },
+    "required": ["tags"],
+  });
+
+  let result = llm_structured::call_structured(&request, &schema).await?;
+  let names = result["tags"].as_array().cloned().unwrap_or_default();
+  let count = names.len();
+  let mut seen = HashSet::new();
+  Ok(names
+    .iter()
+    .filter_map(|n| n.as_str().and_then(normalize))
+    .filter(|n| seen.insert(n.clone()))
+    .enumerate()
+    .map(|(i, name)| TagSuggestion {
+      existing: existing.contains(&name),
+      name,
+      // Keep the model's order
+      score: (count - i) as f64,
+      source: "llm".to_string(),
+    })
+    .collect())
+}
+
+/// Called after an event is inserted; applies keyword tags when `tags.auto`
+/// is on. Failures never block creating the event.
+pub fn auto_tag(conn: &rusqlite::Connection, event_id: &str) {
+  if read_setting(conn, AUTO_TAG_KEY).as_deref() != Some("true") {
+    return;
+  }
+  let result = event_text(conn, event_id)
+    .and_then(|text| keyword_suggestions(conn, &text, &existing_tags(conn)?))
+    .and_then(|suggestions| {
+      let names: Vec<String> = suggestions.into_iter().take(AUTO_TAG_COUNT).map(|s| s.name).collect();
+      apply(conn, event_id, &names, "auto")
+    });
+  if let Err(e) = result {
+    tracing::warn!(%event_id, error = %e, "Auto-tagging failed");
+  }
+}
+
+/// Tag ideas for an event that don't already have it. Uses `llm` when given
+/// and falls back to keywords if the call fails or the event is opted out of AI.
+#[tauri::command]
+pub async fn suggest_tags(
+  state: tauri::State<'_, DbState>,
+  event_id: String,
+  llm: Option<PromptModel>,
+) -> Result<Vec<TagSuggestion>, String> {
+  let _timer = usage_metrics::time_async("suggest_tags");
+  let (text, existing, current, opted_out) = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    (
+      event_text(&conn, &event_id)?,
+      existing_tags(&conn)?,
+      event_tags(&conn, &event_id)?,
+      privacy::is_opted_out(&conn, &event_id)?,
+    )
+  };
+
+  let mut suggestions = None;
+  // Opted-out events only ever get on-device keyword suggestions
+  if let Some(model) = llm.filter(|_| !opted_out) {
+    match llm_suggestions(model, &text, &existing).await {
+      Ok(s) => suggestions = Some(s),
+      Err(e) => tracing::warn!(error = %e, "LLM tag suggestion failed, using keywords"),
+    }
+  }
+  let suggestions = match suggestions {
+    Some(s) => s,
+    None => {
+      let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+      let conn = state.open().map_err(|e| e.to_string())?;
+      keyword_suggestions(&conn, &text, &existing)?
+    }
+  };
+
+  Ok(suggestions.into_iter().filter(|s| !current.contains(&s.name)).collect())
+}
+
+#[tauri::command]
+pub fn add_event_tags(
+  state: tauri::State<DbState>,
+  undo: tauri::State<undo::UndoState>,
+  event_id: String,
+  tags: Vec<String>,
+) -> Result<Vec<String>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  event_text(&conn, &event_id)?;
+  let before = event_tags(&conn, &event_id)?;
+  let added: Vec<String> = apply(&conn, &event_id, &tags, "manual")?
+    .into_iter()
+    .filter(|name| !before.contains(name))
+    .collect();
+  if !added.is_empty() {
+    undo.record("Add tags", undo::Operation::AddTags { event_id: event_id.clone(), tags: added });
+  }
+  event_tags(&conn, &event_id)
+}
+
+#[tauri::command]
+pub fn remove_event_tag(
+  state: tauri::State<DbState>,
+  undo: tauri::State<undo::UndoState>,
+  event_id: String,
+  tag: String,
+) -> Result<Vec<String>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  if let Some(name) = normalize(&tag) {
+    // Kept so undo restores the tag as it was applied
+    let source: Option<String> = conn
+      .query_row(
+        "SELECT et.source FROM event_tags et JOIN tags t ON t.id = et.tag_id WHERE et.event_id = ?1 AND t.name = ?2",
+        (&event_id, &name),
+        |row| row.get(0),
+      )
+      .ok();
+    conn.execute(
+      "DELETE FROM event_tags WHERE event_id = ?1 AND tag_id = (SELECT id FROM tags WHERE name = ?2)",
+      (&event_id, &name),
+    ).map_err(|e| e.to_string())?;
+    if let Some(source) = source {
+      undo.record("Remove tag", undo::Operation::RemoveTag { event_id: event_id.clone(), tag: name, source });
+    }
+  }
+  event_tags(&conn, &event_id)
+}
+
+#[tauri::command]
+pub fn get_event_tags(state: tauri::State<DbState>, event_id: String) -> Result<Vec<String>, String> {
+  app_lock::ensure_unlocked()?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  event_tags(&conn, &event_id)
+}
+
+/// All tags with how many live events use them, most used first.
+#[tauri::command]
+pub fn list_tags(state: tauri::State<DbState>) -> Result<Vec<Tag>, String> {
+  app_lock::ensure_unlocked()?;
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let tags = conn
+    .prepare(
+      "SELECT t.name, COUNT(e.id) AS uses FROM tags t
+       LEFT JOIN event_tags et ON et.tag_id = t.id
+       LEFT JOIN timeline_events e ON e.id = et.event_id AND e.is_deleted = 0
+       GROUP BY t.id ORDER BY uses DESC, t.name",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| Ok(Tag { name: row.get(0)?, count: row.get(1)? }))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  Ok(tags)
+}
+
+/// Turn the hashtags of every existing event into tags.
+#[tauri::command]
+pub fn backfill_hashtags(state: tauri::State<DbState>) -> Result<HashtagBackfill, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let ids: Vec<String> = conn
+    .prepare(&format!(
+      "SELECT id FROM timeline_events
+       WHERE is_deleted = 0 AND locked = 0 AND (note LIKE '%#%' OR {} LIKE '%#%')",
+      compression::FULL_TEXT_SQL
+    ))
+    .map_err(|e| e.to_string())?
+    .query_map([], |row| row.get(0))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let mut report = HashtagBackfill { events: 0, tags_added: 0 };
+  for id in &ids {
+    let added = sync_hashtags(&conn, id)?;
+    if added > 0 {
+      report.events += 1;
+      report.tags_added += added;
+    }
+  }
+  tracing::info!(events = report.events, tags_added = report.tags_added, "Backfilled hashtags");
+  Ok(report)
+}
+
+#[tauri::command]
+pub fn set_auto_tagging(state: tauri::State<DbState>, enabled: bool) -> Result<(), String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  write_setting(&conn, AUTO_TAG_KEY, if enabled { "true" } else { "false" })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn normalizes_tag_names() {
+    assert_eq!(normalize("  #My Tag! ").as_deref(), Some("my-tag"));
+    assert_eq!(normalize("Café_Notes").as_deref(), Some("café_notes"));
+    assert_eq!(normalize("###"), None);
+    assert_eq!(normalize(&"x".repeat(40)).map(|n| n.len()), Some(MAX_TAG_LEN));
+  }
+
+  #[test]
+  fn finds_hashtags_once_each() {
+    assert_eq!(hashtags("#Planning for #work and #travel-2024, #WORK again"), vec!["planning", "work", "travel-2024"]);
+    assert_eq!(hashtags("Ideas (#café) and #日本"), vec!["café", "日本"]);
+    // Trailing dashes are punctuation
+    assert_eq!(hashtags("Done with #shipping-."), vec!["shipping"]);
+  }
+
+  #[test]
+  fn skips_things_that_only_look_like_hashtags() {
+    assert!(hashtags("See https://example.com/page#section").is_empty());
+    assert!(hashtags("It&#39;s fixed in #123").is_empty());
+    assert!(hashtags("a#b and ##double").is_empty());
+  }
+
+  #[test]
+  fn splits_words_for_keywords() {
+    assert_eq!(words("Fixed the build-server, again! 2024 ok"), vec!["fixed", "build-server"]);
+  }
+}
@@ -0,0 +1,218 @@
+// Preview thumbnails for image and video attachments, generated on first
+// request and cached as PNG under `thumbnails/`. Videos get a poster frame.
+//
+// Common formats are decoded in-process. The webview can't show HEIC/HEIF or
+// AVIF and there's no pure-Rust decoder for them, so those go through a
+// platform tool when one is installed (`sips` on macOS, otherwise ImageMagick
+// or ffmpeg). Without a tool the attachment simply has no thumbnail.
+//
+// SVGs can carry scripts, so the UI should never render the original file.
+// They are rasterized here with resvg instead, which ignores scripts and
+// foreign objects and is not allowed to load external files.
+
+use base64::Engine;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, OnceLock};
+use tauri::Manager;
+
+use crate::media::{self, tool_command};
+use crate::{app_lock, Attachment, DbState};
+
+const THUMB_SIZE: u32 = 256;
+// Larger thumbnails are passed by path only
+const MAX_INLINE_PREVIEW_BYTES: u64 = 96 * 1024;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentPreview {
+  attachment_id: String,
+  thumbnail_path: String,
+  /// `data:image/png;base64,...`, when the thumbnail is small enough
+  data_url: Option<String>,
+}
+
+fn thumbnails_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+  let dir = app
+    .path()
+    .app_data_dir()
+    .map_err(|e| format!("Failed to get app data dir: {}", e))?
+    .join("thumbnails");
+  fs::create_dir_all(&dir).map_err(|e| format!("Failed to create thumbnails dir: {}", e))?;
+  Ok(dir)
+}
+
+fn run_tool(mut cmd: Command, dest: &Path) -> bool {
+  cmd.stdout(Stdio::null()).status().map(|s| s.success()).unwrap_or(false) && dest.is_file()
+}
+
+// Loading system fonts is slow; do it once per run
+fn svg_fonts() -> Arc<resvg::usvg::fontdb::Database> {
+  static FONTS: OnceLock<Arc<resvg::usvg::fontdb::Database>> = OnceLock::new();
+  FONTS
+    .get_or_init(|| {
+      let mut db = resvg::usvg::fontdb::Database::new();
+      db.load_system_fonts();
+      Arc::new(db)
+    })
+    .clone()
+}
+
+fn rasterize_svg(source: &Path, dest: &Path) -> Result<(), String> {
+  use resvg::{tiny_skia, usvg};
+
+  let data = fs::read(source).map_err(|e| format!("Failed to read SVG: {}", e))?;
+  let mut options = usvg::Options {
+    fontdb: svg_fonts(),
+    ..usvg::Options::default()
+  };
+  // Inline data: images are fine, but never follow hrefs to files on disk
+  options.image_href_resolver.resolve_string = Box::new(|_, _| None);
+
+  let tree = usvg::Tree::from_data(&data, &options).map_err(|e| format!("Invalid SVG: {}", e))?;
+  let size = tree.size();
+  let scale = (THUMB_SIZE as f32 / size.width().max(size.height())).min(1.0);
+  let width = ((size.width() * scale).ceil() as u32).max(1);
+  let height = ((size.height() * scale).ceil() as u32).max(1);
+
+  let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or("Invalid SVG size")?;
+  resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+  pixmap.save_png(dest).map_err(|e| format!("Failed to write thumbnail: {}", e))
+}
+
+/// HEIC/HEIF/AVIF through whichever converter is available.
+fn convert_with_platform_tool(source: &Path, dest: &Path) -> Result<(), String> {
+  let size = THUMB_SIZE.to_string();
+
+  if cfg!(target_os = "macos") {
+    let mut sips = tool_command("sips");
+    sips.args(["-s", "format", "png", "-Z", &size]).arg(source).arg("--out").arg(dest);
+    if run_tool(sips, dest) {
+      return Ok(());
+    }
+  }
+
+  let mut magick = tool_command("magick");
+  magick
+    .arg(source)
+    .args(["-thumbnail", &format!("{}x{}", size, size)])
+    .arg(dest);
+  if run_tool(magick, dest) {
+    return Ok(());
+  }
+
+  let mut ffmpeg = tool_command("ffmpeg");
+  ffmpeg
+    .args(["-y", "-loglevel", "error", "-i"])
+    .arg(source)
+    .args([
+      "-vf",
+      &format!("scale={}:{}:force_original_aspect_ratio=decrease", size, size),
+      "-frames:v",
+      "1",
+    ])
+    .arg(dest);
+  if run_tool(ffmpeg, dest) {
+    return Ok(());
+  }
+
+  Err("No converter available for this image format".to_string())
+}
+
+fn generate_thumbnail(source: &Path, mime: &str, dest: &Path) -> Result<(), String> {
+  match mime {
+    "image/heic" | "image/heif" | "image/avif" => convert_with_platform_tool(source, dest),
+    "image/svg+xml" => rasterize_svg(source, dest),
+    "image/png" | "image/jpeg" | "image/gif" | "image/webp" | "image/bmp" => {
+      let img = image::open(source).map_err(|e| format!("Failed to decode image: {}", e))?;
+      img
+        .thumbnail(THUMB_SIZE, THUMB_SIZE)
+        .save_with_format(dest, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to write thumbnail: {}", e))
+    }
+    m if m.starts_with("video/") => media::extract_poster_frame(source, dest, THUMB_SIZE),
+    _ => Err(format!("No thumbnail support for {}", mime)),
+  }
+}
+
+/// Cached thumbnail for an attachment, generated on first use. `None` when
+/// there's nothing to preview or decoding fails.
+fn thumbnail_path(
+  app: &tauri::AppHandle,
+  attachment_id: &str,
+  source: &str,
+  mime: Option<&str>,
+) -> Result<Option<PathBuf>, String> {
+  let Some(mime) = mime.filter(|m| m.starts_with("image/") || m.starts_with("video/")) else {
+    return Ok(None);
+  };
+
+  let dest = thumbnails_dir(app)?.join(format!("{}.png", attachment_id));
+  if !dest.is_file() {
+    if let Err(e) = generate_thumbnail(Path::new(source), mime, &dest) {
+      tracing::warn!(%attachment_id, error = %e, "Thumbnail failed");
+      return Ok(None);
+    }
+  }
+  Ok(Some(dest))
+}
+
+/// Drop the cached thumbnail of an attachment, if there is one.
+pub fn forget(app: &tauri::AppHandle, attachment_id: &str) {
+  if let Ok(dir) = thumbnails_dir(app) {
+    let _ = fs::remove_file(dir.join(format!("{}.png", attachment_id)));
+  }
+}
+
+/// Thumbnails for the image attachments among `attachments`, inlined as data
+/// URLs when small enough so a reminder popup can draw them straight away.
+pub fn image_previews(app: &tauri::AppHandle, attachments: &[Attachment]) -> Vec<AttachmentPreview> {
+  attachments
+    .iter()
+    .filter(|a| a.kind == "image")
+    .filter_map(|a| {
+      let source = a.stored_path.as_deref().unwrap_or(&a.original_path);
+      let path = thumbnail_path(app, &a.id, source, a.mime_type.as_deref()).ok()??;
+      let data_url = fs::metadata(&path)
+        .ok()
+        .filter(|m| m.len() <= MAX_INLINE_PREVIEW_BYTES)
+        .and_then(|_| fs::read(&path).ok())
+        .map(|bytes| format!("data:image/png;base64,{}", base64::engine::general_purpose::STANDARD.encode(bytes)));
+      Some(AttachmentPreview {
+        attachment_id: a.id.clone(),
+        thumbnail_path: path.to_str()?.to_string(),
+        data_url,
+      })
+    })
+    .collect()
+}
+
+/// Path of the thumbnail of `attachment_id`, generating it if it isn't
+/// cached yet. Also run ahead of time by the `thumbnail` job.
+pub fn prepare(app: &tauri::AppHandle, attachment_id: &str) -> Result<Option<String>, String> {
+  let (source, mime): (String, Option<String>) = {
+    let state = app.state::<DbState>();
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    conn
+      .query_row(
+        "SELECT COALESCE(stored_path, original_path), mime_type FROM attachments WHERE id = ?",
+        [attachment_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+      )
+      .map_err(|_| "Attachment not found".to_string())?
+  };
+
+  Ok(thumbnail_path(app, attachment_id, &source, mime.as_deref())?
+    .and_then(|dest| dest.to_str().map(|s| s.to_string())))
+}
+
+/// Path of a PNG thumbnail (or video poster) for an attachment, generating it
+/// on first use. `None` when there's nothing to preview or decoding fails.
+#[tauri::command]
+pub fn get_attachment_thumbnail(app: tauri::AppHandle, attachment_id: String) -> Result<Option<String>, String> {
+  app_lock::ensure_unlocked()?;
+  prepare(&app, &attachment_id)
+}
@@ -0,0 +1,194 @@
+// Which timezone days are counted in.
+//
+// Exports, stats, streaks and goals all cut time into days. By default that
+// follows the machine's timezone, so a trip abroad moves events onto other
+// days once the clock changes. The `timezone` setting pins the days to a
+// fixed UTC offset ("+01:00") instead; "system" keeps the old behaviour.
+// There is no timezone database in this build, so a pinned offset doesn't
+// follow daylight saving time.
+//
+// Every new event also records the offset its day was counted in when it was
+// captured in `tz_offset_min` (a trigger in init_db does it: the pinned
+// offset, or the machine's under "system"). Events are bucketed into days by
+// that offset, so one captured late in the evening at home stays on that
+// day after a trip abroad; events without one use the current offset. An
+// export also shows the local time an event happened at when that differs.
+//
+// Reminders, snoozes and quiet hours are wall-clock times where the user is
+// and keep using the machine's timezone.
+
+use chrono::{DateTime, Duration as ChronoDuration, FixedOffset, Local, NaiveDate, NaiveTime, Offset, TimeZone, Utc};
+use serde::Serialize;
+use std::sync::RwLock;
+use tauri::Manager;
+
+use crate::{config, i18n, now_ms, read_setting, write_setting, DbState};
+
+pub const TIMEZONE_KEY: &str = "timezone";
+const SYSTEM: &str = "system";
+const MAX_OFFSET_SECS: i32 = 14 * 60 * 60;
+
+// `None` follows the machine
+static ZONE: RwLock<Option<FixedOffset>> = RwLock::new(None);
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TimezoneInfo {
+  /// "system" or a UTC offset like "+01:00"
+  timezone: String,
+  /// Offset days are counted in right now
+  offset_minutes: i32,
+  /// The machine's current offset
+  system_offset_minutes: i32,
+}
+
+/// Parse "system", "UTC", "+01:00", "UTC-05:30" or "+9".
+fn parse(value: &str) -> Result<Option<FixedOffset>, String> {
+  let value = value.trim();
+  if value.eq_ignore_ascii_case(SYSTEM) || value.is_empty() {
+    return Ok(None);
+  }
+  let offset = value.strip_prefix("UTC").or_else(|| value.strip_prefix("utc")).unwrap_or(value);
+  if offset.is_empty() || offset == "Z" {
+    return Ok(FixedOffset::east_opt(0));
+  }
+  let invalid = || format!("Invalid timezone: {} (use \"system\" or an offset like +01:00)", value);
+  let (sign, rest) = match offset.split_at(1) {
+    ("+", rest) => (1, rest),
+    ("-", rest) => (-1, rest),
+    _ => return Err(invalid()),
+  };
+  let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+  let hours: i32 = hours.parse().map_err(|_| invalid())?;
+  let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+  let secs = sign * (hours * 3600 + minutes * 60);
+  if !(0..60).contains(&minutes) || secs.abs() > MAX_OFFSET_SECS {
+    return Err(invalid());
+  }
+  FixedOffset::east_opt(secs).map(Some).ok_or_else(invalid)
+}
+
+fn zone() -> Option<FixedOffset> {
+  ZONE.read().ok().and_then(|zone| *zone)
+}
+
+fn system_offset_at(ms: i64) -> FixedOffset {
+  Local
+    .timestamp_millis_opt(ms)
+    .single()
+    .map(|t| t.offset().fix())
+    .unwrap_or_else(|| Utc.fix())
+}
+
+/// Read the setting again; call after it changes.
+pub fn reload(app: &tauri::AppHandle) {
+  let state = app.state::<DbState>();
+  let Ok(_guard) = state.lock.lock() else { return };
+  let Ok(conn) = state.open() else { return };
+  let zone = read_setting(&conn, TIMEZONE_KEY).and_then(|value| parse(&value).ok()).flatten();
+  if let Ok(mut slot) = ZONE.write() {
+    *slot = zone;
+  }
+}
+
+/// `ms` as a date and time in the day-counting timezone.
+pub fn datetime(ms: i64) -> Option<DateTime<FixedOffset>> {
+  let offset = zone().unwrap_or_else(|| system_offset_at(ms));
+  DateTime::<Utc>::from_timestamp_millis(ms).map(|t| t.with_timezone(&offset))
+}
+
+/// "YYYY-MM-DD" of the day `ms` falls on.
+pub fn date_key(ms: i64) -> String {
+  datetime(ms).map(|t| t.format("%Y-%m-%d").to_string()).unwrap_or_default()
+}
+
+pub fn today() -> NaiveDate {
+  datetime(now_ms()).map(|t| t.date_naive()).unwrap_or_else(|| Local::now().date_naive())
+}
+
+pub fn today_key() -> String {
+  today().format("%Y-%m-%d").to_string()
+}
+
+/// `time` on `date` as unix ms. In a DST gap the earlier reading wins.
+pub fn at(date: NaiveDate, time: NaiveTime) -> Option<i64> {
+  let naive = date.and_time(time);
+  match zone() {
+    Some(offset) => offset.from_local_datetime(&naive).single().map(|t| t.timestamp_millis()),
+    None => Local.from_local_datetime(&naive).earliest().map(|t| t.timestamp_millis()),
+  }
+}
+
+/// Midnight at the start of `date`, as unix ms.
+pub fn day_start(date: NaiveDate) -> Option<i64> {
+  at(date, NaiveTime::MIN)
+}
+
+/// First and last millisecond of a "YYYY-MM-DD" day.
+pub fn day_bounds(date_key: &str) -> Result<(i64, i64), String> {
+  let date = NaiveDate::parse_from_str(date_key, "%Y-%m-%d").map_err(|_| i18n::t("Invalid date format"))?;
+  let start = day_start(date).ok_or_else(|| "Invalid local time".to_string())?;
+  let next = day_start(date + ChronoDuration::days(1)).ok_or_else(|| "Invalid local time".to_string())?;
+  Ok((start, next - 1))
+}
+
+/// SQLite modifier turning 'unixepoch' times into the day-counting timezone,
+/// for `date(created_at / 1000, 'unixepoch', ?)` and friends.
+pub fn sql_modifier() -> String {
+  match zone() {
+    Some(offset) => format!("{:+} minutes", offset.local_minus_utc() / 60),
+    None => "localtime".to_string(),
+  }
+}
+
+/// SQLite modifier for the day an event falls on: its recorded offset, else
+/// `fallback` (a placeholder bound to `sql_modifier()`). Use it on rows with a
+/// `tz_offset_min` column, as in `date(created_at / 1000, 'unixepoch', ...)`.
+pub fn event_modifier_sql(fallback: &str) -> String {
+  format!("COALESCE(tz_offset_min || ' minutes', {})", fallback)
+}
+
+/// Widest span a day's events can lie outside the day's bounds in the
+/// current offset, given they may be counted in any other offset.
+pub const DAY_MARGIN_MS: i64 = 2 * MAX_OFFSET_SECS as i64 * 1000;
+
+/// The offset recorded when `event_id` was captured, if there is one and it
+/// differs from the current day-counting offset.
+pub fn capture_offset(conn: &rusqlite::Connection, event_id: &str, created_at: i64) -> Option<FixedOffset> {
+  let minutes: i32 = conn
+    .query_row("SELECT tz_offset_min FROM timeline_events WHERE id = ?", [event_id], |row| row.get(0))
+    .ok()?;
+  let offset = FixedOffset::east_opt(minutes * 60)?;
+  (Some(offset) != datetime(created_at).map(|t| *t.offset())).then_some(offset)
+}
+
+fn info() -> TimezoneInfo {
+  let now = now_ms();
+  let system = system_offset_at(now);
+  TimezoneInfo {
+    timezone: zone().map(|offset| offset.to_string()).unwrap_or_else(|| SYSTEM.to_string()),
+    offset_minutes: zone().unwrap_or(system).local_minus_utc() / 60,
+    system_offset_minutes: system.local_minus_utc() / 60,
+  }
+}
+
+#[tauri::command]
+pub fn get_timezone() -> Result<TimezoneInfo, String> {
+  Ok(info())
+}
+
+#[tauri::command]
+pub fn set_timezone(app: tauri::AppHandle, state: tauri::State<DbState>, timezone: String) -> Result<TimezoneInfo, String> {
+  let zone = parse(&timezone)?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let value = zone.map(|offset| offset.to_string()).unwrap_or_else(|| SYSTEM.to_string());
+    write_setting(&conn, TIMEZONE_KEY, &value)?;
+  }
+  if let Ok(mut slot) = ZONE.write() {
+    *slot = zone;
+  }
+  config::emit_changed(&app, vec![TIMEZONE_KEY.to_string()]);
+  Ok(info())
+}
@@ -0,0 +1,75 @@
+// Text-to-speech via the platform's built-in voice.
+//
+// Nothing is bundled: Windows uses SAPI through PowerShell, macOS uses `say`
+// and Linux uses `espeak`. Text is always passed on stdin so it never has to
+// be quoted into a command line.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Setting key that makes the reminder scanner read due reminders aloud.
+pub const SPEAK_REMINDERS_KEY: &str = "tts.speak_reminders";
+
+#[cfg(target_os = "windows")]
+fn speech_command() -> Command {
+  use std::os::windows::process::CommandExt;
+  const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+  let mut cmd = Command::new("powershell");
+  cmd
+    .args([
+      "-NoProfile",
+      "-NonInteractive",
+      "-Command",
+      "Add-Type -AssemblyName System.Speech; \
+       $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+       $s.Speak([Console]::In.ReadToEnd())",
+    ])
+    .creation_flags(CREATE_NO_WINDOW);
+  cmd
+}
+
+#[cfg(target_os = "macos")]
+fn speech_command() -> Command {
+  // `say` reads from stdin when no text argument is given
+  Command::new("say")
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn speech_command() -> Command {
+  let mut cmd = Command::new("espeak");
+  cmd.arg("--stdin");
+  cmd
+}
+
+/// Start speaking `text` without waiting for playback to finish.
+pub fn speak_text(text: &str) -> Result<(), String> {
+  let text = text.trim();
+  if text.is_empty() {
+    return Ok(());
+  }
+
+  let mut child = speech_command()
+    .stdin(Stdio::piped())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()
+    .map_err(|e| format!("Text-to-speech unavailable: {}", e))?;
+
+  if let Some(mut stdin) = child.stdin.take() {
+    stdin
+      .write_all(text.as_bytes())
+      .map_err(|e| format!("Failed to send text to speech engine: {}", e))?;
+  }
+
+  // Reap the process in the background so it doesn't linger as a zombie
+  std::thread::spawn(move || {
+    let _ = child.wait();
+  });
+  Ok(())
+}
+
+#[tauri::command]
+pub fn speak(text: String) -> Result<(), String> {
+  speak_text(&text)
+}
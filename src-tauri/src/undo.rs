@@ -0,0 +1,241 @@
+// Undo and redo for edits made through the app.
+//
+// Commands that delete, drop or edit something record an `Operation`
+// describing the change, from which both directions can be replayed. The
+// history lives in memory for the session, holds the last `MAX_HISTORY`
+// operations and is cleared when switching profiles. Recording anything new
+// drops the redo side, as in any editor. Deletes are soft, so undoing one
+// only flips `is_deleted` back.
+//
+// Operations keep the note text they need to replay an edit, so they never
+// leave the backend: the webview only sees what kind of change an entry is
+// and which events it touched. Locking an event drops its entries, so its
+// note isn't kept in plain text here either. The database lock is always
+// taken before the history's, as the recording commands hold it already.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tauri::Manager;
+
+use crate::{app_lock, cancel_reminders, event_lock, i18n, mentions, now_ms, restore_reminders, tags, DbState};
+
+const MAX_HISTORY: usize = 50;
+
+pub enum Operation {
+  /// Events were deleted (`deleted`) or created and so can be taken back
+  SetDeleted { event_ids: Vec<String>, deleted: bool },
+  UpdateNote { event_id: String, before: Option<String>, after: Option<String> },
+  /// Only the tags the event didn't have before
+  AddTags { event_id: String, tags: Vec<String> },
+  RemoveTag { event_id: String, tag: String, source: String },
+}
+
+impl Operation {
+  fn kind(&self) -> &'static str {
+    match self {
+      Operation::SetDeleted { .. } => "setDeleted",
+      Operation::UpdateNote { .. } => "updateNote",
+      Operation::AddTags { .. } => "addTags",
+      Operation::RemoveTag { .. } => "removeTag",
+    }
+  }
+
+  fn event_ids(&self) -> Vec<String> {
+    match self {
+      Operation::SetDeleted { event_ids, .. } => event_ids.clone(),
+      Operation::UpdateNote { event_id, .. } | Operation::AddTags { event_id, .. } | Operation::RemoveTag { event_id, .. } => {
+        vec![event_id.clone()]
+      }
+    }
+  }
+
+  /// Leave `event_id` out; false when nothing is left of the operation.
+  fn retain_other_events(&mut self, event_id: &str) -> bool {
+    match self {
+      Operation::SetDeleted { event_ids, .. } => {
+        event_ids.retain(|id| id != event_id);
+        !event_ids.is_empty()
+      }
+      Operation::UpdateNote { event_id: id, .. } | Operation::AddTags { event_id: id, .. } | Operation::RemoveTag { event_id: id, .. } => {
+        id != event_id
+      }
+    }
+  }
+}
+
+struct Record {
+  label: String,
+  operation: Operation,
+  recorded_at: i64,
+}
+
+/// What the webview gets to see of a recorded change.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoEntry {
+  /// Short description for the UI, e.g. "Delete event"
+  label: String,
+  /// "setDeleted", "updateNote", "addTags" or "removeTag"
+  kind: &'static str,
+  event_ids: Vec<String>,
+  recorded_at: i64,
+}
+
+impl Record {
+  fn entry(&self) -> UndoEntry {
+    UndoEntry {
+      label: self.label.clone(),
+      kind: self.operation.kind(),
+      event_ids: self.operation.event_ids(),
+      recorded_at: self.recorded_at,
+    }
+  }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoHistory {
+  /// Most recent first
+  undo: Vec<UndoEntry>,
+  redo: Vec<UndoEntry>,
+}
+
+#[derive(Default)]
+struct History {
+  undo: VecDeque<Record>,
+  redo: Vec<Record>,
+}
+
+#[derive(Default)]
+pub struct UndoState {
+  history: Mutex<History>,
+}
+
+impl UndoState {
+  pub fn record(&self, label: &str, operation: Operation) {
+    let Ok(mut history) = self.history.lock() else { return };
+    history.redo.clear();
+    history.undo.push_back(Record { label: label.to_string(), operation, recorded_at: now_ms() });
+    if history.undo.len() > MAX_HISTORY {
+      history.undo.pop_front();
+    }
+  }
+}
+
+/// Forget everything, e.g. when the database underneath changes.
+pub fn clear(app: &tauri::AppHandle) {
+  if let Ok(mut history) = app.state::<UndoState>().history.lock() {
+    *history = History::default();
+  }
+}
+
+/// Drop everything recorded about `event_id`. Called when the event is
+/// locked, so its note text doesn't outlive the lock in memory.
+pub fn forget_event(app: &tauri::AppHandle, event_id: &str) {
+  let Ok(mut history) = app.state::<UndoState>().history.lock() else { return };
+  let History { undo, redo } = &mut *history;
+  undo.retain_mut(|record| record.operation.retain_other_events(event_id));
+  redo.retain_mut(|record| record.operation.retain_other_events(event_id));
+}
+
+fn set_deleted(conn: &rusqlite::Connection, event_ids: &[String], deleted: bool) -> Result<(), String> {
+  for id in event_ids {
+    let changed = conn
+      .execute("UPDATE timeline_events SET is_deleted = ?1 WHERE id = ?2", (deleted as i32, id))
+      .map_err(|e| e.to_string())?;
+    // An event archived meanwhile isn't in the table any more; its reminders
+    // were cancelled with the move and stay that way
+    if changed == 0 {
+      continue;
+    }
+    if deleted {
+      cancel_reminders(conn, id, "event deleted")?;
+    } else {
+      restore_reminders(conn, id)?;
+    }
+  }
+  Ok(())
+}
+
+fn set_note(conn: &rusqlite::Connection, event_id: &str, note: &Option<String>) -> Result<(), String> {
+  if event_lock::is_locked(conn, event_id)? {
+    return Err(i18n::t("Event is locked"));
+  }
+  conn
+    .execute("UPDATE timeline_events SET note = ?1 WHERE id = ?2", (note, event_id))
+    .map_err(|e| e.to_string())?;
+  tags::sync_hashtags(conn, event_id)?;
+  mentions::index(conn, event_id)
+}
+
+fn remove_tags(conn: &rusqlite::Connection, event_id: &str, names: &[String]) -> Result<(), String> {
+  for name in names {
+    conn
+      .execute(
+        "DELETE FROM event_tags WHERE event_id = ?1 AND tag_id = (SELECT id FROM tags WHERE name = ?2)",
+        (event_id, name),
+      )
+      .map_err(|e| e.to_string())?;
+  }
+  Ok(())
+}
+
+/// Apply `operation` forwards (`redo`) or backwards.
+fn replay(conn: &rusqlite::Connection, operation: &Operation, redo: bool) -> Result<(), String> {
+  match operation {
+    Operation::SetDeleted { event_ids, deleted } => set_deleted(conn, event_ids, *deleted == redo),
+    Operation::UpdateNote { event_id, before, after } => set_note(conn, event_id, if redo { after } else { before }),
+    Operation::AddTags { event_id, tags: names } if redo => tags::apply(conn, event_id, names, "manual").map(|_| ()),
+    Operation::AddTags { event_id, tags: names } => remove_tags(conn, event_id, names),
+    Operation::RemoveTag { event_id, tag, .. } if redo => remove_tags(conn, event_id, std::slice::from_ref(tag)),
+    Operation::RemoveTag { event_id, tag, source } => {
+      tags::apply(conn, event_id, std::slice::from_ref(tag), source).map(|_| ())
+    }
+  }
+}
+
+/// Take the newest entry off one side, replay it and move it to the other.
+/// A failed replay drops the entry; it can't be applied any more.
+fn step(state: &DbState, undo: &UndoState, redo: bool) -> Result<Option<UndoEntry>, String> {
+  // Same order as the commands that record: database first, then history
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut history = undo.history.lock().map_err(|_| "undo lock".to_string())?;
+  let record = if redo { history.redo.pop() } else { history.undo.pop_back() };
+  let Some(record) = record else { return Ok(None) };
+
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  replay(&tx, &record.operation, redo)?;
+  tx.commit().map_err(|e| e.to_string())?;
+
+  let entry = record.entry();
+  if redo {
+    history.undo.push_back(record);
+  } else {
+    history.redo.push(record);
+  }
+  Ok(Some(entry))
+}
+
+/// Undo the most recent change. Returns what was undone, `None` when there
+/// is nothing left.
+#[tauri::command]
+pub fn undo_last(state: tauri::State<DbState>, undo: tauri::State<UndoState>) -> Result<Option<UndoEntry>, String> {
+  step(&state, &undo, false)
+}
+
+#[tauri::command]
+pub fn redo_last(state: tauri::State<DbState>, undo: tauri::State<UndoState>) -> Result<Option<UndoEntry>, String> {
+  step(&state, &undo, true)
+}
+
+#[tauri::command]
+pub fn get_undo_history(undo: tauri::State<UndoState>) -> Result<UndoHistory, String> {
+  app_lock::ensure_unlocked()?;
+  let history = undo.history.lock().map_err(|_| "undo lock".to_string())?;
+  Ok(UndoHistory {
+    undo: history.undo.iter().rev().map(Record::entry).collect(),
+    redo: history.redo.iter().rev().map(Record::entry).collect(),
+  })
+}
@@ -0,0 +1,196 @@
+// Chunked uploads for files dropped into the webview.
+//
+// `save_dropped_file` pushes the whole file through IPC as a JSON number
+// array, which is fine for small snippets but blows up memory for large
+// drops. The commands here let the frontend stream a file in base64 chunks
+// into a temp file, or hand over a path that is copied server-side. Both
+// honour the ingestion policy's skipped extensions and size limit.
+
+use base64::Engine;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{drops_dir, generate_id, i18n, ingest, now_ms, sandbox, unique_drop_name, DbState};
+
+// Abandoned sessions older than this are dropped on the next begin_upload
+const UPLOAD_SESSION_TTL_MS: i64 = 30 * 60 * 1000;
+
+struct UploadSession {
+  file_name: String,
+  temp_path: PathBuf,
+  file: File,
+  bytes_written: u64,
+  max_bytes: Option<u64>,
+  updated_at: i64,
+}
+
+#[derive(Default)]
+pub struct UploadState {
+  sessions: Mutex<HashMap<String, UploadSession>>,
+}
+
+fn discard_session(session: UploadSession) {
+  drop(session.file);
+  let _ = fs::remove_file(&session.temp_path);
+}
+
+#[tauri::command]
+pub fn begin_upload(
+  app: tauri::AppHandle,
+  state: tauri::State<UploadState>,
+  db: tauri::State<DbState>,
+  file_name: String,
+) -> Result<String, String> {
+  // Reject bad names up front rather than after the whole file arrived
+  let file_name = sandbox::safe_file_name(&file_name)?;
+  let policy = ingest::policy_for(&db)?;
+  if ingest::is_skipped(&policy, Path::new(&file_name)) {
+    return Err(i18n::t("This file type is skipped by the ingestion policy"));
+  }
+  let drops_dir = drops_dir(&app)?;
+  let upload_id = generate_id();
+  let temp_path = drops_dir.join(format!(".upload_{}.part", upload_id));
+  let file = File::create(&temp_path)
+    .map_err(|e| format!("Failed to create upload file: {}", e))?;
+
+  let mut sessions = state.sessions.lock().map_err(|_| "upload lock".to_string())?;
+
+  // Clean up sessions the frontend never finished
+  let now = now_ms();
+  let stale: Vec<String> = sessions
+    .iter()
+    .filter(|(_, s)| now - s.updated_at > UPLOAD_SESSION_TTL_MS)
+    .map(|(id, _)| id.clone())
+    .collect();
+  for id in stale {
+    if let Some(session) = sessions.remove(&id) {
+      discard_session(session);
+    }
+  }
+
+  sessions.insert(upload_id.clone(), UploadSession {
+    file_name,
+    temp_path,
+    file,
+    bytes_written: 0,
+    max_bytes: policy.max_ingest_bytes,
+    updated_at: now,
+  });
+
+  Ok(upload_id)
+}
+
+/// Append a base64-encoded chunk; returns the total bytes written so far.
+#[tauri::command]
+pub fn append_chunk(
+  state: tauri::State<UploadState>,
+  upload_id: String,
+  chunk: String,
+) -> Result<u64, String> {
+  let bytes = base64::engine::general_purpose::STANDARD
+    .decode(chunk.as_bytes())
+    .map_err(|e| format!("Invalid chunk encoding: {}", e))?;
+
+  let mut sessions = state.sessions.lock().map_err(|_| "upload lock".to_string())?;
+  let session = sessions
+    .get_mut(&upload_id)
+    .ok_or_else(|| "Unknown upload".to_string())?;
+
+  if let Some(max) = session.max_bytes {
+    if session.bytes_written + bytes.len() as u64 > max {
+      if let Some(session) = sessions.remove(&upload_id) {
+        discard_session(session);
+      }
+      return Err(format!("File is too large (limit is {} bytes)", max));
+    }
+  }
+
+  session.file.write_all(&bytes)
+    .map_err(|e| format!("Failed to write chunk: {}", e))?;
+  session.bytes_written += bytes.len() as u64;
+  session.updated_at = now_ms();
+
+  Ok(session.bytes_written)
+}
+
+/// Finalize the upload and return the stored path, like `save_dropped_file`.
+#[tauri::command]
+pub fn finish_upload(
+  app: tauri::AppHandle,
+  state: tauri::State<UploadState>,
+  upload_id: String,
+) -> Result<String, String> {
+  let session = state
+    .sessions
+    .lock()
+    .map_err(|_| "upload lock".to_string())?
+    .remove(&upload_id)
+    .ok_or_else(|| "Unknown upload".to_string())?;
+
+  let UploadSession { file_name, temp_path, mut file, .. } = session;
+  file.flush().map_err(|e| format!("Failed to flush upload: {}", e))?;
+  drop(file);
+
+  let file_path = drops_dir(&app)?.join(unique_drop_name(&file_name)?);
+  fs::rename(&temp_path, &file_path)
+    .map_err(|e| format!("Failed to finalize upload: {}", e))?;
+
+  file_path.to_str()
+    .map(|s| s.to_string())
+    .ok_or_else(|| "Invalid path".to_string())
+}
+
+#[tauri::command]
+pub fn abort_upload(
+  state: tauri::State<UploadState>,
+  upload_id: String,
+) -> Result<(), String> {
+  let session = state
+    .sessions
+    .lock()
+    .map_err(|_| "upload lock".to_string())?
+    .remove(&upload_id);
+
+  if let Some(session) = session {
+    discard_session(session);
+  }
+  Ok(())
+}
+
+/// Copy a file the frontend already has a path for into the drops dir,
+/// without routing its bytes through IPC. The path must pass the sandbox,
+/// otherwise this would launder any file into the readable drops dir.
+#[tauri::command]
+pub fn copy_dropped_file(
+  app: tauri::AppHandle,
+  db: tauri::State<DbState>,
+  source_path: String,
+) -> Result<String, String> {
+  let (source, policy) = {
+    let _guard = db.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = db.open().map_err(|e| e.to_string())?;
+    (sandbox::check_readable(&app, &conn, &source_path)?, ingest::load_policy(&conn))
+  };
+  if !source.is_file() {
+    return Err(format!("File not found: {}", source_path));
+  }
+  if !ingest::admit(&policy, &source)? {
+    return Err(i18n::t("This file type is skipped by the ingestion policy"));
+  }
+
+  let file_name = source
+    .file_name()
+    .and_then(|n| n.to_str())
+    .ok_or_else(|| "Invalid file name".to_string())?;
+
+  let file_path = drops_dir(&app)?.join(unique_drop_name(file_name)?);
+  fs::copy(&source, &file_path)
+    .map_err(|e| format!("Failed to copy file: {}", e))?;
+
+  file_path.to_str()
+    .map(|s| s.to_string())
+    .ok_or_else(|| "Invalid path".to_string())
+}
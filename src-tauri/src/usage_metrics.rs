@@ -0,0 +1,173 @@
+// How often each command is called and how long it takes, kept on this
+// machine only.
+//
+// Every command the webview invokes passes through `measure`, which counts
+// the call and times the handler. The tallies collect in memory and are
+// added to the `usage_metrics` table (one row per command and day) once a
+// minute and whenever they're read, so a call costs no database write.
+// Nothing here is ever sent anywhere; `get_usage_metrics` is the only way
+// out, for a settings page or a developer looking for hot paths. Calls slower
+// than `SLOW_MS` are also logged at debug level.
+//
+// Async commands hand their work to the runtime and return at once, so
+// `measure` only sees them start. Each one holds a `CommandTimer` for the
+// length of its body, which adds the time until it answers. Rows older than
+// `KEEP_DAYS` are dropped.
+
+use chrono::Duration as ChronoDuration;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+use crate::{supervisor, timezone, DbState};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+const KEEP_DAYS: i64 = 90;
+const DEFAULT_DAYS: u32 = 30;
+const SLOW_MS: f64 = 500.0;
+
+static PENDING: Mutex<BTreeMap<String, Tally>> = Mutex::new(BTreeMap::new());
+
+#[derive(Default)]
+struct Tally {
+  calls: i64,
+  total_ms: f64,
+  max_ms: f64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandUsage {
+  command: String,
+  calls: i64,
+  total_ms: f64,
+  avg_ms: f64,
+  max_ms: f64,
+}
+
+/// Add `calls` calls and `elapsed` of time to a command's tally.
+fn record(command: &str, calls: i64, elapsed: Duration) {
+  let ms = elapsed.as_secs_f64() * 1000.0;
+  if ms >= SLOW_MS {
+    tracing::debug!(command, ms, "Slow command");
+  }
+  let Ok(mut pending) = PENDING.lock() else { return };
+  let tally = pending.entry(command.to_string()).or_default();
+  tally.calls += calls;
+  tally.total_ms += ms;
+  tally.max_ms = tally.max_ms.max(ms);
+}
+
+/// Times an async command's body; the time is added when it's dropped, that
+/// is when the command answers (or is cancelled). Create one first thing in
+/// every `async fn` command, under the command's name, and don't call those
+/// commands from other Rust code, which would add time without a call.
+pub struct CommandTimer {
+  command: &'static str,
+  started: Instant,
+}
+
+pub fn time_async(command: &'static str) -> CommandTimer {
+  CommandTimer { command, started: Instant::now() }
+}
+
+impl Drop for CommandTimer {
+  fn drop(&mut self) {
+    record(self.command, 0, self.started.elapsed());
+  }
+}
+
+/// Wrap the invoke handler so every command call is counted and timed. For
+/// async commands that's only the time to start them; their `CommandTimer`
+/// adds the rest.
+pub fn measure<F>(handler: F) -> impl Fn(tauri::ipc::Invoke<tauri::Wry>) -> bool + Send + Sync + 'static
+where
+  F: Fn(tauri::ipc::Invoke<tauri::Wry>) -> bool + Send + Sync + 'static,
+{
+  move |invoke| {
+    let command = invoke.message.command().to_string();
+    let started = Instant::now();
+    let handled = handler(invoke);
+    if handled {
+      record(&command, 1, started.elapsed());
+    }
+    handled
+  }
+}
+
+/// Add the tallies collected since the last flush to today's rows.
+fn flush(app: &tauri::AppHandle) -> Result<(), String> {
+  let pending = match PENDING.lock() {
+    Ok(mut pending) => std::mem::take(&mut *pending),
+    Err(_) => return Err("usage metrics lock".to_string()),
+  };
+  let cutoff = (timezone::today() - ChronoDuration::days(KEEP_DAYS)).format("%Y-%m-%d").to_string();
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  let today = timezone::today_key();
+  for (command, tally) in &pending {
+    tx.execute(
+      "INSERT INTO usage_metrics (date_key, command, calls, total_ms, max_ms) VALUES (?1, ?2, ?3, ?4, ?5)
+       ON CONFLICT(date_key, command) DO UPDATE SET
+         calls = calls + excluded.calls,
+         total_ms = total_ms + excluded.total_ms,
+         max_ms = MAX(max_ms, excluded.max_ms)",
+      (&today, command, tally.calls, tally.total_ms, tally.max_ms),
+    )
+    .map_err(|e| e.to_string())?;
+  }
+  tx.execute("DELETE FROM usage_metrics WHERE date_key < ?1", [&cutoff]).map_err(|e| e.to_string())?;
+  tx.commit().map_err(|e| e.to_string())
+}
+
+pub fn spawn_metrics_flusher(app: tauri::AppHandle) {
+  supervisor::supervise(app, "usage_metrics", |app| async move {
+    loop {
+      tokio::time::sleep(FLUSH_INTERVAL).await;
+      if let Err(e) = flush(&app) {
+        tracing::warn!(error = %e, "Saving usage metrics failed");
+      }
+    }
+  });
+}
+
+/// Calls per command over the last `days` days (30 by default), the most
+/// time spent first.
+#[tauri::command]
+pub fn get_usage_metrics(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  days: Option<u32>,
+) -> Result<Vec<CommandUsage>, String> {
+  flush(&app)?;
+  let days = days.unwrap_or(DEFAULT_DAYS).max(1) as i64;
+  let since = (timezone::today() - ChronoDuration::days(days - 1)).format("%Y-%m-%d").to_string();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  let mut stmt = conn
+    .prepare(
+      "SELECT command, SUM(calls), SUM(total_ms), MAX(max_ms) FROM usage_metrics
+       WHERE date_key >= ?1
+       GROUP BY command
+       ORDER BY SUM(total_ms) DESC",
+    )
+    .map_err(|e| e.to_string())?;
+  let rows = stmt
+    .query_map([&since], |row| {
+      let calls: i64 = row.get(1)?;
+      let total_ms: f64 = row.get(2)?;
+      Ok(CommandUsage {
+        command: row.get(0)?,
+        calls,
+        total_ms,
+        avg_ms: if calls > 0 { total_ms / calls as f64 } else { 0.0 },
+        max_ms: row.get(3)?,
+      })
+    })
+    .map_err(|e| e.to_string())?;
+  rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
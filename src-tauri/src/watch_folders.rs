@@ -0,0 +1,411 @@
+// Watch folders: files that appear in chosen directories become drop events.
+//
+// Folders and their rules live in the `watch.folders` setting. A rule set
+// limits which files are taken (extensions, a minimum size, subfolders or
+// not) and names tags every capture from that folder gets. The ingestion
+// policy applies on top, as for dropped files.
+//
+// File system notifications only mark a path as pending. The settle loop
+// takes it once its size has stayed the same for `SETTLE_TIME`, so a file
+// that's still being downloaded or copied isn't captured half-written. Each
+// file is captured once: a path already recorded as an attachment is skipped.
+// Captures are emitted to the main window as `watch-capture`. A file turned
+// away by the rate limit (see rate_limit.rs) goes back to pending and is
+// taken on a later round.
+//
+// A folder can carry a preset that changes how its files are captured; see
+// screenshots.rs and downloads.rs, whose files wait for the user's decision
+// instead of being captured right away. Folders with `move_files` have their
+// files moved into app storage instead of referenced or copied.
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+use crate::{
+  config, downloads, drops_dir, generate_id, get_mime_type, ingest, insert_attachment, media, now_ms, rate_limit,
+  read_setting, screenshots, supervisor, tags, unique_drop_name, write_setting, DbState, TimelineEvent,
+  TimelineEventWithAttachments,
+};
+
+pub const WATCH_FOLDERS_KEY: &str = "watch.folders";
+
+const SETTLE_TIME: Duration = Duration::from_secs(3);
+const SETTLE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatchRules {
+  /// Lowercase extensions without the dot; empty takes every file
+  pub extensions: Vec<String>,
+  pub min_size_bytes: u64,
+  /// Also watch subfolders
+  pub recursive: bool,
+  /// Tags added to every capture from the folder
+  pub tags: Vec<String>,
+  /// Only files whose name starts with one of these; empty takes any name
+  pub name_prefixes: Vec<String>,
+  /// Move captured files into app storage, leaving the folder empty
+  pub move_files: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchFolder {
+  pub path: String,
+  pub rules: WatchRules,
+  /// "screenshots" for the screenshot folder, `None` for plain folders
+  #[serde(default)]
+  pub preset: Option<String>,
+}
+
+struct Pending {
+  size: u64,
+  changed_at: Instant,
+}
+
+#[derive(Default)]
+pub struct WatchState {
+  watcher: Mutex<Option<RecommendedWatcher>>,
+  folders: Mutex<Vec<WatchFolder>>,
+  pending: Mutex<HashMap<PathBuf, Pending>>,
+}
+
+pub fn load_folders(conn: &rusqlite::Connection) -> Vec<WatchFolder> {
+  read_setting(conn, WATCH_FOLDERS_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn save_folders(conn: &rusqlite::Connection, folders: &[WatchFolder]) -> Result<(), String> {
+  let json = serde_json::to_string(folders).map_err(|e| e.to_string())?;
+  write_setting(conn, WATCH_FOLDERS_KEY, &json)
+}
+
+pub fn validate(mut rules: WatchRules) -> Result<WatchRules, String> {
+  rules.extensions = rules
+    .extensions
+    .iter()
+    .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+    .filter(|ext| !ext.is_empty())
+    .collect();
+  rules.tags = rules
+    .tags
+    .iter()
+    .map(|tag| tags::normalize(tag).ok_or_else(|| format!("Invalid tag: {}", tag)))
+    .collect::<Result<_, _>>()?;
+  Ok(rules)
+}
+
+/// The folder `path` was seen in, when its rules take the file.
+fn folder_for<'a>(folders: &'a [WatchFolder], path: &Path, size: u64) -> Option<&'a WatchFolder> {
+  let folder = folders
+    .iter()
+    .filter(|f| {
+      let root = Path::new(&f.path);
+      if f.rules.recursive { path.starts_with(root) } else { path.parent() == Some(root) }
+    })
+    .max_by_key(|f| f.path.len())?;
+  let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+  let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+  let wanted = (folder.rules.extensions.is_empty() || folder.rules.extensions.contains(&extension))
+    && (folder.rules.name_prefixes.is_empty() || folder.rules.name_prefixes.iter().any(|p| name.starts_with(p)))
+    && size >= folder.rules.min_size_bytes;
+  wanted.then_some(folder)
+}
+
+/// Temporary names browsers and editors write to before renaming.
+fn is_partial(path: &Path) -> bool {
+  let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+  name.starts_with('.')
+    || name.starts_with("~$")
+    || [".part", ".crdownload", ".download", ".tmp", ".partial"].iter().any(|ext| name.ends_with(ext))
+}
+
+fn on_event(app: &tauri::AppHandle, event: notify::Event) {
+  if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+    return;
+  }
+  let watch = app.state::<WatchState>();
+  let Ok(mut pending) = watch.pending.lock() else { return };
+  for path in event.paths.into_iter().filter(|p| !is_partial(p)) {
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    pending.insert(path, Pending { size, changed_at: Instant::now() });
+  }
+}
+
+/// Capture `path` as a drop event; `None` when it's already captured or the
+/// ingestion policy skips it.
+pub fn capture(
+  app: &tauri::AppHandle,
+  conn: &rusqlite::Connection,
+  folder: &WatchFolder,
+  path: &Path,
+) -> Result<Option<TimelineEventWithAttachments>, String> {
+  let path_str = path.to_string_lossy().to_string();
+  let known: i64 = conn
+    .query_row("SELECT COUNT(*) FROM attachments WHERE original_path = ?", [&path_str], |row| row.get(0))
+    .map_err(|e| e.to_string())?;
+  let policy = ingest::load_policy(conn);
+  if known > 0 || !ingest::admit(&policy, path)? {
+    return Ok(None);
+  }
+  let source = match folder.preset.as_deref() {
+    Some(screenshots::PRESET) => "screenshot",
+    Some(downloads::PRESET) => "download",
+    _ => "watch",
+  };
+  let admission = rate_limit::check(source, &path_str)?;
+  let drops_dir = drops_dir(app)?;
+  let title = path.file_name().map(|n| n.to_string_lossy().to_string());
+
+  // A moved file is staged in drops; insert_attachment takes it into the store
+  let path_str = if folder.rules.move_files {
+    let dest = drops_dir.join(unique_drop_name(title.as_deref().unwrap_or("file"))?);
+    move_file(path, &dest)?;
+    dest.to_string_lossy().to_string()
+  } else {
+    path_str
+  };
+
+  let event_id = generate_id();
+  let created_at = now_ms();
+  let event_type = media::attachment_kind(&get_mime_type(Path::new(&path_str)));
+  let metadata = json!({ "watchFolder": folder.path });
+  conn.execute(
+    "INSERT INTO timeline_events (id, type, title, created_at, source, is_deleted, metadata)
+     VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6)",
+    (&event_id, event_type, &title, created_at, source, metadata.to_string()),
+  ).map_err(|e| e.to_string())?;
+  let attachment = insert_attachment(conn, &policy, &app.state::<DbState>().data_dir(), &event_id, &path_str, created_at)?;
+  tags::apply(conn, &event_id, &folder.rules.tags, "auto")?;
+  tags::auto_tag(conn, &event_id);
+
+  let event = TimelineEvent {
+    id: event_id,
+    event_type: event_type.to_string(),
+    title,
+    note: None,
+    text_content: None,
+    created_at,
+    source: Some(source.to_string()),
+    is_deleted: false,
+    metadata: Some(metadata),
+    ai_opt_out: false,
+    locked: false,
+  };
+  rate_limit::record(admission);
+  Ok(Some(TimelineEventWithAttachments { event, attachments: vec![attachment], reminders: vec![] }))
+}
+
+/// Rename, or copy and delete when `dest` is on another drive.
+fn move_file(from: &Path, dest: &Path) -> Result<(), String> {
+  if fs::rename(from, dest).is_ok() {
+    return Ok(());
+  }
+  fs::copy(from, dest).map_err(|e| format!("Failed to move file: {}", e))?;
+  if let Err(e) = fs::remove_file(from) {
+    tracing::warn!(path = %from.display(), error = %e, "Moved file left behind");
+  }
+  Ok(())
+}
+
+/// Paths whose size has held still for `SETTLE_TIME`.
+fn settled(watch: &WatchState) -> Vec<(PathBuf, u64)> {
+  let Ok(mut pending) = watch.pending.lock() else { return Vec::new() };
+  let mut ready = Vec::new();
+  pending.retain(|path, entry| {
+    let Ok(meta) = fs::metadata(path) else { return false };
+    if !meta.is_file() {
+      return false;
+    }
+    if meta.len() != entry.size {
+      entry.size = meta.len();
+      entry.changed_at = Instant::now();
+      return true;
+    }
+    if entry.changed_at.elapsed() < SETTLE_TIME {
+      return true;
+    }
+    // Still held open by the writer on some platforms
+    if fs::File::open(path).is_err() {
+      return true;
+    }
+    ready.push((path.clone(), meta.len()));
+    false
+  });
+  ready
+}
+
+pub fn spawn_settler(app: tauri::AppHandle) {
+  supervisor::supervise(app, "watch_folders", |app| async move {
+    loop {
+      tokio::time::sleep(SETTLE_INTERVAL).await;
+
+      let watch = app.state::<WatchState>();
+      let ready = settled(&watch);
+      if ready.is_empty() {
+        continue;
+      }
+      let folders = watch.folders.lock().map(|f| f.clone()).unwrap_or_default();
+
+      let mut created = Vec::new();
+      let mut triage = Vec::new();
+      {
+        let state = app.state::<DbState>();
+        let Ok(_guard) = state.lock.lock() else { continue };
+        let Ok(conn) = state.open() else { continue };
+        for (path, size) in ready {
+          let Some(folder) = folder_for(&folders, &path, size) else { continue };
+          if folder.preset.as_deref() == Some(downloads::PRESET) {
+            match downloads::queue(&conn, &path, size) {
+              Ok(Some(item)) => triage.push(item),
+              Ok(None) => {}
+              Err(e) => tracing::warn!(path = %path.display(), error = %e, "Download triage failed"),
+            }
+            continue;
+          }
+          match capture(&app, &conn, folder, &path) {
+            Ok(Some(event)) => created.push(event),
+            Ok(None) => {}
+            // Taken again once the burst has passed
+            Err(e) if rate_limit::is_rate_limited(&e) => {
+              if let Ok(mut pending) = watch.pending.lock() {
+                pending.insert(path, Pending { size, changed_at: Instant::now() });
+              }
+            }
+            Err(e) => tracing::warn!(path = %path.display(), error = %e, "Watch folder capture failed"),
+          }
+        }
+      }
+
+      for event in created {
+        tracing::info!(event_id = %event.event.id, "Captured file from watch folder");
+        if let Some(window) = app.get_webview_window("main") {
+          let _ = window.emit("watch-capture", &event);
+        }
+      }
+      for item in triage {
+        if let Some(window) = app.get_webview_window("main") {
+          let _ = window.emit("triage-needed", &item);
+        }
+      }
+    }
+  });
+}
+
+/// (Re)start watching `folders`, replacing the previous watcher.
+fn start(app: &tauri::AppHandle, folders: Vec<WatchFolder>) -> Result<(), String> {
+  let watch = app.state::<WatchState>();
+  let mut slot = watch.watcher.lock().map_err(|_| "watch lock".to_string())?;
+  *slot = None;
+  if let Ok(mut pending) = watch.pending.lock() {
+    pending.clear();
+  }
+
+  if !folders.is_empty() {
+    let handle = app.clone();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| match result {
+      Ok(event) => on_event(&handle, event),
+      Err(e) => tracing::warn!(error = %e, "Watch folder notification failed"),
+    })
+    .map_err(|e| e.to_string())?;
+    for folder in &folders {
+      let mode = if folder.rules.recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+      // A folder that's gone (e.g. an unplugged drive) shouldn't stop the rest
+      if let Err(e) = watcher.watch(Path::new(&folder.path), mode) {
+        tracing::warn!(path = %folder.path, error = %e, "Folder not watched");
+      }
+    }
+    *slot = Some(watcher);
+  }
+  *watch.folders.lock().map_err(|_| "watch lock".to_string())? = folders;
+  Ok(())
+}
+
+/// Watch the folders of the active profile, at launch and after a profile
+/// switch.
+pub fn restore(app: &tauri::AppHandle) {
+  let folders = {
+    let state = app.state::<DbState>();
+    let Ok(_guard) = state.lock.lock() else { return };
+    match state.open() {
+      Ok(conn) => load_folders(&conn),
+      Err(_) => return,
+    }
+  };
+  if let Err(e) = start(app, folders) {
+    tracing::warn!(error = %e, "Watch folders not started");
+  }
+}
+
+/// Change the stored folders and restart the watcher on them.
+pub fn update_folders(
+  app: &tauri::AppHandle,
+  state: &DbState,
+  change: impl FnOnce(&mut Vec<WatchFolder>),
+) -> Result<Vec<WatchFolder>, String> {
+  let folders = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let mut folders = load_folders(&conn);
+    change(&mut folders);
+    save_folders(&conn, &folders)?;
+    folders
+  };
+  start(app, folders.clone())?;
+  config::emit_changed(app, vec![WATCH_FOLDERS_KEY.to_string()]);
+  Ok(folders)
+}
+
+#[tauri::command]
+pub fn list_watch_folders(state: tauri::State<DbState>) -> Result<Vec<WatchFolder>, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_folders(&conn))
+}
+
+/// Watch `path` for new files, or update its rules if it's already watched.
+/// Only files appearing from now on are captured.
+#[tauri::command]
+pub fn add_watch_folder(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  path: String,
+  rules: Option<WatchRules>,
+) -> Result<Vec<WatchFolder>, String> {
+  let canonical = fs::canonicalize(&path).map_err(|_| format!("Folder not found: {}", path))?;
+  if !canonical.is_dir() {
+    return Err(format!("Not a folder: {}", path));
+  }
+  // Copy mode writes into app storage; watching it would capture every drop twice
+  if let Ok(app_data) = app.path().app_data_dir().and_then(|dir| Ok(fs::canonicalize(dir)?)) {
+    if canonical.starts_with(&app_data) || app_data.starts_with(&canonical) {
+      return Err("The app's own data folder can't be watched".to_string());
+    }
+  }
+  let folder = WatchFolder {
+    path: canonical.to_string_lossy().to_string(),
+    rules: validate(rules.unwrap_or_default())?,
+    preset: None,
+  };
+  update_folders(&app, &state, |folders| {
+    folders.retain(|f| f.path != folder.path);
+    folders.push(folder);
+  })
+}
+
+#[tauri::command]
+pub fn remove_watch_folder(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  path: String,
+) -> Result<Vec<WatchFolder>, String> {
+  update_folders(&app, &state, |folders| folders.retain(|f| f.path != path))
+}
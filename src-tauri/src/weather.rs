@@ -0,0 +1,289 @@
+// The day's weather, for the top of daily exports.
+//
+// With `weather.settings` enabled, a background loop fetches today's and
+// yesterday's weather from Open-Meteo (free, no API key) every hour and keeps
+// one row per day in `daily_weather`; yesterday is fetched again so the
+// stored day ends up with its final numbers rather than a forecast. Exports
+// only read that table, so writing one never waits on the network.
+// `fetch_weather` fills in any other day on demand, going to Open-Meteo's
+// archive for dates past the forecast API's three months of history.
+//
+// The place is the one configured in the settings, or the latest fix from
+// location.rs when none is set and location is on.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::permissions::{self, Permission};
+use crate::{config, connectivity, i18n, location, now_ms, read_setting, supervisor, timezone, usage_metrics, write_setting, DbState};
+
+pub const SETTINGS_KEY: &str = "weather.settings";
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(15);
+// How far back the forecast API serves past days
+const FORECAST_PAST_DAYS: i64 = 90;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WeatherSettings {
+  enabled: bool,
+  /// Where to get the weather for; falls back to the current location
+  lat: Option<f64>,
+  lon: Option<f64>,
+  /// "celsius" or "fahrenheit"
+  unit: String,
+}
+
+impl Default for WeatherSettings {
+  fn default() -> Self {
+    Self { enabled: false, lat: None, lon: None, unit: "celsius".to_string() }
+  }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyWeather {
+  date_key: String,
+  lat: f64,
+  lon: f64,
+  /// In the unit the settings ask for
+  temp_max: f64,
+  temp_min: f64,
+  unit: String,
+  precipitation_mm: f64,
+  /// WMO weather interpretation code
+  weather_code: i64,
+  summary: String,
+  fetched_at: i64,
+}
+
+fn load_settings(conn: &rusqlite::Connection) -> WeatherSettings {
+  read_setting(conn, SETTINGS_KEY)
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or_default()
+}
+
+fn validate(settings: WeatherSettings) -> Result<WeatherSettings, String> {
+  if settings.lat.is_some() != settings.lon.is_some() {
+    return Err("Set both latitude and longitude, or neither".to_string());
+  }
+  if let (Some(lat), Some(lon)) = (settings.lat, settings.lon) {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+      return Err("Invalid coordinates".to_string());
+    }
+  }
+  if !["celsius", "fahrenheit"].contains(&settings.unit.as_str()) {
+    return Err(format!("Unknown unit: {}", settings.unit));
+  }
+  Ok(settings)
+}
+
+/// Short description of a WMO weather code, as Open-Meteo reports them.
+fn describe(code: i64) -> &'static str {
+  match code {
+    0 => "clear",
+    1 => "mostly clear",
+    2 => "partly cloudy",
+    3 => "overcast",
+    45 | 48 => "fog",
+    51..=57 => "drizzle",
+    61..=67 => "rain",
+    71..=77 => "snow",
+    80..=82 => "showers",
+    85 | 86 => "snow showers",
+    95..=99 => "thunderstorm",
+    _ => "mixed weather",
+  }
+}
+
+fn place(settings: &WeatherSettings) -> Option<(f64, f64)> {
+  settings.lat.zip(settings.lon).or_else(location::current_position)
+}
+
+async fn fetch(lat: f64, lon: f64, date: NaiveDate, unit: &str) -> Result<DailyWeather, String> {
+  permissions::ensure(Permission::Network)?;
+  let date_key = date.format("%Y-%m-%d").to_string();
+  let archived = (timezone::today() - date).num_days() > FORECAST_PAST_DAYS;
+  let url = match archived {
+    true => "https://archive-api.open-meteo.com/v1/archive",
+    false => "https://api.open-meteo.com/v1/forecast",
+  };
+  let client = connectivity::client_builder().timeout(HTTP_TIMEOUT).build().map_err(|e| e.to_string())?;
+  let body: Value = client
+    .get(url)
+    .query(&[
+      ("latitude", lat.to_string()),
+      ("longitude", lon.to_string()),
+      ("daily", "weather_code,temperature_2m_max,temperature_2m_min,precipitation_sum".to_string()),
+      ("temperature_unit", unit.to_string()),
+      ("timezone", "auto".to_string()),
+      ("start_date", date_key.clone()),
+      ("end_date", date_key.clone()),
+    ])
+    .send()
+    .await
+    .map_err(|e| connectivity::request_error("Weather request failed", e))?
+    .json()
+    .await
+    .map_err(|e| format!("Invalid weather response: {}", e))?;
+  if let Some(reason) = body["reason"].as_str() {
+    return Err(format!("Open-Meteo: {}", reason));
+  }
+
+  let daily = &body["daily"];
+  let first = |key: &str| daily[key][0].as_f64();
+  let (Some(temp_max), Some(temp_min)) = (first("temperature_2m_max"), first("temperature_2m_min")) else {
+    return Err(format!("No weather data for {}", date_key));
+  };
+  let weather_code = daily["weather_code"][0].as_i64().unwrap_or(-1);
+  Ok(DailyWeather {
+    date_key,
+    lat,
+    lon,
+    temp_max,
+    temp_min,
+    unit: unit.to_string(),
+    precipitation_mm: first("precipitation_sum").unwrap_or(0.0),
+    weather_code,
+    summary: describe(weather_code).to_string(),
+    fetched_at: now_ms(),
+  })
+}
+
+fn store(conn: &rusqlite::Connection, weather: &DailyWeather) -> Result<(), String> {
+  conn
+    .execute(
+      "INSERT OR REPLACE INTO daily_weather
+         (date_key, lat, lon, temp_max, temp_min, unit, precipitation_mm, weather_code, summary, fetched_at)
+       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+      rusqlite::params![
+        weather.date_key,
+        weather.lat,
+        weather.lon,
+        weather.temp_max,
+        weather.temp_min,
+        weather.unit,
+        weather.precipitation_mm,
+        weather.weather_code,
+        weather.summary,
+        weather.fetched_at,
+      ],
+    )
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+fn stored(conn: &rusqlite::Connection, date_key: &str) -> Option<DailyWeather> {
+  conn
+    .query_row(
+      "SELECT date_key, lat, lon, temp_max, temp_min, unit, precipitation_mm, weather_code, summary, fetched_at
+       FROM daily_weather WHERE date_key = ?",
+      [date_key],
+      |row| {
+        Ok(DailyWeather {
+          date_key: row.get(0)?,
+          lat: row.get(1)?,
+          lon: row.get(2)?,
+          temp_max: row.get(3)?,
+          temp_min: row.get(4)?,
+          unit: row.get(5)?,
+          precipitation_mm: row.get(6)?,
+          weather_code: row.get(7)?,
+          summary: row.get(8)?,
+          fetched_at: row.get(9)?,
+        })
+      },
+    )
+    .ok()
+}
+
+/// "Tuesday, 14°C, rain" for the export of `date_key`, when that day's
+/// weather has been stored.
+pub fn format_day(conn: &rusqlite::Connection, date_key: &str) -> Option<String> {
+  let weather = stored(conn, date_key)?;
+  let date = NaiveDate::parse_from_str(date_key, "%Y-%m-%d").ok()?;
+  let unit = if weather.unit == "fahrenheit" { "°F" } else { "°C" };
+  Some(i18n::tf(
+    "{weekday}, {temperature}, {summary}",
+    &[
+      ("weekday", i18n::t(&date.format("%A").to_string())),
+      ("temperature", format!("{}{}", weather.temp_max.round(), unit)),
+      ("summary", i18n::t(&weather.summary)),
+    ],
+  ))
+}
+
+async fn fetch_and_store(app: &tauri::AppHandle, date: NaiveDate) -> Result<Option<DailyWeather>, String> {
+  let settings = {
+    let state = app.state::<DbState>();
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    load_settings(&conn)
+  };
+  if !settings.enabled {
+    return Ok(None);
+  }
+  let (lat, lon) = place(&settings).ok_or("No place set for the weather, and location is off")?;
+  let weather = fetch(lat, lon, date, &settings.unit).await?;
+
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  store(&conn, &weather)?;
+  Ok(Some(weather))
+}
+
+pub fn spawn_weather_fetcher(app: tauri::AppHandle) {
+  supervisor::supervise(app, "weather", |app| async move {
+    loop {
+      let today = timezone::today();
+      let dates = [today.pred_opt(), Some(today)].into_iter().flatten();
+      for date in dates.filter(|_| connectivity::reachable()) {
+        if let Err(e) = fetch_and_store(&app, date).await {
+          tracing::debug!(%date, error = %e, "Fetching weather failed");
+        }
+      }
+      connectivity::sleep(REFRESH_INTERVAL).await;
+    }
+  });
+}
+
+#[tauri::command]
+pub fn get_weather_settings(state: tauri::State<DbState>) -> Result<WeatherSettings, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+  Ok(load_settings(&conn))
+}
+
+#[tauri::command]
+pub fn set_weather_settings(
+  app: tauri::AppHandle,
+  state: tauri::State<DbState>,
+  settings: WeatherSettings,
+) -> Result<WeatherSettings, String> {
+  let settings = validate(settings)?;
+  {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    write_setting(&conn, SETTINGS_KEY, &json)?;
+  }
+  config::emit_changed(&app, vec![SETTINGS_KEY.to_string()]);
+  Ok(settings)
+}
+
+/// Fetch and store the weather of `date_key` (YYYY-MM-DD), replacing what was
+/// stored for it. `None` while the integration is off.
+#[tauri::command]
+pub async fn fetch_weather(app: tauri::AppHandle, date_key: String) -> Result<Option<DailyWeather>, String> {
+  let _timer = usage_metrics::time_async("fetch_weather");
+  let date = NaiveDate::parse_from_str(&date_key, "%Y-%m-%d").map_err(|_| "Date must be YYYY-MM-DD".to_string())?;
+  if date > timezone::today() {
+    return Err("Only past days and today have weather to record".to_string());
+  }
+  fetch_and_store(&app, date).await
+}
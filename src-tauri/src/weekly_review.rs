@@ -0,0 +1,135 @@
+// Weekly review, GTD style: go through the week's loose ends.
+//
+// `start_weekly_review` gathers what needs attention over the last seven
+// days: events nobody has tagged or reviewed yet and reminders that fired or
+// came due without being dismissed, alongside what got done (captures,
+// reminders handled, goals met). `mark_reviewed` stamps `reviewed_at` on
+// events the user has gone through, so they drop out of the next review.
+
+use serde::Serialize;
+
+use crate::capture_stats::{self, CaptureStats};
+use crate::reminder_stats::{self, ReminderStats};
+use crate::{app_lock, event_from_row, now_ms, DbState, TimelineEvent};
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenReminder {
+  id: String,
+  event_id: String,
+  event_title: Option<String>,
+  message: String,
+  status: String,
+  remind_at: i64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyReview {
+  start: i64,
+  end: i64,
+  /// This week's events without tags that haven't been reviewed, oldest first
+  untagged: Vec<TimelineEvent>,
+  /// Fired, snoozed or overdue reminders not dismissed yet, whenever due
+  open_reminders: Vec<OpenReminder>,
+  captures: CaptureStats,
+  reminders: ReminderStats,
+  goals_met: i64,
+  /// Events of the week already reviewed
+  reviewed: i64,
+  last_reviewed_at: Option<i64>,
+}
+
+#[tauri::command]
+pub fn start_weekly_review(state: tauri::State<DbState>) -> Result<WeeklyReview, String> {
+  app_lock::ensure_unlocked()?;
+  let start = reminder_stats::period_start_ms("week")?.unwrap_or(0);
+  let end = now_ms();
+
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let untagged = conn
+    .prepare(
+      "SELECT id, type, title, note, text_content, created_at, source, is_deleted, metadata, ai_opt_out, locked, text_compressed, text_codec
+       FROM timeline_events e
+       WHERE is_deleted = 0 AND scheduled_for IS NULL AND reviewed_at IS NULL
+         AND created_at BETWEEN ?1 AND ?2
+         AND NOT EXISTS (SELECT 1 FROM event_tags t WHERE t.event_id = e.id)
+       ORDER BY created_at",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([start, end], event_from_row)
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let open_reminders = conn
+    .prepare(
+      "SELECT r.id, r.event_id, e.title, r.message, r.status, r.remind_at
+       FROM reminders r
+       LEFT JOIN timeline_events e ON e.id = r.event_id
+       WHERE r.status IN ('triggered', 'snoozed') OR (r.status = 'pending' AND r.remind_at <= ?1)
+       ORDER BY r.remind_at",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([end], |row| {
+      Ok(OpenReminder {
+        id: row.get(0)?,
+        event_id: row.get(1)?,
+        event_title: row.get(2)?,
+        message: row.get(3)?,
+        status: row.get(4)?,
+        remind_at: row.get(5)?,
+      })
+    })
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  let goals_met: i64 = conn
+    .query_row(
+      "SELECT COUNT(*) FROM goal_progress WHERE met_at BETWEEN ?1 AND ?2",
+      [start, end],
+      |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())?;
+  let (reviewed, last_reviewed_at): (i64, Option<i64>) = conn
+    .query_row(
+      "SELECT COALESCE(SUM(created_at BETWEEN ?1 AND ?2 AND is_deleted = 0), 0), MAX(reviewed_at)
+       FROM timeline_events WHERE reviewed_at IS NOT NULL",
+      [start, end],
+      |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(|e| e.to_string())?;
+
+  Ok(WeeklyReview {
+    start,
+    end,
+    untagged,
+    open_reminders,
+    captures: capture_stats::query_capture_stats(&conn, Some(start), Some(end))?,
+    reminders: reminder_stats::query_reminder_stats(&conn, Some(start), Some(end))?,
+    goals_met,
+    reviewed,
+    last_reviewed_at,
+  })
+}
+
+/// Stamp `reviewed_at` on the given events. Returns how many were marked.
+#[tauri::command]
+pub fn mark_reviewed(state: tauri::State<DbState>, event_ids: Vec<String>) -> Result<usize, String> {
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let mut conn = state.open().map_err(|e| e.to_string())?;
+  let reviewed_at = now_ms();
+
+  let tx = conn.transaction().map_err(|e| e.to_string())?;
+  let mut marked = 0;
+  for id in &event_ids {
+    marked += tx
+      .execute("UPDATE timeline_events SET reviewed_at = ?1 WHERE id = ?2", (reviewed_at, id))
+      .map_err(|e| e.to_string())?;
+  }
+  tx.commit().map_err(|e| e.to_string())?;
+  Ok(marked)
+}
@@ -0,0 +1,300 @@
+// Wellness nudges: simple rules evaluated over behavior samples.
+//
+// Rules live in the `wellness.rules` setting as JSON so they can be tuned
+// without a rebuild. The behavior monitor feeds every analysis window into
+// the engine; matching rules emit `wellness-nudge` and can optionally leave
+// a reminder behind.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+use crate::{generate_id, i18n, now_ms, pet_state, quiet_hours, read_setting, reminder_scan, write_setting, DbState};
+
+pub const WELLNESS_RULES_KEY: &str = "wellness.rules";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum WellnessTrigger {
+  /// No away interval for at least this long
+  #[serde(rename_all = "camelCase")]
+  ContinuousActivity { minutes: f64 },
+  /// Lots of corrections while typing over a sliding window
+  #[serde(rename_all = "camelCase")]
+  BackspaceBurst { window_seconds: f64, min_keys: u32, ratio: f64 },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WellnessRule {
+  id: String,
+  trigger: WellnessTrigger,
+  message: String,
+  #[serde(default = "default_cooldown")]
+  cooldown_minutes: f64,
+  /// Also create a reminder this many minutes after the nudge
+  #[serde(default)]
+  remind_in_minutes: Option<i64>,
+  #[serde(default = "default_enabled")]
+  enabled: bool,
+}
+
+fn default_cooldown() -> f64 {
+  30.0
+}
+
+fn default_enabled() -> bool {
+  true
+}
+
+fn default_rules() -> Vec<WellnessRule> {
+  vec![
+    WellnessRule {
+      id: "long-session".to_string(),
+      trigger: WellnessTrigger::ContinuousActivity { minutes: 90.0 },
+      message: i18n::t("You've been at it for a while. Time to stretch and rest your eyes?"),
+      cooldown_minutes: 60.0,
+      remind_in_minutes: None,
+      enabled: true,
+    },
+    WellnessRule {
+      id: "frustrated-typing".to_string(),
+      trigger: WellnessTrigger::BackspaceBurst { window_seconds: 60.0, min_keys: 40, ratio: 0.3 },
+      message: i18n::t("Lots of rewrites lately. Take a breath, you've got this."),
+      cooldown_minutes: 20.0,
+      remind_in_minutes: None,
+      enabled: true,
+    },
+  ]
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct WellnessNudge {
+  rule_id: String,
+  message: String,
+  reminder_id: Option<String>,
+}
+
+struct TypingSample {
+  at: Instant,
+  keys: u32,
+  backspaces: u32,
+}
+
+// Longest sliding window kept for backspace rules
+const MAX_TYPING_HISTORY: Duration = Duration::from_secs(30 * 60);
+
+pub struct WellnessEngine {
+  rules: Vec<WellnessRule>,
+  last_fired: HashMap<String, Instant>,
+  active_since: Option<Instant>,
+  typing: VecDeque<TypingSample>,
+}
+
+impl Default for WellnessEngine {
+  fn default() -> Self {
+    Self {
+      rules: default_rules(),
+      last_fired: HashMap::new(),
+      active_since: None,
+      typing: VecDeque::new(),
+    }
+  }
+}
+
+impl WellnessEngine {
+  fn on_away(&mut self) {
+    self.active_since = None;
+    self.typing.clear();
+  }
+
+  fn observe(&mut self, now: Instant, keys: u32, backspaces: u32) -> Vec<WellnessRule> {
+    let active_since = *self.active_since.get_or_insert(now);
+    self.typing.push_back(TypingSample { at: now, keys, backspaces });
+    while self
+      .typing
+      .front()
+      .map(|s| now.duration_since(s.at) > MAX_TYPING_HISTORY)
+      .unwrap_or(false)
+    {
+      self.typing.pop_front();
+    }
+
+    let mut fired = Vec::new();
+    for rule in self.rules.iter().filter(|r| r.enabled) {
+      let cooldown = Duration::from_secs_f64(rule.cooldown_minutes.max(0.0) * 60.0);
+      if let Some(last) = self.last_fired.get(&rule.id) {
+        if now.duration_since(*last) < cooldown {
+          continue;
+        }
+      }
+
+      let matched = match &rule.trigger {
+        WellnessTrigger::ContinuousActivity { minutes } => {
+          now.duration_since(active_since).as_secs_f64() >= minutes * 60.0
+        }
+        WellnessTrigger::BackspaceBurst { window_seconds, min_keys, ratio } => {
+          let window = Duration::from_secs_f64(window_seconds.max(1.0));
+          let (total_keys, total_backspaces) = self
+            .typing
+            .iter()
+            .filter(|s| now.duration_since(s.at) <= window)
+            .fold((0u32, 0u32), |(k, b), s| (k + s.keys, b + s.backspaces));
+          total_keys >= *min_keys
+            && total_keys > 0
+            && total_backspaces as f64 / total_keys as f64 >= *ratio
+        }
+      };
+
+      if matched {
+        fired.push(rule.clone());
+      }
+    }
+
+    for rule in &fired {
+      self.last_fired.insert(rule.id.clone(), now);
+      // A break suggestion restarts the session clock
+      if matches!(rule.trigger, WellnessTrigger::ContinuousActivity { .. }) {
+        self.active_since = Some(now);
+      }
+    }
+    fired
+  }
+}
+
+#[derive(Default)]
+pub struct WellnessState {
+  engine: Mutex<WellnessEngine>,
+}
+
+fn parse_rules(json: &str) -> Result<Vec<WellnessRule>, String> {
+  let rules: Vec<WellnessRule> =
+    serde_json::from_str(json).map_err(|e| format!("Invalid wellness rules: {}", e))?;
+  for rule in &rules {
+    if rule.id.trim().is_empty() {
+      return Err("Wellness rule id must not be empty".to_string());
+    }
+    if rule.message.trim().is_empty() {
+      return Err(format!("Wellness rule '{}' needs a message", rule.id));
+    }
+  }
+  Ok(rules)
+}
+
+/// Load rules from settings into the engine (defaults when unset).
+pub fn load_rules(app: &tauri::AppHandle) {
+  let stored = {
+    let state = app.state::<DbState>();
+    let Ok(_guard) = state.lock.lock() else { return };
+    state.open()
+      .ok()
+      .and_then(|conn| read_setting(&conn, WELLNESS_RULES_KEY))
+  };
+
+  let rules = match stored {
+    Some(json) => match parse_rules(&json) {
+      Ok(rules) => rules,
+      Err(e) => {
+        tracing::warn!("{}", e);
+        default_rules()
+      }
+    },
+    None => default_rules(),
+  };
+
+  if let Ok(mut engine) = app.state::<WellnessState>().engine.lock() {
+    engine.rules = rules;
+  }
+}
+
+/// Called by the behavior monitor when the user goes idle.
+pub fn notify_away(app: &tauri::AppHandle) {
+  if let Ok(mut engine) = app.state::<WellnessState>().engine.lock() {
+    engine.on_away();
+  }
+}
+
+fn create_nudge_reminder(app: &tauri::AppHandle, message: &str, minutes: i64) -> Result<String, String> {
+  let state = app.state::<DbState>();
+  let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+  let conn = state.open().map_err(|e| e.to_string())?;
+
+  let event_id = generate_id();
+  let reminder_id = generate_id();
+  let created_at = now_ms();
+
+  conn.execute(
+    "INSERT INTO timeline_events (id, type, note, created_at, source, is_deleted)
+     VALUES (?1, 'thought', ?2, ?3, 'wellness', 0)",
+    (&event_id, message, created_at),
+  ).map_err(|e| e.to_string())?;
+
+  conn.execute(
+    "INSERT INTO reminders (id, event_id, remind_at, message, status, created_at)
+     VALUES (?1, ?2, ?3, ?4, 'pending', ?5)",
+    (&reminder_id, &event_id, created_at + minutes * 60 * 1000, message, created_at),
+  ).map_err(|e| e.to_string())?;
+  reminder_scan::wake(app);
+
+  Ok(reminder_id)
+}
+
+/// Feed one behavior analysis window into the rules engine.
+pub fn observe_sample(app: &tauri::AppHandle, key_presses: u32, backspaces: u32) {
+  let fired = match app.state::<WellnessState>().engine.lock() {
+    Ok(mut engine) => engine.observe(Instant::now(), key_presses, backspaces),
+    Err(_) => return,
+  };
+  // Rules still run during quiet hours so cooldowns carry on; the nudges are
+  // just dropped
+  if fired.is_empty() || quiet_hours::is_quiet(app) {
+    return;
+  }
+
+  for rule in fired {
+    let reminder_id = rule
+      .remind_in_minutes
+      .and_then(|minutes| create_nudge_reminder(app, &rule.message, minutes.max(0)).ok());
+
+    let nudge = WellnessNudge {
+      rule_id: rule.id,
+      message: rule.message,
+      reminder_id,
+    };
+    pet_state::concern(app, nudge.message.clone());
+    if let Some(window) = app.get_webview_window("main") {
+      let _ = window.emit("wellness-nudge", &nudge);
+    }
+  }
+}
+
+#[tauri::command]
+pub fn get_wellness_rules(state: tauri::State<WellnessState>) -> Result<Vec<WellnessRule>, String> {
+  let engine = state.engine.lock().map_err(|_| "wellness lock".to_string())?;
+  Ok(engine.rules.clone())
+}
+
+/// Validate, persist and apply a new rule set.
+#[tauri::command]
+pub fn set_wellness_rules(
+  db: tauri::State<DbState>,
+  state: tauri::State<WellnessState>,
+  rules: Vec<WellnessRule>,
+) -> Result<(), String> {
+  let json = serde_json::to_string(&rules).map_err(|e| e.to_string())?;
+  let rules = parse_rules(&json)?;
+
+  {
+    let _guard = db.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = db.open().map_err(|e| e.to_string())?;
+    write_setting(&conn, WELLNESS_RULES_KEY, &json)?;
+  }
+
+  let mut engine = state.engine.lock().map_err(|_| "wellness lock".to_string())?;
+  engine.rules = rules;
+  engine.last_fired.clear();
+  Ok(())
+}
@@ -0,0 +1,321 @@
+// Year in review: one HTML page summing up everything captured in a year.
+//
+// The report has the capture statistics for the whole year, events per
+// month, the busiest days, the top tags and photo highlights (the year's
+// largest images, copied next to the report like daily export assets).
+// When a model is passed, the LLM writes a short narrative from those
+// aggregates; no event content is sent. The page carries a print stylesheet,
+// so "Save as PDF" from a browser gives the PDF version.
+//
+// Reports are written to `<year>_review.html` in the exports folder.
+
+use chrono::NaiveDate;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::capture_stats::{self, CaptureStats};
+use crate::journal::PromptModel;
+use crate::{app_lock, export_files, maintenance, request_llm, timezone, usage_metrics, DbState, LlmRequest};
+
+const BUSIEST_DAYS: usize = 5;
+const HIGHLIGHTS: usize = 8;
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+struct Highlight {
+  id: String,
+  file_name: String,
+  path: PathBuf,
+  taken: String,
+}
+
+struct YearData {
+  stats: CaptureStats,
+  journal_entries: i64,
+  /// Events per month, January first
+  months: Vec<i64>,
+  busiest_days: Vec<(String, i64)>,
+  highlights: Vec<Highlight>,
+}
+
+fn year_bounds(year: i32) -> Result<(i64, i64), String> {
+  let start_of = |y: i32| {
+    NaiveDate::from_ymd_opt(y, 1, 1)
+      .and_then(timezone::day_start)
+      .ok_or_else(|| format!("Invalid year: {}", year))
+  };
+  Ok((start_of(year)?, start_of(year + 1)? - 1))
+}
+
+fn escape(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn collect(conn: &rusqlite::Connection, start: i64, end: i64) -> Result<YearData, String> {
+  let stats = capture_stats::query_capture_stats(conn, Some(start), Some(end))?;
+
+  let journal_entries: i64 = conn
+    .query_row(
+      "SELECT COUNT(*) FROM timeline_events
+       WHERE type = 'journal' AND is_deleted = 0 AND created_at BETWEEN ?1 AND ?2",
+      [start, end],
+      |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())?;
+
+  let mut months = vec![0; 12];
+  let per_month: Vec<(usize, i64)> = conn
+    .prepare(&format!(
+      "SELECT CAST(strftime('%m', created_at / 1000, 'unixepoch', {}) AS INTEGER), COUNT(*)
+       FROM timeline_events
+       WHERE is_deleted = 0 AND created_at BETWEEN ?1 AND ?2
+       GROUP BY 1",
+      timezone::event_modifier_sql("?3")
+    ))
+    .map_err(|e| e.to_string())?
+    .query_map((start, end, timezone::sql_modifier()), |row| Ok((row.get(0)?, row.get(1)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  for (month, count) in per_month {
+    if let Some(slot) = months.get_mut(month.wrapping_sub(1)) {
+      *slot = count;
+    }
+  }
+
+  let busiest_days = conn
+    .prepare(&format!(
+      "SELECT date(created_at / 1000, 'unixepoch', {}) AS day, COUNT(*)
+       FROM timeline_events
+       WHERE is_deleted = 0 AND created_at BETWEEN ?1 AND ?2
+       GROUP BY day
+       ORDER BY COUNT(*) DESC, day
+       LIMIT {}",
+      timezone::event_modifier_sql("?3"),
+      BUSIEST_DAYS
+    ))
+    .map_err(|e| e.to_string())?
+    .query_map((start, end, timezone::sql_modifier()), |row| Ok((row.get(0)?, row.get(1)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+
+  // Locked events' files are encrypted and stay out of the report
+  let candidates: Vec<(String, String, String, i64)> = conn
+    .prepare(
+      "SELECT a.id, COALESCE(a.file_name, 'image'), COALESCE(a.stored_path, a.original_path), e.created_at
+       FROM attachments a JOIN timeline_events e ON e.id = a.event_id
+       WHERE a.kind = 'image' AND e.is_deleted = 0 AND e.locked = 0 AND e.created_at BETWEEN ?1 AND ?2
+       ORDER BY a.size_bytes DESC",
+    )
+    .map_err(|e| e.to_string())?
+    .query_map([start, end], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+    .map_err(|e| e.to_string())?
+    .filter_map(|r| r.ok())
+    .collect();
+  let highlights = candidates
+    .into_iter()
+    .filter(|(_, _, path, _)| Path::new(path).exists())
+    .take(HIGHLIGHTS)
+    .map(|(id, file_name, path, created_at)| Highlight {
+      id,
+      file_name,
+      path: PathBuf::from(path),
+      taken: timezone::datetime(created_at)
+        .map(|dt| dt.format("%B %-d").to_string())
+        .unwrap_or_default(),
+    })
+    .collect();
+
+  Ok(YearData { stats, journal_entries, months, busiest_days, highlights })
+}
+
+/// Aggregates only, for the narrative prompt.
+fn facts(year: i32, data: &YearData) -> String {
+  let mut out = format!(
+    "Year: {}\nEvents captured: {}\nWords written: {}\nFiles captured: {}\nJournal entries: {}\n",
+    year, data.stats.events, data.stats.words, data.stats.attachments, data.journal_entries
+  );
+  let months: Vec<String> = MONTHS.iter().zip(&data.months).map(|(m, n)| format!("{} {}", m, n)).collect();
+  out.push_str(&format!("Events per month: {}\n", months.join(", ")));
+  if let Some(hour) = data.stats.busiest_hour {
+    out.push_str(&format!("Busiest hour: {:02}:00\n", hour));
+  }
+  if !data.stats.top_tags.is_empty() {
+    let tags: Vec<&str> = data.stats.top_tags.iter().map(|t| t.name.as_str()).collect();
+    out.push_str(&format!("Top tags: {}\n", tags.join(", ")));
+  }
+  if let Some((day, count)) = data.busiest_days.first() {
+    out.push_str(&format!("Busiest day: {} ({} events)\n", day, count));
+  }
+  out
+}
+
+async fn narrative(year: i32, data: &YearData, model: PromptModel) -> Option<String> {
+  let request = LlmRequest {
+    provider: model.provider,
+    api_key: model.api_key,
+    model: model.model,
+    prompt: format!(
+      "Here are statistics about everything I captured in my personal timeline this year:\n{}\n\
+       Write a warm, playful year-in-review of two short paragraphs, addressed to me. \
+       Only use the facts given. Reply with the text only.",
+      facts(year, data)
+    ),
+    max_tokens: Some(400),
+  };
+  match request_llm(request).await {
+    Ok(text) if !text.trim().is_empty() => Some(text.trim().to_string()),
+    Ok(_) => None,
+    Err(e) => {
+      tracing::warn!(error = %e, "Year review narrative failed");
+      None
+    }
+  }
+}
+
+fn render(year: i32, data: &YearData, narrative: Option<&str>, assets: &str) -> String {
+  let stats = &data.stats;
+  let mut body = format!("<h1>{} in review</h1>\n", year);
+
+  if let Some(text) = narrative {
+    for paragraph in text.split("\n\n").filter(|p| !p.trim().is_empty()) {
+      body.push_str(&format!("<p class=\"narrative\">{}</p>\n", escape(paragraph.trim())));
+    }
+  }
+
+  body.push_str("<div class=\"totals\">\n");
+  for (value, label) in [
+    (stats.events.to_string(), "events"),
+    (stats.words.to_string(), "words written"),
+    (stats.attachments.to_string(), "files captured"),
+    (data.journal_entries.to_string(), "journal entries"),
+  ] {
+    body.push_str(&format!("<div><strong>{}</strong><span>{}</span></div>\n", value, label));
+  }
+  body.push_str("</div>\n");
+
+  let busiest_month = data.months.iter().copied().max().unwrap_or(0).max(1);
+  body.push_str("<h2>Month by month</h2>\n<div class=\"months\">\n");
+  for (name, count) in MONTHS.iter().zip(&data.months) {
+    body.push_str(&format!(
+      "<div><span class=\"bar\" style=\"height: {}%\" title=\"{}\"></span>{}</div>\n",
+      count * 100 / busiest_month,
+      count,
+      name
+    ));
+  }
+  body.push_str("</div>\n");
+
+  if !data.busiest_days.is_empty() {
+    body.push_str("<h2>Busiest days</h2>\n<ol>\n");
+    for (day, count) in &data.busiest_days {
+      body.push_str(&format!("<li>{}: {} events</li>\n", day, count));
+    }
+    body.push_str("</ol>\n");
+  }
+  if let Some(hour) = stats.busiest_hour {
+    body.push_str(&format!("<p>You captured most around {:02}:00.</p>\n", hour));
+  }
+
+  if !stats.top_tags.is_empty() {
+    body.push_str("<h2>Top tags</h2>\n<p class=\"tags\">");
+    for tag in &stats.top_tags {
+      body.push_str(&format!("<span>#{} <small>{}</small></span> ", escape(&tag.name), tag.count));
+    }
+    body.push_str("</p>\n");
+  }
+
+  if !data.highlights.is_empty() {
+    body.push_str("<h2>Photo highlights</h2>\n<div class=\"photos\">\n");
+    for photo in &data.highlights {
+      body.push_str(&format!(
+        "<figure><img src=\"{}/{}\" alt=\"{}\"><figcaption>{}</figcaption></figure>\n",
+        assets,
+        escape(&asset_name(photo)),
+        escape(&photo.file_name),
+        escape(&photo.taken)
+      ));
+    }
+    body.push_str("</div>\n");
+  }
+
+  format!(
+    r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="UTF-8">
+  <title>{year} in review</title>
+  <style>
+    body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; max-width: 900px; margin: 0 auto; padding: 20px; line-height: 1.6; color: #333; }}
+    h1 {{ border-bottom: 2px solid #ffb347; padding-bottom: 10px; }}
+    h2 {{ color: #555; margin-top: 40px; }}
+    .narrative {{ font-size: 1.1em; }}
+    .totals {{ display: flex; gap: 16px; flex-wrap: wrap; }}
+    .totals div {{ flex: 1; min-width: 140px; background: #fff5e6; border-radius: 8px; padding: 16px; text-align: center; }}
+    .totals strong {{ display: block; font-size: 2em; color: #e08a00; }}
+    .months {{ display: flex; align-items: flex-end; gap: 6px; height: 160px; }}
+    .months div {{ flex: 1; display: flex; flex-direction: column; justify-content: flex-end; height: 100%; text-align: center; font-size: 0.8em; }}
+    .bar {{ display: block; background: #ffb347; border-radius: 4px 4px 0 0; min-height: 2px; }}
+    .tags span {{ display: inline-block; background: #f0f0f0; border-radius: 12px; padding: 2px 10px; margin: 3px; }}
+    .photos {{ display: grid; grid-template-columns: repeat(auto-fill, minmax(200px, 1fr)); gap: 12px; }}
+    figure {{ margin: 0; }}
+    img {{ width: 100%; border-radius: 8px; box-shadow: 0 2px 8px rgba(0,0,0,0.1); }}
+    figcaption {{ font-size: 0.8em; color: #777; text-align: center; }}
+    @media print {{ h2 {{ break-after: avoid; }} figure, .totals {{ break-inside: avoid; }} }}
+  </style>
+</head>
+<body>
+{body}
+</body>
+</html>"#
+  )
+}
+
+fn asset_name(photo: &Highlight) -> String {
+  format!("{}_{}", &photo.id[..8.min(photo.id.len())], photo.file_name)
+}
+
+/// Write the year in review for `year` and return its path. `llm` adds a
+/// written narrative; `custom_path` overrides the exports folder.
+#[tauri::command]
+pub async fn generate_year_review(
+  app: tauri::AppHandle,
+  state: tauri::State<'_, DbState>,
+  maintenance: tauri::State<'_, maintenance::MaintenanceState>,
+  year: i32,
+  llm: Option<PromptModel>,
+  custom_path: Option<String>,
+) -> Result<String, String> {
+  let _timer = usage_metrics::time_async("generate_year_review");
+  app_lock::ensure_unlocked()?;
+  let _export = maintenance.begin_export();
+  let (start, end) = year_bounds(year)?;
+
+  let (data, exports_dir) = {
+    let _guard = state.lock.lock().map_err(|_| "db lock".to_string())?;
+    let conn = state.open().map_err(|e| e.to_string())?;
+    (collect(&conn, start, end)?, export_files::exports_dir(&app, &conn, custom_path.as_deref())?)
+  };
+  if data.stats.events == 0 {
+    return Err(format!("Nothing was captured in {}", year));
+  }
+
+  let narrative = match llm {
+    Some(model) => narrative(year, &data, model).await,
+    None => None,
+  };
+
+  let assets = format!("{}_review_assets", year);
+  let assets_dir = exports_dir.join(&assets);
+  fs::create_dir_all(&assets_dir).map_err(|e| e.to_string())?;
+  for photo in &data.highlights {
+    if let Err(e) = fs::copy(&photo.path, assets_dir.join(asset_name(photo))) {
+      tracing::warn!(path = %photo.path.display(), error = %e, "Year review photo not copied");
+    }
+  }
+
+  let output_path = exports_dir.join(format!("{}_review.html", year));
+  fs::write(&output_path, render(year, &data, narrative.as_deref(), &assets)).map_err(|e| e.to_string())?;
+  tracing::info!(year, events = data.stats.events, narrative = narrative.is_some(), "Year review written");
+  Ok(output_path.to_string_lossy().to_string())
+}